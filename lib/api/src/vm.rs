@@ -32,8 +32,12 @@ pub use crate::jsc::vm::{VMFunction, VMGlobal, VMMemory, VMSharedMemory, VMTable
 pub use wasmer_vm::{
     // An extra one for VMMemory implementors
     LinearMemory,
+    // Needed to forward atomics through custom LinearMemory implementations
+    NotifyLocation,
+    ThreadConditions,
     VMMemoryDefinition,
     VMTableDefinition,
+    WaiterError,
 };
 
 // Deprecated exports
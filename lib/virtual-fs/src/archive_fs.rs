@@ -0,0 +1,806 @@
+//! A read-only file system that serves the contents of a `.tar`, `.tar.gz`
+//! or `.zip` archive without extracting it first.
+//!
+//! Only an index of the entries is built when the archive is mounted, the
+//! central directory of a zip archive is used for it while the headers of a
+//! tar archive are collected in a single pass. Files that are stored without
+//! compression are read straight from the archive, compressed files are
+//! decompressed into memory when they are opened.
+//!
+//! A `.tar.gz` archive is a single compressed stream, so every open of one of
+//! its files decompresses everything in front of that file again. Prefer a
+//! `.zip` or a plain `.tar` archive when files are opened often.
+
+use std::{
+    collections::BTreeMap,
+    convert::TryInto,
+    io::{self, Read, Seek, SeekFrom},
+    path::{Component, Path, PathBuf},
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+};
+
+use derivative::Derivative;
+use flate2::read::{DeflateDecoder, GzDecoder};
+use futures::future::BoxFuture;
+use tokio::io::{AsyncRead, AsyncSeek, AsyncWrite, ReadBuf};
+
+use crate::{
+    DirEntry, FileOpener, FileSystem, FileType, FsError, Metadata, OpenOptionsConfig, ReadDir,
+    VirtualFile,
+};
+
+/// Anything an archive can be read from
+pub trait ArchiveSource: Read + Seek + Send + 'static {}
+
+impl<T> ArchiveSource for T where T: Read + Seek + Send + 'static {}
+
+type SharedSource = Arc<Mutex<Box<dyn ArchiveSource>>>;
+
+/// Upper bound of the buffer that is allocated up front for a file that is
+/// decompressed
+const DECOMPRESS_PREALLOCATE_LIMIT: u64 = 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Data {
+    /// Stored without compression at this offset of the archive
+    Raw { offset: u64 },
+    /// Deflate stream of a zip entry at this offset of the archive
+    Deflated { offset: u64, compressed_len: u64 },
+    /// At this offset of the decompressed stream of a `.tar.gz` archive
+    Gzipped { offset: u64 },
+    /// Compressed with a method that is not supported or encrypted
+    Unsupported,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Node {
+    Dir,
+    File { data: Data, len: u64 },
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Entry {
+    node: Node,
+    /// Modification time in nanoseconds since the epoch
+    modified: u64,
+}
+
+impl Entry {
+    fn metadata(&self) -> Metadata {
+        let (ft, len) = match self.node {
+            Node::Dir => (
+                FileType {
+                    dir: true,
+                    ..Default::default()
+                },
+                0,
+            ),
+            Node::File { len, .. } => (
+                FileType {
+                    file: true,
+                    ..Default::default()
+                },
+                len,
+            ),
+        };
+        Metadata {
+            ft,
+            modified: self.modified,
+            len,
+            ..Default::default()
+        }
+    }
+}
+
+/// A read-only [`FileSystem`] over the entries of an archive
+#[derive(Derivative, Clone)]
+#[derivative(Debug)]
+pub struct ArchiveFileSystem {
+    #[derivative(Debug = "ignore")]
+    source: SharedSource,
+    entries: Arc<BTreeMap<PathBuf, Entry>>,
+}
+
+impl ArchiveFileSystem {
+    /// Mounts the archive at `path`, the format is taken from its extension
+    /// (`.zip`, `.tar`, `.tar.gz` or `.tgz`).
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, FsError> {
+        let path = path.as_ref();
+        let name = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .map(|name| name.to_ascii_lowercase())
+            .ok_or(FsError::InvalidInput)?;
+
+        if name.ends_with(".zip") {
+            Self::from_zip(std::fs::File::open(path)?)
+        } else if name.ends_with(".tar") {
+            Self::from_tar(std::fs::File::open(path)?)
+        } else if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+            Self::from_tar_gz(std::fs::File::open(path)?)
+        } else {
+            Err(FsError::InvalidInput)
+        }
+    }
+
+    /// Mounts a zip archive, its central directory is used as the index.
+    pub fn from_zip(source: impl ArchiveSource) -> Result<Self, FsError> {
+        let mut archive = zip::ZipArchive::new(source).map_err(zip_error)?;
+        let mut entries = Self::root();
+
+        for index in 0..archive.len() {
+            // Encrypted entries can not be read without the password
+            let encrypted = matches!(
+                archive.by_index(index),
+                Err(zip::result::ZipError::UnsupportedArchive(
+                    zip::result::ZipError::PASSWORD_REQUIRED
+                ))
+            );
+            let file = archive.by_index_raw(index).map_err(zip_error)?;
+            let Some(path) = file.enclosed_name().as_deref().and_then(archive_path) else {
+                continue;
+            };
+            let node = if file.is_dir() {
+                Node::Dir
+            } else {
+                let data = match file.compression() {
+                    _ if encrypted => Data::Unsupported,
+                    zip::CompressionMethod::Stored => Data::Raw {
+                        offset: file.data_start(),
+                    },
+                    zip::CompressionMethod::Deflated => Data::Deflated {
+                        offset: file.data_start(),
+                        compressed_len: file.compressed_size(),
+                    },
+                    _ => Data::Unsupported,
+                };
+                Node::File {
+                    data,
+                    len: file.size(),
+                }
+            };
+            let modified = zip_time(file.last_modified());
+            insert(&mut entries, path, Entry { node, modified });
+        }
+
+        Ok(Self::new(Box::new(archive.into_inner()), entries))
+    }
+
+    /// Mounts a tar archive, the entries are skipped over by seeking while
+    /// the headers are indexed.
+    pub fn from_tar(source: impl ArchiveSource) -> Result<Self, FsError> {
+        let mut source: Box<dyn ArchiveSource> = Box::new(source);
+        let mut entries = Self::root();
+        {
+            let mut archive = tar::Archive::new(&mut source);
+            index_tar(archive.entries_with_seek()?, &mut entries, |offset| {
+                Data::Raw { offset }
+            })?;
+        }
+        Ok(Self::new(source, entries))
+    }
+
+    /// Mounts a gzip compressed tar archive.
+    ///
+    /// A gzip stream can not be read from the middle, so opening a file
+    /// decompresses the archive up to the end of that file. Nothing is
+    /// cached, the cost of an open grows with the position of the file in
+    /// the archive.
+    pub fn from_tar_gz(source: impl ArchiveSource) -> Result<Self, FsError> {
+        let mut source: Box<dyn ArchiveSource> = Box::new(source);
+        let mut entries = Self::root();
+        {
+            let mut archive = tar::Archive::new(GzDecoder::new(&mut source));
+            index_tar(archive.entries()?, &mut entries, |offset| Data::Gzipped {
+                offset,
+            })?;
+        }
+        Ok(Self::new(source, entries))
+    }
+
+    fn new(source: Box<dyn ArchiveSource>, entries: BTreeMap<PathBuf, Entry>) -> Self {
+        ArchiveFileSystem {
+            source: Arc::new(Mutex::new(source)),
+            entries: Arc::new(entries),
+        }
+    }
+
+    fn root() -> BTreeMap<PathBuf, Entry> {
+        let mut entries = BTreeMap::new();
+        entries.insert(
+            PathBuf::from("/"),
+            Entry {
+                node: Node::Dir,
+                modified: 0,
+            },
+        );
+        entries
+    }
+
+    fn entry(&self, path: &Path) -> Result<&Entry, FsError> {
+        self.entries
+            .get(&normalize(path))
+            .ok_or(FsError::EntryNotFound)
+    }
+
+    /// Reads a compressed file into memory
+    fn decompress(&self, data: Data, len: u64) -> io::Result<Vec<u8>> {
+        let mut source = self.source.lock().unwrap();
+        // The length comes from the archive, so the buffer only grows past
+        // the limit as the data actually shows up
+        let capacity = len.min(DECOMPRESS_PREALLOCATE_LIMIT);
+        let mut contents = Vec::with_capacity(capacity.try_into().unwrap_or_default());
+
+        match data {
+            Data::Deflated {
+                offset,
+                compressed_len,
+            } => {
+                source.seek(SeekFrom::Start(offset))?;
+                DeflateDecoder::new((&mut *source).take(compressed_len))
+                    .take(len)
+                    .read_to_end(&mut contents)?;
+            }
+            Data::Gzipped { offset } => {
+                source.seek(SeekFrom::Start(0))?;
+                let mut decoder = GzDecoder::new(&mut *source);
+                io::copy(&mut (&mut decoder).take(offset), &mut io::sink())?;
+                decoder.take(len).read_to_end(&mut contents)?;
+            }
+            Data::Raw { .. } | Data::Unsupported => unreachable!(),
+        }
+
+        if contents.len() as u64 != len {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        Ok(contents)
+    }
+}
+
+fn index_tar<R: Read>(
+    tar_entries: tar::Entries<'_, R>,
+    entries: &mut BTreeMap<PathBuf, Entry>,
+    data: impl Fn(u64) -> Data,
+) -> io::Result<()> {
+    for tar_entry in tar_entries {
+        let tar_entry = tar_entry?;
+        let Some(path) = archive_path(&tar_entry.path()?) else {
+            continue;
+        };
+        let node = match tar_entry.header().entry_type() {
+            tar::EntryType::Directory => Node::Dir,
+            tar::EntryType::Regular | tar::EntryType::Continuous => Node::File {
+                data: data(tar_entry.raw_file_position()),
+                len: tar_entry.size(),
+            },
+            // Links and special files are not served
+            _ => continue,
+        };
+        let modified = tar_entry
+            .header()
+            .mtime()
+            .map(|secs| secs.saturating_mul(1_000_000_000))
+            .unwrap_or(0);
+        insert(entries, path, Entry { node, modified });
+    }
+    Ok(())
+}
+
+/// Adds an entry together with any parent directories that the archive does
+/// not list on their own
+fn insert(entries: &mut BTreeMap<PathBuf, Entry>, path: PathBuf, entry: Entry) {
+    for parent in path.ancestors().skip(1) {
+        entries.entry(parent.to_path_buf()).or_insert(Entry {
+            node: Node::Dir,
+            modified: 0,
+        });
+    }
+    entries.insert(path, entry);
+}
+
+/// Turns the path of an entry into an absolute path (a leading `/` is
+/// dropped like tar does), entries that would escape the root of the archive
+/// are left out
+fn archive_path(path: &Path) -> Option<PathBuf> {
+    let mut absolute = PathBuf::from("/");
+    for component in path.components() {
+        match component {
+            Component::Normal(part) => absolute.push(part),
+            Component::CurDir | Component::RootDir => {}
+            Component::ParentDir | Component::Prefix(_) => return None,
+        }
+    }
+    (absolute.as_os_str() != "/").then_some(absolute)
+}
+
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::from("/");
+    for component in path.components() {
+        match component {
+            Component::Normal(part) => normalized.push(part),
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            Component::CurDir | Component::RootDir | Component::Prefix(_) => {}
+        }
+    }
+    normalized
+}
+
+fn zip_error(err: zip::result::ZipError) -> FsError {
+    match err {
+        zip::result::ZipError::Io(err) => err.into(),
+        _ => FsError::InvalidData,
+    }
+}
+
+/// Converts the MS-DOS time of a zip entry into nanoseconds since the epoch
+fn zip_time(time: zip::DateTime) -> u64 {
+    // Days since the epoch of the civil date, see
+    // http://howardhinnant.github.io/date_algorithms.html#days_from_civil
+    let (month, day) = (time.month() as i64, time.day() as i64);
+    let year = time.year() as i64 - i64::from(month <= 2);
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    let days = era * 146_097 + day_of_era - 719_468;
+
+    let secs = days * 86_400
+        + time.hour() as i64 * 3_600
+        + time.minute() as i64 * 60
+        + time.second() as i64;
+    (secs.max(0) as u64) * 1_000_000_000
+}
+
+impl FileSystem for ArchiveFileSystem {
+    fn readlink(&self, _path: &Path) -> crate::Result<PathBuf> {
+        Err(FsError::InvalidInput)
+    }
+
+    fn read_dir(&self, path: &Path) -> crate::Result<ReadDir> {
+        let path = normalize(path);
+        match self.entries.get(&path) {
+            Some(Entry {
+                node: Node::Dir, ..
+            }) => {}
+            Some(_) => return Err(FsError::BaseNotDirectory),
+            None => return Err(FsError::EntryNotFound),
+        }
+
+        let entries = self
+            .entries
+            .range(path.clone()..)
+            .skip(1)
+            .take_while(|(child, _)| child.starts_with(&path))
+            .filter(|(child, _)| child.parent() == Some(path.as_path()))
+            .map(|(child, entry)| DirEntry {
+                path: child.clone(),
+                metadata: Ok(entry.metadata()),
+            })
+            .collect();
+        Ok(ReadDir::new(entries))
+    }
+
+    fn create_dir(&self, path: &Path) -> crate::Result<()> {
+        if self.entry(path).is_ok() {
+            return Err(FsError::AlreadyExists);
+        }
+        let parent = path.parent().unwrap_or_else(|| Path::new("/"));
+        match self.entry(parent)?.node {
+            Node::Dir => Err(FsError::PermissionDenied),
+            Node::File { .. } => Err(FsError::BaseNotDirectory),
+        }
+    }
+
+    fn remove_dir(&self, path: &Path) -> crate::Result<()> {
+        match self.entry(path)?.node {
+            Node::Dir => Err(FsError::PermissionDenied),
+            Node::File { .. } => Err(FsError::BaseNotDirectory),
+        }
+    }
+
+    fn rename<'a>(&'a self, from: &'a Path, _to: &'a Path) -> BoxFuture<'a, crate::Result<()>> {
+        Box::pin(async move {
+            self.entry(from)?;
+            Err(FsError::PermissionDenied)
+        })
+    }
+
+    fn metadata(&self, path: &Path) -> crate::Result<Metadata> {
+        self.entry(path).map(Entry::metadata)
+    }
+
+    fn symlink_metadata(&self, path: &Path) -> crate::Result<Metadata> {
+        self.metadata(path)
+    }
+
+    fn remove_file(&self, path: &Path) -> crate::Result<()> {
+        match self.entry(path)?.node {
+            Node::File { .. } => Err(FsError::PermissionDenied),
+            Node::Dir => Err(FsError::NotAFile),
+        }
+    }
+
+    fn new_open_options(&self) -> crate::OpenOptions {
+        crate::OpenOptions::new(self)
+    }
+}
+
+impl FileOpener for ArchiveFileSystem {
+    fn open(
+        &self,
+        path: &Path,
+        conf: &OpenOptionsConfig,
+    ) -> crate::Result<Box<dyn VirtualFile + Send + Sync + 'static>> {
+        let entry = match self.entry(path) {
+            Ok(entry) => *entry,
+            Err(FsError::EntryNotFound) if conf.create() || conf.create_new() => {
+                return Err(FsError::PermissionDenied)
+            }
+            Err(err) => return Err(err),
+        };
+        let (data, len) = match entry.node {
+            Node::File { data, len } => (data, len),
+            Node::Dir => return Err(FsError::NotAFile),
+        };
+        if conf.would_mutate() {
+            return Err(FsError::PermissionDenied);
+        }
+
+        let contents = match data {
+            Data::Raw { offset } => Contents::Range {
+                source: self.source.clone(),
+                start: offset,
+            },
+            Data::Deflated { .. } | Data::Gzipped { .. } => {
+                Contents::Buffer(self.decompress(data, len)?)
+            }
+            Data::Unsupported => return Err(FsError::InvalidData),
+        };
+
+        Ok(Box::new(File {
+            contents,
+            len,
+            position: 0,
+            modified: entry.modified,
+        }))
+    }
+}
+
+#[derive(Derivative)]
+#[derivative(Debug)]
+enum Contents {
+    /// Read on demand from a range of the archive
+    Range {
+        #[derivative(Debug = "ignore")]
+        source: SharedSource,
+        start: u64,
+    },
+    Buffer(#[derivative(Debug = "ignore")] Vec<u8>),
+}
+
+#[derive(Debug)]
+struct File {
+    contents: Contents,
+    len: u64,
+    position: u64,
+    modified: u64,
+}
+
+impl File {
+    fn read_at(&self, buf: &mut [u8]) -> io::Result<usize> {
+        let remaining = self.len.saturating_sub(self.position);
+        let n = buf.len().min(remaining.try_into().unwrap_or(usize::MAX));
+        if n == 0 {
+            return Ok(0);
+        }
+
+        match &self.contents {
+            Contents::Range { source, start } => {
+                let mut source = source.lock().unwrap();
+                source.seek(SeekFrom::Start(start + self.position))?;
+                source.read_exact(&mut buf[..n])?;
+            }
+            Contents::Buffer(bytes) => {
+                let position = self.position as usize;
+                buf[..n].copy_from_slice(&bytes[position..position + n]);
+            }
+        }
+        Ok(n)
+    }
+}
+
+impl VirtualFile for File {
+    fn last_accessed(&self) -> u64 {
+        0
+    }
+
+    fn last_modified(&self) -> u64 {
+        self.modified
+    }
+
+    fn created_time(&self) -> u64 {
+        0
+    }
+
+    fn size(&self) -> u64 {
+        self.len
+    }
+
+    fn set_len(&mut self, _new_size: u64) -> crate::Result<()> {
+        Err(FsError::PermissionDenied)
+    }
+
+    fn unlink(&mut self) -> crate::Result<()> {
+        Err(FsError::PermissionDenied)
+    }
+
+    fn poll_read_ready(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<usize>> {
+        let remaining = self.len.saturating_sub(self.position);
+        Poll::Ready(Ok(remaining.try_into().unwrap_or(usize::MAX)))
+    }
+
+    fn poll_write_ready(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<usize>> {
+        Poll::Ready(Err(io::ErrorKind::PermissionDenied.into()))
+    }
+}
+
+impl AsyncRead for File {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let n = self.read_at(buf.initialize_unfilled())?;
+        buf.advance(n);
+        self.position += n as u64;
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl AsyncSeek for File {
+    fn start_seek(mut self: Pin<&mut Self>, position: SeekFrom) -> io::Result<()> {
+        let (base, offset) = match position {
+            SeekFrom::Start(position) => {
+                self.position = position;
+                return Ok(());
+            }
+            SeekFrom::End(offset) => (self.len, offset),
+            SeekFrom::Current(offset) => (self.position, offset),
+        };
+        self.position = base.checked_add_signed(offset).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "invalid seek to a negative or overflowing position",
+            )
+        })?;
+        Ok(())
+    }
+
+    fn poll_complete(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<u64>> {
+        Poll::Ready(Ok(self.position))
+    }
+}
+
+impl AsyncWrite for File {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        _buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Poll::Ready(Err(io::ErrorKind::PermissionDenied.into()))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Cursor, Write};
+
+    use tokio::io::{AsyncReadExt, AsyncSeekExt};
+
+    use super::*;
+
+    const FILES: &[(&str, &[u8])] = &[
+        ("hello.txt", b"Hello, World!"),
+        ("nested/dir/data.bin", &[0xAB; 4096]),
+    ];
+
+    fn zip_archive() -> Vec<u8> {
+        let mut writer = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        for (i, (path, contents)) in FILES.iter().enumerate() {
+            // Alternate between stored and compressed entries
+            let method = if i % 2 == 0 {
+                zip::CompressionMethod::Stored
+            } else {
+                zip::CompressionMethod::Deflated
+            };
+            let options = zip::write::SimpleFileOptions::default().compression_method(method);
+            writer.start_file(*path, options).unwrap();
+            writer.write_all(contents).unwrap();
+        }
+        writer
+            .add_directory("empty", zip::write::SimpleFileOptions::default())
+            .unwrap();
+        writer.finish().unwrap().into_inner()
+    }
+
+    fn tar_archive() -> Vec<u8> {
+        let mut builder = tar::Builder::new(Vec::new());
+        for (path, contents) in FILES {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(contents.len() as u64);
+            header.set_mtime(1_700_000_000);
+            header.set_mode(0o644);
+            builder.append_data(&mut header, path, *contents).unwrap();
+        }
+        let mut header = tar::Header::new_gnu();
+        header.set_entry_type(tar::EntryType::Directory);
+        header.set_size(0);
+        header.set_mode(0o755);
+        builder
+            .append_data(&mut header, "empty", io::empty())
+            .unwrap();
+        builder.into_inner().unwrap()
+    }
+
+    fn tar_gz_archive() -> Vec<u8> {
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(&tar_archive()).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    async fn check(fs: ArchiveFileSystem) {
+        for (path, contents) in FILES {
+            let path = Path::new("/").join(path);
+            assert_eq!(fs.metadata(&path).unwrap().len, contents.len() as u64);
+
+            let mut file = fs.new_open_options().read(true).open(&path).unwrap();
+            let mut buf = Vec::new();
+            file.read_to_end(&mut buf).await.unwrap();
+            assert_eq!(&buf, contents);
+
+            // Seeking back reads the same bytes again
+            file.seek(SeekFrom::Start(1)).await.unwrap();
+            let mut buf = [0; 4];
+            file.read_exact(&mut buf).await.unwrap();
+            assert_eq!(&buf, &contents[1..5]);
+        }
+
+        let mut root: Vec<_> = fs
+            .read_dir(Path::new("/"))
+            .unwrap()
+            .map(|entry| entry.unwrap().path)
+            .collect();
+        root.sort();
+        assert_eq!(
+            root,
+            [
+                PathBuf::from("/empty"),
+                PathBuf::from("/hello.txt"),
+                PathBuf::from("/nested"),
+            ]
+        );
+        assert!(fs.metadata(Path::new("/nested/dir")).unwrap().is_dir());
+        assert!(fs.metadata(Path::new("/empty")).unwrap().is_dir());
+        assert_eq!(
+            fs.metadata(Path::new("/missing")).unwrap_err(),
+            FsError::EntryNotFound
+        );
+    }
+
+    #[tokio::test]
+    async fn serves_a_zip_archive() {
+        check(ArchiveFileSystem::from_zip(Cursor::new(zip_archive())).unwrap()).await;
+    }
+
+    #[test]
+    fn encrypted_zip_entries_are_rejected() {
+        use zip::unstable::write::FileOptionsExt;
+
+        let mut writer = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        let options = zip::write::SimpleFileOptions::default()
+            .compression_method(zip::CompressionMethod::Stored)
+            .with_deprecated_encryption(b"secret");
+        writer.start_file("secret.txt", options).unwrap();
+        writer.write_all(b"Hello, World!").unwrap();
+        let archive = writer.finish().unwrap().into_inner();
+
+        let fs = ArchiveFileSystem::from_zip(Cursor::new(archive)).unwrap();
+        assert!(fs.metadata(Path::new("/secret.txt")).unwrap().is_file());
+        assert_eq!(
+            fs.new_open_options()
+                .read(true)
+                .open(Path::new("/secret.txt"))
+                .unwrap_err(),
+            FsError::InvalidData
+        );
+    }
+
+    #[tokio::test]
+    async fn serves_a_tar_archive() {
+        let fs = ArchiveFileSystem::from_tar(Cursor::new(tar_archive())).unwrap();
+        assert_eq!(
+            fs.metadata(Path::new("/hello.txt")).unwrap().modified,
+            1_700_000_000 * 1_000_000_000
+        );
+        check(fs).await;
+    }
+
+    #[tokio::test]
+    async fn serves_a_tar_gz_archive() {
+        check(ArchiveFileSystem::from_tar_gz(Cursor::new(tar_gz_archive())).unwrap()).await;
+    }
+
+    #[tokio::test]
+    async fn opens_archives_by_extension() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("assets.tgz");
+        std::fs::write(&path, tar_gz_archive()).unwrap();
+
+        check(ArchiveFileSystem::open(&path).unwrap()).await;
+        assert_eq!(
+            ArchiveFileSystem::open(dir.path().join("assets.rar")).unwrap_err(),
+            FsError::InvalidInput
+        );
+    }
+
+    #[test]
+    fn the_archive_is_read_only() {
+        let fs = ArchiveFileSystem::from_tar(Cursor::new(tar_archive())).unwrap();
+
+        assert_eq!(
+            fs.new_open_options()
+                .write(true)
+                .open(Path::new("/hello.txt"))
+                .unwrap_err(),
+            FsError::PermissionDenied
+        );
+        assert_eq!(
+            fs.new_open_options()
+                .create(true)
+                .write(true)
+                .open(Path::new("/new.txt"))
+                .unwrap_err(),
+            FsError::PermissionDenied
+        );
+        assert_eq!(
+            fs.remove_file(Path::new("/hello.txt")).unwrap_err(),
+            FsError::PermissionDenied
+        );
+        assert_eq!(
+            fs.create_dir(Path::new("/new")).unwrap_err(),
+            FsError::PermissionDenied
+        );
+    }
+
+    #[test]
+    fn entries_outside_the_root_are_skipped() {
+        assert_eq!(archive_path(Path::new("../etc/passwd")), None);
+        assert_eq!(archive_path(Path::new("a/../../b")), None);
+        assert_eq!(
+            archive_path(Path::new("/etc/passwd")),
+            Some(PathBuf::from("/etc/passwd"))
+        );
+        assert_eq!(
+            archive_path(Path::new("./a/b.txt")),
+            Some(PathBuf::from("/a/b.txt"))
+        );
+    }
+
+    #[test]
+    fn zip_times_are_converted_to_the_epoch() {
+        let time = zip::DateTime::from_date_and_time(2023, 11, 14, 22, 13, 20).unwrap();
+        assert_eq!(zip_time(time), 1_700_000_000 * 1_000_000_000);
+    }
+}
@@ -78,29 +78,29 @@ impl RootFileSystemBuilder {
         if self.default_dev_files {
             let _ = tmp
                 .new_open_options_ext()
-                .insert_device_file(PathBuf::from("/dev/null"), Box::<NullFile>::default());
+                .insert_char_device(PathBuf::from("/dev/null"), Box::<NullFile>::default());
             let _ = tmp
                 .new_open_options_ext()
-                .insert_device_file(PathBuf::from("/dev/zero"), Box::<ZeroFile>::default());
+                .insert_char_device(PathBuf::from("/dev/zero"), Box::<ZeroFile>::default());
             let _ = tmp
                 .new_open_options_ext()
-                .insert_device_file(PathBuf::from("/dev/urandom"), Box::<RandomFile>::default());
-            let _ = tmp.new_open_options_ext().insert_device_file(
+                .insert_char_device(PathBuf::from("/dev/urandom"), Box::<RandomFile>::default());
+            let _ = tmp.new_open_options_ext().insert_char_device(
                 PathBuf::from("/dev/stdin"),
                 self.stdin
                     .unwrap_or_else(|| Box::new(DeviceFile::new(DeviceFile::STDIN))),
             );
-            let _ = tmp.new_open_options_ext().insert_device_file(
+            let _ = tmp.new_open_options_ext().insert_char_device(
                 PathBuf::from("/dev/stdout"),
                 self.stdout
                     .unwrap_or_else(|| Box::new(DeviceFile::new(DeviceFile::STDOUT))),
             );
-            let _ = tmp.new_open_options_ext().insert_device_file(
+            let _ = tmp.new_open_options_ext().insert_char_device(
                 PathBuf::from("/dev/stderr"),
                 self.stderr
                     .unwrap_or_else(|| Box::new(DeviceFile::new(DeviceFile::STDERR))),
             );
-            let _ = tmp.new_open_options_ext().insert_device_file(
+            let _ = tmp.new_open_options_ext().insert_char_device(
                 PathBuf::from("/dev/tty"),
                 self.tty.unwrap_or_else(|| Box::<NullFile>::default()),
             );
@@ -111,6 +111,8 @@ impl RootFileSystemBuilder {
 
 #[cfg(test)]
 mod test_builder {
+    use std::path::Path;
+
     use crate::{FileSystem, RootFileSystemBuilder};
     use tokio::io::{AsyncReadExt, AsyncWriteExt};
 
@@ -180,5 +182,11 @@ mod test_builder {
             .open("/dev/stderr")
             .unwrap();
         assert_eq!(dev_stderr.get_special_fd().unwrap(), 2);
+
+        // Only the devices are reported as character devices
+        let ft = root_fs.metadata(Path::new("/dev/null")).unwrap().ft;
+        assert!(ft.is_file() && ft.is_char_device());
+        let ft = root_fs.metadata(Path::new("/bin/wasmer")).unwrap().ft;
+        assert!(ft.is_file() && !ft.is_char_device());
     }
 }
@@ -0,0 +1,202 @@
+//! ChannelFile is a write-only file that forwards every write, as it
+//! happens, to a [`tokio::sync::mpsc`] channel.
+
+use std::future::Future;
+use std::io::{self, SeekFrom};
+use std::pin::Pin;
+use std::sync::Mutex;
+use std::task::{ready, Context, Poll};
+
+use bytes::Bytes;
+use derivative::Derivative;
+use tokio::io::{AsyncRead, AsyncSeek, AsyncWrite};
+use tokio::sync::mpsc::{
+    self,
+    error::{SendError, TrySendError},
+    OwnedPermit,
+};
+
+use crate::VirtualFile;
+
+type ReserveFuture =
+    Pin<Box<dyn Future<Output = Result<OwnedPermit<Bytes>, SendError<()>>> + Send + 'static>>;
+
+/// What a [`ChannelFile`] does with a write when the channel is full
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ChannelBackpressure {
+    /// The write waits until the receiver made room in the channel
+    #[default]
+    Wait,
+    /// The written data is discarded
+    Drop,
+    /// The write fails with [`io::ErrorKind::WouldBlock`]
+    Fail,
+}
+
+/// Sends each write as a separate message on a channel, once the receiver
+/// is dropped writes fail with [`io::ErrorKind::BrokenPipe`].
+///
+/// Reading from the file always returns EOF.
+#[derive(Derivative)]
+#[derivative(Debug)]
+pub struct ChannelFile {
+    tx: mpsc::Sender<Bytes>,
+    backpressure: ChannelBackpressure,
+    /// Pending wait for room in the channel (when waiting on backpressure)
+    #[derivative(Debug = "ignore")]
+    reserve: Mutex<Option<ReserveFuture>>,
+    /// Room that was reserved in the channel for the next write
+    #[derivative(Debug = "ignore")]
+    permit: Option<OwnedPermit<Bytes>>,
+}
+
+impl ChannelFile {
+    pub fn new(tx: mpsc::Sender<Bytes>) -> Self {
+        Self {
+            tx,
+            backpressure: ChannelBackpressure::default(),
+            reserve: Mutex::new(None),
+            permit: None,
+        }
+    }
+
+    /// Sets what happens to a write when the channel is full
+    pub fn with_backpressure(mut self, backpressure: ChannelBackpressure) -> Self {
+        self.backpressure = backpressure;
+        self
+    }
+
+    /// Waits until there is room in the channel for the next write
+    fn poll_reserve(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        if self.permit.is_some() {
+            return Poll::Ready(Ok(()));
+        }
+        let reserve = self.reserve.get_mut().unwrap();
+        let permit = reserve
+            .get_or_insert_with(|| Box::pin(self.tx.clone().reserve_owned()))
+            .as_mut()
+            .poll(cx);
+        match permit {
+            Poll::Ready(Ok(permit)) => {
+                reserve.take();
+                self.permit.replace(permit);
+                Poll::Ready(Ok(()))
+            }
+            Poll::Ready(Err(_)) => {
+                reserve.take();
+                Poll::Ready(Err(io::ErrorKind::BrokenPipe.into()))
+            }
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+impl AsyncWrite for ChannelFile {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        if buf.is_empty() {
+            return Poll::Ready(Ok(0));
+        }
+
+        let this = self.get_mut();
+        if this.backpressure == ChannelBackpressure::Wait {
+            if let Err(err) = ready!(this.poll_reserve(cx)) {
+                return Poll::Ready(Err(err));
+            }
+        }
+        // Room that was reserved while polling for readiness is used first
+        if let Some(permit) = this.permit.take() {
+            permit.send(Bytes::copy_from_slice(buf));
+            return Poll::Ready(Ok(buf.len()));
+        }
+
+        match this.tx.try_send(Bytes::copy_from_slice(buf)) {
+            Ok(()) => Poll::Ready(Ok(buf.len())),
+            Err(TrySendError::Full(_)) if this.backpressure == ChannelBackpressure::Drop => {
+                Poll::Ready(Ok(buf.len()))
+            }
+            Err(TrySendError::Full(_)) => Poll::Ready(Err(io::ErrorKind::WouldBlock.into())),
+            Err(TrySendError::Closed(_)) => Poll::Ready(Err(io::ErrorKind::BrokenPipe.into())),
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl AsyncRead for ChannelFile {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        _buf: &mut tokio::io::ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl AsyncSeek for ChannelFile {
+    fn start_seek(self: Pin<&mut Self>, _position: SeekFrom) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn poll_complete(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<u64>> {
+        Poll::Ready(Ok(0))
+    }
+}
+
+impl VirtualFile for ChannelFile {
+    fn last_accessed(&self) -> u64 {
+        0
+    }
+
+    fn last_modified(&self) -> u64 {
+        0
+    }
+
+    fn created_time(&self) -> u64 {
+        0
+    }
+
+    fn size(&self) -> u64 {
+        0
+    }
+
+    fn set_len(&mut self, _new_size: u64) -> crate::Result<()> {
+        Ok(())
+    }
+
+    fn unlink(&mut self) -> crate::Result<()> {
+        Ok(())
+    }
+
+    fn poll_read_ready(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<usize>> {
+        Poll::Ready(Ok(0))
+    }
+
+    /// Every write is sent as a single message whatever its size, so the
+    /// file is ready as long as the channel has room for one more message
+    fn poll_write_ready(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        if this.tx.is_closed() {
+            return Poll::Ready(Ok(0));
+        }
+        match this.backpressure {
+            // Writes never block as they are discarded when the channel is full
+            ChannelBackpressure::Drop => Poll::Ready(Ok(8192)),
+            ChannelBackpressure::Wait | ChannelBackpressure::Fail => {
+                match ready!(this.poll_reserve(cx)) {
+                    Ok(()) => Poll::Ready(Ok(8192)),
+                    Err(_) => Poll::Ready(Ok(0)),
+                }
+            }
+        }
+    }
+}
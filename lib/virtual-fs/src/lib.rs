@@ -19,14 +19,18 @@ use thiserror::Error;
 pub mod arc_box_file;
 pub mod arc_file;
 pub mod arc_fs;
+#[cfg(feature = "archive-fs")]
+pub mod archive_fs;
 pub mod buffer_file;
 pub mod builder;
+pub mod channel_file;
 pub mod combine_file;
 pub mod cow_file;
 pub mod dual_write_file;
 pub mod empty_fs;
 #[cfg(feature = "host-fs")]
 pub mod host_fs;
+pub mod line_buffered_file;
 pub mod mem_fs;
 pub mod null_file;
 pub mod passthru_fs;
@@ -40,12 +44,15 @@ mod filesystems;
 pub(crate) mod ops;
 mod overlay_fs;
 pub mod pipe;
+mod read_only_fs;
 #[cfg(feature = "host-fs")]
 mod scoped_directory_fs;
 mod static_file;
 #[cfg(feature = "static-fs")]
 pub mod static_fs;
 mod trace_fs;
+#[cfg(feature = "host-fs")]
+mod translated_fs;
 #[cfg(feature = "webc-fs")]
 pub mod webc_fs;
 #[cfg(feature = "webc-fs")]
@@ -58,21 +65,26 @@ pub use arc_file::*;
 pub use arc_fs::*;
 pub use buffer_file::*;
 pub use builder::*;
+pub use channel_file::*;
 pub use combine_file::*;
 pub use cow_file::*;
 pub use dual_write_file::*;
 pub use empty_fs::*;
 pub use filesystems::FileSystems;
+pub use line_buffered_file::*;
 pub use null_file::*;
 pub use overlay_fs::OverlayFileSystem;
 pub use passthru_fs::*;
 pub use pipe::*;
+pub use read_only_fs::ReadOnlyFileSystem;
 #[cfg(feature = "host-fs")]
 pub use scoped_directory_fs::ScopedDirectoryFileSystem;
 pub use special_file::*;
 pub use static_file::StaticFile;
 pub use tmp_fs::*;
 pub use trace_fs::TraceFileSystem;
+#[cfg(feature = "host-fs")]
+pub use translated_fs::{TranslatePathFn, TranslatedFileSystem};
 pub use union_fs::*;
 #[cfg(feature = "webc-fs")]
 pub use webc_volume_fs::WebcVolumeFileSystem;
@@ -513,6 +525,13 @@ pub enum FsError {
     DirectoryNotEmpty,
     #[error("storage full")]
     StorageFull,
+    /// The operation would have to move data between two different file
+    /// systems (e.g. two different mounts)
+    #[error("cross-device link")]
+    CrossDevice,
+    /// The operation would modify a read-only file system
+    #[error("read-only file system")]
+    ReadOnlyFileSystem,
     /// Some other unhandled error. If you see this, it's probably a bug.
     #[error("unknown error found")]
     UnknownError,
@@ -576,6 +595,10 @@ impl From<FsError> for io::Error {
             FsError::DirectoryNotEmpty => io::ErrorKind::Other,
             FsError::UnknownError => io::ErrorKind::Other,
             FsError::StorageFull => io::ErrorKind::Other,
+            FsError::CrossDevice => io::ErrorKind::Other,
+            // NOTE: Use `io::ErrorKind::ReadOnlyFilesystem` once the minimum
+            // supported Rust version has it
+            FsError::ReadOnlyFileSystem => io::ErrorKind::PermissionDenied,
             // NOTE: Add this once the "io_error_more" Rust feature is stabilized
             // FsError::StorageFull => io::ErrorKind::StorageFull,
         };
@@ -0,0 +1,177 @@
+use derivative::Derivative;
+
+use super::*;
+
+use crate::VirtualFile;
+
+/// Number of bytes that are held back before a partial line is written
+/// out anyway (same as the default `BUFSIZ` of most libc implementations)
+const MAX_LINE_BUFFER: usize = 8192;
+
+/// Wraps a [`VirtualFile`] and holds back written data until a complete
+/// line (terminated by `\n`) is available, at which point the line is
+/// written out to the inner file.
+///
+/// Flushing only writes out the complete lines, the trailing partial line
+/// is written when the file is shut down or dropped, or when it grows
+/// beyond the size of the line buffer.
+#[derive(Derivative)]
+#[derivative(Debug)]
+pub struct LineBufferedFile {
+    inner: Box<dyn VirtualFile + Send + Sync + 'static>,
+    #[derivative(Debug = "ignore")]
+    buffer: Vec<u8>,
+}
+
+impl LineBufferedFile {
+    pub fn new(inner: Box<dyn VirtualFile + Send + Sync + 'static>) -> Self {
+        Self {
+            inner,
+            buffer: Vec::new(),
+        }
+    }
+
+    /// Length of the buffered data that makes up complete lines
+    fn complete_lines(&self) -> usize {
+        self.buffer
+            .iter()
+            .rposition(|b| *b == b'\n')
+            .map(|pos| pos + 1)
+            .unwrap_or(0)
+    }
+
+    /// Writes the first `len` bytes of the buffer to the inner file
+    fn poll_write_out(&mut self, cx: &mut Context<'_>, mut len: usize) -> Poll<io::Result<()>> {
+        while len > 0 {
+            match Pin::new(self.inner.as_mut()).poll_write(cx, &self.buffer[..len]) {
+                Poll::Ready(Ok(0)) => return Poll::Ready(Err(io::ErrorKind::WriteZero.into())),
+                Poll::Ready(Ok(amt)) => {
+                    self.buffer.drain(..amt);
+                    len -= amt;
+                }
+                Poll::Ready(Err(err)) => return Poll::Ready(Err(err)),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl Drop for LineBufferedFile {
+    fn drop(&mut self) {
+        // Best effort attempt to write out the trailing partial line
+        if !self.buffer.is_empty() {
+            let mut cx = Context::from_waker(futures::task::noop_waker_ref());
+            let len = self.buffer.len();
+            let _ = self.poll_write_out(&mut cx, len);
+        }
+    }
+}
+
+impl VirtualFile for LineBufferedFile {
+    fn last_accessed(&self) -> u64 {
+        self.inner.last_accessed()
+    }
+
+    fn last_modified(&self) -> u64 {
+        self.inner.last_modified()
+    }
+
+    fn created_time(&self) -> u64 {
+        self.inner.created_time()
+    }
+
+    fn set_times(&mut self, atime: Option<u64>, mtime: Option<u64>) -> crate::Result<()> {
+        self.inner.set_times(atime, mtime)
+    }
+
+    fn size(&self) -> u64 {
+        self.inner.size()
+    }
+
+    fn set_len(&mut self, new_size: u64) -> crate::Result<()> {
+        self.inner.set_len(new_size)
+    }
+
+    fn unlink(&mut self) -> Result<()> {
+        self.inner.unlink()
+    }
+
+    fn get_special_fd(&self) -> Option<u32> {
+        self.inner.get_special_fd()
+    }
+
+    fn poll_read_ready(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<usize>> {
+        Pin::new(self.inner.as_mut()).poll_read_ready(cx)
+    }
+
+    fn poll_write_ready(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<usize>> {
+        Pin::new(self.inner.as_mut()).poll_write_ready(cx)
+    }
+}
+
+impl AsyncWrite for LineBufferedFile {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        // Make room in the buffer when the line is too long
+        if self.buffer.len() >= MAX_LINE_BUFFER {
+            let len = self.buffer.len();
+            match self.poll_write_out(cx, len) {
+                Poll::Ready(Ok(())) => {}
+                res => return res.map_ok(|_| 0),
+            }
+        }
+
+        self.buffer.extend_from_slice(buf);
+
+        // The data is accepted even if the inner file is not ready yet,
+        // whatever is left over is written out by the next write or flush
+        let len = self.complete_lines();
+        if let Poll::Ready(Err(err)) = self.poll_write_out(cx, len) {
+            return Poll::Ready(Err(err));
+        }
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        let len = self.complete_lines();
+        match self.poll_write_out(cx, len) {
+            Poll::Ready(Ok(())) => Pin::new(self.inner.as_mut()).poll_flush(cx),
+            res => res,
+        }
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        let len = self.buffer.len();
+        match self.poll_write_out(cx, len) {
+            Poll::Ready(Ok(())) => Pin::new(self.inner.as_mut()).poll_shutdown(cx),
+            res => res,
+        }
+    }
+}
+
+impl AsyncRead for LineBufferedFile {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+        buf: &mut tokio::io::ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_read(cx, buf)
+    }
+}
+
+impl AsyncSeek for LineBufferedFile {
+    fn start_seek(mut self: Pin<&mut Self>, position: io::SeekFrom) -> io::Result<()> {
+        Pin::new(&mut self.inner).start_seek(position)
+    }
+
+    fn poll_complete(
+        mut self: Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> Poll<io::Result<u64>> {
+        Pin::new(&mut self.inner).poll_complete(cx)
+    }
+}
@@ -361,15 +361,15 @@ impl VirtualFile for FileHandle {
         let inode = fs.storage.get_mut(self.inode);
         match inode {
             Some(Node::File(node)) => {
-                let remaining = node.file.buffer.len() - (self.cursor as usize);
+                let remaining = node.file.buffer.len().saturating_sub(self.cursor as usize);
                 Poll::Ready(Ok(remaining))
             }
             Some(Node::OffloadedFile(node)) => {
-                let remaining = node.file.len() as usize - (self.cursor as usize);
+                let remaining = (node.file.len() as usize).saturating_sub(self.cursor as usize);
                 Poll::Ready(Ok(remaining))
             }
             Some(Node::ReadOnlyFile(node)) => {
-                let remaining = node.file.buffer.len() - (self.cursor as usize);
+                let remaining = node.file.buffer.len().saturating_sub(self.cursor as usize);
                 Poll::Ready(Ok(remaining))
             }
             Some(Node::CustomFile(node)) => {
@@ -1202,6 +1202,47 @@ mod test_read_write_seek {
         assert_eq!(string, "");
     }
 
+    #[tokio::test]
+    async fn test_reading_after_truncation() {
+        let fs = FileSystem::default();
+
+        let mut file = fs
+            .new_open_options()
+            .read(true)
+            .write(true)
+            .create_new(true)
+            .open(path!("/foo.txt"))
+            .expect("failed to create a new file");
+
+        assert!(
+            matches!(file.write(b"foobar").await, Ok(6)),
+            "writing `foobar`",
+        );
+
+        // The cursor is now past the end of the file
+        file.set_len(3).expect("failed to shrink the file");
+        assert_eq!(file.size(), 3, "checking the size of the file");
+
+        let mut string = String::new();
+        assert!(
+            matches!(file.read_to_string(&mut string).await, Ok(0)),
+            "reading past the end of the file",
+        );
+
+        file.set_len(5).expect("failed to grow the file");
+        assert!(
+            matches!(file.seek(io::SeekFrom::Start(0)).await, Ok(0)),
+            "seeking to 0",
+        );
+
+        let mut buf = Vec::new();
+        assert!(
+            matches!(file.read_to_end(&mut buf).await, Ok(5)),
+            "reading the grown file",
+        );
+        assert_eq!(buf, b"foo\0\0");
+    }
+
     #[test]
     pub fn writing_to_middle() {
         fn assert_contents(file: &File, expected: &[u8]) {
@@ -1432,7 +1473,8 @@ impl File {
 
 impl File {
     pub fn read(&self, buf: &mut [u8], cursor: &mut u64) -> io::Result<usize> {
-        let cur_pos = *cursor as usize;
+        // The file may have been truncated below the cursor, which is EOF
+        let cur_pos = cmp::min(*cursor as usize, self.buffer.len());
         let max_to_read = cmp::min(self.buffer.len() - cur_pos, buf.len());
         let data_to_copy = &self.buffer[cur_pos..][..max_to_read];
 
@@ -1526,7 +1568,7 @@ impl ReadOnlyFile {
 
 impl ReadOnlyFile {
     pub fn read(&self, buf: &mut [u8], cursor: &mut u64) -> io::Result<usize> {
-        let cur_pos = *cursor as usize;
+        let cur_pos = cmp::min(*cursor as usize, self.buffer.len());
         let max_to_read = cmp::min(self.buffer.len() - cur_pos, buf.len());
         let data_to_copy = &self.buffer[cur_pos..][..max_to_read];
 
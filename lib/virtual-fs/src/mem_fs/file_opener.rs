@@ -228,6 +228,25 @@ impl FileSystem {
         &self,
         path: PathBuf,
         file: Box<dyn crate::VirtualFile + Send + Sync>,
+    ) -> Result<()> {
+        self.insert_custom_file(path, file, false)
+    }
+
+    /// Inserts a character device (such as `/dev/null`) into the file system,
+    /// it is opened like any other file but reported as a character device
+    pub fn insert_char_device(
+        &self,
+        path: PathBuf,
+        file: Box<dyn crate::VirtualFile + Send + Sync>,
+    ) -> Result<()> {
+        self.insert_custom_file(path, file, true)
+    }
+
+    fn insert_custom_file(
+        &self,
+        path: PathBuf,
+        file: Box<dyn crate::VirtualFile + Send + Sync>,
+        char_device: bool,
     ) -> Result<()> {
         let _ = crate::FileSystem::remove_file(self, path.as_path());
         let (inode_of_parent, maybe_inode_of_file, name_of_file) =
@@ -259,6 +278,7 @@ impl FileSystem {
                 Metadata {
                     ft: FileType {
                         file: true,
+                        char_device,
                         ..Default::default()
                     },
                     accessed: time,
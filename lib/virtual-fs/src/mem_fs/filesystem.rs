@@ -178,6 +178,28 @@ impl FileSystem {
         }
     }
 
+    /// Checks whether both paths are on the same device, paths below two
+    /// different mounts (or below a mount and outside of any mount) are not.
+    pub fn is_same_device(&self, a: &Path, b: &Path) -> Result<bool> {
+        let guard = self.inner.read().map_err(|_| FsError::Lock)?;
+        let device_of = |path: &Path| {
+            let path = guard.canonicalize_without_inode(path)?;
+            let parent = path.parent().unwrap_or(&path);
+            guard
+                .inode_of_parent(parent)
+                .map(|resolution| match resolution {
+                    InodeResolution::Found(_) => None,
+                    InodeResolution::Redirect(fs, _) => Some(fs),
+                })
+        };
+
+        Ok(match (device_of(a)?, device_of(b)?) {
+            (None, None) => true,
+            (Some(a), Some(b)) => Arc::ptr_eq(&a, &b),
+            _ => false,
+        })
+    }
+
     pub fn mount(
         &self,
         target_path: PathBuf,
@@ -578,10 +600,10 @@ impl crate::FileSystem for FileSystem {
 
                         same_fs.rename(&from_path, &to_path).await
                     } else {
-                        Err(FsError::InvalidInput)
+                        Err(FsError::CrossDevice)
                     }
                 }
-                _ => Err(FsError::InvalidInput),
+                _ => Err(FsError::CrossDevice),
             }
         })
     }
@@ -1914,6 +1936,39 @@ mod test_filesystem {
         assert!(ops::is_file(&fs, "/top-level/nested/another-file.txt"));
     }
 
+    #[tokio::test]
+    async fn rename_across_mounts_is_cross_device() {
+        let a = FileSystem::default();
+        ops::touch(&a, "/file.txt").unwrap();
+        let a: Arc<dyn crate::FileSystem + Send + Sync> = Arc::new(a);
+        let b: Arc<dyn crate::FileSystem + Send + Sync> = Arc::new(FileSystem::default());
+
+        let fs = FileSystem::default();
+        ops::touch(&fs, "/root.txt").unwrap();
+        fs.mount("/a".into(), &a, "/".into()).unwrap();
+        fs.mount("/b".into(), &b, "/".into()).unwrap();
+
+        assert!(fs
+            .is_same_device(Path::new("/a/file.txt"), Path::new("/a/other.txt"))
+            .unwrap());
+        assert!(!fs
+            .is_same_device(Path::new("/a/file.txt"), Path::new("/b/file.txt"))
+            .unwrap());
+        assert!(!fs
+            .is_same_device(Path::new("/root.txt"), Path::new("/a/root.txt"))
+            .unwrap());
+        assert_eq!(
+            fs.rename(Path::new("/a/file.txt"), Path::new("/b/file.txt"))
+                .await,
+            Err(FsError::CrossDevice)
+        );
+        assert_eq!(
+            fs.rename(Path::new("/root.txt"), Path::new("/a/root.txt"))
+                .await,
+            Err(FsError::CrossDevice)
+        );
+    }
+
     #[tokio::test]
     async fn test_merge_flat() {
         let main = FileSystem::default();
@@ -1,4 +1,4 @@
-use bytes::{Buf, Bytes};
+use bytes::{Buf, Bytes, BytesMut};
 #[cfg(feature = "futures")]
 use futures::Future;
 use std::io::IoSlice;
@@ -9,6 +9,7 @@ use std::sync::Arc;
 use std::sync::Mutex;
 use std::task::Context;
 use std::task::Poll;
+use std::task::Waker;
 use tokio::io::{AsyncRead, AsyncSeek, AsyncWrite};
 use tokio::sync::{mpsc, mpsc::error::TryRecvError};
 
@@ -26,6 +27,8 @@ pub struct Pipe {
 pub struct PipeTx {
     /// Sends bytes down the pipe
     tx: Arc<Mutex<mpsc::UnboundedSender<Vec<u8>>>>,
+    /// Limits the number of bytes that are buffered in the pipe
+    capacity: Option<Arc<PipeCapacity>>,
 }
 
 #[derive(Debug, Clone)]
@@ -52,6 +55,7 @@ impl PipeRx {
                             Err(_) => return None,
                         };
                         read_buffer.advance(read);
+                        rx.release(read);
                         return Some(read);
                     }
                 }
@@ -70,34 +74,145 @@ impl PipeRx {
             rx.buffer.replace(Bytes::from(data));
         }
     }
+
+    /// Number of bytes that can be read without blocking, every chunk that
+    /// is waiting in the channel is moved into the read buffer to count it
+    fn bytes_available(&self) -> usize {
+        let mut rx = self.rx.lock().unwrap();
+
+        let mut queued = Vec::new();
+        while let Ok(data) = rx.chan.try_recv() {
+            queued.push(data);
+        }
+        if !queued.is_empty() {
+            let mut buffer = BytesMut::from(rx.buffer.take().unwrap_or_default().as_ref());
+            for data in queued {
+                buffer.extend_from_slice(&data);
+            }
+            rx.buffer.replace(buffer.freeze());
+        }
+
+        rx.buffer.as_ref().map(|b| b.len()).unwrap_or_default()
+    }
 }
 
 #[derive(Debug)]
 struct PipeReceiver {
     chan: mpsc::UnboundedReceiver<Vec<u8>>,
     buffer: Option<Bytes>,
+    capacity: Option<Arc<PipeCapacity>>,
+}
+
+impl PipeReceiver {
+    /// Makes room for more data once bytes have been consumed
+    fn release(&self, amt: usize) {
+        if let Some(capacity) = self.capacity.as_ref() {
+            capacity.release(amt);
+        }
+    }
+}
+
+impl Drop for PipeReceiver {
+    fn drop(&mut self) {
+        // Writers that wait for room need to learn that the pipe is broken
+        if let Some(capacity) = self.capacity.as_ref() {
+            capacity.wake_writers();
+        }
+    }
+}
+
+/// Keeps track of the number of bytes that are buffered in a bounded pipe,
+/// writers wait for room once the capacity is used up
+#[derive(Debug)]
+struct PipeCapacity {
+    capacity: usize,
+    state: Mutex<PipeCapacityState>,
+}
+
+#[derive(Debug, Default)]
+struct PipeCapacityState {
+    buffered: usize,
+    wakers: Vec<Waker>,
+}
+
+impl PipeCapacity {
+    fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            state: Mutex::new(PipeCapacityState::default()),
+        }
+    }
+
+    /// Number of bytes that can be written without exceeding the capacity,
+    /// registers the waker when the pipe is full
+    fn poll_room(&self, cx: Option<&mut Context<'_>>) -> usize {
+        let mut state = self.state.lock().unwrap();
+        let room = self.capacity.saturating_sub(state.buffered);
+        if room == 0 {
+            if let Some(cx) = cx {
+                if !state.wakers.iter().any(|w| w.will_wake(cx.waker())) {
+                    state.wakers.push(cx.waker().clone());
+                }
+            }
+        }
+        room
+    }
+
+    fn reserve(&self, amt: usize) {
+        self.state.lock().unwrap().buffered += amt;
+    }
+
+    fn release(&self, amt: usize) {
+        let wakers = {
+            let mut state = self.state.lock().unwrap();
+            state.buffered = state.buffered.saturating_sub(amt);
+            std::mem::take(&mut state.wakers)
+        };
+        wakers.into_iter().for_each(Waker::wake);
+    }
+
+    fn wake_writers(&self) {
+        let wakers = std::mem::take(&mut self.state.lock().unwrap().wakers);
+        wakers.into_iter().for_each(Waker::wake);
+    }
 }
 
 impl Pipe {
-    fn new() -> Self {
+    fn new(capacity: Option<usize>) -> Self {
         let (tx, rx) = mpsc::unbounded_channel();
+        let capacity = capacity.map(|capacity| Arc::new(PipeCapacity::new(capacity)));
 
         Pipe {
             send: PipeTx {
                 tx: Arc::new(Mutex::new(tx)),
+                capacity: capacity.clone(),
             },
             recv: PipeRx {
                 rx: Arc::new(Mutex::new(PipeReceiver {
                     chan: rx,
                     buffer: None,
+                    capacity,
                 })),
             },
         }
     }
 
     pub fn channel() -> (Pipe, Pipe) {
-        let (tx1, rx1) = Pipe::new().split();
-        let (tx2, rx2) = Pipe::new().split();
+        let (tx1, rx1) = Pipe::new(None).split();
+        let (tx2, rx2) = Pipe::new(None).split();
+
+        let end1 = Pipe::combine(tx1, rx2);
+        let end2 = Pipe::combine(tx2, rx1);
+        (end1, end2)
+    }
+
+    /// Creates a pair of connected pipes where at most `capacity` bytes are
+    /// buffered in each direction, once a direction is full writes to it
+    /// wait (or fail with [`io::ErrorKind::WouldBlock`] for the blocking
+    /// [`std::io::Write`] interface) until the other end reads some data.
+    pub fn bounded_channel(capacity: usize) -> (Pipe, Pipe) {
+        let (tx1, rx1) = Pipe::new(Some(capacity)).split();
+        let (tx2, rx2) = Pipe::new(Some(capacity)).split();
 
         let end1 = Pipe::combine(tx1, rx2);
         let end2 = Pipe::combine(tx2, rx1);
@@ -115,6 +230,12 @@ impl Pipe {
     pub fn try_read(&mut self, buf: &mut [u8]) -> Option<usize> {
         self.recv.try_read(buf)
     }
+
+    /// Returns the number of bytes that are buffered in the pipe and can be
+    /// read without blocking (like `FIONREAD`)
+    pub fn bytes_available(&self) -> usize {
+        self.recv.bytes_available()
+    }
 }
 
 impl From<Pipe> for PipeTx {
@@ -184,6 +305,7 @@ impl Read for PipeRx {
                         let mut inner_buf = &read_buffer[..read];
                         read = Read::read(&mut inner_buf, buf)?;
                         read_buffer.advance(read);
+                        rx.release(read);
                         return Ok(read);
                     }
                 }
@@ -223,12 +345,38 @@ impl std::io::Write for Pipe {
     }
 }
 
+impl PipeTx {
+    /// Sends as much of the buffer as the capacity of the pipe allows
+    fn send(&self, buf: &[u8], cx: Option<&mut Context<'_>>) -> Poll<io::Result<usize>> {
+        let tx = self.tx.lock().unwrap();
+        let buf = match self.capacity.as_ref() {
+            Some(capacity) if !buf.is_empty() => {
+                if tx.is_closed() {
+                    return Poll::Ready(Err(io::ErrorKind::BrokenPipe.into()));
+                }
+                let room = capacity.poll_room(cx);
+                if room == 0 {
+                    return Poll::Pending;
+                }
+                let buf = &buf[..room.min(buf.len())];
+                capacity.reserve(buf.len());
+                buf
+            }
+            _ => buf,
+        };
+        match tx.send(buf.to_vec()) {
+            Ok(()) => Poll::Ready(Ok(buf.len())),
+            Err(_) => Poll::Ready(Err(io::ErrorKind::BrokenPipe.into())),
+        }
+    }
+}
+
 impl std::io::Write for PipeTx {
     fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
-        let tx = self.tx.lock().unwrap();
-        tx.send(buf.to_vec())
-            .map_err(|_| Into::<std::io::Error>::into(std::io::ErrorKind::BrokenPipe))?;
-        Ok(buf.len())
+        match self.send(buf, None) {
+            Poll::Ready(res) => res,
+            Poll::Pending => Err(io::ErrorKind::WouldBlock.into()),
+        }
     }
 
     fn flush(&mut self) -> std::io::Result<()> {
@@ -302,16 +450,10 @@ impl AsyncWrite for Pipe {
 impl AsyncWrite for PipeTx {
     fn poll_write(
         self: Pin<&mut Self>,
-        _cx: &mut Context<'_>,
+        cx: &mut Context<'_>,
         buf: &[u8],
     ) -> Poll<io::Result<usize>> {
-        let guard = self.tx.lock().unwrap();
-        match guard.send(buf.to_vec()) {
-            Ok(()) => Poll::Ready(Ok(buf.len())),
-            Err(_) => Poll::Ready(Err(Into::<std::io::Error>::into(
-                std::io::ErrorKind::BrokenPipe,
-            ))),
-        }
+        self.send(buf, Some(cx))
     }
 
     fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
@@ -350,6 +492,7 @@ impl AsyncRead for PipeRx {
                         let read = buf_len.min(buf.remaining());
                         buf.put_slice(&inner_buf[..read]);
                         inner_buf.advance(read);
+                        rx.release(read);
                         return Poll::Ready(Ok(()));
                     }
                 }
@@ -433,12 +576,17 @@ impl VirtualFile for Pipe {
     }
 
     /// Polls the file for when it is available for writing
-    fn poll_write_ready(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<usize>> {
+    fn poll_write_ready(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<usize>> {
         let tx = self.send.tx.lock().unwrap();
         if tx.is_closed() {
-            Poll::Ready(Ok(0))
-        } else {
-            Poll::Ready(Ok(8192))
+            return Poll::Ready(Ok(0));
+        }
+        match self.send.capacity.as_ref() {
+            Some(capacity) => match capacity.poll_room(Some(cx)) {
+                0 => Poll::Pending,
+                room => Poll::Ready(Ok(room)),
+            },
+            None => Poll::Ready(Ok(8192)),
         }
     }
 }
@@ -0,0 +1,298 @@
+use std::{
+    io,
+    path::{Path, PathBuf},
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use futures::future::BoxFuture;
+use tokio::io::{AsyncRead, AsyncSeek, AsyncWrite, ReadBuf};
+
+use crate::{
+    FileOpener, FileSystem, FsError, Metadata, OpenOptions, OpenOptionsConfig, ReadDir, VirtualFile,
+};
+
+/// A [`FileSystem`] wrapper that makes the wrapped file system read-only.
+///
+/// Every operation that would modify the file system fails with
+/// [`FsError::ReadOnlyFileSystem`], this includes opening a file for writing
+/// and the mutating operations on files that were opened for reading (such
+/// as truncating them or changing their timestamps).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReadOnlyFileSystem<F> {
+    inner: F,
+}
+
+impl<F> ReadOnlyFileSystem<F> {
+    pub fn new(inner: F) -> Self {
+        ReadOnlyFileSystem { inner }
+    }
+
+    pub fn inner(&self) -> &F {
+        &self.inner
+    }
+
+    pub fn into_inner(self) -> F {
+        self.inner
+    }
+}
+
+impl<F> FileSystem for ReadOnlyFileSystem<F>
+where
+    F: FileSystem,
+{
+    fn readlink(&self, path: &Path) -> crate::Result<PathBuf> {
+        self.inner.readlink(path)
+    }
+
+    fn read_dir(&self, path: &Path) -> crate::Result<ReadDir> {
+        self.inner.read_dir(path)
+    }
+
+    fn create_dir(&self, _path: &Path) -> crate::Result<()> {
+        Err(FsError::ReadOnlyFileSystem)
+    }
+
+    fn remove_dir(&self, _path: &Path) -> crate::Result<()> {
+        Err(FsError::ReadOnlyFileSystem)
+    }
+
+    fn rename<'a>(&'a self, _from: &'a Path, _to: &'a Path) -> BoxFuture<'a, crate::Result<()>> {
+        Box::pin(async { Err(FsError::ReadOnlyFileSystem) })
+    }
+
+    fn metadata(&self, path: &Path) -> crate::Result<Metadata> {
+        self.inner.metadata(path)
+    }
+
+    fn symlink_metadata(&self, path: &Path) -> crate::Result<Metadata> {
+        self.inner.symlink_metadata(path)
+    }
+
+    fn remove_file(&self, _path: &Path) -> crate::Result<()> {
+        Err(FsError::ReadOnlyFileSystem)
+    }
+
+    fn new_open_options(&self) -> OpenOptions {
+        OpenOptions::new(self)
+    }
+}
+
+impl<F> FileOpener for ReadOnlyFileSystem<F>
+where
+    F: FileSystem,
+{
+    fn open(
+        &self,
+        path: &Path,
+        conf: &OpenOptionsConfig,
+    ) -> crate::Result<Box<dyn VirtualFile + Send + Sync + 'static>> {
+        if conf.would_mutate() {
+            return Err(FsError::ReadOnlyFileSystem);
+        }
+
+        let file = self
+            .inner
+            .new_open_options()
+            .options(conf.clone())
+            .open(path)?;
+        Ok(Box::new(ReadOnlyFile { inner: file }))
+    }
+}
+
+/// A file of a [`ReadOnlyFileSystem`], it can only be read from
+#[derive(Debug)]
+struct ReadOnlyFile {
+    inner: Box<dyn VirtualFile + Send + Sync + 'static>,
+}
+
+/// The error writes to a [`ReadOnlyFile`] fail with, it carries the
+/// [`FsError`] so that it can be recovered from the [`io::Error`]
+fn read_only_error() -> io::Error {
+    io::Error::new(io::ErrorKind::PermissionDenied, FsError::ReadOnlyFileSystem)
+}
+
+impl VirtualFile for ReadOnlyFile {
+    fn last_accessed(&self) -> u64 {
+        self.inner.last_accessed()
+    }
+
+    fn last_modified(&self) -> u64 {
+        self.inner.last_modified()
+    }
+
+    fn created_time(&self) -> u64 {
+        self.inner.created_time()
+    }
+
+    fn set_times(&mut self, _atime: Option<u64>, _mtime: Option<u64>) -> crate::Result<()> {
+        Err(FsError::ReadOnlyFileSystem)
+    }
+
+    fn size(&self) -> u64 {
+        self.inner.size()
+    }
+
+    fn set_len(&mut self, _new_size: u64) -> crate::Result<()> {
+        Err(FsError::ReadOnlyFileSystem)
+    }
+
+    fn unlink(&mut self) -> crate::Result<()> {
+        Err(FsError::ReadOnlyFileSystem)
+    }
+
+    fn is_open(&self) -> bool {
+        self.inner.is_open()
+    }
+
+    fn write_from_mmap(&mut self, _offset: u64, _len: u64) -> io::Result<()> {
+        Err(read_only_error())
+    }
+
+    fn poll_read_ready(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<usize>> {
+        Pin::new(&mut *self.inner).poll_read_ready(cx)
+    }
+
+    fn poll_write_ready(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<usize>> {
+        Poll::Ready(Err(read_only_error()))
+    }
+}
+
+impl AsyncRead for ReadOnlyFile {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        Pin::new(&mut *self.inner).poll_read(cx, buf)
+    }
+}
+
+impl AsyncWrite for ReadOnlyFile {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        _buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Poll::Ready(Err(read_only_error()))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl AsyncSeek for ReadOnlyFile {
+    fn start_seek(mut self: Pin<&mut Self>, position: io::SeekFrom) -> io::Result<()> {
+        Pin::new(&mut *self.inner).start_seek(position)
+    }
+
+    fn poll_complete(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<u64>> {
+        Pin::new(&mut *self.inner).poll_complete(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    use super::*;
+    use crate::mem_fs;
+
+    async fn read_only_mem_fs() -> ReadOnlyFileSystem<mem_fs::FileSystem> {
+        let fs = mem_fs::FileSystem::default();
+        fs.create_dir(Path::new("/dir")).unwrap();
+        let mut f = fs
+            .new_open_options()
+            .write(true)
+            .create(true)
+            .open("/dir/file.txt")
+            .unwrap();
+        f.write_all(b"Hello").await.unwrap();
+
+        ReadOnlyFileSystem::new(fs)
+    }
+
+    #[tokio::test]
+    async fn files_can_be_read() {
+        let fs = read_only_mem_fs().await;
+
+        let mut f = fs
+            .new_open_options()
+            .read(true)
+            .open("/dir/file.txt")
+            .unwrap();
+        let mut contents = String::new();
+        f.read_to_string(&mut contents).await.unwrap();
+
+        assert_eq!(contents, "Hello");
+        assert_eq!(fs.read_dir(Path::new("/dir")).unwrap().count(), 1);
+    }
+
+    #[tokio::test]
+    async fn the_file_system_can_not_be_modified() {
+        let fs = read_only_mem_fs().await;
+
+        assert_eq!(
+            fs.create_dir(Path::new("/other")).unwrap_err(),
+            FsError::ReadOnlyFileSystem
+        );
+        assert_eq!(
+            fs.remove_dir(Path::new("/dir")).unwrap_err(),
+            FsError::ReadOnlyFileSystem
+        );
+        assert_eq!(
+            fs.remove_file(Path::new("/dir/file.txt")).unwrap_err(),
+            FsError::ReadOnlyFileSystem
+        );
+        assert_eq!(
+            fs.rename(Path::new("/dir/file.txt"), Path::new("/dir/moved.txt"))
+                .await
+                .unwrap_err(),
+            FsError::ReadOnlyFileSystem
+        );
+        assert_eq!(
+            fs.new_open_options()
+                .write(true)
+                .open("/dir/file.txt")
+                .unwrap_err(),
+            FsError::ReadOnlyFileSystem
+        );
+        assert_eq!(
+            fs.new_open_options()
+                .read(true)
+                .create(true)
+                .open("/dir/new.txt")
+                .unwrap_err(),
+            FsError::ReadOnlyFileSystem
+        );
+
+        assert!(fs.inner().metadata(Path::new("/dir/file.txt")).is_ok());
+        assert!(fs.inner().metadata(Path::new("/other")).is_err());
+    }
+
+    #[tokio::test]
+    async fn files_opened_for_reading_can_not_be_modified() {
+        let fs = read_only_mem_fs().await;
+
+        let mut f = fs
+            .new_open_options()
+            .read(true)
+            .open("/dir/file.txt")
+            .unwrap();
+
+        let err = f.write_all(b"World").await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::PermissionDenied);
+        assert_eq!(f.set_len(0).unwrap_err(), FsError::ReadOnlyFileSystem);
+        assert_eq!(
+            f.set_times(Some(0), Some(0)).unwrap_err(),
+            FsError::ReadOnlyFileSystem
+        );
+        assert_eq!(f.unlink().unwrap_err(), FsError::ReadOnlyFileSystem);
+        assert_eq!(f.size(), 5);
+    }
+}
@@ -54,6 +54,11 @@ impl TmpFileSystem {
         self.fs.mount(src_path, other, dst_path)
     }
 
+    /// See [`mem_fs::FileSystem::is_same_device`].
+    pub fn is_same_device(&self, a: &Path, b: &Path) -> Result<bool> {
+        self.fs.is_same_device(a, b)
+    }
+
     /// Canonicalize a path without validating that it actually exists.
     pub fn canonicalize_unchecked(&self, path: &Path) -> Result<PathBuf> {
         self.fs.canonicalize_unchecked(path)
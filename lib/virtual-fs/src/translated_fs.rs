@@ -0,0 +1,259 @@
+use std::{
+    fmt,
+    path::{Component, Path, PathBuf},
+    sync::Arc,
+};
+
+use futures::future::BoxFuture;
+
+use crate::{
+    DirEntry, FileOpener, FileSystem, FsError, Metadata, OpenOptions, OpenOptionsConfig, ReadDir,
+    VirtualFile,
+};
+
+/// Function that translates a path of the guest into a path on the host
+pub type TranslatePathFn = Arc<dyn Fn(&str) -> PathBuf + Send + Sync + 'static>;
+
+/// A [`FileSystem`] implementation that translates every path through a
+/// function before it is looked up on the host.
+///
+/// The translated paths are confined to the `root` directory on the host,
+/// relative paths are resolved against it and any path that ends up outside
+/// of it (including through symbolic links) is rejected with
+/// [`FsError::PermissionDenied`].
+#[derive(Clone)]
+pub struct TranslatedFileSystem {
+    root: PathBuf,
+    translate: TranslatePathFn,
+    inner: crate::host_fs::FileSystem,
+}
+
+impl fmt::Debug for TranslatedFileSystem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TranslatedFileSystem")
+            .field("root", &self.root)
+            .field("inner", &self.inner)
+            .finish()
+    }
+}
+
+impl TranslatedFileSystem {
+    pub fn new(
+        root: impl Into<PathBuf>,
+        translate: TranslatePathFn,
+        inner: crate::host_fs::FileSystem,
+    ) -> Self {
+        TranslatedFileSystem {
+            root: normalize_path(&root.into()),
+            translate,
+            inner,
+        }
+    }
+
+    /// Create a new [`TranslatedFileSystem`] using the current
+    /// [`tokio::runtime::Handle`].
+    ///
+    /// # Panics
+    ///
+    /// This will panic if called outside of a `tokio` context.
+    pub fn new_with_default_runtime(root: impl Into<PathBuf>, translate: TranslatePathFn) -> Self {
+        let handle = tokio::runtime::Handle::current();
+        let fs = crate::host_fs::FileSystem::new(handle);
+        TranslatedFileSystem::new(root, translate, fs)
+    }
+
+    fn prepare_path(&self, path: &Path) -> Result<PathBuf, FsError> {
+        let path = Path::new("/").join(normalize_path(path));
+        let path = path.to_str().ok_or(FsError::InvalidInput)?;
+
+        let translated = (self.translate)(path);
+        if translated
+            .components()
+            .any(|c| matches!(c, Component::ParentDir))
+        {
+            return Err(FsError::PermissionDenied);
+        }
+        let translated = self.root.join(translated);
+        if !translated.starts_with(&self.root) {
+            return Err(FsError::PermissionDenied);
+        }
+
+        // Symbolic links on the host must not lead out of the root either
+        let root = self.root.canonicalize().map_err(FsError::from)?;
+        let existing = translated
+            .ancestors()
+            .find_map(|ancestor| ancestor.canonicalize().ok());
+        match existing {
+            Some(existing) if existing.starts_with(root) => Ok(translated),
+            _ => Err(FsError::PermissionDenied),
+        }
+    }
+}
+
+impl FileSystem for TranslatedFileSystem {
+    fn readlink(&self, path: &Path) -> crate::Result<PathBuf> {
+        let path = self.prepare_path(path)?;
+        self.inner.readlink(&path)
+    }
+
+    fn read_dir(&self, path: &Path) -> Result<ReadDir, FsError> {
+        let guest_path = Path::new("/").join(normalize_path(path));
+        let path = self.prepare_path(path)?;
+
+        let mut entries = Vec::new();
+
+        for entry in self.inner.read_dir(&path)? {
+            let entry = entry?;
+            let name = entry.path.file_name().ok_or(FsError::InvalidData)?;
+            entries.push(DirEntry {
+                path: guest_path.join(name),
+                ..entry
+            });
+        }
+
+        Ok(ReadDir::new(entries))
+    }
+
+    fn create_dir(&self, path: &Path) -> Result<(), FsError> {
+        let path = self.prepare_path(path)?;
+        self.inner.create_dir(&path)
+    }
+
+    fn remove_dir(&self, path: &Path) -> Result<(), FsError> {
+        let path = self.prepare_path(path)?;
+        self.inner.remove_dir(&path)
+    }
+
+    fn rename<'a>(&'a self, from: &'a Path, to: &'a Path) -> BoxFuture<'a, Result<(), FsError>> {
+        Box::pin(async move {
+            let from = self.prepare_path(from)?;
+            let to = self.prepare_path(to)?;
+            self.inner.rename(&from, &to).await
+        })
+    }
+
+    fn metadata(&self, path: &Path) -> Result<Metadata, FsError> {
+        let path = self.prepare_path(path)?;
+        self.inner.metadata(&path)
+    }
+
+    fn symlink_metadata(&self, path: &Path) -> crate::Result<Metadata> {
+        let path = self.prepare_path(path)?;
+        self.inner.symlink_metadata(&path)
+    }
+
+    fn remove_file(&self, path: &Path) -> Result<(), FsError> {
+        let path = self.prepare_path(path)?;
+        self.inner.remove_file(&path)
+    }
+
+    fn new_open_options(&self) -> OpenOptions {
+        OpenOptions::new(self)
+    }
+}
+
+impl FileOpener for TranslatedFileSystem {
+    fn open(
+        &self,
+        path: &Path,
+        conf: &OpenOptionsConfig,
+    ) -> Result<Box<dyn VirtualFile + Send + Sync + 'static>, FsError> {
+        let path = self.prepare_path(path)?;
+        self.inner
+            .new_open_options()
+            .options(conf.clone())
+            .open(&path)
+    }
+}
+
+/// Resolves `.` and `..` without touching the file system, `..` never goes
+/// above the root
+fn normalize_path(path: &Path) -> PathBuf {
+    let mut ret = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::Prefix(..) | Component::CurDir => {}
+            Component::RootDir => ret.push("/"),
+            Component::ParentDir => {
+                ret.pop();
+            }
+            Component::Normal(c) => ret.push(c),
+        }
+    }
+    ret
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::TempDir;
+    use tokio::io::AsyncReadExt;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn paths_are_translated() {
+        let temp = TempDir::new().unwrap();
+        std::fs::create_dir(temp.path().join("tenant-42")).unwrap();
+        std::fs::write(temp.path().join("tenant-42").join("file.txt"), "Hello").unwrap();
+        let fs = TranslatedFileSystem::new_with_default_runtime(
+            temp.path(),
+            Arc::new(|path: &str| {
+                PathBuf::from(format!("tenant-{}", path.trim_start_matches('/')))
+            }),
+        );
+
+        let mut f = fs
+            .new_open_options()
+            .read(true)
+            .open("/42/file.txt")
+            .unwrap();
+        let mut contents = String::new();
+        f.read_to_string(&mut contents).await.unwrap();
+
+        assert_eq!(contents, "Hello");
+    }
+
+    #[tokio::test]
+    async fn translations_cant_escape_the_root() {
+        let parent = TempDir::new().unwrap();
+        let root = parent.path().join("root");
+        std::fs::create_dir(&root).unwrap();
+        std::fs::write(parent.path().join("secret.txt"), "").unwrap();
+        let outside = parent.path().to_path_buf();
+        let fs = TranslatedFileSystem::new_with_default_runtime(
+            &root,
+            Arc::new(move |path: &str| match path {
+                "/dotdot" => PathBuf::from("../secret.txt"),
+                _ => outside.join("secret.txt"),
+            }),
+        );
+
+        assert_eq!(
+            fs.metadata(Path::new("/dotdot")).unwrap_err(),
+            FsError::PermissionDenied
+        );
+        assert_eq!(
+            fs.metadata(Path::new("/absolute")).unwrap_err(),
+            FsError::PermissionDenied
+        );
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn symlinks_cant_escape_the_root() {
+        let parent = TempDir::new().unwrap();
+        let root = parent.path().join("root");
+        std::fs::create_dir(&root).unwrap();
+        std::fs::write(parent.path().join("secret.txt"), "").unwrap();
+        std::os::unix::fs::symlink(parent.path(), root.join("link")).unwrap();
+        let fs = TranslatedFileSystem::new_with_default_runtime(
+            &root,
+            Arc::new(|path: &str| PathBuf::from(path.trim_start_matches('/'))),
+        );
+
+        assert_eq!(
+            fs.metadata(Path::new("/link/secret.txt")).unwrap_err(),
+            FsError::PermissionDenied
+        );
+    }
+}
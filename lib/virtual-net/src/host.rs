@@ -492,10 +492,21 @@ impl VirtualConnectedSocket for LocalTcpStream {
 
 impl VirtualSocket for LocalTcpStream {
     fn set_ttl(&mut self, ttl: u32) -> Result<()> {
+        // IPv6 sockets have a hop limit rather than a time-to-live
+        if self.addr.is_ipv6() {
+            return socket2::SockRef::from(&self.stream)
+                .set_unicast_hops_v6(ttl)
+                .map_err(io_err_into_net_error);
+        }
         self.stream.set_ttl(ttl).map_err(io_err_into_net_error)
     }
 
     fn ttl(&self) -> Result<u32> {
+        if self.addr.is_ipv6() {
+            return socket2::SockRef::from(&self.stream)
+                .unicast_hops_v6()
+                .map_err(io_err_into_net_error);
+        }
         self.stream.ttl().map_err(io_err_into_net_error)
     }
 
@@ -647,7 +658,6 @@ fn libc_poll(fd: RawFd, events: libc::c_short) -> Option<libc::c_short> {
 #[derive(Debug)]
 pub struct LocalUdpSocket {
     socket: mio::net::UdpSocket,
-    #[allow(dead_code)]
     addr: SocketAddr,
     selector: Arc<Selector>,
     handler_guard: HandlerGuardState,
@@ -758,10 +768,21 @@ impl VirtualConnectionlessSocket for LocalUdpSocket {
 
 impl VirtualSocket for LocalUdpSocket {
     fn set_ttl(&mut self, ttl: u32) -> Result<()> {
+        // IPv6 sockets have a hop limit rather than a time-to-live
+        if self.addr.is_ipv6() {
+            return socket2::SockRef::from(&self.socket)
+                .set_unicast_hops_v6(ttl)
+                .map_err(io_err_into_net_error);
+        }
         self.socket.set_ttl(ttl).map_err(io_err_into_net_error)
     }
 
     fn ttl(&self) -> Result<u32> {
+        if self.addr.is_ipv6() {
+            return socket2::SockRef::from(&self.socket)
+                .unicast_hops_v6()
+                .map_err(io_err_into_net_error);
+        }
         self.socket.ttl().map_err(io_err_into_net_error)
     }
 
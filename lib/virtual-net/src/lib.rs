@@ -88,6 +88,12 @@ pub trait VirtualIoSource: fmt::Debug + Send + Sync + 'static {
 #[async_trait::async_trait]
 #[allow(unused_variables)]
 pub trait VirtualNetworking: fmt::Debug + Send + Sync + 'static {
+    /// Indicates if this implementation is capable of providing any
+    /// networking at all. Defaults to true
+    fn is_supported(&self) -> bool {
+        true
+    }
+
     /// Bridges this local network with a remote network, which is required in
     /// order to make lower level networking calls (such as UDP/TCP)
     async fn bridge(
@@ -750,7 +756,11 @@ pub trait VirtualUdpSocket:
 pub struct UnsupportedVirtualNetworking {}
 
 #[async_trait::async_trait]
-impl VirtualNetworking for UnsupportedVirtualNetworking {}
+impl VirtualNetworking for UnsupportedVirtualNetworking {
+    fn is_supported(&self) -> bool {
+        false
+    }
+}
 
 #[derive(Error, Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum NetworkError {
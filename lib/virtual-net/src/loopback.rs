@@ -14,6 +14,9 @@ use virtual_mio::InterestType;
 
 const DEFAULT_MAX_BUFFER_SIZE: usize = 1_048_576;
 
+/// Ports handed out to the listeners that ask for port zero
+const EPHEMERAL_PORTS: std::ops::RangeInclusive<u16> = 49152..=65535;
+
 #[derive(Debug, Default)]
 struct LoopbackNetworkingState {
     tcp_listeners: HashMap<SocketAddr, LoopbackTcpListener>,
@@ -32,6 +35,25 @@ impl LoopbackNetworking {
         }
     }
 
+    /// Caps the number of pending connections that the listener on `addr`
+    /// queues up (unlimited when [`None`]), connection attempts beyond it
+    /// are refused
+    pub fn set_max_backlog(
+        &self,
+        addr: SocketAddr,
+        max_backlog: Option<usize>,
+    ) -> crate::Result<()> {
+        let state = self.state.lock().unwrap();
+        let listener = state
+            .tcp_listeners
+            .get(&addr)
+            .ok_or(NetworkError::AddressNotAvailable)?;
+        listener.set_max_backlog(max_backlog);
+        Ok(())
+    }
+
+    /// Connects to a listener, returns [`None`] if there is no listener or
+    /// if its backlog is full
     pub fn loopback_connect_to(
         &self,
         mut local_addr: SocketAddr,
@@ -53,15 +75,11 @@ impl LoopbackNetworking {
         };
 
         let state = self.state.lock().unwrap();
-        if let Some(listener) = state.tcp_listeners.get(&peer_addr) {
-            Some(listener.connect_to(local_addr))
-        } else {
-            state
-                .tcp_listeners
-                .iter()
-                .next()
-                .map(|listener| listener.1.connect_to(local_addr))
-        }
+        let listener = state
+            .tcp_listeners
+            .get(&peer_addr)
+            .or_else(|| state.tcp_listeners.values().next())?;
+        listener.connect_to(local_addr).ok()
     }
 }
 
@@ -121,16 +139,31 @@ impl VirtualNetworking for LoopbackNetworking {
         _reuse_port: bool,
         _reuse_addr: bool,
     ) -> crate::Result<Box<dyn VirtualTcpListener + Sync>> {
-        let listener = LoopbackTcpListener::new(addr);
+        let mut state = self.state.lock().unwrap();
+        let ip = match addr.ip() {
+            IpAddr::V4(Ipv4Addr::UNSPECIFIED) => Ipv4Addr::LOCALHOST.into(),
+            IpAddr::V6(Ipv6Addr::UNSPECIFIED) => Ipv6Addr::LOCALHOST.into(),
+            ip => ip,
+        };
 
-        if addr.ip() == IpAddr::V4(Ipv4Addr::UNSPECIFIED) {
-            addr = SocketAddr::new(Ipv4Addr::LOCALHOST.into(), addr.port());
-        } else if addr.ip() == IpAddr::V6(Ipv6Addr::UNSPECIFIED) {
-            addr = SocketAddr::new(Ipv6Addr::LOCALHOST.into(), addr.port());
+        // Port zero is replaced by an ephemeral port that is not in use
+        if addr.port() == 0 {
+            let port = EPHEMERAL_PORTS
+                .clone()
+                .find(|port| {
+                    !state
+                        .tcp_listeners
+                        .contains_key(&SocketAddr::new(ip, *port))
+                })
+                .ok_or(NetworkError::AddressInUse)?;
+            addr.set_port(port);
         }
 
-        let mut state = self.state.lock().unwrap();
-        state.tcp_listeners.insert(addr, listener.clone());
+        let listener = LoopbackTcpListener::new(addr);
+
+        state
+            .tcp_listeners
+            .insert(SocketAddr::new(ip, addr.port()), listener.clone());
 
         Ok(Box::new(listener))
     }
@@ -143,6 +176,8 @@ struct LoopbackTcpListenerState {
     handler: Option<Box<dyn InterestHandler + Send + Sync>>,
     addr_local: SocketAddr,
     backlog: VecDeque<TcpSocketHalf>,
+    /// Maximum number of connections in the backlog
+    max_backlog: Option<usize>,
     wakers: Vec<Waker>,
 }
 
@@ -158,13 +193,28 @@ impl LoopbackTcpListener {
                 handler: None,
                 addr_local,
                 backlog: Default::default(),
+                max_backlog: None,
                 wakers: Default::default(),
             })),
         }
     }
 
-    pub fn connect_to(&self, addr_local: SocketAddr) -> TcpSocketHalf {
+    /// Caps the number of pending connections that are queued up until
+    /// they are accepted (unlimited when [`None`])
+    pub fn set_max_backlog(&self, max_backlog: Option<usize>) {
+        let mut state = self.state.lock().unwrap();
+        state.max_backlog = max_backlog;
+    }
+
+    /// Queues up a connection to this listener, the connection is refused
+    /// when the backlog is full
+    pub fn connect_to(&self, addr_local: SocketAddr) -> crate::Result<TcpSocketHalf> {
         let mut state = self.state.lock().unwrap();
+        if let Some(max_backlog) = state.max_backlog {
+            if state.backlog.len() >= max_backlog {
+                return Err(NetworkError::ConnectionRefused);
+            }
+        }
         let (half1, half2) =
             TcpSocketHalf::channel(DEFAULT_MAX_BUFFER_SIZE, state.addr_local, addr_local);
 
@@ -174,7 +224,7 @@ impl LoopbackTcpListener {
         }
         state.wakers.drain(..).for_each(|w| w.wake());
 
-        half2
+        Ok(half2)
     }
 }
 
@@ -238,3 +288,74 @@ impl VirtualTcpListener for LoopbackTcpListener {
         Ok(64)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn backlog_refuses_excess_connections() {
+        let networking = LoopbackNetworking::new();
+        let addr = SocketAddr::new(Ipv4Addr::LOCALHOST.into(), 8080);
+        let other_addr = SocketAddr::new(Ipv4Addr::LOCALHOST.into(), 8081);
+        let local = SocketAddr::new(Ipv4Addr::UNSPECIFIED.into(), 0);
+        let mut listener = networking
+            .listen_tcp(addr, false, false, false)
+            .await
+            .unwrap();
+        let _other_listener = networking
+            .listen_tcp(other_addr, false, false, false)
+            .await
+            .unwrap();
+        networking.set_max_backlog(addr, Some(2)).unwrap();
+
+        // Only the first connections fit into the backlog
+        let accepted: Vec<_> = (0..5)
+            .map(|_| networking.loopback_connect_to(local, addr))
+            .collect();
+        assert_eq!(accepted.iter().filter(|c| c.is_some()).count(), 2);
+        assert!(accepted[0].is_some());
+        assert!(accepted[1].is_some());
+
+        // Accepting a connection makes room for another one
+        listener.try_accept().unwrap();
+        assert!(networking.loopback_connect_to(local, addr).is_some());
+        assert!(networking.loopback_connect_to(local, addr).is_none());
+
+        // The cap only applies to the listener it was set on
+        let accepted = (0..5)
+            .filter(|_| networking.loopback_connect_to(local, other_addr).is_some())
+            .count();
+        assert_eq!(accepted, 5);
+
+        // Listeners have to exist for a cap to be set on them
+        assert_eq!(
+            networking.set_max_backlog(SocketAddr::new(Ipv4Addr::LOCALHOST.into(), 8082), Some(1)),
+            Err(NetworkError::AddressNotAvailable)
+        );
+    }
+
+    #[tokio::test]
+    async fn listen_on_ephemeral_port() {
+        let networking = LoopbackNetworking::new();
+        let any = SocketAddr::new(Ipv4Addr::UNSPECIFIED.into(), 0);
+        let first = networking
+            .listen_tcp(any, false, false, false)
+            .await
+            .unwrap();
+        let second = networking
+            .listen_tcp(any, false, false, false)
+            .await
+            .unwrap();
+
+        // Each listener gets its own port that can be connected to
+        let first_addr = first.addr_local().unwrap();
+        let second_addr = second.addr_local().unwrap();
+        assert_ne!(first_addr.port(), 0);
+        assert_ne!(second_addr.port(), 0);
+        assert_ne!(first_addr.port(), second_addr.port());
+
+        let peer = SocketAddr::new(Ipv4Addr::LOCALHOST.into(), first_addr.port());
+        assert!(networking.loopback_connect_to(any, peer).is_some());
+    }
+}
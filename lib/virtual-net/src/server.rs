@@ -189,6 +189,10 @@ impl RemoteNetworkingServer {
 
 #[async_trait::async_trait]
 impl VirtualNetworking for RemoteNetworkingServer {
+    fn is_supported(&self) -> bool {
+        self.inner.is_supported()
+    }
+
     async fn bridge(
         &self,
         network: &str,
@@ -331,9 +331,13 @@ impl From<Errno> for std::io::Error {
 impl From<std::io::Error> for Errno {
     fn from(err: std::io::Error) -> Self {
         use std::io::ErrorKind;
+        // Errors that carry an errno are mapped back to it unchanged
+        if let Some(errno) = err.get_ref().and_then(|err| err.downcast_ref::<Errno>()) {
+            return *errno;
+        }
         match err.kind() {
             ErrorKind::NotFound => Errno::Noent,
-            ErrorKind::PermissionDenied => Errno::Perm,
+            ErrorKind::PermissionDenied => Errno::Access,
             ErrorKind::ConnectionRefused => Errno::Connrefused,
             ErrorKind::ConnectionReset => Errno::Connreset,
             ErrorKind::ConnectionAborted => Errno::Connaborted,
@@ -372,3 +376,17 @@ unsafe impl wasmer::FromToNativeWasmType for JoinFlags {
         false
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn permission_denied_maps_to_access() {
+        let err = std::io::Error::from(std::io::ErrorKind::PermissionDenied);
+        assert_eq!(Errno::from(err), Errno::Access);
+        // Errors that carry an errno keep it
+        let err = std::io::Error::new(std::io::ErrorKind::PermissionDenied, Errno::Perm);
+        assert_eq!(Errno::from(err), Errno::Perm);
+    }
+}
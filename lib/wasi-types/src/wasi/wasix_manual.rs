@@ -5,7 +5,7 @@ use std::mem::MaybeUninit;
 use wasmer::{FromToNativeWasmType, MemorySize, ValueType};
 
 use super::{
-    Errno, ErrnoSignal, EventFdReadwrite, Eventtype, Fd, JoinStatusType, Signal,
+    Errno, ErrnoSignal, EventFdReadwrite, Eventtype, Fd, Filesize, JoinStatusType, Signal,
     Snapshot0SubscriptionClock, SubscriptionClock, SubscriptionFsReadwrite, Userdata,
 };
 
@@ -493,3 +493,205 @@ where
     #[inline]
     fn zero_padding_bytes(&self, _bytes: &mut [MaybeUninit<u8>]) {}
 }
+
+#[doc = " Operation executed by `syscall_batch`."]
+#[repr(u8)]
+#[derive(Clone, Copy, PartialEq, Eq, num_enum :: TryFromPrimitive, Hash)]
+#[cfg_attr(feature = "enable-serde", derive(Serialize, Deserialize))]
+pub enum BatchOpcode {
+    #[doc = " Reads into the vectors at the cursor of the file descriptor, like `fd_read`."]
+    Read,
+    #[doc = " Writes the vectors at the cursor of the file descriptor, like `fd_write`."]
+    Write,
+    #[doc = " Reads into the vectors at the offset, like `fd_pread`."]
+    Pread,
+    #[doc = " Writes the vectors at the offset, like `fd_pwrite`."]
+    Pwrite,
+    #[doc = " Returns the number of bytes that can be read without blocking, like `fd_bytes_available`."]
+    Poll,
+}
+impl core::fmt::Debug for BatchOpcode {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            BatchOpcode::Read => f.debug_tuple("BatchOpcode::Read").finish(),
+            BatchOpcode::Write => f.debug_tuple("BatchOpcode::Write").finish(),
+            BatchOpcode::Pread => f.debug_tuple("BatchOpcode::Pread").finish(),
+            BatchOpcode::Pwrite => f.debug_tuple("BatchOpcode::Pwrite").finish(),
+            BatchOpcode::Poll => f.debug_tuple("BatchOpcode::Poll").finish(),
+        }
+    }
+}
+
+wai_bindgen_rust::bitflags::bitflags! {
+    #[doc = " Flags of an operation executed by `syscall_batch`."]
+    #[cfg_attr(feature = "enable-serde", derive(Serialize, Deserialize))]
+    pub struct BatchFlags : u8 {
+        #[doc = " The next operation is only executed if this one succeeds, otherwise"]
+        #[doc = " it fails with `canceled` (as do the operations linked to it)."]
+        const LINK = 1 << 0;
+    }
+}
+
+/// An operation submitted to `syscall_batch`
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub struct BatchOp<M: MemorySize> {
+    /// The operation to execute (see [`BatchOpcode`])
+    pub opcode: u8,
+    /// Flags of the operation
+    pub flags: BatchFlags,
+    /// File descriptor the operation is executed against
+    pub fd: Fd,
+    /// Vectors that are read into or written from
+    pub iovs: M::Offset,
+    /// Number of vectors
+    pub iovs_len: M::Offset,
+    /// Offset used by the positional reads and writes
+    pub offset: Filesize,
+}
+impl<M> core::fmt::Debug for BatchOp<M>
+where
+    M: MemorySize,
+{
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("BatchOp")
+            .field("opcode", &self.opcode)
+            .field("flags", &self.flags)
+            .field("fd", &self.fd)
+            .field("iovs", &self.iovs)
+            .field("iovs_len", &self.iovs_len)
+            .field("offset", &self.offset)
+            .finish()
+    }
+}
+unsafe impl<M> ValueType for BatchOp<M>
+where
+    M: MemorySize,
+{
+    fn zero_padding_bytes(&self, bytes: &mut [MaybeUninit<u8>]) {
+        macro_rules! field {
+            ($($f:tt)*) => {
+                &self.$($f)* as *const _ as usize - self as *const _ as usize
+            };
+        }
+        macro_rules! field_end {
+            ($($f:tt)*) => {
+                field!($($f)*) + core::mem::size_of_val(&self.$($f)*)
+            };
+        }
+        macro_rules! zero {
+            ($start:expr, $end:expr) => {
+                for i in $start..$end {
+                    bytes[i] = core::mem::MaybeUninit::new(0);
+                }
+            };
+        }
+        zero!(field_end!(opcode), field!(flags));
+        zero!(field_end!(flags), field!(fd));
+        zero!(field_end!(fd), field!(iovs));
+        zero!(field_end!(iovs), field!(iovs_len));
+        zero!(field_end!(iovs_len), field!(offset));
+        zero!(field_end!(offset), core::mem::size_of_val(self));
+    }
+}
+
+/// The result of an operation executed by `syscall_batch`
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub struct BatchResult<M: MemorySize> {
+    /// Error of the operation
+    pub errno: Errno,
+    /// Number of bytes read, written or readable
+    pub nbytes: M::Offset,
+}
+impl<M> core::fmt::Debug for BatchResult<M>
+where
+    M: MemorySize,
+{
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("BatchResult")
+            .field("errno", &self.errno)
+            .field("nbytes", &self.nbytes)
+            .finish()
+    }
+}
+unsafe impl<M> ValueType for BatchResult<M>
+where
+    M: MemorySize,
+{
+    fn zero_padding_bytes(&self, bytes: &mut [MaybeUninit<u8>]) {
+        macro_rules! field {
+            ($($f:tt)*) => {
+                &self.$($f)* as *const _ as usize - self as *const _ as usize
+            };
+        }
+        macro_rules! field_end {
+            ($($f:tt)*) => {
+                field!($($f)*) + core::mem::size_of_val(&self.$($f)*)
+            };
+        }
+        macro_rules! zero {
+            ($start:expr, $end:expr) => {
+                for i in $start..$end {
+                    bytes[i] = core::mem::MaybeUninit::new(0);
+                }
+            };
+        }
+        zero!(field_end!(errno), field!(nbytes));
+        zero!(field_end!(nbytes), core::mem::size_of_val(self));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wasmer::{Memory32, Memory64};
+
+    fn padded_bytes<T: ValueType>(value: &T) -> Vec<u8> {
+        let mut bytes = vec![MaybeUninit::new(0xaa); std::mem::size_of::<T>()];
+        value.zero_padding_bytes(&mut bytes);
+        bytes
+            .into_iter()
+            .map(|b| unsafe { b.assume_init() })
+            .collect()
+    }
+
+    fn batch_op<M: MemorySize>() -> BatchOp<M> {
+        BatchOp {
+            opcode: 0,
+            flags: BatchFlags::empty(),
+            fd: 0,
+            iovs: 0u8.into(),
+            iovs_len: 0u8.into(),
+            offset: 0,
+        }
+    }
+
+    #[test]
+    fn batch_padding_is_zeroed() {
+        // Only the padding is zeroed, the fields are left untouched
+        let op = padded_bytes(&batch_op::<Memory32>());
+        assert_eq!(&op[2..4], &[0, 0]);
+        assert!(op[..2].iter().chain(&op[4..]).all(|b| *b == 0xaa));
+        let op = padded_bytes(&batch_op::<Memory64>());
+        assert_eq!(&op[2..4], &[0, 0]);
+        assert!(op[..2].iter().chain(&op[4..]).all(|b| *b == 0xaa));
+
+        let result = BatchResult::<Memory32> {
+            errno: Errno::Success,
+            nbytes: 0,
+        };
+        assert_eq!(
+            padded_bytes(&result),
+            [0xaa, 0xaa, 0, 0, 0xaa, 0xaa, 0xaa, 0xaa]
+        );
+        let result = BatchResult::<Memory64> {
+            errno: Errno::Success,
+            nbytes: 0,
+        };
+        assert_eq!(
+            padded_bytes(&result),
+            [0xaa, 0xaa, 0, 0, 0, 0, 0, 0, 0xaa, 0xaa, 0xaa, 0xaa, 0xaa, 0xaa, 0xaa, 0xaa]
+        );
+    }
+}
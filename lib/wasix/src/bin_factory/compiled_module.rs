@@ -0,0 +1,185 @@
+use std::sync::Arc;
+
+use wasmer::{AsStoreMut, Engine, Instance, Module, Store};
+use wasmer_types::ModuleHash;
+
+use crate::{
+    runtime::{
+        load_module,
+        module_cache::{self, ModuleCache},
+        task_manager::InlineWaker,
+    },
+    Runtime, SpawnError, WasiEnvBuilder, WasiFunctionEnv, WasiRuntimeError,
+};
+
+/// A module that has been compiled once and can be instantiated as often
+/// as needed, cloning it is cheap.
+#[derive(Debug, Clone)]
+pub struct CompiledModule {
+    module: Module,
+    hash: ModuleHash,
+}
+
+impl CompiledModule {
+    /// The compiled module
+    pub fn module(&self) -> &Module {
+        &self.module
+    }
+
+    /// Hash of the bytes the module was compiled from
+    pub fn hash(&self) -> ModuleHash {
+        self.hash
+    }
+
+    /// Instantiates the module with a fresh [`WasiEnv`](crate::WasiEnv) built
+    /// from the builder.
+    #[allow(clippy::result_large_err)]
+    pub fn instantiate(
+        &self,
+        builder: WasiEnvBuilder,
+        store: &mut impl AsStoreMut,
+    ) -> Result<(Instance, WasiFunctionEnv), WasiRuntimeError> {
+        builder.instantiate_ext(self.module.clone(), self.hash, store)
+    }
+
+    /// Runs the module to completion with a fresh [`WasiEnv`](crate::WasiEnv)
+    /// built from the builder.
+    #[allow(clippy::result_large_err)]
+    pub fn run(&self, builder: WasiEnvBuilder, store: &mut Store) -> Result<(), WasiRuntimeError> {
+        builder.run_with_store_ext(self.module.clone(), self.hash, store)
+    }
+}
+
+/// Compiles WebAssembly modules once and hands out the compiled module for
+/// every later request with the same bytes (keyed by their hash).
+///
+/// The compiled modules are kept in a [`ModuleCache`], so the cache of a
+/// [`Runtime`] can be reused (along with its fallbacks) through
+/// [`CompiledModuleCache::from_runtime`]. Clones of the cache share the
+/// compiled modules.
+#[derive(Debug, Clone)]
+pub struct CompiledModuleCache {
+    engine: Engine,
+    module_cache: Arc<dyn ModuleCache + Send + Sync>,
+}
+
+impl CompiledModuleCache {
+    /// Creates a cache that keeps the compiled modules in memory
+    pub fn new(engine: Engine) -> Self {
+        Self::with_module_cache(engine, Arc::new(module_cache::in_memory()))
+    }
+
+    /// Creates a cache that keeps the compiled modules in `module_cache`
+    pub fn with_module_cache(
+        engine: Engine,
+        module_cache: Arc<dyn ModuleCache + Send + Sync>,
+    ) -> Self {
+        Self {
+            engine,
+            module_cache,
+        }
+    }
+
+    /// Creates a cache that uses the engine and the module cache of the
+    /// runtime
+    pub fn from_runtime(runtime: &(dyn Runtime + Send + Sync)) -> Self {
+        Self::with_module_cache(runtime.engine(), runtime.module_cache())
+    }
+
+    /// The engine the modules are compiled with
+    pub fn engine(&self) -> &Engine {
+        &self.engine
+    }
+
+    /// The cache the compiled modules are kept in
+    pub fn module_cache(&self) -> &Arc<dyn ModuleCache + Send + Sync> {
+        &self.module_cache
+    }
+
+    /// Returns the compiled module for these bytes, compiling them first
+    /// if they are not in the cache yet.
+    pub fn get_or_compile(&self, wasm: &[u8]) -> Result<CompiledModule, SpawnError> {
+        let hash = ModuleHash::xxhash(wasm);
+        let module = InlineWaker::block_on(load_module(
+            &self.engine,
+            self.module_cache.as_ref(),
+            wasm,
+            hash,
+        ))?;
+        Ok(CompiledModule { module, hash })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+    use crate::runtime::module_cache::CacheError;
+
+    /// Counts the modules that are compiled, every compiled module is saved
+    /// to the cache exactly once
+    #[derive(Debug)]
+    struct CountingCache<I> {
+        inner: I,
+        compiled: AtomicUsize,
+    }
+
+    #[async_trait::async_trait]
+    impl<I: ModuleCache + Send + Sync> ModuleCache for CountingCache<I> {
+        async fn load(&self, key: ModuleHash, engine: &Engine) -> Result<Module, CacheError> {
+            self.inner.load(key, engine).await
+        }
+
+        async fn save(
+            &self,
+            key: ModuleHash,
+            engine: &Engine,
+            module: &Module,
+        ) -> Result<(), CacheError> {
+            self.compiled.fetch_add(1, Ordering::SeqCst);
+            self.inner.save(key, engine, module).await
+        }
+    }
+
+    #[cfg(feature = "sys-thread")]
+    #[test]
+    fn modules_are_kept_in_the_module_cache() {
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        let handle = runtime.handle().clone();
+        let _guard = handle.enter();
+
+        let wasm = br#"
+            (module
+                (memory 1)
+                (export "memory" (memory 0))
+                (func (export "_start"))
+            )
+        "#;
+
+        let counter = Arc::new(CountingCache {
+            inner: module_cache::in_memory(),
+            compiled: AtomicUsize::new(0),
+        });
+        let cache = CompiledModuleCache::with_module_cache(Engine::default(), counter.clone());
+        let mut store = Store::new(cache.engine().clone());
+
+        let first = cache.get_or_compile(wasm).unwrap();
+        first
+            .instantiate(WasiEnvBuilder::new("first"), &mut store)
+            .unwrap();
+
+        let second = cache.get_or_compile(wasm).unwrap();
+        second
+            .instantiate(WasiEnvBuilder::new("second"), &mut store)
+            .unwrap();
+
+        assert_eq!(first.hash(), second.hash());
+        assert_eq!(counter.compiled.load(Ordering::SeqCst), 1);
+        let cached = InlineWaker::block_on(cache.module_cache().load(first.hash(), cache.engine()));
+        assert!(cached.is_ok());
+    }
+}
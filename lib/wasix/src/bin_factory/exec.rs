@@ -306,6 +306,14 @@ fn call_module(
                     runtime.on_taint(TaintReason::UnknownWasiVersion);
                     Ok(Errno::Noexec)
                 }
+                Ok(WasiError::SyscallDenied(syscall)) => {
+                    debug!("failed as the syscall `{}` is not allowed", syscall);
+                    Err(WasiError::SyscallDenied(syscall).into())
+                }
+                Ok(WasiError::InstructionLimitExceeded) => {
+                    debug!("failed as the instruction limit was exceeded");
+                    Err(WasiError::InstructionLimitExceeded.into())
+                }
                 Err(err) => {
                     runtime.on_taint(TaintReason::RuntimeError(err.clone()));
                     Err(WasiRuntimeError::from(err))
@@ -324,6 +332,8 @@ fn call_module(
 
     // Cleanup the environment
     ctx.data(&store).blocking_on_exit(Some(code));
+    ctx.data(&store)
+        .report_process_exit(code, ret.as_ref().err());
     unsafe { run_recycle(recycle, ctx, store) };
 
     debug!("wasi[{pid}]::main() has exited with {code}");
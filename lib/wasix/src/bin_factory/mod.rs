@@ -14,10 +14,12 @@ use wasmer_wasix_types::wasi::Errno;
 use webc::Container;
 
 mod binary_package;
+mod compiled_module;
 mod exec;
 
 pub use self::{
     binary_package::*,
+    compiled_module::{CompiledModule, CompiledModuleCache},
     exec::{
         run_exec, spawn_exec, spawn_exec_module, spawn_load_module, spawn_load_wasm, spawn_union_fs,
     },
@@ -3,7 +3,10 @@ use std::{
     collections::{HashMap, HashSet},
     path::PathBuf,
     pin::Pin,
-    sync::{atomic::AtomicU64, Arc, RwLock, RwLockReadGuard, RwLockWriteGuard},
+    sync::{
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+        Arc, RwLock, RwLockReadGuard, RwLockWriteGuard,
+    },
     task::Context,
 };
 
@@ -11,8 +14,8 @@ use futures::Future;
 use serde_derive::{Deserialize, Serialize};
 use std::sync::Mutex as StdMutex;
 use tokio::sync::{watch, Mutex as AsyncMutex};
-use virtual_fs::{Pipe, VirtualFile};
-use wasmer_wasix_types::wasi::{EpollType, Fd as WasiFd, Fdflags, Filestat, Rights};
+use virtual_fs::{AsyncReadExt, AsyncSeekExt, Pipe, VirtualFile};
+use wasmer_wasix_types::wasi::{EpollType, Fd as WasiFd, Fdflags, Filestat, Filetype, Rights};
 
 use crate::{net::socket::InodeSocket, syscalls::EpollJoinWaker};
 
@@ -34,6 +37,15 @@ pub struct Fd {
     pub open_flags: u16,
     pub inode: InodeGuard,
     pub is_stdio: bool,
+    /// The rights of this stdio [`Fd`] were set explicitly, they are then
+    /// enforced like those of any other [`Fd`] (stdio is otherwise always
+    /// readable and writable)
+    #[cfg_attr(feature = "enable-serde", serde(default))]
+    pub stdio_rights_enforced: bool,
+    /// Entries of the directory as they were last served by `fd_readdir`,
+    /// reading on from a cookie then does not have to list it again
+    #[cfg_attr(feature = "enable-serde", serde(skip))]
+    pub readdir_snapshot: Arc<StdMutex<Option<Arc<DirSnapshot>>>>,
 }
 
 impl Fd {
@@ -56,6 +68,105 @@ impl Fd {
     pub const CREATE: u16 = 16;
 }
 
+/// Snapshot of the entries of a directory taken by `fd_readdir`
+#[derive(Debug)]
+pub struct DirSnapshot {
+    /// Directory generation of the file system when the snapshot was taken
+    pub generation: u64,
+    /// Name, type and inode of every entry in the order they are served
+    pub entries: Vec<(String, Filetype, u64)>,
+}
+
+/// Contents of a file that were read ahead of time after `fd_advise` was
+/// told they will be needed
+#[derive(Debug)]
+pub struct ReadAhead {
+    /// Offset in the file of the first byte of `data`
+    pub offset: u64,
+    pub data: Vec<u8>,
+    /// Size and modification time of the file when it was read, the data is
+    /// stale once either of them changed
+    size: u64,
+    modified: u64,
+    reservation: Option<ReadAheadReservation>,
+}
+
+impl ReadAhead {
+    /// Reads up to `size` bytes of `file` from `offset` on, less are read
+    /// when the file ends first
+    pub async fn fill(
+        file: &mut (dyn VirtualFile + Send + Sync + 'static),
+        offset: u64,
+        size: usize,
+    ) -> std::io::Result<Self> {
+        file.seek(std::io::SeekFrom::Start(offset)).await?;
+        let mut data = vec![0; size];
+        let mut filled = 0;
+        while filled < size {
+            match file.read(&mut data[filled..]).await? {
+                0 => break,
+                read => filled += read,
+            }
+        }
+        data.truncate(filled);
+        Ok(Self {
+            offset,
+            data,
+            size: file.size(),
+            modified: file.last_modified(),
+            reservation: None,
+        })
+    }
+
+    /// Keeps the share of the read ahead budget that holds the data until
+    /// this is dropped
+    pub(crate) fn with_reservation(mut self, reservation: ReadAheadReservation) -> Self {
+        self.reservation = Some(reservation);
+        self
+    }
+
+    /// Checks that `file` was not changed since it was read ahead, as it may
+    /// be changed by anything that does not go through this inode
+    pub fn is_current(&self, file: &(dyn VirtualFile + Send + Sync + 'static)) -> bool {
+        file.size() == self.size && file.last_modified() == self.modified
+    }
+
+    /// Returns the data of the `len` bytes from `offset` on when all of
+    /// them were read ahead
+    pub fn get(&self, offset: u64, len: usize) -> Option<&[u8]> {
+        let start = usize::try_from(offset.checked_sub(self.offset)?).ok()?;
+        self.data.get(start..start.checked_add(len)?)
+    }
+}
+
+/// Bytes of the read ahead budget of [`WasiInodes`](super::WasiInodes) that
+/// are in use, they are given back once this is dropped
+#[derive(Debug)]
+pub(crate) struct ReadAheadReservation {
+    used: Arc<AtomicUsize>,
+    len: usize,
+}
+
+impl ReadAheadReservation {
+    /// Reserves `len` bytes when that leaves `used` within `limit`
+    pub(crate) fn new(used: &Arc<AtomicUsize>, len: usize, limit: usize) -> Option<Self> {
+        used.fetch_update(Ordering::AcqRel, Ordering::Acquire, |used| {
+            used.checked_add(len).filter(|used| *used <= limit)
+        })
+        .ok()?;
+        Some(Self {
+            used: used.clone(),
+            len,
+        })
+    }
+}
+
+impl Drop for ReadAheadReservation {
+    fn drop(&mut self) {
+        self.used.fetch_sub(self.len, Ordering::AcqRel);
+    }
+}
+
 /// A file that Wasi knows about that may or may not be open
 #[derive(Debug)]
 #[cfg_attr(feature = "enable-serde", derive(Serialize, Deserialize))]
@@ -64,6 +175,10 @@ pub struct InodeVal {
     pub is_preopened: bool,
     pub name: Cow<'static, str>,
     pub kind: RwLock<Kind>,
+    /// Data read ahead by `fd_advise`, it is dropped when the file is
+    /// opened, written, truncated or renamed through this inode
+    #[cfg_attr(feature = "enable-serde", serde(skip))]
+    pub readahead: StdMutex<Option<ReadAhead>>,
 }
 
 impl InodeVal {
@@ -223,3 +338,60 @@ pub enum Kind {
         inner: Arc<NotificationInner>,
     },
 }
+
+#[cfg(test)]
+mod tests {
+    use virtual_fs::{mem_fs, AsyncWriteExt, FileSystem};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_readahead_fill() {
+        let fs = mem_fs::FileSystem::default();
+        let mut file = fs
+            .new_open_options()
+            .read(true)
+            .write(true)
+            .create_new(true)
+            .open("/file")
+            .unwrap();
+        let contents = (0..64u8).collect::<Vec<_>>();
+        file.write_all(&contents).await.unwrap();
+
+        // The buffer fills up to the read ahead size
+        let readahead = ReadAhead::fill(&mut *file, 8, 16).await.unwrap();
+        assert_eq!(readahead.data, &contents[8..24]);
+        assert_eq!(readahead.get(12, 12), Some(&contents[12..24]));
+        assert_eq!(readahead.get(12, 13), None);
+        assert_eq!(readahead.get(4, 4), None);
+
+        // Or to the end of the file
+        let readahead = ReadAhead::fill(&mut *file, 56, 16).await.unwrap();
+        assert_eq!(readahead.data, &contents[56..]);
+
+        // The data is stale once the file is changed through another handle
+        assert!(readahead.is_current(&*file));
+        let mut other = fs
+            .new_open_options()
+            .write(true)
+            .append(true)
+            .open("/file")
+            .unwrap();
+        other.write_all(b"more").await.unwrap();
+        assert!(!readahead.is_current(&*file));
+    }
+
+    #[test]
+    fn test_readahead_reservation() {
+        let used = Arc::new(AtomicUsize::new(0));
+        let first = ReadAheadReservation::new(&used, 12, 16).unwrap();
+        assert!(ReadAheadReservation::new(&used, 8, 16).is_none());
+
+        let second = ReadAheadReservation::new(&used, 4, 16).unwrap();
+        assert_eq!(used.load(Ordering::Acquire), 16);
+
+        drop(first);
+        drop(second);
+        assert_eq!(used.load(Ordering::Acquire), 0);
+    }
+}
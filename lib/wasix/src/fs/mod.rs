@@ -1,6 +1,10 @@
+mod errno_file;
 mod fd;
 mod inode_guard;
 mod notification;
+mod proc_fs;
+#[cfg(feature = "host-fs")]
+mod temp_dir_fs;
 
 use std::{
     borrow::{Borrow, Cow},
@@ -9,7 +13,7 @@ use std::{
     path::{Component, Path, PathBuf},
     pin::Pin,
     sync::{
-        atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering},
+        atomic::{AtomicBool, AtomicU32, AtomicU64, AtomicUsize, Ordering},
         Arc, Mutex, RwLock, Weak,
     },
     task::{Context, Poll},
@@ -34,12 +38,19 @@ use wasmer_wasix_types::{
     },
 };
 
-pub use self::fd::{EpollFd, EpollInterest, EpollJoinGuard, Fd, InodeVal, Kind};
+pub(crate) use self::errno_file::ErrnoFile;
+pub(crate) use self::fd::ReadAheadReservation;
+pub use self::fd::{
+    DirSnapshot, EpollFd, EpollInterest, EpollJoinGuard, Fd, InodeVal, Kind, ReadAhead,
+};
 pub(crate) use self::inode_guard::{
     InodeValFilePollGuard, InodeValFilePollGuardJoin, InodeValFilePollGuardMode,
     InodeValFileReadGuard, InodeValFileWriteGuard, WasiStateFileGuard, POLL_GUARD_MAX_RET,
 };
 pub use self::notification::NotificationInner;
+pub(crate) use self::proc_fs::{ProcFileSystem, ProcSelf, PROC_MOUNT};
+#[cfg(feature = "host-fs")]
+pub(crate) use self::temp_dir_fs::TempDirFileSystem;
 use crate::syscalls::map_io_err;
 use crate::{bin_factory::BinaryPackage, state::PreopenedDir, ALL_RIGHTS};
 
@@ -88,6 +99,24 @@ const STDERR_DEFAULT_RIGHTS: Rights = STDOUT_DEFAULT_RIGHTS;
 /// the number of symlinks that can be traversed when resolving a path
 pub const MAX_SYMLINKS: u32 = 128;
 
+/// Default upper limit for the length (in bytes) of a path that is resolved
+pub const MAX_PATH_LEN: usize = 4096;
+
+/// Default upper limit for the length (in bytes) of a single path component
+pub const MAX_NAME_LEN: usize = 255;
+
+/// Default upper limit for the number of components of a path that is
+/// resolved (including the components of the symlinks it goes through)
+pub const MAX_PATH_DEPTH: usize = 512;
+
+/// Default number of bytes that are read ahead when `fd_advise` is told
+/// that a range of a file will be needed
+pub const READAHEAD_SIZE: usize = 128 * 1024;
+
+/// Upper limit for the number of bytes that are read ahead for all the
+/// inodes together
+pub const READAHEAD_LIMIT: usize = 16 * 1024 * 1024;
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct Inode(u64);
 
@@ -151,6 +180,9 @@ struct WasiInodesProtected {
 #[cfg_attr(feature = "enable-serde", derive(Serialize, Deserialize))]
 pub struct WasiInodes {
     protected: Arc<RwLock<WasiInodesProtected>>,
+    /// Bytes that are read ahead for all the inodes together
+    #[cfg_attr(feature = "enable-serde", serde(skip))]
+    readahead_used: Arc<AtomicUsize>,
 }
 
 impl WasiInodes {
@@ -160,9 +192,16 @@ impl WasiInodes {
                 seed: 1,
                 lookup: Default::default(),
             })),
+            readahead_used: Default::default(),
         }
     }
 
+    /// Reserves `len` bytes for data that is read ahead, unless the data read
+    /// ahead for all the inodes would exceed [`READAHEAD_LIMIT`]
+    pub(crate) fn reserve_readahead(&self, len: usize) -> Option<ReadAheadReservation> {
+        ReadAheadReservation::new(&self.readahead_used, len, READAHEAD_LIMIT)
+    }
+
     /// adds another value to the inodes
     pub fn add_inode_val(&self, val: InodeVal) -> InodeGuard {
         let val = Arc::new(val);
@@ -281,6 +320,37 @@ impl Default for WasiInodes {
     }
 }
 
+/// A sandboxed file system that several WASI instances can be built on
+/// (see [`crate::WasiEnvBuilder::shared_fs`]).
+///
+/// The instances see the files that the others create and they share the
+/// inode store, so the inode numbers they hand out never collide. Both
+/// are protected by locks, hence the instances may run concurrently.
+#[derive(Debug, Clone, Default)]
+pub struct SharedFileSystem {
+    pub(crate) fs: Arc<virtual_fs::tmp_fs::TmpFileSystem>,
+    pub(crate) inodes: WasiInodes,
+}
+
+impl SharedFileSystem {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Shares an existing (possibly pre-populated) file system
+    pub fn from_fs(fs: virtual_fs::tmp_fs::TmpFileSystem) -> Self {
+        Self {
+            fs: Arc::new(fs),
+            inodes: WasiInodes::new(),
+        }
+    }
+
+    /// The file system that the instances share
+    pub fn fs(&self) -> &virtual_fs::tmp_fs::TmpFileSystem {
+        &self.fs
+    }
+}
+
 #[derive(Debug, Clone)]
 pub enum WasiFsRoot {
     Sandbox(Arc<virtual_fs::tmp_fs::TmpFileSystem>),
@@ -305,6 +375,15 @@ impl WasiFsRoot {
             }
         }
     }
+
+    /// Checks whether both paths are on the same device (i.e. not below two
+    /// different mounts), which is assumed for file systems that can not tell
+    pub(crate) fn is_same_device(&self, a: &Path, b: &Path) -> bool {
+        match self {
+            WasiFsRoot::Sandbox(fs) => fs.is_same_device(a, b).unwrap_or(true),
+            WasiFsRoot::Backing(_) => true,
+        }
+    }
 }
 
 impl FileSystem for WasiFsRoot {
@@ -508,6 +587,28 @@ pub struct WasiFs {
     // It should not be necessary at all.
     is_wasix: AtomicBool,
 
+    // Bumped whenever entries are added to or removed from a directory,
+    // directory snapshots taken at an older generation are stale
+    dir_generation: AtomicU64,
+
+    // Paths (or components of them) longer than these limits are rejected
+    // with `Errno::Nametoolong`
+    pub(crate) max_path_len: usize,
+    pub(crate) max_name_len: usize,
+    pub(crate) max_path_depth: usize,
+
+    // Rights that the fds opened under these paths are limited to
+    pub(crate) path_rights: HashMap<PathBuf, Rights>,
+
+    // The `/proc` that is mounted (if any) where the processes register
+    // their directories
+    #[cfg_attr(feature = "enable-serde", serde(skip, default))]
+    pub(crate) proc_fs: Option<ProcFileSystem>,
+    // The directory of this process in the mounted `/proc`, which is where
+    // `/proc/self` links to
+    #[cfg_attr(feature = "enable-serde", serde(skip, default))]
+    pub(crate) proc_self: Option<ProcSelf>,
+
     // The preopens when this was initialized
     pub(crate) init_preopens: Vec<PreopenedDir>,
     // The virtual file system preopens when this was initialized
@@ -525,6 +626,17 @@ impl WasiFs {
         self.is_wasix.store(is_wasix, Ordering::SeqCst);
     }
 
+    /// Generation of the directories, see [`WasiFs::invalidate_dir_snapshots`]
+    pub(crate) fn dir_generation(&self) -> u64 {
+        self.dir_generation.load(Ordering::Acquire)
+    }
+
+    /// Invalidates the directory snapshots held by open file descriptors,
+    /// must be called whenever an entry is added to or removed from a directory
+    pub(crate) fn invalidate_dir_snapshots(&self) {
+        self.dir_generation.fetch_add(1, Ordering::AcqRel);
+    }
+
     /// Forking the WasiState is used when either fork or vfork is called
     pub fn fork(&self) -> Self {
         let fd_map = self.fd_map.read().unwrap().clone();
@@ -534,6 +646,14 @@ impl WasiFs {
             next_fd: self.next_fd.fork(),
             current_dir: Mutex::new(self.current_dir.lock().unwrap().clone()),
             is_wasix: AtomicBool::new(self.is_wasix.load(Ordering::Acquire)),
+            dir_generation: AtomicU64::new(self.dir_generation.load(Ordering::Acquire)),
+            max_path_len: self.max_path_len,
+            max_name_len: self.max_name_len,
+            max_path_depth: self.max_path_depth,
+            path_rights: self.path_rights.clone(),
+            proc_fs: self.proc_fs.clone(),
+            // registered by `WasiState::fork` which knows the process
+            proc_self: None,
             root_fs: self.root_fs.clone(),
             root_inode: self.root_inode.clone(),
             has_unioned: Arc::new(Mutex::new(HashSet::new())),
@@ -567,6 +687,83 @@ impl WasiFs {
         }
     }
 
+    /// Writes the contents of the file system out to a directory on the
+    /// host, recreating its directories and regular files (which overwrite
+    /// the files already on the host) and their modification times. The
+    /// host file system sets the times in whole seconds so the fractional
+    /// part of the modification times is dropped.
+    ///
+    /// Device files and the generated files of `/proc` are skipped. The
+    /// first file that fails to be written out aborts the whole operation
+    /// and its error is returned, the files before it stay on the host.
+    #[cfg(feature = "host-fs")]
+    pub async fn persist_to(&self, host_dir: impl AsRef<Path>) -> Result<(), FsError> {
+        let host_dir = host_dir.as_ref();
+        let host = virtual_fs::host_fs::FileSystem::default();
+
+        let mut to_persist = VecDeque::new();
+        to_persist.push_back(PathBuf::from("/"));
+
+        while let Some(path) = to_persist.pop_front() {
+            if path == Path::new(PROC_MOUNT) {
+                continue;
+            }
+            let metadata = self.root_fs.metadata(&path)?;
+            let host_path = host_dir.join(path.strip_prefix("/").unwrap_or(&path));
+
+            if metadata.is_dir() {
+                match host.create_dir(&host_path) {
+                    Ok(()) | Err(FsError::AlreadyExists) => {}
+                    Err(err) => return Err(err),
+                }
+                for entry in self.root_fs.read_dir(&path)? {
+                    to_persist.push_back(entry?.path);
+                }
+            } else if metadata.is_file() && !metadata.ft.is_char_device() {
+                self.persist_file(&host, &path, &host_path, &metadata)
+                    .await
+                    .map_err(|err| {
+                        debug!(path=%path.display(), %err, "failed to persist file");
+                        err
+                    })?;
+            } else {
+                trace!(path=%path.display(), ?metadata, "skipping special file while persisting");
+            }
+        }
+
+        Ok(())
+    }
+
+    #[cfg(feature = "host-fs")]
+    async fn persist_file(
+        &self,
+        host: &virtual_fs::host_fs::FileSystem,
+        path: &Path,
+        host_path: &Path,
+        metadata: &virtual_fs::Metadata,
+    ) -> Result<(), FsError> {
+        use virtual_fs::AsyncReadExt;
+
+        let mut data = Vec::with_capacity(metadata.len as usize);
+        self.root_fs
+            .new_open_options()
+            .read(true)
+            .open(path)?
+            .read_to_end(&mut data)
+            .await?;
+
+        let mut file = host
+            .new_open_options()
+            .create(true)
+            .write(true)
+            .truncate(true)
+            .open(host_path)?;
+        file.write_all(&data).await?;
+        file.flush().await?;
+        // The host file system takes the times in seconds
+        file.set_times(None, Some(metadata.modified / 1_000_000_000))
+    }
+
     /// Will conditionally union the binary file system with this one
     /// if it has not already been unioned
     pub async fn conditional_union(
@@ -627,6 +824,7 @@ impl WasiFs {
             is_preopened: true,
             name: "/".into(),
             kind: RwLock::new(root_kind),
+            readahead: Default::default(),
         });
 
         let wasi_fs = Self {
@@ -635,6 +833,13 @@ impl WasiFs {
             next_fd: WasiFdSeed::default(),
             current_dir: Mutex::new("/".to_string()),
             is_wasix: AtomicBool::new(false),
+            dir_generation: AtomicU64::new(0),
+            max_path_len: MAX_PATH_LEN,
+            max_name_len: MAX_NAME_LEN,
+            max_path_depth: MAX_PATH_DEPTH,
+            path_rights: HashMap::new(),
+            proc_fs: None,
+            proc_self: None,
             root_fs: fs_backing,
             root_inode,
             has_unioned: Arc::new(Mutex::new(HashSet::new())),
@@ -927,161 +1132,157 @@ impl WasiFs {
             return Err(Errno::Mlink);
         }
 
-        let path: &Path = Path::new(path);
-        let n_components = path.components().count();
+        // The components that are left to resolve, the targets of the
+        // symlinks are spliced in here rather than resolved recursively so
+        // that deep paths can not exhaust the stack
+        let mut components: VecDeque<PathBuf> = Path::new(path)
+            .components()
+            .map(|component| PathBuf::from(component.as_os_str()))
+            .collect();
 
         // TODO: rights checks
-        'path_iter: for (i, component) in path.components().enumerate() {
+        while let Some(component_path) = components.pop_front() {
+            let component = match component_path.components().next() {
+                Some(component) => component,
+                None => continue,
+            };
             // used to terminate symlink resolution properly
-            let last_component = i + 1 == n_components;
+            let last_component = components.is_empty();
             // for each component traverse file structure
             // loading inodes as necessary
-            'symlink_resolution: while symlink_count < MAX_SYMLINKS {
-                let processing_cur_inode = cur_inode.clone();
-                let mut guard = processing_cur_inode.write();
-                match guard.deref_mut() {
-                    Kind::Buffer { .. } => unimplemented!("state::get_inode_at_path for buffers"),
-                    Kind::Dir {
-                        ref mut entries,
-                        ref path,
-                        ref parent,
-                        ..
-                    } => {
-                        match component.as_os_str().to_string_lossy().borrow() {
-                            ".." => {
-                                if let Some(p) = parent.upgrade() {
-                                    cur_inode = p;
-                                    continue 'path_iter;
-                                } else {
-                                    return Err(Errno::Access);
-                                }
+            let processing_cur_inode = cur_inode.clone();
+            let mut guard = processing_cur_inode.write();
+            match guard.deref_mut() {
+                Kind::Buffer { .. } => unimplemented!("state::get_inode_at_path for buffers"),
+                Kind::Dir {
+                    ref mut entries,
+                    ref path,
+                    ref parent,
+                    ..
+                } => {
+                    match component.as_os_str().to_string_lossy().borrow() {
+                        ".." => {
+                            if let Some(p) = parent.upgrade() {
+                                cur_inode = p;
+                                continue;
+                            } else {
+                                return Err(Errno::Access);
                             }
-                            "." => continue 'path_iter,
-                            _ => (),
                         }
-                        // used for full resolution of symlinks
-                        let mut loop_for_symlink = false;
-                        if let Some(entry) =
-                            entries.get(component.as_os_str().to_string_lossy().as_ref())
-                        {
-                            cur_inode = entry.clone();
-                        } else {
-                            let file = {
-                                let mut cd = path.clone();
-                                cd.push(component);
-                                cd
-                            };
-                            let metadata = self
-                                .root_fs
+                        "." => continue,
+                        _ => (),
+                    }
+                    // used for full resolution of symlinks
+                    let mut loop_for_symlink = false;
+                    if let Some(entry) =
+                        entries.get(component.as_os_str().to_string_lossy().as_ref())
+                    {
+                        cur_inode = entry.clone();
+                        // symlinks in the middle of a path are followed when
+                        // the next component is processed
+                        if last_component && follow_symlinks {
+                            drop(guard);
+                            self.splice_symlink(
+                                &mut cur_inode,
+                                &mut components,
+                                &mut symlink_count,
+                            )?;
+                        }
+                        continue;
+                    } else {
+                        let file = {
+                            let mut cd = path.clone();
+                            cd.push(component);
+                            cd
+                        };
+                        let metadata =
+                            self.root_fs
                                 .symlink_metadata(&file)
-                                .ok()
-                                .ok_or(Errno::Noent)?;
-                            let file_type = metadata.file_type();
-                            // we want to insert newly opened dirs and files, but not transient symlinks
-                            // TODO: explain why (think about this deeply when well rested)
-                            let should_insert;
-
-                            let kind = if file_type.is_dir() {
-                                should_insert = true;
-                                // load DIR
-                                Kind::Dir {
-                                    parent: cur_inode.downgrade(),
-                                    path: file.clone(),
-                                    entries: Default::default(),
+                                .map_err(|err| match err {
+                                    FsError::BaseNotDirectory => Errno::Notdir,
+                                    _ => Errno::Noent,
+                                })?;
+                        let file_type = metadata.file_type();
+                        // we want to insert newly opened dirs and files, but not transient symlinks
+                        // TODO: explain why (think about this deeply when well rested)
+                        let should_insert;
+
+                        let kind = if file_type.is_dir() {
+                            should_insert = true;
+                            // load DIR
+                            Kind::Dir {
+                                parent: cur_inode.downgrade(),
+                                path: file.clone(),
+                                entries: Default::default(),
+                            }
+                        } else if file_type.is_file() {
+                            should_insert = true;
+                            // load file
+                            Kind::File {
+                                handle: None,
+                                path: file.clone(),
+                                fd: None,
+                            }
+                        } else if file_type.is_symlink() {
+                            should_insert = false;
+                            let link_value = match &self.proc_self {
+                                Some(proc_self) if file == Path::new(PROC_MOUNT).join("self") => {
+                                    PathBuf::from(proc_self.pid().to_string())
                                 }
-                            } else if file_type.is_file() {
-                                should_insert = true;
-                                // load file
-                                Kind::File {
+                                _ => self.root_fs.readlink(&file).ok().ok_or(Errno::Noent)?,
+                            };
+                            debug!("attempting to decompose path {:?}", link_value);
+
+                            let (pre_open_dir_fd, relative_path) = if link_value.is_relative() {
+                                self.path_into_pre_open_and_relative_path(&file)?
+                            } else {
+                                tracing::error!("Absolute symlinks are not yet supported");
+                                return Err(Errno::Notsup);
+                            };
+                            loop_for_symlink = true;
+                            symlink_count += 1;
+                            Kind::Symlink {
+                                base_po_dir: pre_open_dir_fd,
+                                path_to_symlink: relative_path.to_owned(),
+                                relative_path: link_value,
+                            }
+                        } else {
+                            #[cfg(unix)]
+                            {
+                                //use std::os::unix::fs::FileTypeExt;
+                                let file_type: Filetype = if file_type.is_char_device() {
+                                    Filetype::CharacterDevice
+                                } else if file_type.is_block_device() {
+                                    Filetype::BlockDevice
+                                } else if file_type.is_fifo() {
+                                    // FIFO doesn't seem to fit any other type, so unknown
+                                    Filetype::Unknown
+                                } else if file_type.is_socket() {
+                                    // TODO: how do we know if it's a `SocketStream` or
+                                    // a `SocketDgram`?
+                                    Filetype::SocketStream
+                                } else {
+                                    unimplemented!("state::get_inode_at_path unknown file type: not file, directory, symlink, char device, block device, fifo, or socket");
+                                };
+
+                                let kind = Kind::File {
                                     handle: None,
                                     path: file.clone(),
                                     fd: None,
-                                }
-                            } else if file_type.is_symlink() {
-                                should_insert = false;
-                                let link_value =
-                                    self.root_fs.readlink(&file).ok().ok_or(Errno::Noent)?;
-                                debug!("attempting to decompose path {:?}", link_value);
-
-                                let (pre_open_dir_fd, relative_path) = if link_value.is_relative() {
-                                    self.path_into_pre_open_and_relative_path(&file)?
-                                } else {
-                                    tracing::error!("Absolute symlinks are not yet supported");
-                                    return Err(Errno::Notsup);
                                 };
-                                loop_for_symlink = true;
-                                symlink_count += 1;
-                                Kind::Symlink {
-                                    base_po_dir: pre_open_dir_fd,
-                                    path_to_symlink: relative_path.to_owned(),
-                                    relative_path: link_value,
-                                }
-                            } else {
-                                #[cfg(unix)]
-                                {
-                                    //use std::os::unix::fs::FileTypeExt;
-                                    let file_type: Filetype = if file_type.is_char_device() {
-                                        Filetype::CharacterDevice
-                                    } else if file_type.is_block_device() {
-                                        Filetype::BlockDevice
-                                    } else if file_type.is_fifo() {
-                                        // FIFO doesn't seem to fit any other type, so unknown
-                                        Filetype::Unknown
-                                    } else if file_type.is_socket() {
-                                        // TODO: how do we know if it's a `SocketStream` or
-                                        // a `SocketDgram`?
-                                        Filetype::SocketStream
-                                    } else {
-                                        unimplemented!("state::get_inode_at_path unknown file type: not file, directory, symlink, char device, block device, fifo, or socket");
-                                    };
-
-                                    let kind = Kind::File {
-                                        handle: None,
-                                        path: file.clone(),
-                                        fd: None,
-                                    };
-                                    drop(guard);
-                                    let new_inode = self.create_inode_with_stat(
-                                        inodes,
-                                        kind,
-                                        false,
-                                        file.to_string_lossy().to_string().into(),
-                                        Filestat {
-                                            st_filetype: file_type,
-                                            ..Filestat::default()
-                                        },
-                                    );
-
-                                    let mut guard = cur_inode.write();
-                                    if let Kind::Dir {
-                                        ref mut entries, ..
-                                    } = guard.deref_mut()
-                                    {
-                                        entries.insert(
-                                            component.as_os_str().to_string_lossy().to_string(),
-                                            new_inode.clone(),
-                                        );
-                                    } else {
-                                        unreachable!(
-                                            "Attempted to insert special device into non-directory"
-                                        );
-                                    }
-                                    // perhaps just continue with symlink resolution and return at the end
-                                    return Ok(new_inode);
-                                }
-                                #[cfg(not(unix))]
-                                unimplemented!("state::get_inode_at_path unknown file type: not file, directory, or symlink");
-                            };
-                            drop(guard);
-
-                            let new_inode = self.create_inode(
-                                inodes,
-                                kind,
-                                false,
-                                file.to_string_lossy().to_string(),
-                            )?;
-                            if should_insert {
-                                let mut guard = processing_cur_inode.write();
+                                drop(guard);
+                                let new_inode = self.create_inode_with_stat(
+                                    inodes,
+                                    kind,
+                                    false,
+                                    file.to_string_lossy().to_string().into(),
+                                    Filestat {
+                                        st_filetype: file_type,
+                                        ..Filestat::default()
+                                    },
+                                );
+
+                                let mut guard = cur_inode.write();
                                 if let Kind::Dir {
                                     ref mut entries, ..
                                 } = guard.deref_mut()
@@ -1090,93 +1291,132 @@ impl WasiFs {
                                         component.as_os_str().to_string_lossy().to_string(),
                                         new_inode.clone(),
                                     );
+                                } else {
+                                    unreachable!(
+                                        "Attempted to insert special device into non-directory"
+                                    );
                                 }
+                                // perhaps just continue with symlink resolution and return at the end
+                                return Ok(new_inode);
                             }
-                            cur_inode = new_inode;
-
-                            if loop_for_symlink && follow_symlinks {
-                                debug!("Following symlink to {:?}", cur_inode);
-                                continue 'symlink_resolution;
-                            }
-                        }
-                    }
-                    Kind::Root { entries } => {
-                        match component {
-                            // the root's parent is the root
-                            Component::ParentDir => continue 'path_iter,
-                            // the root's current directory is the root
-                            Component::CurDir => continue 'path_iter,
-                            _ => {}
-                        }
-
-                        let component = component.as_os_str().to_string_lossy();
-
-                        if let Some(entry) = entries.get(component.as_ref()) {
-                            cur_inode = entry.clone();
-                        } else if let Some(root) = entries.get(&"/".to_string()) {
-                            cur_inode = root.clone();
-                            continue 'symlink_resolution;
-                        } else {
-                            // Root is not capable of having something other then preopenned folders
-                            return Err(Errno::Notcapable);
-                        }
-                    }
-                    Kind::File { .. }
-                    | Kind::Socket { .. }
-                    | Kind::Pipe { .. }
-                    | Kind::EventNotifications { .. }
-                    | Kind::Epoll { .. } => {
-                        return Err(Errno::Notdir);
-                    }
-                    Kind::Symlink {
-                        base_po_dir,
-                        path_to_symlink,
-                        relative_path,
-                    } => {
-                        let new_base_dir = *base_po_dir;
-                        let new_base_inode = self.get_fd_inode(new_base_dir)?;
-
-                        // allocate to reborrow mutabily to recur
-                        let new_path = {
-                            /*if let Kind::Root { .. } = self.inodes[base_po_dir].kind {
-                                assert!(false, "symlinks should never be relative to the root");
-                            }*/
-                            let mut base = path_to_symlink.clone();
-                            // remove the symlink file itself from the path, leaving just the path from the base
-                            // to the dir containing the symlink
-                            base.pop();
-                            base.push(relative_path);
-                            base.to_string_lossy().to_string()
+                            #[cfg(not(unix))]
+                            unimplemented!("state::get_inode_at_path unknown file type: not file, directory, or symlink");
                         };
-                        debug!("Following symlink recursively");
                         drop(guard);
-                        let symlink_inode = self.get_inode_at_path_inner(
+
+                        let new_inode = self.create_inode(
                             inodes,
-                            new_base_inode,
-                            &new_path,
-                            symlink_count + 1,
-                            follow_symlinks,
+                            kind,
+                            false,
+                            file.to_string_lossy().to_string(),
                         )?;
-                        cur_inode = symlink_inode;
-                        // if we're at the very end and we found a file, then we're done
-                        // TODO: figure out if this should also happen for directories?
-                        let guard = cur_inode.read();
-                        if let Kind::File { .. } = guard.deref() {
-                            // check if on last step
-                            if last_component {
-                                break 'symlink_resolution;
+                        if should_insert {
+                            let mut guard = processing_cur_inode.write();
+                            if let Kind::Dir {
+                                ref mut entries, ..
+                            } = guard.deref_mut()
+                            {
+                                entries.insert(
+                                    component.as_os_str().to_string_lossy().to_string(),
+                                    new_inode.clone(),
+                                );
                             }
                         }
-                        continue 'symlink_resolution;
+                        cur_inode = new_inode;
+
+                        if loop_for_symlink && follow_symlinks && last_component {
+                            debug!("Following symlink to {:?}", cur_inode);
+                            self.splice_symlink(
+                                &mut cur_inode,
+                                &mut components,
+                                &mut symlink_count,
+                            )?;
+                        }
+                    }
+                }
+                Kind::Root { entries } => {
+                    match component {
+                        // the root's parent is the root
+                        Component::ParentDir => continue,
+                        // the root's current directory is the root
+                        Component::CurDir => continue,
+                        _ => {}
+                    }
+
+                    let component = component.as_os_str().to_string_lossy();
+
+                    if let Some(entry) = entries.get(component.as_ref()) {
+                        cur_inode = entry.clone();
+                    } else if let Some(root) = entries.get(&"/".to_string()) {
+                        // the component is resolved again under the root
+                        cur_inode = root.clone();
+                        components.push_front(component_path);
+                        continue;
+                    } else {
+                        // Root is not capable of having something other then preopenned folders
+                        return Err(Errno::Notcapable);
                     }
                 }
-                break 'symlink_resolution;
+                Kind::File { .. }
+                | Kind::Socket { .. }
+                | Kind::Pipe { .. }
+                | Kind::EventNotifications { .. }
+                | Kind::Epoll { .. } => {
+                    return Err(Errno::Notdir);
+                }
+                Kind::Symlink { .. } => {
+                    // the component is resolved again under the target
+                    // of the symlink
+                    drop(guard);
+                    components.push_front(component_path);
+                    self.splice_symlink(&mut cur_inode, &mut components, &mut symlink_count)?;
+                }
             }
         }
 
         Ok(cur_inode)
     }
 
+    /// When `inode` is a symlink, moves it to the directory the symlink is
+    /// relative to and puts the components of its target in front of the
+    /// components that are left to resolve
+    fn splice_symlink(
+        &self,
+        inode: &mut InodeGuard,
+        components: &mut VecDeque<PathBuf>,
+        symlink_count: &mut u32,
+    ) -> Result<(), Errno> {
+        let (base_po_dir, new_path) = {
+            let guard = inode.read();
+            match guard.deref() {
+                Kind::Symlink {
+                    base_po_dir,
+                    path_to_symlink,
+                    relative_path,
+                } => {
+                    // the link is relative to the directory that contains it
+                    let mut base = path_to_symlink.clone();
+                    base.pop();
+                    base.push(relative_path);
+                    (*base_po_dir, base)
+                }
+                _ => return Ok(()),
+            }
+        };
+        *symlink_count += 1;
+        if *symlink_count > MAX_SYMLINKS {
+            return Err(Errno::Mlink);
+        }
+        for component in new_path.components().rev() {
+            components.push_front(PathBuf::from(component.as_os_str()));
+        }
+        if components.len() > self.max_path_depth {
+            return Err(Errno::Nametoolong);
+        }
+        *inode = self.get_fd_inode(base_po_dir)?;
+        Ok(())
+    }
+
     /// Finds the preopened directory that is the "best match" for the given path and
     /// returns a path relative to this preopened directory.
     ///
@@ -1272,6 +1512,9 @@ impl WasiFs {
     // even if it's false, it still follows symlinks, just not the last
     // symlink so
     // This will be resolved when we have tests asserting the correct behavior
+    //
+    // An empty path does not refer to anything (not even `base`), like on
+    // POSIX it fails with `Errno::Noent`
     pub(crate) fn get_inode_at_path(
         &self,
         inodes: &WasiInodes,
@@ -1279,6 +1522,11 @@ impl WasiFs {
         path: &str,
         follow_symlinks: bool,
     ) -> Result<InodeGuard, Errno> {
+        if path.is_empty() {
+            return Err(Errno::Noent);
+        }
+        self.check_path_len(path)?;
+
         let base_inode = self.get_fd_inode(base)?;
         let start_inode =
             if !base_inode.deref().name.starts_with('/') && self.is_wasix.load(Ordering::Acquire) {
@@ -1287,9 +1535,83 @@ impl WasiFs {
             } else {
                 self.get_fd_inode(base)?
             };
+
+        // A trailing slash can only refer to a directory, hence the path is
+        // always resolved to its target when it is a symlink
+        if path.ends_with('/') {
+            let inode = self.get_inode_at_path_inner(inodes, start_inode, path, 0, true)?;
+            let is_dir = matches!(inode.read().deref(), Kind::Dir { .. } | Kind::Root { .. });
+            return match is_dir {
+                true => Ok(inode),
+                false => Err(Errno::Notdir),
+            };
+        }
+
         self.get_inode_at_path_inner(inodes, start_inode, path, 0, follow_symlinks)
     }
 
+    /// Rejects paths that are longer (or deeper) than the limits of the file
+    /// system
+    fn check_path_len(&self, path: &str) -> Result<(), Errno> {
+        if path.len() > self.max_path_len
+            || path.split('/').any(|name| name.len() > self.max_name_len)
+            || path.split('/').filter(|name| !name.is_empty()).count() > self.max_path_depth
+        {
+            return Err(Errno::Nametoolong);
+        }
+        Ok(())
+    }
+
+    /// Returns the rights that the fds opened at `path` are limited to, which
+    /// are those of the most specific path (of `path_rights`) it is under
+    pub(crate) fn path_rights_at(&self, path: &Path) -> Rights {
+        // The entries of the root carry paths relative to it
+        let path = Path::new("/").join(path);
+        path.ancestors()
+            .find_map(|ancestor| self.path_rights.get(ancestor))
+            .copied()
+            .unwrap_or(ALL_RIGHTS)
+    }
+
+    /// Fails with `Errno::Access` when the entry at `path` (relative to
+    /// `base`) is under a path (of `path_rights`) whose rights lack `right`
+    pub(crate) fn check_path_rights(
+        &self,
+        inodes: &WasiInodes,
+        base: WasiFd,
+        path: &Path,
+        follow_symlinks: bool,
+        right: Rights,
+    ) -> Result<(), Errno> {
+        if self.path_rights.is_empty() {
+            return Ok(());
+        }
+        match self.entry_path_at(inodes, base, path, follow_symlinks) {
+            Some(path) if !self.path_rights_at(&path).contains(right) => Err(Errno::Access),
+            _ => Ok(()),
+        }
+    }
+
+    /// Returns the path in the file system of the entry at `path` (relative
+    /// to `base`), whether or not it exists yet
+    pub(crate) fn entry_path_at(
+        &self,
+        inodes: &WasiInodes,
+        base: WasiFd,
+        path: &Path,
+        follow_symlinks: bool,
+    ) -> Option<PathBuf> {
+        let (parent_inode, name) = self
+            .get_parent_inode_at_path(inodes, base, path, follow_symlinks)
+            .ok()?;
+        let guard = parent_inode.read();
+        match guard.deref() {
+            Kind::Dir { path, .. } => Some(path.join(name)),
+            Kind::Root { .. } => Some(PathBuf::from(name)),
+            _ => None,
+        }
+    }
+
     /// Returns the parent Dir or Root that the file at a given path is in and the file name
     /// stripped off
     pub(crate) fn get_parent_inode_at_path(
@@ -1299,17 +1621,23 @@ impl WasiFs {
         path: &Path,
         follow_symlinks: bool,
     ) -> Result<(InodeGuard, String), Errno> {
+        self.check_path_len(&path.to_string_lossy())?;
+
         let mut parent_dir = std::path::PathBuf::new();
         let mut components = path.components().rev();
         let new_entity_name = components
             .next()
-            .ok_or(Errno::Inval)?
+            .ok_or(Errno::Noent)?
             .as_os_str()
             .to_string_lossy()
             .to_string();
         for comp in components.rev() {
             parent_dir.push(comp);
         }
+        // The entity is directly inside of `base`
+        if parent_dir.as_os_str().is_empty() {
+            parent_dir.push(".");
+        }
         self.get_inode_at_path(inodes, base, &parent_dir.to_string_lossy(), follow_symlinks)
             .map(|v| (v, new_entity_name))
     }
@@ -1332,6 +1660,8 @@ impl WasiFs {
                 open_flags: 0,
                 inode: self.root_inode.clone(),
                 is_stdio: false,
+                stdio_rights_enforced: false,
+                readdir_snapshot: Default::default(),
             })
         } else {
             ret
@@ -1339,16 +1669,8 @@ impl WasiFs {
     }
 
     pub fn get_fd_inode(&self, fd: WasiFd) -> Result<InodeGuard, Errno> {
-        // see `VIRTUAL_ROOT_FD` for details as to why this exists
-        if fd == VIRTUAL_ROOT_FD {
-            return Ok(self.root_inode.clone());
-        }
-        self.fd_map
-            .read()
-            .unwrap()
-            .get(&fd)
-            .ok_or(Errno::Badf)
-            .map(|a| a.inode.clone())
+        // `get_fd` falls back to the virtual root, see `VIRTUAL_ROOT_FD`
+        self.get_fd(fd).map(|a| a.inode)
     }
 
     pub fn filestat_fd(&self, fd: WasiFd) -> Result<Filestat, Errno> {
@@ -1363,7 +1685,7 @@ impl WasiFs {
                 return Ok(Fdstat {
                     fs_filetype: Filetype::CharacterDevice,
                     fs_flags: Fdflags::empty(),
-                    fs_rights_base: STDIN_DEFAULT_RIGHTS,
+                    fs_rights_base: self.std_dev_rights(fd).unwrap_or(STDIN_DEFAULT_RIGHTS),
                     fs_rights_inheriting: Rights::empty(),
                 })
             }
@@ -1371,7 +1693,7 @@ impl WasiFs {
                 return Ok(Fdstat {
                     fs_filetype: Filetype::CharacterDevice,
                     fs_flags: Fdflags::APPEND,
-                    fs_rights_base: STDOUT_DEFAULT_RIGHTS,
+                    fs_rights_base: self.std_dev_rights(fd).unwrap_or(STDOUT_DEFAULT_RIGHTS),
                     fs_rights_inheriting: Rights::empty(),
                 })
             }
@@ -1379,19 +1701,10 @@ impl WasiFs {
                 return Ok(Fdstat {
                     fs_filetype: Filetype::CharacterDevice,
                     fs_flags: Fdflags::APPEND,
-                    fs_rights_base: STDERR_DEFAULT_RIGHTS,
+                    fs_rights_base: self.std_dev_rights(fd).unwrap_or(STDERR_DEFAULT_RIGHTS),
                     fs_rights_inheriting: Rights::empty(),
                 })
             }
-            VIRTUAL_ROOT_FD => {
-                return Ok(Fdstat {
-                    fs_filetype: Filetype::Directory,
-                    fs_flags: Fdflags::empty(),
-                    // TODO: fix this
-                    fs_rights_base: ALL_RIGHTS,
-                    fs_rights_inheriting: ALL_RIGHTS,
-                });
-            }
             _ => (),
         }
         let fd = self.get_fd(fd)?;
@@ -1401,7 +1714,7 @@ impl WasiFs {
         Ok(Fdstat {
             fs_filetype: match deref {
                 Kind::File { .. } => Filetype::RegularFile,
-                Kind::Dir { .. } => Filetype::Directory,
+                Kind::Dir { .. } | Kind::Root { .. } => Filetype::Directory,
                 Kind::Symlink { .. } => Filetype::SymbolicLink,
                 Kind::Socket { socket } => match &socket.inner.protected.read().unwrap().kind {
                     InodeSocketKind::TcpStream { .. } => Filetype::SocketStream,
@@ -1419,10 +1732,36 @@ impl WasiFs {
             },
             fs_flags: fd.flags,
             fs_rights_base: fd.rights,
-            fs_rights_inheriting: fd.rights_inheriting, // TODO(lachlan): Is this right?
+            fs_rights_inheriting: fd.rights_inheriting,
         })
     }
 
+    /// Rights currently held by one of the stdio file descriptors
+    fn std_dev_rights(&self, fd: WasiFd) -> Option<Rights> {
+        self.fd_map
+            .read()
+            .unwrap()
+            .get(&fd)
+            .filter(|fd| fd.is_stdio)
+            .map(|fd| fd.rights)
+    }
+
+    /// Replaces the rights of one of the stdio file descriptors, the syscalls
+    /// that operate on it are then restricted to these rights and fail with
+    /// `Errno::Notcapable` when they need any other right.
+    pub fn set_std_dev_rights(&self, fd: WasiFd, rights: Rights) -> Result<(), FsError> {
+        match fd {
+            __WASI_STDIN_FILENO | __WASI_STDOUT_FILENO | __WASI_STDERR_FILENO => {
+                let mut fd_map = self.fd_map.write().unwrap();
+                let fd = fd_map.get_mut(&fd).ok_or(FsError::NoDevice)?;
+                fd.rights = rights;
+                fd.stdio_rights_enforced = true;
+                Ok(())
+            }
+            _ => Err(FsError::InvalidInput),
+        }
+    }
+
     pub fn prestat_fd(&self, fd: WasiFd) -> Result<Prestat, Errno> {
         let inode = self.get_fd_inode(fd)?;
         //trace!("in prestat_fd {:?}", self.get_fd(fd)?);
@@ -1551,6 +1890,7 @@ impl WasiFs {
             is_preopened,
             name,
             kind: RwLock::new(kind),
+            readahead: Default::default(),
         });
         stat.st_ino = ret.ino().as_u64();
         ret
@@ -1596,6 +1936,8 @@ impl WasiFs {
                 open_flags,
                 inode,
                 is_stdio,
+                stdio_rights_enforced: false,
+                readdir_snapshot: Default::default(),
             },
         );
         Ok(())
@@ -1614,6 +1956,8 @@ impl WasiFs {
                 open_flags: fd.open_flags,
                 inode: fd.inode,
                 is_stdio: fd.is_stdio,
+                stdio_rights_enforced: fd.stdio_rights_enforced,
+                readdir_snapshot: fd.readdir_snapshot.clone(),
             },
         );
         Ok(idx)
@@ -1897,6 +2241,7 @@ impl WasiFs {
                 is_preopened: true,
                 name: name.to_string().into(),
                 kind: RwLock::new(kind),
+                readahead: Default::default(),
             })
         };
         self.fd_map.write().unwrap().insert(
@@ -1910,6 +2255,8 @@ impl WasiFs {
                 offset: Arc::new(AtomicU64::new(0)),
                 inode,
                 is_stdio: true,
+                stdio_rights_enforced: false,
+                readdir_snapshot: Default::default(),
             },
         );
     }
@@ -1938,16 +2285,22 @@ impl WasiFs {
                 .root_fs
                 .metadata(path)
                 .map_err(fs_error_into_wasi_err)?,
+            Kind::Root { .. } => {
+                return Ok(Filestat {
+                    st_filetype: Filetype::Directory,
+                    ..Filestat::default()
+                })
+            }
             Kind::Symlink {
                 base_po_dir,
                 path_to_symlink,
-                ..
+                relative_path,
             } => {
                 let base_po_inode = &self.fd_map.read().unwrap()[base_po_dir].inode;
                 let guard = base_po_inode.read();
-                match guard.deref() {
+                let md = match guard.deref() {
                     Kind::Root { .. } => {
-                        self.root_fs.symlink_metadata(path_to_symlink).map_err(fs_error_into_wasi_err)?
+                        self.root_fs.symlink_metadata(path_to_symlink)
                     }
                     Kind::Dir { path, .. } => {
                         let mut real_path = path.clone();
@@ -1958,10 +2311,22 @@ impl WasiFs {
                         // TODO: adjust size of symlink, too
                         //      for all paths adjusted think about this
                         real_path.push(path_to_symlink);
-                        self.root_fs.symlink_metadata(&real_path).map_err(fs_error_into_wasi_err)?
+                        self.root_fs.symlink_metadata(&real_path)
                     }
                     // if this triggers, there's a bug in the symlink code
                     _ => unreachable!("Symlink pointing to something that's not a directory as its base preopened directory"),
+                };
+                match md {
+                    Ok(md) => md,
+                    // symlinks created with `path_symlink` only exist in the inode tree
+                    Err(FsError::EntryNotFound) => {
+                        return Ok(Filestat {
+                            st_filetype: Filetype::SymbolicLink,
+                            st_size: relative_path.as_os_str().len() as u64,
+                            ..Filestat::default()
+                        })
+                    }
+                    Err(err) => return Err(fs_error_into_wasi_err(err)),
                 }
             }
             _ => return Err(Errno::Io),
@@ -2092,12 +2457,15 @@ pub fn fs_error_from_wasi_err(err: Errno) -> FsError {
         Errno::Notconn => FsError::NotConnected,
         Errno::Nodev => FsError::NoDevice,
         Errno::Noent => FsError::EntryNotFound,
+        Errno::Access => FsError::PermissionDenied,
         Errno::Perm => FsError::PermissionDenied,
         Errno::Timedout => FsError::TimedOut,
         Errno::Proto => FsError::UnexpectedEof,
         Errno::Again => FsError::WouldBlock,
         Errno::Nospc => FsError::WriteZero,
         Errno::Notempty => FsError::DirectoryNotEmpty,
+        Errno::Xdev => FsError::CrossDevice,
+        Errno::Rofs => FsError::ReadOnlyFileSystem,
         _ => FsError::UnknownError,
     }
 }
@@ -2121,13 +2489,17 @@ pub fn fs_error_into_wasi_err(fs_error: FsError) -> Errno {
         FsError::NotAFile => Errno::Inval,
         FsError::NotConnected => Errno::Notconn,
         FsError::EntryNotFound => Errno::Noent,
-        FsError::PermissionDenied => Errno::Perm,
+        // Denied by the permissions of the file rather than a privilege of
+        // the process
+        FsError::PermissionDenied => Errno::Access,
         FsError::TimedOut => Errno::Timedout,
         FsError::UnexpectedEof => Errno::Proto,
         FsError::WouldBlock => Errno::Again,
         FsError::WriteZero => Errno::Nospc,
         FsError::DirectoryNotEmpty => Errno::Notempty,
         FsError::StorageFull => Errno::Overflow,
+        FsError::CrossDevice => Errno::Xdev,
+        FsError::ReadOnlyFileSystem => Errno::Rofs,
         FsError::Lock | FsError::UnknownError => Errno::Io,
     }
 }
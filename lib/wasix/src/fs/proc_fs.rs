@@ -0,0 +1,365 @@
+//! A synthetic, read-only `/proc` file system that exposes the state of the
+//! running processes the way Linux does under `/proc/<id>`.
+//!
+//! The file system is shared by every process that is forked off the same
+//! [`WasiFs`](super::WasiFs), so `/proc/self` can not be answered here. It is
+//! resolved by the [`WasiFs`](super::WasiFs) of the calling process instead,
+//! which links it to the directory of its own [`ProcSelf`].
+
+use std::{
+    collections::HashMap,
+    ops::Deref,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, RwLock, Weak},
+};
+
+use futures::future::BoxFuture;
+use virtual_fs::{
+    DirEntry, FileOpener, FileSystem, FileType, FsError, Metadata, OpenOptions, OpenOptionsConfig,
+    ReadDir, StaticFile, VirtualFile,
+};
+use wasmer_wasix_types::wasi::Fd as WasiFd;
+
+use super::{Fd, Kind};
+use crate::os::task::{
+    process::{WasiProcess, WasiProcessId},
+    OwnedTaskStatus,
+};
+
+/// Where the [`ProcFileSystem`] is mounted
+pub(crate) const PROC_MOUNT: &str = "/proc";
+
+/// Serves `/<pid>/cmdline`, `/<pid>/environ` and `/<pid>/fd` from the state
+/// of every registered process, every read is generated from the current
+/// state. The directories are named after the [`WasiProcessId`] of the
+/// processes and disappear once the process exits.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ProcFileSystem {
+    processes: Arc<RwLock<HashMap<u32, ProcState>>>,
+}
+
+#[derive(Debug)]
+struct ProcState {
+    finished: Arc<OwnedTaskStatus>,
+    /// The [`ProcSelf`] that this state was registered through
+    owner: Weak<()>,
+    args: Vec<String>,
+    envs: Arc<Mutex<Vec<Vec<u8>>>>,
+    fd_map: Arc<RwLock<HashMap<WasiFd, Fd>>>,
+}
+
+impl ProcState {
+    fn exited(&self) -> bool {
+        self.finished.status().is_finished()
+    }
+}
+
+/// The entry of a process in a [`ProcFileSystem`], which is removed again
+/// once this is dropped (unless it was replaced in the meantime)
+#[derive(Debug)]
+pub(crate) struct ProcSelf {
+    fs: ProcFileSystem,
+    pid: WasiProcessId,
+    finished: Arc<OwnedTaskStatus>,
+    token: Arc<()>,
+}
+
+enum ProcEntry {
+    Dir,
+    SelfLink,
+    Cmdline(u32),
+    Environ(u32),
+    Fd(u32, WasiFd),
+}
+
+impl ProcFileSystem {
+    /// Adds the directory of a process, which lives as long as the returned
+    /// [`ProcSelf`] and the process itself
+    pub(crate) fn register(
+        &self,
+        process: &WasiProcess,
+        args: Vec<String>,
+        envs: Arc<Mutex<Vec<Vec<u8>>>>,
+        fd_map: Arc<RwLock<HashMap<WasiFd, Fd>>>,
+    ) -> ProcSelf {
+        self.register_inner(process.pid(), process.finished.clone(), args, envs, fd_map)
+    }
+
+    fn register_inner(
+        &self,
+        pid: WasiProcessId,
+        finished: Arc<OwnedTaskStatus>,
+        args: Vec<String>,
+        envs: Arc<Mutex<Vec<Vec<u8>>>>,
+        fd_map: Arc<RwLock<HashMap<WasiFd, Fd>>>,
+    ) -> ProcSelf {
+        let token = Arc::new(());
+        let mut processes = self.processes.write().unwrap();
+        // Forget about the processes that exited while we are at it
+        processes.retain(|_, process| !process.exited());
+        processes.insert(
+            pid.raw(),
+            ProcState {
+                finished: finished.clone(),
+                owner: Arc::downgrade(&token),
+                args,
+                envs,
+                fd_map,
+            },
+        );
+        ProcSelf {
+            fs: self.clone(),
+            pid,
+            finished,
+            token,
+        }
+    }
+
+    fn lookup(&self, path: &Path) -> Result<ProcEntry, FsError> {
+        let path = path.strip_prefix("/").unwrap_or(path);
+        let components = path
+            .iter()
+            .map(|c| c.to_str().ok_or(FsError::EntryNotFound))
+            .collect::<Result<Vec<_>, _>>()?;
+        let (id, rest) = match components.as_slice() {
+            [] => return Ok(ProcEntry::Dir),
+            ["self"] => return Ok(ProcEntry::SelfLink),
+            [id, rest @ ..] => (id.parse::<u32>().map_err(|_| FsError::EntryNotFound)?, rest),
+        };
+        let processes = self.processes.read().unwrap();
+        let process = processes
+            .get(&id)
+            .filter(|process| !process.exited())
+            .ok_or(FsError::EntryNotFound)?;
+        match rest {
+            [] | ["fd"] => Ok(ProcEntry::Dir),
+            ["cmdline"] => Ok(ProcEntry::Cmdline(id)),
+            ["environ"] => Ok(ProcEntry::Environ(id)),
+            ["fd", fd] => {
+                let fd = fd.parse::<WasiFd>().map_err(|_| FsError::EntryNotFound)?;
+                if process.fd_map.read().unwrap().contains_key(&fd) {
+                    Ok(ProcEntry::Fd(id, fd))
+                } else {
+                    Err(FsError::EntryNotFound)
+                }
+            }
+            _ => Err(FsError::EntryNotFound),
+        }
+    }
+
+    /// The arguments of the process, each one terminated by a NUL byte
+    fn cmdline(&self, id: u32) -> Vec<u8> {
+        let mut ret = Vec::new();
+        if let Some(process) = self.processes.read().unwrap().get(&id) {
+            for arg in process.args.iter() {
+                ret.extend_from_slice(arg.as_bytes());
+                ret.push(0);
+            }
+        }
+        ret
+    }
+
+    /// The `KEY=VALUE` environment variables, each one terminated by a NUL byte
+    fn environ(&self, id: u32) -> Vec<u8> {
+        let mut ret = Vec::new();
+        if let Some(process) = self.processes.read().unwrap().get(&id) {
+            for env in process.envs.lock().unwrap().iter() {
+                ret.extend_from_slice(env);
+                ret.push(0);
+            }
+        }
+        ret
+    }
+
+    fn entry_metadata(&self, entry: &ProcEntry) -> Metadata {
+        let symlink = FileType {
+            symlink: true,
+            ..Default::default()
+        };
+        let (ft, len) = match entry {
+            ProcEntry::Dir => (FileType::new_dir(), 0),
+            ProcEntry::Cmdline(id) => (FileType::new_file(), self.cmdline(*id).len()),
+            ProcEntry::Environ(id) => (FileType::new_file(), self.environ(*id).len()),
+            ProcEntry::SelfLink | ProcEntry::Fd(..) => (symlink, 0),
+        };
+        Metadata {
+            ft,
+            accessed: 0,
+            created: 0,
+            modified: 0,
+            len: len as u64,
+        }
+    }
+}
+
+impl ProcSelf {
+    /// ID of the process, which names its directory below [`PROC_MOUNT`]
+    pub(crate) fn pid(&self) -> WasiProcessId {
+        self.pid
+    }
+
+    /// Replaces the arguments served by `cmdline`
+    pub(crate) fn set_args(&self, args: Vec<String>) {
+        if let Some(process) = self.fs.processes.write().unwrap().get_mut(&self.pid.raw()) {
+            process.args = args;
+        }
+    }
+
+    /// Serves the directory of the same process from another state, which
+    /// is the case when the process replaces its state
+    pub(crate) fn replace(
+        &self,
+        args: Vec<String>,
+        envs: Arc<Mutex<Vec<Vec<u8>>>>,
+        fd_map: Arc<RwLock<HashMap<WasiFd, Fd>>>,
+    ) -> ProcSelf {
+        self.fs
+            .register_inner(self.pid, self.finished.clone(), args, envs, fd_map)
+    }
+}
+
+impl Drop for ProcSelf {
+    fn drop(&mut self) {
+        if let Ok(mut processes) = self.fs.processes.write() {
+            let owned = processes
+                .get(&self.pid.raw())
+                .is_some_and(|process| process.owner.ptr_eq(&Arc::downgrade(&self.token)));
+            if owned {
+                processes.remove(&self.pid.raw());
+            }
+        }
+    }
+}
+
+impl FileSystem for ProcFileSystem {
+    fn readlink(&self, path: &Path) -> Result<PathBuf, FsError> {
+        let (id, fd) = match self.lookup(path)? {
+            ProcEntry::Fd(id, fd) => (id, fd),
+            // Only the `WasiFs` of the calling process knows where this leads
+            ProcEntry::SelfLink => return Err(FsError::EntryNotFound),
+            _ => return Err(FsError::InvalidInput),
+        };
+        let processes = self.processes.read().unwrap();
+        let process = processes.get(&id).ok_or(FsError::EntryNotFound)?;
+        let fd_map = process.fd_map.read().unwrap();
+        let fd = fd_map.get(&fd).ok_or(FsError::EntryNotFound)?;
+        let ino = fd.inode.ino().as_u64();
+        let guard = fd.inode.read();
+        let link = match guard.deref() {
+            Kind::File { path, .. } | Kind::Dir { path, .. } => return Ok(path.clone()),
+            Kind::Socket { .. } => format!("socket:[{ino}]"),
+            Kind::Pipe { .. } => format!("pipe:[{ino}]"),
+            _ => format!("anon_inode:[{}]", fd.inode.name),
+        };
+        Ok(PathBuf::from(link))
+    }
+
+    fn read_dir(&self, path: &Path) -> Result<ReadDir, FsError> {
+        if !matches!(self.lookup(path)?, ProcEntry::Dir) {
+            return Err(FsError::BaseNotDirectory);
+        }
+        let components = path
+            .strip_prefix("/")
+            .unwrap_or(path)
+            .iter()
+            .filter_map(|c| c.to_str())
+            .collect::<Vec<_>>();
+        let names = match components.as_slice() {
+            [] => {
+                let mut ids = self
+                    .processes
+                    .read()
+                    .unwrap()
+                    .iter()
+                    .filter(|(_, process)| !process.exited())
+                    .map(|(id, _)| *id)
+                    .collect::<Vec<_>>();
+                ids.sort_unstable();
+                std::iter::once("self".to_string())
+                    .chain(ids.into_iter().map(|id| id.to_string()))
+                    .collect()
+            }
+            [_] => vec![
+                "cmdline".to_string(),
+                "environ".to_string(),
+                "fd".to_string(),
+            ],
+            [id, _] => {
+                let id = id.parse::<u32>().map_err(|_| FsError::EntryNotFound)?;
+                let processes = self.processes.read().unwrap();
+                let process = processes
+                    .get(&id)
+                    .filter(|process| !process.exited())
+                    .ok_or(FsError::EntryNotFound)?;
+                let mut fds = process
+                    .fd_map
+                    .read()
+                    .unwrap()
+                    .keys()
+                    .copied()
+                    .collect::<Vec<_>>();
+                fds.sort_unstable();
+                fds.into_iter().map(|fd| fd.to_string()).collect()
+            }
+            _ => return Err(FsError::EntryNotFound),
+        };
+        let entries = names
+            .into_iter()
+            .map(|name| {
+                let path = path.join(name);
+                let metadata = self.lookup(&path).map(|entry| self.entry_metadata(&entry));
+                DirEntry { path, metadata }
+            })
+            .collect();
+        Ok(ReadDir::new(entries))
+    }
+
+    fn create_dir(&self, _path: &Path) -> Result<(), FsError> {
+        Err(FsError::PermissionDenied)
+    }
+
+    fn remove_dir(&self, _path: &Path) -> Result<(), FsError> {
+        Err(FsError::PermissionDenied)
+    }
+
+    fn rename<'a>(&'a self, _from: &'a Path, _to: &'a Path) -> BoxFuture<'a, Result<(), FsError>> {
+        Box::pin(async { Err(FsError::PermissionDenied) })
+    }
+
+    fn metadata(&self, path: &Path) -> Result<Metadata, FsError> {
+        let entry = self.lookup(path)?;
+        Ok(self.entry_metadata(&entry))
+    }
+
+    fn symlink_metadata(&self, path: &Path) -> Result<Metadata, FsError> {
+        self.metadata(path)
+    }
+
+    fn remove_file(&self, _path: &Path) -> Result<(), FsError> {
+        Err(FsError::PermissionDenied)
+    }
+
+    fn new_open_options(&self) -> OpenOptions {
+        OpenOptions::new(self)
+    }
+}
+
+impl FileOpener for ProcFileSystem {
+    fn open(
+        &self,
+        path: &Path,
+        conf: &OpenOptionsConfig,
+    ) -> Result<Box<dyn VirtualFile + Send + Sync + 'static>, FsError> {
+        if conf.would_mutate() {
+            return Err(FsError::PermissionDenied);
+        }
+        let contents = match self.lookup(path)? {
+            ProcEntry::Cmdline(id) => self.cmdline(id),
+            ProcEntry::Environ(id) => self.environ(id),
+            ProcEntry::Dir | ProcEntry::SelfLink | ProcEntry::Fd(..) => {
+                return Err(FsError::NotAFile)
+            }
+        };
+        Ok(Box::new(StaticFile::new(contents)))
+    }
+}
@@ -0,0 +1,104 @@
+//! A file system whose files spill to a scratch directory on the host, the
+//! directory is removed again once the file system is dropped.
+
+use std::path::{Path, PathBuf};
+
+use futures::future::BoxFuture;
+use tempfile::TempDir;
+use virtual_fs::{FileSystem, Metadata, OpenOptions, ReadDir, ScopedDirectoryFileSystem};
+
+#[derive(Debug)]
+pub(crate) struct TempDirFileSystem {
+    fs: ScopedDirectoryFileSystem,
+    // Declared after `fs` so that it is dropped (and deleted) last
+    dir: TempDir,
+}
+
+impl TempDirFileSystem {
+    /// Creates the scratch directory under `base`, which is created first
+    /// when it does not exist yet.
+    pub fn new_in(base: &Path, handle: tokio::runtime::Handle) -> std::io::Result<Self> {
+        std::fs::create_dir_all(base)?;
+        let dir = tempfile::Builder::new().prefix("wasix-").tempdir_in(base)?;
+        let fs = ScopedDirectoryFileSystem::new(
+            dir.path(),
+            virtual_fs::host_fs::FileSystem::new(handle),
+        );
+        Ok(Self { fs, dir })
+    }
+
+    /// Path of the scratch directory on the host
+    pub fn path(&self) -> &Path {
+        self.dir.path()
+    }
+}
+
+impl FileSystem for TempDirFileSystem {
+    fn readlink(&self, path: &Path) -> virtual_fs::Result<PathBuf> {
+        self.fs.readlink(path)
+    }
+
+    fn read_dir(&self, path: &Path) -> virtual_fs::Result<ReadDir> {
+        self.fs.read_dir(path)
+    }
+
+    fn create_dir(&self, path: &Path) -> virtual_fs::Result<()> {
+        self.fs.create_dir(path)
+    }
+
+    fn remove_dir(&self, path: &Path) -> virtual_fs::Result<()> {
+        self.fs.remove_dir(path)
+    }
+
+    fn rename<'a>(&'a self, from: &'a Path, to: &'a Path) -> BoxFuture<'a, virtual_fs::Result<()>> {
+        self.fs.rename(from, to)
+    }
+
+    fn metadata(&self, path: &Path) -> virtual_fs::Result<Metadata> {
+        self.fs.metadata(path)
+    }
+
+    fn symlink_metadata(&self, path: &Path) -> virtual_fs::Result<Metadata> {
+        self.fs.symlink_metadata(path)
+    }
+
+    fn remove_file(&self, path: &Path) -> virtual_fs::Result<()> {
+        self.fs.remove_file(path)
+    }
+
+    fn new_open_options(&self) -> OpenOptions {
+        self.fs.new_open_options()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use virtual_fs::AsyncWriteExt;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn files_spill_under_the_base_and_are_removed_on_drop() {
+        let base = tempfile::tempdir().unwrap();
+        let fs = TempDirFileSystem::new_in(base.path(), tokio::runtime::Handle::current()).unwrap();
+        assert_eq!(fs.path().parent(), Some(base.path()));
+
+        let mut file = fs
+            .new_open_options()
+            .create(true)
+            .write(true)
+            .open(Path::new("/hello.txt"))
+            .unwrap();
+        file.write_all(b"hello").await.unwrap();
+        file.flush().await.unwrap();
+        drop(file);
+        assert_eq!(
+            std::fs::read(fs.path().join("hello.txt")).unwrap(),
+            b"hello"
+        );
+
+        let dir = fs.path().to_path_buf();
+        drop(fs);
+        assert!(!dir.exists());
+    }
+}
@@ -0,0 +1,138 @@
+use std::sync::Arc;
+
+use futures::future::BoxFuture;
+use tokio::sync::Semaphore;
+
+use super::{DynHttpClient, HttpClient, HttpRequest, HttpResponse};
+
+/// A [`HttpClient`] that bounds the number of requests the wrapped client
+/// runs at the same time, requests beyond the limit wait until one of the
+/// in-flight requests completes.
+#[derive(Debug, Clone)]
+pub struct ConcurrencyLimitedHttpClient {
+    inner: DynHttpClient,
+    permits: Arc<Semaphore>,
+}
+
+impl ConcurrencyLimitedHttpClient {
+    /// Wraps `inner` so that at most `limit` requests are in flight at once
+    /// (a limit of zero is treated as one).
+    pub fn new(inner: DynHttpClient, limit: usize) -> Self {
+        Self {
+            inner,
+            permits: Arc::new(Semaphore::new(limit.max(1))),
+        }
+    }
+}
+
+impl HttpClient for ConcurrencyLimitedHttpClient {
+    fn request(&self, request: HttpRequest) -> BoxFuture<'_, Result<HttpResponse, anyhow::Error>> {
+        Box::pin(async move {
+            let _permit = self.permits.acquire().await?;
+            self.inner.request(request).await
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::atomic::{AtomicUsize, Ordering},
+        time::Duration,
+    };
+
+    use http::{HeaderMap, StatusCode};
+
+    use super::*;
+
+    #[derive(Debug, Default)]
+    struct CountingClient {
+        in_flight: AtomicUsize,
+        max_in_flight: AtomicUsize,
+    }
+
+    impl HttpClient for CountingClient {
+        fn request(
+            &self,
+            _request: HttpRequest,
+        ) -> BoxFuture<'_, Result<HttpResponse, anyhow::Error>> {
+            Box::pin(async move {
+                let in_flight = self.in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                self.max_in_flight.fetch_max(in_flight, Ordering::SeqCst);
+                tokio::time::sleep(Duration::from_millis(10)).await;
+                self.in_flight.fetch_sub(1, Ordering::SeqCst);
+
+                Ok(HttpResponse {
+                    body: None,
+                    redirected: false,
+                    status: StatusCode::OK,
+                    headers: HeaderMap::new(),
+                })
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn in_flight_requests_never_exceed_the_limit() {
+        let counting = Arc::new(CountingClient::default());
+        let client = ConcurrencyLimitedHttpClient::new(counting.clone(), 3);
+
+        let requests = (0..20).map(|_| {
+            client.request(
+                http::Request::get("https://example.com/")
+                    .body(())
+                    .unwrap()
+                    .into(),
+            )
+        });
+        let responses = futures::future::join_all(requests).await;
+
+        assert!(responses.iter().all(|response| response.is_ok()));
+        assert_eq!(counting.in_flight.load(Ordering::SeqCst), 0);
+        assert_eq!(counting.max_in_flight.load(Ordering::SeqCst), 3);
+    }
+
+    async fn max_in_flight(client: &DynHttpClient, counting: &CountingClient) -> usize {
+        let requests = (0..20).map(|_| {
+            client.request(
+                http::Request::get("https://example.com/")
+                    .body(())
+                    .unwrap()
+                    .into(),
+            )
+        });
+        let responses = futures::future::join_all(requests).await;
+
+        assert!(responses.iter().all(|response| response.is_ok()));
+        counting.max_in_flight.swap(0, Ordering::SeqCst)
+    }
+
+    #[cfg(feature = "sys-thread")]
+    #[tokio::test]
+    async fn runtime_limit_applies_to_the_client_set_afterwards() {
+        use crate::runtime::{task_manager::tokio::TokioTaskManager, PluggableRuntime, Runtime};
+
+        let counting = Arc::new(CountingClient::default());
+        let mut runtime = PluggableRuntime::new(Arc::new(TokioTaskManager::default()));
+        runtime.set_http_concurrency_limit(3);
+        runtime.set_http_client(counting.clone());
+
+        let client = runtime.http_client().unwrap();
+        assert_eq!(max_in_flight(client, &counting).await, 3);
+    }
+
+    #[cfg(feature = "sys-thread")]
+    #[tokio::test]
+    async fn runtime_limit_replaces_the_previous_one() {
+        use crate::runtime::{task_manager::tokio::TokioTaskManager, PluggableRuntime, Runtime};
+
+        let counting = Arc::new(CountingClient::default());
+        let mut runtime = PluggableRuntime::new(Arc::new(TokioTaskManager::default()));
+        runtime.set_http_client(counting.clone());
+        runtime.set_http_concurrency_limit(2);
+        runtime.set_http_concurrency_limit(4);
+
+        let client = runtime.http_client().unwrap();
+        assert_eq!(max_in_flight(client, &counting).await, 4);
+    }
+}
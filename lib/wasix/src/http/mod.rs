@@ -1,4 +1,5 @@
 mod client;
+mod limit;
 
 #[cfg(feature = "host-reqwest")]
 pub mod reqwest;
@@ -9,7 +10,7 @@ mod web_http_client;
 #[cfg(feature = "js")]
 pub use self::web_http_client::WebHttpClient;
 
-pub use self::client::*;
+pub use self::{client::*, limit::ConcurrencyLimitedHttpClient};
 
 pub(crate) const USER_AGENT: &str = concat!(env!("CARGO_PKG_NAME"), "-", env!("CARGO_PKG_VERSION"));
 
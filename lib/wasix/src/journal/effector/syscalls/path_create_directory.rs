@@ -30,14 +30,19 @@ impl JournalEffector {
         if fd == VIRTUAL_ROOT_FD {
             ctx.data().state.fs.root_fs.create_dir(Path::new(path))?;
         } else {
-            crate::syscalls::path_create_directory_internal(ctx, fd, path).map_err(|err| {
-                anyhow::format_err!(
+            // The directory may already exist when the journal is replayed
+            // on top of a file system that persisted it
+            match crate::syscalls::path_create_directory_internal(ctx, fd, path) {
+                Ok(()) | Err(Errno::Exist) => {}
+                Err(err) => {
+                    return Err(anyhow::format_err!(
                     "journal restore error: failed to create directory path (fd={}, path={}) - {}",
                     fd,
                     path,
                     err
-                )
-            })?;
+                ))
+                }
+            }
         }
         Ok(())
     }
@@ -60,6 +60,7 @@ impl JournalEffector {
                     no_delay: None,
                     keep_alive: None,
                     dont_route: None,
+                    ttl: None,
                     send_buf_size: None,
                     recv_buf_size: None,
                     write_timeout: None,
@@ -67,8 +67,8 @@ pub use wasmer;
 pub use wasmer_wasix_types;
 
 use wasmer::{
-    imports, namespace, AsStoreMut, Exports, FunctionEnv, Imports, Memory32, MemoryAccessError,
-    MemorySize, RuntimeError,
+    imports, namespace, AsStoreMut, Exports, Extern, Function, FunctionEnv, FunctionEnvMut,
+    Imports, Memory32, MemoryAccessError, MemorySize, RuntimeError, Type, Value,
 };
 
 pub use virtual_fs;
@@ -84,11 +84,14 @@ pub use virtual_net::{
 use wasmer_wasix_types::wasi::{Errno, ExitCode};
 
 pub use crate::{
-    fs::{default_fs_backing, Fd, WasiFs, WasiInodes, VIRTUAL_ROOT_FD},
+    fs::{default_fs_backing, Fd, SharedFileSystem, WasiFs, WasiInodes, VIRTUAL_ROOT_FD},
     os::{
+        command::{SpawnHandler, SpawnStdio},
         task::{
+            clock::{ScriptedClock, WasiClock},
             control_plane::WasiControlPlane,
-            process::{WasiProcess, WasiProcessId},
+            process::{FutexStats, WasiProcess, WasiProcessId, MAX_TRACKED_FUTEXES},
+            signal::SignalDisposition,
             thread::{WasiThread, WasiThreadError, WasiThreadHandle, WasiThreadId},
         },
         WasiTtyState,
@@ -96,8 +99,9 @@ pub use crate::{
     rewind::*,
     runtime::{task_manager::VirtualTaskManager, PluggableRuntime, Runtime},
     state::{
-        WasiEnv, WasiEnvBuilder, WasiEnvInit, WasiFunctionEnv, WasiInstanceHandles,
-        WasiStateCreationError, ALL_RIGHTS,
+        ClosedStdin, StdoutBuffering, SyscallStep, UnattachedStdout, UnimplementedSyscall,
+        UnimplementedSyscallHandler, WasiEnv, WasiEnvBuilder, WasiEnvInit, WasiFunctionEnv,
+        WasiInstanceHandles, WasiStateCreationError, ALL_RIGHTS,
     },
     syscalls::{journal::wait_for_snapshot, rewind, rewind_ext, types, unwind},
     utils::is_wasix_module,
@@ -118,6 +122,10 @@ pub enum WasiError {
     DeepSleep(DeepSleepWork),
     #[error("The WASI version could not be determined")]
     UnknownWasiVersion,
+    #[error("WASI syscall `{0}` is not allowed")]
+    SyscallDenied(String),
+    #[error("WASI instruction limit exceeded")]
+    InstructionLimitExceeded,
 }
 
 pub type WasiResult<T> = Result<Result<T, Errno>, WasiError>;
@@ -350,14 +358,18 @@ pub fn generate_import_object_from_env(
     ctx: &FunctionEnv<WasiEnv>,
     version: WasiVersion,
 ) -> Imports {
-    match version {
+    let mut imports = match version {
         WasiVersion::Snapshot0 => generate_import_object_snapshot0(store, ctx),
         WasiVersion::Snapshot1 | WasiVersion::Latest => {
             generate_import_object_snapshot1(store, ctx)
         }
         WasiVersion::Wasix32v1 => generate_import_object_wasix32_v1(store, ctx),
         WasiVersion::Wasix64v1 => generate_import_object_wasix64_v1(store, ctx),
-    }
+    };
+    apply_syscall_allowlist(store, ctx, &mut imports);
+    apply_errno_remap(store, ctx, &mut imports);
+    apply_step_mode(store, ctx, &mut imports);
+    imports
 }
 
 fn wasi_exports_generic(mut store: &mut impl AsStoreMut, env: &FunctionEnv<WasiEnv>) -> Exports {
@@ -515,6 +527,8 @@ fn wasix_exports_32(mut store: &mut impl AsStoreMut, env: &FunctionEnv<WasiEnv>)
         "fd_tell" => Function::new_typed_with_env(&mut store, env, fd_tell::<Memory32>),
         "fd_write" => Function::new_typed_with_env(&mut store, env, fd_write::<Memory32>),
         "fd_pipe" => Function::new_typed_with_env(&mut store, env, fd_pipe::<Memory32>),
+        "fd_bytes_available" => Function::new_typed_with_env(&mut store, env, fd_bytes_available::<Memory32>),
+        "syscall_batch" => Function::new_typed_with_env(&mut store, env, syscall_batch::<Memory32>),
         "path_create_directory" => Function::new_typed_with_env(&mut store, env, path_create_directory::<Memory32>),
         "path_filestat_get" => Function::new_typed_with_env(&mut store, env, path_filestat_get::<Memory32>),
         "path_filestat_set_times" => Function::new_typed_with_env(&mut store, env, path_filestat_set_times::<Memory32>),
@@ -536,6 +550,11 @@ fn wasix_exports_32(mut store: &mut impl AsStoreMut, env: &FunctionEnv<WasiEnv>)
         "proc_spawn" => Function::new_typed_with_env(&mut store, env, proc_spawn::<Memory32>),
         "proc_id" => Function::new_typed_with_env(&mut store, env, proc_id::<Memory32>),
         "proc_parent" => Function::new_typed_with_env(&mut store, env, proc_parent::<Memory32>),
+        "proc_getpgid" => Function::new_typed_with_env(&mut store, env, proc_getpgid::<Memory32>),
+        "proc_setpgid" => Function::new_typed_with_env(&mut store, env, proc_setpgid),
+        "proc_getsid" => Function::new_typed_with_env(&mut store, env, proc_getsid::<Memory32>),
+        "proc_setsid" => Function::new_typed_with_env(&mut store, env, proc_setsid::<Memory32>),
+        "proc_signal_group" => Function::new_typed_with_env(&mut store, env, proc_signal_group),
         "random_get" => Function::new_typed_with_env(&mut store, env, random_get::<Memory32>),
         "tty_get" => Function::new_typed_with_env(&mut store, env, tty_get::<Memory32>),
         "tty_set" => Function::new_typed_with_env(&mut store, env, tty_set::<Memory32>),
@@ -572,6 +591,7 @@ fn wasix_exports_32(mut store: &mut impl AsStoreMut, env: &FunctionEnv<WasiEnv>)
         "sock_status" => Function::new_typed_with_env(&mut store, env, sock_status::<Memory32>),
         "sock_addr_local" => Function::new_typed_with_env(&mut store, env, sock_addr_local::<Memory32>),
         "sock_addr_peer" => Function::new_typed_with_env(&mut store, env, sock_addr_peer::<Memory32>),
+        "sock_peer_pid" => Function::new_typed_with_env(&mut store, env, sock_peer_pid::<Memory32>),
         "sock_open" => Function::new_typed_with_env(&mut store, env, sock_open::<Memory32>),
         "sock_set_opt_flag" => Function::new_typed_with_env(&mut store, env, sock_set_opt_flag),
         "sock_get_opt_flag" => Function::new_typed_with_env(&mut store, env, sock_get_opt_flag::<Memory32>),
@@ -636,6 +656,8 @@ fn wasix_exports_64(mut store: &mut impl AsStoreMut, env: &FunctionEnv<WasiEnv>)
         "fd_tell" => Function::new_typed_with_env(&mut store, env, fd_tell::<Memory64>),
         "fd_write" => Function::new_typed_with_env(&mut store, env, fd_write::<Memory64>),
         "fd_pipe" => Function::new_typed_with_env(&mut store, env, fd_pipe::<Memory64>),
+        "fd_bytes_available" => Function::new_typed_with_env(&mut store, env, fd_bytes_available::<Memory64>),
+        "syscall_batch" => Function::new_typed_with_env(&mut store, env, syscall_batch::<Memory64>),
         "path_create_directory" => Function::new_typed_with_env(&mut store, env, path_create_directory::<Memory64>),
         "path_filestat_get" => Function::new_typed_with_env(&mut store, env, path_filestat_get::<Memory64>),
         "path_filestat_set_times" => Function::new_typed_with_env(&mut store, env, path_filestat_set_times::<Memory64>),
@@ -657,6 +679,11 @@ fn wasix_exports_64(mut store: &mut impl AsStoreMut, env: &FunctionEnv<WasiEnv>)
         "proc_spawn" => Function::new_typed_with_env(&mut store, env, proc_spawn::<Memory64>),
         "proc_id" => Function::new_typed_with_env(&mut store, env, proc_id::<Memory64>),
         "proc_parent" => Function::new_typed_with_env(&mut store, env, proc_parent::<Memory64>),
+        "proc_getpgid" => Function::new_typed_with_env(&mut store, env, proc_getpgid::<Memory64>),
+        "proc_setpgid" => Function::new_typed_with_env(&mut store, env, proc_setpgid),
+        "proc_getsid" => Function::new_typed_with_env(&mut store, env, proc_getsid::<Memory64>),
+        "proc_setsid" => Function::new_typed_with_env(&mut store, env, proc_setsid::<Memory64>),
+        "proc_signal_group" => Function::new_typed_with_env(&mut store, env, proc_signal_group),
         "random_get" => Function::new_typed_with_env(&mut store, env, random_get::<Memory64>),
         "tty_get" => Function::new_typed_with_env(&mut store, env, tty_get::<Memory64>),
         "tty_set" => Function::new_typed_with_env(&mut store, env, tty_set::<Memory64>),
@@ -693,6 +720,7 @@ fn wasix_exports_64(mut store: &mut impl AsStoreMut, env: &FunctionEnv<WasiEnv>)
         "sock_status" => Function::new_typed_with_env(&mut store, env, sock_status::<Memory64>),
         "sock_addr_local" => Function::new_typed_with_env(&mut store, env, sock_addr_local::<Memory64>),
         "sock_addr_peer" => Function::new_typed_with_env(&mut store, env, sock_addr_peer::<Memory64>),
+        "sock_peer_pid" => Function::new_typed_with_env(&mut store, env, sock_peer_pid::<Memory64>),
         "sock_open" => Function::new_typed_with_env(&mut store, env, sock_open::<Memory64>),
         "sock_set_opt_flag" => Function::new_typed_with_env(&mut store, env, sock_set_opt_flag),
         "sock_get_opt_flag" => Function::new_typed_with_env(&mut store, env, sock_get_opt_flag::<Memory64>),
@@ -737,7 +765,7 @@ fn stub_initializer(
 // TODO: split function into two variants, one for JS and one for sys.
 // (this will make code less messy)
 fn import_object_for_all_wasi_versions(
-    _module: &wasmer::Module,
+    module: &wasmer::Module,
     store: &mut impl AsStoreMut,
     env: &FunctionEnv<WasiEnv>,
 ) -> (Imports, ModuleInitializer) {
@@ -756,12 +784,204 @@ fn import_object_for_all_wasi_versions(
         "wasix_32v1" => exports_wasix_32v1,
         "wasix_64v1" => exports_wasix_64v1,
     };
+    apply_syscall_allowlist(store, env, &mut imports);
+    apply_unimplemented_syscall(module, store, env, &mut imports);
+    apply_errno_remap(store, env, &mut imports);
+    apply_step_mode(store, env, &mut imports);
 
     let init = Box::new(stub_initializer) as ModuleInitializer;
 
     (imports, init)
 }
 
+/// Replaces every syscall that is not part of the allowlist of the environment
+/// (if it has one) with a function that terminates the guest when it is called
+fn apply_syscall_allowlist(
+    store: &mut impl AsStoreMut,
+    env: &FunctionEnv<WasiEnv>,
+    imports: &mut Imports,
+) {
+    let allowlist = match env.as_ref(&*store).syscall_allowlist.clone() {
+        Some(allowlist) => allowlist,
+        None => return,
+    };
+
+    let denied = imports
+        .iter()
+        .filter(|(_, name, _)| !allowlist.contains(*name))
+        .filter_map(|(namespace, name, export)| match export {
+            Extern::Function(func) => {
+                Some((namespace.to_string(), name.to_string(), func.ty(&*store)))
+            }
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+
+    for (namespace, name, ty) in denied {
+        let syscall = name.clone();
+        let func = Function::new(&mut *store, ty, move |_| {
+            tracing::warn!("denied call to the syscall `{}`", syscall);
+            Err(RuntimeError::user(Box::new(WasiError::SyscallDenied(
+                syscall.clone(),
+            ))))
+        });
+        imports.define(&namespace, &name, func);
+    }
+}
+
+/// Defines the syscalls that the module imports but that are not implemented
+/// with the fallback of the environment (if it has one), so that the module
+/// links and calling them returns an errno instead
+fn apply_unimplemented_syscall(
+    module: &wasmer::Module,
+    store: &mut impl AsStoreMut,
+    env: &FunctionEnv<WasiEnv>,
+    imports: &mut Imports,
+) {
+    let fallback = match env.as_ref(&*store).unimplemented_syscall.clone() {
+        Some(fallback) => fallback,
+        None => return,
+    };
+
+    let missing = module
+        .imports()
+        .functions()
+        .filter(|import| {
+            matches!(
+                import.module(),
+                "wasi" | "wasi_unstable" | "wasi_snapshot_preview1" | "wasix_32v1" | "wasix_64v1"
+            )
+        })
+        .filter(|import| !imports.exists(import.module(), import.name()))
+        .collect::<Vec<_>>();
+
+    for import in missing {
+        let namespace = import.module().to_string();
+        let syscall = import.name().to_string();
+        let ty = import.ty().clone();
+        let fallback = fallback.clone();
+        let func = Function::new(&mut *store, ty.clone(), move |_| {
+            let errno = match &fallback {
+                UnimplementedSyscall::Errno(errno) => {
+                    tracing::warn!("call to the unimplemented syscall `{}`", syscall);
+                    *errno
+                }
+                UnimplementedSyscall::Handler(handler) => handler(&namespace, &syscall),
+            };
+
+            // The errno is returned in the first result (if there is one),
+            // all the other results are zeroed
+            Ok(ty
+                .results()
+                .iter()
+                .enumerate()
+                .map(|(n, ty)| match ty {
+                    Type::I32 if n == 0 => Value::I32(errno as i32),
+                    Type::I32 => Value::I32(0),
+                    Type::I64 => Value::I64(0),
+                    Type::F32 => Value::F32(0.0),
+                    Type::F64 => Value::F64(0.0),
+                    Type::V128 => Value::V128(0),
+                    Type::ExternRef => Value::ExternRef(None),
+                    Type::FuncRef => Value::FuncRef(None),
+                })
+                .collect())
+        });
+        imports.define(import.module(), import.name(), func);
+    }
+}
+
+/// Syscalls that return an `i32` which is not an errno, `thread-spawn`
+/// returns the id of the new thread (or a negated errno)
+const NON_ERRNO_SYSCALLS: &[(&str, &str)] = &[("wasi", "thread-spawn")];
+
+/// Wraps every syscall that returns an errno so that the errnos found in the
+/// remap table of the environment (if it has one) are presented to the guest
+/// as the errno they are mapped to
+fn apply_errno_remap(
+    store: &mut impl AsStoreMut,
+    env: &FunctionEnv<WasiEnv>,
+    imports: &mut Imports,
+) {
+    let remap = env.as_ref(&*store).errno_remap.clone();
+    if remap.is_empty() {
+        return;
+    }
+
+    let syscalls = imports
+        .iter()
+        .filter(|(namespace, name, _)| !NON_ERRNO_SYSCALLS.contains(&(*namespace, *name)))
+        .filter_map(|(namespace, name, export)| match export {
+            Extern::Function(func) if func.ty(&*store).results() == [Type::I32] => {
+                Some((namespace.to_string(), name.to_string(), func.clone()))
+            }
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+
+    for (namespace, name, syscall) in syscalls {
+        let ty = syscall.ty(&*store);
+        let remap = remap.clone();
+        let func = Function::new_with_env(
+            &mut *store,
+            env,
+            ty,
+            move |mut ctx: FunctionEnvMut<'_, WasiEnv>, args| {
+                let mut results = syscall.call(&mut ctx, args)?.into_vec();
+                if let Some(Value::I32(ret)) = results.first_mut() {
+                    let remapped = u16::try_from(*ret)
+                        .ok()
+                        .and_then(|errno| Errno::try_from(errno).ok())
+                        .and_then(|errno| remap.get(&errno));
+                    if let Some(errno) = remapped {
+                        *ret = *errno as i32;
+                    }
+                }
+                Ok(results)
+            },
+        );
+        imports.define(&namespace, &name, func);
+    }
+}
+
+/// Wraps every syscall so that a guest running in step mode is paused before
+/// each of them until the host decides whether the syscall is executed
+fn apply_step_mode(store: &mut impl AsStoreMut, env: &FunctionEnv<WasiEnv>, imports: &mut Imports) {
+    let steps = match env.as_ref(&*store).syscall_steps.clone() {
+        Some(steps) => steps,
+        None => return,
+    };
+
+    let syscalls = imports
+        .iter()
+        .filter_map(|(namespace, name, export)| match export {
+            Extern::Function(func) => Some((namespace.to_string(), name.to_string(), func.clone())),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+
+    for (namespace, name, syscall) in syscalls {
+        let ty = syscall.ty(&*store);
+        let steps = steps.clone();
+        let (syscall_namespace, syscall_name) = (namespace.clone(), name.clone());
+        let func = Function::new_with_env(
+            &mut *store,
+            env,
+            ty,
+            move |mut ctx: FunctionEnvMut<'_, WasiEnv>, args| {
+                let (namespace, name) = (&syscall_namespace, &syscall_name);
+                if !SyscallStep::wait(&steps, namespace, name, args) {
+                    return Err(RuntimeError::user(Box::new(WasiError::SyscallDenied(
+                        name.clone(),
+                    ))));
+                }
+                Ok(syscall.call(&mut ctx, args)?.into_vec())
+            },
+        );
+        imports.define(&namespace, &name, func);
+    }
+}
+
 /// Combines a state generating function with the import list for legacy WASI
 fn generate_import_object_snapshot0(
     store: &mut impl AsStoreMut,
@@ -0,0 +1,728 @@
+//! Networking that degrades the sockets of another implementation by holding
+//! back their reads and writes for a while, this is used to test how guests
+//! behave on slow networks
+
+use std::{
+    mem::MaybeUninit,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, Shutdown, SocketAddr},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    task::{Context, Poll, Waker},
+    time::Duration,
+};
+
+use derivative::Derivative;
+use rand::Rng;
+use virtual_mio::{InterestHandler, InterestType};
+use virtual_net::{
+    DynVirtualNetworking, IpCidr, IpRoute, NetworkError, Result, SocketStatus, StreamSecurity,
+    VirtualConnectedSocket, VirtualConnectionlessSocket, VirtualIcmpSocket, VirtualIoSource,
+    VirtualNetworking, VirtualRawSocket, VirtualSocket, VirtualTcpListener, VirtualTcpSocket,
+    VirtualUdpSocket,
+};
+
+use crate::VirtualTaskManager;
+
+/// Wraps another networking implementation and delays every read and write
+/// of its TCP and UDP sockets by `latency` plus a random amount of up to
+/// `jitter`. The delays are timed with the task manager.
+///
+/// Received data is held back from the moment it arrives, sends are held
+/// back from the moment they are attempted.
+#[derive(Debug)]
+pub struct ChaosNetworking {
+    inner: DynVirtualNetworking,
+    config: Arc<ChaosConfig>,
+}
+
+impl ChaosNetworking {
+    pub fn new<I>(inner: I, tasks: Arc<dyn VirtualTaskManager>) -> Self
+    where
+        I: VirtualNetworking + Sync,
+    {
+        Self {
+            inner: Arc::new(inner),
+            config: Arc::new(ChaosConfig {
+                tasks,
+                latency: Duration::ZERO,
+                jitter: Duration::ZERO,
+            }),
+        }
+    }
+
+    /// Sets the delay that is applied to every operation
+    pub fn with_latency(mut self, latency: Duration) -> Self {
+        Arc::make_mut(&mut self.config).latency = latency;
+        self
+    }
+
+    /// Sets the upper limit of the random delay that is added on top of the
+    /// latency of every operation
+    pub fn with_jitter(mut self, jitter: Duration) -> Self {
+        Arc::make_mut(&mut self.config).jitter = jitter;
+        self
+    }
+}
+
+#[derive(Derivative, Clone)]
+#[derivative(Debug)]
+struct ChaosConfig {
+    #[derivative(Debug = "ignore")]
+    tasks: Arc<dyn VirtualTaskManager>,
+    latency: Duration,
+    jitter: Duration,
+}
+
+impl ChaosConfig {
+    /// Starts a delay that notifies `handler` of `interest` once it elapsed,
+    /// the returned flag is set at that point
+    fn start_delay(&self, handler: &ChaosHandler, interest: InterestType) -> Arc<AtomicBool> {
+        let mut delay = self.latency;
+        if !self.jitter.is_zero() {
+            delay += rand::thread_rng().gen_range(Duration::ZERO..=self.jitter);
+        }
+        if delay.is_zero() {
+            return Arc::new(AtomicBool::new(true));
+        }
+
+        let elapsed = Arc::new(AtomicBool::new(false));
+        let sleep = self.tasks.sleep_now(delay);
+        let task_elapsed = elapsed.clone();
+        let mut handler = handler.clone();
+        let spawned = self.tasks.task_shared(Box::new(move || {
+            Box::pin(async move {
+                sleep.await;
+                task_elapsed.store(true, Ordering::Release);
+                handler.push_interest(interest);
+            })
+        }));
+        // Without a task to time the delay the operation is not held back
+        if spawned.is_err() {
+            elapsed.store(true, Ordering::Release);
+        }
+        elapsed
+    }
+}
+
+/// One direction of a socket that is held back by a delay
+#[derive(Debug, Default)]
+struct ChaosDelay(Option<Arc<AtomicBool>>);
+
+impl ChaosDelay {
+    /// Starts the delay (unless it already runs) and returns true once it
+    /// has elapsed
+    fn elapsed(
+        &mut self,
+        config: &ChaosConfig,
+        handler: &ChaosHandler,
+        interest: InterestType,
+    ) -> bool {
+        self.0
+            .get_or_insert_with(|| config.start_delay(handler, interest))
+            .load(Ordering::Acquire)
+    }
+
+    /// The next operation is held back by a new delay
+    fn reset(&mut self) {
+        self.0 = None;
+    }
+}
+
+/// Handler of a socket that is notified both by the socket it wraps and by
+/// the delays once they elapse
+#[derive(Debug, Clone, Default)]
+struct ChaosHandler {
+    state: Arc<Mutex<ChaosHandlerState>>,
+}
+
+#[derive(Derivative, Default)]
+#[derivative(Debug)]
+struct ChaosHandlerState {
+    #[derivative(Debug = "ignore")]
+    handler: Option<Box<dyn InterestHandler + Send + Sync>>,
+    /// Wakers of the readiness polls that are waiting on a delay
+    wakers: Vec<Waker>,
+}
+
+impl ChaosHandler {
+    fn set(&self, handler: Box<dyn InterestHandler + Send + Sync>) {
+        self.state.lock().unwrap().handler = Some(handler);
+    }
+
+    fn clear(&self) {
+        self.state.lock().unwrap().handler = None;
+    }
+
+    fn add_waker(&self, waker: &Waker) {
+        let mut state = self.state.lock().unwrap();
+        if !state.wakers.iter().any(|w| w.will_wake(waker)) {
+            state.wakers.push(waker.clone());
+        }
+    }
+}
+
+impl InterestHandler for ChaosHandler {
+    fn push_interest(&mut self, interest: InterestType) {
+        let mut state = self.state.lock().unwrap();
+        state.wakers.drain(..).for_each(Waker::wake);
+        if let Some(handler) = state.handler.as_mut() {
+            handler.push_interest(interest);
+        }
+    }
+
+    fn pop_interest(&mut self, interest: InterestType) -> bool {
+        let mut state = self.state.lock().unwrap();
+        match state.handler.as_mut() {
+            Some(handler) => handler.pop_interest(interest),
+            None => false,
+        }
+    }
+
+    fn has_interest(&self, interest: InterestType) -> bool {
+        let state = self.state.lock().unwrap();
+        match state.handler.as_ref() {
+            Some(handler) => handler.has_interest(interest),
+            None => false,
+        }
+    }
+}
+
+fn as_uninit(buf: &mut [u8]) -> &mut [MaybeUninit<u8>] {
+    // SAFETY: initialized bytes are valid `MaybeUninit<u8>` and nothing but
+    // initialized bytes are written through the returned slice
+    unsafe { &mut *(buf as *mut [u8] as *mut [MaybeUninit<u8>]) }
+}
+
+/// Copies as much of `data` as fits into `buf` and returns the amount
+fn copy_into(data: &[u8], buf: &mut [MaybeUninit<u8>]) -> usize {
+    let amt = data.len().min(buf.len());
+    for (dst, src) in buf.iter_mut().zip(&data[..amt]) {
+        dst.write(*src);
+    }
+    amt
+}
+
+#[async_trait::async_trait]
+impl VirtualNetworking for ChaosNetworking {
+    fn is_supported(&self) -> bool {
+        self.inner.is_supported()
+    }
+
+    async fn bridge(
+        &self,
+        network: &str,
+        access_token: &str,
+        security: StreamSecurity,
+    ) -> Result<()> {
+        self.inner.bridge(network, access_token, security).await
+    }
+
+    async fn unbridge(&self) -> Result<()> {
+        self.inner.unbridge().await
+    }
+
+    async fn dhcp_acquire(&self) -> Result<Vec<IpAddr>> {
+        self.inner.dhcp_acquire().await
+    }
+
+    async fn ip_add(&self, ip: IpAddr, prefix: u8) -> Result<()> {
+        self.inner.ip_add(ip, prefix).await
+    }
+
+    async fn ip_remove(&self, ip: IpAddr) -> Result<()> {
+        self.inner.ip_remove(ip).await
+    }
+
+    async fn ip_clear(&self) -> Result<()> {
+        self.inner.ip_clear().await
+    }
+
+    async fn ip_list(&self) -> Result<Vec<IpCidr>> {
+        self.inner.ip_list().await
+    }
+
+    async fn mac(&self) -> Result<[u8; 6]> {
+        self.inner.mac().await
+    }
+
+    async fn gateway_set(&self, ip: IpAddr) -> Result<()> {
+        self.inner.gateway_set(ip).await
+    }
+
+    async fn route_add(
+        &self,
+        cidr: IpCidr,
+        via_router: IpAddr,
+        preferred_until: Option<Duration>,
+        expires_at: Option<Duration>,
+    ) -> Result<()> {
+        self.inner
+            .route_add(cidr, via_router, preferred_until, expires_at)
+            .await
+    }
+
+    async fn route_remove(&self, cidr: IpAddr) -> Result<()> {
+        self.inner.route_remove(cidr).await
+    }
+
+    async fn route_clear(&self) -> Result<()> {
+        self.inner.route_clear().await
+    }
+
+    async fn route_list(&self) -> Result<Vec<IpRoute>> {
+        self.inner.route_list().await
+    }
+
+    async fn bind_raw(&self) -> Result<Box<dyn VirtualRawSocket + Sync>> {
+        self.inner.bind_raw().await
+    }
+
+    async fn listen_tcp(
+        &self,
+        addr: SocketAddr,
+        only_v6: bool,
+        reuse_port: bool,
+        reuse_addr: bool,
+    ) -> Result<Box<dyn VirtualTcpListener + Sync>> {
+        let inner = self
+            .inner
+            .listen_tcp(addr, only_v6, reuse_port, reuse_addr)
+            .await?;
+        Ok(Box::new(ChaosTcpListener {
+            inner,
+            config: self.config.clone(),
+        }))
+    }
+
+    async fn bind_udp(
+        &self,
+        addr: SocketAddr,
+        reuse_port: bool,
+        reuse_addr: bool,
+    ) -> Result<Box<dyn VirtualUdpSocket + Sync>> {
+        let inner = self.inner.bind_udp(addr, reuse_port, reuse_addr).await?;
+        Ok(Box::new(ChaosUdpSocket::new(inner, self.config.clone())))
+    }
+
+    async fn bind_icmp(&self, addr: IpAddr) -> Result<Box<dyn VirtualIcmpSocket + Sync>> {
+        self.inner.bind_icmp(addr).await
+    }
+
+    async fn connect_tcp(
+        &self,
+        addr: SocketAddr,
+        peer: SocketAddr,
+    ) -> Result<Box<dyn VirtualTcpSocket + Sync>> {
+        let inner = self.inner.connect_tcp(addr, peer).await?;
+        Ok(Box::new(ChaosTcpSocket::new(inner, self.config.clone())))
+    }
+
+    async fn resolve(
+        &self,
+        host: &str,
+        port: Option<u16>,
+        dns_server: Option<IpAddr>,
+    ) -> Result<Vec<IpAddr>> {
+        self.inner.resolve(host, port, dns_server).await
+    }
+}
+
+/// Listener whose connections are degraded as well
+#[derive(Debug)]
+struct ChaosTcpListener {
+    inner: Box<dyn VirtualTcpListener + Sync>,
+    config: Arc<ChaosConfig>,
+}
+
+impl VirtualIoSource for ChaosTcpListener {
+    fn remove_handler(&mut self) {
+        self.inner.remove_handler()
+    }
+
+    fn poll_read_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<usize>> {
+        self.inner.poll_read_ready(cx)
+    }
+
+    fn poll_write_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<usize>> {
+        self.inner.poll_write_ready(cx)
+    }
+}
+
+impl VirtualTcpListener for ChaosTcpListener {
+    fn try_accept(&mut self) -> Result<(Box<dyn VirtualTcpSocket + Sync>, SocketAddr)> {
+        let (socket, addr) = self.inner.try_accept()?;
+        let socket = ChaosTcpSocket::new(socket, self.config.clone());
+        Ok((Box::new(socket), addr))
+    }
+
+    fn set_handler(&mut self, handler: Box<dyn InterestHandler + Send + Sync>) -> Result<()> {
+        self.inner.set_handler(handler)
+    }
+
+    fn addr_local(&self) -> Result<SocketAddr> {
+        self.inner.addr_local()
+    }
+
+    fn set_ttl(&mut self, ttl: u8) -> Result<()> {
+        self.inner.set_ttl(ttl)
+    }
+
+    fn ttl(&self) -> Result<u8> {
+        self.inner.ttl()
+    }
+}
+
+#[derive(Debug)]
+struct ChaosTcpSocket {
+    inner: Box<dyn VirtualTcpSocket + Sync>,
+    config: Arc<ChaosConfig>,
+    handler: ChaosHandler,
+    recv_delay: ChaosDelay,
+    send_delay: ChaosDelay,
+    /// Data that was received but is still held back (and how much of it
+    /// was read already)
+    received: Option<(Vec<u8>, usize)>,
+}
+
+impl ChaosTcpSocket {
+    fn new(inner: Box<dyn VirtualTcpSocket + Sync>, config: Arc<ChaosConfig>) -> Self {
+        Self {
+            inner,
+            config,
+            handler: Default::default(),
+            recv_delay: Default::default(),
+            send_delay: Default::default(),
+            received: None,
+        }
+    }
+}
+
+impl VirtualIoSource for ChaosTcpSocket {
+    fn remove_handler(&mut self) {
+        self.handler.clear();
+        self.inner.remove_handler()
+    }
+
+    fn poll_read_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<usize>> {
+        match &self.received {
+            Some((data, read)) => {
+                if self
+                    .recv_delay
+                    .elapsed(&self.config, &self.handler, InterestType::Readable)
+                {
+                    return Poll::Ready(Ok(data.len() - read));
+                }
+                self.handler.add_waker(cx.waker());
+                Poll::Pending
+            }
+            None => self.inner.poll_read_ready(cx),
+        }
+    }
+
+    fn poll_write_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<usize>> {
+        self.inner.poll_write_ready(cx)
+    }
+}
+
+impl VirtualSocket for ChaosTcpSocket {
+    fn set_ttl(&mut self, ttl: u32) -> Result<()> {
+        self.inner.set_ttl(ttl)
+    }
+
+    fn ttl(&self) -> Result<u32> {
+        self.inner.ttl()
+    }
+
+    fn addr_local(&self) -> Result<SocketAddr> {
+        self.inner.addr_local()
+    }
+
+    fn status(&self) -> Result<SocketStatus> {
+        self.inner.status()
+    }
+
+    fn set_handler(&mut self, handler: Box<dyn InterestHandler + Send + Sync>) -> Result<()> {
+        self.handler.set(handler);
+        self.inner.set_handler(Box::new(self.handler.clone()))
+    }
+}
+
+impl VirtualConnectedSocket for ChaosTcpSocket {
+    fn set_linger(&mut self, linger: Option<Duration>) -> Result<()> {
+        self.inner.set_linger(linger)
+    }
+
+    fn linger(&self) -> Result<Option<Duration>> {
+        self.inner.linger()
+    }
+
+    fn try_send(&mut self, data: &[u8]) -> Result<usize> {
+        if !self
+            .send_delay
+            .elapsed(&self.config, &self.handler, InterestType::Writable)
+        {
+            return Err(NetworkError::WouldBlock);
+        }
+        let ret = self.inner.try_send(data);
+        if ret.is_ok() {
+            self.send_delay.reset();
+        }
+        ret
+    }
+
+    fn try_flush(&mut self) -> Result<()> {
+        self.inner.try_flush()
+    }
+
+    fn close(&mut self) -> Result<()> {
+        self.inner.close()
+    }
+
+    fn try_recv(&mut self, buf: &mut [MaybeUninit<u8>]) -> Result<usize> {
+        if self.received.is_none() {
+            let mut data = vec![0u8; buf.len()];
+            let amt = self.inner.try_recv(as_uninit(&mut data))?;
+            // The end of the stream is not held back
+            if amt == 0 {
+                return Ok(0);
+            }
+            data.truncate(amt);
+            self.received = Some((data, 0));
+        }
+        if !self
+            .recv_delay
+            .elapsed(&self.config, &self.handler, InterestType::Readable)
+        {
+            return Err(NetworkError::WouldBlock);
+        }
+
+        let (data, read) = self.received.as_mut().unwrap();
+        let amt = copy_into(&data[*read..], buf);
+        *read += amt;
+        if *read == data.len() {
+            self.received = None;
+            self.recv_delay.reset();
+        }
+        Ok(amt)
+    }
+}
+
+impl VirtualTcpSocket for ChaosTcpSocket {
+    fn set_recv_buf_size(&mut self, size: usize) -> Result<()> {
+        self.inner.set_recv_buf_size(size)
+    }
+
+    fn recv_buf_size(&self) -> Result<usize> {
+        self.inner.recv_buf_size()
+    }
+
+    fn set_send_buf_size(&mut self, size: usize) -> Result<()> {
+        self.inner.set_send_buf_size(size)
+    }
+
+    fn send_buf_size(&self) -> Result<usize> {
+        self.inner.send_buf_size()
+    }
+
+    fn set_nodelay(&mut self, reuse: bool) -> Result<()> {
+        self.inner.set_nodelay(reuse)
+    }
+
+    fn nodelay(&self) -> Result<bool> {
+        self.inner.nodelay()
+    }
+
+    fn set_keepalive(&mut self, keepalive: bool) -> Result<()> {
+        self.inner.set_keepalive(keepalive)
+    }
+
+    fn keepalive(&self) -> Result<bool> {
+        self.inner.keepalive()
+    }
+
+    fn set_dontroute(&mut self, keepalive: bool) -> Result<()> {
+        self.inner.set_dontroute(keepalive)
+    }
+
+    fn dontroute(&self) -> Result<bool> {
+        self.inner.dontroute()
+    }
+
+    fn addr_peer(&self) -> Result<SocketAddr> {
+        self.inner.addr_peer()
+    }
+
+    fn shutdown(&mut self, how: Shutdown) -> Result<()> {
+        self.inner.shutdown(how)
+    }
+
+    fn is_closed(&self) -> bool {
+        self.inner.is_closed()
+    }
+}
+
+#[derive(Debug)]
+struct ChaosUdpSocket {
+    inner: Box<dyn VirtualUdpSocket + Sync>,
+    config: Arc<ChaosConfig>,
+    handler: ChaosHandler,
+    recv_delay: ChaosDelay,
+    send_delay: ChaosDelay,
+    /// Datagram that was received but is still held back
+    received: Option<(Vec<u8>, SocketAddr)>,
+}
+
+impl ChaosUdpSocket {
+    fn new(inner: Box<dyn VirtualUdpSocket + Sync>, config: Arc<ChaosConfig>) -> Self {
+        Self {
+            inner,
+            config,
+            handler: Default::default(),
+            recv_delay: Default::default(),
+            send_delay: Default::default(),
+            received: None,
+        }
+    }
+}
+
+impl VirtualIoSource for ChaosUdpSocket {
+    fn remove_handler(&mut self) {
+        self.handler.clear();
+        self.inner.remove_handler()
+    }
+
+    fn poll_read_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<usize>> {
+        match &self.received {
+            Some((data, _)) => {
+                if self
+                    .recv_delay
+                    .elapsed(&self.config, &self.handler, InterestType::Readable)
+                {
+                    return Poll::Ready(Ok(data.len()));
+                }
+                self.handler.add_waker(cx.waker());
+                Poll::Pending
+            }
+            None => self.inner.poll_read_ready(cx),
+        }
+    }
+
+    fn poll_write_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<usize>> {
+        self.inner.poll_write_ready(cx)
+    }
+}
+
+impl VirtualSocket for ChaosUdpSocket {
+    fn set_ttl(&mut self, ttl: u32) -> Result<()> {
+        self.inner.set_ttl(ttl)
+    }
+
+    fn ttl(&self) -> Result<u32> {
+        self.inner.ttl()
+    }
+
+    fn addr_local(&self) -> Result<SocketAddr> {
+        self.inner.addr_local()
+    }
+
+    fn status(&self) -> Result<SocketStatus> {
+        self.inner.status()
+    }
+
+    fn set_handler(&mut self, handler: Box<dyn InterestHandler + Send + Sync>) -> Result<()> {
+        self.handler.set(handler);
+        self.inner.set_handler(Box::new(self.handler.clone()))
+    }
+}
+
+impl VirtualConnectionlessSocket for ChaosUdpSocket {
+    fn try_send_to(&mut self, data: &[u8], addr: SocketAddr) -> Result<usize> {
+        if !self
+            .send_delay
+            .elapsed(&self.config, &self.handler, InterestType::Writable)
+        {
+            return Err(NetworkError::WouldBlock);
+        }
+        let ret = self.inner.try_send_to(data, addr);
+        if ret.is_ok() {
+            self.send_delay.reset();
+        }
+        ret
+    }
+
+    fn try_recv_from(&mut self, buf: &mut [MaybeUninit<u8>]) -> Result<(usize, SocketAddr)> {
+        if self.received.is_none() {
+            let mut data = vec![0u8; buf.len()];
+            let (amt, addr) = self.inner.try_recv_from(as_uninit(&mut data))?;
+            data.truncate(amt);
+            self.received = Some((data, addr));
+        }
+        if !self
+            .recv_delay
+            .elapsed(&self.config, &self.handler, InterestType::Readable)
+        {
+            return Err(NetworkError::WouldBlock);
+        }
+
+        // Like any datagram, the part that does not fit is lost
+        let (data, addr) = self.received.take().unwrap();
+        self.recv_delay.reset();
+        Ok((copy_into(&data, buf), addr))
+    }
+}
+
+impl VirtualUdpSocket for ChaosUdpSocket {
+    fn set_broadcast(&mut self, broadcast: bool) -> Result<()> {
+        self.inner.set_broadcast(broadcast)
+    }
+
+    fn broadcast(&self) -> Result<bool> {
+        self.inner.broadcast()
+    }
+
+    fn set_multicast_loop_v4(&mut self, val: bool) -> Result<()> {
+        self.inner.set_multicast_loop_v4(val)
+    }
+
+    fn multicast_loop_v4(&self) -> Result<bool> {
+        self.inner.multicast_loop_v4()
+    }
+
+    fn set_multicast_loop_v6(&mut self, val: bool) -> Result<()> {
+        self.inner.set_multicast_loop_v6(val)
+    }
+
+    fn multicast_loop_v6(&self) -> Result<bool> {
+        self.inner.multicast_loop_v6()
+    }
+
+    fn set_multicast_ttl_v4(&mut self, ttl: u32) -> Result<()> {
+        self.inner.set_multicast_ttl_v4(ttl)
+    }
+
+    fn multicast_ttl_v4(&self) -> Result<u32> {
+        self.inner.multicast_ttl_v4()
+    }
+
+    fn join_multicast_v4(&mut self, multiaddr: Ipv4Addr, iface: Ipv4Addr) -> Result<()> {
+        self.inner.join_multicast_v4(multiaddr, iface)
+    }
+
+    fn leave_multicast_v4(&mut self, multiaddr: Ipv4Addr, iface: Ipv4Addr) -> Result<()> {
+        self.inner.leave_multicast_v4(multiaddr, iface)
+    }
+
+    fn join_multicast_v6(&mut self, multiaddr: Ipv6Addr, iface: u32) -> Result<()> {
+        self.inner.join_multicast_v6(multiaddr, iface)
+    }
+
+    fn leave_multicast_v6(&mut self, multiaddr: Ipv6Addr, iface: u32) -> Result<()> {
+        self.inner.leave_multicast_v6(multiaddr, iface)
+    }
+
+    fn addr_peer(&self) -> Result<Option<SocketAddr>> {
+        self.inner.addr_peer()
+    }
+}
@@ -16,8 +16,13 @@ use wasmer_wasix_types::{
     wasi::{Addressfamily, Errno},
 };
 
+pub mod chaos;
 pub mod socket;
 
+/// Largest payload that fits in a single UDP datagram over IPv4, this is the
+/// default maximum size of the datagrams that `sock_send_to` will send
+pub const DEFAULT_MAX_DATAGRAM_SIZE: usize = 65_507;
+
 #[allow(dead_code)]
 pub(crate) fn read_ip<M: MemorySize>(
     memory: &MemoryView,
@@ -387,7 +392,7 @@ pub fn net_error_into_wasi_err(net_error: NetworkError) -> Errno {
         NetworkError::InvalidInput => Errno::Inval,
         NetworkError::NotConnected => Errno::Notconn,
         NetworkError::NoDevice => Errno::Nodev,
-        NetworkError::PermissionDenied => Errno::Perm,
+        NetworkError::PermissionDenied => Errno::Access,
         NetworkError::TimedOut => Errno::Timedout,
         NetworkError::UnexpectedEof => Errno::Proto,
         NetworkError::WouldBlock => Errno::Again,
@@ -398,3 +403,16 @@ pub fn net_error_into_wasi_err(net_error: NetworkError) -> Errno {
         NetworkError::UnknownError => Errno::Io,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn permission_denied_maps_to_access() {
+        assert_eq!(
+            net_error_into_wasi_err(NetworkError::PermissionDenied),
+            Errno::Access
+        );
+    }
+}
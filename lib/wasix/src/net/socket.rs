@@ -4,7 +4,7 @@ use std::{
     mem::MaybeUninit,
     net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
     pin::Pin,
-    sync::{Arc, RwLock},
+    sync::{Arc, Mutex, RwLock},
     task::{Context, Poll},
     time::Duration,
 };
@@ -14,13 +14,18 @@ use derivative::Derivative;
 use serde_derive::{Deserialize, Serialize};
 use virtual_mio::InterestHandler;
 use virtual_net::{
-    net_error_into_io_err, NetworkError, VirtualIcmpSocket, VirtualNetworking, VirtualRawSocket,
-    VirtualTcpListener, VirtualTcpSocket, VirtualUdpSocket,
+    net_error_into_io_err, DynVirtualNetworking, NetworkError, VirtualIcmpSocket,
+    VirtualNetworking, VirtualRawSocket, VirtualTcpListener, VirtualTcpSocket, VirtualUdpSocket,
 };
 use wasmer_types::MemorySize;
 use wasmer_wasix_types::wasi::{Addressfamily, Errno, Rights, SockProto, Sockoption, Socktype};
 
-use crate::{net::net_error_into_wasi_err, VirtualTaskManager};
+use crate::{
+    net::net_error_into_wasi_err,
+    os::task::control_plane::{SocketOwnerGuard, WasiControlPlane},
+    utils::map_io_err,
+    VirtualTaskManager, WasiProcess, WasiProcessId,
+};
 
 #[derive(Debug)]
 #[cfg_attr(feature = "enable-serde", derive(Serialize, Deserialize))]
@@ -45,6 +50,8 @@ pub struct SocketProperties {
     pub no_delay: Option<bool>,
     pub keep_alive: Option<bool>,
     pub dont_route: Option<bool>,
+    /// Time-to-live (or hop limit for IPv6) applied once the socket is created
+    pub ttl: Option<u32>,
     pub send_buf_size: Option<usize>,
     pub recv_buf_size: Option<usize>,
     pub write_timeout: Option<Duration>,
@@ -55,12 +62,45 @@ pub struct SocketProperties {
     pub handler: Option<Box<dyn InterestHandler + Send + Sync>>,
 }
 
+type ConnectFuture =
+    Pin<Box<dyn Future<Output = Result<Box<dyn VirtualTcpSocket + Sync>, NetworkError>> + Send>>;
+
+/// Connection attempt of a non-blocking socket that has not yet
+/// completed, it is driven forward whenever the socket is polled
+pub struct PendingConnect {
+    peer: SocketAddr,
+    connect: Mutex<ConnectFuture>,
+    /// Process that owns the connection once it is established
+    owner: (WasiControlPlane, WasiProcessId),
+    /// Set when the attempt failed, the error is reported by every poll
+    /// until it is read with `SO_ERROR` (see [`InodeSocket::take_error`])
+    error: Option<NetworkError>,
+}
+
+impl PendingConnect {
+    fn is_failed(&self) -> bool {
+        self.error.is_some()
+    }
+}
+
+impl std::fmt::Debug for PendingConnect {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PendingConnect")
+            .field("peer", &self.peer)
+            .field("owner", &self.owner.1)
+            .field("error", &self.error)
+            .finish()
+    }
+}
+
 #[derive(Debug)]
 //#[cfg_attr(feature = "enable-serde", derive(Serialize, Deserialize))]
 pub enum InodeSocketKind {
     PreSocket {
         props: SocketProperties,
         addr: Option<SocketAddr>,
+        /// Set when a non-blocking connect is in progress
+        connecting: Option<PendingConnect>,
     },
     Icmp(Box<dyn VirtualIcmpSocket + Sync>),
     Raw(Box<dyn VirtualRawSocket + Sync>),
@@ -201,6 +241,8 @@ impl From<wasmer_journal::SocketOptTimeType> for TimeType {
 //#[cfg_attr(feature = "enable-serde", derive(Serialize, Deserialize))]
 pub(crate) struct InodeSocketProtected {
     pub kind: InodeSocketKind,
+    /// Registers the local address of this socket against the owning process
+    pub owner: Option<SocketOwnerGuard>,
 }
 
 #[derive(Debug)]
@@ -217,7 +259,7 @@ pub struct InodeSocket {
 
 impl InodeSocket {
     pub fn new(kind: InodeSocketKind) -> Self {
-        let protected = InodeSocketProtected { kind };
+        let protected = InodeSocketProtected { kind, owner: None };
         Self {
             inner: Arc::new(InodeSocketInner {
                 protected: RwLock::new(protected),
@@ -247,6 +289,7 @@ impl InodeSocket {
             .flatten()
             .unwrap_or(Duration::from_secs(30));
 
+        let mut ttl = None;
         let socket = {
             let mut inner = self.inner.protected.write().unwrap();
             match &mut inner.kind {
@@ -285,6 +328,7 @@ impl InodeSocket {
                         Socktype::Dgram => {
                             let reuse_port = props.reuse_port;
                             let reuse_addr = props.reuse_addr;
+                            ttl = props.ttl;
                             drop(inner);
 
                             net.bind_udp(addr, reuse_port, reuse_addr)
@@ -344,7 +388,10 @@ impl InodeSocket {
 
         tokio::select! {
             socket = socket => {
-                let socket = socket.map_err(net_error_into_wasi_err)?;
+                let mut socket = socket.map_err(net_error_into_wasi_err)?;
+                if let Some(ttl) = ttl {
+                    socket.set_ttl(ttl).map_err(net_error_into_wasi_err)?;
+                }
                 Ok(Some(InodeSocket::new(InodeSocketKind::UdpSocket { socket, peer: None })))
             },
             _ = tasks.sleep_now(timeout) => Err(Errno::Timedout)
@@ -368,11 +415,19 @@ impl InodeSocket {
             match &inner.kind {
                 InodeSocketKind::PreSocket { props, addr, .. } => match props.ty {
                     Socktype::Stream => {
-                        if addr.is_none() {
-                            tracing::warn!("wasi[?]::sock_listen - failed - address not set");
-                            return Err(Errno::Inval);
-                        }
-                        let addr = *addr.as_ref().unwrap();
+                        // A socket that was not bound is bound to an ephemeral
+                        // port on all the interfaces (like Linux does)
+                        let addr = match addr {
+                            Some(addr) => *addr,
+                            None => SocketAddr::new(
+                                match props.family {
+                                    Addressfamily::Inet4 => IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+                                    Addressfamily::Inet6 => IpAddr::V6(Ipv6Addr::UNSPECIFIED),
+                                    _ => return Err(Errno::Inval),
+                                },
+                                0,
+                            ),
+                        };
                         let only_v6 = props.only_v6;
                         let reuse_port = props.reuse_port;
                         let reuse_addr = props.reuse_addr;
@@ -546,6 +601,10 @@ impl InodeSocket {
         let connect = {
             let mut inner = self.inner.protected.write().unwrap();
             match &mut inner.kind {
+                InodeSocketKind::PreSocket {
+                    connecting: Some(connecting),
+                    ..
+                } if !connecting.is_failed() => return Err(Errno::Already),
                 InodeSocketKind::PreSocket { props, addr, .. } => {
                     handler = props.handler.take();
                     new_write_timeout = props.write_timeout;
@@ -555,6 +614,7 @@ impl InodeSocket {
                             let no_delay = props.no_delay;
                             let keep_alive = props.keep_alive;
                             let dont_route = props.dont_route;
+                            let ttl = props.ttl;
                             let addr = match addr {
                                 Some(a) => *a,
                                 None => {
@@ -576,6 +636,9 @@ impl InodeSocket {
                                 if let Some(dont_route) = dont_route {
                                     ret.set_dontroute(dont_route).ok();
                                 }
+                                if let Some(ttl) = ttl {
+                                    ret.set_ttl(ttl).ok();
+                                }
                                 Ok(ret)
                             })
                         }
@@ -593,6 +656,7 @@ impl InodeSocket {
                     *peer_addr = peer;
                     return Ok(None);
                 }
+                InodeSocketKind::TcpStream { .. } => return Err(Errno::Isconn),
                 _ => return Err(Errno::Notsup),
             }
         };
@@ -617,6 +681,85 @@ impl InodeSocket {
         Ok(Some(socket))
     }
 
+    /// Starts connecting the socket to a peer without waiting for the
+    /// connection to be established. Returns true if the connection is
+    /// still in progress, in which case the socket becomes writable once
+    /// the connection has completed (or failed). The connection is
+    /// registered against the `owner` process once it is established.
+    pub fn connect_nonblocking(
+        &mut self,
+        net: DynVirtualNetworking,
+        peer: SocketAddr,
+        owner: (WasiControlPlane, WasiProcessId),
+    ) -> Result<bool, Errno> {
+        let mut inner = self.inner.protected.write().unwrap();
+        match &mut inner.kind {
+            InodeSocketKind::PreSocket {
+                connecting: Some(connecting),
+                ..
+            } if !connecting.is_failed() => Err(Errno::Already),
+            InodeSocketKind::PreSocket {
+                props,
+                addr,
+                connecting,
+            } => match props.ty {
+                Socktype::Stream => {
+                    let no_delay = props.no_delay;
+                    let keep_alive = props.keep_alive;
+                    let dont_route = props.dont_route;
+                    let ttl = props.ttl;
+                    let addr = match addr {
+                        Some(a) => *a,
+                        None => {
+                            let ip = match peer.is_ipv4() {
+                                true => IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+                                false => IpAddr::V6(Ipv6Addr::UNSPECIFIED),
+                            };
+                            SocketAddr::new(ip, 0)
+                        }
+                    };
+                    let connect: ConnectFuture = Box::pin(async move {
+                        let mut ret = net.connect_tcp(addr, peer).await?;
+                        if let Some(no_delay) = no_delay {
+                            ret.set_nodelay(no_delay).ok();
+                        }
+                        if let Some(keep_alive) = keep_alive {
+                            ret.set_keepalive(keep_alive).ok();
+                        }
+                        if let Some(dont_route) = dont_route {
+                            ret.set_dontroute(dont_route).ok();
+                        }
+                        if let Some(ttl) = ttl {
+                            ret.set_ttl(ttl).ok();
+                        }
+                        Ok(ret)
+                    });
+                    connecting.replace(PendingConnect {
+                        peer,
+                        connect: Mutex::new(connect),
+                        owner,
+                        error: None,
+                    });
+                    Ok(true)
+                }
+                Socktype::Dgram => Err(Errno::Inval),
+                _ => Err(Errno::Notsup),
+            },
+            InodeSocketKind::UdpSocket {
+                peer: target_peer, ..
+            } => {
+                target_peer.replace(peer);
+                Ok(false)
+            }
+            InodeSocketKind::RemoteSocket { peer_addr, .. } => {
+                *peer_addr = peer;
+                Ok(false)
+            }
+            InodeSocketKind::TcpStream { .. } => Err(Errno::Isconn),
+            _ => Err(Errno::Notsup),
+        }
+    }
+
     pub fn status(&self) -> Result<WasiSocketStatus, Errno> {
         let inner = self.inner.protected.read().unwrap();
         Ok(match &inner.kind {
@@ -629,6 +772,27 @@ impl InodeSocket {
         })
     }
 
+    /// Returns the number of bytes that can be read from the socket
+    /// without blocking (like `FIONREAD`)
+    pub fn bytes_available(&self) -> Result<usize, Errno> {
+        let mut inner = self.inner.protected.write().unwrap();
+        match &inner.kind {
+            InodeSocketKind::TcpListener { .. } => return Err(Errno::Inval),
+            InodeSocketKind::PreSocket { .. } | InodeSocketKind::RemoteSocket { .. } => {
+                return Ok(0)
+            }
+            _ => {}
+        }
+
+        let waker = futures::task::noop_waker();
+        let mut cx = Context::from_waker(&waker);
+        match inner.poll_read_ready(&mut cx) {
+            Poll::Ready(Ok(amt)) => Ok(amt),
+            Poll::Ready(Err(err)) => Err(map_io_err(err)),
+            Poll::Pending => Ok(0),
+        }
+    }
+
     pub fn addr_local(&self) -> Result<SocketAddr, Errno> {
         let inner = self.inner.protected.read().unwrap();
         Ok(match &inner.kind {
@@ -663,6 +827,56 @@ impl InodeSocket {
         })
     }
 
+    /// Registers the local address of this socket as being owned by a
+    /// process so that in-process peers can resolve its credentials
+    pub(crate) fn set_owner(
+        &self,
+        control_plane: &WasiControlPlane,
+        pid: WasiProcessId,
+    ) -> Result<(), Errno> {
+        let addr = self.addr_local()?;
+        let guard = control_plane.register_socket_owner(addr, pid);
+        let mut inner = self.inner.protected.write().unwrap();
+        inner.owner.replace(guard);
+        Ok(())
+    }
+
+    /// Returns the process on the other end of an in-process connection
+    /// (this is the equivalent of `SO_PEERCRED`)
+    pub fn peer_pid(&self, control_plane: &WasiControlPlane) -> Result<WasiProcessId, Errno> {
+        let peer = {
+            let inner = self.inner.protected.read().unwrap();
+            match &inner.kind {
+                InodeSocketKind::TcpStream { socket, .. } => {
+                    socket.addr_peer().map_err(net_error_into_wasi_err)?
+                }
+                InodeSocketKind::PreSocket { .. } => return Err(Errno::Notconn),
+                _ => return Err(Errno::Noprotoopt),
+            }
+        };
+        // Peers outside of this control plane have no credentials
+        control_plane.socket_owner(&peer).ok_or(Errno::Noprotoopt)
+    }
+
+    /// Returns the error of a non-blocking connect that failed and clears it
+    /// so that a new connection can be attempted (this is the equivalent of
+    /// `SO_ERROR`), `Errno::Success` is returned when there is no error
+    pub fn take_error(&self) -> Errno {
+        let mut inner = self.inner.protected.write().unwrap();
+        match &mut inner.kind {
+            InodeSocketKind::PreSocket { connecting, .. }
+                if connecting.as_ref().is_some_and(PendingConnect::is_failed) =>
+            {
+                connecting
+                    .take()
+                    .and_then(|connecting| connecting.error)
+                    .map(net_error_into_wasi_err)
+                    .unwrap_or(Errno::Success)
+            }
+            _ => Errno::Success,
+        }
+    }
+
     pub fn addr_peer(&self) -> Result<SocketAddr, Errno> {
         let inner = self.inner.protected.read().unwrap();
         Ok(match &inner.kind {
@@ -962,7 +1176,10 @@ impl InodeSocket {
                 *set_ttl = ttl;
                 Ok(())
             }
-            InodeSocketKind::PreSocket { .. } => Err(Errno::Io),
+            InodeSocketKind::PreSocket { props, .. } => {
+                props.ttl = Some(ttl);
+                Ok(())
+            }
             _ => Err(Errno::Notsup),
         }
     }
@@ -977,7 +1194,7 @@ impl InodeSocket {
                 socket.ttl().map_err(net_error_into_wasi_err)
             }
             InodeSocketKind::RemoteSocket { ttl, .. } => Ok(*ttl),
-            InodeSocketKind::PreSocket { .. } => Err(Errno::Io),
+            InodeSocketKind::PreSocket { props, .. } => props.ttl.ok_or(Errno::Notconn),
             _ => Err(Errno::Notsup),
         }
     }
@@ -1063,10 +1280,13 @@ impl InodeSocket {
     pub async fn send(
         &self,
         tasks: &dyn VirtualTaskManager,
+        process: &WasiProcess,
         buf: &[u8],
         timeout: Option<Duration>,
         nonblocking: bool,
     ) -> Result<usize, Errno> {
+        let buf = &buf[..process.socket_byte_allowance(buf.len())?];
+
         struct SocketSender<'a, 'b> {
             inner: &'a InodeSocketInner,
             data: &'b [u8],
@@ -1099,7 +1319,9 @@ impl InodeSocket {
                                 Err(NetworkError::NotConnected)
                             }
                         }
-                        InodeSocketKind::PreSocket { .. } => {
+                        // Sockets that were never connected (or that are
+                        // listening) have no peer to send the data to
+                        InodeSocketKind::PreSocket { .. } | InodeSocketKind::TcpListener { .. } => {
                             return Poll::Ready(Err(Errno::Notconn))
                         }
                         InodeSocketKind::RemoteSocket { .. } => {
@@ -1133,24 +1355,31 @@ impl InodeSocket {
             nonblocking,
             handler_registered: false,
         };
-        if let Some(timeout) = timeout {
+        let res = if let Some(timeout) = timeout {
             tokio::select! {
                 res = poller => res,
                 _ = tasks.sleep_now(timeout) => Err(Errno::Timedout)
             }
         } else {
             poller.await
+        };
+        if let Ok(amt) = &res {
+            process.add_socket_bytes_sent(*amt);
         }
+        res
     }
 
     pub async fn send_to<M: MemorySize>(
         &self,
         tasks: &dyn VirtualTaskManager,
+        process: &WasiProcess,
         buf: &[u8],
         addr: SocketAddr,
         timeout: Option<Duration>,
         nonblocking: bool,
     ) -> Result<usize, Errno> {
+        let buf = &buf[..process.socket_byte_allowance(buf.len())?];
+
         struct SocketSender<'a, 'b> {
             inner: &'a InodeSocketInner,
             data: &'b [u8],
@@ -1214,23 +1443,31 @@ impl InodeSocket {
             nonblocking,
             handler_registered: false,
         };
-        if let Some(timeout) = timeout {
+        let res = if let Some(timeout) = timeout {
             tokio::select! {
                 res = poller => res,
                 _ = tasks.sleep_now(timeout) => Err(Errno::Timedout)
             }
         } else {
             poller.await
+        };
+        if let Ok(amt) = &res {
+            process.add_socket_bytes_sent(*amt);
         }
+        res
     }
 
     pub async fn recv(
         &self,
         tasks: &dyn VirtualTaskManager,
+        process: &WasiProcess,
         buf: &mut [MaybeUninit<u8>],
         timeout: Option<Duration>,
         nonblocking: bool,
     ) -> Result<usize, Errno> {
+        let allowance = process.socket_byte_allowance(buf.len())?;
+        let buf = &mut buf[..allowance];
+
         struct SocketReceiver<'a, 'b> {
             inner: &'a InodeSocketInner,
             data: &'b mut [MaybeUninit<u8>],
@@ -1260,7 +1497,8 @@ impl InodeSocket {
                             if let Some(peer) = peer {
                                 match socket.try_recv_from(self.data) {
                                     Ok((amt, addr)) if addr == *peer => Ok(amt),
-                                    Ok(_) => Err(NetworkError::WouldBlock),
+                                    // Datagrams from other peers are dropped
+                                    Ok(_) => continue,
                                     Err(err) => Err(err),
                                 }
                             } else {
@@ -1305,23 +1543,31 @@ impl InodeSocket {
             nonblocking,
             handler_registered: false,
         };
-        if let Some(timeout) = timeout {
+        let res = if let Some(timeout) = timeout {
             tokio::select! {
                 res = poller => res,
                 _ = tasks.sleep_now(timeout) => Err(Errno::Timedout)
             }
         } else {
             poller.await
+        };
+        if let Ok(amt) = &res {
+            process.add_socket_bytes_received(*amt);
         }
+        res
     }
 
     pub async fn recv_from(
         &self,
         tasks: &dyn VirtualTaskManager,
+        process: &WasiProcess,
         buf: &mut [MaybeUninit<u8>],
         timeout: Option<Duration>,
         nonblocking: bool,
     ) -> Result<(usize, SocketAddr), Errno> {
+        let allowance = process.socket_byte_allowance(buf.len())?;
+        let buf = &mut buf[..allowance];
+
         struct SocketReceiver<'a, 'b> {
             inner: &'a InodeSocketInner,
             data: &'b mut [MaybeUninit<u8>],
@@ -1346,8 +1592,14 @@ impl InodeSocket {
                 loop {
                     let res = match &mut inner.kind {
                         InodeSocketKind::Icmp(socket) => socket.try_recv_from(self.data),
-                        InodeSocketKind::UdpSocket { socket, .. } => {
-                            socket.try_recv_from(self.data)
+                        InodeSocketKind::UdpSocket { socket, peer } => {
+                            match socket.try_recv_from(self.data) {
+                                // Connected sockets drop the datagrams of other peers
+                                Ok((_, addr)) if matches!(peer, Some(peer) if *peer != addr) => {
+                                    continue
+                                }
+                                res => res,
+                            }
                         }
                         InodeSocketKind::RemoteSocket { .. } => {
                             return Poll::Pending;
@@ -1382,14 +1634,18 @@ impl InodeSocket {
             nonblocking,
             handler_registered: false,
         };
-        if let Some(timeout) = timeout {
+        let res = if let Some(timeout) = timeout {
             tokio::select! {
                 res = poller => res,
                 _ = tasks.sleep_now(timeout) => Err(Errno::Timedout)
             }
         } else {
             poller.await
+        };
+        if let Ok((amt, _)) = &res {
+            process.add_socket_bytes_received(*amt);
         }
+        res
     }
 
     pub fn shutdown(&mut self, how: std::net::Shutdown) -> Result<(), Errno> {
@@ -1438,7 +1694,65 @@ impl InodeSocketProtected {
         }
     }
 
+    /// Drives any non-blocking connect that is in progress, once the
+    /// connection is established the socket is turned into a stream.
+    /// Returns `Poll::Ready(Ok(()))` when no connect is pending.
+    fn poll_connect(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), NetworkError>> {
+        let res = match &mut self.kind {
+            InodeSocketKind::PreSocket {
+                connecting: Some(connecting),
+                ..
+            } => {
+                if let Some(err) = connecting.error {
+                    return Poll::Ready(Err(err));
+                }
+                let res = match connecting.connect.lock().unwrap().as_mut().poll(cx) {
+                    Poll::Ready(res) => res,
+                    Poll::Pending => return Poll::Pending,
+                };
+                if let Err(err) = &res {
+                    connecting.error.replace(*err);
+                }
+                res?
+            }
+            _ => return Poll::Ready(Ok(())),
+        };
+
+        let (handler, write_timeout, read_timeout, owner) = match &mut self.kind {
+            InodeSocketKind::PreSocket {
+                props, connecting, ..
+            } => (
+                props.handler.take(),
+                props.write_timeout,
+                props.read_timeout,
+                connecting.take().map(|connecting| connecting.owner),
+            ),
+            _ => unreachable!(),
+        };
+
+        let mut socket = res;
+        if let Some(handler) = handler {
+            socket.set_handler(handler)?;
+        }
+        // Register the new connection so that in-process peers can look us up
+        if let (Some((control_plane, pid)), Ok(addr)) = (owner, socket.addr_local()) {
+            self.owner
+                .replace(control_plane.register_socket_owner(addr, pid));
+        }
+        self.kind = InodeSocketKind::TcpStream {
+            socket,
+            write_timeout,
+            read_timeout,
+        };
+        Poll::Ready(Ok(()))
+    }
+
     pub fn poll_read_ready(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<usize>> {
+        match self.poll_connect(cx) {
+            Poll::Ready(Ok(())) => {}
+            Poll::Ready(Err(err)) => return Poll::Ready(Err(net_error_into_io_err(err))),
+            Poll::Pending => return Poll::Pending,
+        }
         match &mut self.kind {
             InodeSocketKind::TcpListener { socket, .. } => socket.poll_read_ready(cx),
             InodeSocketKind::TcpStream { socket, .. } => socket.poll_read_ready(cx),
@@ -1452,6 +1766,11 @@ impl InodeSocketProtected {
     }
 
     pub fn poll_write_ready(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<usize>> {
+        match self.poll_connect(cx) {
+            Poll::Ready(Ok(())) => {}
+            Poll::Ready(Err(err)) => return Poll::Ready(Err(net_error_into_io_err(err))),
+            Poll::Pending => return Poll::Pending,
+        }
         match &mut self.kind {
             InodeSocketKind::TcpListener { socket, .. } => socket.poll_write_ready(cx),
             InodeSocketKind::TcpStream { socket, .. } => socket.poll_write_ready(cx),
@@ -1516,3 +1835,229 @@ pub(crate) fn all_socket_rights() -> Rights {
         .union(Rights::SOCK_RECV_FROM)
         .union(Rights::SOCK_SEND_TO)
 }
+
+#[cfg(test)]
+mod tests {
+    use std::future::poll_fn;
+
+    use virtual_net::{LoopbackNetworking, VirtualTcpSocket};
+
+    use super::*;
+    use crate::utils::xxhash_random;
+
+    /// Loopback networking that is also able to establish connections
+    #[derive(Debug, Default)]
+    struct ConnectableLoopback(LoopbackNetworking);
+
+    #[async_trait::async_trait]
+    impl VirtualNetworking for ConnectableLoopback {
+        async fn listen_tcp(
+            &self,
+            addr: SocketAddr,
+            only_v6: bool,
+            reuse_port: bool,
+            reuse_addr: bool,
+        ) -> virtual_net::Result<Box<dyn VirtualTcpListener + Sync>> {
+            self.0
+                .listen_tcp(addr, only_v6, reuse_port, reuse_addr)
+                .await
+        }
+
+        async fn connect_tcp(
+            &self,
+            addr: SocketAddr,
+            peer: SocketAddr,
+        ) -> virtual_net::Result<Box<dyn VirtualTcpSocket + Sync>> {
+            self.0
+                .loopback_connect_to(addr, peer)
+                .map(|socket| Box::new(socket) as Box<dyn VirtualTcpSocket + Sync>)
+                .ok_or(NetworkError::ConnectionRefused)
+        }
+    }
+
+    fn stream_socket() -> InodeSocket {
+        InodeSocket::new(InodeSocketKind::PreSocket {
+            props: SocketProperties {
+                family: Addressfamily::Inet4,
+                ty: Socktype::Stream,
+                pt: SockProto::Tcp,
+                only_v6: false,
+                reuse_port: false,
+                reuse_addr: false,
+                no_delay: None,
+                keep_alive: None,
+                dont_route: None,
+                ttl: None,
+                send_buf_size: None,
+                recv_buf_size: None,
+                write_timeout: None,
+                read_timeout: None,
+                accept_timeout: None,
+                connect_timeout: None,
+                handler: None,
+            },
+            addr: None,
+            connecting: None,
+        })
+    }
+
+    #[tokio::test]
+    async fn test_nonblocking_connect_completes_on_poll() {
+        let net = Arc::new(ConnectableLoopback::default());
+        let peer: SocketAddr = "127.0.0.1:8080".parse().unwrap();
+        let _listener = net.listen_tcp(peer, false, false, false).await.unwrap();
+
+        let control_plane = WasiControlPlane::default();
+        let pid = control_plane.new_process(xxhash_random()).unwrap().pid();
+        let owner = (control_plane.clone(), pid);
+
+        let mut socket = stream_socket();
+        let in_progress = socket
+            .connect_nonblocking(net.clone(), peer, owner.clone())
+            .unwrap();
+        assert!(in_progress);
+        assert!(matches!(
+            socket.status().unwrap(),
+            WasiSocketStatus::Opening
+        ));
+
+        // A second attempt while the first is pending is rejected
+        assert_eq!(
+            socket.connect_nonblocking(net.clone(), peer, owner),
+            Err(Errno::Already)
+        );
+
+        // Polling for writability completes the connection
+        poll_fn(|cx| {
+            let mut inner = socket.inner.protected.write().unwrap();
+            inner.poll_write_ready(cx)
+        })
+        .await
+        .unwrap();
+
+        assert!(matches!(socket.status().unwrap(), WasiSocketStatus::Opened));
+        assert_eq!(socket.addr_peer().unwrap(), peer);
+
+        // The established connection belongs to the process that connected
+        assert_eq!(
+            control_plane.socket_owner(&socket.addr_local().unwrap()),
+            Some(pid)
+        );
+        assert_eq!(socket.take_error(), Errno::Success);
+    }
+
+    #[tokio::test]
+    async fn test_failed_nonblocking_connect_is_reported_until_read() {
+        let net = Arc::new(ConnectableLoopback::default());
+        let peer: SocketAddr = "127.0.0.1:8080".parse().unwrap();
+        let control_plane = WasiControlPlane::default();
+        let pid = control_plane.new_process(xxhash_random()).unwrap().pid();
+        let owner = (control_plane, pid);
+
+        // Nothing listens on the peer address so the connect fails
+        let mut socket = stream_socket();
+        socket
+            .connect_nonblocking(net.clone(), peer, owner.clone())
+            .unwrap();
+        for _ in 0..2 {
+            let err = poll_fn(|cx| {
+                let mut inner = socket.inner.protected.write().unwrap();
+                inner.poll_write_ready(cx)
+            })
+            .await
+            .unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::ConnectionRefused);
+        }
+
+        // Reading the error clears it so the connect can be attempted again
+        assert_eq!(socket.take_error(), Errno::Connrefused);
+        assert_eq!(socket.take_error(), Errno::Success);
+        let _listener = net.listen_tcp(peer, false, false, false).await.unwrap();
+        assert_eq!(
+            socket.connect_nonblocking(net.clone(), peer, owner),
+            Ok(true)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_peer_pid_of_in_process_connection() {
+        let control_plane = WasiControlPlane::default();
+        let parent = control_plane.new_process(xxhash_random()).unwrap();
+        let child = control_plane.new_process(xxhash_random()).unwrap();
+
+        // The parent listens for connections
+        let net = ConnectableLoopback::default();
+        let addr: SocketAddr = "127.0.0.1:8080".parse().unwrap();
+        let mut listener = net.listen_tcp(addr, false, false, false).await.unwrap();
+        let _listener_owner = control_plane.register_socket_owner(addr, parent.pid());
+
+        // The child connects to the parent
+        let client = InodeSocket::new(InodeSocketKind::TcpStream {
+            socket: net
+                .connect_tcp("0.0.0.0:0".parse().unwrap(), addr)
+                .await
+                .unwrap(),
+            write_timeout: None,
+            read_timeout: None,
+        });
+        client.set_owner(&control_plane, child.pid()).unwrap();
+
+        let (accepted, _) = listener.try_accept().unwrap();
+        let accepted = InodeSocket::new(InodeSocketKind::TcpStream {
+            socket: accepted,
+            write_timeout: None,
+            read_timeout: None,
+        });
+
+        assert_eq!(accepted.peer_pid(&control_plane), Ok(child.pid()));
+        assert_eq!(client.peer_pid(&control_plane), Ok(parent.pid()));
+
+        // Once the child closes its socket the credentials are gone
+        drop(client);
+        assert_eq!(accepted.peer_pid(&control_plane), Err(Errno::Noprotoopt));
+
+        // Sockets that are not connected have no peer
+        assert_eq!(
+            stream_socket().peer_pid(&control_plane),
+            Err(Errno::Notconn)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_ttl_is_applied_on_connect() {
+        let net = Arc::new(ConnectableLoopback::default());
+        let peer: SocketAddr = "127.0.0.1:8080".parse().unwrap();
+        let listener = net.listen_tcp(peer, false, false, false).await.unwrap();
+
+        // The TTL is remembered until the socket is connected
+        let mut socket = stream_socket();
+        assert_eq!(socket.ttl(), Err(Errno::Notconn));
+        socket.set_ttl(7).unwrap();
+        assert_eq!(socket.ttl(), Ok(7));
+
+        let control_plane = WasiControlPlane::default();
+        let pid = control_plane.new_process(xxhash_random()).unwrap().pid();
+        socket
+            .connect_nonblocking(net.clone(), peer, (control_plane, pid))
+            .unwrap();
+        poll_fn(|cx| {
+            let mut inner = socket.inner.protected.write().unwrap();
+            inner.poll_write_ready(cx)
+        })
+        .await
+        .unwrap();
+
+        assert!(matches!(socket.status().unwrap(), WasiSocketStatus::Opened));
+        assert_eq!(socket.ttl(), Ok(7));
+        socket.set_ttl(12).unwrap();
+        assert_eq!(socket.ttl(), Ok(12));
+
+        // Listeners have no time-to-live
+        let listener = InodeSocket::new(InodeSocketKind::TcpListener {
+            socket: listener,
+            accept_timeout: None,
+        });
+        assert_eq!(listener.set_ttl(7), Err(Errno::Notsup));
+        assert_eq!(listener.ttl(), Err(Errno::Notsup));
+    }
+}
@@ -69,7 +69,7 @@ impl CmdWasmer {
             // Set the arguments of the environment by replacing the state
             let mut state = env.state.fork();
             args.insert(0, what.clone());
-            state.args = args;
+            state.set_args(args);
             env.state = Arc::new(state);
 
             if let Ok(binary) = self.get_package(&what).await {
@@ -1,9 +1,11 @@
 pub mod builtins;
 
-use std::{collections::HashMap, sync::Arc};
+use std::{collections::HashMap, sync::Arc, time::Duration};
 
+use futures::future::BoxFuture;
+use virtual_fs::VirtualFile;
 use wasmer::{FunctionEnvMut, Store};
-use wasmer_wasix_types::wasi::Errno;
+use wasmer_wasix_types::wasi::{Errno, ExitCode};
 
 use crate::{
     runtime::task_manager::InlineWaker, syscalls::stderr_write, Runtime, SpawnError, WasiEnv,
@@ -32,6 +34,44 @@ where
     ) -> Result<TaskJoinHandle, SpawnError>;
 }
 
+/// Runs some of the programs that the guest spawns natively on the host
+/// rather than as WASIX processes.
+pub trait SpawnHandler
+where
+    Self: std::fmt::Debug,
+{
+    /// Returns true if the handler runs the program with the given name,
+    /// otherwise the program is spawned as usual.
+    fn claims(&self, name: &str) -> bool;
+
+    /// Runs the program, the process exits with the returned exit code.
+    ///
+    /// `args` includes the program name as its first entry.
+    fn spawn(
+        &self,
+        name: &str,
+        args: Vec<String>,
+        stdio: SpawnStdio,
+    ) -> BoxFuture<'static, ExitCode>;
+
+    /// How long a program may run before its process exits with
+    /// [`Errno::Timedout`], by default programs run until they finish.
+    ///
+    /// A program that panics exits with [`Errno::Noexec`].
+    fn timeout(&self) -> Option<Duration> {
+        None
+    }
+}
+
+/// The standard streams of a process run by a [`SpawnHandler`], only the
+/// streams that the guest asked to be piped are present.
+#[derive(Debug, Default)]
+pub struct SpawnStdio {
+    pub stdin: Option<Box<dyn VirtualFile + Send + Sync + 'static>>,
+    pub stdout: Option<Box<dyn VirtualFile + Send + Sync + 'static>>,
+    pub stderr: Option<Box<dyn VirtualFile + Send + Sync + 'static>>,
+}
+
 #[derive(Debug, Clone)]
 pub struct Commands {
     commands: HashMap<String, Arc<dyn VirtualCommand + Send + Sync + 'static>>,
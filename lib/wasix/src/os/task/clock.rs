@@ -0,0 +1,53 @@
+//! Clocks that override the time seen by a thread.
+
+use std::{collections::VecDeque, sync::Mutex};
+
+use wasmer_wasix_types::wasi::{Snapshot0Clockid, Timestamp};
+
+/// Source of time for a thread, when installed on a
+/// [`WasiThread`](super::thread::WasiThread) it is consulted by
+/// `clock_time_get` instead of the clocks of the host.
+pub trait WasiClock: std::fmt::Debug + Send + Sync {
+    /// Returns the time of a clock in nanoseconds, or [`None`] to fall
+    /// back to the clocks of the host.
+    fn time(&self, clock_id: Snapshot0Clockid) -> Option<Timestamp>;
+}
+
+/// Clock that returns a scripted sequence of times (for all clocks), once
+/// the script runs out the last time is repeated.
+#[derive(Debug, Default)]
+pub struct ScriptedClock {
+    state: Mutex<ScriptedClockState>,
+}
+
+#[derive(Debug, Default)]
+struct ScriptedClockState {
+    script: VecDeque<Timestamp>,
+    last: Option<Timestamp>,
+}
+
+impl ScriptedClock {
+    pub fn new(script: impl IntoIterator<Item = Timestamp>) -> Self {
+        Self {
+            state: Mutex::new(ScriptedClockState {
+                script: script.into_iter().collect(),
+                last: None,
+            }),
+        }
+    }
+
+    /// Appends more times to the end of the script
+    pub fn push(&self, time: Timestamp) {
+        self.state.lock().unwrap().script.push_back(time);
+    }
+}
+
+impl WasiClock for ScriptedClock {
+    fn time(&self, _clock_id: Snapshot0Clockid) -> Option<Timestamp> {
+        let mut state = self.state.lock().unwrap();
+        if let Some(time) = state.script.pop_front() {
+            state.last.replace(time);
+        }
+        state.last
+    }
+}
@@ -1,5 +1,6 @@
 use std::{
     collections::HashMap,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
     sync::{
         atomic::{AtomicUsize, Ordering},
         Arc, RwLock,
@@ -7,8 +8,11 @@ use std::{
     time::Duration,
 };
 
-use crate::{WasiProcess, WasiProcessId};
+use crate::{WasiProcess, WasiProcessId, WasiThreadId};
 use wasmer_types::ModuleHash;
+use wasmer_wasix_types::types::Signal;
+
+use super::TaskStatus;
 
 #[derive(Debug, Clone)]
 pub struct WasiControlPlane {
@@ -66,6 +70,26 @@ impl Default for ControlPlaneConfig {
     }
 }
 
+/// Snapshot of a process that is registered with the [`WasiControlPlane`]
+#[derive(Debug, Clone)]
+pub struct ProcessInfo {
+    pub pid: WasiProcessId,
+    /// Process that spawned this process (if any)
+    pub parent: Option<WasiProcessId>,
+    /// Number of threads that are running in the process
+    pub thread_count: u32,
+    pub status: TaskStatus,
+    pub labels: HashMap<String, String>,
+}
+
+/// Snapshot of a thread that belongs to a process
+#[derive(Debug, Clone)]
+pub struct ThreadInfo {
+    pub tid: WasiThreadId,
+    pub is_main: bool,
+    pub status: TaskStatus,
+}
+
 #[derive(Debug)]
 struct State {
     config: ControlPlaneConfig,
@@ -83,6 +107,9 @@ struct MutableState {
     process_seed: u32,
     /// The processes running on this machine
     processes: HashMap<WasiProcessId, WasiProcess>,
+    /// Local socket addresses that are owned by processes on this machine
+    /// (used to resolve the peer credentials of in-process connections)
+    socket_owners: HashMap<SocketAddr, WasiProcessId>,
     // TODO: keep a queue of terminated process ids for id reuse.
 }
 
@@ -95,6 +122,7 @@ impl WasiControlPlane {
                 mutable: RwLock::new(MutableState {
                     process_seed: 0,
                     processes: Default::default(),
+                    socket_owners: Default::default(),
                 }),
             }),
         }
@@ -167,6 +195,113 @@ impl WasiControlPlane {
             .get(&pid)
             .cloned()
     }
+
+    /// Takes a snapshot of all the processes that are registered with
+    /// this control plane (ordered by their process ID)
+    pub fn processes(&self) -> Vec<ProcessInfo> {
+        let processes: Vec<_> = {
+            let mutable = self.state.mutable.read().unwrap();
+            mutable.processes.values().cloned().collect()
+        };
+
+        // Processes only keep track of their children
+        let mut parents = HashMap::new();
+        let mut ret: Vec<_> = processes
+            .iter()
+            .map(|process| {
+                let inner = process.lock();
+                for child in inner.children.iter() {
+                    parents.insert(child.pid(), process.pid());
+                }
+                ProcessInfo {
+                    pid: process.pid(),
+                    parent: None,
+                    thread_count: inner.thread_count,
+                    status: process.status(),
+                    labels: inner.labels.clone(),
+                }
+            })
+            .collect();
+        for info in ret.iter_mut() {
+            info.parent = parents.get(&info.pid).copied();
+        }
+        ret.sort_by_key(|info| info.pid);
+        ret
+    }
+
+    /// Takes a snapshot of the threads of a process (ordered by their
+    /// thread ID), returns [`None`] if the process does not exist
+    pub fn threads(&self, pid: WasiProcessId) -> Option<Vec<ThreadInfo>> {
+        let process = self.get_process(pid)?;
+        let inner = process.lock();
+        let mut ret: Vec<_> = inner
+            .threads
+            .values()
+            .map(|thread| ThreadInfo {
+                tid: thread.tid(),
+                is_main: thread.is_main(),
+                status: thread.status(),
+            })
+            .collect();
+        ret.sort_by_key(|info| info.tid);
+        Some(ret)
+    }
+
+    /// Gets the processes that are members of a process group (ordered
+    /// by their process ID), processes that have finished are skipped
+    pub fn process_group(&self, pgid: WasiProcessId) -> Vec<WasiProcess> {
+        let mut ret: Vec<_> = self
+            .state
+            .mutable
+            .read()
+            .unwrap()
+            .processes
+            .values()
+            .filter(|process| process.pgid() == pgid && !process.status().is_finished())
+            .cloned()
+            .collect();
+        ret.sort_by_key(|process| process.pid());
+        ret
+    }
+
+    /// Sends a signal to every member of a process group, returns the
+    /// number of processes that were signaled
+    pub fn signal_process_group(&self, pgid: WasiProcessId, signal: Signal) -> usize {
+        let members = self.process_group(pgid);
+        for process in members.iter() {
+            process.signal_process(signal);
+        }
+        members.len()
+    }
+
+    /// Records that the local socket address is owned by a process, the
+    /// registration is removed again when the returned guard is dropped
+    pub(crate) fn register_socket_owner(
+        &self,
+        addr: SocketAddr,
+        pid: WasiProcessId,
+    ) -> SocketOwnerGuard {
+        let mut mutable = self.state.mutable.write().unwrap();
+        mutable.socket_owners.insert(addr, pid);
+        SocketOwnerGuard {
+            control_plane: self.handle(),
+            addr,
+            pid,
+        }
+    }
+
+    /// Gets the process that owns a local socket address, sockets that are
+    /// bound to the wildcard address own the port on every address
+    pub fn socket_owner(&self, addr: &SocketAddr) -> Option<WasiProcessId> {
+        let mutable = self.state.mutable.read().unwrap();
+        let wildcards = [
+            IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+            IpAddr::V6(Ipv6Addr::UNSPECIFIED),
+        ];
+        std::iter::once(*addr)
+            .chain(wildcards.map(|ip| SocketAddr::new(ip, addr.port())))
+            .find_map(|addr| mutable.socket_owners.get(&addr).copied())
+    }
 }
 
 impl MutableState {
@@ -199,6 +334,26 @@ impl Drop for TaskCountGuard {
     }
 }
 
+/// Guard that removes a socket owner registration from the [`WasiControlPlane`] when dropped.
+#[derive(Debug)]
+pub struct SocketOwnerGuard {
+    control_plane: WasiControlPlaneHandle,
+    addr: SocketAddr,
+    pid: WasiProcessId,
+}
+
+impl Drop for SocketOwnerGuard {
+    fn drop(&mut self) {
+        if let Some(control_plane) = self.control_plane.upgrade() {
+            let mut mutable = control_plane.state.mutable.write().unwrap();
+            // The address may have been taken over by another socket since
+            if mutable.socket_owners.get(&self.addr) == Some(&self.pid) {
+                mutable.socket_owners.remove(&self.addr);
+            }
+        }
+    }
+}
+
 #[derive(thiserror::Error, PartialEq, Eq, Clone, Debug)]
 pub enum ControlPlaneError {
     /// The maximum number of execution tasks has been reached.
@@ -240,6 +395,84 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_control_plane_snapshot() {
+        let p = WasiControlPlane::default();
+
+        let parent = p.new_process(xxhash_random()).unwrap();
+        parent.set_label("role", "supervisor");
+        let main = parent
+            .new_thread(WasiMemoryLayout::default(), ThreadStartType::MainThread)
+            .unwrap();
+        main.set_status_running();
+        let spawned: Vec<_> = (0..2)
+            .map(|_| {
+                parent
+                    .new_thread(
+                        WasiMemoryLayout::default(),
+                        ThreadStartType::ThreadSpawn { start_ptr: 0 },
+                    )
+                    .unwrap()
+            })
+            .collect();
+
+        let child = p.new_process(xxhash_random()).unwrap();
+        let _child_main = child
+            .new_thread(WasiMemoryLayout::default(), ThreadStartType::MainThread)
+            .unwrap();
+        parent.lock().children.push(child.clone());
+
+        let processes = p.processes();
+        assert_eq!(processes.len(), 2);
+        assert_eq!(processes[0].pid, parent.pid());
+        assert_eq!(processes[0].parent, None);
+        assert_eq!(processes[0].thread_count, 3);
+        assert!(processes[0].status.is_running());
+        assert_eq!(processes[0].labels.get("role").unwrap(), "supervisor");
+        assert_eq!(processes[1].pid, child.pid());
+        assert_eq!(processes[1].parent, Some(parent.pid()));
+        assert_eq!(processes[1].thread_count, 1);
+        assert!(processes[1].status.is_pending());
+        assert!(processes[1].labels.is_empty());
+
+        let threads = p.threads(parent.pid()).unwrap();
+        assert_eq!(threads.len(), 3);
+        assert_eq!(threads.iter().filter(|t| t.is_main).count(), 1);
+        assert!(threads.iter().any(|t| t.tid == main.tid()));
+
+        // Threads that exit are no longer part of the snapshot
+        drop(spawned);
+        assert_eq!(p.threads(parent.pid()).unwrap().len(), 1);
+        assert_eq!(p.processes()[0].thread_count, 1);
+
+        assert!(p.threads(WasiProcessId::from(1000u32)).is_none());
+    }
+
+    #[test]
+    fn test_socket_owner_of_wildcard_address() {
+        let p = WasiControlPlane::default();
+        let listener = p.new_process(xxhash_random()).unwrap();
+        let other = p.new_process(xxhash_random()).unwrap();
+
+        let _wildcard = p.register_socket_owner("0.0.0.0:8080".parse().unwrap(), listener.pid());
+        let _exact = p.register_socket_owner("127.0.0.1:9090".parse().unwrap(), other.pid());
+
+        // Connections to any address on the port belong to the wildcard listener
+        assert_eq!(
+            p.socket_owner(&"127.0.0.1:8080".parse().unwrap()),
+            Some(listener.pid())
+        );
+        assert_eq!(
+            p.socket_owner(&"10.0.0.1:8080".parse().unwrap()),
+            Some(listener.pid())
+        );
+        assert_eq!(
+            p.socket_owner(&"127.0.0.1:9090".parse().unwrap()),
+            Some(other.pid())
+        );
+        assert_eq!(p.socket_owner(&"127.0.0.1:7070".parse().unwrap()), None);
+    }
+
     /// Simple test to ensure task limits are respected and that thread drop guards work.
     #[test]
     fn test_control_plane_task_limits_with_dropped_threads() {
@@ -269,4 +502,40 @@ mod tests {
             ControlPlaneError::TaskLimitReached { max: 2 }
         );
     }
+
+    #[test]
+    fn test_control_plane_signal_process_group() {
+        let p = WasiControlPlane::default();
+
+        let leader = p.new_process(xxhash_random()).unwrap();
+        let member = p.new_process(xxhash_random()).unwrap();
+        let outsider = p.new_process(xxhash_random()).unwrap();
+        let threads: Vec<_> = [&leader, &member, &outsider]
+            .into_iter()
+            .map(|process| {
+                process
+                    .new_thread(WasiMemoryLayout::default(), ThreadStartType::MainThread)
+                    .unwrap()
+            })
+            .collect();
+
+        // Every process starts out as the leader of its own group
+        assert_eq!(member.pgid(), member.pid());
+        member.set_pgid(leader.pid());
+
+        let group = p.process_group(leader.pid());
+        assert_eq!(group.len(), 2);
+        assert_eq!(group[0].pid(), leader.pid());
+        assert_eq!(group[1].pid(), member.pid());
+
+        assert_eq!(p.signal_process_group(leader.pid(), Signal::Sigterm), 2);
+        assert_eq!(threads[0].as_thread().pop_signals(), vec![Signal::Sigterm]);
+        assert_eq!(threads[1].as_thread().pop_signals(), vec![Signal::Sigterm]);
+        assert!(threads[2].as_thread().pop_signals().is_empty());
+
+        assert_eq!(
+            p.signal_process_group(WasiProcessId::from(1000u32), Signal::Sigterm),
+            0
+        );
+    }
 }
@@ -1,6 +1,7 @@
 //! OS task management for processes and threads.
 
 pub mod backoff;
+pub mod clock;
 pub mod control_plane;
 pub mod process;
 pub mod signal;
@@ -1,15 +1,14 @@
 #[cfg(feature = "journal")]
 use crate::{journal::JournalEffector, syscalls::do_checkpoint_from_outside, unwind, WasiResult};
 use crate::{journal::SnapshotTrigger, WasiEnv, WasiRuntimeError};
+use derivative::Derivative;
 use serde::{Deserialize, Serialize};
-#[cfg(feature = "journal")]
-use std::collections::HashSet;
 use std::{
-    collections::HashMap,
+    collections::{hash_map::Entry, HashMap, HashSet},
     convert::TryInto,
     ops::Range,
     sync::{
-        atomic::{AtomicU32, Ordering},
+        atomic::{AtomicI64, AtomicU32, AtomicU64, Ordering},
         Arc, Condvar, Mutex, MutexGuard, RwLock, Weak,
     },
     task::Waker,
@@ -32,7 +31,7 @@ use crate::{
 use super::{
     backoff::WasiProcessCpuBackoff,
     control_plane::{ControlPlaneError, WasiControlPlaneHandle},
-    signal::{SignalDeliveryError, SignalHandlerAbi},
+    signal::{SignalDeliveryError, SignalDisposition, SignalHandlerAbi},
     task_join_handle::OwnedTaskStatus,
     thread::WasiMemoryLayout,
     TaskStatus,
@@ -111,6 +110,38 @@ pub struct WasiProcess {
     /// the exponential backoff of CPU is halted (as in CPU
     /// is allowed to run freely)
     pub(crate) cpu_run_tokens: Arc<AtomicU32>,
+    /// Total number of bytes sent over the sockets of this process
+    pub(crate) socket_bytes_sent: Arc<AtomicU64>,
+    /// Total number of bytes received over the sockets of this process
+    pub(crate) socket_bytes_received: Arc<AtomicU64>,
+    /// Maximum number of bytes the sockets of this process may send and
+    /// receive combined (`u64::MAX` when there is no limit)
+    pub(crate) socket_byte_limit: Arc<AtomicU64>,
+    /// Highest reading of the monotonic clock handed out to any of the
+    /// threads of this process
+    pub(crate) monotonic_high_water: Arc<AtomicI64>,
+    /// ID of the process group that this process belongs to
+    pub(crate) pgid: Arc<AtomicU32>,
+    /// ID of the session that this process belongs to
+    pub(crate) sid: Arc<AtomicU32>,
+    /// Contention counters of the futexes of this process keyed by the
+    /// address of the futex (at most [`MAX_TRACKED_FUTEXES`] of them)
+    pub(crate) futex_stats: Arc<Mutex<HashMap<u64, FutexStats>>>,
+}
+
+/// Maximum number of distinct futexes that [`WasiProcess::futex_stats`]
+/// keeps counters for, futexes beyond that are not tracked
+pub const MAX_TRACKED_FUTEXES: usize = 1024;
+
+/// Contention counters of a single futex of a process
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FutexStats {
+    /// Number of times a thread went to sleep on the futex
+    pub waits: u64,
+    /// Number of sleeping threads that were woken by a wake call
+    pub wakes: u64,
+    /// Total time the threads spent asleep on the futex
+    pub total_wait_time: Duration,
 }
 
 /// Represents a freeze of all threads to perform some action
@@ -163,8 +194,24 @@ pub struct WasiProcessInner {
     pub thread_count: u32,
     /// Signals that will be triggered at specific intervals
     pub signal_intervals: HashMap<Signal, WasiSignalInterval>,
+    /// Signals that are blocked from being delivered to the process
+    pub signal_mask: HashSet<Signal>,
+    /// Signals that were raised while blocked, these will be delivered
+    /// once they are unblocked
+    pub pending_signals: Vec<Signal>,
+    /// Set once the guest registered a signal handler with `callback_signal`
+    pub signal_handler: bool,
+    /// Dispositions that the host forced for specific signals, these take
+    /// precedence over whatever the guest configured
+    pub signal_dispositions: HashMap<Signal, SignalDisposition>,
+    /// Signal that terminated the process (if it was terminated by one)
+    pub exit_signal: Option<Signal>,
+    /// Monotonic time (in nanoseconds) at which the process was created
+    pub started_at: u128,
     /// List of all the children spawned from this thread
     pub children: Vec<WasiProcess>,
+    /// Labels that were attached to the process by the host
+    pub labels: HashMap<String, String>,
     /// Represents a checkpoint which blocks all the threads
     /// and then executes some maintenance action
     pub checkpoint: WasiProcessCheckpoint,
@@ -184,6 +231,27 @@ pub struct WasiProcessInner {
     /// which will be used to determine if the CPU should be
     /// throttled or not
     pub(super) backoff: WasiProcessCpuBackoff,
+    /// Threads that trapped and that the host may restart
+    pub(crate) crashed_threads: HashMap<WasiThreadId, CrashedThread>,
+    /// Whether threads that trapped are kept around so that the host can
+    /// restart them (see [`WasiProcess::set_retain_crashed_threads`])
+    pub(crate) retain_crashed_threads: bool,
+}
+
+pub(crate) type RespawnThreadFn = Box<dyn FnMut(Arc<WasiThreadHandle>) -> Result<(), Errno> + Send>;
+
+/// A thread that trapped, it can be restarted from its entry point
+/// with [`WasiProcess::respawn_thread`]
+#[derive(Derivative)]
+#[derivative(Debug)]
+pub(crate) struct CrashedThread {
+    pub layout: WasiMemoryLayout,
+    pub start: ThreadStartType,
+    /// Spawns the entry point of the thread again using the new handle
+    /// (it owns the store of the thread until a respawn succeeds, the mutex
+    /// keeps the process `Sync`), dropping it completes the exit of the thread
+    #[derivative(Debug = "ignore")]
+    pub respawn: Mutex<RespawnThreadFn>,
 }
 
 pub enum MaybeCheckpointResult<'a> {
@@ -418,7 +486,15 @@ impl WasiProcess {
                 threads: Default::default(),
                 thread_count: Default::default(),
                 signal_intervals: Default::default(),
+                signal_mask: Default::default(),
+                pending_signals: Default::default(),
+                signal_handler: false,
+                signal_dispositions: Default::default(),
+                exit_signal: None,
+                started_at: platform_clock_time_get(Snapshot0Clockid::Monotonic, 1)
+                    .unwrap_or_default() as u128,
                 children: Default::default(),
+                labels: Default::default(),
                 checkpoint: WasiProcessCheckpoint::Execute,
                 wakers: Default::default(),
                 waiting: waiting.clone(),
@@ -428,6 +504,8 @@ impl WasiProcess {
                 snapshot_memory_hash: Default::default(),
                 disable_journaling_after_checkpoint: false,
                 backoff: WasiProcessCpuBackoff::new(max_cpu_backoff_time, max_cpu_cool_off_time),
+                crashed_threads: Default::default(),
+                retain_crashed_threads: false,
             }),
             Condvar::new(),
         ));
@@ -457,11 +535,20 @@ impl WasiProcess {
             ),
             waiting,
             cpu_run_tokens: Arc::new(AtomicU32::new(0)),
+            socket_bytes_sent: Arc::new(AtomicU64::new(0)),
+            socket_bytes_received: Arc::new(AtomicU64::new(0)),
+            socket_byte_limit: Arc::new(AtomicU64::new(u64::MAX)),
+            monotonic_high_water: Arc::new(AtomicI64::new(0)),
+            pgid: Arc::new(AtomicU32::new(pid.raw())),
+            sid: Arc::new(AtomicU32::new(pid.raw())),
+            futex_stats: Default::default(),
         }
     }
 
     pub(super) fn set_pid(&mut self, pid: WasiProcessId) {
         self.pid = pid;
+        self.pgid.store(pid.raw(), Ordering::Release);
+        self.sid.store(pid.raw(), Ordering::Release);
     }
 
     /// Gets the process ID of this process
@@ -469,6 +556,34 @@ impl WasiProcess {
         self.pid
     }
 
+    /// Gets the ID of the process group that this process belongs to
+    /// (a process starts out as the leader of its own group)
+    pub fn pgid(&self) -> WasiProcessId {
+        WasiProcessId(self.pgid.load(Ordering::Acquire))
+    }
+
+    /// Moves this process into another process group
+    pub fn set_pgid(&self, pgid: WasiProcessId) {
+        self.pgid.store(pgid.raw(), Ordering::Release);
+    }
+
+    /// Gets the ID of the session that this process belongs to
+    pub fn sid(&self) -> WasiProcessId {
+        WasiProcessId(self.sid.load(Ordering::Acquire))
+    }
+
+    /// Moves this process into another session
+    pub fn set_sid(&self, sid: WasiProcessId) {
+        self.sid.store(sid.raw(), Ordering::Release);
+    }
+
+    /// Puts this (child) process into the process group and session of
+    /// its parent, as is the case for a freshly spawned process
+    pub(crate) fn inherit_process_group(&self, parent: &WasiProcess) {
+        self.set_pgid(parent.pgid());
+        self.set_sid(parent.sid());
+    }
+
     /// Gets the process ID of the parent process
     pub fn ppid(&self) -> WasiProcessId {
         self.parent
@@ -544,6 +659,94 @@ impl WasiProcess {
         Ok(WasiThreadHandle::new(ctrl, &self.inner))
     }
 
+    /// Keeps the threads that trap around (along with their stores) so that
+    /// they can be restarted with [`WasiProcess::respawn_thread`], which is
+    /// off by default. Threads that trap while this is off exit right away,
+    /// turning it off releases the threads that are currently kept.
+    pub fn set_retain_crashed_threads(&self, retain: bool) {
+        let released = {
+            let mut inner = self.inner.0.lock().unwrap();
+            inner.retain_crashed_threads = retain;
+            match retain {
+                true => Default::default(),
+                false => std::mem::take(&mut inner.crashed_threads),
+            }
+        };
+        drop(released);
+    }
+
+    /// Returns true if threads that trap are kept so they can be restarted
+    pub fn retains_crashed_threads(&self) -> bool {
+        self.inner.0.lock().unwrap().retain_crashed_threads
+    }
+
+    /// Returns the IDs of the threads that trapped and that can be
+    /// restarted with [`WasiProcess::respawn_thread`]
+    pub fn crashed_threads(&self) -> Vec<WasiThreadId> {
+        let inner = self.inner.0.lock().unwrap();
+        inner.crashed_threads.keys().copied().collect()
+    }
+
+    pub(crate) fn add_crashed_thread(&self, tid: WasiThreadId, thread: CrashedThread) {
+        let mut inner = self.inner.0.lock().unwrap();
+        inner.crashed_threads.insert(tid, thread);
+    }
+
+    /// Puts back a thread that could not be restarted so it can be retried
+    fn readd_crashed_thread(&self, tid: WasiThreadId, thread: CrashedThread) {
+        let released = {
+            let mut inner = self.inner.0.lock().unwrap();
+            match inner.retain_crashed_threads {
+                true => {
+                    inner.crashed_threads.entry(tid).or_insert(thread);
+                    None
+                }
+                false => Some(thread),
+            }
+        };
+        drop(released);
+    }
+
+    /// Restarts a thread that trapped from its original entry point and
+    /// start arguments on a fresh stack. The thread keeps its ID unless
+    /// another thread took it in the meantime, the ID of the restarted
+    /// thread is returned. Fails with [`Errno::Inval`] if the guest changed
+    /// the start arguments of the thread since it trapped, a thread that
+    /// could not be restarted stays in [`WasiProcess::crashed_threads`].
+    pub fn respawn_thread(&self, tid: WasiThreadId) -> Result<WasiThreadId, Errno> {
+        let (mut crashed, id_is_free) = {
+            let mut inner = self.inner.0.lock().unwrap();
+            let crashed = inner.crashed_threads.remove(&tid).ok_or(Errno::Srch)?;
+            (crashed, !inner.threads.contains_key(&tid))
+        };
+
+        let handle = match id_is_free {
+            true => self.new_thread_with_id(crashed.layout.clone(), crashed.start, tid),
+            false => self.new_thread(crashed.layout.clone(), crashed.start),
+        };
+        let handle = match handle {
+            Ok(handle) => Arc::new(handle),
+            Err(err) => {
+                tracing::error!(
+                    %tid,
+                    error = &err as &dyn std::error::Error,
+                    "failed to create the handle of the restarted thread",
+                );
+                self.readd_crashed_thread(tid, crashed);
+                return Err(Errno::Again);
+            }
+        };
+        let new_tid = handle.tid();
+
+        trace!(%tid, %new_tid, "respawning crashed thread");
+        let respawn = crashed.respawn.get_mut().unwrap();
+        if let Err(err) = respawn(handle) {
+            self.readd_crashed_thread(tid, crashed);
+            return Err(err);
+        }
+        Ok(new_tid)
+    }
+
     /// Gets a reference to a particular thread
     pub fn get_thread(&self, tid: &WasiThreadId) -> Option<WasiThread> {
         let inner = self.inner.0.lock().unwrap();
@@ -562,7 +765,14 @@ impl WasiProcess {
         let pid = self.pid();
         tracing::trace!(%pid, %tid, "signal-thread({:?})", signal);
 
-        let inner = self.inner.0.lock().unwrap();
+        let mut inner = self.inner.0.lock().unwrap();
+        if inner.signal_mask.contains(&signal) {
+            trace!(%pid, %tid, "signal-blocked({:?})", signal);
+            if !inner.pending_signals.contains(&signal) {
+                inner.pending_signals.push(signal);
+            }
+            return;
+        }
         if let Some(thread) = inner.threads.get(&tid) {
             thread.signal(signal);
         } else {
@@ -580,6 +790,168 @@ impl WasiProcess {
         signal_process_internal(&self.inner, signal);
     }
 
+    /// Returns the signals that are currently blocked from being delivered
+    pub fn signal_mask(&self) -> HashSet<Signal> {
+        let inner = self.inner.0.lock().unwrap();
+        inner.signal_mask.clone()
+    }
+
+    /// Replaces the set of signals that are blocked from being delivered,
+    /// any pending signals that are no longer blocked will be delivered
+    ///
+    /// Note: `SIGKILL` and `SIGSTOP` can not be blocked
+    pub fn set_signal_mask(&self, mask: impl IntoIterator<Item = Signal>) {
+        let mut inner = self.inner.0.lock().unwrap();
+        inner.signal_mask = mask
+            .into_iter()
+            .filter(|s| !matches!(s, Signal::Sigkill | Signal::Sigstop))
+            .collect();
+        self.deliver_unblocked_signals(inner);
+    }
+
+    /// Blocks a signal from being delivered to this process, while blocked
+    /// the signal will be queued instead
+    pub fn block_signal(&self, signal: Signal) {
+        if matches!(signal, Signal::Sigkill | Signal::Sigstop) {
+            return;
+        }
+        let mut inner = self.inner.0.lock().unwrap();
+        inner.signal_mask.insert(signal);
+    }
+
+    /// Unblocks a signal, if it is pending then it will be delivered
+    pub fn unblock_signal(&self, signal: Signal) {
+        let mut inner = self.inner.0.lock().unwrap();
+        inner.signal_mask.remove(&signal);
+        self.deliver_unblocked_signals(inner);
+    }
+
+    /// Returns the signals that were raised but not delivered yet, first
+    /// the ones held back by the signal mask and then the ones that are
+    /// queued on the threads of the process
+    pub fn pending_signals(&self) -> Vec<Signal> {
+        let inner = self.inner.0.lock().unwrap();
+        let mut ret = inner.pending_signals.clone();
+        for thread in inner.threads.values() {
+            let guard = thread.signals().lock().unwrap();
+            for signal in guard.0.iter() {
+                if !ret.contains(signal) {
+                    ret.push(*signal);
+                }
+            }
+        }
+        ret
+    }
+
+    /// Discards all the signals that are pending (see
+    /// [`WasiProcess::pending_signals`]) and returns them
+    pub fn clear_pending_signals(&self) -> Vec<Signal> {
+        let mut inner = self.inner.0.lock().unwrap();
+        let mut ret = std::mem::take(&mut inner.pending_signals);
+        for thread in inner.threads.values() {
+            for signal in thread.pop_signals() {
+                if !ret.contains(&signal) {
+                    ret.push(signal);
+                }
+            }
+        }
+        ret
+    }
+
+    /// Returns the disposition of every signal, which reflects the handler
+    /// the guest registered and any disposition the host forced
+    /// (`SIGKILL` and `SIGSTOP` can not be handled so they always report
+    /// [`SignalDisposition::Default`])
+    pub fn signal_dispositions(&self) -> HashMap<Signal, SignalDisposition> {
+        let inner = self.inner.0.lock().unwrap();
+        (Signal::Sighup as u8..=Signal::Sigsys as u8)
+            .filter_map(|signal| Signal::try_from(signal).ok())
+            .map(|signal| {
+                let disposition = match inner.signal_dispositions.get(&signal) {
+                    _ if matches!(signal, Signal::Sigkill | Signal::Sigstop) => {
+                        SignalDisposition::Default
+                    }
+                    Some(disposition) => *disposition,
+                    None if inner.signal_handler => SignalDisposition::Handler,
+                    None => SignalDisposition::Default,
+                };
+                (signal, disposition)
+            })
+            .collect()
+    }
+
+    /// Forces the disposition of a signal regardless of what the guest
+    /// configured (e.g. to make `SIGTERM` take its default action even
+    /// though the guest registered a handler)
+    ///
+    /// Note: The disposition of `SIGKILL` and `SIGSTOP` can not be changed
+    pub fn set_signal_disposition(&self, signal: Signal, disposition: SignalDisposition) {
+        if matches!(signal, Signal::Sigkill | Signal::Sigstop) {
+            return;
+        }
+        let mut inner = self.inner.0.lock().unwrap();
+        inner.signal_dispositions.insert(signal, disposition);
+    }
+
+    /// Removes a disposition forced with [`WasiProcess::set_signal_disposition`]
+    /// so the signal is handled the way the guest configured it again
+    pub fn clear_signal_disposition(&self, signal: Signal) {
+        let mut inner = self.inner.0.lock().unwrap();
+        inner.signal_dispositions.remove(&signal);
+    }
+
+    /// Returns the disposition the host forced for a signal (if any)
+    pub(crate) fn forced_signal_disposition(&self, signal: Signal) -> Option<SignalDisposition> {
+        // SIGKILL and SIGSTOP never reach a guest handler
+        if matches!(signal, Signal::Sigkill | Signal::Sigstop) {
+            return Some(SignalDisposition::Default);
+        }
+        let inner = self.inner.0.lock().unwrap();
+        inner.signal_dispositions.get(&signal).copied()
+    }
+
+    /// Gives this (child) process the signal mask and dispositions of its
+    /// parent, as is the case for a forked process. The signals pending on
+    /// the parent are not inherited.
+    pub(crate) fn inherit_signal_state(&self, parent: &WasiProcess) {
+        let (signal_mask, signal_handler, signal_dispositions) = {
+            let parent = parent.inner.0.lock().unwrap();
+            (
+                parent.signal_mask.clone(),
+                parent.signal_handler,
+                parent.signal_dispositions.clone(),
+            )
+        };
+        let mut inner = self.inner.0.lock().unwrap();
+        inner.signal_mask = signal_mask;
+        inner.signal_handler = signal_handler;
+        inner.signal_dispositions = signal_dispositions;
+    }
+
+    /// Records that the guest registered a signal handler
+    pub(crate) fn set_signal_handler(&self, registered: bool) {
+        let mut inner = self.inner.0.lock().unwrap();
+        inner.signal_handler = registered;
+    }
+
+    fn deliver_unblocked_signals(&self, mut inner: MutexGuard<'_, WasiProcessInner>) {
+        let mask = inner.signal_mask.clone();
+        let mut unblocked = Vec::new();
+        inner.pending_signals.retain(|s| {
+            if mask.contains(s) {
+                true
+            } else {
+                unblocked.push(*s);
+                false
+            }
+        });
+        drop(inner);
+
+        for signal in unblocked {
+            signal_process_internal(&self.inner, signal);
+        }
+    }
+
     /// Takes a snapshot of the process and disables journaling returning
     /// a future that can be waited on for the snapshot to complete
     ///
@@ -705,6 +1077,121 @@ impl WasiProcess {
         );
     }
 
+    /// Attaches a label to the process (replacing any earlier value), labels
+    /// are not interpreted by the runtime but help to identify processes
+    pub fn set_label(&self, key: impl Into<String>, value: impl Into<String>) {
+        let mut inner = self.inner.0.lock().unwrap();
+        inner.labels.insert(key.into(), value.into());
+    }
+
+    /// Returns the labels that are attached to the process
+    pub fn labels(&self) -> HashMap<String, String> {
+        let inner = self.inner.0.lock().unwrap();
+        inner.labels.clone()
+    }
+
+    /// Returns the total number of bytes sent over the sockets of the process
+    pub fn socket_bytes_sent(&self) -> u64 {
+        self.socket_bytes_sent.load(Ordering::Acquire)
+    }
+
+    /// Returns the total number of bytes received over the sockets of the process
+    pub fn socket_bytes_received(&self) -> u64 {
+        self.socket_bytes_received.load(Ordering::Acquire)
+    }
+
+    /// Caps the number of bytes the sockets of the process may send and
+    /// receive combined, a transfer that would cross the cap is cut short
+    /// and once it is reached sending and receiving fails with
+    /// `Errno::Notcapable`
+    pub fn set_socket_byte_limit(&self, limit: Option<u64>) {
+        self.socket_byte_limit
+            .store(limit.unwrap_or(u64::MAX), Ordering::Release);
+    }
+
+    /// Returns the cap on the number of bytes sent and received (if any)
+    pub fn socket_byte_limit(&self) -> Option<u64> {
+        match self.socket_byte_limit.load(Ordering::Acquire) {
+            u64::MAX => None,
+            limit => Some(limit),
+        }
+    }
+
+    /// Returns how many of `len` bytes the sockets of the process may still
+    /// send or receive, which fails once the cap is reached
+    pub(crate) fn socket_byte_allowance(&self, len: usize) -> Result<usize, Errno> {
+        let total = self
+            .socket_bytes_sent()
+            .saturating_add(self.socket_bytes_received());
+        let remaining = self
+            .socket_byte_limit
+            .load(Ordering::Acquire)
+            .saturating_sub(total);
+        if remaining == 0 {
+            return Err(Errno::Notcapable);
+        }
+        Ok(len.min(remaining.try_into().unwrap_or(usize::MAX)))
+    }
+
+    pub(crate) fn add_socket_bytes_sent(&self, amt: usize) {
+        self.socket_bytes_sent
+            .fetch_add(amt as u64, Ordering::AcqRel);
+    }
+
+    pub(crate) fn add_socket_bytes_received(&self, amt: usize) {
+        self.socket_bytes_received
+            .fetch_add(amt as u64, Ordering::AcqRel);
+    }
+
+    /// Returns the contention counters of the futexes that the threads of
+    /// the process waited on, keyed by the address of the futex
+    ///
+    /// Only the first [`MAX_TRACKED_FUTEXES`] futexes that are used by the
+    /// process are tracked so that the counters stay bounded.
+    pub fn futex_stats(&self) -> HashMap<u64, FutexStats> {
+        self.futex_stats.lock().unwrap().clone()
+    }
+
+    fn update_futex_stats(&self, futex: u64, update: impl FnOnce(&mut FutexStats)) {
+        let mut stats = self.futex_stats.lock().unwrap();
+        let len = stats.len();
+        match stats.entry(futex) {
+            Entry::Occupied(mut entry) => update(entry.get_mut()),
+            Entry::Vacant(entry) if len < MAX_TRACKED_FUTEXES => {
+                update(entry.insert(FutexStats::default()))
+            }
+            Entry::Vacant(_) => {}
+        }
+    }
+
+    pub(crate) fn record_futex_wait(&self, futex: u64) {
+        self.update_futex_stats(futex, |stats| stats.waits += 1);
+    }
+
+    pub(crate) fn record_futex_wait_time(&self, futex: u64, time: Duration) {
+        self.update_futex_stats(futex, |stats| stats.total_wait_time += time);
+    }
+
+    pub(crate) fn record_futex_wakes(&self, futex: u64, woken: u64) {
+        if woken == 0 {
+            return;
+        }
+        self.update_futex_stats(futex, |stats| stats.wakes += woken);
+    }
+
+    /// Clamps a reading of the monotonic clock so that the readings seen by
+    /// the threads of the process never decrease, even when the clock of the
+    /// host appears to go backwards slightly across cores
+    pub(crate) fn clamp_monotonic(&self, time: i64) -> i64 {
+        let high_water = self.monotonic_high_water.fetch_max(time, Ordering::AcqRel);
+        time.max(high_water)
+    }
+
+    /// Returns the status of the process
+    pub fn status(&self) -> TaskStatus {
+        self.finished.status()
+    }
+
     /// Returns the number of active threads for this process
     pub fn active_threads(&self) -> u32 {
         let inner = self.inner.0.lock().unwrap();
@@ -783,20 +1270,45 @@ impl WasiProcess {
         Ok(Some((child.pid, code)))
     }
 
+    /// Records the signal that is terminating this process, only the
+    /// first signal is kept
+    pub(crate) fn set_exit_signal(&self, signal: Signal) {
+        let mut inner = self.inner.0.lock().unwrap();
+        inner.exit_signal.get_or_insert(signal);
+    }
+
+    /// Returns the signal that terminated this process (if any)
+    pub fn exit_signal(&self) -> Option<Signal> {
+        let inner = self.inner.0.lock().unwrap();
+        inner.exit_signal
+    }
+
+    /// Amount of time that has passed since the process was created
+    pub fn uptime(&self) -> Duration {
+        let started_at = self.inner.0.lock().unwrap().started_at;
+        let now =
+            platform_clock_time_get(Snapshot0Clockid::Monotonic, 1).unwrap_or_default() as u128;
+        Duration::from_nanos(now.saturating_sub(started_at) as u64)
+    }
+
     /// Terminate the process and all its threads
     pub fn terminate(&self, exit_code: ExitCode) {
         // FIXME: this is wrong, threads might still be running!
         // Need special logic for the main thread.
-        let guard = self.inner.0.lock().unwrap();
+        let mut guard = self.inner.0.lock().unwrap();
         for thread in guard.threads.values() {
             thread.set_status_finished(Ok(exit_code))
         }
+
+        // Threads that trapped can no longer be restarted, release their stores
+        let crashed_threads = std::mem::take(&mut guard.crashed_threads);
+        drop(guard);
+        drop(crashed_threads);
     }
 }
 
 /// Signals all the threads in this process
 fn signal_process_internal(process: &LockableWasiProcessInner, signal: Signal) {
-    #[allow(unused_mut)]
     let mut guard = process.0.lock().unwrap();
     let pid = guard.pid;
     tracing::trace!(%pid, "signal-process({:?})", signal);
@@ -823,6 +1335,15 @@ fn signal_process_internal(process: &LockableWasiProcessInner, signal: Signal) {
         };
     }
 
+    // Blocked signals are queued until they are unblocked
+    if guard.signal_mask.contains(&signal) {
+        tracing::trace!(%pid, "signal-blocked({:?})", signal);
+        if !guard.pending_signals.contains(&signal) {
+            guard.pending_signals.push(signal);
+        }
+        return;
+    }
+
     // Check if there are subprocesses that will receive this signal
     // instead of this process
     if guard.waiting.load(Ordering::Acquire) > 0 {
@@ -852,3 +1373,65 @@ impl SignalHandlerAbi for WasiProcess {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use wasmer_wasix_types::wasix::ThreadStartType;
+
+    use super::*;
+    use crate::{os::task::control_plane::WasiControlPlane, utils::xxhash_random};
+
+    #[test]
+    fn test_pending_signals() {
+        let control_plane = WasiControlPlane::default();
+        let process = control_plane.new_process(xxhash_random()).unwrap();
+        let _thread = process
+            .new_thread(WasiMemoryLayout::default(), ThreadStartType::MainThread)
+            .unwrap();
+
+        // Signals raised while they are blocked are held back
+        process.block_signal(Signal::Sigusr1);
+        process.block_signal(Signal::Sigusr2);
+        process.signal_process(Signal::Sigusr2);
+        process.signal_process(Signal::Sigusr1);
+        process.signal_process(Signal::Sigusr2);
+        assert_eq!(
+            process.pending_signals(),
+            vec![Signal::Sigusr2, Signal::Sigusr1]
+        );
+
+        // Signals that are not blocked are queued on the threads
+        process.signal_process(Signal::Sighup);
+        assert_eq!(
+            process.pending_signals(),
+            vec![Signal::Sigusr2, Signal::Sigusr1, Signal::Sighup]
+        );
+
+        assert_eq!(
+            process.clear_pending_signals(),
+            vec![Signal::Sigusr2, Signal::Sigusr1, Signal::Sighup]
+        );
+        assert!(process.pending_signals().is_empty());
+
+        // Nothing is delivered once the signals are unblocked
+        process.set_signal_mask([]);
+        assert!(process.pending_signals().is_empty());
+    }
+
+    #[test]
+    fn test_futex_stats_are_bounded() {
+        let control_plane = WasiControlPlane::default();
+        let process = control_plane.new_process(xxhash_random()).unwrap();
+
+        for futex in 0..(MAX_TRACKED_FUTEXES as u64 + 16) {
+            process.record_futex_wait(futex * 4);
+        }
+        let stats = process.futex_stats();
+        assert_eq!(stats.len(), MAX_TRACKED_FUTEXES);
+        assert!(!stats.contains_key(&(MAX_TRACKED_FUTEXES as u64 * 4)));
+
+        // The futexes that are already tracked keep being counted
+        process.record_futex_wakes(0, 2);
+        assert_eq!(process.futex_stats()[&0].wakes, 2);
+    }
+}
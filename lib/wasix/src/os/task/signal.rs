@@ -17,6 +17,27 @@ where
 
 pub type DynSignalHandlerAbi = dyn SignalHandlerAbi + Send + Sync + 'static;
 
+/// What happens to a signal that is delivered to a process
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SignalDisposition {
+    /// The default action is taken (see [`signal_terminates_by_default`])
+    Default,
+    /// The signal is discarded
+    Ignore,
+    /// The signal is passed to the handler that the guest registered
+    /// with `callback_signal`
+    Handler,
+}
+
+/// Returns true if the default action of the signal terminates the process,
+/// all the other signals are ignored by default
+pub fn signal_terminates_by_default(signal: Signal) -> bool {
+    matches!(
+        signal,
+        Signal::Sigint | Signal::Sigquit | Signal::Sigkill | Signal::Sigabrt
+    )
+}
+
 #[derive(Debug)]
 pub struct WasiSignalInterval {
     /// Signal that will be raised
@@ -22,8 +22,9 @@ use crate::{
 };
 
 use super::{
+    clock::WasiClock,
     control_plane::TaskCountGuard,
-    task_join_handle::{OwnedTaskStatus, TaskJoinHandle},
+    task_join_handle::{OwnedTaskStatus, TaskJoinHandle, TaskStatus},
 };
 
 /// Represents the ID of a WASI thread
@@ -94,6 +95,28 @@ pub struct ThreadStack {
     next: Option<Box<ThreadStack>>,
 }
 
+/// Execution state of a single thread captured with `stack_checkpoint` that
+/// can be exported from one instance and restored into another one (for
+/// instance to migrate the thread to another host)
+///
+/// The stacks refer to absolute addresses in the linear memory and to the
+/// functions of the module that captured them, hence a checkpoint may only
+/// be restored into an instance of the same module whose thread has the
+/// same memory layout (stack location and size)
+#[derive(Debug, Clone)]
+pub struct ThreadCheckpoint {
+    /// Hash of the stack snapshot as it was returned to the guest
+    pub hash: u128,
+    /// Memory stack (local variables) of the thread
+    pub memory_stack: Bytes,
+    /// Call stack used to rewind the thread back to where it was
+    pub rewind_stack: Bytes,
+    /// Globals of the instance at the time of the checkpoint
+    pub store_data: Bytes,
+    /// Memory layout of the thread that was checkpointed
+    pub layout: WasiMemoryLayout,
+}
+
 /// Represents a running thread which allows a joiner to
 /// wait for the thread to exit
 #[derive(Clone, Debug)]
@@ -242,6 +265,7 @@ struct WasiThreadState {
     #[cfg(feature = "journal")]
     check_pointing: AtomicBool,
     deep_sleeping: AtomicBool,
+    clock: Mutex<Option<Arc<dyn WasiClock>>>,
 
     // Registers the task termination with the ControlPlane on drop.
     // Never accessed, since it's a drop guard.
@@ -271,6 +295,7 @@ impl WasiThread {
                 #[cfg(feature = "journal")]
                 check_pointing: AtomicBool::new(false),
                 deep_sleeping: AtomicBool::new(false),
+                clock: Mutex::new(None),
                 _task_count_guard: guard,
             }),
             layout,
@@ -279,6 +304,17 @@ impl WasiThread {
         }
     }
 
+    /// Overrides the clock that this thread sees (or restores the clocks
+    /// of the host when [`None`] is passed)
+    pub fn set_clock(&self, clock: Option<Arc<dyn WasiClock>>) {
+        *self.state.clock.lock().unwrap() = clock;
+    }
+
+    /// Returns the clock that overrides the time of this thread (if any)
+    pub fn clock(&self) -> Option<Arc<dyn WasiClock>> {
+        self.state.clock.lock().unwrap().clone()
+    }
+
     /// Returns the process ID
     pub fn pid(&self) -> WasiProcessId {
         self.state.pid
@@ -339,6 +375,11 @@ impl WasiThread {
         self.state.status.status().into_finished()
     }
 
+    /// Returns the status of the thread
+    pub fn status(&self) -> TaskStatus {
+        self.state.status.status()
+    }
+
     /// Adds a signal for this thread to process
     pub fn signal(&self, signal: Signal) {
         let mut guard = self.state.signals.lock().unwrap();
@@ -530,6 +571,40 @@ impl WasiThread {
         }
     }
 
+    /// Exports a stack snapshot previously captured by the guest with
+    /// `stack_checkpoint` so that it can be restored elsewhere
+    pub fn export_checkpoint(&self, hash: u128) -> Option<ThreadCheckpoint> {
+        let (memory_stack, rewind_stack, store_data) = self.get_snapshot(hash)?;
+        Some(ThreadCheckpoint {
+            hash,
+            memory_stack: memory_stack.freeze(),
+            rewind_stack,
+            store_data,
+            layout: self.layout.clone(),
+        })
+    }
+
+    /// Imports a checkpoint that was exported from another thread, after
+    /// which it can be resumed with [`crate::WasiEnv::resume_checkpoint`] or
+    /// restored by the guest itself with `stack_restore`
+    pub fn import_checkpoint(&self, checkpoint: &ThreadCheckpoint) -> Result<(), Errno> {
+        if checkpoint.layout != self.layout {
+            tracing::warn!(
+                hash = checkpoint.hash,
+                "thread checkpoint was captured with an incompatible memory layout"
+            );
+            return Err(Errno::Inval);
+        }
+        self.add_snapshot(
+            &checkpoint.memory_stack[..],
+            &checkpoint.memory_stack[..],
+            checkpoint.hash,
+            &checkpoint.rewind_stack[..],
+            &checkpoint.store_data[..],
+        );
+        Ok(())
+    }
+
     // Copy the stacks from another thread
     pub fn copy_stack_from(&self, other: &WasiThread) {
         let mut stack = {
@@ -629,3 +704,50 @@ impl From<WasiThreadError> for Errno {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{os::task::control_plane::WasiControlPlane, utils::xxhash_random};
+
+    fn layout(stack_upper: u64) -> WasiMemoryLayout {
+        WasiMemoryLayout {
+            stack_upper,
+            stack_lower: stack_upper - 1024,
+            guard_size: 0,
+            stack_size: 1024,
+        }
+    }
+
+    #[test]
+    fn test_checkpoint_round_trip() {
+        let control_plane = WasiControlPlane::default();
+        let source = control_plane.new_process(xxhash_random()).unwrap();
+        let target = control_plane.new_process(xxhash_random()).unwrap();
+        let source = source
+            .new_thread(layout(4096), ThreadStartType::MainThread)
+            .unwrap();
+
+        // The guest checkpoints itself at a yield point
+        source.add_snapshot(b"locals", b"locals", 42, b"calls", b"globals");
+        let checkpoint = source.export_checkpoint(42).unwrap();
+        assert!(source.export_checkpoint(43).is_none());
+
+        // The thread is resumed in another (compatible) instance
+        let thread = target
+            .new_thread(layout(4096), ThreadStartType::MainThread)
+            .unwrap();
+        thread.import_checkpoint(&checkpoint).unwrap();
+        let (memory_stack, rewind_stack, store_data) = thread.get_snapshot(42).unwrap();
+        assert_eq!(&memory_stack[..], b"locals");
+        assert_eq!(&rewind_stack[..], b"calls");
+        assert_eq!(&store_data[..], b"globals");
+
+        // Threads with another memory layout can not resume the checkpoint
+        let other = target
+            .new_thread(layout(8192), ThreadStartType::ThreadSpawn { start_ptr: 0 })
+            .unwrap();
+        assert_eq!(other.import_checkpoint(&checkpoint), Err(Errno::Inval));
+        assert!(other.get_snapshot(42).is_none());
+    }
+}
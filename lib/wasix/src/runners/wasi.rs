@@ -379,6 +379,12 @@ impl crate::runners::Runner for WasiRunner {
                                     WasiRuntimeError::Wasi(WasiError::UnknownWasiVersion) => {
                                         WasiRuntimeError::Wasi(WasiError::UnknownWasiVersion)
                                     }
+                                    WasiRuntimeError::Wasi(WasiError::SyscallDenied(a)) => {
+                                        WasiRuntimeError::Wasi(WasiError::SyscallDenied(a.clone()))
+                                    }
+                                    WasiRuntimeError::Wasi(WasiError::InstructionLimitExceeded) => {
+                                        WasiRuntimeError::Wasi(WasiError::InstructionLimitExceeded)
+                                    }
                                     WasiRuntimeError::Wasi(WasiError::DeepSleep(_)) => {
                                         WasiRuntimeError::Anyhow(Arc::new(anyhow::format_err!(
                                             "deep-sleep"
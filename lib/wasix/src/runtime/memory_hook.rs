@@ -0,0 +1,550 @@
+//! Hooks that are invoked whenever the guest grows one of its linear memories.
+//!
+//! The hooks are installed through [`MemoryHookTunables`], a [`Tunables`]
+//! implementation that wraps every memory created by an engine so that calls
+//! to `memory.grow` (from the guest or the host) pass through the registered
+//! [`MemoryGrowHook`]s first.
+
+use std::{
+    fmt,
+    ptr::NonNull,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use wasmer::{
+    vm::{
+        LinearMemory, MemoryError, MemoryStyle, NotifyLocation, TableStyle, ThreadConditions,
+        VMConfig, VMGlobal, VMMemory, VMMemoryDefinition, VMTable, VMTableDefinition, WaiterError,
+    },
+    GlobalType, MemoryType, NativeEngineExt, Pages, TableType, Tunables, WASM_PAGE_SIZE,
+};
+
+use crate::runtime::task_manager::{InlineWaker, VirtualTaskManager};
+
+/// Callback which is notified when a linear memory is grown.
+pub trait MemoryGrowHook
+where
+    Self: fmt::Debug,
+{
+    /// Invoked before the memory is grown by `delta` pages. Returning an
+    /// error fails the grow and leaves the memory untouched.
+    fn before_grow(&self, current: Pages, delta: Pages) -> Result<(), MemoryError> {
+        let _ = (current, delta);
+        Ok(())
+    }
+
+    /// Invoked after the memory was successfully grown.
+    fn after_grow(&self, old: Pages, new: Pages) {
+        let _ = (old, new);
+    }
+
+    /// Invoked when a grow that this hook allowed in
+    /// [`MemoryGrowHook::before_grow`] did not happen after all, either
+    /// because another hook rejected it or because the memory itself
+    /// failed to grow.
+    fn grow_failed(&self, current: Pages, delta: Pages) {
+        let _ = (current, delta);
+    }
+}
+
+pub type DynMemoryGrowHook = Arc<dyn MemoryGrowHook + Send + Sync>;
+
+/// Observer which is told about every successful grow of a linear memory,
+/// useful for accounting of the memory used by a guest.
+pub trait MemoryGrowObserver
+where
+    Self: fmt::Debug,
+{
+    /// Invoked after the memory grew from `old` to `new` pages.
+    fn on_grow(&self, old: Pages, new: Pages);
+}
+
+pub type DynMemoryGrowObserver = Arc<dyn MemoryGrowObserver + Send + Sync>;
+
+/// Adapts a [`MemoryGrowObserver`] so it can be installed as a hook
+#[derive(Debug)]
+pub struct ObserverHook(pub DynMemoryGrowObserver);
+
+impl MemoryGrowHook for ObserverHook {
+    fn after_grow(&self, old: Pages, new: Pages) {
+        self.0.on_grow(old, new);
+    }
+}
+
+/// Returns a copy of `engine` that notifies `observer` whenever a guest grows
+/// its memory, the engine itself is returned when there is no observer.
+pub fn observe_memory_grows(
+    engine: wasmer::Engine,
+    observer: Option<DynMemoryGrowObserver>,
+) -> wasmer::Engine {
+    match observer {
+        Some(observer) => MemoryHookTunables::new(engine)
+            .with_hook(Arc::new(ObserverHook(observer)))
+            .into_engine(),
+        None => engine,
+    }
+}
+
+/// [`Tunables`] that delegate to the tunables of another engine while
+/// attaching a set of [`MemoryGrowHook`]s to every memory that is created.
+#[derive(Clone)]
+pub struct MemoryHookTunables {
+    base: wasmer::Engine,
+    hooks: Arc<Vec<DynMemoryGrowHook>>,
+}
+
+impl MemoryHookTunables {
+    pub fn new(base: wasmer::Engine) -> Self {
+        Self {
+            base,
+            hooks: Default::default(),
+        }
+    }
+
+    pub fn with_hook(mut self, hook: DynMemoryGrowHook) -> Self {
+        Arc::make_mut(&mut self.hooks).push(hook);
+        self
+    }
+
+    pub fn with_hooks(mut self, hooks: impl IntoIterator<Item = DynMemoryGrowHook>) -> Self {
+        Arc::make_mut(&mut self.hooks).extend(hooks);
+        self
+    }
+
+    /// Returns a copy of the base engine that uses these tunables.
+    pub fn into_engine(self) -> wasmer::Engine {
+        let mut engine = self.base.clone();
+        engine.set_tunables(self);
+        engine
+    }
+
+    fn wrap(&self, memory: VMMemory) -> VMMemory {
+        if self.hooks.is_empty() {
+            return memory;
+        }
+        let hooked: Box<dyn LinearMemory + 'static> = Box::new(HookedMemory {
+            inner: memory,
+            hooks: self.hooks.clone(),
+        });
+        VMMemory::from(hooked)
+    }
+}
+
+impl Tunables for MemoryHookTunables {
+    fn memory_style(&self, memory: &MemoryType) -> MemoryStyle {
+        self.base.tunables().memory_style(memory)
+    }
+
+    fn table_style(&self, table: &TableType) -> TableStyle {
+        self.base.tunables().table_style(table)
+    }
+
+    fn create_host_memory(
+        &self,
+        ty: &MemoryType,
+        style: &MemoryStyle,
+    ) -> Result<VMMemory, MemoryError> {
+        let memory = self.base.tunables().create_host_memory(ty, style)?;
+        Ok(self.wrap(memory))
+    }
+
+    unsafe fn create_vm_memory(
+        &self,
+        ty: &MemoryType,
+        style: &MemoryStyle,
+        vm_definition_location: NonNull<VMMemoryDefinition>,
+    ) -> Result<VMMemory, MemoryError> {
+        let memory = self
+            .base
+            .tunables()
+            .create_vm_memory(ty, style, vm_definition_location)?;
+        Ok(self.wrap(memory))
+    }
+
+    fn create_host_table(&self, ty: &TableType, style: &TableStyle) -> Result<VMTable, String> {
+        self.base.tunables().create_host_table(ty, style)
+    }
+
+    unsafe fn create_vm_table(
+        &self,
+        ty: &TableType,
+        style: &TableStyle,
+        vm_definition_location: NonNull<VMTableDefinition>,
+    ) -> Result<VMTable, String> {
+        self.base
+            .tunables()
+            .create_vm_table(ty, style, vm_definition_location)
+    }
+
+    fn create_global(&self, ty: GlobalType) -> Result<VMGlobal, String> {
+        self.base.tunables().create_global(ty)
+    }
+
+    fn vmconfig(&self) -> &VMConfig {
+        self.base.tunables().vmconfig()
+    }
+}
+
+/// Linear memory that runs the hooks around every grow of the inner memory
+#[derive(Debug)]
+struct HookedMemory {
+    inner: VMMemory,
+    hooks: Arc<Vec<DynMemoryGrowHook>>,
+}
+
+impl HookedMemory {
+    fn rewrap(&self, inner: Box<dyn LinearMemory + 'static>) -> Box<dyn LinearMemory + 'static> {
+        Box::new(Self {
+            inner: VMMemory::from(inner),
+            hooks: self.hooks.clone(),
+        })
+    }
+
+    fn before_grow(&self, current: Pages, delta: Pages) -> Result<(), MemoryError> {
+        for (n, hook) in self.hooks.iter().enumerate() {
+            if let Err(err) = hook.before_grow(current, delta) {
+                for hook in self.hooks[..n].iter() {
+                    hook.grow_failed(current, delta);
+                }
+                return Err(err);
+            }
+        }
+        Ok(())
+    }
+
+    fn grow_failed(&self, current: Pages, delta: Pages) {
+        for hook in self.hooks.iter() {
+            hook.grow_failed(current, delta);
+        }
+    }
+
+    fn after_grow(&self, old: Pages, new: Pages) {
+        for hook in self.hooks.iter() {
+            hook.after_grow(old, new);
+        }
+    }
+}
+
+impl LinearMemory for HookedMemory {
+    fn ty(&self) -> MemoryType {
+        self.inner.ty()
+    }
+
+    fn size(&self) -> Pages {
+        self.inner.size()
+    }
+
+    fn style(&self) -> MemoryStyle {
+        self.inner.style()
+    }
+
+    fn grow(&mut self, delta: Pages) -> Result<Pages, MemoryError> {
+        let current = self.inner.size();
+        self.before_grow(current, delta)?;
+        let old = self.inner.grow(delta).map_err(|err| {
+            self.grow_failed(current, delta);
+            err
+        })?;
+        self.after_grow(old, self.inner.size());
+        Ok(old)
+    }
+
+    fn grow_at_least(&mut self, min_size: u64) -> Result<(), MemoryError> {
+        let current = self.inner.size();
+        let missing = min_size.saturating_sub(current.bytes().0 as u64);
+        let delta = Pages(missing.div_ceil(WASM_PAGE_SIZE as u64) as u32);
+        if missing > 0 {
+            self.before_grow(current, delta)?;
+        }
+        self.inner.grow_at_least(min_size).map_err(|err| {
+            if missing > 0 {
+                self.grow_failed(current, delta);
+            }
+            err
+        })?;
+        let new = self.inner.size();
+        if new > current {
+            self.after_grow(current, new);
+        }
+        Ok(())
+    }
+
+    fn reset(&mut self) -> Result<(), MemoryError> {
+        self.inner.reset()
+    }
+
+    fn vmmemory(&self) -> NonNull<VMMemoryDefinition> {
+        self.inner.vmmemory()
+    }
+
+    fn try_clone(&self) -> Result<Box<dyn LinearMemory + 'static>, MemoryError> {
+        Ok(self.rewrap(self.inner.try_clone()?))
+    }
+
+    fn copy(&mut self) -> Result<Box<dyn LinearMemory + 'static>, MemoryError> {
+        let copy = self.inner.copy()?;
+        Ok(self.rewrap(copy))
+    }
+
+    fn do_wait(
+        &mut self,
+        dst: NotifyLocation,
+        timeout: Option<Duration>,
+    ) -> Result<u32, WaiterError> {
+        self.inner.do_wait(dst, timeout)
+    }
+
+    fn do_notify(&mut self, dst: NotifyLocation, count: u32) -> u32 {
+        self.inner.do_notify(dst, count)
+    }
+
+    fn thread_conditions(&self) -> Option<&ThreadConditions> {
+        self.inner.thread_conditions()
+    }
+}
+
+/// Limits how many bytes of linear memory may be grown within a time
+/// interval, smoothing out allocation spikes of the guest.
+///
+/// A single limiter shared between several memories enforces one combined
+/// budget across all of them.
+#[derive(Debug)]
+pub struct MemoryGrowRateLimiter {
+    bytes_per_interval: u64,
+    interval: Duration,
+    delay: Option<Arc<dyn VirtualTaskManager>>,
+    window: Mutex<RateWindow>,
+}
+
+#[derive(Debug)]
+struct RateWindow {
+    started: Instant,
+    granted: u64,
+}
+
+impl MemoryGrowRateLimiter {
+    /// Allows at most `bytes_per_interval` bytes of growth every `interval`,
+    /// grows beyond that budget fail.
+    pub fn new(bytes_per_interval: u64, interval: Duration) -> Self {
+        Self {
+            bytes_per_interval,
+            interval,
+            delay: None,
+            window: Mutex::new(RateWindow {
+                started: Instant::now(),
+                granted: 0,
+            }),
+        }
+    }
+
+    /// Grows that exceed the budget wait (using the sleep of the task
+    /// manager) until the next interval starts rather than failing.
+    ///
+    /// `memory.grow` is synchronous, so the wait blocks the thread that is
+    /// growing the memory (and with it any other work scheduled on that
+    /// thread) for up to a whole interval.
+    pub fn with_delay(mut self, tasks: Arc<dyn VirtualTaskManager>) -> Self {
+        self.delay = Some(tasks);
+        self
+    }
+}
+
+impl MemoryGrowHook for MemoryGrowRateLimiter {
+    fn before_grow(&self, _current: Pages, delta: Pages) -> Result<(), MemoryError> {
+        let requested = delta.bytes().0 as u64;
+        if requested > self.bytes_per_interval {
+            return Err(MemoryError::Generic(format!(
+                "memory grow of {requested} bytes exceeds the rate limit of {} bytes per {:?}",
+                self.bytes_per_interval, self.interval
+            )));
+        }
+
+        loop {
+            let (tasks, remaining) = {
+                let mut window = self.window.lock().unwrap();
+                let elapsed = window.started.elapsed();
+                if elapsed >= self.interval {
+                    window.started = Instant::now();
+                    window.granted = 0;
+                }
+                if window.granted + requested <= self.bytes_per_interval {
+                    window.granted += requested;
+                    return Ok(());
+                }
+                let tasks = self.delay.as_ref().ok_or_else(|| {
+                    MemoryError::Generic(format!(
+                        "memory grow rate limit of {} bytes per {:?} exceeded",
+                        self.bytes_per_interval, self.interval
+                    ))
+                })?;
+                (tasks, self.interval.saturating_sub(elapsed))
+            };
+            InlineWaker::block_on(tasks.sleep_now(remaining));
+        }
+    }
+
+    fn grow_failed(&self, _current: Pages, delta: Pages) {
+        // Hand back the budget of a grow that never happened
+        let mut window = self.window.lock().unwrap();
+        window.granted = window.granted.saturating_sub(delta.bytes().0 as u64);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use wasmer::{imports, Instance, Module, Store, TypedFunction};
+
+    use super::*;
+
+    fn grow_fn(engine: wasmer::Engine) -> (Store, TypedFunction<(), i32>) {
+        let mut store = Store::new(engine);
+        let module = Module::new(
+            &store,
+            r#"
+            (module
+                (memory 1)
+                (func (export "grow") (result i32)
+                    (memory.grow (i32.const 1))))
+            "#,
+        )
+        .unwrap();
+        let instance = Instance::new(&mut store, &module, &imports! {}).unwrap();
+        let grow = instance
+            .exports
+            .get_typed_function::<(), i32>(&store, "grow")
+            .unwrap();
+        (store, grow)
+    }
+
+    #[test]
+    fn rapid_grows_are_throttled() {
+        let limiter =
+            MemoryGrowRateLimiter::new(2 * WASM_PAGE_SIZE as u64, Duration::from_secs(3600));
+        let engine = MemoryHookTunables::new(wasmer::Engine::default())
+            .with_hook(Arc::new(limiter))
+            .into_engine();
+        let (mut store, grow) = grow_fn(engine);
+
+        assert_eq!(grow.call(&mut store).unwrap(), 1);
+        assert_eq!(grow.call(&mut store).unwrap(), 2);
+        // The budget for this interval has been used up
+        assert_eq!(grow.call(&mut store).unwrap(), -1);
+    }
+
+    #[test]
+    fn failed_grows_are_refunded() {
+        /// Rejects the first grow it sees
+        #[derive(Debug, Default)]
+        struct RejectOnce(Mutex<bool>);
+
+        impl MemoryGrowHook for RejectOnce {
+            fn before_grow(&self, _current: Pages, _delta: Pages) -> Result<(), MemoryError> {
+                let mut rejected = self.0.lock().unwrap();
+                if *rejected {
+                    return Ok(());
+                }
+                *rejected = true;
+                Err(MemoryError::Generic("rejected".to_string()))
+            }
+        }
+
+        let limiter =
+            MemoryGrowRateLimiter::new(2 * WASM_PAGE_SIZE as u64, Duration::from_secs(3600));
+        let engine = MemoryHookTunables::new(wasmer::Engine::default())
+            .with_hook(Arc::new(limiter))
+            .with_hook(Arc::new(RejectOnce::default()))
+            .into_engine();
+        let mut store = Store::new(engine);
+        let module = Module::new(
+            &store,
+            r#"
+            (module
+                (memory 1 3)
+                (func (export "grow") (param i32) (result i32)
+                    (memory.grow (local.get 0))))
+            "#,
+        )
+        .unwrap();
+        let instance = Instance::new(&mut store, &module, &imports! {}).unwrap();
+        let grow = instance
+            .exports
+            .get_typed_function::<i32, i32>(&store, "grow")
+            .unwrap();
+
+        // Rejected by the other hook
+        assert_eq!(grow.call(&mut store, 2).unwrap(), -1);
+        // Beyond the maximum of the memory
+        assert_eq!(grow.call(&mut store, 3).unwrap(), -1);
+        // Neither of the failed grows used up the budget
+        assert_eq!(grow.call(&mut store, 2).unwrap(), 1);
+    }
+
+    #[cfg(feature = "sys-thread")]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn throttled_grows_can_be_delayed() {
+        use crate::runtime::task_manager::tokio::TokioTaskManager;
+
+        let interval = Duration::from_millis(100);
+        let started = Instant::now();
+        let limiter = MemoryGrowRateLimiter::new(WASM_PAGE_SIZE as u64, interval)
+            .with_delay(Arc::new(TokioTaskManager::default()));
+        let engine = MemoryHookTunables::new(wasmer::Engine::default())
+            .with_hook(Arc::new(limiter))
+            .into_engine();
+        let (mut store, grow) = grow_fn(engine);
+
+        assert_eq!(grow.call(&mut store).unwrap(), 1);
+        assert_eq!(grow.call(&mut store).unwrap(), 2);
+        assert!(started.elapsed() >= interval);
+    }
+
+    #[derive(Debug, Default)]
+    struct RecordingObserver {
+        events: Mutex<Vec<(Pages, Pages)>>,
+    }
+
+    impl MemoryGrowObserver for RecordingObserver {
+        fn on_grow(&self, old: Pages, new: Pages) {
+            self.events.lock().unwrap().push((old, new));
+        }
+    }
+
+    #[cfg(feature = "sys-thread")]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn runtime_observer_records_grows() {
+        use crate::runtime::{task_manager::tokio::TokioTaskManager, PluggableRuntime, Runtime};
+
+        let observer = Arc::new(RecordingObserver::default());
+        let mut runtime = PluggableRuntime::new(Arc::new(TokioTaskManager::default()));
+        runtime.set_memory_grow_observer(observer.clone());
+
+        let (mut store, grow) = grow_fn(runtime.engine());
+        assert_eq!(grow.call(&mut store).unwrap(), 1);
+        assert_eq!(grow.call(&mut store).unwrap(), 2);
+
+        assert_eq!(
+            *observer.events.lock().unwrap(),
+            vec![(Pages(1), Pages(2)), (Pages(2), Pages(3))]
+        );
+    }
+
+    #[cfg(feature = "sys-thread")]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn overridden_engine_is_observed() {
+        use crate::runtime::{
+            task_manager::tokio::TokioTaskManager, OverriddenRuntime, PluggableRuntime, Runtime,
+        };
+
+        let observer = Arc::new(RecordingObserver::default());
+        let mut runtime = PluggableRuntime::new(Arc::new(TokioTaskManager::default()));
+        runtime.set_memory_grow_observer(observer.clone());
+        let runtime =
+            OverriddenRuntime::new(Arc::new(runtime)).with_engine(wasmer::Engine::default());
+
+        let (mut store, grow) = grow_fn(runtime.engine());
+        assert_eq!(grow.call(&mut store).unwrap(), 1);
+
+        assert_eq!(*observer.events.lock().unwrap(), vec![(Pages(1), Pages(2))]);
+    }
+}
@@ -1,3 +1,5 @@
+#[cfg(feature = "sys")]
+pub mod memory_hook;
 pub mod module_cache;
 pub mod package_loader;
 pub mod resolver;
@@ -11,19 +13,20 @@ use std::{
     fmt,
     ops::Deref,
     sync::{Arc, Mutex},
+    time::Duration,
 };
 
 use derivative::Derivative;
 use futures::future::BoxFuture;
 use virtual_net::{DynVirtualNetworking, VirtualNetworking};
 use wasmer::{Module, RuntimeError};
-use wasmer_wasix_types::wasi::ExitCode;
+use wasmer_wasix_types::{types::Signal, wasi::ExitCode};
 
 #[cfg(feature = "journal")]
 use crate::journal::DynJournal;
 use crate::{
-    http::{DynHttpClient, HttpClient},
-    os::TtyBridge,
+    http::{ConcurrencyLimitedHttpClient, DynHttpClient, HttpClient},
+    os::{task::process::WasiProcessId, TtyBridge},
     runtime::{
         module_cache::{ModuleCache, ThreadLocalCache},
         package_loader::{PackageLoader, UnsupportedPackageLoader},
@@ -39,6 +42,32 @@ pub enum TaintReason {
     RuntimeError(RuntimeError),
 }
 
+/// How a guest process came to an end
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ProcessExitStatus {
+    /// The process exited by itself with an exit code
+    Exited(ExitCode),
+    /// The process was terminated by a signal
+    Signaled(Signal),
+    /// The process was terminated by a trap (or another runtime error),
+    /// this holds the message of the error
+    Trapped(String),
+}
+
+/// Details about a guest process that has ended (see [`Runtime::on_process_exit`])
+#[derive(Debug, Clone)]
+pub struct ProcessExit {
+    /// ID of the process that ended
+    pub pid: WasiProcessId,
+    /// How the process ended
+    pub status: ProcessExitStatus,
+    /// Amount of time the process was running for
+    pub duration: Duration,
+}
+
+/// Callback that is invoked whenever a guest process ends
+pub type ProcessExitHook = Arc<dyn Fn(&ProcessExit) + Send + Sync + 'static>;
+
 /// Runtime components used when running WebAssembly programs.
 ///
 /// Think of this as the "System" in "WebAssembly Systems Interface".
@@ -74,7 +103,10 @@ where
 
     /// Get a [`wasmer::Engine`] for module compilation.
     fn engine(&self) -> wasmer::Engine {
-        wasmer::Engine::default()
+        let engine = wasmer::Engine::default();
+        #[cfg(feature = "sys")]
+        let engine = memory_hook::observe_memory_grows(engine, self.memory_grow_observer());
+        engine
     }
 
     /// Create a new [`wasmer::Store`].
@@ -120,6 +152,10 @@ where
     /// for multiple reasons however the most common is a panic within the process
     fn on_taint(&self, _reason: TaintReason) {}
 
+    /// Callback thats invoked whenever a process ends, either because it exited
+    /// by itself or because it was terminated by a trap or a signal
+    fn on_process_exit(&self, exit: &ProcessExit) {}
+
     /// The list of journals which will be used to restore the state of the
     /// runtime at a particular point in time
     #[cfg(feature = "journal")]
@@ -133,6 +169,12 @@ where
     fn active_journal(&self) -> Option<&'_ DynJournal> {
         None
     }
+
+    /// Observer that is notified whenever a guest grows its linear memory
+    #[cfg(feature = "sys")]
+    fn memory_grow_observer(&self) -> Option<memory_hook::DynMemoryGrowObserver> {
+        None
+    }
 }
 
 pub type DynRuntime = dyn Runtime + Send + Sync;
@@ -211,6 +253,11 @@ pub struct PluggableRuntime {
     pub rt: Arc<dyn VirtualTaskManager>,
     pub networking: DynVirtualNetworking,
     pub http_client: Option<DynHttpClient>,
+    /// Maximum number of requests the HTTP client runs at the same time
+    /// (see [`PluggableRuntime::set_http_concurrency_limit`])
+    http_concurrency_limit: Option<usize>,
+    /// The HTTP client wrapped with the concurrency limit
+    limited_http_client: Option<DynHttpClient>,
     pub package_loader: Arc<dyn PackageLoader + Send + Sync>,
     pub source: Arc<dyn Source + Send + Sync>,
     pub engine: Option<wasmer::Engine>,
@@ -220,6 +267,13 @@ pub struct PluggableRuntime {
     #[cfg(feature = "journal")]
     #[derivative(Debug = "ignore")]
     pub journals: Vec<Arc<DynJournal>>,
+    /// Hooks attached to every linear memory created by the engine
+    #[cfg(feature = "sys")]
+    pub memory_grow_hooks: Vec<memory_hook::DynMemoryGrowHook>,
+    #[cfg(feature = "sys")]
+    pub memory_grow_observer: Option<memory_hook::DynMemoryGrowObserver>,
+    #[derivative(Debug = "ignore")]
+    pub process_exit_hook: Option<ProcessExitHook>,
 }
 
 impl PluggableRuntime {
@@ -249,6 +303,8 @@ impl PluggableRuntime {
             rt,
             networking,
             http_client,
+            http_concurrency_limit: None,
+            limited_http_client: None,
             engine: None,
             tty: None,
             source: Arc::new(source),
@@ -256,6 +312,11 @@ impl PluggableRuntime {
             module_cache: Arc::new(module_cache::in_memory()),
             #[cfg(feature = "journal")]
             journals: Vec::new(),
+            #[cfg(feature = "sys")]
+            memory_grow_hooks: Vec::new(),
+            #[cfg(feature = "sys")]
+            memory_grow_observer: None,
+            process_exit_hook: None,
         }
     }
 
@@ -303,14 +364,72 @@ impl PluggableRuntime {
         client: impl HttpClient + Send + Sync + 'static,
     ) -> &mut Self {
         self.http_client = Some(Arc::new(client));
+        self.limit_http_client();
+        self
+    }
+
+    /// Limits the number of requests the HTTP client of the runtime runs at
+    /// the same time, the requests beyond the limit wait for a slot.
+    ///
+    /// The limit also applies to any client that is set afterwards and
+    /// replaces any limit that was set before.
+    pub fn set_http_concurrency_limit(&mut self, limit: usize) -> &mut Self {
+        self.http_concurrency_limit = Some(limit);
+        self.limit_http_client();
         self
     }
 
+    fn limit_http_client(&mut self) {
+        self.limited_http_client = match (&self.http_client, self.http_concurrency_limit) {
+            (Some(client), Some(limit)) => Some(Arc::new(ConcurrencyLimitedHttpClient::new(
+                client.clone(),
+                limit,
+            ))),
+            _ => None,
+        };
+    }
+
     #[cfg(feature = "journal")]
     pub fn add_journal(&mut self, journal: Arc<DynJournal>) -> &mut Self {
         self.journals.push(journal);
         self
     }
+
+    /// Adds a hook that is invoked whenever the guest grows its memory
+    /// (see [`memory_hook::MemoryGrowRateLimiter`] for throttling growth)
+    #[cfg(feature = "sys")]
+    pub fn add_memory_grow_hook(&mut self, hook: memory_hook::DynMemoryGrowHook) -> &mut Self {
+        self.memory_grow_hooks.push(hook);
+        self
+    }
+
+    /// Sets the observer that is notified whenever the guest grows its memory
+    #[cfg(feature = "sys")]
+    pub fn set_memory_grow_observer(
+        &mut self,
+        observer: memory_hook::DynMemoryGrowObserver,
+    ) -> &mut Self {
+        self.memory_grow_observer = Some(observer);
+        self
+    }
+
+    /// Sets the callback that is invoked whenever a process ends
+    pub fn set_process_exit_hook(
+        &mut self,
+        hook: impl Fn(&ProcessExit) + Send + Sync + 'static,
+    ) -> &mut Self {
+        self.process_exit_hook = Some(Arc::new(hook));
+        self
+    }
+
+    #[cfg(feature = "sys")]
+    fn memory_grow_hooks(&self) -> Vec<memory_hook::DynMemoryGrowHook> {
+        let mut hooks = self.memory_grow_hooks.clone();
+        if let Some(observer) = Runtime::memory_grow_observer(self) {
+            hooks.push(Arc::new(memory_hook::ObserverHook(observer)));
+        }
+        hooks
+    }
 }
 
 impl Runtime for PluggableRuntime {
@@ -319,7 +438,9 @@ impl Runtime for PluggableRuntime {
     }
 
     fn http_client(&self) -> Option<&DynHttpClient> {
-        self.http_client.as_ref()
+        self.limited_http_client
+            .as_ref()
+            .or(self.http_client.as_ref())
     }
 
     fn package_loader(&self) -> Arc<dyn PackageLoader + Send + Sync> {
@@ -331,14 +452,31 @@ impl Runtime for PluggableRuntime {
     }
 
     fn engine(&self) -> wasmer::Engine {
-        if let Some(engine) = self.engine.clone() {
+        let engine = if let Some(engine) = self.engine.clone() {
             engine
         } else {
             wasmer::Engine::default()
+        };
+
+        #[cfg(feature = "sys")]
+        {
+            let hooks = self.memory_grow_hooks();
+            if !hooks.is_empty() {
+                return memory_hook::MemoryHookTunables::new(engine)
+                    .with_hooks(hooks)
+                    .into_engine();
+            }
         }
+
+        engine
     }
 
     fn new_store(&self) -> wasmer::Store {
+        #[cfg(feature = "sys")]
+        if !self.memory_grow_hooks.is_empty() || Runtime::memory_grow_observer(self).is_some() {
+            return wasmer::Store::new(self.engine());
+        }
+
         self.engine
             .clone()
             .map(wasmer::Store::new)
@@ -366,6 +504,17 @@ impl Runtime for PluggableRuntime {
     fn active_journal(&self) -> Option<&DynJournal> {
         self.journals.iter().last().map(|a| a.as_ref())
     }
+
+    #[cfg(feature = "sys")]
+    fn memory_grow_observer(&self) -> Option<memory_hook::DynMemoryGrowObserver> {
+        self.memory_grow_observer.clone()
+    }
+
+    fn on_process_exit(&self, exit: &ProcessExit) {
+        if let Some(hook) = self.process_exit_hook.as_ref() {
+            hook(exit);
+        }
+    }
 }
 
 /// Runtime that allows for certain things to be overridden
@@ -499,6 +648,10 @@ impl Runtime for OverriddenRuntime {
 
     fn engine(&self) -> wasmer::Engine {
         if let Some(engine) = self.engine.clone() {
+            // The memory grows of the guests are still observed on the
+            // engine that overrides the one of the inner runtime
+            #[cfg(feature = "sys")]
+            let engine = memory_hook::observe_memory_grows(engine, self.memory_grow_observer());
             engine
         } else {
             self.inner.engine()
@@ -506,8 +659,8 @@ impl Runtime for OverriddenRuntime {
     }
 
     fn new_store(&self) -> wasmer::Store {
-        if let Some(engine) = self.engine.clone() {
-            wasmer::Store::new(engine)
+        if self.engine.is_some() {
+            wasmer::Store::new(self.engine())
         } else {
             self.inner.new_store()
         }
@@ -547,6 +700,15 @@ impl Runtime for OverriddenRuntime {
         }
     }
 
+    #[cfg(feature = "sys")]
+    fn memory_grow_observer(&self) -> Option<memory_hook::DynMemoryGrowObserver> {
+        self.inner.memory_grow_observer()
+    }
+
+    fn on_process_exit(&self, exit: &ProcessExit) {
+        self.inner.on_process_exit(exit)
+    }
+
     fn load_module<'a>(&'a self, wasm: &'a [u8]) -> BoxFuture<'a, Result<Module, SpawnError>> {
         if self.engine.is_some() || self.module_cache.is_some() {
             let engine = self.engine();
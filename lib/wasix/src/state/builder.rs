@@ -1,23 +1,36 @@
 //! Builder system for configuring a [`WasiState`] and creating it.
 
 use std::{
-    collections::HashMap,
+    collections::{HashMap, HashSet},
+    net::IpAddr,
     path::{Path, PathBuf},
     sync::Arc,
 };
 
+use bytes::Bytes;
 use rand::Rng;
 use thiserror::Error;
-use virtual_fs::{ArcFile, FileSystem, FsError, TmpFileSystem, VirtualFile};
-use wasmer::{AsStoreMut, Extern, Imports, Instance, Module, Store};
+use virtual_fs::{
+    ArcBoxFile, ArcFile, ChannelBackpressure, ChannelFile, FileSystem, FsError, LineBufferedFile,
+    TmpFileSystem, VirtualFile,
+};
+use wasmer::{AsStoreMut, Extern, Imports, Instance, Module, Store, Value};
 
+#[cfg(feature = "host-fs")]
+use crate::fs::TempDirFileSystem;
 #[cfg(feature = "journal")]
 use crate::journal::{DynJournal, SnapshotTrigger};
 use crate::{
     bin_factory::{BinFactory, BinaryPackage},
     capabilities::Capabilities,
-    fs::{WasiFs, WasiFsRoot, WasiInodes},
-    os::task::control_plane::{ControlPlaneConfig, ControlPlaneError, WasiControlPlane},
+    fs::{
+        ErrnoFile, ProcFileSystem, SharedFileSystem, WasiFs, WasiFsRoot, WasiInodes, PROC_MOUNT,
+        READAHEAD_SIZE,
+    },
+    os::{
+        command::SpawnHandler,
+        task::control_plane::{ControlPlaneConfig, ControlPlaneError, WasiControlPlane},
+    },
     state::WasiState,
     syscalls::{
         rewind_ext2,
@@ -27,6 +40,7 @@ use crate::{
     Runtime, WasiEnv, WasiError, WasiFunctionEnv, WasiRuntimeError,
 };
 use wasmer_types::ModuleHash;
+use wasmer_wasix_types::wasi::{Errno, Rights, Signal};
 
 use super::env::WasiEnvInit;
 
@@ -88,6 +102,208 @@ pub struct WasiEnvBuilder {
 
     #[cfg(feature = "ctrlc")]
     pub(super) attach_ctrl_c: bool,
+
+    /// When set the capabilities of the environment are exposed to the
+    /// guest as `WASIX_CAP_*` environment variables
+    pub(super) expose_capabilities: bool,
+
+    /// Signals that are blocked when the process starts
+    pub(super) signal_mask: Vec<Signal>,
+
+    /// When set the environment variables are sorted by name before
+    /// they are handed to the guest
+    pub(super) sort_envs: bool,
+
+    /// When set the guest stdio is connected to the stdio of the host
+    /// process rather than to the default pipes
+    #[cfg(feature = "host-fs")]
+    pub(super) inherit_stdio: bool,
+
+    /// Host directories that are mounted into the file system of the guest
+    /// with their paths translated through a function
+    #[cfg(feature = "host-fs")]
+    pub(super) translated_mounts: Vec<(PathBuf, PathBuf, virtual_fs::TranslatePathFn)>,
+
+    /// Syscalls the guest is allowed to invoke (all of them when not set)
+    pub(super) syscall_allowlist: Option<HashSet<String>>,
+
+    /// What the syscalls the guest imports but that are not implemented do
+    /// (the module fails to link when not set)
+    pub(super) unimplemented_syscall: Option<UnimplementedSyscall>,
+
+    /// Runs some of the programs spawned by the guest natively
+    pub(super) spawn_handler: Option<Arc<dyn SpawnHandler + Send + Sync + 'static>>,
+
+    /// Files (in the `.env` format) that environment variables are loaded from
+    pub(super) env_files: Vec<PathBuf>,
+
+    /// How the data written to `stdout` is buffered before it reaches the
+    /// underlying file
+    pub(super) stdout_buffering: StdoutBuffering,
+    pub(super) stderr_to_stdout: bool,
+
+    /// What reading from `stdin` returns once its writer was closed
+    pub(super) closed_stdin: ClosedStdin,
+
+    /// Where the writes to `stdout` go when no sink was attached to it
+    pub(super) unattached_stdout: UnattachedStdout,
+
+    /// Number of metering points the guest may consume before it is stopped
+    #[cfg(feature = "metering")]
+    pub(super) instruction_limit: Option<u64>,
+
+    /// Directories that bare program names passed to `proc_exec` are looked up in
+    pub(super) exec_search_path: Vec<String>,
+
+    /// Maximum number of bytes the sockets of the process may send and receive
+    pub(super) socket_byte_limit: Option<u64>,
+
+    /// Whether threads that trap are kept so the host can restart them
+    pub(super) retain_crashed_threads: bool,
+
+    /// Static host name mappings consulted before the network resolver
+    pub(super) hosts: HashMap<String, Vec<IpAddr>>,
+
+    /// Maximum size of the datagrams the sockets of the process may send
+    pub(super) max_datagram_size: Option<usize>,
+
+    /// Receive and send timeouts that new sockets start with
+    pub(super) default_socket_read_timeout: Option<std::time::Duration>,
+    pub(super) default_socket_write_timeout: Option<std::time::Duration>,
+
+    /// Errnos returned by the syscalls that are presented to the guest as
+    /// another errno
+    pub(super) errno_remap: HashMap<Errno, Errno>,
+
+    /// Longest path (and path component) that the file system resolves
+    pub(super) max_path_len: Option<usize>,
+    pub(super) max_name_len: Option<usize>,
+    pub(super) max_path_depth: Option<usize>,
+
+    /// Rights that the fds opened under a path are limited to
+    pub(super) path_rights: HashMap<PathBuf, Rights>,
+
+    /// When set a synthetic `/proc` is mounted into the file system
+    pub(super) proc_fs: bool,
+
+    /// Inode store that is shared with other instances
+    pub(super) inodes: Option<WasiInodes>,
+
+    /// Rights held by the stdio file descriptors instead of the defaults
+    pub(super) stdin_rights: Option<Rights>,
+    pub(super) stdout_rights: Option<Rights>,
+    pub(super) stderr_rights: Option<Rights>,
+
+    /// Number of bytes that `fd_advise` reads ahead
+    pub(super) readahead_size: Option<usize>,
+
+    /// Receives the syscalls of a guest that runs in step mode
+    pub(super) syscall_steps: Option<std::sync::mpsc::Sender<SyscallStep>>,
+}
+
+/// Buffering mode of the `stdout` of the guest
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum StdoutBuffering {
+    /// Every write is passed straight through to the underlying file
+    #[default]
+    Unbuffered,
+    /// Writes are held back until a complete line has been written
+    Line,
+}
+
+/// What the guest sees when it reads from a `stdin` whose writer was closed
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ClosedStdin {
+    /// Reads return zero bytes (EOF)
+    #[default]
+    Eof,
+    /// Reads fail with `Errno::Again` so that interactive guests keep
+    /// waiting for input rather than terminating
+    Again,
+}
+
+/// What happens to the data that the guest writes to `stdout` when no sink
+/// was attached with [`WasiEnvBuilder::stdout`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum UnattachedStdout {
+    /// The data is written to the `stdout` of the host process (or kept in
+    /// memory when the `host-fs` feature is disabled)
+    #[default]
+    Host,
+    /// The data is discarded, like writing to `/dev/null`
+    Discard,
+    /// Writes fail with the errno
+    Errno(Errno),
+}
+
+/// Callback that is invoked with the namespace and the name of a syscall
+/// that is not implemented, it returns the errno handed back to the guest
+pub type UnimplementedSyscallHandler = Arc<dyn Fn(&str, &str) -> Errno + Send + Sync + 'static>;
+
+/// Fallback for the syscalls that a module imports but that are not
+/// implemented by this crate
+#[derive(Clone)]
+pub enum UnimplementedSyscall {
+    /// A warning is logged and the errno is returned to the guest
+    Errno(Errno),
+    /// The handler is invoked with the namespace and the name of the
+    /// syscall, the errno it returns is handed back to the guest
+    Handler(UnimplementedSyscallHandler),
+}
+
+/// A syscall that a guest running in step mode is about to make, the guest
+/// is paused until the step is resumed or aborted (dropping the step
+/// resumes it)
+#[derive(Debug)]
+pub struct SyscallStep {
+    pub namespace: String,
+    pub name: String,
+    pub args: Vec<Value>,
+    decision: std::sync::mpsc::SyncSender<bool>,
+}
+
+impl SyscallStep {
+    /// Executes the syscall, the guest then runs until its next syscall
+    pub fn resume(self) {
+        self.decision.send(true).ok();
+    }
+
+    /// Terminates the guest with [`WasiError::SyscallDenied`] instead of
+    /// executing the syscall
+    pub fn abort(self) {
+        self.decision.send(false).ok();
+    }
+
+    /// Hands the syscall over to the host and waits for its decision,
+    /// returns whether the syscall may be executed
+    pub(crate) fn wait(
+        steps: &std::sync::mpsc::Sender<SyscallStep>,
+        namespace: &str,
+        name: &str,
+        args: &[Value],
+    ) -> bool {
+        let (decision, decided) = std::sync::mpsc::sync_channel(1);
+        let step = SyscallStep {
+            namespace: namespace.to_string(),
+            name: name.to_string(),
+            args: args.to_vec(),
+            decision,
+        };
+        // The guest runs freely once the host stopped listening
+        if steps.send(step).is_err() {
+            return true;
+        }
+        decided.recv().unwrap_or(true)
+    }
+}
+
+impl std::fmt::Debug for UnimplementedSyscall {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Errno(errno) => f.debug_tuple("Errno").field(errno).finish(),
+            Self::Handler(_) => f.debug_tuple("Handler").finish(),
+        }
+    }
 }
 
 impl std::fmt::Debug for WasiEnvBuilder {
@@ -112,6 +328,8 @@ impl std::fmt::Debug for WasiEnvBuilder {
 pub enum WasiStateCreationError {
     #[error("bad environment variable format: `{0}`")]
     EnvironmentVariableFormatError(String),
+    #[error("environment file error: `{0}`")]
+    EnvironmentFileError(String),
     #[error("argument contains null byte: `{0}`")]
     ArgumentContainsNulByte(String),
     #[error("preopened directory not found: `{0}`")]
@@ -144,6 +362,81 @@ fn validate_mapped_dir_alias(alias: &str) -> Result<(), WasiStateCreationError>
     Ok(())
 }
 
+/// Parses the contents of a `.env` style file into a list of variables.
+///
+/// Every line holds a `KEY=VALUE` pair (optionally prefixed with `export`),
+/// values may be wrapped in double quotes (which support the `\n`, `\t`,
+/// `\"` and `\\` escapes) or in single quotes (which are taken literally).
+/// Empty lines and lines starting with `#` are ignored, as is anything
+/// after a ` #` in an unquoted value.
+fn parse_env_file(contents: &str) -> Result<Vec<(String, String)>, String> {
+    let mut vars = Vec::new();
+    for (index, line) in contents.lines().enumerate() {
+        let line_no = index + 1;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let line = line
+            .strip_prefix("export ")
+            .map(|line| line.trim_start())
+            .unwrap_or(line);
+
+        let (key, value) = line
+            .split_once('=')
+            .ok_or_else(|| format!("line {line_no}: expected KEY=VALUE"))?;
+        let key = key.trim();
+        if key.is_empty() || key.contains(char::is_whitespace) {
+            return Err(format!("line {line_no}: invalid key \"{key}\""));
+        }
+
+        let value = value.trim();
+        let value = if let Some(quoted) = value.strip_prefix('"') {
+            let mut ret = String::new();
+            let mut chars = quoted.chars();
+            loop {
+                match chars.next() {
+                    Some('"') => break,
+                    Some('\\') => match chars.next() {
+                        Some('n') => ret.push('\n'),
+                        Some('t') => ret.push('\t'),
+                        Some(c @ ('"' | '\\')) => ret.push(c),
+                        Some(c) => {
+                            ret.push('\\');
+                            ret.push(c);
+                        }
+                        None => return Err(format!("line {line_no}: unterminated quote")),
+                    },
+                    Some(c) => ret.push(c),
+                    None => return Err(format!("line {line_no}: unterminated quote")),
+                }
+            }
+            let rest = chars.as_str().trim_start();
+            if !rest.is_empty() && !rest.starts_with('#') {
+                return Err(format!("line {line_no}: unexpected characters after quote"));
+            }
+            ret
+        } else if let Some(quoted) = value.strip_prefix('\'') {
+            let (ret, rest) = quoted
+                .split_once('\'')
+                .ok_or_else(|| format!("line {line_no}: unterminated quote"))?;
+            let rest = rest.trim_start();
+            if !rest.is_empty() && !rest.starts_with('#') {
+                return Err(format!("line {line_no}: unexpected characters after quote"));
+            }
+            ret.to_string()
+        } else {
+            match value.find(" #") {
+                Some(pos) => value[..pos].trim_end().to_string(),
+                None => value.to_string(),
+            }
+        };
+
+        vars.push((key.to_string(), value));
+    }
+    Ok(vars)
+}
+
 pub type SetupFsFn = Box<dyn Fn(&WasiInodes, &mut WasiFs) -> Result<(), String> + Send>;
 
 // TODO add other WasiFS APIs here like swapping out stdout, for example (though we need to
@@ -227,6 +520,27 @@ impl WasiEnvBuilder {
         }
     }
 
+    /// Loads environment variables from a `.env` style file when the
+    /// environment is built.
+    ///
+    /// Variables that are set explicitly (e.g. with [`WasiEnvBuilder::env`])
+    /// take precedence over the ones in the file, as do variables from
+    /// files that were added earlier.
+    pub fn env_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.add_env_file(path);
+        self
+    }
+
+    /// Loads environment variables from a `.env` style file when the
+    /// environment is built.
+    ///
+    /// Variables that are set explicitly (e.g. with [`WasiEnvBuilder::env`])
+    /// take precedence over the ones in the file, as do variables from
+    /// files that were added earlier.
+    pub fn add_env_file(&mut self, path: impl Into<PathBuf>) {
+        self.env_files.push(path.into());
+    }
+
     /// Get a reference to the configured environment variables.
     pub fn get_env(&self) -> &[(String, Vec<u8>)] {
         &self.envs
@@ -427,6 +741,38 @@ impl WasiEnvBuilder {
         Ok(self)
     }
 
+    /// Preopens the root directory of the **host** file system, `/`, at the
+    /// root of the guest with read-only rights.
+    ///
+    /// # Danger
+    ///
+    /// This replaces the sandboxed file system of the guest with the real
+    /// file system of the host: the guest can read every file the host
+    /// process can read, including credentials, keys and the home directory
+    /// of the user. Only use it for quick experiments with trusted guests.
+    ///
+    /// The preopen carries no write rights, so files are opened read-only on
+    /// the host and creating, writing, renaming or removing anything fails.
+    /// Any file system set with [`WasiEnvBuilder::fs`] is replaced.
+    ///
+    /// This must be called from within a tokio runtime.
+    #[cfg(feature = "host-fs")]
+    pub fn preopen_host_root_ro(mut self) -> Result<Self, WasiStateCreationError> {
+        self.add_preopen_host_root_ro()?;
+        Ok(self)
+    }
+
+    #[cfg(feature = "host-fs")]
+    pub fn add_preopen_host_root_ro(&mut self) -> Result<(), WasiStateCreationError> {
+        let handle = tokio::runtime::Handle::try_current().map_err(|err| {
+            WasiStateCreationError::WasiFsSetupError(format!(
+                "Could not preopen the host root: {err}"
+            ))
+        })?;
+        self.set_fs(Box::new(virtual_fs::host_fs::FileSystem::new(handle)));
+        self.add_preopen_build(|p| p.directory("/").read(true))
+    }
+
     /// Preopen a directory and configure it.
     ///
     /// Usage:
@@ -566,6 +912,33 @@ impl WasiEnvBuilder {
         self.stdout = Some(new_file);
     }
 
+    /// Delivers everything the guest writes to `stdout` to a channel, each
+    /// write arriving as a separate message as soon as it is made.
+    ///
+    /// `backpressure` decides what happens to a write when the channel is
+    /// full, the guest either waits for the receiver to catch up or the write
+    /// is dropped or fails.
+    pub fn stdout_channel(
+        mut self,
+        tx: tokio::sync::mpsc::Sender<Bytes>,
+        backpressure: ChannelBackpressure,
+    ) -> Self {
+        self.set_stdout_channel(tx, backpressure);
+        self
+    }
+
+    /// Delivers everything the guest writes to `stdout` to a channel, each
+    /// write arriving as a separate message as soon as it is made.
+    pub fn set_stdout_channel(
+        &mut self,
+        tx: tokio::sync::mpsc::Sender<Bytes>,
+        backpressure: ChannelBackpressure,
+    ) {
+        self.stdout = Some(Box::new(
+            ChannelFile::new(tx).with_backpressure(backpressure),
+        ));
+    }
+
     /// Overwrite the default WASI `stderr`, if you want to hold on to the
     /// original `stderr` use [`WasiFs::swap_file`] after building.
     pub fn stderr(mut self, new_file: Box<dyn VirtualFile + Send + Sync + 'static>) -> Self {
@@ -593,6 +966,92 @@ impl WasiEnvBuilder {
         self.stdin = Some(new_file);
     }
 
+    /// Sets the rights held by `stdin`, the syscalls that need any other right
+    /// fail on it with `Errno::Notcapable`.
+    pub fn stdin_rights(mut self, rights: Rights) -> Self {
+        self.set_stdin_rights(rights);
+        self
+    }
+
+    pub fn set_stdin_rights(&mut self, rights: Rights) {
+        self.stdin_rights = Some(rights);
+    }
+
+    /// Sets the rights held by `stdout`, the syscalls that need any other right
+    /// fail on it with `Errno::Notcapable`.
+    pub fn stdout_rights(mut self, rights: Rights) -> Self {
+        self.set_stdout_rights(rights);
+        self
+    }
+
+    pub fn set_stdout_rights(&mut self, rights: Rights) {
+        self.stdout_rights = Some(rights);
+    }
+
+    /// Sets the rights held by `stderr`, the syscalls that need any other right
+    /// fail on it with `Errno::Notcapable`.
+    pub fn stderr_rights(mut self, rights: Rights) -> Self {
+        self.set_stderr_rights(rights);
+        self
+    }
+
+    pub fn set_stderr_rights(&mut self, rights: Rights) {
+        self.stderr_rights = Some(rights);
+    }
+
+    /// Mounts the host directory `root` at `mountpoint` in the file system of
+    /// the guest, every path below the mount point (e.g. `/42/file.txt` when
+    /// the guest opens `/u/42/file.txt` on a mount at `/u`) is passed through
+    /// `translate` to find the path on the host.
+    ///
+    /// Relative paths returned by `translate` are resolved against `root`, any
+    /// path that ends up outside of `root` is rejected.
+    ///
+    /// Only sandboxed file systems (the default) support mounts.
+    #[cfg(feature = "host-fs")]
+    pub fn mount_translated<F>(
+        mut self,
+        mountpoint: impl Into<PathBuf>,
+        root: impl Into<PathBuf>,
+        translate: F,
+    ) -> Self
+    where
+        F: Fn(&str) -> PathBuf + Send + Sync + 'static,
+    {
+        self.add_mount_translated(mountpoint, root, translate);
+        self
+    }
+
+    #[cfg(feature = "host-fs")]
+    pub fn add_mount_translated<F>(
+        &mut self,
+        mountpoint: impl Into<PathBuf>,
+        root: impl Into<PathBuf>,
+        translate: F,
+    ) where
+        F: Fn(&str) -> PathBuf + Send + Sync + 'static,
+    {
+        self.translated_mounts
+            .push((mountpoint.into(), root.into(), Arc::new(translate)));
+    }
+
+    /// Mounts a synthetic, read-only `/proc` into the file system of the
+    /// guest that serves `/proc/self/cmdline`, `/proc/self/environ` and
+    /// `/proc/self/fd` from the current state of the process.
+    ///
+    /// Processes that are forked or spawned from this one get a directory of
+    /// their own, `/proc/self` always links to the one of the calling process.
+    ///
+    /// Only sandboxed file systems (the default) support mounts.
+    pub fn proc_fs(mut self, enabled: bool) -> Self {
+        self.set_proc_fs(enabled);
+        self
+    }
+
+    pub fn set_proc_fs(&mut self, enabled: bool) {
+        self.proc_fs = enabled;
+    }
+
     /// Sets the FileSystem to be used with this WASI instance.
     ///
     /// This is usually used in case a custom `virtual_fs::FileSystem` is needed.
@@ -605,6 +1064,40 @@ impl WasiEnvBuilder {
         self.fs = Some(WasiFsRoot::Backing(Arc::new(fs)));
     }
 
+    /// Backs the file system with a scratch directory on the host, so that
+    /// the files of the guest spill to disk rather than being kept in memory.
+    /// The directory is created under `base` (the temp directory of the
+    /// system when `None`) and deleted once the file system is dropped.
+    ///
+    /// This replaces any file system set with [`WasiEnvBuilder::fs`] and
+    /// must be called from within a tokio runtime.
+    #[cfg(feature = "host-fs")]
+    pub fn temp_dir_fs(mut self, base: Option<&Path>) -> Result<Self, WasiStateCreationError> {
+        self.set_temp_dir_fs(base)?;
+        Ok(self)
+    }
+
+    #[cfg(feature = "host-fs")]
+    pub fn set_temp_dir_fs(&mut self, base: Option<&Path>) -> Result<(), WasiStateCreationError> {
+        let handle = tokio::runtime::Handle::try_current().map_err(|err| {
+            WasiStateCreationError::WasiFsCreationError(format!(
+                "Could not create the temp directory file system: {err}"
+            ))
+        })?;
+        let base = base
+            .map(Path::to_path_buf)
+            .unwrap_or_else(std::env::temp_dir);
+        let fs = TempDirFileSystem::new_in(&base, handle).map_err(|err| {
+            WasiStateCreationError::WasiFsCreationError(format!(
+                "Could not create a temp directory in '{}': {err}",
+                base.display()
+            ))
+        })?;
+        tracing::debug!(path = %fs.path().display(), "created the temp directory file system");
+        self.set_fs(Box::new(fs));
+        Ok(())
+    }
+
     /// Sets a new sandbox FileSystem to be used with this WASI instance.
     ///
     /// This is usually used in case a custom `virtual_fs::FileSystem` is needed.
@@ -613,6 +1106,22 @@ impl WasiEnvBuilder {
         self
     }
 
+    /// Builds this instance on a file system that is shared with other
+    /// instances, the files that one instance writes can be read by the
+    /// others (even while they are running).
+    ///
+    /// This replaces any file system set with [`WasiEnvBuilder::fs`] or
+    /// [`WasiEnvBuilder::sandbox_fs`].
+    pub fn shared_fs(mut self, fs: &SharedFileSystem) -> Self {
+        self.set_shared_fs(fs);
+        self
+    }
+
+    pub fn set_shared_fs(&mut self, fs: &SharedFileSystem) {
+        self.fs = Some(WasiFsRoot::Sandbox(fs.fs.clone()));
+        self.inodes = Some(fs.inodes.clone());
+    }
+
     /// Configure the WASI filesystem before running.
     // TODO: improve ergonomics on this function
     pub fn setup_fs(mut self, setup_fs_fn: SetupFsFn) -> Self {
@@ -645,6 +1154,392 @@ impl WasiEnvBuilder {
         self.capabilites = capabilities;
     }
 
+    /// Exposes the capabilities of this environment to the guest as a set
+    /// of environment variables (`WASIX_CAP_NET`, `WASIX_CAP_THREADS` and
+    /// `WASIX_CAP_BUS`) which hold either `1` or `0`, so that programs are
+    /// able to gracefully degrade when a feature is not available.
+    ///
+    /// Variables that were explicitly set on the builder are not overridden.
+    pub fn expose_capabilities(mut self, expose: bool) -> Self {
+        self.set_expose_capabilities(expose);
+        self
+    }
+
+    pub fn set_expose_capabilities(&mut self, expose: bool) {
+        self.expose_capabilities = expose;
+    }
+
+    /// Sorts the environment variables by name before they are served to
+    /// the guest (via `environ_get`) so that their order is deterministic.
+    ///
+    /// By default the variables are served in the order they were added.
+    pub fn sort_envs(mut self, sort: bool) -> Self {
+        self.set_sort_envs(sort);
+        self
+    }
+
+    pub fn set_sort_envs(&mut self, sort: bool) {
+        self.sort_envs = sort;
+    }
+
+    /// Connects `stdin`, `stdout` and `stderr` of the guest directly to the
+    /// stdio of the host process (inheriting the terminal if there is one),
+    /// the TTY state reported to the guest is then read from the host.
+    ///
+    /// Any overrides set with [`WasiEnvBuilder::stdin`],
+    /// [`WasiEnvBuilder::stdout`] or [`WasiEnvBuilder::stderr`] are ignored.
+    #[cfg(feature = "host-fs")]
+    pub fn inherit_stdio(mut self) -> Self {
+        self.set_inherit_stdio();
+        self
+    }
+
+    #[cfg(feature = "host-fs")]
+    pub fn set_inherit_stdio(&mut self) {
+        self.inherit_stdio = true;
+    }
+
+    /// Redirects `stderr` of the guest to its `stdout` (like `2>&1` in a
+    /// shell), both descriptors then write to the same file so the output
+    /// ends up in one stream in the order the guest produced it.
+    ///
+    /// Any override set with [`WasiEnvBuilder::stderr`] is ignored.
+    pub fn stderr_to_stdout(mut self) -> Self {
+        self.set_stderr_to_stdout();
+        self
+    }
+
+    pub fn set_stderr_to_stdout(&mut self) {
+        self.stderr_to_stdout = true;
+    }
+
+    /// Sets how the data written by the guest to `stdout` is buffered, in
+    /// [`StdoutBuffering::Line`] mode the reader of `stdout` only sees the
+    /// data once a complete line has been written.
+    pub fn stdout_buffering(mut self, buffering: StdoutBuffering) -> Self {
+        self.set_stdout_buffering(buffering);
+        self
+    }
+
+    pub fn set_stdout_buffering(&mut self, buffering: StdoutBuffering) {
+        self.stdout_buffering = buffering;
+    }
+
+    /// Sets what the guest sees when it reads from `stdin` after the writer
+    /// of `stdin` was closed.
+    ///
+    /// Defaults to [`ClosedStdin::Eof`].
+    pub fn closed_stdin(mut self, behavior: ClosedStdin) -> Self {
+        self.set_closed_stdin(behavior);
+        self
+    }
+
+    pub fn set_closed_stdin(&mut self, behavior: ClosedStdin) {
+        self.closed_stdin = behavior;
+    }
+
+    /// Sets what happens to the writes to `stdout` when no sink was attached
+    /// to it, this has no effect once [`WasiEnvBuilder::stdout`] was called.
+    ///
+    /// Defaults to [`UnattachedStdout::Host`].
+    pub fn unattached_stdout(mut self, behavior: UnattachedStdout) -> Self {
+        self.set_unattached_stdout(behavior);
+        self
+    }
+
+    pub fn set_unattached_stdout(&mut self, behavior: UnattachedStdout) {
+        self.unattached_stdout = behavior;
+    }
+
+    /// Sets the initial signal mask of the process, signals in the mask
+    /// are queued rather than delivered until they are unblocked
+    /// (see [`WasiProcess::unblock_signal`](crate::WasiProcess::unblock_signal))
+    pub fn signal_mask<I>(mut self, signals: I) -> Self
+    where
+        I: IntoIterator<Item = Signal>,
+    {
+        self.set_signal_mask(signals);
+        self
+    }
+
+    pub fn set_signal_mask<I>(&mut self, signals: I)
+    where
+        I: IntoIterator<Item = Signal>,
+    {
+        self.signal_mask = signals.into_iter().collect();
+    }
+
+    /// Restricts the syscalls the guest is allowed to invoke to the given
+    /// list of names (e.g. `fd_write`), calling any other syscall terminates
+    /// the guest with [`WasiError::SyscallDenied`].
+    ///
+    /// By default all syscalls are allowed.
+    pub fn syscall_allowlist<I, S>(mut self, syscalls: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.set_syscall_allowlist(syscalls);
+        self
+    }
+
+    pub fn set_syscall_allowlist<I, S>(&mut self, syscalls: I)
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.syscall_allowlist = Some(syscalls.into_iter().map(Into::into).collect());
+    }
+
+    /// Sets what happens when the guest calls a syscall that it imports but
+    /// that is not implemented, rather than failing to link the module.
+    pub fn unimplemented_syscall(mut self, fallback: UnimplementedSyscall) -> Self {
+        self.set_unimplemented_syscall(fallback);
+        self
+    }
+
+    pub fn set_unimplemented_syscall(&mut self, fallback: UnimplementedSyscall) {
+        self.unimplemented_syscall = Some(fallback);
+    }
+
+    /// Sets a handler that may run the programs spawned by the guest (with
+    /// `proc_spawn`) natively instead of as WASIX processes.
+    pub fn spawn_handler(mut self, handler: Arc<dyn SpawnHandler + Send + Sync + 'static>) -> Self {
+        self.set_spawn_handler(handler);
+        self
+    }
+
+    pub fn set_spawn_handler(&mut self, handler: Arc<dyn SpawnHandler + Send + Sync + 'static>) {
+        self.spawn_handler = Some(handler);
+    }
+
+    /// Sets the directories (in the file system of the guest) that a program
+    /// name without a slash is looked up in when the guest calls `proc_exec`,
+    /// the first directory that holds a file of that name wins.
+    ///
+    /// By default bare program names are not looked up in any directory.
+    pub fn exec_search_path<I, S>(mut self, dirs: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.set_exec_search_path(dirs);
+        self
+    }
+
+    pub fn set_exec_search_path<I, S>(&mut self, dirs: I)
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.exec_search_path = dirs.into_iter().map(Into::into).collect();
+    }
+
+    /// Sets the length (in bytes) of the longest path the guest may pass to
+    /// the file system calls, longer paths fail with `Errno::Nametoolong`.
+    ///
+    /// Defaults to [`MAX_PATH_LEN`](crate::fs::MAX_PATH_LEN).
+    pub fn max_path_len(mut self, len: usize) -> Self {
+        self.set_max_path_len(len);
+        self
+    }
+
+    pub fn set_max_path_len(&mut self, len: usize) {
+        self.max_path_len = Some(len);
+    }
+
+    /// Sets the length (in bytes) of the longest component of a path the
+    /// guest may pass to the file system calls, paths with longer components
+    /// fail with `Errno::Nametoolong`.
+    ///
+    /// Defaults to [`MAX_NAME_LEN`](crate::fs::MAX_NAME_LEN).
+    pub fn max_name_len(mut self, len: usize) -> Self {
+        self.set_max_name_len(len);
+        self
+    }
+
+    pub fn set_max_name_len(&mut self, len: usize) {
+        self.max_name_len = Some(len);
+    }
+
+    /// Sets the number of components of the longest path the file system
+    /// resolves, counting the components of the symlinks that are followed
+    /// along the way. Deeper paths fail with `Errno::Nametoolong`.
+    ///
+    /// Defaults to [`MAX_PATH_DEPTH`](crate::fs::MAX_PATH_DEPTH).
+    pub fn max_path_depth(mut self, depth: usize) -> Self {
+        self.set_max_path_depth(depth);
+        self
+    }
+
+    pub fn set_max_path_depth(&mut self, depth: usize) {
+        self.max_path_depth = Some(depth);
+    }
+
+    /// Limits the rights of the fds that are opened at or under a path of the
+    /// file system to `rights`, on top of the rights of the preopen they are
+    /// opened from. When several paths match, the most specific one applies.
+    ///
+    /// The syscalls that change the entries under the path (such as
+    /// `path_unlink_file`, `path_rename` or `path_create_directory`) are
+    /// held to the same rights.
+    ///
+    /// For example granting a preopen all rights while limiting `/app/config`
+    /// to rights without [`Rights::FD_WRITE`] leaves that directory read-only.
+    pub fn path_rights(mut self, path: impl Into<PathBuf>, rights: Rights) -> Self {
+        self.set_path_rights(path, rights);
+        self
+    }
+
+    pub fn set_path_rights(&mut self, path: impl Into<PathBuf>, rights: Rights) {
+        self.path_rights.insert(path.into(), rights);
+    }
+
+    /// Sets the number of bytes that are read ahead (and then served to the
+    /// reads of the guest) when `fd_advise` is told that a range of a file
+    /// will be needed, a size of zero disables read ahead.
+    pub fn readahead_size(mut self, size: usize) -> Self {
+        self.set_readahead_size(size);
+        self
+    }
+
+    pub fn set_readahead_size(&mut self, size: usize) {
+        self.readahead_size = Some(size);
+    }
+
+    /// Caps the number of bytes the sockets of the process may send and
+    /// receive combined (through the `sock_*` syscalls as well as `fd_read`
+    /// and `fd_write`). A transfer that would cross the cap is cut short,
+    /// once it is reached sending and receiving fails with `Errno::Notcapable`.
+    ///
+    /// The counters can be observed with
+    /// [`WasiProcess::socket_bytes_sent`](crate::WasiProcess::socket_bytes_sent)
+    /// and [`WasiProcess::socket_bytes_received`](crate::WasiProcess::socket_bytes_received).
+    pub fn socket_byte_limit(mut self, limit: u64) -> Self {
+        self.set_socket_byte_limit(limit);
+        self
+    }
+
+    pub fn set_socket_byte_limit(&mut self, limit: u64) {
+        self.socket_byte_limit = Some(limit);
+    }
+
+    /// Keeps the threads of the process that trap (along with their stores)
+    /// so that the host can restart them with
+    /// [`WasiProcess::respawn_thread`](crate::WasiProcess::respawn_thread).
+    /// Without this a thread that traps exits right away.
+    pub fn retain_crashed_threads(mut self, retain: bool) -> Self {
+        self.set_retain_crashed_threads(retain);
+        self
+    }
+
+    pub fn set_retain_crashed_threads(&mut self, retain: bool) {
+        self.retain_crashed_threads = retain;
+    }
+
+    /// Caps the size of the datagrams that `sock_send_to` will send, larger
+    /// datagrams are rejected with `Errno::Msgsize`.
+    ///
+    /// Defaults to [`DEFAULT_MAX_DATAGRAM_SIZE`](crate::net::DEFAULT_MAX_DATAGRAM_SIZE).
+    pub fn max_datagram_size(mut self, size: usize) -> Self {
+        self.set_max_datagram_size(size);
+        self
+    }
+
+    pub fn set_max_datagram_size(&mut self, size: usize) {
+        self.max_datagram_size = Some(size);
+    }
+
+    /// Sets the receive timeout (`SO_RCVTIMEO`) that the sockets opened by
+    /// `sock_open` and `sock_accept` start with, the guest can still change
+    /// it with `sock_set_opt_time`.
+    pub fn default_socket_read_timeout(mut self, timeout: std::time::Duration) -> Self {
+        self.set_default_socket_read_timeout(timeout);
+        self
+    }
+
+    pub fn set_default_socket_read_timeout(&mut self, timeout: std::time::Duration) {
+        self.default_socket_read_timeout = Some(timeout);
+    }
+
+    /// Sets the send timeout (`SO_SNDTIMEO`) that the sockets opened by
+    /// `sock_open` and `sock_accept` start with, the guest can still change
+    /// it with `sock_set_opt_time`.
+    pub fn default_socket_write_timeout(mut self, timeout: std::time::Duration) -> Self {
+        self.set_default_socket_write_timeout(timeout);
+        self
+    }
+
+    pub fn set_default_socket_write_timeout(&mut self, timeout: std::time::Duration) {
+        self.default_socket_write_timeout = Some(timeout);
+    }
+
+    /// Presents the errno `from` to the guest as `to` whenever a syscall
+    /// returns it, for programs that expect a slightly different errno
+    /// numbering (e.g. `ENOTSUP` versus `EOPNOTSUPP`).
+    ///
+    /// The remapping is applied to every syscall so use it with care.
+    pub fn errno_remap(mut self, from: Errno, to: Errno) -> Self {
+        self.set_errno_remap(from, to);
+        self
+    }
+
+    pub fn set_errno_remap(&mut self, from: Errno, to: Errno) {
+        self.errno_remap.insert(from, to);
+    }
+
+    /// Runs the guest in step mode, every syscall it makes is first sent as
+    /// a [`SyscallStep`] (with its name and arguments) to `tx` and the guest
+    /// is paused until the host resumes (or aborts) the step.
+    ///
+    /// The guest has to run on another thread than the one that makes the
+    /// decisions.
+    pub fn step_mode(mut self, tx: std::sync::mpsc::Sender<SyscallStep>) -> Self {
+        self.set_step_mode(tx);
+        self
+    }
+
+    pub fn set_step_mode(&mut self, tx: std::sync::mpsc::Sender<SyscallStep>) {
+        self.syscall_steps = Some(tx);
+    }
+
+    /// Sets static host name mappings (like the `/etc/hosts` file) that the
+    /// `resolve` syscall consults before it asks the network resolver.
+    ///
+    /// Host names are matched case-insensitively.
+    pub fn hosts(mut self, hosts: HashMap<String, Vec<IpAddr>>) -> Self {
+        self.set_hosts(hosts);
+        self
+    }
+
+    pub fn set_hosts(&mut self, hosts: HashMap<String, Vec<IpAddr>>) {
+        self.hosts = hosts
+            .into_iter()
+            .map(|(host, ips)| (host.to_ascii_lowercase(), ips))
+            .collect();
+    }
+
+    /// Limits the number of instructions the guest may execute, once the
+    /// budget is used up the guest is stopped with
+    /// [`WasiError::InstructionLimitExceeded`].
+    ///
+    /// The module must be compiled by an engine that has the
+    /// [`Metering`](wasmer_middlewares::Metering) middleware installed, the
+    /// limit replaces the initial limit of the middleware.
+    ///
+    /// The limit covers all the threads of the guest, a thread that is
+    /// spawned takes half of the budget that remains for its parent.
+    #[cfg(feature = "metering")]
+    pub fn instruction_limit(mut self, limit: u64) -> Self {
+        self.set_instruction_limit(limit);
+        self
+    }
+
+    #[cfg(feature = "metering")]
+    pub fn set_instruction_limit(&mut self, limit: u64) {
+        self.instruction_limit = Some(limit);
+    }
+
     #[cfg(feature = "journal")]
     pub fn add_snapshot_trigger(&mut self, on: SnapshotTrigger) {
         self.snapshot_on.push(on);
@@ -709,6 +1604,23 @@ impl WasiEnvBuilder {
     /// Use [`WasiEnvBuilder::run`] or [`WasiEnvBuilder::run_with_store`] instead
     /// to ensure proper invokation of WASI modules.
     pub fn build_init(mut self) -> Result<WasiEnvInit, WasiStateCreationError> {
+        for path in std::mem::take(&mut self.env_files) {
+            let contents = std::fs::read_to_string(&path).map_err(|err| {
+                WasiStateCreationError::EnvironmentFileError(format!(
+                    "could not read '{}': {err}",
+                    path.display()
+                ))
+            })?;
+            let vars = parse_env_file(&contents).map_err(|err| {
+                WasiStateCreationError::EnvironmentFileError(format!("{}: {err}", path.display()))
+            })?;
+            for (key, value) in vars {
+                if !self.envs.iter().any(|(k, _)| *k == key) {
+                    self.envs.push((key, value.into_bytes()));
+                }
+            }
+        }
+
         for arg in self.args.iter() {
             for b in arg.as_bytes().iter() {
                 if *b == 0 {
@@ -767,6 +1679,45 @@ impl WasiEnvBuilder {
         //     .clone()
         //     .unwrap_or_else(|| Arc::new(PluggableRuntimeImplementation::default()));
 
+        #[cfg(feature = "host-fs")]
+        if self.inherit_stdio {
+            self.stdin = Some(Box::new(ArcFile::new(
+                Box::<virtual_fs::host_fs::Stdin>::default(),
+            )));
+            self.stdout = Some(Box::<virtual_fs::host_fs::Stdout>::default());
+            self.stderr = Some(Box::<virtual_fs::host_fs::Stderr>::default());
+        }
+
+        if self.stdout.is_none() {
+            match self.unattached_stdout {
+                UnattachedStdout::Host => {}
+                UnattachedStdout::Discard => {
+                    self.stdout = Some(Box::<virtual_fs::NullFile>::default());
+                }
+                UnattachedStdout::Errno(errno) => {
+                    self.stdout = Some(Box::new(ErrnoFile::new(errno)));
+                }
+            }
+        }
+
+        if self.stdout_buffering == StdoutBuffering::Line {
+            let stdout = self
+                .stdout
+                .take()
+                .unwrap_or_else(|| Box::<super::Stdout>::default());
+            self.stdout = Some(Box::new(LineBufferedFile::new(stdout)));
+        }
+
+        if self.stderr_to_stdout {
+            let stdout = ArcBoxFile::new(
+                self.stdout
+                    .take()
+                    .unwrap_or_else(|| Box::<super::Stdout>::default()),
+            );
+            self.stdout = Some(Box::new(stdout.clone()));
+            self.stderr = Some(Box::new(stdout));
+        }
+
         // Determine the STDIN
         let stdin: Box<dyn VirtualFile + Send + Sync + 'static> = self
             .stdin
@@ -778,6 +1729,58 @@ impl WasiEnvBuilder {
             .take()
             .unwrap_or_else(|| WasiFsRoot::Sandbox(Arc::new(TmpFileSystem::new())));
 
+        #[cfg(feature = "host-fs")]
+        for (mountpoint, root, translate) in self.translated_mounts.drain(..) {
+            let sandbox = match &fs_backing {
+                WasiFsRoot::Sandbox(sandbox) => sandbox,
+                WasiFsRoot::Backing(_) => {
+                    return Err(WasiStateCreationError::WasiFsSetupError(format!(
+                        "Could not mount '{}', only sandboxed file systems support mounts",
+                        mountpoint.display()
+                    )));
+                }
+            };
+            // The parent directories of the mount point must exist
+            for dir in mountpoint
+                .ancestors()
+                .skip(1)
+                .collect::<Vec<_>>()
+                .into_iter()
+                .rev()
+            {
+                match sandbox.create_dir(dir) {
+                    Ok(()) | Err(FsError::AlreadyExists) => {}
+                    Err(_) if dir == Path::new("/") => {}
+                    Err(err) => {
+                        return Err(WasiStateCreationError::WasiFsSetupError(format!(
+                            "Could not create '{}': {err}",
+                            dir.display()
+                        )));
+                    }
+                }
+            }
+            let handle = tokio::runtime::Handle::try_current().map_err(|err| {
+                WasiStateCreationError::WasiFsSetupError(format!(
+                    "Could not mount '{}': {err}",
+                    mountpoint.display()
+                ))
+            })?;
+            let fs: Arc<dyn FileSystem + Send + Sync> =
+                Arc::new(virtual_fs::TranslatedFileSystem::new(
+                    root,
+                    translate,
+                    virtual_fs::host_fs::FileSystem::new(handle),
+                ));
+            sandbox
+                .mount(mountpoint.clone(), &fs, PathBuf::from("/"))
+                .map_err(|err| {
+                    WasiStateCreationError::WasiFsSetupError(format!(
+                        "Could not mount '{}': {err}",
+                        mountpoint.display()
+                    ))
+                })?;
+        }
+
         if let Some(dir) = &self.current_dir {
             match fs_backing.read_dir(dir) {
                 Ok(_) => {
@@ -801,8 +1804,8 @@ impl WasiEnvBuilder {
         }
 
         // self.preopens are checked in [`PreopenDirBuilder::build`]
-        let inodes = crate::state::WasiInodes::new();
-        let wasi_fs = {
+        let inodes = self.inodes.take().unwrap_or_default();
+        let mut wasi_fs = {
             // self.preopens are checked in [`PreopenDirBuilder::build`]
             let mut wasi_fs =
                 WasiFs::new_with_preopen(&inodes, &self.preopens, &self.vfs_preopens, fs_backing)
@@ -825,6 +1828,29 @@ impl WasiEnvBuilder {
                     .map_err(WasiStateCreationError::FileSystemError)?;
             }
 
+            for (fd, rights) in [
+                (__WASI_STDIN_FILENO, self.stdin_rights),
+                (__WASI_STDOUT_FILENO, self.stdout_rights),
+                (__WASI_STDERR_FILENO, self.stderr_rights),
+            ] {
+                if let Some(rights) = rights {
+                    wasi_fs
+                        .set_std_dev_rights(fd, rights)
+                        .map_err(WasiStateCreationError::FileSystemError)?;
+                }
+            }
+
+            if let Some(len) = self.max_path_len {
+                wasi_fs.max_path_len = len;
+            }
+            if let Some(len) = self.max_name_len {
+                wasi_fs.max_name_len = len;
+            }
+            if let Some(depth) = self.max_path_depth {
+                wasi_fs.max_path_depth = depth;
+            }
+            wasi_fs.path_rights = self.path_rights.clone();
+
             if let Some(f) = &self.setup_fs_fn {
                 f(&inodes, &mut wasi_fs).map_err(WasiStateCreationError::WasiFsSetupError)?;
             }
@@ -841,18 +1867,7 @@ impl WasiEnvBuilder {
             wasi_fs.set_current_dir(s);
         }
 
-        let state = WasiState {
-            fs: wasi_fs,
-            secret: rand::thread_rng().gen::<[u8; 32]>(),
-            inodes,
-            args: self.args.clone(),
-            preopen: self.vfs_preopens.clone(),
-            futexs: Default::default(),
-            clock_offset: Default::default(),
-            envs: std::sync::Mutex::new(conv_env_vars(self.envs)),
-        };
-
-        let runtime = self.runtime.unwrap_or_else(|| {
+        let runtime = self.runtime.take().unwrap_or_else(|| {
             #[cfg(feature = "sys-thread")]
             {
                 #[allow(unused_mut)]
@@ -870,6 +1885,84 @@ impl WasiEnvBuilder {
             }
         });
 
+        // The guest is talking to the host terminal so it should also see its state
+        #[cfg(feature = "host-fs")]
+        let runtime: Arc<dyn crate::Runtime + Send + Sync> = if self.inherit_stdio {
+            Arc::new(
+                crate::runtime::OverriddenRuntime::new(runtime)
+                    .with_tty(Arc::new(crate::os::tty_sys::SysTty)),
+            )
+        } else {
+            runtime
+        };
+
+        if self.expose_capabilities {
+            let threading = &self.capabilites.threading;
+            let caps = [
+                ("WASIX_CAP_NET", runtime.networking().is_supported()),
+                (
+                    "WASIX_CAP_THREADS",
+                    threading.max_threads.map(|max| max > 1).unwrap_or(true),
+                ),
+                // There is no bus implementation in this runtime
+                ("WASIX_CAP_BUS", false),
+            ];
+            for (key, supported) in caps {
+                if !self.envs.iter().any(|(k, _)| k == key) {
+                    self.envs.push((
+                        key.to_string(),
+                        if supported {
+                            b"1".to_vec()
+                        } else {
+                            b"0".to_vec()
+                        },
+                    ));
+                }
+            }
+        }
+
+        if self.sort_envs {
+            // Stable sort so that duplicated keys keep their relative order
+            self.envs.sort_by(|(a, _), (b, _)| a.cmp(b));
+        }
+
+        let envs = Arc::new(std::sync::Mutex::new(conv_env_vars(self.envs)));
+
+        if self.proc_fs {
+            let sandbox = match &wasi_fs.root_fs {
+                WasiFsRoot::Sandbox(sandbox) => sandbox,
+                WasiFsRoot::Backing(_) => {
+                    return Err(WasiStateCreationError::WasiFsSetupError(
+                        "Could not mount '/proc', only sandboxed file systems support mounts"
+                            .to_string(),
+                    ));
+                }
+            };
+            let proc_fs = ProcFileSystem::default();
+            let fs: Arc<dyn FileSystem + Send + Sync> = Arc::new(proc_fs.clone());
+            sandbox
+                .mount(PathBuf::from(PROC_MOUNT), &fs, PathBuf::from("/"))
+                .map_err(|err| {
+                    WasiStateCreationError::WasiFsSetupError(format!(
+                        "Could not mount '/proc': {err}"
+                    ))
+                })?;
+            // The directory of the process is added once the process exists
+            wasi_fs.proc_fs = Some(proc_fs);
+        }
+
+        let state = WasiState {
+            fs: wasi_fs,
+            secret: rand::thread_rng().gen::<[u8; 32]>(),
+            inodes,
+            args: self.args.clone(),
+            preopen: self.vfs_preopens.clone(),
+            futexs: Default::default(),
+            clock_offset: Default::default(),
+            envs,
+            readahead_size: self.readahead_size.unwrap_or(READAHEAD_SIZE),
+        };
+
         let uses = self.uses;
         let map_commands = self.map_commands;
 
@@ -903,6 +1996,24 @@ impl WasiEnvBuilder {
             extra_tracing: true,
             #[cfg(feature = "journal")]
             snapshot_on: self.snapshot_on,
+            signal_mask: self.signal_mask,
+            syscall_allowlist: self.syscall_allowlist.map(Arc::new),
+            unimplemented_syscall: self.unimplemented_syscall,
+            spawn_handler: self.spawn_handler,
+            exec_search_path: Arc::new(self.exec_search_path),
+            socket_byte_limit: self.socket_byte_limit,
+            retain_crashed_threads: self.retain_crashed_threads,
+            hosts: Arc::new(self.hosts),
+            max_datagram_size: self
+                .max_datagram_size
+                .unwrap_or(crate::net::DEFAULT_MAX_DATAGRAM_SIZE),
+            default_socket_read_timeout: self.default_socket_read_timeout,
+            default_socket_write_timeout: self.default_socket_write_timeout,
+            errno_remap: Arc::new(self.errno_remap),
+            syscall_steps: self.syscall_steps,
+            closed_stdin: self.closed_stdin,
+            #[cfg(feature = "metering")]
+            instruction_limit: self.instruction_limit,
             additional_imports: self.additional_imports,
         };
 
@@ -1018,6 +2129,8 @@ impl WasiEnvBuilder {
         env.data(&store).thread.set_status_running();
 
         let result = crate::run_wasi_func_start(start, store);
+        #[cfg(feature = "metering")]
+        let result = env.map_instruction_limit(store, result);
         let (result, exit_code) = super::wasi_exit_code(result);
 
         let pid = env.data(&store).pid();
@@ -1031,6 +2144,8 @@ impl WasiEnvBuilder {
         );
 
         env.on_exit(store, Some(exit_code));
+        env.data(&store)
+            .report_process_exit(exit_code, result.as_ref().err());
 
         result
     }
@@ -1190,6 +2305,32 @@ impl PreopenDirBuilder {
 mod test {
     use super::*;
 
+    #[cfg(feature = "host-fs")]
+    #[test]
+    fn temp_dir_fs_is_created_under_the_base_and_removed_on_drop() {
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        let _guard = runtime.enter();
+
+        let base = tempfile::tempdir().unwrap();
+        let init = WasiEnvBuilder::new("test_prog")
+            .temp_dir_fs(Some(base.path()))
+            .unwrap()
+            .build_init()
+            .unwrap();
+
+        let dirs = std::fs::read_dir(base.path())
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .collect::<Vec<_>>();
+        assert_eq!(dirs.len(), 1);
+
+        drop(init);
+        assert!(!dirs[0].exists());
+    }
+
     #[test]
     fn env_var_errors() {
         #[cfg(not(target_arch = "wasm32"))]
@@ -1239,6 +2380,225 @@ mod test {
         );
     }
 
+    #[cfg(feature = "sys-thread")]
+    #[test]
+    fn capabilities_exposed_as_env_vars() {
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        let handle = runtime.handle().clone();
+        let _guard = handle.enter();
+
+        let mut rt = crate::runtime::PluggableRuntime::new(Arc::new(
+            crate::runtime::task_manager::tokio::TokioTaskManager::default(),
+        ));
+        rt.set_networking_implementation(virtual_net::UnsupportedVirtualNetworking::default());
+
+        let init = WasiEnvBuilder::new("test_prog")
+            .runtime(Arc::new(rt))
+            .expose_capabilities(true)
+            .build_init()
+            .unwrap();
+
+        let envs = init.state.envs.lock().unwrap();
+        assert!(envs.contains(&b"WASIX_CAP_NET=0".to_vec()));
+        assert!(envs.contains(&b"WASIX_CAP_THREADS=1".to_vec()));
+        assert!(envs.contains(&b"WASIX_CAP_BUS=0".to_vec()));
+    }
+
+    #[test]
+    fn sorted_env_vars() {
+        #[cfg(not(target_arch = "wasm32"))]
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        #[cfg(not(target_arch = "wasm32"))]
+        let handle = runtime.handle().clone();
+        #[cfg(not(target_arch = "wasm32"))]
+        let _guard = handle.enter();
+
+        let builder = || {
+            WasiEnvBuilder::new("test_prog")
+                .env("ZED", "1")
+                .env("ALPHA", "2")
+                .env("MIDDLE", "3")
+        };
+
+        let init = builder().build_init().unwrap();
+        assert_eq!(
+            *init.state.envs.lock().unwrap(),
+            vec![b"ZED=1".to_vec(), b"ALPHA=2".to_vec(), b"MIDDLE=3".to_vec()]
+        );
+
+        let init = builder().sort_envs(true).build_init().unwrap();
+        assert_eq!(
+            *init.state.envs.lock().unwrap(),
+            vec![b"ALPHA=2".to_vec(), b"MIDDLE=3".to_vec(), b"ZED=1".to_vec()]
+        );
+    }
+
+    #[test]
+    fn parse_env_files() {
+        let vars = parse_env_file(
+            r#"
+            # A comment
+            PLAIN=value
+            export EXPORTED = spaced # trailing comment
+            DOUBLE="line\nbreak \"quoted\" # not a comment"
+            SINGLE='raw \n value'
+            EMPTY=
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            vars,
+            vec![
+                ("PLAIN".to_string(), "value".to_string()),
+                ("EXPORTED".to_string(), "spaced".to_string()),
+                (
+                    "DOUBLE".to_string(),
+                    "line\nbreak \"quoted\" # not a comment".to_string()
+                ),
+                ("SINGLE".to_string(), "raw \\n value".to_string()),
+                ("EMPTY".to_string(), String::new()),
+            ]
+        );
+
+        assert!(parse_env_file("NO_EQUALS").is_err());
+        assert!(parse_env_file("BAD KEY=1").is_err());
+        assert!(parse_env_file("UNTERMINATED=\"value").is_err());
+        assert!(parse_env_file("TRAILING='value' junk").is_err());
+    }
+
+    #[test]
+    fn env_vars_from_file() {
+        #[cfg(not(target_arch = "wasm32"))]
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        #[cfg(not(target_arch = "wasm32"))]
+        let handle = runtime.handle().clone();
+        #[cfg(not(target_arch = "wasm32"))]
+        let _guard = handle.enter();
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(".env");
+        std::fs::write(&path, "HOME=/from/file\nUSER='wasix'\n").unwrap();
+
+        let init = WasiEnvBuilder::new("test_prog")
+            .env("HOME", "/explicit")
+            .env_file(&path)
+            .build_init()
+            .unwrap();
+        assert_eq!(
+            *init.state.envs.lock().unwrap(),
+            vec![b"HOME=/explicit".to_vec(), b"USER=wasix".to_vec()]
+        );
+
+        std::fs::write(&path, "USER=\"wasix").unwrap();
+        let err = WasiEnvBuilder::new("test_prog")
+            .env_file(&path)
+            .build_init()
+            .expect_err("should fail");
+        assert!(matches!(
+            err,
+            WasiStateCreationError::EnvironmentFileError(_)
+        ));
+    }
+
+    #[cfg(all(feature = "sys-thread", feature = "host-fs"))]
+    #[test]
+    fn inherited_stdio_is_backed_by_the_host() {
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        let handle = runtime.handle().clone();
+        let _guard = handle.enter();
+
+        let (stdout_tx, _stdout_rx) = crate::Pipe::channel();
+        let init = WasiEnvBuilder::new("test_prog")
+            .stdout(Box::new(stdout_tx))
+            .inherit_stdio()
+            .build_init()
+            .unwrap();
+
+        let fd_map = &init.state.fs.fd_map;
+        let stdin = WasiInodes::stdin(fd_map).unwrap();
+        assert_eq!(stdin.get_special_fd(), Some(__WASI_STDIN_FILENO));
+        let stdout = WasiInodes::stdout(fd_map).unwrap();
+        assert_eq!(stdout.get_special_fd(), Some(__WASI_STDOUT_FILENO));
+        let stderr = WasiInodes::stderr(fd_map).unwrap();
+        assert_eq!(stderr.get_special_fd(), Some(__WASI_STDERR_FILENO));
+
+        let tty = init.runtime.tty().unwrap().tty_get();
+        assert_eq!(
+            tty.stdout_tty,
+            std::io::IsTerminal::is_terminal(&std::io::stdout())
+        );
+    }
+
+    #[cfg(feature = "sys-thread")]
+    #[test]
+    fn masked_signals_are_pending_until_unblocked() {
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        let handle = runtime.handle().clone();
+        let _guard = handle.enter();
+
+        let env = WasiEnvBuilder::new("test_prog")
+            .signal_mask([Signal::Sigint])
+            .build()
+            .unwrap();
+
+        env.process.signal_process(Signal::Sigint);
+        assert!(env.process.lock().pending_signals.contains(&Signal::Sigint));
+        assert!(!env.thread.has_signal(&[Signal::Sigint]));
+
+        env.process.unblock_signal(Signal::Sigint);
+        assert!(env.process.lock().pending_signals.is_empty());
+        assert!(env.thread.has_signal(&[Signal::Sigint]));
+    }
+
+    #[cfg(feature = "sys-thread")]
+    #[test]
+    fn exec_search_path_resolves_bare_names() {
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        let handle = runtime.handle().clone();
+        let _guard = handle.enter();
+
+        let fs = virtual_fs::mem_fs::FileSystem::default();
+        fs.create_dir(Path::new("/opt")).unwrap();
+        fs.create_dir(Path::new("/opt/bin")).unwrap();
+        fs.new_open_options()
+            .write(true)
+            .create(true)
+            .open("/opt/bin/prog")
+            .unwrap();
+
+        let env = WasiEnvBuilder::new("test_prog")
+            .fs(Box::new(fs))
+            .exec_search_path(["/usr/bin", "/opt", "/opt/bin/"])
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            env.resolve_exec_path("prog").as_deref(),
+            Some("/opt/bin/prog")
+        );
+        assert_eq!(env.resolve_exec_path("missing"), None);
+        // Directories are not programs
+        assert_eq!(env.resolve_exec_path("bin"), None);
+    }
+
     #[test]
     fn nul_character_in_args() {
         let output = WasiEnvBuilder::new("test_prog")
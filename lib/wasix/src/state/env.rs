@@ -1,8 +1,10 @@
 use std::{
-    collections::HashMap,
+    borrow::Cow,
+    collections::{HashMap, HashSet},
+    net::IpAddr,
     ops::Deref,
     path::{Path, PathBuf},
-    sync::Arc,
+    sync::{atomic::Ordering, Arc},
     time::Duration,
 };
 
@@ -12,13 +14,13 @@ use rand::Rng;
 use virtual_fs::{FileSystem, FsError, StaticFile, VirtualFile};
 use virtual_net::DynVirtualNetworking;
 use wasmer::{
-    AsStoreMut, AsStoreRef, FunctionEnvMut, Global, Imports, Instance, Memory, MemoryType,
-    MemoryView, Module, TypedFunction,
+    AsStoreMut, AsStoreRef, FunctionEnvMut, Global, Imports, Instance, Memory, Memory64,
+    MemorySize, MemoryType, MemoryView, Module, TypedFunction, WasmPtr,
 };
 use wasmer_config::package::PackageSource;
 use wasmer_wasix_types::{
     types::Signal,
-    wasi::{Errno, ExitCode, Snapshot0Clockid},
+    wasi::{Errno, ExitCode, Longsize, Snapshot0Clockid},
     wasix::ThreadStartType,
 };
 
@@ -27,22 +29,36 @@ use crate::journal::{DynJournal, JournalEffector, SnapshotTrigger};
 use crate::{
     bin_factory::{BinFactory, BinaryPackage},
     capabilities::Capabilities,
-    fs::{WasiFsRoot, WasiInodes},
+    fs::{WasiFs, WasiFsRoot, WasiInodes},
     import_object_for_all_wasi_versions,
-    os::task::{
-        control_plane::ControlPlaneError,
-        process::{WasiProcess, WasiProcessId},
-        thread::{WasiMemoryLayout, WasiThread, WasiThreadHandle, WasiThreadId},
+    os::{
+        command::SpawnHandler,
+        task::{
+            control_plane::ControlPlaneError,
+            process::{WasiProcess, WasiProcessId},
+            signal::{signal_terminates_by_default, SignalDisposition},
+            thread::{
+                RewindResultType, ThreadCheckpoint, WasiMemoryLayout, WasiThread, WasiThreadHandle,
+                WasiThreadId,
+            },
+        },
+    },
+    runtime::{task_manager::InlineWaker, ProcessExit, ProcessExitStatus, SpawnMemoryType},
+    syscalls::{
+        fd_read_internal, fd_write_internal, platform_clock_time_get, rewind_ext, FdReadDest,
+        FdWriteSource, WasiFd,
     },
-    runtime::{task_manager::InlineWaker, SpawnMemoryType},
-    syscalls::platform_clock_time_get,
     Runtime, VirtualTaskManager, WasiControlPlane, WasiEnvBuilder, WasiError, WasiFunctionEnv,
     WasiResult, WasiRuntimeError, WasiStateCreationError, WasiVFork,
 };
 use wasmer_types::ModuleHash;
 
 pub(crate) use super::handles::*;
-use super::WasiState;
+use super::{ClosedStdin, SyscallStep, UnimplementedSyscall, WasiState};
+
+/// Name of the global the metering middleware keeps the remaining points in
+#[cfg(feature = "metering")]
+pub(crate) const METERING_POINTS_EXPORT: &str = "wasmer_metering_remaining_points";
 
 /// Various [`TypedFunction`] and [`Global`] handles for an active WASI(X) instance.
 ///
@@ -266,6 +282,51 @@ pub struct WasiEnvInit {
     /// Indicates triggers that will cause a snapshot to be taken
     #[cfg(feature = "journal")]
     pub snapshot_on: Vec<SnapshotTrigger>,
+
+    /// Signals that are blocked when the process starts
+    pub signal_mask: Vec<Signal>,
+
+    /// Syscalls the guest is allowed to invoke (all of them when [`None`])
+    pub syscall_allowlist: Option<Arc<HashSet<String>>>,
+
+    /// Fallback for the imported syscalls that are not implemented
+    pub unimplemented_syscall: Option<UnimplementedSyscall>,
+
+    /// Runs some of the programs spawned by the guest natively
+    pub spawn_handler: Option<Arc<dyn SpawnHandler + Send + Sync + 'static>>,
+
+    /// Directories that `proc_exec` looks up bare program names in
+    pub exec_search_path: Arc<Vec<String>>,
+
+    /// Maximum number of bytes the sockets of the process may send and receive
+    pub socket_byte_limit: Option<u64>,
+
+    /// Whether threads that trap are kept so the host can restart them
+    pub retain_crashed_threads: bool,
+
+    /// Static host name mappings consulted before the network resolver
+    pub hosts: Arc<HashMap<String, Vec<IpAddr>>>,
+
+    /// Maximum size of the datagrams the sockets of the process may send
+    pub max_datagram_size: usize,
+
+    /// Receive and send timeouts that new sockets start with
+    pub default_socket_read_timeout: Option<Duration>,
+    pub default_socket_write_timeout: Option<Duration>,
+
+    /// Errnos returned by the syscalls that are presented to the guest as
+    /// another errno
+    pub errno_remap: Arc<HashMap<Errno, Errno>>,
+
+    /// Receives the syscalls of the guest when it runs in step mode
+    pub syscall_steps: Option<std::sync::mpsc::Sender<SyscallStep>>,
+
+    /// What reading from `stdin` returns once its writer was closed
+    pub closed_stdin: ClosedStdin,
+
+    /// Number of metering points the guest may consume
+    #[cfg(feature = "metering")]
+    pub instruction_limit: Option<u64>,
 }
 
 impl WasiEnvInit {
@@ -287,7 +348,10 @@ impl WasiEnvInit {
                     self.state.clock_offset.lock().unwrap().clone(),
                 ),
                 args: self.state.args.clone(),
-                envs: std::sync::Mutex::new(self.state.envs.lock().unwrap().deref().clone()),
+                envs: Arc::new(std::sync::Mutex::new(
+                    self.state.envs.lock().unwrap().deref().clone(),
+                )),
+                readahead_size: self.state.readahead_size,
                 preopen: self.state.preopen.clone(),
             },
             runtime: self.runtime.clone(),
@@ -304,6 +368,22 @@ impl WasiEnvInit {
             extra_tracing: false,
             #[cfg(feature = "journal")]
             snapshot_on: self.snapshot_on.clone(),
+            signal_mask: self.signal_mask.clone(),
+            syscall_allowlist: self.syscall_allowlist.clone(),
+            unimplemented_syscall: self.unimplemented_syscall.clone(),
+            spawn_handler: self.spawn_handler.clone(),
+            exec_search_path: self.exec_search_path.clone(),
+            socket_byte_limit: self.socket_byte_limit,
+            retain_crashed_threads: self.retain_crashed_threads,
+            hosts: self.hosts.clone(),
+            max_datagram_size: self.max_datagram_size,
+            default_socket_read_timeout: self.default_socket_read_timeout,
+            default_socket_write_timeout: self.default_socket_write_timeout,
+            errno_remap: self.errno_remap.clone(),
+            syscall_steps: self.syscall_steps.clone(),
+            closed_stdin: self.closed_stdin,
+            #[cfg(feature = "metering")]
+            instruction_limit: self.instruction_limit,
             additional_imports: self.additional_imports.clone(),
         }
     }
@@ -354,6 +434,46 @@ pub struct WasiEnv {
     /// (this is normally used so that the instance can be reused later on)
     pub(crate) disable_fs_cleanup: bool,
 
+    /// Syscalls the guest is allowed to invoke, any other syscall will
+    /// terminate the guest with [`WasiError::SyscallDenied`]
+    /// (all syscalls are allowed when [`None`])
+    pub syscall_allowlist: Option<Arc<HashSet<String>>>,
+
+    /// What the imported syscalls that are not implemented do (the module
+    /// fails to link when [`None`])
+    pub unimplemented_syscall: Option<UnimplementedSyscall>,
+
+    /// Runs the programs it claims when the guest spawns them natively
+    /// instead of as WASIX processes
+    pub spawn_handler: Option<Arc<dyn SpawnHandler + Send + Sync + 'static>>,
+
+    /// Directories that bare program names passed to `proc_exec` are
+    /// looked up in (in order)
+    pub exec_search_path: Arc<Vec<String>>,
+
+    /// Static host name mappings (lower case) that `resolve` consults
+    /// before the network resolver
+    pub hosts: Arc<HashMap<String, Vec<IpAddr>>>,
+
+    /// Datagrams larger than this are rejected by `sock_send_to`
+    /// with `Errno::Msgsize`
+    pub max_datagram_size: usize,
+
+    /// Receive and send timeouts (`SO_RCVTIMEO` and `SO_SNDTIMEO`) that
+    /// the sockets opened by `sock_open` and `sock_accept` start with
+    pub default_socket_read_timeout: Option<Duration>,
+    pub default_socket_write_timeout: Option<Duration>,
+
+    /// Errnos returned by the syscalls that are presented to the guest as
+    /// another errno (see [`WasiEnvBuilder::errno_remap`])
+    pub errno_remap: Arc<HashMap<Errno, Errno>>,
+
+    /// Receives the syscalls of the guest when it runs in step mode
+    pub syscall_steps: Option<std::sync::mpsc::Sender<SyscallStep>>,
+
+    /// What reading from `stdin` returns once its writer was closed
+    pub closed_stdin: ClosedStdin,
+
     /// Inner functions and references that are loaded before the environment starts
     /// (inner is not safe to send between threads and so it is private and will
     ///  not be cloned when `WasiEnv` is cloned)
@@ -387,6 +507,17 @@ impl Clone for WasiEnv {
             enable_exponential_cpu_backoff: self.enable_exponential_cpu_backoff,
             replaying_journal: self.replaying_journal,
             disable_fs_cleanup: self.disable_fs_cleanup,
+            syscall_allowlist: self.syscall_allowlist.clone(),
+            unimplemented_syscall: self.unimplemented_syscall.clone(),
+            spawn_handler: self.spawn_handler.clone(),
+            exec_search_path: self.exec_search_path.clone(),
+            hosts: self.hosts.clone(),
+            max_datagram_size: self.max_datagram_size,
+            default_socket_read_timeout: self.default_socket_read_timeout,
+            default_socket_write_timeout: self.default_socket_write_timeout,
+            errno_remap: self.errno_remap.clone(),
+            syscall_steps: self.syscall_steps.clone(),
+            closed_stdin: self.closed_stdin,
         }
     }
 }
@@ -400,12 +531,14 @@ impl WasiEnv {
     /// Forking the WasiState is used when either fork or vfork is called
     pub fn fork(&self) -> Result<(Self, WasiThreadHandle), ControlPlaneError> {
         let process = self.control_plane.new_process(self.process.module_hash)?;
+        process.inherit_process_group(&self.process);
+        process.inherit_signal_state(&self.process);
         let handle = process.new_thread(self.layout.clone(), ThreadStartType::MainThread)?;
 
         let thread = handle.as_thread();
         thread.copy_stack_from(&self.thread);
 
-        let state = Arc::new(self.state.fork());
+        let state = Arc::new(self.state.fork_for(&process));
 
         let bin_factory = self.bin_factory.clone();
 
@@ -427,6 +560,17 @@ impl WasiEnv {
             enable_exponential_cpu_backoff: self.enable_exponential_cpu_backoff,
             replaying_journal: false,
             disable_fs_cleanup: self.disable_fs_cleanup,
+            syscall_allowlist: self.syscall_allowlist.clone(),
+            unimplemented_syscall: self.unimplemented_syscall.clone(),
+            spawn_handler: self.spawn_handler.clone(),
+            exec_search_path: self.exec_search_path.clone(),
+            hosts: self.hosts.clone(),
+            max_datagram_size: self.max_datagram_size,
+            default_socket_read_timeout: self.default_socket_read_timeout,
+            default_socket_write_timeout: self.default_socket_write_timeout,
+            errno_remap: self.errno_remap.clone(),
+            syscall_steps: self.syscall_steps.clone(),
+            closed_stdin: self.closed_stdin,
         };
         Ok((new_env, handle))
     }
@@ -525,6 +669,21 @@ impl WasiEnv {
             process.inner.0.lock().unwrap().snapshot_on = init.snapshot_on.into_iter().collect();
         }
 
+        if !init.signal_mask.is_empty() {
+            process.set_signal_mask(init.signal_mask);
+        }
+
+        if let Some(limit) = init.socket_byte_limit {
+            process.set_socket_byte_limit(Some(limit));
+        }
+
+        if init.retain_crashed_threads {
+            process.set_retain_crashed_threads(true);
+        }
+
+        let mut state = init.state;
+        state.register_proc(&process);
+
         let layout = WasiMemoryLayout::default();
         let thread = if let Some(t) = init.thread {
             t
@@ -539,7 +698,7 @@ impl WasiEnv {
             layout,
             vfork: None,
             poll_seed: 0,
-            state: Arc::new(init.state),
+            state: Arc::new(state),
             inner: Default::default(),
             owned_handles: Vec::new(),
             #[cfg(feature = "journal")]
@@ -556,6 +715,17 @@ impl WasiEnv {
             bin_factory: init.bin_factory,
             capabilities: init.capabilities,
             disable_fs_cleanup: false,
+            syscall_allowlist: init.syscall_allowlist,
+            unimplemented_syscall: init.unimplemented_syscall,
+            spawn_handler: init.spawn_handler,
+            exec_search_path: init.exec_search_path,
+            hosts: init.hosts,
+            max_datagram_size: init.max_datagram_size,
+            default_socket_read_timeout: init.default_socket_read_timeout,
+            default_socket_write_timeout: init.default_socket_write_timeout,
+            errno_remap: init.errno_remap,
+            syscall_steps: init.syscall_steps,
+            closed_stdin: init.closed_stdin,
         };
         env.owned_handles.push(thread);
 
@@ -580,6 +750,8 @@ impl WasiEnv {
     ) -> Result<(Instance, WasiFunctionEnv), WasiRuntimeError> {
         let call_initialize = init.call_initialize;
         let spawn_type = init.memory_ty.take();
+        #[cfg(feature = "metering")]
+        let instruction_limit = init.instruction_limit;
 
         if init.extra_tracing {
             for import in module.imports() {
@@ -646,6 +818,22 @@ impl WasiEnv {
             }
         };
 
+        // Hand the instruction budget to the metering middleware
+        #[cfg(feature = "metering")]
+        if let Some(limit) = instruction_limit {
+            if let Err(err) = instance.exports.get_global(METERING_POINTS_EXPORT) {
+                tracing::error!(
+                    %pid,
+                    "Instruction limit set but the module was not compiled with metering",
+                );
+                func_env
+                    .data(&store)
+                    .blocking_on_exit(Some(Errno::Noexec.into()));
+                return Err(err.into());
+            }
+            wasmer_middlewares::metering::set_remaining_points(&mut store, &instance, limit);
+        }
+
         // Run initializers.
         instance_init_callback(&instance, &store).unwrap();
 
@@ -693,6 +881,25 @@ impl WasiEnv {
         &self.state.fs.root_fs
     }
 
+    /// The file system of the environment
+    pub fn fs(&self) -> &WasiFs {
+        &self.state.fs
+    }
+
+    /// Looks up a program name (that has no slash in it) in the directories
+    /// of the exec search path and returns the path of the first match
+    pub(crate) fn resolve_exec_path(&self, name: &str) -> Option<String> {
+        self.exec_search_path
+            .iter()
+            .map(|dir| format!("{}/{}", dir.trim_end_matches('/'), name))
+            .find(|path| {
+                self.fs_root()
+                    .metadata(Path::new(path))
+                    .map(|meta| meta.is_file())
+                    .unwrap_or(false)
+            })
+    }
+
     /// Overrides the runtime implementation for this environment
     pub fn set_runtime<R>(&mut self, runtime: R)
     where
@@ -718,16 +925,7 @@ impl WasiEnv {
             let signals = env.thread.pop_signals();
             if !signals.is_empty() {
                 for sig in signals {
-                    if sig == Signal::Sigint
-                        || sig == Signal::Sigquit
-                        || sig == Signal::Sigkill
-                        || sig == Signal::Sigabrt
-                    {
-                        let exit_code = env.thread.set_or_get_exit_code_for_signal(sig);
-                        return Err(WasiError::Exit(exit_code));
-                    } else {
-                        tracing::trace!(pid=%env.pid(), ?sig, "Signal ignored");
-                    }
+                    env.apply_signal_disposition(sig, false)?;
                 }
                 return Ok(Ok(true));
             }
@@ -765,6 +963,32 @@ impl WasiEnv {
         Ok(Ok(ret))
     }
 
+    /// Takes the action for a signal that was delivered to this thread
+    /// according to the disposition the host forced for it (if any), the
+    /// default action of a terminating signal exits the thread.
+    ///
+    /// Returns true if the signal still has to be passed to the handler of
+    /// the guest (which is only ever the case when `has_handler` is set).
+    pub(crate) fn apply_signal_disposition(
+        &self,
+        signal: Signal,
+        has_handler: bool,
+    ) -> Result<bool, WasiError> {
+        match self.process.forced_signal_disposition(signal) {
+            Some(SignalDisposition::Ignore) => {}
+            Some(SignalDisposition::Handler) | None if has_handler => return Ok(true),
+            Some(SignalDisposition::Default | SignalDisposition::Handler) | None => {
+                if signal_terminates_by_default(signal) {
+                    self.process.set_exit_signal(signal);
+                    let exit_code = self.thread.set_or_get_exit_code_for_signal(signal);
+                    return Err(WasiError::Exit(exit_code));
+                }
+            }
+        }
+        tracing::trace!(pid=%self.pid(), ?signal, "Signal ignored");
+        Ok(false)
+    }
+
     pub(crate) fn process_signals_internal(
         ctx: &mut FunctionEnvMut<'_, Self>,
         mut signals: Vec<Signal>,
@@ -803,6 +1027,11 @@ impl WasiEnv {
             }
 
             for signal in signals {
+                // The host may have forced what happens to some signals
+                if !ctx.data().apply_signal_disposition(signal, true)? {
+                    continue;
+                }
+
                 tracing::trace!(
                     pid=%ctx.data().pid(),
                     ?signal,
@@ -835,6 +1064,10 @@ impl WasiEnv {
             }
             Ok(true)
         } else {
+            // Without a handler the signals still take their default action
+            for signal in signals {
+                env.apply_signal_disposition(signal, false)?;
+            }
             tracing::trace!("no signal handler");
             Ok(false)
         }
@@ -911,6 +1144,29 @@ impl WasiEnv {
         self.inner.get().map(|i| i.instance.clone())
     }
 
+    /// Takes half of the instruction budget that remains for this thread so
+    /// it can be handed to a thread that is being spawned (the budget is
+    /// split rather than copied so the threads share the limit)
+    ///
+    /// Returns [`None`] when the module is not metered
+    #[cfg(feature = "metering")]
+    pub(crate) fn split_instruction_budget(&self, store: &mut impl AsStoreMut) -> Option<u64> {
+        use wasmer_middlewares::metering::{
+            get_remaining_points, set_remaining_points, MeteringPoints,
+        };
+
+        let instance = self.try_clone_instance()?;
+        instance.exports.get_global(METERING_POINTS_EXPORT).ok()?;
+        match get_remaining_points(store, &instance) {
+            MeteringPoints::Remaining(points) => {
+                let share = points / 2;
+                set_remaining_points(store, &instance, points - share);
+                Some(share)
+            }
+            MeteringPoints::Exhausted => Some(0),
+        }
+    }
+
     /// Providers safe access to the memory
     /// (it must be initialized before it can be used)
     pub(crate) fn try_memory(&self) -> Option<WasiInstanceGuardMemory<'_>> {
@@ -1017,6 +1273,131 @@ impl WasiEnv {
         self.state.std_dev_get(fd)
     }
 
+    /// Resumes a thread checkpoint that was exported from another instance
+    /// with [`WasiThread::export_checkpoint`] on the current thread.
+    ///
+    /// The stacks are rewound when the entry point of the instance is invoked
+    /// next, at which point the guest returns from `stack_checkpoint` with
+    /// `val`. The checkpoint must come from an instance of the same module
+    /// whose thread has the same memory layout, otherwise `Errno::Inval` is
+    /// returned.
+    pub fn resume_checkpoint<M: MemorySize>(
+        ctx: &mut FunctionEnvMut<'_, Self>,
+        checkpoint: &ThreadCheckpoint,
+        val: Longsize,
+    ) -> Errno {
+        if let Err(err) = ctx.data().thread.import_checkpoint(checkpoint) {
+            return err;
+        }
+        let rewind_result = bincode::serialize(&val).unwrap().into();
+        rewind_ext::<M>(
+            ctx,
+            Some(checkpoint.memory_stack.clone()),
+            checkpoint.rewind_stack.clone(),
+            checkpoint.store_data.clone(),
+            RewindResultType::RewindWithResult(rewind_result),
+        )
+    }
+
+    /// Writes `data` to one of the guest's file descriptors from within a
+    /// host function.
+    ///
+    /// The descriptor is looked up in the fd table of this environment so its
+    /// rights, flags and cursor are honored exactly as if the guest had called
+    /// `fd_write` itself. Host functions get hold of the `ctx` by being created
+    /// with [`wasmer::Function::new_typed_with_env`] against the `FunctionEnv`
+    /// of the [`WasiFunctionEnv`] that runs the guest.
+    pub fn fd_write_from_host(
+        ctx: &FunctionEnvMut<'_, Self>,
+        fd: WasiFd,
+        data: &[u8],
+    ) -> WasiResult<usize> {
+        let env = ctx.data();
+        let fd_entry = wasi_try_ok_ok!(env.state.fs.get_fd(fd));
+        let offset = fd_entry.offset.load(Ordering::Acquire);
+
+        fd_write_internal::<Memory64>(
+            ctx,
+            fd,
+            FdWriteSource::Buffer(Cow::Borrowed(data)),
+            offset,
+            true,
+            env.enable_journal,
+        )
+    }
+
+    /// Writes a region of the guest's linear memory to one of its file
+    /// descriptors from within a host function.
+    ///
+    /// The region is bounds checked against the memory of the guest before
+    /// anything is written, out of range regions fail with `Errno::Fault`.
+    pub fn fd_write_from_guest<M: MemorySize>(
+        ctx: &FunctionEnvMut<'_, Self>,
+        fd: WasiFd,
+        buf: WasmPtr<u8, M>,
+        buf_len: M::Offset,
+    ) -> WasiResult<usize> {
+        let data = {
+            let env = ctx.data();
+            let memory = unsafe { env.memory_view(ctx) };
+            let slice = wasi_try_ok_ok!(buf.slice(&memory, buf_len).map_err(|_| Errno::Fault));
+            wasi_try_ok_ok!(slice.read_to_vec().map_err(|_| Errno::Fault))
+        };
+        Self::fd_write_from_host(ctx, fd, &data)
+    }
+
+    /// Reads from one of the guest's file descriptors into `buf` from within
+    /// a host function and returns the number of bytes that were read.
+    ///
+    /// Like [`WasiEnv::fd_write_from_host`] the descriptor is looked up in the
+    /// fd table of this environment, so the read honors its rights and flags
+    /// and advances its cursor exactly as if the guest had called `fd_read`.
+    pub fn fd_read_to_host(
+        ctx: &mut FunctionEnvMut<'_, Self>,
+        fd: WasiFd,
+        buf: &mut [u8],
+    ) -> WasiResult<usize> {
+        let env = ctx.data();
+        let fd_entry = wasi_try_ok_ok!(env.state.fs.get_fd(fd));
+        let offset = fd_entry.offset.load(Ordering::Acquire) as usize;
+
+        fd_read_internal::<Memory64>(ctx, fd, FdReadDest::Buffer(buf), offset, true)
+    }
+
+    /// Reads from one of the guest's file descriptors into a region of its
+    /// linear memory from within a host function.
+    ///
+    /// The region is bounds checked against the memory of the guest before
+    /// anything is read, out of range regions fail with `Errno::Fault`.
+    pub fn fd_read_to_guest<M: MemorySize>(
+        ctx: &mut FunctionEnvMut<'_, Self>,
+        fd: WasiFd,
+        buf: WasmPtr<u8, M>,
+        buf_len: M::Offset,
+    ) -> WasiResult<usize> {
+        let mut data = {
+            let env = ctx.data();
+            let memory = unsafe { env.memory_view(ctx) };
+            let slice = wasi_try_ok_ok!(buf.slice(&memory, buf_len).map_err(|_| Errno::Fault));
+            if slice.offset() + slice.len() > memory.data_size() {
+                return Ok(Err(Errno::Fault));
+            }
+            vec![0u8; slice.len() as usize]
+        };
+        let read = match Self::fd_read_to_host(ctx, fd, &mut data)? {
+            Ok(read) => read,
+            Err(err) => return Ok(Err(err)),
+        };
+
+        let env = ctx.data();
+        let memory = unsafe { env.memory_view(ctx) };
+        wasi_try_ok_ok!(buf
+            .slice(&memory, buf_len)
+            .and_then(|slice| slice.subslice(0..read as u64).write_slice(&data[..read]))
+            .map_err(|_| Errno::Fault));
+        Ok(Ok(read))
+    }
+
     /// Unsafe:
     ///
     /// This will access the memory of the WASM process and create a view into it which is
@@ -1054,6 +1435,54 @@ impl WasiEnv {
         (state, inodes)
     }
 
+    /// Sets an environment variable of the guest (replacing its current
+    /// value), the guest sees it the next time it reads its environment
+    /// with `environ_get`.
+    ///
+    /// The key must not contain a nul byte (`0x0`) or the `=` byte (`0x3d`)
+    /// and the value must not contain a nul byte.
+    pub fn set_env_var(
+        &self,
+        key: impl AsRef<[u8]>,
+        value: impl AsRef<[u8]>,
+    ) -> Result<(), WasiStateCreationError> {
+        let (key, value) = (key.as_ref(), value.as_ref());
+        if key.is_empty() || key.iter().any(|&ch| ch == 0 || ch == b'=') {
+            return Err(WasiStateCreationError::EnvironmentVariableFormatError(
+                format!("invalid env var key \"{}\"", String::from_utf8_lossy(key)),
+            ));
+        }
+        if value.contains(&0) {
+            return Err(WasiStateCreationError::EnvironmentVariableFormatError(
+                format!(
+                    "found nul byte in env var value \"{}\"",
+                    String::from_utf8_lossy(value)
+                ),
+            ));
+        }
+
+        let mut var = key.to_vec();
+        var.push(b'=');
+        var.extend_from_slice(value);
+
+        let mut envs = self.state.envs.lock().unwrap();
+        match envs.iter_mut().find(|env| env_var_key(env) == key) {
+            Some(env) => *env = var,
+            None => envs.push(var),
+        }
+        Ok(())
+    }
+
+    /// Removes an environment variable of the guest, returns whether it
+    /// was set.
+    pub fn unset_env_var(&self, key: impl AsRef<[u8]>) -> bool {
+        let key = key.as_ref();
+        let mut envs = self.state.envs.lock().unwrap();
+        let len = envs.len();
+        envs.retain(|env| env_var_key(env) != key);
+        envs.len() != len
+    }
+
     /// Make all the commands in a [`BinaryPackage`] available to the WASI
     /// instance.
     ///
@@ -1219,6 +1648,33 @@ impl WasiEnv {
         Ok(())
     }
 
+    /// Reports the end of the process to the runtime (if this is the main
+    /// thread), the error is the one that ended the main thread (if any)
+    pub(crate) fn report_process_exit(
+        &self,
+        exit_code: ExitCode,
+        error: Option<&WasiRuntimeError>,
+    ) {
+        if !self.thread.is_main() {
+            return;
+        }
+
+        let status = match error {
+            Some(err) if err.as_exit_code().is_none() => {
+                ProcessExitStatus::Trapped(err.to_string())
+            }
+            _ => match self.process.exit_signal() {
+                Some(signal) => ProcessExitStatus::Signaled(signal),
+                None => ProcessExitStatus::Exited(exit_code),
+            },
+        };
+        self.runtime.on_process_exit(&ProcessExit {
+            pid: self.pid(),
+            status,
+            duration: self.process.uptime(),
+        });
+    }
+
     /// Cleans up all the open files (if this is the main thread)
     #[allow(clippy::await_holding_lock)]
     pub fn blocking_on_exit(&self, exit_code: Option<ExitCode>) {
@@ -1247,6 +1703,10 @@ impl WasiEnv {
 
         // If this is the main thread then also close all the files
         if self.thread.is_main() {
+            // Threads that trapped can no longer be restarted
+            let crashed_threads = std::mem::take(&mut self.process.lock().crashed_threads);
+            drop(crashed_threads);
+
             let process = self.process.clone();
             let disable_fs_cleanup = self.disable_fs_cleanup;
             let pid = self.pid();
@@ -1280,3 +1740,8 @@ impl WasiEnv {
         }
     }
 }
+
+/// Key of an environment variable stored as `key=value`
+fn env_var_key(env: &[u8]) -> &[u8] {
+    env.split(|&ch| ch == b'=').next().unwrap_or_default()
+}
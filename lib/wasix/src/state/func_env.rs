@@ -259,6 +259,61 @@ impl WasiFunctionEnv {
         Ok(resolver)
     }
 
+    /// Reports a trap that was raised because the guest used up its
+    /// instruction budget as [`WasiError::InstructionLimitExceeded`]
+    #[cfg(feature = "metering")]
+    #[allow(clippy::result_large_err)]
+    pub(crate) fn map_instruction_limit(
+        &self,
+        store: &mut impl AsStoreMut,
+        result: Result<(), WasiRuntimeError>,
+    ) -> Result<(), WasiRuntimeError> {
+        match result {
+            Err(WasiRuntimeError::Runtime(_)) if self.instruction_limit_exhausted(store) => {
+                Err(WasiError::InstructionLimitExceeded.into())
+            }
+            result => result,
+        }
+    }
+
+    /// Same as [`WasiFunctionEnv::map_instruction_limit`] for the result of
+    /// calling into the guest directly (which is what spawned threads do)
+    #[cfg(feature = "metering")]
+    pub(crate) fn map_instruction_limit_trap<T>(
+        &self,
+        store: &mut impl AsStoreMut,
+        result: Result<T, wasmer::RuntimeError>,
+    ) -> Result<T, wasmer::RuntimeError> {
+        match result {
+            Err(err) if !err.is::<WasiError>() && self.instruction_limit_exhausted(store) => Err(
+                wasmer::RuntimeError::user(Box::new(WasiError::InstructionLimitExceeded)),
+            ),
+            result => result,
+        }
+    }
+
+    /// Returns true if the guest is metered and used up its instruction budget
+    #[cfg(feature = "metering")]
+    fn instruction_limit_exhausted(&self, store: &mut impl AsStoreMut) -> bool {
+        use wasmer_middlewares::metering::{get_remaining_points, MeteringPoints};
+
+        let instance = match self.data(store).try_clone_instance() {
+            Some(instance) => instance,
+            None => return false,
+        };
+        if instance
+            .exports
+            .get_global(super::env::METERING_POINTS_EXPORT)
+            .is_err()
+        {
+            return false;
+        }
+        matches!(
+            get_remaining_points(store, &instance),
+            MeteringPoints::Exhausted
+        )
+    }
+
     /// # Safety
     ///
     /// This function should only be called from within a syscall
@@ -25,7 +25,7 @@ mod types;
 use std::{
     collections::{BTreeMap, HashMap},
     path::Path,
-    sync::Mutex,
+    sync::{Arc, Mutex},
     task::Waker,
     time::Duration,
 };
@@ -45,6 +45,7 @@ pub use self::{
 pub use crate::fs::{InodeGuard, InodeWeakGuard};
 use crate::{
     fs::{fs_error_into_wasi_err, WasiFs, WasiFsRoot, WasiInodes, WasiStateFileGuard},
+    os::task::process::WasiProcess,
     syscalls::types::*,
     utils::WasiParkingLot,
 };
@@ -133,7 +134,9 @@ pub(crate) struct WasiState {
     pub futexs: Mutex<WasiFutexState>,
     pub clock_offset: Mutex<HashMap<Snapshot0Clockid, i64>>,
     pub args: Vec<String>,
-    pub envs: Mutex<Vec<Vec<u8>>>,
+    pub envs: Arc<Mutex<Vec<Vec<u8>>>>,
+    /// Number of bytes that `fd_advise` reads ahead, zero disables it
+    pub readahead_size: usize,
 
     // TODO: should not be here, since this requires active work to resolve.
     // State should only hold active runtime state that can be reproducibly re-created.
@@ -247,16 +250,63 @@ impl WasiState {
     }
 
     /// Forking the WasiState is used when either fork or vfork is called
+    ///
+    /// The fork belongs to the same process, so it takes over the directory
+    /// of the process in `/proc`.
     pub fn fork(&self) -> Self {
+        let mut state = self.fork_inner();
+        state.fs.proc_self = self.fs.proc_self.as_ref().map(|proc_self| {
+            proc_self.replace(
+                state.args.clone(),
+                state.envs.clone(),
+                state.fs.fd_map.clone(),
+            )
+        });
+        state
+    }
+
+    /// Forks the state for another process, which gets its own directory
+    /// in `/proc`
+    pub(crate) fn fork_for(&self, process: &WasiProcess) -> Self {
+        let mut state = self.fork_inner();
+        state.register_proc(process);
+        state
+    }
+
+    /// Adds the directory of the process to `/proc` (if it is mounted)
+    pub(crate) fn register_proc(&mut self, process: &WasiProcess) {
+        self.fs.proc_self = self.fs.proc_fs.as_ref().map(|proc_fs| {
+            proc_fs.register(
+                process,
+                self.args.clone(),
+                self.envs.clone(),
+                self.fs.fd_map.clone(),
+            )
+        });
+    }
+
+    fn fork_inner(&self) -> Self {
+        let fs = self.fs.fork();
+        let envs = Arc::new(Mutex::new(self.envs.lock().unwrap().clone()));
         WasiState {
-            fs: self.fs.fork(),
+            fs,
             secret: self.secret,
             inodes: self.inodes.clone(),
             futexs: Default::default(),
             clock_offset: Mutex::new(self.clock_offset.lock().unwrap().clone()),
             args: self.args.clone(),
-            envs: Mutex::new(self.envs.lock().unwrap().clone()),
+            envs,
+            readahead_size: self.readahead_size,
             preopen: self.preopen.clone(),
         }
     }
+
+    /// Replaces the arguments of the process, which is done when it
+    /// executes another program
+    pub(crate) fn set_args(&mut self, args: Vec<String>) {
+        if let Some(proc_self) = &self.fs.proc_self {
+            proc_self.set_args(args.clone());
+        }
+        self.args = args;
+    }
 }
@@ -180,9 +180,13 @@ fn handle_result(
         Err(Ok(other)) => Err(other.into()),
         Err(Err(e)) => Err(e.into()),
     };
+    #[cfg(feature = "metering")]
+    let result = env.map_instruction_limit(&mut store, result);
 
     let (result, exit_code) = wasi_exit_code(result);
     env.on_exit(&mut store, Some(exit_code));
+    env.data(&store)
+        .report_process_exit(exit_code, result.as_ref().err());
     sender.send(result.map(|_| store)).ok();
 }
 
@@ -118,8 +118,8 @@ pub(crate) use crate::{
 };
 use crate::{
     fs::{
-        fs_error_into_wasi_err, virtual_file_type_to_wasi_file_type, Fd, InodeVal, Kind,
-        MAX_SYMLINKS,
+        fs_error_into_wasi_err, virtual_file_type_to_wasi_file_type, DirSnapshot, Fd, InodeVal,
+        Kind, ReadAhead, MAX_SYMLINKS,
     },
     journal::{DynJournal, JournalEffector},
     os::task::{
@@ -193,7 +193,7 @@ pub(crate) fn copy_from_slice<M: MemorySize>(
             break;
         }
         let (left, right) = read_loc.split_at(to_read);
-        buf.copy_from_slice(left);
+        buf.as_mut()[..to_read].copy_from_slice(left);
 
         read_loc = right;
         bytes_read += to_read;
@@ -381,15 +381,10 @@ where
             }))));
         }
         if self.process_signals && env.thread.has_signals_or_subscribe(cx.waker()) {
-            let signals = env.thread.signals().lock().unwrap();
-            for sig in signals.0.iter() {
-                if *sig == Signal::Sigint
-                    || *sig == Signal::Sigquit
-                    || *sig == Signal::Sigkill
-                    || *sig == Signal::Sigabrt
-                {
-                    let exit_code = env.thread.set_or_get_exit_code_for_signal(*sig);
-                    return Poll::Ready(Err(WasiError::Exit(exit_code)));
+            let signals = env.thread.signals().lock().unwrap().0.clone();
+            for sig in signals {
+                if let Err(err) = env.apply_signal_disposition(sig, false) {
+                    return Poll::Ready(Err(err));
                 }
             }
         }
@@ -586,6 +581,59 @@ where
     Ok(InlineWaker::block_on(work))
 }
 
+/// Works like [`__asyncify_light`] except that waiting on the work is abandoned
+/// with `Errno::Intr` when a signal arrives for the thread, and with
+/// `Errno::Timedout` once the (optional) timeout elapses. Work that completes
+/// without waiting is never interrupted.
+pub(crate) fn __asyncify_light_interruptible<T, Fut>(
+    env: &WasiEnv,
+    timeout: Option<Duration>,
+    work: Fut,
+) -> WasiResult<T>
+where
+    T: 'static,
+    Fut: Future<Output = Result<T, Errno>>,
+{
+    struct Poller<'a, Fut, T>
+    where
+        Fut: Future<Output = Result<T, Errno>>,
+    {
+        env: &'a WasiEnv,
+        pinned_work: Pin<Box<Fut>>,
+        pinned_timeout: Option<Pin<Box<dyn Future<Output = ()> + Send + Sync>>>,
+    }
+    impl<'a, Fut, T> Future for Poller<'a, Fut, T>
+    where
+        Fut: Future<Output = Result<T, Errno>>,
+    {
+        type Output = WasiResult<T>;
+        fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+            if let Poll::Ready(res) = Pin::new(&mut self.pinned_work).poll(cx) {
+                return Poll::Ready(Ok(res));
+            }
+            if let Some(exit_code) = self.env.should_exit() {
+                return Poll::Ready(Err(WasiError::Exit(exit_code)));
+            }
+            if self.env.thread.has_signals_or_subscribe(cx.waker()) {
+                return Poll::Ready(Ok(Err(Errno::Intr)));
+            }
+            if let Some(timeout) = self.pinned_timeout.as_mut() {
+                if timeout.as_mut().poll(cx).is_ready() {
+                    return Poll::Ready(Ok(Err(Errno::Timedout)));
+                }
+            }
+            Poll::Pending
+        }
+    }
+
+    let poller = Poller {
+        env,
+        pinned_work: Box::pin(work),
+        pinned_timeout: timeout.map(|timeout| env.tasks().sleep_now(timeout)),
+    };
+    InlineWaker::block_on(poller)
+}
+
 // This should be compiled away, it will simply wait forever however its never
 // used by itself, normally this is passed into asyncify which will still abort
 // the operating on timeouts, signals or other work due to a select! around the await
@@ -1475,7 +1523,7 @@ pub(crate) fn _prepare_wasi(wasi_env: &mut WasiEnv, args: Option<Vec<String>>) {
     // Swap out the arguments with the new ones
     if let Some(args) = args {
         let mut wasi_state = wasi_env.state.fork();
-        wasi_state.args = args;
+        wasi_state.set_args(args);
         wasi_env.state = Arc::new(wasi_state);
     }
 
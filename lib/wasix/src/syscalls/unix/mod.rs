@@ -33,15 +33,49 @@ pub fn platform_clock_res_get(
     Ok(t_out)
 }
 
+/// Resolution of the coarse clocks, which serve the time the kernel cached
+/// on its last tick and are much cheaper to read than the precise clocks
+#[cfg(target_os = "linux")]
+static COARSE_CLOCK_RESOLUTION: once_cell::sync::Lazy<i64> = once_cell::sync::Lazy::new(|| {
+    let mut timespec_out = timespec {
+        tv_sec: 0,
+        tv_nsec: 0,
+    };
+    match unsafe { clock_getres(libc::CLOCK_MONOTONIC_COARSE, &mut timespec_out) } {
+        0 => (timespec_out.tv_sec * 1_000_000_000).wrapping_add(timespec_out.tv_nsec),
+        _ => i64::MAX,
+    }
+});
+
+/// Returns the coarse version of the clock when the caller is happy with a
+/// reading that is off by up to `precision` nanoseconds
+#[cfg(target_os = "linux")]
+fn coarse_clock_id(clock_id: Snapshot0Clockid, precision: Timestamp) -> Option<libc::clockid_t> {
+    if (precision as i64) < *COARSE_CLOCK_RESOLUTION {
+        return None;
+    }
+    match clock_id {
+        Snapshot0Clockid::Monotonic => Some(libc::CLOCK_MONOTONIC_COARSE),
+        Snapshot0Clockid::Realtime => Some(libc::CLOCK_REALTIME_COARSE),
+        _ => None,
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn coarse_clock_id(_clock_id: Snapshot0Clockid, _precision: Timestamp) -> Option<libc::clockid_t> {
+    None
+}
+
 pub fn platform_clock_time_get(
     clock_id: Snapshot0Clockid,
     precision: Timestamp,
 ) -> Result<i64, Errno> {
-    let unix_clock_id = match clock_id {
-        Snapshot0Clockid::Monotonic => CLOCK_MONOTONIC,
-        Snapshot0Clockid::ProcessCputimeId => CLOCK_PROCESS_CPUTIME_ID,
-        Snapshot0Clockid::Realtime => CLOCK_REALTIME,
-        Snapshot0Clockid::ThreadCputimeId => CLOCK_THREAD_CPUTIME_ID,
+    let unix_clock_id = match (clock_id, coarse_clock_id(clock_id, precision)) {
+        (_, Some(coarse_clock_id)) => coarse_clock_id,
+        (Snapshot0Clockid::Monotonic, None) => CLOCK_MONOTONIC,
+        (Snapshot0Clockid::ProcessCputimeId, None) => CLOCK_PROCESS_CPUTIME_ID,
+        (Snapshot0Clockid::Realtime, None) => CLOCK_REALTIME,
+        (Snapshot0Clockid::ThreadCputimeId, None) => CLOCK_THREAD_CPUTIME_ID,
         _ => return Err(Errno::Inval),
     };
 
@@ -24,16 +24,29 @@ pub fn clock_time_get<M: MemorySize>(
     precision: Timestamp,
     time: WasmPtr<Timestamp, M>,
 ) -> Result<Errno, WasiError> {
+    // Clock IDs that are not known must not fall back to another clock
+    if clock_id == Snapshot0Clockid::Unknown {
+        return Ok(Errno::Inval);
+    }
+
     ctx = wasi_try_ok!(maybe_backoff::<M>(ctx)?);
 
     let env = ctx.data();
     let memory = unsafe { env.memory_view(&ctx) };
 
-    let mut t_out = wasi_try_ok!(platform_clock_time_get(clock_id, precision));
-    {
-        let guard = env.state.clock_offset.lock().unwrap();
-        if let Some(offset) = guard.get(&clock_id) {
-            t_out += *offset;
+    // Threads may have their own clock (e.g. when testing)
+    let t_out = match env.thread.clock().and_then(|clock| clock.time(clock_id)) {
+        Some(t) => t as i64,
+        None => {
+            let mut t_out = wasi_try_ok!(platform_clock_time_get(clock_id, precision));
+            let guard = env.state.clock_offset.lock().unwrap();
+            if let Some(offset) = guard.get(&clock_id) {
+                t_out += *offset;
+            }
+            if clock_id == Snapshot0Clockid::Monotonic {
+                t_out = env.process.clamp_monotonic(t_out);
+            }
+            t_out
         }
     };
     wasi_try_mem_ok!(time.write(&memory, t_out as Timestamp));
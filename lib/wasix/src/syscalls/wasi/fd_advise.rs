@@ -12,6 +12,13 @@ use crate::syscalls::*;
 ///     The length from the offset to which the advice applies
 /// - `__wasi_advice_t advice`
 ///     The advice to give
+///
+/// Advice about a range that extends past the end of the file succeeds,
+/// the range is clamped to the file.
+///
+/// `Advice::Willneed` reads the start of the range ahead (up to the read
+/// ahead size of the process), reads that fall within it are served from
+/// memory until the file is written to. `Advice::Dontneed` drops it.
 #[instrument(level = "debug", skip_all, fields(%fd, %offset, %len, ?advice), ret)]
 pub fn fd_advise(
     mut ctx: FunctionEnvMut<'_, WasiEnv>,
@@ -41,9 +48,6 @@ pub(crate) fn fd_advise_internal(
     len: Filesize,
     advice: Advice,
 ) -> Result<(), Errno> {
-    // Instead of unconditionally returning OK.  This barebones implementation
-    // only performs basic fd and rights checks.
-
     let env = ctx.data();
     let (_, mut state) = unsafe { env.get_memory_and_wasi_state(&ctx, 0) };
     let fd_entry = state.fs.get_fd(fd)?;
@@ -53,7 +57,60 @@ pub(crate) fn fd_advise_internal(
         return Err(Errno::Access);
     }
 
-    let _end = offset.checked_add(len).ok_or(Errno::Inval)?;
+    // The advised range is clamped to the end of the file (a length of
+    // zero advises up to the end), the parts past EOF are a no-op
+    let size = inode.stat.read().unwrap().st_size;
+    let end = match len {
+        0 => size,
+        len => offset.saturating_add(len).min(size),
+    };
+    let range = offset.min(size)..end;
+
+    match advice {
+        Advice::Willneed if state.readahead_size > 0 && !range.is_empty() => {
+            let path = match inode.read().deref() {
+                Kind::File {
+                    handle: Some(_),
+                    path,
+                    ..
+                } => path.clone(),
+                _ => return Ok(()),
+            };
+            let len = (range.end - range.start).min(state.readahead_size as u64) as usize;
+
+            // The data read ahead for all the inodes together is limited,
+            // once that is used up nothing more is read ahead
+            inode.readahead.lock().unwrap().take();
+            let reservation = match state.inodes.reserve_readahead(len) {
+                Some(reservation) => reservation,
+                None => return Ok(()),
+            };
+
+            // Reading ahead is only advisory so failing to do it is not an error,
+            // the data is read through a handle of its own so that the handle of
+            // the descriptor is neither locked while waiting nor moved
+            let mut file = match state.fs_new_open_options().read(true).open(&path) {
+                Ok(file) => file,
+                Err(_) => return Ok(()),
+            };
+            let res = __asyncify_light(env, None, async move {
+                ReadAhead::fill(&mut *file, range.start, len)
+                    .await
+                    .map_err(map_io_err)
+            });
+            if let Ok(Ok(readahead)) = res {
+                inode
+                    .readahead
+                    .lock()
+                    .unwrap()
+                    .replace(readahead.with_reservation(reservation));
+            }
+        }
+        Advice::Dontneed => {
+            inode.readahead.lock().unwrap().take();
+        }
+        _ => {}
+    }
 
     Ok(())
 }
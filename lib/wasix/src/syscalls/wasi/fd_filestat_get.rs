@@ -42,7 +42,10 @@ pub(crate) fn fd_filestat_get_internal(
     let (_, state, inodes) = unsafe { env.get_memory_and_wasi_state_and_inodes(&ctx, 0) };
     let fd_entry = state.fs.get_fd(fd)?;
     if !fd_entry.rights.contains(Rights::FD_FILESTAT_GET) {
-        return Err(Errno::Access);
+        return Err(match fd_entry.stdio_rights_enforced {
+            true => Errno::Notcapable,
+            false => Errno::Access,
+        });
     }
 
     state.fs.filestat_fd(fd)
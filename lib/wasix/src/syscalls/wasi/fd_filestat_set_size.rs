@@ -3,6 +3,8 @@ use crate::syscalls::*;
 
 /// ### `fd_filestat_set_size()`
 /// Change the size of an open file, zeroing out any new bytes
+/// (the cursor is left untouched, reads from a cursor past the new end of
+/// the file return EOF)
 /// Inputs:
 /// - `Fd fd`
 ///     File descriptor to adjust
@@ -14,7 +16,7 @@ pub fn fd_filestat_set_size(
     fd: WasiFd,
     st_size: Filesize,
 ) -> Result<Errno, WasiError> {
-    wasi_try_ok!(fd_filestat_set_size_internal(&mut ctx, fd, st_size));
+    wasi_try_ok!(fd_filestat_set_size_internal(&mut ctx, fd, st_size)?);
     let env = ctx.data();
 
     #[cfg(feature = "journal")]
@@ -32,14 +34,14 @@ pub(crate) fn fd_filestat_set_size_internal(
     ctx: &mut FunctionEnvMut<'_, WasiEnv>,
     fd: WasiFd,
     st_size: Filesize,
-) -> Result<(), Errno> {
+) -> Result<Result<(), Errno>, WasiError> {
     let env = ctx.data();
     let (_, mut state) = unsafe { env.get_memory_and_wasi_state(&ctx, 0) };
-    let fd_entry = state.fs.get_fd(fd)?;
+    let fd_entry = wasi_try_ok_ok!(state.fs.get_fd(fd));
     let inode = fd_entry.inode;
 
     if !fd_entry.rights.contains(Rights::FD_FILESTAT_SET_SIZE) {
-        return Err(Errno::Access);
+        return Ok(Err(Errno::Access));
     }
 
     {
@@ -48,22 +50,31 @@ pub(crate) fn fd_filestat_set_size_internal(
             Kind::File { handle, .. } => {
                 if let Some(handle) = handle {
                     let mut handle = handle.write().unwrap();
-                    handle.set_len(st_size).map_err(fs_error_into_wasi_err)?;
+
+                    // Writes that are still in flight would otherwise land after
+                    // the truncation and grow the file again
+                    let file = &mut **handle;
+                    wasi_try_ok_ok!(__asyncify_light(env, None, async move {
+                        file.flush().await.map_err(map_io_err)
+                    })?);
+
+                    wasi_try_ok_ok!(handle.set_len(st_size).map_err(fs_error_into_wasi_err));
                 } else {
-                    return Err(Errno::Badf);
+                    return Ok(Err(Errno::Badf));
                 }
             }
             Kind::Buffer { buffer } => {
                 buffer.resize(st_size as usize, 0);
             }
-            Kind::Socket { .. } => return Err(Errno::Badf),
-            Kind::Pipe { .. } => return Err(Errno::Badf),
-            Kind::Symlink { .. } => return Err(Errno::Badf),
-            Kind::EventNotifications { .. } | Kind::Epoll { .. } => return Err(Errno::Badf),
-            Kind::Dir { .. } | Kind::Root { .. } => return Err(Errno::Isdir),
+            Kind::Socket { .. } => return Ok(Err(Errno::Badf)),
+            Kind::Pipe { .. } => return Ok(Err(Errno::Badf)),
+            Kind::Symlink { .. } => return Ok(Err(Errno::Badf)),
+            Kind::EventNotifications { .. } | Kind::Epoll { .. } => return Ok(Err(Errno::Badf)),
+            Kind::Dir { .. } | Kind::Root { .. } => return Ok(Err(Errno::Isdir)),
         }
     }
     inode.stat.write().unwrap().st_size = st_size;
+    inode.readahead.lock().unwrap().take();
 
-    Ok(())
+    Ok(Ok(()))
 }
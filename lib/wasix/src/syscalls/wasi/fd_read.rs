@@ -1,6 +1,7 @@
 use std::{collections::VecDeque, task::Waker};
 
 use virtual_fs::{AsyncReadExt, DeviceFile, ReadBuf};
+use wasmer::WasmSliceAccess;
 
 use super::*;
 use crate::{
@@ -8,9 +9,14 @@ use crate::{
     journal::SnapshotTrigger,
     net::socket::TimeType,
     os::task::process::{MaybeCheckpointResult, WasiProcessCheckpoint, WasiProcessInner},
+    state::ClosedStdin,
     syscalls::*,
 };
 
+/// How often a blocking read from a closed `stdin` checks whether it was
+/// reopened, see [`ClosedStdin::Again`]
+const CLOSED_STDIN_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
 /// ### `fd_read()`
 /// Read data from file descriptor
 /// Inputs:
@@ -49,7 +55,13 @@ pub fn fd_read<M: MemorySize>(
         ctx = wasi_try_ok!(maybe_snapshot_once::<M>(ctx, SnapshotTrigger::FirstStdin)?);
     }
 
-    let res = fd_read_internal::<M>(&mut ctx, fd, iovs, iovs_len, offset, nread, true)?;
+    let res = fd_read_internal::<M>(
+        &mut ctx,
+        fd,
+        FdReadDest::Iovs { iovs, iovs_len },
+        offset,
+        true,
+    )?;
     fd_read_internal_handler(ctx, res, nread)
 }
 
@@ -85,7 +97,13 @@ pub fn fd_pread<M: MemorySize>(
         ctx = wasi_try_ok!(maybe_snapshot_once::<M>(ctx, SnapshotTrigger::FirstStdin)?);
     }
 
-    let res = fd_read_internal::<M>(&mut ctx, fd, iovs, iovs_len, offset as usize, nread, false)?;
+    let res = fd_read_internal::<M>(
+        &mut ctx,
+        fd,
+        FdReadDest::Iovs { iovs, iovs_len },
+        offset as usize,
+        false,
+    )?;
     fd_read_internal_handler::<M>(ctx, res, nread)
 }
 
@@ -117,14 +135,108 @@ pub(crate) fn fd_read_internal_handler<M: MemorySize>(
     Ok(ret)
 }
 
+/// Where the data read from a file descriptor is stored
+pub(crate) enum FdReadDest<'a, M: MemorySize> {
+    Iovs {
+        iovs: WasmPtr<__wasi_iovec_t<M>, M>,
+        iovs_len: M::Offset,
+    },
+    Buffer(&'a mut [u8]),
+}
+
+/// One of the buffers of a [`FdReadDest`]
+enum FdReadBuf<'a> {
+    Guest(WasmSliceAccess<'a, u8>),
+    Host(&'a mut [u8]),
+}
+
+impl<'a> FdReadBuf<'a> {
+    fn as_mut(&mut self) -> &mut [u8] {
+        match self {
+            Self::Guest(buf) => buf.as_mut(),
+            Self::Host(buf) => buf,
+        }
+    }
+
+    fn as_mut_uninit(&mut self) -> &mut [MaybeUninit<u8>] {
+        match self {
+            Self::Guest(buf) => buf.as_mut_uninit(),
+            // Readers only ever write initialized bytes into the buffer
+            Self::Host(buf) => {
+                let buf: *mut [u8] = *buf;
+                unsafe { &mut *(buf as *mut [MaybeUninit<u8>]) }
+            }
+        }
+    }
+}
+
+impl<'a, M: MemorySize> FdReadDest<'a, M> {
+    /// Number of buffers the data is stored in
+    fn count(&self) -> u64 {
+        match self {
+            Self::Iovs { iovs_len, .. } => (*iovs_len).into(),
+            Self::Buffer(_) => 1,
+        }
+    }
+
+    /// Total number of bytes that fit in the buffers
+    fn len(&self, memory: &MemoryView) -> Result<u64, Errno> {
+        match self {
+            Self::Iovs { iovs, iovs_len } => {
+                let iovs_arr = iovs.slice(memory, *iovs_len).map_err(mem_error_to_wasi)?;
+                let iovs_arr = iovs_arr.access().map_err(mem_error_to_wasi)?;
+                Ok(iovs_arr.iter().map(|iov| iov.buf_len.into()).sum())
+            }
+            Self::Buffer(buf) => Ok(buf.len() as u64),
+        }
+    }
+
+    /// Returns the buffer at `index` (guest buffers are bounds checked)
+    fn buf<'b>(&'b mut self, memory: &'b MemoryView, index: u64) -> Result<FdReadBuf<'b>, Errno> {
+        match self {
+            Self::Iovs { iovs, iovs_len } => {
+                let iov = iovs
+                    .slice(memory, *iovs_len)
+                    .map_err(mem_error_to_wasi)?
+                    .index(index)
+                    .read()
+                    .map_err(mem_error_to_wasi)?;
+                let buf = WasmPtr::<u8, M>::new(iov.buf)
+                    .slice(memory, iov.buf_len)
+                    .map_err(mem_error_to_wasi)?
+                    .access()
+                    .map_err(mem_error_to_wasi)?;
+                Ok(FdReadBuf::Guest(buf))
+            }
+            Self::Buffer(buf) => Ok(FdReadBuf::Host(buf)),
+        }
+    }
+
+    /// Copies `data` into the buffers (in order) and returns the number of
+    /// bytes that fit
+    fn copy_from(&mut self, memory: &MemoryView, mut data: &[u8]) -> Result<usize, Errno> {
+        let mut copied = 0usize;
+        for index in 0..self.count() {
+            if data.is_empty() {
+                break;
+            }
+            let mut buf = self.buf(memory, index)?;
+            let buf = buf.as_mut();
+            let amt = buf.len().min(data.len());
+            buf[..amt].copy_from_slice(&data[..amt]);
+            data = &data[amt..];
+            copied += amt;
+        }
+        Ok(copied)
+    }
+}
+
 #[allow(clippy::await_holding_lock)]
 pub(crate) fn fd_read_internal<M: MemorySize>(
     ctx: &mut FunctionEnvMut<'_, WasiEnv>,
     fd: WasiFd,
-    iovs: WasmPtr<__wasi_iovec_t<M>, M>,
-    iovs_len: M::Offset,
+    mut dest: FdReadDest<'_, M>,
     offset: usize,
-    nread: WasmPtr<M::Offset, M>,
     should_update_cursor: bool,
 ) -> WasiResult<usize> {
     wasi_try_ok_ok!(WasiEnv::process_signals_and_exit(ctx)?);
@@ -136,7 +248,19 @@ pub(crate) fn fd_read_internal<M: MemorySize>(
     let fd_entry = wasi_try_ok_ok!(state.fs.get_fd(fd));
     let is_stdio = fd_entry.is_stdio;
 
+    // Directories can never be read from, regardless of the rights of the
+    // descriptor, which guests rely on to detect them
+    if matches!(
+        fd_entry.inode.read().deref(),
+        Kind::Dir { .. } | Kind::Root { .. }
+    ) {
+        return Ok(Err(Errno::Isdir));
+    }
+
     let bytes_read = {
+        if fd_entry.stdio_rights_enforced && !fd_entry.rights.contains(Rights::FD_READ) {
+            return Ok(Err(Errno::Notcapable));
+        }
         if !is_stdio && !fd_entry.rights.contains(Rights::FD_READ) {
             // TODO: figure out the error to return when lacking rights
             return Ok(Err(Errno::Access));
@@ -154,60 +278,93 @@ pub(crate) fn fd_read_internal<M: MemorySize>(
 
                         drop(guard);
 
+                        // Once the writer of stdin is closed the reads may be
+                        // told to try again rather than seeing EOF
+                        let closed_is_again = is_stdio && env.closed_stdin == ClosedStdin::Again;
+                        let nonblocking = fd_flags.contains(Fdflags::NONBLOCK);
+                        let tasks = env.tasks().clone();
+                        let inode = inode.clone();
+
                         let res = __asyncify_light(
                             env,
-                            if fd_flags.contains(Fdflags::NONBLOCK) {
+                            if nonblocking {
                                 Some(Duration::ZERO)
                             } else {
                                 None
                             },
                             async move {
-                                let mut handle = match handle.write() {
-                                    Ok(a) => a,
-                                    Err(_) => return Err(Errno::Fault),
-                                };
-                                if !is_stdio {
-                                    handle
-                                        .seek(std::io::SeekFrom::Start(offset as u64))
-                                        .await
-                                        .map_err(map_io_err)?;
-                                }
+                                loop {
+                                    let mut handle = match handle.write() {
+                                        Ok(a) => a,
+                                        Err(_) => return Err(Errno::Fault),
+                                    };
+                                    let len = dest.len(&memory)?;
+
+                                    // Reads that fall within the data that `fd_advise`
+                                    // read ahead are served from it, as long as the
+                                    // file was not changed since
+                                    if !is_stdio {
+                                        let mut readahead = inode.readahead.lock().unwrap();
+                                        if readahead
+                                            .as_ref()
+                                            .map(|ahead| !ahead.is_current(handle.as_ref()))
+                                            .unwrap_or(false)
+                                        {
+                                            readahead.take();
+                                        }
+                                        if let Some(data) = readahead.as_ref().and_then(|ahead| {
+                                            ahead.get(offset as u64, len as usize)
+                                        }) {
+                                            return dest.copy_from(&memory, data);
+                                        }
+                                    }
 
-                                let mut total_read = 0usize;
-
-                                let iovs_arr =
-                                    iovs.slice(&memory, iovs_len).map_err(mem_error_to_wasi)?;
-                                let iovs_arr = iovs_arr.access().map_err(mem_error_to_wasi)?;
-                                for iovs in iovs_arr.iter() {
-                                    let mut buf = WasmPtr::<u8, M>::new(iovs.buf)
-                                        .slice(&memory, iovs.buf_len)
-                                        .map_err(mem_error_to_wasi)?
-                                        .access()
-                                        .map_err(mem_error_to_wasi)?;
-                                    let local_read =
-                                        match handle.read(buf.as_mut()).await.map_err(|err| {
-                                            let err = From::<std::io::Error>::from(err);
-                                            match err {
-                                                Errno::Again => {
-                                                    if is_stdio {
-                                                        Errno::Badf
-                                                    } else {
-                                                        Errno::Again
+                                    if !is_stdio {
+                                        handle
+                                            .seek(std::io::SeekFrom::Start(offset as u64))
+                                            .await
+                                            .map_err(map_io_err)?;
+                                    }
+
+                                    let mut total_read = 0usize;
+                                    for index in 0..dest.count() {
+                                        let mut buf = dest.buf(&memory, index)?;
+                                        let buf = buf.as_mut();
+                                        let local_read =
+                                            match handle.read(buf).await.map_err(|err| {
+                                                let err = From::<std::io::Error>::from(err);
+                                                match err {
+                                                    Errno::Again => {
+                                                        if is_stdio {
+                                                            Errno::Badf
+                                                        } else {
+                                                            Errno::Again
+                                                        }
                                                     }
+                                                    a => a,
                                                 }
-                                                a => a,
-                                            }
-                                        }) {
-                                            Ok(s) => s,
-                                            Err(_) if total_read > 0 => break,
-                                            Err(err) => return Err(err),
-                                        };
-                                    total_read += local_read;
-                                    if local_read != buf.len() {
-                                        break;
+                                            }) {
+                                                Ok(s) => s,
+                                                Err(_) if total_read > 0 => break,
+                                                Err(err) => return Err(err),
+                                            };
+                                        total_read += local_read;
+                                        if local_read != buf.len() {
+                                            break;
+                                        }
                                     }
+                                    if closed_is_again && total_read == 0 && len > 0 {
+                                        if nonblocking {
+                                            return Err(Errno::Again);
+                                        }
+                                        // Blocking reads wait for stdin to be reopened
+                                        // rather than have the guest spin on Again
+                                        drop(handle);
+                                        tasks.sleep_now(CLOSED_STDIN_POLL_INTERVAL).await;
+                                        continue;
+                                    }
+                                    return Ok(total_read);
                                 }
-                                Ok(total_read)
                             },
                         );
                         let read = wasi_try_ok_ok!(res?.map_err(|err| match err {
@@ -232,6 +389,7 @@ pub(crate) fn fd_read_internal<M: MemorySize>(
                         .unwrap_or(Duration::from_secs(30));
 
                     let tasks = env.tasks().clone();
+                    let process = env.process.clone();
                     let res = __asyncify_light(
                         env,
                         if fd_flags.contains(Fdflags::NONBLOCK) {
@@ -242,24 +400,19 @@ pub(crate) fn fd_read_internal<M: MemorySize>(
                         async move {
                             let mut total_read = 0usize;
 
-                            let iovs_arr =
-                                iovs.slice(&memory, iovs_len).map_err(mem_error_to_wasi)?;
-                            let iovs_arr = iovs_arr.access().map_err(mem_error_to_wasi)?;
-                            for iovs in iovs_arr.iter() {
-                                let mut buf = WasmPtr::<u8, M>::new(iovs.buf)
-                                    .slice(&memory, iovs.buf_len)
-                                    .map_err(mem_error_to_wasi)?
-                                    .access()
-                                    .map_err(mem_error_to_wasi)?;
-
-                                let local_read = socket
-                                    .recv(
-                                        tasks.deref(),
-                                        buf.as_mut_uninit(),
-                                        Some(timeout),
-                                        nonblocking,
-                                    )
-                                    .await?;
+                            for index in 0..dest.count() {
+                                let mut buf = dest.buf(&memory, index)?;
+                                let buf = buf.as_mut_uninit();
+
+                                let local_read = match socket
+                                    .recv(tasks.deref(), &process, buf, Some(timeout), nonblocking)
+                                    .await
+                                {
+                                    Ok(amt) => amt,
+                                    // The byte cap was reached by the previous buffers
+                                    Err(Errno::Notcapable) if total_read > 0 => break,
+                                    Err(err) => return Err(err),
+                                };
                                 total_read += local_read;
                                 if total_read != buf.len() {
                                     break;
@@ -297,27 +450,18 @@ pub(crate) fn fd_read_internal<M: MemorySize>(
                         async move {
                             let mut total_read = 0usize;
 
-                            let iovs_arr =
-                                iovs.slice(&memory, iovs_len).map_err(mem_error_to_wasi)?;
-                            let iovs_arr = iovs_arr.access().map_err(mem_error_to_wasi)?;
-                            for iovs in iovs_arr.iter() {
-                                let mut buf = WasmPtr::<u8, M>::new(iovs.buf)
-                                    .slice(&memory, iovs.buf_len)
-                                    .map_err(mem_error_to_wasi)?
-                                    .access()
-                                    .map_err(mem_error_to_wasi)?;
+                            for index in 0..dest.count() {
+                                let mut buf = dest.buf(&memory, index)?;
+                                let buf = buf.as_mut();
 
                                 let local_read = match nonblocking {
-                                    true => match pipe.try_read(buf.as_mut()) {
+                                    true => match pipe.try_read(buf) {
                                         Some(amt) => amt,
                                         None => {
                                             return Err(Errno::Again);
                                         }
                                     },
-                                    false => {
-                                        virtual_fs::AsyncReadExt::read(&mut pipe, buf.as_mut())
-                                            .await?
-                                    }
+                                    false => virtual_fs::AsyncReadExt::read(&mut pipe, buf).await?,
                                 };
                                 total_read += local_read;
                                 if local_read != buf.len() {
@@ -336,7 +480,6 @@ pub(crate) fn fd_read_internal<M: MemorySize>(
                     (bytes_read, false)
                 }
                 Kind::Dir { .. } | Kind::Root { .. } => {
-                    // TODO: verify
                     return Ok(Err(Errno::Isdir));
                 }
                 Kind::EventNotifications { inner } => {
@@ -374,10 +517,9 @@ pub(crate) fn fd_read_internal<M: MemorySize>(
                     });
                     let val = wasi_try_ok_ok!(res);
 
-                    let mut memory = unsafe { env.memory_view(ctx) };
+                    let memory = unsafe { env.memory_view(ctx) };
                     let reader = val.to_ne_bytes();
-                    let iovs_arr = wasi_try_mem_ok_ok!(iovs.slice(&memory, iovs_len));
-                    let ret = wasi_try_ok_ok!(read_bytes(&reader[..], &memory, iovs_arr));
+                    let ret = wasi_try_ok_ok!(dest.copy_from(&memory, &reader[..]));
                     (ret, false)
                 }
                 Kind::Symlink { .. } | Kind::Epoll { .. } => {
@@ -385,8 +527,9 @@ pub(crate) fn fd_read_internal<M: MemorySize>(
                 }
                 Kind::Buffer { buffer } => {
                     let memory = unsafe { env.memory_view(ctx) };
-                    let iovs_arr = wasi_try_mem_ok_ok!(iovs.slice(&memory, iovs_len));
-                    let read = wasi_try_ok_ok!(read_bytes(&buffer[offset..], &memory, iovs_arr));
+                    // Reading at (or past) the end of the buffer is EOF
+                    let data = buffer.get(offset..).unwrap_or_default();
+                    let read = wasi_try_ok_ok!(dest.copy_from(&memory, data));
                     (read, true)
                 }
             }
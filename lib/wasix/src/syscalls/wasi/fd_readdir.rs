@@ -36,73 +36,26 @@ pub fn fd_readdir<M: MemorySize>(
     let mut cur_cookie = cookie;
     let mut buf_idx = 0usize;
 
-    let entries: Vec<(String, Filetype, u64)> = {
-        let guard = working_dir.inode.read();
-        match guard.deref() {
-            Kind::Dir { path, entries, .. } => {
-                trace!("reading dir {:?}", path);
-                // TODO: refactor this code
-                // we need to support multiple calls,
-                // simple and obviously correct implementation for now:
-                // maintain consistent order via lexacographic sorting
-                let fs_info = wasi_try!(wasi_try!(state.fs_read_dir(path))
-                    .collect::<Result<Vec<_>, _>>()
-                    .map_err(fs_error_into_wasi_err));
-                let mut entry_vec = wasi_try!(fs_info
-                    .into_iter()
-                    .map(|entry| {
-                        let filename = entry.file_name().to_string_lossy().to_string();
-                        trace!("getting file: {:?}", filename);
-                        let filetype = virtual_file_type_to_wasi_file_type(
-                            entry.file_type().map_err(fs_error_into_wasi_err)?,
-                        );
-                        Ok((
-                            filename, filetype, 0, // TODO: inode
-                        ))
-                    })
-                    .collect::<Result<Vec<(String, Filetype, u64)>, _>>());
-                entry_vec.extend(entries.iter().filter(|(_, inode)| inode.is_preopened).map(
-                    |(name, inode)| {
-                        let stat = inode.stat.read().unwrap();
-                        (inode.name.to_string(), stat.st_filetype, stat.st_ino)
-                    },
-                ));
-                // adding . and .. special folders
-                // TODO: inode
-                entry_vec.push((".".to_string(), Filetype::Directory, 0));
-                entry_vec.push(("..".to_string(), Filetype::Directory, 0));
-                entry_vec.sort_by(|a, b| a.0.cmp(&b.0));
-                entry_vec
-            }
-            Kind::Root { entries } => {
-                trace!("reading root");
-                let sorted_entries = {
-                    let mut entry_vec: Vec<(String, InodeGuard)> = entries
-                        .iter()
-                        .map(|(a, b)| (a.clone(), b.clone()))
-                        .collect();
-                    entry_vec.sort_by(|a, b| a.0.cmp(&b.0));
-                    entry_vec
-                };
-                sorted_entries
-                    .into_iter()
-                    .map(|(name, inode)| {
-                        let stat = inode.stat.read().unwrap();
-                        (format!("/{}", inode.name), stat.st_filetype, stat.st_ino)
-                    })
-                    .collect()
-            }
-            Kind::File { .. }
-            | Kind::Symlink { .. }
-            | Kind::Buffer { .. }
-            | Kind::Socket { .. }
-            | Kind::Pipe { .. }
-            | Kind::EventNotifications { .. }
-            | Kind::Epoll { .. } => return Errno::Notdir,
+    // Reading from the start lists the directory again, reading on from a
+    // cookie serves from the snapshot taken by the previous call (as long as
+    // no directory was modified in the meantime)
+    let generation = state.fs.dir_generation();
+    let mut snapshot = working_dir.readdir_snapshot.lock().unwrap();
+    let entries = match snapshot.as_ref() {
+        Some(cached) if cookie != 0 && cached.generation == generation => cached.clone(),
+        _ => {
+            let entries = wasi_try!(read_dir_entries(state, &working_dir));
+            let cached = Arc::new(DirSnapshot {
+                generation,
+                entries,
+            });
+            snapshot.replace(cached.clone());
+            cached
         }
     };
+    drop(snapshot);
 
-    for (entry_path_str, wasi_file_type, ino) in entries.iter().skip(cookie as usize) {
+    for (entry_path_str, wasi_file_type, ino) in entries.entries.iter().skip(cookie as usize) {
         cur_cookie += 1;
         let namlen = entry_path_str.len();
         trace!("returning dirent for {}", entry_path_str);
@@ -139,3 +92,74 @@ pub fn fd_readdir<M: MemorySize>(
     wasi_try_mem!(bufused_ref.write(buf_idx));
     Errno::Success
 }
+
+/// Lists the entries of a directory in the order `fd_readdir` serves them
+fn read_dir_entries(
+    state: &WasiState,
+    working_dir: &Fd,
+) -> Result<Vec<(String, Filetype, u64)>, Errno> {
+    let guard = working_dir.inode.read();
+    match guard.deref() {
+        Kind::Dir { path, entries, .. } => {
+            trace!("reading dir {:?}", path);
+            // TODO: refactor this code
+            // we need to support multiple calls,
+            // simple and obviously correct implementation for now:
+            // maintain consistent order via lexacographic sorting
+            let fs_info = state
+                .fs_read_dir(path)?
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(fs_error_into_wasi_err)?;
+            let mut entry_vec = fs_info
+                .into_iter()
+                .map(|entry| {
+                    let filename = entry.file_name().to_string_lossy().to_string();
+                    trace!("getting file: {:?}", filename);
+                    let filetype = virtual_file_type_to_wasi_file_type(
+                        entry.file_type().map_err(fs_error_into_wasi_err)?,
+                    );
+                    Ok::<_, Errno>((
+                        filename, filetype, 0, // TODO: inode
+                    ))
+                })
+                .collect::<Result<Vec<(String, Filetype, u64)>, _>>()?;
+            entry_vec.extend(entries.iter().filter(|(_, inode)| inode.is_preopened).map(
+                |(name, inode)| {
+                    let stat = inode.stat.read().unwrap();
+                    (inode.name.to_string(), stat.st_filetype, stat.st_ino)
+                },
+            ));
+            // adding . and .. special folders
+            // TODO: inode
+            entry_vec.push((".".to_string(), Filetype::Directory, 0));
+            entry_vec.push(("..".to_string(), Filetype::Directory, 0));
+            entry_vec.sort_by(|a, b| a.0.cmp(&b.0));
+            Ok(entry_vec)
+        }
+        Kind::Root { entries } => {
+            trace!("reading root");
+            let sorted_entries = {
+                let mut entry_vec: Vec<(String, InodeGuard)> = entries
+                    .iter()
+                    .map(|(a, b)| (a.clone(), b.clone()))
+                    .collect();
+                entry_vec.sort_by(|a, b| a.0.cmp(&b.0));
+                entry_vec
+            };
+            Ok(sorted_entries
+                .into_iter()
+                .map(|(name, inode)| {
+                    let stat = inode.stat.read().unwrap();
+                    (format!("/{}", inode.name), stat.st_filetype, stat.st_ino)
+                })
+                .collect())
+        }
+        Kind::File { .. }
+        | Kind::Symlink { .. }
+        | Kind::Buffer { .. }
+        | Kind::Socket { .. }
+        | Kind::Pipe { .. }
+        | Kind::EventNotifications { .. }
+        | Kind::Epoll { .. } => Err(Errno::Notdir),
+    }
+}
@@ -2,12 +2,13 @@ use super::*;
 use crate::syscalls::*;
 
 /// ### `fd_renumber()`
-/// Atomically copy file descriptor
+/// Atomically move a file descriptor to another number, replacing any
+/// file descriptor that was already open at that number
 /// Inputs:
 /// - `Fd from`
-///     File descriptor to copy
+///     File descriptor to move
 /// - `Fd to`
-///     Location to copy file descriptor to
+///     Location to move file descriptor to
 #[instrument(level = "debug", skip_all, fields(%from, %to), ret)]
 pub fn fd_renumber(
     mut ctx: FunctionEnvMut<'_, WasiEnv>,
@@ -40,17 +41,21 @@ pub(crate) fn fd_renumber_internal(
     let env = ctx.data();
     let (_, mut state) = unsafe { env.get_memory_and_wasi_state(&ctx, 0) };
 
-    let mut fd_map = state.fs.fd_map.write().unwrap();
-    let fd_entry = wasi_try!(fd_map.get_mut(&from).ok_or(Errno::Badf));
+    {
+        let mut fd_map = state.fs.fd_map.write().unwrap();
+        let fd_entry = wasi_try!(fd_map.remove(&from).ok_or(Errno::Badf));
+        fd_map.insert(to, fd_entry);
+    }
 
-    let new_fd_entry = Fd {
-        // TODO: verify this is correct
-        offset: fd_entry.offset.clone(),
-        rights: fd_entry.rights_inheriting,
-        inode: fd_entry.inode.clone(),
-        ..*fd_entry
-    };
-    fd_map.insert(to, new_fd_entry);
+    // Preopens are tracked by their file descriptor so the prestat
+    // metadata has to move along with the descriptor
+    {
+        let mut preopen_fds = state.fs.preopen_fds.write().unwrap();
+        preopen_fds.retain(|fd| *fd != to);
+        for fd in preopen_fds.iter_mut().filter(|fd| **fd == from) {
+            *fd = to;
+        }
+    }
     state.fs.make_max_fd(to + 1);
 
     Errno::Success
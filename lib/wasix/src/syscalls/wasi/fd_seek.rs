@@ -13,6 +13,9 @@ use crate::syscalls::*;
 /// Output:
 /// - `Filesize *fd`
 ///     The new offset relative to the start of the file
+/// Errors:
+/// - `Errno::Spipe`
+///     The file descriptor is a pipe or a socket
 #[instrument(level = "trace", skip_all, fields(%fd, %offset, ?whence), ret)]
 pub fn fd_seek<M: MemorySize>(
     mut ctx: FunctionEnvMut<'_, WasiEnv>,
@@ -59,8 +62,18 @@ pub(crate) fn fd_seek_internal(
     let (memory, _) = unsafe { env.get_memory_and_wasi_state(&ctx, 0) };
     let fd_entry = wasi_try_ok_ok!(state.fs.get_fd(fd));
 
+    // Pipes and sockets are streams that can not be seeked
+    if matches!(
+        fd_entry.inode.read().deref(),
+        Kind::Socket { .. } | Kind::Pipe { .. }
+    ) {
+        return Ok(Err(Errno::Spipe));
+    }
     if !fd_entry.rights.contains(Rights::FD_SEEK) {
-        return Ok(Err(Errno::Access));
+        return Ok(Err(match fd_entry.stdio_rights_enforced {
+            true => Errno::Notcapable,
+            false => Errno::Access,
+        }));
     }
     if fd_entry.flags.contains(Fdflags::APPEND) {
         return Ok(Ok(fd_entry.offset.load(Ordering::Acquire)));
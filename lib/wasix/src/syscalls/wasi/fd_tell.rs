@@ -9,6 +9,11 @@ use crate::syscalls::*;
 /// Output:
 /// - `Filesize *offset`
 ///     The offset of `fd` relative to the start of the file
+///
+/// Writes to a file descriptor opened with `Fdflags::APPEND` always land at
+/// the end of the file and move the offset to the new end of the file, so
+/// after an append this reports the size of the file (which is where the
+/// next append will be written).
 #[instrument(level = "debug", skip_all, fields(%fd, offset = field::Empty), ret)]
 pub fn fd_tell<M: MemorySize>(
     ctx: FunctionEnvMut<'_, WasiEnv>,
@@ -22,7 +27,10 @@ pub fn fd_tell<M: MemorySize>(
     let fd_entry = wasi_try!(state.fs.get_fd(fd));
 
     if !fd_entry.rights.contains(Rights::FD_TELL) {
-        return Errno::Access;
+        return match fd_entry.stdio_rights_enforced {
+            true => Errno::Notcapable,
+            false => Errno::Access,
+        };
     }
 
     let offset = fd_entry.offset.load(Ordering::Acquire);
@@ -72,7 +72,8 @@ pub fn fd_write<M: MemorySize>(
 /// - `u32 iovs_len`
 ///     Length of data in `iovs`
 /// - `Filesize offset`
-///     The offset to write at
+///     The offset to write at (as on Linux, the data is appended instead
+///     when the file descriptor has the `APPEND` flag)
 /// Output:
 /// - `u32 *nwritten`
 ///     Number of bytes written
@@ -134,6 +135,9 @@ pub(crate) fn fd_write_internal<M: MemorySize>(
     let is_stdio = fd_entry.is_stdio;
 
     let bytes_written = {
+        if fd_entry.stdio_rights_enforced && !fd_entry.rights.contains(Rights::FD_WRITE) {
+            return Ok(Err(Errno::Notcapable));
+        }
         if !is_stdio && !fd_entry.rights.contains(Rights::FD_WRITE) {
             return Ok(Err(Errno::Access));
         }
@@ -141,6 +145,12 @@ pub(crate) fn fd_write_internal<M: MemorySize>(
         let fd_flags = fd_entry.flags;
         let mut memory = unsafe { env.memory_view(&ctx) };
 
+        // Writes to a file opened with APPEND always land at the end of the
+        // file so we track where it went, like on Linux this includes the
+        // positional writes (which still leave the cursor alone)
+        let append = fd_flags.contains(Fdflags::APPEND);
+        let mut offset = offset;
+
         let (bytes_written, is_file, can_snapshot) = {
             let (mut memory, _) = unsafe { env.get_memory_and_wasi_state(&ctx, 0) };
             let mut guard = fd_entry.inode.write();
@@ -150,7 +160,9 @@ pub(crate) fn fd_write_internal<M: MemorySize>(
                         let handle = handle.clone();
                         drop(guard);
 
-                        let res = __asyncify_light(
+                        // Writes that have to wait for room (e.g. in a bounded pipe)
+                        // can be interrupted by signals
+                        let res = __asyncify_light_interruptible(
                             env,
                             if fd_entry.flags.contains(Fdflags::NONBLOCK) {
                                 Some(Duration::ZERO)
@@ -159,11 +171,36 @@ pub(crate) fn fd_write_internal<M: MemorySize>(
                             },
                             async {
                                 let mut handle = handle.write().unwrap();
+                                // What was read ahead of the write is stale
+                                fd_entry.inode.readahead.lock().unwrap().take();
+                                // The cursor is read and advanced while the file is
+                                // locked so that concurrent writes through the same
+                                // descriptor neither interleave nor overwrite each other
+                                let move_cursor = should_update_cursor && !is_stdio;
+                                let offset = if move_cursor {
+                                    fd_entry.offset.load(Ordering::Acquire)
+                                } else {
+                                    offset
+                                };
+                                let mut start = offset;
                                 if !is_stdio {
-                                    handle
-                                        .seek(std::io::SeekFrom::Start(offset))
-                                        .await
-                                        .map_err(map_io_err)?;
+                                    let pos = if append {
+                                        std::io::SeekFrom::End(0)
+                                    } else {
+                                        std::io::SeekFrom::Start(offset)
+                                    };
+                                    start = handle.seek(pos).await.map_err(map_io_err)?;
+
+                                    // Some file systems will not seek beyond the end of
+                                    // the file, in that case the file is extended first
+                                    // so the gap reads back as zeros
+                                    if !append && start < offset {
+                                        handle.set_len(offset).map_err(fs_error_into_wasi_err)?;
+                                        start = handle
+                                            .seek(std::io::SeekFrom::Start(offset))
+                                            .await
+                                            .map_err(map_io_err)?;
+                                    }
                                 }
 
                                 let mut written = 0usize;
@@ -194,21 +231,37 @@ pub(crate) fn fd_write_internal<M: MemorySize>(
                                         }
                                     }
                                     FdWriteSource::Buffer(data) => {
-                                        handle.write_all(data).await?;
-                                        written += data.len();
+                                        // Short devices may not accept the whole buffer
+                                        // so we only report what was actually written
+                                        while written < data.len() {
+                                            let local_written =
+                                                match handle.write(&data[written..]).await {
+                                                    Ok(0) => break,
+                                                    Ok(s) => s,
+                                                    Err(_) if written > 0 => break,
+                                                    Err(err) => return Err(map_io_err(err)),
+                                                };
+                                            written += local_written;
+                                        }
                                     }
                                 }
 
                                 if is_stdio {
                                     handle.flush().await.map_err(map_io_err)?;
                                 }
-                                Ok(written)
+                                if move_cursor {
+                                    fd_entry
+                                        .offset
+                                        .store(start + written as u64, Ordering::Release);
+                                }
+                                Ok((written, start))
                             },
                         );
-                        let written = wasi_try_ok_ok!(res?.map_err(|err| match err {
+                        let (written, start) = wasi_try_ok_ok!(res?.map_err(|err| match err {
                             Errno::Timedout => Errno::Again,
                             a => a,
                         }));
+                        offset = start;
 
                         (written, true, true)
                     } else {
@@ -227,6 +280,7 @@ pub(crate) fn fd_write_internal<M: MemorySize>(
                         .unwrap_or(Duration::from_secs(30));
 
                     let tasks = env.tasks().clone();
+                    let process = env.process.clone();
 
                     let res = __asyncify_light(env, None, async {
                         let mut sent = 0usize;
@@ -242,14 +296,21 @@ pub(crate) fn fd_write_internal<M: MemorySize>(
                                         .map_err(mem_error_to_wasi)?
                                         .access()
                                         .map_err(mem_error_to_wasi)?;
-                                    let local_sent = socket
+                                    let local_sent = match socket
                                         .send(
                                             tasks.deref(),
+                                            &process,
                                             buf.as_ref(),
                                             Some(timeout),
                                             nonblocking,
                                         )
-                                        .await?;
+                                        .await
+                                    {
+                                        Ok(amt) => amt,
+                                        // The byte cap was reached by the previous buffers
+                                        Err(Errno::Notcapable) if sent > 0 => break,
+                                        Err(err) => return Err(err),
+                                    };
                                     sent += local_sent;
                                     if local_sent != buf.len() {
                                         break;
@@ -258,7 +319,13 @@ pub(crate) fn fd_write_internal<M: MemorySize>(
                             }
                             FdWriteSource::Buffer(data) => {
                                 sent += socket
-                                    .send(tasks.deref(), data.as_ref(), Some(timeout), nonblocking)
+                                    .send(
+                                        tasks.deref(),
+                                        &process,
+                                        data.as_ref(),
+                                        Some(timeout),
+                                        nonblocking,
+                                    )
                                     .await?;
                             }
                         }
@@ -414,15 +481,12 @@ pub(crate) fn fd_write_internal<M: MemorySize>(
 
         // reborrow and update the size
         if !is_stdio {
-            let offset = if is_file && should_update_cursor {
-                let bytes_written = bytes_written as u64;
-                let mut fd_map = state.fs.fd_map.write().unwrap();
-                let fd_entry = wasi_try_ok_ok!(fd_map.get_mut(&fd).ok_or(Errno::Badf));
-                fd_entry
-                    .offset
-                    .fetch_add(bytes_written, Ordering::AcqRel)
-                    // fetch_add returns the previous value, we have to add bytes_written again here
-                    + bytes_written
+            let offset = if is_file {
+                // The cursor was already advanced while the file was locked (an
+                // appending file follows the end of the file) and positional writes
+                // leave it alone, either way the end of the write is derived from
+                // the offset that was written to
+                offset + bytes_written as u64
             } else {
                 fd_entry.offset.load(Ordering::Acquire)
             };
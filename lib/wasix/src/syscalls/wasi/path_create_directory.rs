@@ -11,6 +11,12 @@ use crate::syscalls::*;
 /// - `u32 path_len`
 ///     The length of `path`
 /// Errors:
+/// - `Errno::Exist`
+///     The path already exists
+/// - `Errno::Noent`
+///     The path is empty or a parent directory of the path does not exist
+/// - `Errno::Notdir`
+///     A parent of the path is not a directory
 /// Required Rights:
 /// - Rights::PATH_CREATE_DIRECTORY
 ///     This right must be set on the directory that the file is created in (TODO: verify that this is true)
@@ -68,6 +74,13 @@ pub(crate) fn path_create_directory_internal(
         trace!("working directory (fd={fd}) has no rights to create a directory");
         return Err(Errno::Access);
     }
+    state.fs.check_path_rights(
+        inodes,
+        fd,
+        std::path::Path::new(path),
+        false,
+        Rights::PATH_CREATE_DIRECTORY,
+    )?;
 
     let path = std::path::PathBuf::from(path);
     let path_vec = path
@@ -80,12 +93,15 @@ pub(crate) fn path_create_directory_internal(
         })
         .collect::<Result<Vec<String>, Errno>>()?;
     if path_vec.is_empty() {
-        trace!("path vector is inva;id (its empty)");
-        return Err(Errno::Inval);
+        trace!("path is empty");
+        return Err(Errno::Noent);
     }
 
     let mut cur_dir_inode = working_dir.inode;
-    for comp in &path_vec {
+    for (n, comp) in path_vec.iter().enumerate() {
+        // Only the last component is created, all the ones before it must
+        // already exist and be directories
+        let is_last = n == path_vec.len() - 1;
         let processing_cur_dir_inode = cur_dir_inode.clone();
         let mut guard = processing_cur_dir_inode.write();
         match guard.deref_mut() {
@@ -105,6 +121,10 @@ pub(crate) fn path_create_directory_internal(
                     _ => (),
                 }
                 if let Some(child) = entries.get(comp) {
+                    if is_last {
+                        trace!("path already exists");
+                        return Err(Errno::Exist);
+                    }
                     cur_dir_inode = child.clone();
                 } else {
                     let mut adjusted_path = path.clone();
@@ -120,12 +140,19 @@ pub(crate) fn path_create_directory_internal(
                         0,
                         &adjusted_path.to_string_lossy(),
                     ) {
+                        if is_last {
+                            trace!("path already exists");
+                            return Err(Errno::Exist);
+                        }
                         if adjusted_path_stat.st_filetype != Filetype::Directory {
                             trace!("path is not a directory");
                             return Err(Errno::Notdir);
                         }
-                    } else {
+                    } else if is_last {
                         state.fs_create_dir(&adjusted_path)?;
+                    } else {
+                        trace!("parent directory does not exist");
+                        return Err(Errno::Noent);
                     }
                     let kind = Kind::Dir {
                         parent: cur_dir_inode.downgrade(),
@@ -159,6 +186,7 @@ pub(crate) fn path_create_directory_internal(
             }
         }
     }
+    state.fs.invalidate_dir_snapshots();
 
     Ok(())
 }
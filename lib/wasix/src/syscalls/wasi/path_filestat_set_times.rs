@@ -100,6 +100,13 @@ pub(crate) fn path_filestat_set_times_internal(
         state
             .fs
             .get_inode_at_path(inodes, fd, path, flags & __WASI_LOOKUP_SYMLINK_FOLLOW != 0)?;
+    state.fs.check_path_rights(
+        inodes,
+        fd,
+        std::path::Path::new(path),
+        flags & __WASI_LOOKUP_SYMLINK_FOLLOW != 0,
+        Rights::PATH_FILESTAT_SET_TIMES,
+    )?;
     let stat = {
         let guard = file_inode.read();
         state.fs.get_stat_for_kind(guard.deref())?
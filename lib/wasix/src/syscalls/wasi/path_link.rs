@@ -106,11 +106,40 @@ pub(crate) fn path_link_internal(
         old_flags & __WASI_LOOKUP_SYMLINK_FOLLOW != 0,
     )?;
     let target_path_arg = std::path::PathBuf::from(&new_path_str);
+    state.fs.check_path_rights(
+        inodes,
+        old_fd,
+        std::path::Path::new(&old_path_str),
+        old_flags & __WASI_LOOKUP_SYMLINK_FOLLOW != 0,
+        Rights::PATH_LINK_SOURCE,
+    )?;
+    state.fs.check_path_rights(
+        inodes,
+        new_fd,
+        &target_path_arg,
+        false,
+        Rights::PATH_LINK_TARGET,
+    )?;
     let (target_parent_inode, new_entry_name) =
         state
             .fs
             .get_parent_inode_at_path(inodes, new_fd, &target_path_arg, false)?;
 
+    // Hard links can not span different mounts
+    let source_path = match source_inode.read().deref() {
+        Kind::File { path, .. } | Kind::Dir { path, .. } => Some(path.clone()),
+        _ => None,
+    };
+    let target_path = match target_parent_inode.read().deref() {
+        Kind::Dir { path, .. } => Some(path.join(&new_entry_name)),
+        _ => None,
+    };
+    if let (Some(source_path), Some(target_path)) = (source_path, target_path) {
+        if !state.fs.root_fs.is_same_device(&source_path, &target_path) {
+            return Err(Errno::Xdev);
+        }
+    }
+
     if source_inode.stat.write().unwrap().st_nlink == Linkcount::max_value() {
         return Err(Errno::Mlink);
     }
@@ -134,6 +163,7 @@ pub(crate) fn path_link_internal(
         }
     }
     source_inode.stat.write().unwrap().st_nlink += 1;
+    state.fs.invalidate_dir_snapshots();
 
     Ok(())
 }
@@ -25,6 +25,9 @@ use crate::syscalls::*;
 ///     The new file descriptor
 /// Possible Errors:
 /// - `Errno::Access`, `Errno::Badf`, `Errno::Fault`, `Errno::Fbig?`, `Errno::Inval`, `Errno::Io`, `Errno::Loop`, `Errno::Mfile`, `Errno::Nametoolong?`, `Errno::Nfile`, `Errno::Noent`, `Errno::Notdir`, `Errno::Rofs`, and `Errno::Notcapable`
+///
+/// An empty `path` does not refer to `dirfd` itself, like all the other
+/// `path_*` syscalls it fails with `Errno::Noent`.
 #[instrument(level = "debug", skip_all, fields(%dirfd, path = field::Empty, follow_symlinks = field::Empty, ret_fd = field::Empty), ret)]
 pub fn path_open<M: MemorySize>(
     mut ctx: FunctionEnvMut<'_, WasiEnv>,
@@ -141,13 +144,35 @@ pub(crate) fn path_open_internal(
     }
 
     let mut open_flags = 0;
-    // TODO: traverse rights of dirs properly
-    // COMMENTED OUT: WASI isn't giving appropriate rights here when opening
-    //              TODO: look into this; file a bug report if this is a bug
-    //
-    // Maximum rights: should be the working dir rights
-    // Minimum rights: whatever rights are provided
-    let adjusted_rights = /*fs_rights_base &*/ working_dir_rights_inheriting;
+    // The rights of the new fd (and of the fds derived from it) are the
+    // requested ones bounded by the inheriting rights of the working dir
+    let mut adjusted_rights = fs_rights_base & working_dir_rights_inheriting;
+    let mut adjusted_rights_inheriting = fs_rights_inheriting & working_dir_rights_inheriting;
+
+    // and then by the rights registered for the path that is opened
+    let path_rights = if state.fs.path_rights.is_empty() {
+        None
+    } else {
+        path_open_target_path(
+            state,
+            inodes,
+            dirfd,
+            &path_arg,
+            dirflags & __WASI_LOOKUP_SYMLINK_FOLLOW != 0,
+            maybe_inode.as_ref().ok(),
+        )
+        .map(|target_path| state.fs.path_rights_at(&target_path))
+    };
+    if let Some(path_rights) = path_rights {
+        if maybe_inode.is_err()
+            && o_flags.contains(Oflags::CREATE)
+            && !path_rights.contains(Rights::PATH_CREATE_FILE)
+        {
+            return Ok(Err(Errno::Access));
+        }
+        adjusted_rights &= path_rights;
+        adjusted_rights_inheriting &= path_rights;
+    }
     let mut open_options = state.fs_new_open_options();
 
     let target_rights = match maybe_inode {
@@ -176,7 +201,8 @@ pub(crate) fn path_open_internal(
         }
         Err(_) => virtual_fs::OpenOptionsConfig {
             append: fs_flags.contains(Fdflags::APPEND),
-            write: fs_rights_base.contains(Rights::FD_WRITE),
+            write: fs_rights_base.contains(Rights::FD_WRITE)
+                && path_rights.map_or(true, |rights| rights.contains(Rights::FD_WRITE)),
             read: fs_rights_base.contains(Rights::FD_READ),
             create_new: o_flags.contains(Oflags::CREATE) && o_flags.contains(Oflags::EXCL),
             create: o_flags.contains(Oflags::CREATE),
@@ -224,10 +250,12 @@ pub(crate) fn path_open_internal(
                     return Ok(Err(Errno::Exist));
                 }
 
+                // The handle is never opened in append mode, `Fdflags::APPEND` is
+                // applied to each write instead so that it can be toggled later
                 let open_options = open_options
-                    .write(minimum_rights.write)
+                    .write(minimum_rights.write || minimum_rights.append)
                     .create(minimum_rights.create)
-                    .append(minimum_rights.append)
+                    .append(false)
                     .truncate(minimum_rights.truncate);
 
                 if minimum_rights.read {
@@ -242,9 +270,29 @@ pub(crate) fn path_open_internal(
                 if minimum_rights.truncate {
                     open_flags |= Fd::TRUNCATE;
                 }
-                *handle = Some(Arc::new(std::sync::RwLock::new(wasi_try_ok_ok!(
-                    open_options.open(&path).map_err(fs_error_into_wasi_err)
-                ))));
+                // The handle is shared by every fd that was opened on this
+                // inode (each fd keeps its own cursor), so when replacing it
+                // the new handle must still serve the reads and writes of
+                // the fds that are already open
+                let new_handle =
+                    if handle.is_some() && !(minimum_rights.read && minimum_rights.write) {
+                        let requested = open_options.get_config();
+                        match open_options.read(true).write(true).open(&path) {
+                            Ok(file) => file,
+                            // The file system does not allow it, so none of
+                            // the other fds can write either
+                            Err(_) => wasi_try_ok_ok!(open_options
+                                .options(requested)
+                                .open(&path)
+                                .map_err(fs_error_into_wasi_err)),
+                        }
+                    } else {
+                        wasi_try_ok_ok!(open_options.open(&path).map_err(fs_error_into_wasi_err))
+                    };
+                *handle = Some(Arc::new(std::sync::RwLock::new(new_handle)));
+                // The file may have been changed (or truncated) since it was
+                // read ahead
+                inode.readahead.lock().unwrap().take();
 
                 if let Some(handle) = handle {
                     let handle = handle.read().unwrap();
@@ -291,6 +339,13 @@ pub(crate) fn path_open_internal(
             if o_flags.contains(Oflags::DIRECTORY) {
                 return Ok(Err(Errno::Notdir));
             }
+            // Files can not be created with a trailing slash
+            if path.ends_with('/') {
+                return Ok(Err(match maybe_inode.unwrap_err() {
+                    Errno::Noent => Errno::Isdir,
+                    err => err,
+                }));
+            }
             // strip end file name
 
             let (parent_inode, new_entity_name) =
@@ -313,16 +368,23 @@ pub(crate) fn path_open_internal(
                         new_path.push(&new_entity_name);
                         new_path
                     }
+                    // an intermediate component that is not a directory
+                    Kind::File { .. }
+                    | Kind::Socket { .. }
+                    | Kind::Pipe { .. }
+                    | Kind::EventNotifications { .. }
+                    | Kind::Epoll { .. } => return Ok(Err(Errno::Notdir)),
                     _ => return Ok(Err(Errno::Inval)),
                 }
             };
             // once we got the data we need from the parent, we lookup the host file
             // todo: extra check that opening with write access is okay
             let handle = {
+                // Like above, `Fdflags::APPEND` is applied to each write
                 let open_options = open_options
                     .read(minimum_rights.read)
-                    .append(minimum_rights.append)
-                    .write(minimum_rights.write)
+                    .append(false)
+                    .write(minimum_rights.write || minimum_rights.append)
                     .create_new(minimum_rights.create_new);
 
                 if minimum_rights.read {
@@ -363,6 +425,7 @@ pub(crate) fn path_open_internal(
                     entries.insert(new_entity_name, new_inode.clone());
                 }
             }
+            state.fs.invalidate_dir_snapshots();
 
             new_inode
         } else {
@@ -370,11 +433,10 @@ pub(crate) fn path_open_internal(
         }
     };
 
-    // TODO: check and reduce these
     // TODO: ensure a mutable fd to root can never be opened
     let out_fd = wasi_try_ok_ok!(state.fs.create_fd(
         adjusted_rights,
-        fs_rights_inheriting,
+        adjusted_rights_inheriting,
         fs_flags,
         open_flags,
         inode
@@ -382,3 +444,23 @@ pub(crate) fn path_open_internal(
 
     Ok(Ok(out_fd))
 }
+
+/// Returns the path in the file system of the file that `path_open` opens,
+/// or of the file it creates when none exists yet
+fn path_open_target_path(
+    state: &WasiState,
+    inodes: &WasiInodes,
+    dirfd: WasiFd,
+    path: &Path,
+    follow_symlinks: bool,
+    inode: Option<&InodeGuard>,
+) -> Option<std::path::PathBuf> {
+    if let Some(inode) = inode {
+        return match inode.read().deref() {
+            Kind::File { path, .. } | Kind::Dir { path, .. } => Some(path.clone()),
+            Kind::Root { .. } => Some(std::path::PathBuf::from("/")),
+            _ => None,
+        };
+    }
+    state.fs.entry_path_at(inodes, dirfd, path, follow_symlinks)
+}
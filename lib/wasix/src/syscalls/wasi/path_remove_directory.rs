@@ -14,6 +14,9 @@ pub fn path_remove_directory<M: MemorySize>(
     let (memory, mut state, inodes) = unsafe { env.get_memory_and_wasi_state_and_inodes(&ctx, 0) };
 
     let base_dir = wasi_try!(state.fs.get_fd(fd));
+    if !base_dir.rights.contains(Rights::PATH_REMOVE_DIRECTORY) {
+        return Errno::Access;
+    }
     let mut path_str = unsafe { get_input_str!(&memory, path, path_len) };
     Span::current().record("path", path_str.as_str());
 
@@ -50,6 +53,13 @@ pub(crate) fn path_remove_directory_internal(
     let (memory, mut state, inodes) = unsafe { env.get_memory_and_wasi_state_and_inodes(&ctx, 0) };
 
     let inode = state.fs.get_inode_at_path(inodes, fd, path, false)?;
+    state.fs.check_path_rights(
+        inodes,
+        fd,
+        std::path::Path::new(path),
+        false,
+        Rights::PATH_REMOVE_DIRECTORY,
+    )?;
     let (parent_inode, childs_name) =
         state
             .fs
@@ -100,6 +110,7 @@ pub(crate) fn path_remove_directory_internal(
         }
         return Err(err);
     }
+    state.fs.invalidate_dir_snapshots();
 
     Ok(())
 }
@@ -71,6 +71,20 @@ pub fn path_rename_internal(
             return Ok(Errno::Access);
         }
     }
+    wasi_try_ok!(state.fs.check_path_rights(
+        inodes,
+        source_fd,
+        Path::new(source_path),
+        true,
+        Rights::PATH_RENAME_SOURCE
+    ));
+    wasi_try_ok!(state.fs.check_path_rights(
+        inodes,
+        target_fd,
+        Path::new(target_path),
+        true,
+        Rights::PATH_RENAME_TARGET
+    ));
 
     // this is to be sure the source file is fetch from filesystem if needed
     wasi_try_ok!(state
@@ -116,6 +130,55 @@ pub fn path_rename_internal(
         }
     };
 
+    // Renames can not move entries between different mounts
+    let host_adjusted_source_path = match source_parent_inode.read().deref() {
+        Kind::Dir { path, .. } => Some(path.join(&source_entry_name)),
+        _ => None,
+    };
+    if let Some(host_adjusted_source_path) = host_adjusted_source_path {
+        if !state
+            .fs
+            .root_fs
+            .is_same_device(&host_adjusted_source_path, &host_adjusted_target_path)
+        {
+            return Ok(Errno::Xdev);
+        }
+    }
+
+    // A directory may only replace an existing directory that is empty and
+    // a file may not replace a directory, the rename itself replaces the
+    // target so it is only dropped from the inode table once that succeeded
+    let mut replaces_dir = false;
+    if !need_create {
+        let source_is_dir = match source_parent_inode.read().deref() {
+            Kind::Dir { entries, .. } => entries
+                .get(&source_entry_name)
+                .map(|entry| matches!(entry.read().deref(), Kind::Dir { .. }))
+                .unwrap_or(false),
+            _ => false,
+        };
+        let target_entry = match target_parent_inode.read().deref() {
+            Kind::Dir { entries, .. } => entries.get(&target_entry_name).cloned(),
+            _ => None,
+        };
+        let is_same_entry = source_parent_inode.ino() == target_parent_inode.ino()
+            && source_entry_name == target_entry_name;
+        if let Some(target_entry) = target_entry.filter(|_| !is_same_entry) {
+            let guard = target_entry.read();
+            match (source_is_dir, guard.deref()) {
+                (true, Kind::Dir { entries, path, .. }) => {
+                    if !entries.is_empty() || wasi_try_ok!(state.fs_read_dir(path)).count() != 0 {
+                        return Ok(Errno::Notempty);
+                    }
+                    replaces_dir = true;
+                }
+                (true, _) => return Ok(Errno::Notdir),
+                (false, Kind::Dir { .. }) => return Ok(Errno::Isdir),
+                (false, _) => {}
+            }
+        }
+    }
+
     let source_entry = {
         let mut guard = source_parent_inode.write();
         match guard.deref_mut() {
@@ -167,6 +230,7 @@ pub fn path_rename_internal(
                             unreachable!()
                         }
                     }
+                    source_entry.readahead.lock().unwrap().take();
                 }
             }
             Kind::Dir { ref path, .. } => {
@@ -201,6 +265,15 @@ pub fn path_rename_internal(
         }
     }
 
+    // The replaced directory is gone, the source takes its place
+    if replaces_dir {
+        let mut guard = target_parent_inode.write();
+        if let Kind::Dir { entries, .. } = guard.deref_mut() {
+            entries.remove(&target_entry_name);
+        }
+        need_create = true;
+    }
+
     if need_create {
         let mut guard = target_parent_inode.write();
         if let Kind::Dir { entries, .. } = guard.deref_mut() {
@@ -211,6 +284,7 @@ pub fn path_rename_internal(
             );
         }
     }
+    state.fs.invalidate_dir_snapshots();
 
     Ok(Errno::Success)
 }
@@ -66,6 +66,13 @@ pub fn path_symlink_internal(
     if !base_fd.rights.contains(Rights::PATH_SYMLINK) {
         return Err(Errno::Access);
     }
+    state.fs.check_path_rights(
+        inodes,
+        fd,
+        std::path::Path::new(new_path),
+        true,
+        Rights::PATH_SYMLINK,
+    )?;
 
     // get the depth of the parent + 1 (UNDER INVESTIGATION HMMMMMMMM THINK FISH ^ THINK FISH)
     let old_path_path = std::path::Path::new(old_path);
@@ -132,6 +139,7 @@ pub fn path_symlink_internal(
             entries.insert(entry_name, new_inode);
         }
     }
+    state.fs.invalidate_dir_snapshots();
 
     Ok(())
 }
@@ -59,6 +59,13 @@ pub(crate) fn path_unlink_file_internal(
     let (memory, mut state, inodes) = unsafe { env.get_memory_and_wasi_state_and_inodes(&ctx, 0) };
 
     let inode = wasi_try_ok!(state.fs.get_inode_at_path(inodes, fd, path, false));
+    wasi_try_ok!(state.fs.check_path_rights(
+        inodes,
+        fd,
+        std::path::Path::new(path),
+        false,
+        Rights::PATH_UNLINK_FILE
+    ));
     let (parent_inode, childs_name) = wasi_try_ok!(state.fs.get_parent_inode_at_path(
         inodes,
         fd,
@@ -91,30 +98,41 @@ pub(crate) fn path_unlink_file_internal(
         guard.st_nlink
     };
     if st_nlink == 0 {
-        {
+        let res = {
             let mut guard = removed_inode.read();
             match guard.deref() {
                 Kind::File { handle, path, .. } => {
                     if let Some(h) = handle {
                         let mut h = h.write().unwrap();
-                        wasi_try_ok!(h.unlink().map_err(fs_error_into_wasi_err));
+                        h.unlink().map_err(fs_error_into_wasi_err)
                     } else {
                         // File is closed
                         // problem with the abstraction, we can't call unlink because there's no handle
                         // drop mutable borrow on `path`
                         let path = path.clone();
                         drop(guard);
-                        wasi_try_ok!(state.fs_remove_file(path));
+                        state.fs_remove_file(path)
                     }
                 }
                 Kind::Dir { .. } | Kind::Root { .. } => return Ok(Errno::Isdir),
                 Kind::Symlink { .. } => {
                     // TODO: actually delete real symlinks and do nothing for virtual symlinks
+                    Ok(())
                 }
                 _ => unimplemented!("wasi::path_unlink_file for Buffer"),
             }
+        };
+        if let Err(err) = res {
+            // The file system refused to remove the file (for instance
+            // because it is read-only) so the file stays where it was
+            removed_inode.stat.write().unwrap().st_nlink += 1;
+            if let Kind::Dir { entries, .. } = parent_inode.write().deref_mut() {
+                entries.insert(childs_name, removed_inode);
+            }
+            return Ok(err);
         }
     }
+    state.fs.invalidate_dir_snapshots();
 
     Ok(Errno::Success)
 }
@@ -291,17 +291,16 @@ where
                         continue;
                     }
 
-                    // If the timeout duration is zero then this is an immediate check rather than
-                    // a sleep itself
-                    if clock_info.timeout == 0 {
-                        time_to_sleep = Duration::MAX;
-                    } else if clock_info.timeout == 1 {
+                    // If the timeout duration is zero (or one) then this is an immediate
+                    // check of the other subscriptions, the clock event is only reported
+                    // when nothing else is ready
+                    if clock_info.timeout <= 1 {
                         time_to_sleep = Duration::ZERO;
                         clock_subs.push((clock_info, s.userdata));
                     } else {
                         // if the timeout is specified as an absolute time in the future,
                         // we should calculate the duration we need to sleep
-                        time_to_sleep = if clock_info
+                        let duration = if clock_info
                             .flags
                             .contains(Subclockflags::SUBSCRIPTION_CLOCK_ABSTIME)
                         {
@@ -311,11 +310,24 @@ where
                             )) as u64;
 
                             Duration::from_nanos(clock_info.timeout)
-                                - Duration::from_nanos(now as u64)
+                                .saturating_sub(Duration::from_nanos(now as u64))
                         } else {
                             // if the timeout is not absolute, just use it as duration
-                            Duration::from_nanos(clock_info.timeout)
+                            // (unless its deadline can not be represented by the clock
+                            // in which case it is effectively infinite)
+                            let now = wasi_try_ok!(platform_clock_time_get(
+                                Snapshot0Clockid::Monotonic,
+                                1
+                            )) as u64;
+                            match now.checked_add(clock_info.timeout) {
+                                Some(deadline) if deadline <= i64::MAX as u64 => {
+                                    Duration::from_nanos(clock_info.timeout)
+                                }
+                                _ => Duration::MAX,
+                            }
                         };
+                        // the earliest of the clocks wins
+                        time_to_sleep = time_to_sleep.min(duration);
 
                         clock_subs.push((clock_info, s.userdata));
                     }
@@ -391,6 +403,8 @@ where
     // Build the trigger using the timeout
     let trigger = async move {
         tokio::select! {
+            // Ready file descriptors take priority over an elapsed timeout
+            biased;
             res = batch => res,
             _ = timeout => Err(Errno::Timedout)
         }
@@ -33,6 +33,7 @@ pub fn callback_signal<M: MemorySize>(
         .get_typed_function(&ctx, &name)
         .ok();
     Span::current().record("funct_is_some", funct.is_some());
+    env.process.set_signal_handler(funct.is_some());
 
     {
         let mut inner = ctx.data_mut().try_inner_mut().unwrap();
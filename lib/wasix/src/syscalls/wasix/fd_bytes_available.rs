@@ -0,0 +1,70 @@
+use super::*;
+use crate::syscalls::*;
+
+/// ### `fd_bytes_available()`
+/// Returns the number of bytes that can be read from a file descriptor
+/// without blocking
+/// Note: This is similar to `ioctl` in POSIX for FIONREAD
+///
+/// Inputs:
+/// - `Fd fd`
+///     The file descriptor to query
+/// Output:
+/// - `Filesize *ret_size`
+///     The number of bytes that are available to read (for regular files
+///     these are the bytes between the offset and the end of the file)
+#[instrument(level = "trace", skip_all, fields(%fd, size = field::Empty), ret)]
+pub fn fd_bytes_available<M: MemorySize>(
+    ctx: FunctionEnvMut<'_, WasiEnv>,
+    fd: WasiFd,
+    ret_size: WasmPtr<Filesize, M>,
+) -> Errno {
+    let env = ctx.data();
+    let (memory, state) = unsafe { env.get_memory_and_wasi_state(&ctx, 0) };
+
+    let size = wasi_try!(fd_bytes_available_internal(state, fd));
+    Span::current().record("size", size);
+
+    wasi_try_mem!(ret_size.write(&memory, size));
+
+    Errno::Success
+}
+
+pub(crate) fn fd_bytes_available_internal(
+    state: &WasiState,
+    fd: WasiFd,
+) -> Result<Filesize, Errno> {
+    let fd_entry = state.fs.get_fd(fd)?;
+    let offset = fd_entry.offset.load(Ordering::Acquire);
+
+    let size = {
+        let guard = fd_entry.inode.read();
+        match guard.deref() {
+            Kind::Pipe { pipe } => pipe.bytes_available() as Filesize,
+            Kind::Socket { socket } => socket.bytes_available()? as Filesize,
+            Kind::File {
+                handle: Some(handle),
+                ..
+            } => {
+                let mut handle = handle.write().map_err(|_| Errno::Fault)?;
+                if fd_entry.is_stdio {
+                    // The stdio streams are not seekable so we can only ask
+                    // them how much they have buffered
+                    let waker = futures::task::noop_waker();
+                    let mut cx = Context::from_waker(&waker);
+                    match Pin::new(handle.as_mut()).poll_read_ready(&mut cx) {
+                        Poll::Ready(Ok(amt)) => amt as Filesize,
+                        Poll::Ready(Err(err)) => return Err(map_io_err(err)),
+                        Poll::Pending => 0,
+                    }
+                } else {
+                    handle.size().saturating_sub(offset)
+                }
+            }
+            Kind::Buffer { buffer } => (buffer.len() as Filesize).saturating_sub(offset),
+            Kind::Dir { .. } | Kind::Root { .. } => return Err(Errno::Isdir),
+            _ => return Err(Errno::Inval),
+        }
+    };
+    Ok(size)
+}
@@ -1,7 +1,7 @@
 use std::task::Waker;
 
 use super::*;
-use crate::syscalls::*;
+use crate::{syscalls::*, WasiProcess};
 
 /// Poller returns true if its triggered and false if it times out
 struct FutexPoller {
@@ -10,6 +10,9 @@ struct FutexPoller {
     futex_idx: u64,
     expected: u32,
     timeout: Option<Pin<Box<dyn Future<Output = ()> + Send + Sync + 'static>>>,
+    process: WasiProcess,
+    /// Monotonic time at which the thread went to sleep on the futex
+    wait_started: Option<i64>,
 }
 impl Future for FutexPoller {
     type Output = bool;
@@ -58,6 +61,13 @@ impl Drop for FutexPoller {
         if should_remove {
             guard.futexes.remove(&self.futex_idx);
         }
+        drop(guard);
+
+        if let Some(started) = self.wait_started {
+            let now = platform_clock_time_get(Snapshot0Clockid::Monotonic, 1).unwrap_or(started);
+            let waited = Duration::from_nanos(now.saturating_sub(started).max(0) as u64);
+            self.process.record_futex_wait_time(self.futex_idx, waited);
+        }
     }
 }
 
@@ -124,7 +134,7 @@ pub(super) fn futex_wait_internal<M: MemorySize + 'static>(
     // it will remove itself from the lookup. It can also be
     // removed whenever the wake call is invoked (which could
     // be before the poller is polled).
-    let poller = {
+    let mut poller = {
         let mut guard = env.state.futexs.lock().unwrap();
         guard.poller_seed += 1;
         let poller_idx = guard.poller_seed;
@@ -144,6 +154,8 @@ pub(super) fn futex_wait_internal<M: MemorySize + 'static>(
             futex_idx,
             expected,
             timeout,
+            process: env.process.clone(),
+            wait_started: None,
         }
     };
 
@@ -160,6 +172,9 @@ pub(super) fn futex_wait_internal<M: MemorySize + 'static>(
     // then the value is not set) - the poller will set it to true
     wasi_try_mem_ok!(ret_woken.write(&memory, Bool::False));
 
+    env.process.record_futex_wait(futex_idx);
+    poller.wait_started = platform_clock_time_get(Snapshot0Clockid::Monotonic, 1).ok();
+
     // We use asyncify on the poller and potentially go into deep sleep
     tracing::trace!("wait on {futex_idx}");
     let res = __asyncify_with_deep_sleep::<M, _, _>(ctx, Box::pin(poller))?;
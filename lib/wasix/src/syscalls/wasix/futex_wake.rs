@@ -22,6 +22,7 @@ pub fn futex_wake<M: MemorySize>(
     Span::current().record("futex_idx", pointer);
 
     let mut woken = false;
+    let mut woken_waiters = 0;
     let woken = {
         let mut guard = state.futexs.lock().unwrap();
         if let Some(futex) = guard.futexes.get_mut(&pointer) {
@@ -29,6 +30,7 @@ pub fn futex_wake<M: MemorySize>(
             if let Some(id) = first {
                 if let Some(Some(w)) = futex.wakers.remove(&id) {
                     w.wake();
+                    woken_waiters = 1;
                 }
             }
             if futex.wakers.is_empty() {
@@ -42,6 +44,7 @@ pub fn futex_wake<M: MemorySize>(
         }
     };
     Span::current().record("woken", woken);
+    env.process.record_futex_wakes(pointer, woken_waiters);
 
     let woken = match woken {
         false => Bool::False,
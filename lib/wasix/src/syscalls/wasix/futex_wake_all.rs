@@ -20,12 +20,14 @@ pub fn futex_wake_all<M: MemorySize>(
     //Span::current().record("futex_idx", pointer);
 
     let mut woken = false;
+    let mut woken_waiters = 0;
     let woken = {
         let mut guard = state.futexs.lock().unwrap();
         if let Some(futex) = guard.futexes.remove(&pointer) {
             for waker in futex.wakers {
                 if let Some(waker) = waker.1 {
                     waker.wake();
+                    woken_waiters += 1;
                 }
             }
             tracing::trace!("wake_all (hit) on {pointer}");
@@ -36,6 +38,7 @@ pub fn futex_wake_all<M: MemorySize>(
         }
     };
     //Span::current().record("woken", woken);
+    env.process.record_futex_wakes(pointer, woken_waiters);
 
     let woken = match woken {
         false => Bool::False,
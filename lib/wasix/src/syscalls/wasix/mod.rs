@@ -3,6 +3,7 @@ mod chdir;
 mod epoll_create;
 mod epoll_ctl;
 mod epoll_wait;
+mod fd_bytes_available;
 mod fd_pipe;
 mod futex_wait;
 mod futex_wake;
@@ -23,10 +24,15 @@ mod port_route_remove;
 mod port_unbridge;
 mod proc_exec;
 mod proc_fork;
+mod proc_getpgid;
+mod proc_getsid;
 mod proc_id;
 mod proc_join;
 mod proc_parent;
+mod proc_setpgid;
+mod proc_setsid;
 mod proc_signal;
+mod proc_signal_group;
 mod proc_spawn;
 mod resolve;
 mod sched_yield;
@@ -44,6 +50,7 @@ mod sock_leave_multicast_v4;
 mod sock_leave_multicast_v6;
 mod sock_listen;
 mod sock_open;
+mod sock_peer_pid;
 mod sock_recv;
 mod sock_recv_from;
 mod sock_send;
@@ -56,6 +63,7 @@ mod sock_shutdown;
 mod sock_status;
 mod stack_checkpoint;
 mod stack_restore;
+mod syscall_batch;
 mod thread_exit;
 mod thread_id;
 mod thread_join;
@@ -71,6 +79,7 @@ pub use chdir::*;
 pub use epoll_create::*;
 pub use epoll_ctl::*;
 pub use epoll_wait::*;
+pub use fd_bytes_available::*;
 pub use fd_pipe::*;
 pub use futex_wait::*;
 pub use futex_wake::*;
@@ -91,10 +100,15 @@ pub use port_route_remove::*;
 pub use port_unbridge::*;
 pub use proc_exec::*;
 pub use proc_fork::*;
+pub use proc_getpgid::*;
+pub use proc_getsid::*;
 pub use proc_id::*;
 pub use proc_join::*;
 pub use proc_parent::*;
+pub use proc_setpgid::*;
+pub use proc_setsid::*;
 pub use proc_signal::*;
+pub use proc_signal_group::*;
 pub use proc_spawn::*;
 pub use resolve::*;
 pub use sched_yield::*;
@@ -112,6 +126,7 @@ pub use sock_leave_multicast_v4::*;
 pub use sock_leave_multicast_v6::*;
 pub use sock_listen::*;
 pub use sock_open::*;
+pub use sock_peer_pid::*;
 pub use sock_recv::*;
 pub use sock_recv_from::*;
 pub use sock_send::*;
@@ -124,6 +139,7 @@ pub use sock_shutdown::*;
 pub use sock_status::*;
 pub use stack_checkpoint::*;
 pub use stack_restore::*;
+pub use syscall_batch::*;
 pub use thread_exit::*;
 pub use thread_id::*;
 pub use thread_join::*;
@@ -56,6 +56,13 @@ pub fn proc_exec<M: MemorySize>(
     if name.starts_with("./") {
         name = ctx.data().state.fs.relative_path_to_absolute(name);
     }
+
+    // Bare program names are looked up in the exec search path
+    if !name.contains('/') {
+        if let Some(path) = ctx.data().resolve_exec_path(&name) {
+            name = path;
+        }
+    }
     trace!(name);
 
     // Convert the preopen directories
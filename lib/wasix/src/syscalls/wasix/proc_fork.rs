@@ -19,6 +19,10 @@ pub(crate) struct ForkResult {
 /// Forks the current process into a new subprocess. If the function
 /// returns a zero then its the new subprocess. If it returns a positive
 /// number then its the current process and the $pid represents the child.
+///
+/// The child inherits the working directory, the signal mask and the signal
+/// dispositions of the parent but starts without any pending signals. WASIX
+/// has no file mode creation mask (`umask`) so there is none to inherit.
 #[instrument(level = "debug", skip_all, fields(pid = ctx.data().process.pid().raw()), ret)]
 pub fn proc_fork<M: MemorySize>(
     mut ctx: FunctionEnvMut<'_, WasiEnv>,
@@ -52,8 +56,7 @@ pub fn proc_fork<M: MemorySize>(
         Ok(p) => p,
         Err(err) => {
             debug!("could not fork process: {err}");
-            // TODO: evaluate the appropriate error code, document it in the spec.
-            return Ok(Errno::Perm);
+            return Ok(Errno::Again);
         }
     };
     let child_pid = child_env.process.pid();
@@ -0,0 +1,29 @@
+use super::*;
+use crate::syscalls::*;
+
+/// ### `proc_getpgid()`
+/// Returns the process group of a process
+///
+/// ## Parameters
+///
+/// * `pid` - Handle of the process (zero for the current process)
+#[instrument(level = "debug", skip_all, fields(%pid, pgid = field::Empty), ret)]
+pub fn proc_getpgid<M: MemorySize>(
+    ctx: FunctionEnvMut<'_, WasiEnv>,
+    pid: Pid,
+    ret_pgid: WasmPtr<Pid, M>,
+) -> Errno {
+    let env = ctx.data();
+    let process = if pid == 0 {
+        env.process.clone()
+    } else {
+        wasi_try!(env.control_plane.get_process(pid.into()).ok_or(Errno::Srch))
+    };
+
+    let pgid = process.pgid();
+    Span::current().record("pgid", pgid.raw());
+
+    let memory = unsafe { env.memory_view(&ctx) };
+    wasi_try_mem!(ret_pgid.write(&memory, pgid.raw() as Pid));
+    Errno::Success
+}
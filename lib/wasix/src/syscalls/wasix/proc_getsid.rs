@@ -0,0 +1,29 @@
+use super::*;
+use crate::syscalls::*;
+
+/// ### `proc_getsid()`
+/// Returns the session of a process
+///
+/// ## Parameters
+///
+/// * `pid` - Handle of the process (zero for the current process)
+#[instrument(level = "debug", skip_all, fields(%pid, sid = field::Empty), ret)]
+pub fn proc_getsid<M: MemorySize>(
+    ctx: FunctionEnvMut<'_, WasiEnv>,
+    pid: Pid,
+    ret_sid: WasmPtr<Pid, M>,
+) -> Errno {
+    let env = ctx.data();
+    let process = if pid == 0 {
+        env.process.clone()
+    } else {
+        wasi_try!(env.control_plane.get_process(pid.into()).ok_or(Errno::Srch))
+    };
+
+    let sid = process.sid();
+    Span::current().record("sid", sid.raw());
+
+    let memory = unsafe { env.memory_view(&ctx) };
+    wasi_try_mem!(ret_sid.write(&memory, sid.raw() as Pid));
+    Errno::Success
+}
@@ -0,0 +1,60 @@
+use super::*;
+use crate::syscalls::*;
+
+/// ### `proc_setpgid()`
+/// Moves a process into a process group
+///
+/// ## Parameters
+///
+/// * `pid` - Handle of the process to move, either the current process
+///   or one of its children (zero for the current process)
+/// * `pgid` - Process group to move it into (zero to create a new group
+///   that is led by the process)
+///
+/// Errors:
+/// - `Errno::Srch`
+///     The process is neither the current process nor one of its children
+/// - `Errno::Perm`
+///     The process is a session leader or the process group does not
+///     exist in the session of the process
+#[instrument(level = "debug", skip_all, fields(%pid, %pgid), ret)]
+pub fn proc_setpgid(ctx: FunctionEnvMut<'_, WasiEnv>, pid: Pid, pgid: Pid) -> Errno {
+    let env = ctx.data();
+    let process = if pid == 0 || pid == env.process.pid().raw() {
+        env.process.clone()
+    } else {
+        let inner = env.process.lock();
+        wasi_try!(inner
+            .children
+            .iter()
+            .find(|child| child.pid().raw() == pid)
+            .cloned()
+            .ok_or(Errno::Srch))
+    };
+    let pgid = if pgid == 0 {
+        process.pid()
+    } else {
+        WasiProcessId::from(pgid)
+    };
+
+    if process.pgid() == pgid {
+        return Errno::Success;
+    }
+    if process.sid() == process.pid() {
+        return Errno::Perm;
+    }
+    // Joining a group that is led by another process requires that
+    // group to exist in the same session
+    if pgid != process.pid()
+        && !env
+            .control_plane
+            .process_group(pgid)
+            .iter()
+            .any(|member| member.sid() == process.sid())
+    {
+        return Errno::Perm;
+    }
+
+    process.set_pgid(pgid);
+    Errno::Success
+}
@@ -0,0 +1,30 @@
+use super::*;
+use crate::syscalls::*;
+
+/// ### `proc_setsid()`
+/// Creates a new session that is led by the current process, the process
+/// also becomes the leader of a new process group in that session
+///
+/// Errors:
+/// - `Errno::Perm`
+///     The current process is already the leader of a process group
+#[instrument(level = "debug", skip_all, fields(sid = field::Empty), ret)]
+pub fn proc_setsid<M: MemorySize>(
+    ctx: FunctionEnvMut<'_, WasiEnv>,
+    ret_sid: WasmPtr<Pid, M>,
+) -> Errno {
+    let env = ctx.data();
+    let process = &env.process;
+    if process.pgid() == process.pid() {
+        return Errno::Perm;
+    }
+
+    let sid = process.pid();
+    process.set_sid(sid);
+    process.set_pgid(sid);
+    Span::current().record("sid", sid.raw());
+
+    let memory = unsafe { env.memory_view(&ctx) };
+    wasi_try_mem!(ret_sid.write(&memory, sid.raw() as Pid));
+    Errno::Success
+}
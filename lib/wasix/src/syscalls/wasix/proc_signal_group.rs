@@ -0,0 +1,35 @@
+use super::*;
+use crate::syscalls::*;
+
+/// ### `proc_signal_group()`
+/// Sends a signal to all the processes of a process group
+///
+/// ## Parameters
+///
+/// * `pgid` - Process group to signal (zero for the group of the
+///   current process)
+/// * `sig` - Signal to send the processes
+///
+/// Errors:
+/// - `Errno::Srch`
+///     There are no processes in the process group
+#[instrument(level = "trace", skip_all, fields(%pgid, ?sig), ret)]
+pub fn proc_signal_group(
+    mut ctx: FunctionEnvMut<'_, WasiEnv>,
+    pgid: Pid,
+    sig: Signal,
+) -> Result<Errno, WasiError> {
+    let env = ctx.data();
+    let pgid = if pgid == 0 {
+        env.process.pgid()
+    } else {
+        WasiProcessId::from(pgid)
+    };
+    if env.control_plane.signal_process_group(pgid, sig) == 0 {
+        return Ok(Errno::Srch);
+    }
+
+    wasi_try_ok!(WasiEnv::process_signals_and_exit(&mut ctx)?);
+
+    Ok(Errno::Success)
+}
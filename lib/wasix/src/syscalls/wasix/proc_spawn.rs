@@ -1,8 +1,11 @@
-use virtual_fs::Pipe;
+use std::panic::AssertUnwindSafe;
+
+use futures::FutureExt;
+use virtual_fs::{Pipe, VirtualFile};
 use wasmer_wasix_types::wasi::ProcessHandles;
 
 use super::*;
-use crate::syscalls::*;
+use crate::{os::command::SpawnStdio, syscalls::*};
 
 /// Spawns a new process within the context of this machine
 ///
@@ -103,6 +106,12 @@ pub fn proc_spawn_internal(
 ) -> WasiResult<(ProcessHandles, FunctionEnvMut<'_, WasiEnv>)> {
     let env = ctx.data();
 
+    // Programs claimed by the spawn handler run natively on the host
+    let spawn_handler = env
+        .spawn_handler
+        .clone()
+        .filter(|handler| handler.claims(name.as_str()));
+
     // Build a new store that will be passed to the thread
     let new_store = ctx.data().runtime.new_store();
 
@@ -110,14 +119,13 @@ pub fn proc_spawn_internal(
     let (mut child_env, handle) = match ctx.data().fork() {
         Ok(x) => x,
         Err(err) => {
-            // TODO: evaluate the appropriate error code, document it in the spec.
-            return Ok(Err(Errno::Access));
+            return Ok(Err(Errno::Again));
         }
     };
     let child_process = child_env.process.clone();
     if let Some(args) = args {
-        let mut child_state = env.state.fork();
-        child_state.args = args;
+        let mut child_state = child_env.state.fork();
+        child_state.set_args(args);
         child_env.state = Arc::new(child_state);
     }
 
@@ -143,7 +151,9 @@ pub fn proc_spawn_internal(
         child_env.state.fs.set_current_dir(working_dir.as_str());
     }
 
-    // Replace the STDIO
+    // Replace the STDIO (the ends of the pipes of a natively run program
+    // are handed to the spawn handler instead)
+    let mut native_stdio: [Option<Pipe>; 3] = [None, None, None];
     let (stdin, stdout, stderr) = {
         let (child_state, child_inodes) = child_env.get_wasi_state_and_inodes();
         let mut conv_stdio_mode = |mode: WasiStdioMode, fd: WasiFd| -> Result<OptionFd, Errno> {
@@ -156,12 +166,6 @@ pub fn proc_spawn_internal(
                         false,
                         "pipe".into(),
                     );
-                    let inode2 = child_state.fs.create_inode_with_default_stat(
-                        child_inodes,
-                        Kind::Pipe { pipe: pipe2 },
-                        false,
-                        "pipe".into(),
-                    );
 
                     let rights = crate::net::socket::all_socket_rights();
                     let pipe = ctx.data().state.fs.create_fd(
@@ -171,14 +175,24 @@ pub fn proc_spawn_internal(
                         0,
                         inode1,
                     )?;
-                    child_state.fs.create_fd_ext(
-                        rights,
-                        rights,
-                        Fdflags::empty(),
-                        0,
-                        inode2,
-                        fd,
-                    )?;
+                    if spawn_handler.is_some() {
+                        native_stdio[fd as usize] = Some(pipe2);
+                    } else {
+                        let inode2 = child_state.fs.create_inode_with_default_stat(
+                            child_inodes,
+                            Kind::Pipe { pipe: pipe2 },
+                            false,
+                            "pipe".into(),
+                        );
+                        child_state.fs.create_fd_ext(
+                            rights,
+                            rights,
+                            Fdflags::empty(),
+                            0,
+                            inode2,
+                            fd,
+                        )?;
+                    }
 
                     trace!("fd_pipe (fd1={}, fd2={})", pipe, fd);
                     Ok(OptionFd {
@@ -218,12 +232,52 @@ pub fn proc_spawn_internal(
     let bin_factory = Box::new(ctx.data().bin_factory.clone());
     let child_pid = child_env.pid();
 
-    let mut new_store = Some(new_store);
-    let mut builder = Some(child_env);
+    if let Some(spawn_handler) = spawn_handler {
+        let [stdin_pipe, stdout_pipe, stderr_pipe] = native_stdio;
+        let into_file =
+            |pipe: Pipe| -> Box<dyn VirtualFile + Send + Sync + 'static> { Box::new(pipe) };
+        let stdio = SpawnStdio {
+            stdin: stdin_pipe.map(into_file),
+            stdout: stdout_pipe.map(into_file),
+            stderr: stderr_pipe.map(into_file),
+        };
+        let args = child_env.state.args.clone();
+        let work = std::panic::catch_unwind(AssertUnwindSafe(|| {
+            spawn_handler.spawn(name.as_str(), args, stdio)
+        }));
+        let timeout = spawn_handler
+            .timeout()
+            .map(|timeout| ctx.data().tasks().sleep_now(timeout));
+
+        // The process exits once the handler is finished with it
+        let res = ctx.data().tasks().task_shared(Box::new(
+            move || -> futures::future::BoxFuture<'static, ()> {
+                Box::pin(async move {
+                    let exit_code = match work {
+                        Ok(work) => run_spawn_handler(work, timeout).await,
+                        Err(_) => {
+                            error!("the spawn handler panicked");
+                            Errno::Noexec.into()
+                        }
+                    };
+                    child_env.on_exit(Some(exit_code)).await;
+                })
+            },
+        ));
+        if let Err(err) = res {
+            return Ok(Err(err.into()));
+        }
+    } else {
+        let mut new_store = Some(new_store);
+        let mut builder = Some(child_env);
 
-    // First we try the built in commands
-    let mut process =
-        match bin_factory.try_built_in(name.clone(), Some(&ctx), &mut new_store, &mut builder) {
+        // First we try the built in commands
+        let mut process = match bin_factory.try_built_in(
+            name.clone(),
+            Some(&ctx),
+            &mut new_store,
+            &mut builder,
+        ) {
             Ok(a) => a,
             Err(err) => {
                 if !err.is_not_found() {
@@ -242,6 +296,7 @@ pub fn proc_spawn_internal(
                 }
             }
         };
+    }
 
     // Add the process to the environment state
     {
@@ -259,3 +314,30 @@ pub fn proc_spawn_internal(
     };
     Ok(Ok((handles, ctx)))
 }
+
+/// Waits for a program run by the spawn handler, a panic or running past
+/// the timeout ends the program with an error
+async fn run_spawn_handler(
+    work: futures::future::BoxFuture<'static, ExitCode>,
+    timeout: Option<std::pin::Pin<Box<dyn Future<Output = ()> + Send + Sync>>>,
+) -> ExitCode {
+    let timeout = async move {
+        match timeout {
+            Some(timeout) => timeout.await,
+            None => std::future::pending().await,
+        }
+    };
+    tokio::select! {
+        res = AssertUnwindSafe(work).catch_unwind() => match res {
+            Ok(exit_code) => exit_code,
+            Err(_) => {
+                error!("the spawn handler panicked");
+                Errno::Noexec.into()
+            }
+        },
+        _ = timeout => {
+            warn!("the spawn handler has timed out");
+            Errno::Timedout.into()
+        }
+    }
+}
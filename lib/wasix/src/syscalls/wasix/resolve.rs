@@ -39,14 +39,21 @@ pub fn resolve<M: MemorySize>(
 
     let port = if port > 0 { Some(port) } else { None };
 
-    let net = env.net().clone();
-    let tasks = env.tasks().clone();
-    let found_ips = wasi_try_ok!(__asyncify(&mut ctx, None, async move {
-        net.resolve(host_str.as_str(), port, None)
-            .await
-            .map_err(net_error_into_wasi_err)
-    })?);
-    env = ctx.data();
+    // Static mappings take precedence over the network resolver
+    let static_ips = env.hosts.get(&host_str.to_ascii_lowercase()).cloned();
+    let found_ips = match static_ips {
+        Some(ips) => ips,
+        None => {
+            let net = env.net().clone();
+            let found_ips = wasi_try_ok!(__asyncify(&mut ctx, None, async move {
+                net.resolve(host_str.as_str(), port, None)
+                    .await
+                    .map_err(net_error_into_wasi_err)
+            })?);
+            env = ctx.data();
+            found_ips
+        }
+    };
 
     let mut idx = 0;
     let memory = unsafe { env.memory_view(&ctx) };
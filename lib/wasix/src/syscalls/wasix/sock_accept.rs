@@ -26,13 +26,12 @@ pub fn sock_accept<M: MemorySize>(
 
     ctx = wasi_try_ok!(maybe_snapshot::<M>(ctx)?);
 
-    let env = ctx.data();
-    let (memory, state, _) = unsafe { env.get_memory_and_wasi_state_and_inodes(&ctx, 0) };
-
     let nonblocking = fd_flags.contains(Fdflags::NONBLOCK);
 
-    let (fd, _, _) = wasi_try_ok!(sock_accept_internal(env, sock, fd_flags, nonblocking)?);
+    let (fd, _, _) = wasi_try_ok!(sock_accept_internal(&mut ctx, sock, fd_flags, nonblocking)?);
 
+    let env = ctx.data();
+    let (memory, state, _) = unsafe { env.get_memory_and_wasi_state_and_inodes(&ctx, 0) };
     wasi_try_mem_ok!(ro_fd.write(&memory, fd));
 
     Ok(Errno::Success)
@@ -61,13 +60,10 @@ pub fn sock_accept_v2<M: MemorySize>(
 ) -> Result<Errno, WasiError> {
     wasi_try_ok!(WasiEnv::process_signals_and_exit(&mut ctx)?);
 
-    let env = ctx.data();
-    let (memory, state, _) = unsafe { env.get_memory_and_wasi_state_and_inodes(&ctx, 0) };
-
     let nonblocking = fd_flags.contains(Fdflags::NONBLOCK);
 
     let (fd, local_addr, peer_addr) =
-        wasi_try_ok!(sock_accept_internal(env, sock, fd_flags, nonblocking)?);
+        wasi_try_ok!(sock_accept_internal(&mut ctx, sock, fd_flags, nonblocking)?);
 
     #[cfg(feature = "journal")]
     if ctx.data().enable_journal {
@@ -100,42 +96,50 @@ pub fn sock_accept_v2<M: MemorySize>(
 }
 
 pub(crate) fn sock_accept_internal(
-    env: &WasiEnv,
+    ctx: &mut FunctionEnvMut<'_, WasiEnv>,
     sock: WasiFd,
     mut fd_flags: Fdflags,
     mut nonblocking: bool,
 ) -> Result<Result<(WasiFd, SocketAddr, SocketAddr), Errno>, WasiError> {
-    let state = env.state();
-    let inodes = &state.inodes;
+    let env = ctx.data();
+    let fd_entry = wasi_try_ok_ok!(env.state.fs.get_fd(sock));
+    if !fd_entry.rights.contains(Rights::SOCK_ACCEPT) {
+        return Ok(Err(Errno::Access));
+    }
+    let socket = match fd_entry.inode.read().deref() {
+        Kind::Socket { socket } => socket.clone(),
+        _ => return Ok(Err(Errno::Notsock)),
+    };
 
+    if fd_entry.flags.contains(Fdflags::NONBLOCK) {
+        fd_flags.set(Fdflags::NONBLOCK, true);
+        nonblocking = true;
+    }
+    let timeout = socket
+        .opt_time(TimeType::AcceptTimeout)
+        .ok()
+        .flatten()
+        .unwrap_or(Duration::from_secs(30));
+    let local_addr = wasi_try_ok_ok!(socket.addr_local());
+
+    // Signals that arrive while waiting for a connection are handled and
+    // then interrupt the wait with `Errno::Intr`
     let tasks = env.tasks().clone();
-    let (child, local_addr, peer_addr, fd_flags) = wasi_try_ok_ok!(__sock_asyncify(
-        env,
-        sock,
-        Rights::SOCK_ACCEPT,
-        move |socket, fd| async move {
-            if fd.flags.contains(Fdflags::NONBLOCK) {
-                fd_flags.set(Fdflags::NONBLOCK, true);
-                nonblocking = true;
-            }
-            let timeout = socket
-                .opt_time(TimeType::AcceptTimeout)
-                .ok()
-                .flatten()
-                .unwrap_or(Duration::from_secs(30));
-            let local_addr = socket.addr_local()?;
-            socket
-                .accept(tasks.deref(), nonblocking, Some(timeout))
-                .await
-                .map(|a| (a.0, local_addr, a.1, fd_flags))
-        },
-    ));
+    let (child, peer_addr) = wasi_try_ok_ok!(__asyncify(ctx, None, async move {
+        socket
+            .accept(tasks.deref(), nonblocking, Some(timeout))
+            .await
+    })?);
+
+    let env = ctx.data();
+    let state = env.state();
+    let inodes = &state.inodes;
 
     let kind = Kind::Socket {
         socket: InodeSocket::new(InodeSocketKind::TcpStream {
             socket: child,
-            write_timeout: None,
-            read_timeout: None,
+            write_timeout: env.default_socket_write_timeout,
+            read_timeout: env.default_socket_read_timeout,
         }),
     };
     let inode = state
@@ -7,6 +7,10 @@ use crate::syscalls::*;
 /// Polling the socket handle will wait for data to arrive or for
 /// the socket status to change which can be queried via 'sock_status'
 ///
+/// When the socket is in non-blocking mode the connection is started in
+/// the background and `Errno::Inprogress` is returned, the socket will
+/// become writable (see `poll_oneoff`) once the connection completes.
+///
 /// Note: This is similar to `connect` in POSIX
 ///
 /// ## Parameters
@@ -25,7 +29,13 @@ pub fn sock_connect<M: MemorySize>(
     let peer_addr = SocketAddr::new(addr.0, addr.1);
     Span::current().record("addr", &format!("{:?}", peer_addr));
 
-    wasi_try_ok!(sock_connect_internal(&mut ctx, sock, peer_addr)?);
+    // A non-blocking connect that is still in progress is journaled as well,
+    // otherwise the connection would be missing when the journal is replayed
+    let res = sock_connect_internal(&mut ctx, sock, peer_addr)?;
+    match res {
+        Ok(()) | Err(Errno::Inprogress) => {}
+        Err(err) => return Ok(err),
+    }
 
     #[cfg(feature = "journal")]
     if ctx.data().enable_journal {
@@ -43,7 +53,7 @@ pub fn sock_connect<M: MemorySize>(
         )?;
     }
 
-    Ok(Errno::Success)
+    Ok(res.err().unwrap_or(Errno::Success))
 }
 
 pub(crate) fn sock_connect_internal(
@@ -53,6 +63,23 @@ pub(crate) fn sock_connect_internal(
 ) -> Result<Result<(), Errno>, WasiError> {
     let env = ctx.data();
     let net = env.net().clone();
+
+    let fd_entry = wasi_try_ok_ok!(env.state.fs.get_fd(sock));
+    if fd_entry.flags.contains(Fdflags::NONBLOCK) {
+        // The connection is registered against this process once it completes
+        let owner = (env.control_plane.clone(), env.pid());
+        let in_progress = wasi_try_ok_ok!(__sock_actor_mut(
+            ctx,
+            sock,
+            Rights::SOCK_CONNECT,
+            move |mut socket, _| socket.connect_nonblocking(net, addr, owner)
+        ));
+        if in_progress {
+            return Ok(Err(Errno::Inprogress));
+        }
+        return Ok(Ok(()));
+    }
+
     let tasks = ctx.data().tasks().clone();
     wasi_try_ok_ok!(__sock_upgrade(
         ctx,
@@ -61,5 +88,13 @@ pub(crate) fn sock_connect_internal(
         move |mut socket| async move { socket.connect(tasks.deref(), net.deref(), addr, None).await }
     ));
 
+    // Register the new connection so that in-process peers can look us up
+    let control_plane = ctx.data().control_plane.clone();
+    let pid = ctx.data().pid();
+    __sock_actor(ctx, sock, Rights::empty(), |socket, _| {
+        socket.set_owner(&control_plane, pid)
+    })
+    .ok();
+
     Ok(Ok(()))
 }
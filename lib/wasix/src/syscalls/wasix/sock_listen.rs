@@ -7,7 +7,8 @@ use crate::{journal::SnapshotTrigger, syscalls::*};
 /// Polling the socket handle will wait until a connection
 /// attempt is made
 ///
-/// Note: This is similar to `listen`
+/// Note: This is similar to `listen`, a socket that was not bound is bound
+/// to an ephemeral port on all the interfaces first
 ///
 /// ## Parameters
 ///
@@ -52,5 +53,13 @@ pub(crate) fn sock_listen_internal(
         |socket| async move { socket.listen(tasks.deref(), net.deref(), backlog).await }
     ));
 
+    // Register the listener so that in-process peers can look us up
+    let control_plane = ctx.data().control_plane.clone();
+    let pid = ctx.data().pid();
+    __sock_actor(ctx, sock, Rights::empty(), |socket, _| {
+        socket.set_owner(&control_plane, pid)
+    })
+    .ok();
+
     Ok(Ok(()))
 }
@@ -82,15 +82,17 @@ pub(crate) fn sock_open_internal(
                     no_delay: None,
                     keep_alive: None,
                     dont_route: None,
+                    ttl: None,
                     send_buf_size: None,
                     recv_buf_size: None,
-                    write_timeout: None,
-                    read_timeout: None,
+                    write_timeout: env.default_socket_write_timeout,
+                    read_timeout: env.default_socket_read_timeout,
                     accept_timeout: None,
                     connect_timeout: None,
                     handler: None,
                 },
                 addr: None,
+                connecting: None,
             }),
         },
         _ => return Ok(Err(Errno::Notsup)),
@@ -0,0 +1,32 @@
+use super::*;
+use crate::syscalls::*;
+
+/// ### `sock_peer_pid()`
+/// Returns the process on the other end of a connection when both ends
+/// are running in the same control plane
+///
+/// Note: This is similar to `SO_PEERCRED` in POSIX
+///
+/// ## Parameters
+///
+/// * `fd` - Socket that is connected to the peer
+#[instrument(level = "debug", skip_all, fields(%sock, pid = field::Empty), ret)]
+pub fn sock_peer_pid<M: MemorySize>(
+    mut ctx: FunctionEnvMut<'_, WasiEnv>,
+    sock: WasiFd,
+    ret_pid: WasmPtr<Pid, M>,
+) -> Errno {
+    let control_plane = ctx.data().control_plane.clone();
+    let pid = wasi_try!(__sock_actor(
+        &mut ctx,
+        sock,
+        Rights::empty(),
+        |socket, _| socket.peer_pid(&control_plane)
+    ));
+    Span::current().record("pid", pid.raw());
+
+    let env = ctx.data();
+    let memory = unsafe { env.memory_view(&ctx) };
+    wasi_try_mem!(ret_pid.write(&memory, pid.raw() as Pid));
+    Errno::Success
+}
@@ -114,6 +114,7 @@ pub(super) fn sock_recv_internal<M: MemorySize>(
     let memory = unsafe { env.memory_view(ctx) };
 
     let peek = (ri_flags & __WASI_SOCK_RECV_INPUT_PEEK) != 0;
+
     let data = wasi_try_ok_ok!(__sock_asyncify(
         env,
         sock,
@@ -142,6 +143,7 @@ pub(super) fn sock_recv_internal<M: MemorySize>(
                 let local_read = match socket
                     .recv(
                         env.tasks().deref(),
+                        &env.process,
                         buf.as_mut_uninit(),
                         Some(timeout),
                         nonblocking,
@@ -81,7 +81,13 @@ pub(super) fn sock_recv_from_internal<M: MemorySize>(
                         .flatten()
                         .unwrap_or(Duration::from_secs(30));
                     socket
-                        .recv_from(env.tasks().deref(), writer, Some(timeout), nonblocking)
+                        .recv_from(
+                            env.tasks().deref(),
+                            &env.process,
+                            writer,
+                            Some(timeout),
+                            nonblocking,
+                        )
                         .await
                 },
             ));
@@ -111,7 +117,13 @@ pub(super) fn sock_recv_from_internal<M: MemorySize>(
                         buf.set_len(max_size);
                     }
                     socket
-                        .recv_from(env.tasks().deref(), &mut buf, Some(timeout), nonblocking)
+                        .recv_from(
+                            env.tasks().deref(),
+                            &env.process,
+                            &mut buf,
+                            Some(timeout),
+                            nonblocking,
+                        )
                         .await
                         .map(|(amt, addr)| {
                             unsafe {
@@ -132,6 +132,7 @@ pub(crate) fn sock_send_file_internal(
                             Kind::Socket { socket, .. } => {
                                 let socket = socket.clone();
                                 let tasks = tasks.clone();
+                                let process = env.process.clone();
                                 drop(guard);
 
                                 let read_timeout = socket
@@ -146,7 +147,13 @@ pub(crate) fn sock_send_file_internal(
                                         buf.set_len(sub_count as usize);
                                     }
                                     socket
-                                        .recv(tasks.deref(), &mut buf, Some(read_timeout), false)
+                                        .recv(
+                                            tasks.deref(),
+                                            &process,
+                                            &mut buf,
+                                            Some(read_timeout),
+                                            false,
+                                        )
                                         .await
                                         .map(|amt| {
                                             unsafe {
@@ -212,6 +219,7 @@ pub(crate) fn sock_send_file_internal(
 
         // Write it down to the socket
         let tasks = ctx.data().tasks().clone();
+        let process = ctx.data().process.clone();
         let bytes_written = wasi_try_ok_ok!(__sock_asyncify_mut(
             ctx,
             sock,
@@ -223,7 +231,7 @@ pub(crate) fn sock_send_file_internal(
                     .flatten()
                     .unwrap_or(Duration::from_secs(30));
                 socket
-                    .send(tasks.deref(), &data, Some(write_timeout), true)
+                    .send(tasks.deref(), &process, &data, Some(write_timeout), true)
                     .await
             },
         ));
@@ -87,6 +87,19 @@ pub(crate) fn sock_send_to_internal<M: MemorySize>(
     let env = ctx.data();
     let memory = unsafe { env.memory_view(&ctx) };
 
+    // Datagrams are never split up so oversized ones are rejected up front
+    let datagram_len = match &si_data {
+        FdWriteSource::Iovs { iovs, iovs_len } => {
+            let iovs_arr = wasi_try_mem_ok_ok!(iovs.slice(&memory, *iovs_len));
+            let iovs_arr = wasi_try_mem_ok_ok!(iovs_arr.access());
+            iovs_arr.iter().map(|iov| iov.buf_len.into()).sum::<u64>()
+        }
+        FdWriteSource::Buffer(data) => data.len() as u64,
+    };
+    if datagram_len > env.max_datagram_size as u64 {
+        return Ok(Err(Errno::Msgsize));
+    }
+
     let bytes_written = {
         wasi_try_ok_ok!(__sock_asyncify(
             env,
@@ -115,6 +128,7 @@ pub(crate) fn sock_send_to_internal<M: MemorySize>(
                             let local_sent = match socket
                                 .send_to::<M>(
                                     env.tasks().deref(),
+                                    &env.process,
                                     buf.as_ref(),
                                     addr,
                                     Some(timeout),
@@ -137,6 +151,7 @@ pub(crate) fn sock_send_to_internal<M: MemorySize>(
                         socket
                             .send_to::<M>(
                                 env.tasks().deref(),
+                                &env.process,
                                 data.as_ref(),
                                 addr,
                                 Some(timeout),
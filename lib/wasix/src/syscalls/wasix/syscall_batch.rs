@@ -0,0 +1,117 @@
+use wasmer_wasix_types::wasi::{BatchFlags, BatchOp, BatchOpcode, BatchResult};
+
+use super::*;
+use crate::syscalls::*;
+
+/// ### `syscall_batch()`
+/// Executes a batch of reads, writes and polls against the file descriptors
+/// in a single call, this saves the overhead of a call per operation
+///
+/// An operation that fails does not stop the batch, its errno is reported in
+/// its result. When the operation is linked (`BatchFlags::LINK`) to the next
+/// one then the next one fails with `Errno::Canceled` without being executed.
+///
+/// Inputs:
+/// - `const BatchOp *ops`
+///     The operations to execute (in order)
+/// - `u32 ops_len`
+///     The number of operations
+/// Output:
+/// - `BatchResult *results`
+///     The result of each of the operations
+#[instrument(level = "trace", skip_all, fields(%ops_len), ret)]
+pub fn syscall_batch<M: MemorySize>(
+    mut ctx: FunctionEnvMut<'_, WasiEnv>,
+    ops: WasmPtr<BatchOp<M>, M>,
+    ops_len: M::Offset,
+    results: WasmPtr<BatchResult<M>, M>,
+) -> Result<Errno, WasiError> {
+    wasi_try_ok!(WasiEnv::process_signals_and_exit(&mut ctx)?);
+
+    let ops = {
+        let env = ctx.data();
+        let memory = unsafe { env.memory_view(&ctx) };
+        let ops = wasi_try_mem_ok!(ops.slice(&memory, ops_len));
+
+        // None of the operations are executed when their results can not be
+        // stored
+        let results = wasi_try_mem_ok!(results.slice(&memory, ops_len));
+        wasi_try_mem_ok!(results.access());
+
+        wasi_try_mem_ok!(ops.read_to_vec())
+    };
+
+    let mut canceled = false;
+    for (index, op) in ops.into_iter().enumerate() {
+        let res = if canceled {
+            Err(Errno::Canceled)
+        } else {
+            syscall_batch_op::<M>(&mut ctx, op)?
+        };
+        canceled = res.is_err() && op.flags.contains(BatchFlags::LINK);
+
+        let (errno, nbytes) = match res {
+            Ok(nbytes) => (Errno::Success, nbytes),
+            Err(err) => (err, 0),
+        };
+        let nbytes: M::Offset = wasi_try_ok!(nbytes.try_into().map_err(|_| Errno::Overflow));
+
+        let env = ctx.data();
+        let memory = unsafe { env.memory_view(&ctx) };
+        let results = wasi_try_mem_ok!(results.slice(&memory, ops_len));
+        wasi_try_mem_ok!(results
+            .index(index as u64)
+            .write(BatchResult { errno, nbytes }));
+    }
+
+    Ok(Errno::Success)
+}
+
+/// Executes one of the operations of a batch and returns the number of
+/// bytes it read, wrote or found to be readable
+fn syscall_batch_op<M: MemorySize>(
+    ctx: &mut FunctionEnvMut<'_, WasiEnv>,
+    op: BatchOp<M>,
+) -> WasiResult<usize> {
+    let opcode = wasi_try_ok_ok!(BatchOpcode::try_from(op.opcode).map_err(|_| Errno::Inval));
+
+    // The reads and writes at the cursor update it, as `fd_read` and
+    // `fd_write` do
+    let (offset, should_update_cursor) = match opcode {
+        BatchOpcode::Read | BatchOpcode::Write => {
+            let fd_entry = wasi_try_ok_ok!(ctx.data().state.fs.get_fd(op.fd));
+            (fd_entry.offset.load(Ordering::Acquire), true)
+        }
+        _ => (op.offset, false),
+    };
+
+    match opcode {
+        BatchOpcode::Read | BatchOpcode::Pread => fd_read_internal::<M>(
+            ctx,
+            op.fd,
+            FdReadDest::Iovs {
+                iovs: WasmPtr::new(op.iovs),
+                iovs_len: op.iovs_len,
+            },
+            offset as usize,
+            should_update_cursor,
+        ),
+        BatchOpcode::Write | BatchOpcode::Pwrite => {
+            let enable_journal = ctx.data().enable_journal;
+            fd_write_internal::<M>(
+                ctx,
+                op.fd,
+                FdWriteSource::Iovs {
+                    iovs: WasmPtr::new(op.iovs),
+                    iovs_len: op.iovs_len,
+                },
+                offset,
+                should_update_cursor,
+                enable_journal,
+            )
+        }
+        BatchOpcode::Poll => {
+            Ok(fd_bytes_available_internal(&ctx.data().state, op.fd).map(|size| size as usize))
+        }
+    }
+}
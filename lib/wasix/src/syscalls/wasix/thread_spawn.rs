@@ -5,7 +5,7 @@ use super::*;
 use crate::journal::JournalEffector;
 use crate::{
     capture_store_snapshot,
-    os::task::thread::WasiMemoryLayout,
+    os::task::{process::CrashedThread, thread::WasiMemoryLayout},
     runtime::{
         task_manager::{TaskWasm, TaskWasmRunProperties},
         TaintReason,
@@ -85,8 +85,7 @@ pub fn thread_spawn_internal_from_wasi<M: MemorySize>(
                 stack_base = layout.stack_lower,
                 "failed to create thread handle",
             );
-            // TODO: evaluate the appropriate error code, document it in the spec.
-            return Err(Errno::Access);
+            return Err(Errno::Again);
         }
     };
     let thread_id: Tid = thread_handle.id().into();
@@ -143,13 +142,28 @@ pub fn thread_spawn_internal_using_layout<M: MemorySize>(
         return Err(Errno::Notcapable);
     }
     let thread_module = unsafe { env.inner() }.module_clone();
+
+    // The new thread draws from the instruction budget of this one
+    #[cfg(feature = "metering")]
+    let instruction_budget = {
+        let (env, mut store) = ctx.data_and_store_mut();
+        env.split_instruction_budget(&mut store)
+    };
+
     let globals = capture_store_snapshot(&mut ctx.as_store_mut());
     let spawn_type =
         crate::runtime::SpawnMemoryType::ShareMemory(thread_memory, ctx.as_store_ref());
 
     // Now spawn a thread
     trace!("threading: spawning background thread");
-    let run = move |props: TaskWasmRunProperties| {
+    let run = move |mut props: TaskWasmRunProperties| {
+        #[cfg(feature = "metering")]
+        if let (Some(points), Some(instance)) = (
+            instruction_budget,
+            props.ctx.data(&props.store).try_clone_instance(),
+        ) {
+            wasmer_middlewares::metering::set_remaining_points(&mut props.store, &instance, points);
+        }
         execute_module(props.ctx, props.store);
     };
     tasks
@@ -192,18 +206,20 @@ fn call_module<M: MemorySize>(
                 .map_err(|_| Errno::Overflow)
                 .unwrap(),
         );
-        let mut ret = Errno::Success;
+        // A trap because the budget is used up is reported as such
+        #[cfg(feature = "metering")]
+        let call_ret = env.map_instruction_limit_trap(store, call_ret);
+        let mut ret: ExitCode = Errno::Success.into();
+        let mut trapped = false;
         if let Err(err) = call_ret {
             match err.downcast::<WasiError>() {
                 Ok(WasiError::Exit(code)) => {
-                    ret = if code.is_success() {
-                        Errno::Success
-                    } else {
+                    if !code.is_success() {
                         env.data(&store)
                             .runtime
                             .on_taint(TaintReason::NonZeroExitCode(code));
-                        Errno::Noexec
-                    };
+                    }
+                    ret = code;
                 }
                 Ok(WasiError::DeepSleep(deep)) => {
                     trace!("entered a deep sleep");
@@ -214,24 +230,40 @@ fn call_module<M: MemorySize>(
                     env.data(&store)
                         .runtime
                         .on_taint(TaintReason::UnknownWasiVersion);
-                    ret = Errno::Noexec;
+                    ret = Errno::Noexec.into();
+                }
+                Ok(WasiError::SyscallDenied(syscall)) => {
+                    debug!("failed as the syscall `{}` is not allowed", syscall);
+                    ret = Errno::Perm.into();
+                }
+                Ok(WasiError::InstructionLimitExceeded) => {
+                    debug!("failed as the instruction limit was exceeded");
+                    ret = Errno::Noexec.into();
                 }
                 Err(err) => {
                     debug!("failed with runtime error: {}", err);
                     env.data(&store)
                         .runtime
                         .on_taint(TaintReason::RuntimeError(err));
-                    ret = Errno::Noexec;
+                    ret = Errno::Noexec.into();
+                    trapped = true;
                 }
             }
         }
         trace!("callback finished (ret={})", ret);
 
-        // Clean up the environment
-        env.on_exit(store, Some(ret.into()));
+        // Anyone that joins the thread sees its exit code
+        env.data(&store).thread.set_status_finished(Ok(ret));
+
+        // Clean up the environment (threads that trapped and are kept for
+        // the host only exit once it is clear that they will not be restarted)
+        let retained = trapped && env.data(&store).process.retains_crashed_threads();
+        if !retained {
+            env.on_exit(store, Some(ret));
+        }
 
         // Return the result
-        Ok(ret as u32)
+        Ok((ret.raw() as u32, retained))
     };
 
     // If we need to rewind then do so
@@ -254,9 +286,59 @@ fn call_module<M: MemorySize>(
 
     // If it went to deep sleep then we need to handle that
     match ret {
-        Ok(ret) => {
+        Ok((ret, retained)) => {
             // Frees the handle so that it closes
             drop(thread_handle);
+
+            // Threads that trapped may be restarted by the host later
+            if retained {
+                let env = ctx.data(&store);
+                let tid = env.tid();
+                let process = env.process.clone();
+                let layout = env.layout.clone();
+
+                // Remember the start arguments so that the restarted thread
+                // only uses them if the guest did not release them since
+                let start = {
+                    let memory = unsafe { env.memory_view(&store) };
+                    let mut start = vec![0u8; std::mem::size_of::<ThreadStart<M>>()];
+                    memory
+                        .read(start_ptr_offset.into(), &mut start)
+                        .map(|_| start)
+                };
+
+                let mut trapped = TrappedThread {
+                    ctx,
+                    store: Some(store),
+                };
+                if let Ok(start) = start {
+                    let respawn = move |thread_handle: Arc<WasiThreadHandle>| {
+                        let mut store = trapped.store.take().ok_or(Errno::Srch)?;
+                        let ret = respawn_trapped_thread::<M>(
+                            &trapped.ctx,
+                            &mut store,
+                            thread_handle,
+                            start_ptr_offset,
+                            &start,
+                        );
+                        if ret.is_err() {
+                            trapped.store = Some(store);
+                        }
+                        ret
+                    };
+                    process.add_crashed_thread(
+                        tid,
+                        CrashedThread {
+                            layout,
+                            start: ThreadStartType::ThreadSpawn {
+                                start_ptr: start_ptr_offset.into(),
+                            },
+                            respawn: Mutex::new(Box::new(respawn)),
+                        },
+                    );
+                }
+            }
+
             Ok(ret as Pid)
         }
         Err(deep) => {
@@ -284,3 +366,44 @@ fn call_module<M: MemorySize>(
         }
     }
 }
+
+/// A thread that trapped, it exits when it is dropped without
+/// having been restarted
+struct TrappedThread {
+    ctx: WasiFunctionEnv,
+    store: Option<Store>,
+}
+
+impl Drop for TrappedThread {
+    fn drop(&mut self) {
+        if let Some(mut store) = self.store.take() {
+            self.ctx.on_exit(&mut store, Some(Errno::Noexec.into()));
+        }
+    }
+}
+
+/// Spawns a thread that trapped again from its entry point
+fn respawn_trapped_thread<M: MemorySize>(
+    ctx: &WasiFunctionEnv,
+    store: &mut Store,
+    thread_handle: Arc<WasiThreadHandle>,
+    start_ptr_offset: M::Offset,
+    start: &[u8],
+) -> Result<(), Errno> {
+    let mut ctx = ctx.env.clone().into_mut(store);
+
+    // The guest may have freed the start arguments (and with them the
+    // stack of the thread) after the thread trapped
+    let memory = unsafe { ctx.data().memory_view(&ctx) };
+    let mut current = vec![0u8; start.len()];
+    memory
+        .read(start_ptr_offset.into(), &mut current)
+        .map_err(mem_error_to_wasi)?;
+    if current != start {
+        warn!("the start arguments of the thread changed since it trapped");
+        return Err(Errno::Inval);
+    }
+
+    let layout = ctx.data().layout.clone();
+    thread_spawn_internal_using_layout::<M>(&mut ctx, thread_handle, layout, start_ptr_offset, None)
+}
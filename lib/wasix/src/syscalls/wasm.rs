@@ -26,6 +26,11 @@ pub fn platform_clock_time_get(
     clock_id: Snapshot0Clockid,
     precision: Timestamp,
 ) -> Result<i64, Errno> {
+    // All the known clocks are served by the local time, unknown ones
+    // must not fall back to it
+    if clock_id == Snapshot0Clockid::Unknown {
+        return Err(Errno::Inval);
+    }
     Local::now()
         .timestamp_nanos_opt()
         .map(|ts| ts as i64)
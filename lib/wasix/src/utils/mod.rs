@@ -45,6 +45,14 @@ pub fn is_wasix_module(module: &Module) -> bool {
 }
 
 pub fn map_io_err(err: std::io::Error) -> Errno {
+    // Errors that carry a file system error are mapped the same way as the
+    // file system error itself
+    if let Some(err) = err
+        .get_ref()
+        .and_then(|err| err.downcast_ref::<virtual_fs::FsError>())
+    {
+        return crate::fs::fs_error_into_wasi_err(*err);
+    }
     From::<std::io::Error>::from(err)
 }
 
@@ -0,0 +1,92 @@
+use wasmer::{Module, Store};
+use wasmer_wasix::WasiEnv;
+
+mod sys {
+    #[tokio::test]
+    async fn test_access_and_perm() {
+        super::test_access_and_perm().await;
+    }
+}
+
+async fn test_access_and_perm() {
+    let mut store = Store::default();
+    let module = Module::new(
+        &store,
+        br#"
+    (module
+        (import "wasi_snapshot_preview1" "path_open" (func $path_open (param i32 i32 i32 i32 i32 i64 i64 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+        (import "wasix_32v1" "proc_setsid" (func $proc_setsid (param i32) (result i32)))
+        (import "wasix_32v1" "proc_exit" (func $proc_exit (param i32)))
+
+        (memory 1)
+        (export "memory" (memory 0))
+
+        ;; iovec pointing at the contents
+        (data (i32.const 8) "\40\00\00\00\02\00\00\00")
+        (data (i32.const 32) "proc/self/cmdline")
+        (data (i32.const 64) "hi")
+
+        (func $open (param $oflags i32) (param $rights i64) (result i32)
+            (call $path_open
+                (i32.const 4)   ;; dirfd
+                (i32.const 0)   ;; dirflags
+                (i32.const 32)  ;; path
+                (i32.const 17)  ;; path_len
+                (local.get $oflags)
+                (local.get $rights)
+                (i64.const 0)   ;; rights_inheriting
+                (i32.const 0)   ;; fdflags
+                (i32.const 0)   ;; fd_out
+            )
+        )
+
+        (func $main (export "_start")
+            (local $errno i32)
+            (local $write i32)
+            (local $truncate i32)
+
+            ;; Writing to a descriptor that was opened with FD_READ only
+            (local.set $errno (call $open (i32.const 0) (i64.const 2)))
+            (if (local.get $errno)
+                (then (call $proc_exit (i32.add (i32.const 100) (local.get $errno))))
+            )
+            (local.set $write
+                (call $fd_write (i32.load (i32.const 0)) (i32.const 8) (i32.const 1) (i32.const 16))
+            )
+
+            ;; Truncating a file that is read-only
+            (local.set $truncate (call $open (i32.const 8) (i64.const 64)))
+
+            ;; The process already leads its process group
+            (call $proc_exit
+                (i32.or
+                    (i32.or
+                        (i32.shl (local.get $write) (i32.const 16))
+                        (i32.shl (local.get $truncate) (i32.const 8))
+                    )
+                    (call $proc_setsid (i32.const 24))
+                )
+            )
+        )
+    )
+    "#,
+    )
+    .unwrap();
+
+    let builder = WasiEnv::builder("command-name")
+        .proc_fs(true)
+        .preopen_dir("/")
+        .unwrap();
+
+    #[cfg(feature = "js")]
+    let result = builder.run_with_store(module, &mut store);
+    #[cfg(not(feature = "js"))]
+    let result = std::thread::spawn(move || builder.run_with_store(module, &mut store))
+        .join()
+        .unwrap();
+
+    // Errno::Access for the file operations and Errno::Perm for proc_setsid
+    let exit_code = result.unwrap_err().as_exit_code().unwrap();
+    assert_eq!(exit_code.raw(), (2 << 16) | (2 << 8) | 63);
+}
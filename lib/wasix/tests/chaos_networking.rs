@@ -0,0 +1,131 @@
+#![cfg(all(feature = "host-vnet", feature = "sys-thread"))]
+
+use std::{
+    net::UdpSocket,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use wasmer::{Module, Store};
+use wasmer_wasix::{
+    net::chaos::ChaosNetworking, runtime::task_manager::tokio::TokioTaskManager,
+    virtual_net::host::LocalNetworking, PluggableRuntime, WasiEnv,
+};
+
+mod sys {
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_chaos_networking_delays_sock_recv() {
+        super::test_chaos_networking_delays_sock_recv().await;
+    }
+}
+
+const LATENCY: Duration = Duration::from_millis(300);
+
+async fn test_chaos_networking_delays_sock_recv() {
+    let peer = UdpSocket::bind("127.0.0.1:0").unwrap();
+    peer.set_read_timeout(Some(Duration::from_secs(10)))
+        .unwrap();
+    let port = peer.local_addr().unwrap().port();
+
+    let mut store = Store::default();
+    let module = Module::new(
+        &store,
+        format!(
+            r#"
+    (module
+        (import "wasix_32v1" "sock_open" (func $sock_open (param i32 i32 i32 i32) (result i32)))
+        (import "wasix_32v1" "sock_bind" (func $sock_bind (param i32 i32) (result i32)))
+        (import "wasix_32v1" "sock_connect" (func $sock_connect (param i32 i32) (result i32)))
+        (import "wasix_32v1" "sock_send" (func $sock_send (param i32 i32 i32 i32 i32) (result i32)))
+        (import "wasix_32v1" "sock_recv" (func $sock_recv (param i32 i32 i32 i32 i32 i32) (result i32)))
+        (import "wasix_32v1" "proc_exit" (func $proc_exit (param i32)))
+
+        (memory 1)
+        (export "memory" (memory 0))
+
+        (data (i32.const 64) "hi")
+
+        (func $main (export "_start")
+            (local $fd i32)
+            (call $sock_open
+                (i32.const 1)  ;; af (INET4)
+                (i32.const 2)  ;; ty (DGRAM)
+                (i32.const 17) ;; pt (UDP)
+                (i32.const 16) ;; ro_sock
+            )
+            drop
+            (local.set $fd (i32.load (i32.const 16)))
+
+            ;; Bind to 127.0.0.1 on any port
+            (i32.store8 (i32.const 256) (i32.const 1))
+            (i32.store (i32.const 260) (i32.const 16777343))
+            (call $sock_bind (local.get $fd) (i32.const 256))
+            drop
+
+            ;; Connect to the peer
+            (i32.store8 (i32.const 288) (i32.const 1))
+            (i32.store16 (i32.const 290) (i32.const {port}))
+            (i32.store (i32.const 292) (i32.const 16777343))
+            (call $sock_connect (local.get $fd) (i32.const 288))
+            drop
+
+            ;; Let the peer know our address
+            (i32.store (i32.const 0) (i32.const 64))
+            (i32.store (i32.const 4) (i32.const 2))
+            (call $sock_send (local.get $fd) (i32.const 0) (i32.const 1) (i32.const 0) (i32.const 20))
+            drop
+
+            ;; Wait for the reply of the peer
+            (i32.store (i32.const 8) (i32.const 128))
+            (i32.store (i32.const 12) (i32.const 16))
+            (call $sock_recv
+                (local.get $fd)
+                (i32.const 8)   ;; ri_data
+                (i32.const 1)   ;; ri_data_len
+                (i32.const 0)   ;; ri_flags
+                (i32.const 20)  ;; ro_data_len
+                (i32.const 24)  ;; ro_flags
+            )
+            drop
+
+            ;; Report the length and first byte of the datagram as the exit code
+            (call $proc_exit
+                (i32.or
+                    (i32.shl (i32.load (i32.const 20)) (i32.const 8))
+                    (i32.load8_u (i32.const 128))
+                )
+            )
+        )
+    )
+    "#
+        ),
+    )
+    .unwrap();
+
+    let tasks = Arc::new(TokioTaskManager::default());
+    let mut runtime = PluggableRuntime::new(tasks.clone());
+    runtime.set_networking_implementation(
+        ChaosNetworking::new(LocalNetworking::new(), tasks).with_latency(LATENCY),
+    );
+    let builder = WasiEnv::builder("command-name").runtime(Arc::new(runtime));
+
+    let guest = tokio::task::spawn_blocking(move || builder.run_with_store(module, &mut store));
+
+    // The reply is sent as soon as the (delayed) datagram of the guest arrives
+    let sent_at = tokio::task::spawn_blocking(move || {
+        let mut buf = [0u8; 16];
+        let (_, guest_addr) = peer.recv_from(&mut buf).unwrap();
+        let sent_at = Instant::now();
+        peer.send_to(b"good", guest_addr).unwrap();
+        sent_at
+    })
+    .await
+    .unwrap();
+
+    let result = guest.await.unwrap();
+    assert!(sent_at.elapsed() >= LATENCY);
+
+    // Four bytes starting with 'g'
+    let exit_code = result.unwrap_err().as_exit_code().unwrap();
+    assert_eq!(exit_code.raw(), (4 << 8) | b'g' as i32);
+}
@@ -0,0 +1,320 @@
+use std::{
+    sync::Arc,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use wasmer::{Instance, Module, Store};
+use wasmer_wasix::{
+    wasmer_wasix_types::wasix::ThreadStartType, ScriptedClock, WasiEnv, WasiFunctionEnv,
+};
+
+mod sys {
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_threads_see_their_own_clock() {
+        super::test_threads_see_their_own_clock().await;
+    }
+    #[tokio::test]
+    async fn test_loose_precision_returns_plausible_time() {
+        super::test_loose_precision_returns_plausible_time().await;
+    }
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_monotonic_never_decreases_across_threads() {
+        super::test_monotonic_never_decreases_across_threads().await;
+    }
+    #[tokio::test]
+    async fn test_unknown_clock_id_is_inval() {
+        super::test_unknown_clock_id_is_inval().await;
+    }
+    #[tokio::test]
+    async fn test_unknown_clock_id_is_inval_with_thread_clock() {
+        super::test_unknown_clock_id_is_inval_with_thread_clock().await;
+    }
+}
+
+/// Module that reads the realtime clock and returns its value
+const MODULE: &[u8] = br#"
+    (module
+        (import "wasi_snapshot_preview1" "clock_time_get" (func $clock_time_get (param i32 i64 i32) (result i32)))
+
+        (memory 1)
+        (export "memory" (memory 0))
+
+        (func $now (export "now") (result i64)
+            (call $clock_time_get (i32.const 0) (i64.const 1) (i32.const 0))
+            drop
+            (i64.load (i32.const 0))
+        )
+    )
+"#;
+
+async fn test_threads_see_their_own_clock() {
+    let mut store = Store::default();
+    let engine = store.engine().clone();
+    let func_env = WasiEnv::builder("command-name")
+        .finalize(&mut store)
+        .unwrap();
+    let main_env = func_env.data(&store).clone();
+
+    // Spawn a second thread within the same process
+    let thread_handle = main_env
+        .process
+        .new_thread(
+            main_env.layout.clone(),
+            ThreadStartType::ThreadSpawn { start_ptr: 0 },
+        )
+        .unwrap();
+    let mut other_env = main_env.clone();
+    other_env.thread = thread_handle.as_thread();
+
+    main_env
+        .thread
+        .set_clock(Some(Arc::new(ScriptedClock::new([100, 200]))));
+    other_env
+        .thread
+        .set_clock(Some(Arc::new(ScriptedClock::new([5_000]))));
+
+    let threads = [main_env, other_env]
+        .into_iter()
+        .map(|env| {
+            let engine = engine.clone();
+            let handle = tokio::runtime::Handle::current();
+            std::thread::spawn(move || {
+                let _guard = handle.enter();
+                let mut store = Store::new(engine);
+                let module = Module::new(&store, MODULE).unwrap();
+
+                let mut func_env = WasiFunctionEnv::new(&mut store, env);
+                let imports = func_env.import_object(&mut store, &module).unwrap();
+                let instance = Instance::new(&mut store, &module, &imports).unwrap();
+                func_env.initialize(&mut store, instance.clone()).unwrap();
+
+                let now = instance.exports.get_function("now").unwrap();
+                (0..3)
+                    .map(|_| now.call(&mut store, &[]).unwrap()[0].unwrap_i64())
+                    .collect::<Vec<_>>()
+            })
+        })
+        .collect::<Vec<_>>();
+    let times = threads
+        .into_iter()
+        .map(|thread| thread.join().unwrap())
+        .collect::<Vec<_>>();
+
+    // The script is replayed and its last time repeats once it runs out
+    assert_eq!(times[0], vec![100, 200, 200]);
+    assert_eq!(times[1], vec![5_000, 5_000, 5_000]);
+    drop(thread_handle);
+}
+
+async fn test_loose_precision_returns_plausible_time() {
+    let mut store = Store::default();
+    let module = Module::new(
+        &store,
+        br#"
+    (module
+        (import "wasi_snapshot_preview1" "clock_time_get" (func $clock_time_get (param i32 i64 i32) (result i32)))
+
+        (memory 1)
+        (export "memory" (memory 0))
+
+        ;; Reads the realtime clock many times with a precision of a second
+        ;; and returns the last reading
+        (func $now (export "now") (result i64)
+            (local $n i32)
+            (loop $again
+                (call $clock_time_get (i32.const 0) (i64.const 1000000000) (i32.const 0))
+                drop
+                (local.set $n (i32.add (local.get $n) (i32.const 1)))
+                (br_if $again (i32.lt_u (local.get $n) (i32.const 100000)))
+            )
+            (i64.load (i32.const 0))
+        )
+    )
+    "#,
+    )
+    .unwrap();
+
+    let handle = tokio::runtime::Handle::current();
+    let (before, time, elapsed, after) = std::thread::spawn(move || {
+        let _guard = handle.enter();
+        let mut func_env = WasiEnv::builder("command-name")
+            .finalize(&mut store)
+            .unwrap();
+        let imports = func_env.import_object(&mut store, &module).unwrap();
+        let instance = Instance::new(&mut store, &module, &imports).unwrap();
+        func_env.initialize(&mut store, instance.clone()).unwrap();
+        let now = instance.exports.get_function("now").unwrap();
+
+        let before = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+        let started = Instant::now();
+        let time = now.call(&mut store, &[]).unwrap()[0].unwrap_i64() as u128;
+        let elapsed = started.elapsed();
+        let after = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+        (before, time, elapsed, after)
+    })
+    .join()
+    .unwrap();
+
+    // The reading may be off by up to the requested precision
+    let precision = Duration::from_secs(1).as_nanos();
+    assert!(time + precision >= before.as_nanos());
+    assert!(time <= after.as_nanos() + precision);
+    assert!(elapsed < Duration::from_secs(10));
+}
+
+async fn test_monotonic_never_decreases_across_threads() {
+    const MONOTONIC_MODULE: &[u8] = br#"
+    (module
+        (import "wasi_snapshot_preview1" "clock_time_get" (func $clock_time_get (param i32 i64 i32) (result i32)))
+
+        (memory 1)
+        (export "memory" (memory 0))
+
+        ;; Reads the monotonic clock many times, returns the number of times
+        ;; a reading was lower than the one before it
+        (func $decreases (export "decreases") (result i32)
+            (local $n i32)
+            (local $prev i64)
+            (local $now i64)
+            (local $decreases i32)
+            (loop $again
+                (call $clock_time_get (i32.const 1) (i64.const 1) (i32.const 0))
+                drop
+                (local.set $now (i64.load (i32.const 0)))
+                (if (i64.lt_u (local.get $now) (local.get $prev))
+                    (then
+                        (local.set $decreases (i32.add (local.get $decreases) (i32.const 1)))
+                    )
+                )
+                (local.set $prev (local.get $now))
+                (local.set $n (i32.add (local.get $n) (i32.const 1)))
+                (br_if $again (i32.lt_u (local.get $n) (i32.const 10000)))
+            )
+            (local.get $decreases)
+        )
+    )
+    "#;
+
+    let mut store = Store::default();
+    let engine = store.engine().clone();
+    let func_env = WasiEnv::builder("command-name")
+        .finalize(&mut store)
+        .unwrap();
+    let main_env = func_env.data(&store).clone();
+
+    // Every thread of the process reads the clock at the same time
+    let mut thread_handles = Vec::new();
+    let mut envs = vec![main_env.clone()];
+    for _ in 0..3 {
+        let thread_handle = main_env
+            .process
+            .new_thread(
+                main_env.layout.clone(),
+                ThreadStartType::ThreadSpawn { start_ptr: 0 },
+            )
+            .unwrap();
+        let mut env = main_env.clone();
+        env.thread = thread_handle.as_thread();
+        envs.push(env);
+        thread_handles.push(thread_handle);
+    }
+
+    let threads = envs
+        .into_iter()
+        .map(|env| {
+            let engine = engine.clone();
+            let handle = tokio::runtime::Handle::current();
+            std::thread::spawn(move || {
+                let _guard = handle.enter();
+                let mut store = Store::new(engine);
+                let module = Module::new(&store, MONOTONIC_MODULE).unwrap();
+
+                let mut func_env = WasiFunctionEnv::new(&mut store, env);
+                let imports = func_env.import_object(&mut store, &module).unwrap();
+                let instance = Instance::new(&mut store, &module, &imports).unwrap();
+                func_env.initialize(&mut store, instance.clone()).unwrap();
+
+                let decreases = instance.exports.get_function("decreases").unwrap();
+                decreases.call(&mut store, &[]).unwrap()[0].unwrap_i32()
+            })
+        })
+        .collect::<Vec<_>>();
+
+    for thread in threads {
+        assert_eq!(thread.join().unwrap(), 0);
+    }
+    drop(thread_handles);
+}
+
+async fn test_unknown_clock_id_is_inval() {
+    let mut store = Store::default();
+    let module = Module::new(
+        &store,
+        br#"
+    (module
+        (import "wasi_snapshot_preview1" "clock_time_get" (func $clock_time_get (param i32 i64 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "proc_exit" (func $proc_exit (param i32)))
+
+        (memory 1)
+        (export "memory" (memory 0))
+
+        (func $main (export "_start")
+            (call $proc_exit
+                (call $clock_time_get (i32.const 42) (i64.const 1) (i32.const 0))
+            )
+        )
+    )
+    "#,
+    )
+    .unwrap();
+
+    let builder = WasiEnv::builder("command-name");
+    let result = std::thread::spawn(move || builder.run_with_store(module, &mut store))
+        .join()
+        .unwrap();
+
+    // Errno::Inval
+    let exit_code = result.unwrap_err().as_exit_code().unwrap();
+    assert_eq!(exit_code.raw(), 28);
+}
+
+async fn test_unknown_clock_id_is_inval_with_thread_clock() {
+    let mut store = Store::default();
+    let module = Module::new(
+        &store,
+        br#"
+    (module
+        (import "wasi_snapshot_preview1" "clock_time_get" (func $clock_time_get (param i32 i64 i32) (result i32)))
+
+        (memory 1)
+        (export "memory" (memory 0))
+
+        (func $get (export "get") (result i32)
+            (call $clock_time_get (i32.const 42) (i64.const 1) (i32.const 0))
+        )
+    )
+    "#,
+    )
+    .unwrap();
+
+    let mut func_env = WasiEnv::builder("command-name")
+        .finalize(&mut store)
+        .unwrap();
+
+    // The clock of the thread answers for every clock id, unknown ones
+    // must still be rejected
+    func_env
+        .data(&store)
+        .thread
+        .set_clock(Some(Arc::new(ScriptedClock::new([100]))));
+
+    let imports = func_env.import_object(&mut store, &module).unwrap();
+    let instance = Instance::new(&mut store, &module, &imports).unwrap();
+    func_env.initialize(&mut store, instance.clone()).unwrap();
+
+    let get = instance.exports.get_function("get").unwrap();
+    let errno = get.call(&mut store, &[]).unwrap()[0].unwrap_i32();
+
+    // Errno::Inval
+    assert_eq!(errno, 28);
+}
@@ -0,0 +1,82 @@
+use wasmer::{Instance, Module, Store};
+use wasmer_wasix::WasiEnv;
+
+mod sys {
+    #[tokio::test]
+    async fn test_env_var_update() {
+        super::test_env_var_update().await;
+    }
+}
+
+/// Has the guest read its environment again and returns it
+fn read_environ(instance: &Instance, store: &mut Store) -> Vec<String> {
+    let environ = instance.exports.get_function("environ").unwrap();
+    let len = environ.call(store, &[]).unwrap()[0].unwrap_i32() as usize;
+
+    let memory = instance.exports.get_memory("memory").unwrap();
+    let mut buf = vec![0u8; len];
+    memory.view(store).read(1024, &mut buf).unwrap();
+    buf.split(|&ch| ch == 0)
+        .filter(|var| !var.is_empty())
+        .map(|var| String::from_utf8(var.to_vec()).unwrap())
+        .collect()
+}
+
+async fn test_env_var_update() {
+    let mut store = Store::default();
+    let module = Module::new(
+        &store,
+        br#"
+    (module
+        (import "wasi_snapshot_preview1" "environ_sizes_get" (func $environ_sizes_get (param i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "environ_get" (func $environ_get (param i32 i32) (result i32)))
+
+        (memory 1)
+        (export "memory" (memory 0))
+
+        (func (export "_start"))
+
+        ;; Reads the environment into 1024 and returns the size of it
+        (func (export "environ") (result i32)
+            (if (call $environ_sizes_get (i32.const 0) (i32.const 4))
+                (then unreachable)
+            )
+            (if (call $environ_get (i32.const 16) (i32.const 1024))
+                (then unreachable)
+            )
+            (i32.load (i32.const 4))
+        )
+    )
+    "#,
+    )
+    .unwrap();
+
+    let builder = WasiEnv::builder("command-name")
+        .env("MODE", "slow")
+        .env("COLOR", "red");
+
+    let handle = tokio::runtime::Handle::current();
+    std::thread::spawn(move || {
+        let _guard = handle.enter();
+        let (instance, env) = builder.instantiate(module, &mut store).unwrap();
+        assert_eq!(
+            read_environ(&instance, &mut store),
+            ["MODE=slow", "COLOR=red"]
+        );
+
+        // Replace a variable, add another one and remove one
+        let wasi_env = env.data(&store);
+        wasi_env.set_env_var("MODE", "fast").unwrap();
+        wasi_env.set_env_var("LEVEL", "3").unwrap();
+        assert!(wasi_env.unset_env_var("COLOR"));
+        assert!(!wasi_env.unset_env_var("COLOR"));
+        assert!(wasi_env.set_env_var("A=B", "1").is_err());
+
+        assert_eq!(
+            read_environ(&instance, &mut store),
+            ["MODE=fast", "LEVEL=3"]
+        );
+    })
+    .join()
+    .unwrap();
+}
@@ -0,0 +1,135 @@
+use wasmer::{Module, Store};
+use wasmer_wasix::{types::wasi::Errno, WasiEnv};
+
+mod sys {
+    #[tokio::test]
+    async fn test_errno_remap() {
+        super::test_errno_remap().await;
+    }
+
+    #[cfg(not(feature = "js"))]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_errno_remap_skips_thread_spawn() {
+        super::test_errno_remap_skips_thread_spawn().await;
+    }
+}
+
+async fn test_errno_remap() {
+    let mut store = Store::default();
+    let module = Module::new(
+        &store,
+        br#"
+    (module
+        (import "wasix_32v1" "sock_open" (func $sock_open (param i32 i32 i32 i32) (result i32)))
+        (import "wasix_32v1" "fd_fdstat_get" (func $fd_fdstat_get (param i32 i32) (result i32)))
+        (import "wasix_32v1" "proc_exit" (func $proc_exit (param i32)))
+
+        (memory 1)
+        (export "memory" (memory 0))
+
+        (func $main (export "_start")
+            ;; A TCP datagram socket is not supported (Errno::Notsup) which
+            ;; is remapped, while using a bad file descriptor is not
+            (call $proc_exit
+                (i32.or
+                    (i32.shl
+                        (call $sock_open
+                            (i32.const 1)  ;; af (INET4)
+                            (i32.const 2)  ;; ty (DGRAM)
+                            (i32.const 6)  ;; pt (TCP)
+                            (i32.const 16) ;; ro_sock
+                        )
+                        (i32.const 8)
+                    )
+                    (call $fd_fdstat_get (i32.const 99) (i32.const 64))
+                )
+            )
+        )
+    )
+    "#,
+    )
+    .unwrap();
+
+    let builder = WasiEnv::builder("command-name").errno_remap(Errno::Notsup, Errno::Nosys);
+
+    #[cfg(feature = "js")]
+    let result = builder.run_with_store(module, &mut store);
+    #[cfg(not(feature = "js"))]
+    let result = std::thread::spawn(move || builder.run_with_store(module, &mut store))
+        .join()
+        .unwrap();
+
+    let exit_code = result.unwrap_err().as_exit_code().unwrap();
+    assert_eq!(
+        exit_code.raw(),
+        ((Errno::Nosys as i32) << 8) | Errno::Badf as i32
+    );
+}
+
+#[cfg(not(feature = "js"))]
+async fn test_errno_remap_skips_thread_spawn() {
+    use std::time::{Duration, Instant};
+
+    let mut store = Store::default();
+    let module = Module::new(
+        &store,
+        br#"
+    (module
+        (import "env" "memory" (memory 2 2 shared))
+        (import "wasi" "thread-spawn" (func $thread_spawn (param i32) (result i32)))
+
+        ;; The thread stores the id it was started with at 128
+        (func (export "wasi_thread_start") (param $tid i32) (param $start i32)
+            (i32.atomic.store (i32.const 128) (local.get $tid))
+        )
+
+        ;; Spawns a thread with a 4KiB stack right below 64KiB and returns
+        ;; what thread-spawn returned
+        (func (export "spawn") (result i32)
+            (i32.store (i32.const 1024) (i32.const 65536))           ;; stack_upper
+            (i32.store (i32.add (i32.const 1024) (i32.const 56)) (i32.const 4096)) ;; stack_size
+            (call $thread_spawn (i32.const 1024))
+        )
+        (func (export "started") (result i32)
+            (i32.atomic.load (i32.const 128))
+        )
+    )
+    "#,
+    )
+    .unwrap();
+
+    // Every value a thread id could have is also a remapped errno
+    let mut builder = WasiEnv::builder("command-name");
+    for errno in 1..=64u16 {
+        let errno = Errno::try_from(errno).unwrap();
+        if errno != Errno::Nosys {
+            builder = builder.errno_remap(errno, Errno::Nosys);
+        }
+    }
+
+    let handle = tokio::runtime::Handle::current();
+    std::thread::spawn(move || {
+        let _guard = handle.enter();
+        let (instance, _env) = builder.instantiate(module, &mut store).unwrap();
+        let spawn = instance.exports.get_function("spawn").unwrap();
+        let tid = spawn.call(&mut store, &[]).unwrap()[0].unwrap_i32();
+
+        let started = instance.exports.get_function("started").unwrap();
+        let deadline = Instant::now() + Duration::from_secs(10);
+        let started_tid = loop {
+            match started.call(&mut store, &[]).unwrap()[0].unwrap_i32() {
+                0 => {
+                    assert!(Instant::now() < deadline, "the thread did not start");
+                    std::thread::sleep(Duration::from_millis(10));
+                }
+                tid => break tid,
+            }
+        };
+
+        // The id of the thread is returned as is
+        assert!(tid > 0);
+        assert_eq!(tid, started_tid);
+    })
+    .join()
+    .unwrap();
+}
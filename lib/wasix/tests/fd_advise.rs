@@ -0,0 +1,262 @@
+use virtual_fs::mem_fs;
+#[cfg(not(feature = "js"))]
+use virtual_fs::{AsyncWriteExt, FileSystem};
+use wasmer::{Module, Store};
+use wasmer_wasix::WasiEnv;
+
+mod sys {
+    #[tokio::test]
+    async fn test_fd_advise_beyond_eof() {
+        super::test_fd_advise_beyond_eof().await;
+    }
+
+    #[tokio::test]
+    async fn test_fd_advise_willneed() {
+        super::test_fd_advise_willneed().await;
+    }
+
+    #[cfg(not(feature = "js"))]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_fd_advise_changed_behind_the_fd() {
+        super::test_fd_advise_changed_behind_the_fd().await;
+    }
+}
+
+async fn test_fd_advise_beyond_eof() {
+    let mut store = Store::default();
+    let module = Module::new(
+        &store,
+        br#"
+    (module
+        (import "wasi_snapshot_preview1" "path_open" (func $path_open (param i32 i32 i32 i32 i32 i64 i64 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "fd_advise" (func $fd_advise (param i32 i64 i64 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "proc_exit" (func $proc_exit (param i32)))
+
+        (memory 1)
+        (export "memory" (memory 0))
+
+        (data (i32.const 32) "f")
+
+        (func $main (export "_start")
+            (local $errno i32)
+            (local $fd i32)
+
+            ;; Create the empty file 'f'
+            (local.set $errno
+                (call $path_open
+                    (i32.const 4)   ;; dirfd
+                    (i32.const 0)   ;; dirflags
+                    (i32.const 32)  ;; path
+                    (i32.const 1)   ;; path_len
+                    (i32.const 1)   ;; oflags (CREATE)
+                    (i64.const 194) ;; rights_base (FD_READ | FD_WRITE | FD_ADVISE)
+                    (i64.const 0)   ;; rights_inheriting
+                    (i32.const 0)   ;; fdflags
+                    (i32.const 0)   ;; fd_out
+                )
+            )
+            (if (local.get $errno)
+                (then (call $proc_exit (i32.add (i32.const 100) (local.get $errno))))
+            )
+            (local.set $fd (i32.load (i32.const 0)))
+
+            ;; Advise a range that starts past EOF and one whose end overflows
+            (call $proc_exit
+                (i32.or
+                    (i32.shl
+                        (call $fd_advise (local.get $fd) (i64.const 4096) (i64.const 4096) (i32.const 3))
+                        (i32.const 8)
+                    )
+                    (call $fd_advise (local.get $fd) (i64.const 1) (i64.const -1) (i32.const 3))
+                )
+            )
+        )
+    )
+    "#,
+    )
+    .unwrap();
+
+    let builder = WasiEnv::builder("command-name")
+        .fs(Box::<mem_fs::FileSystem>::default())
+        .preopen_dir("/")
+        .unwrap();
+
+    #[cfg(feature = "js")]
+    let result = builder.run_with_store(module, &mut store);
+    #[cfg(not(feature = "js"))]
+    let result = std::thread::spawn(move || builder.run_with_store(module, &mut store))
+        .join()
+        .unwrap();
+
+    // Both calls returned Errno::Success
+    result.unwrap();
+}
+
+async fn test_fd_advise_willneed() {
+    let mut store = Store::default();
+    let module = Module::new(
+        &store,
+        br#"
+    (module
+        (import "wasi_snapshot_preview1" "path_open" (func $path_open (param i32 i32 i32 i32 i32 i64 i64 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "fd_advise" (func $fd_advise (param i32 i64 i64 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "fd_read" (func $fd_read (param i32 i32 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "fd_seek" (func $fd_seek (param i32 i64 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "proc_exit" (func $proc_exit (param i32)))
+
+        (memory 1)
+        (export "memory" (memory 0))
+
+        ;; iovecs for `abcdefgh`, `XY` and a 4 byte buffer
+        (data (i32.const 8) "\40\00\00\00\08\00\00\00")
+        (data (i32.const 16) "\48\00\00\00\02\00\00\00")
+        (data (i32.const 24) "\00\01\00\00\04\00\00\00")
+        (data (i32.const 32) "f")
+        (data (i32.const 64) "abcdefgh")
+        (data (i32.const 72) "XY")
+        (data (i32.const 80) "XYcd")
+
+        (func $check (param $errno i32)
+            (if (local.get $errno)
+                (then (call $proc_exit (i32.add (i32.const 100) (local.get $errno))))
+            )
+        )
+
+        (func $main (export "_start")
+            (local $fd i32)
+
+            (call $check
+                (call $path_open
+                    (i32.const 4)   ;; dirfd
+                    (i32.const 0)   ;; dirflags
+                    (i32.const 32)  ;; path
+                    (i32.const 1)   ;; path_len
+                    (i32.const 1)   ;; oflags (CREATE)
+                    (i64.const 198) ;; rights_base (FD_READ | FD_SEEK | FD_WRITE | FD_ADVISE)
+                    (i64.const 0)   ;; rights_inheriting
+                    (i32.const 0)   ;; fdflags
+                    (i32.const 0)   ;; fd_out
+                )
+            )
+            (local.set $fd (i32.load (i32.const 0)))
+            (call $check (call $fd_write (local.get $fd) (i32.const 8) (i32.const 1) (i32.const 40)))
+
+            ;; The first bytes are read ahead and then read from memory
+            (call $check (call $fd_advise (local.get $fd) (i64.const 0) (i64.const 0) (i32.const 3)))
+            (call $check (call $fd_seek (local.get $fd) (i64.const 0) (i32.const 0) (i32.const 40)))
+            (call $check (call $fd_read (local.get $fd) (i32.const 24) (i32.const 1) (i32.const 40)))
+            (if (i32.ne (i32.load (i32.const 256)) (i32.load (i32.const 64)))
+                (then (call $proc_exit (i32.const 1)))
+            )
+
+            ;; Writing drops what was read ahead
+            (call $check (call $fd_seek (local.get $fd) (i64.const 0) (i32.const 0) (i32.const 40)))
+            (call $check (call $fd_write (local.get $fd) (i32.const 16) (i32.const 1) (i32.const 40)))
+            (call $check (call $fd_seek (local.get $fd) (i64.const 0) (i32.const 0) (i32.const 40)))
+            (call $check (call $fd_read (local.get $fd) (i32.const 24) (i32.const 1) (i32.const 40)))
+            (if (i32.ne (i32.load (i32.const 256)) (i32.load (i32.const 80)))
+                (then (call $proc_exit (i32.const 2)))
+            )
+        )
+    )
+    "#,
+    )
+    .unwrap();
+
+    let builder = WasiEnv::builder("command-name")
+        .fs(Box::<mem_fs::FileSystem>::default())
+        .readahead_size(4)
+        .preopen_dir("/")
+        .unwrap();
+
+    #[cfg(feature = "js")]
+    let result = builder.run_with_store(module, &mut store);
+    #[cfg(not(feature = "js"))]
+    let result = std::thread::spawn(move || builder.run_with_store(module, &mut store))
+        .join()
+        .unwrap();
+
+    result.unwrap();
+}
+
+#[cfg(not(feature = "js"))]
+async fn test_fd_advise_changed_behind_the_fd() {
+    let mut store = Store::default();
+    let module = Module::new(
+        &store,
+        br#"
+    (module
+        (import "wasi_snapshot_preview1" "path_open" (func $path_open (param i32 i32 i32 i32 i32 i64 i64 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "fd_advise" (func $fd_advise (param i32 i64 i64 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "fd_pread" (func $fd_pread (param i32 i32 i32 i64 i32) (result i32)))
+
+        (memory 1)
+        (export "memory" (memory 0))
+
+        ;; iovec for a 4 byte buffer
+        (data (i32.const 24) "\00\01\00\00\04\00\00\00")
+        (data (i32.const 32) "f")
+
+        (func $check (param $errno i32)
+            (if (local.get $errno) (then unreachable))
+        )
+
+        ;; Opens 'f' and reads its first bytes ahead
+        (func (export "advise") (result i32)
+            (call $check
+                (call $path_open (i32.const 4) (i32.const 0) (i32.const 32) (i32.const 1)
+                    (i32.const 0) (i64.const 134) (i64.const 0) (i32.const 0) (i32.const 0))
+            )
+            (call $check (call $fd_advise (i32.load (i32.const 0)) (i64.const 0) (i64.const 0) (i32.const 3)))
+            (i32.load (i32.const 0))
+        )
+
+        ;; Returns the first 4 bytes of the file
+        (func (export "read") (param $fd i32) (result i32)
+            (call $check (call $fd_pread (local.get $fd) (i32.const 24) (i32.const 1) (i64.const 0) (i32.const 40)))
+            (i32.load (i32.const 256))
+        )
+    )
+    "#,
+    )
+    .unwrap();
+
+    let fs = mem_fs::FileSystem::default();
+    let mut file = fs
+        .new_open_options()
+        .write(true)
+        .create(true)
+        .open("/f")
+        .unwrap();
+    file.write_all(b"abcdefgh").await.unwrap();
+
+    let builder = WasiEnv::builder("command-name")
+        .fs(Box::new(fs.clone()))
+        .readahead_size(4)
+        .preopen_dir("/")
+        .unwrap();
+
+    let handle = tokio::runtime::Handle::current();
+    std::thread::spawn(move || {
+        let _guard = handle.enter();
+        let (instance, _env) = builder.instantiate(module, &mut store).unwrap();
+        let advise = instance.exports.get_function("advise").unwrap();
+        let read = instance.exports.get_function("read").unwrap();
+
+        let fd = advise.call(&mut store, &[]).unwrap()[0].clone();
+        let first = read.call(&mut store, &[fd.clone()]).unwrap()[0].unwrap_i32();
+        assert_eq!(first.to_le_bytes(), *b"abcd");
+
+        // The file is changed without going through the fd, so what was
+        // read ahead is stale
+        handle.block_on(async {
+            let mut file = fs.new_open_options().write(true).open("/f").unwrap();
+            file.write_all(b"ABCDEFGHIJ").await.unwrap();
+        });
+        let second = read.call(&mut store, &[fd]).unwrap()[0].unwrap_i32();
+        assert_eq!(second.to_le_bytes(), *b"ABCD");
+    })
+    .join()
+    .unwrap();
+}
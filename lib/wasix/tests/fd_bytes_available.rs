@@ -0,0 +1,78 @@
+use wasmer::{Module, Store};
+use wasmer_wasix::WasiEnv;
+
+mod sys {
+    #[tokio::test]
+    async fn test_fd_bytes_available_on_pipe() {
+        super::test_fd_bytes_available_on_pipe().await;
+    }
+}
+
+async fn test_fd_bytes_available_on_pipe() {
+    let mut store = Store::default();
+    let module = Module::new(
+        &store,
+        br#"
+    (module
+        (import "wasix_32v1" "fd_pipe" (func $fd_pipe (param i32 i32) (result i32)))
+        (import "wasix_32v1" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+        (import "wasix_32v1" "fd_read" (func $fd_read (param i32 i32 i32 i32) (result i32)))
+        (import "wasix_32v1" "fd_bytes_available" (func $fd_bytes_available (param i32 i32) (result i32)))
+        (import "wasix_32v1" "proc_exit" (func $proc_exit (param i32)))
+
+        (memory 1)
+        (export "memory" (memory 0))
+
+        (data (i32.const 64) "hello world")
+
+        (func $main (export "_start")
+            (local $errno i32)
+            (call $fd_pipe (i32.const 0) (i32.const 4))
+            drop
+
+            ;; Write 11 bytes into the pipe with two separate writes
+            (i32.store (i32.const 16) (i32.const 64))
+            (i32.store (i32.const 20) (i32.const 5))
+            (call $fd_write (i32.load (i32.const 4)) (i32.const 16) (i32.const 1) (i32.const 24))
+            drop
+            (i32.store (i32.const 16) (i32.const 69))
+            (i32.store (i32.const 20) (i32.const 6))
+            (call $fd_write (i32.load (i32.const 4)) (i32.const 16) (i32.const 1) (i32.const 24))
+            drop
+
+            ;; Report the errno of the query, the bytes it reported and the
+            ;; number of bytes a read returns afterwards in the exit code
+            (local.set $errno
+                (call $fd_bytes_available (i32.load (i32.const 0)) (i32.const 32))
+            )
+            (i32.store (i32.const 16) (i32.const 128))
+            (i32.store (i32.const 20) (i32.const 64))
+            (call $fd_read (i32.load (i32.const 0)) (i32.const 16) (i32.const 1) (i32.const 24))
+            drop
+            (call $proc_exit
+                (i32.or
+                    (i32.or
+                        (i32.shl (local.get $errno) (i32.const 16))
+                        (i32.shl (i32.wrap_i64 (i64.load (i32.const 32))) (i32.const 8))
+                    )
+                    (i32.load (i32.const 24))
+                )
+            )
+        )
+    )
+    "#,
+    )
+    .unwrap();
+
+    let builder = WasiEnv::builder("command-name");
+
+    #[cfg(feature = "js")]
+    let result = builder.run_with_store(module, &mut store);
+    #[cfg(not(feature = "js"))]
+    let result = std::thread::spawn(move || builder.run_with_store(module, &mut store))
+        .join()
+        .unwrap();
+
+    let exit_code = result.unwrap_err().as_exit_code().unwrap();
+    assert_eq!(exit_code.raw(), (11 << 8) | 11);
+}
@@ -0,0 +1,76 @@
+use virtual_fs::mem_fs;
+use wasmer::{Module, Store};
+use wasmer_wasix::WasiEnv;
+
+mod sys {
+    #[tokio::test]
+    async fn test_fd_fdstat_get_preopens_are_directories() {
+        super::test_fd_fdstat_get_preopens_are_directories().await;
+    }
+}
+
+async fn test_fd_fdstat_get_preopens_are_directories() {
+    let mut store = Store::default();
+    let module = Module::new(
+        &store,
+        br#"
+    (module
+        (import "wasi_snapshot_preview1" "fd_fdstat_get" (func $fd_fdstat_get (param i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "fd_filestat_get" (func $fd_filestat_get (param i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "fd_renumber" (func $fd_renumber (param i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "proc_exit" (func $proc_exit (param i32)))
+
+        (memory 1)
+        (export "memory" (memory 0))
+
+        (func $check (param $errno i32)
+            (if (local.get $errno)
+                (then (call $proc_exit (i32.add (i32.const 100) (local.get $errno))))
+            )
+        )
+
+        ;; Exits with `code` unless `fd` is a directory that paths can be
+        ;; opened under
+        (func $expect_dir (param $fd i32) (param $code i32)
+            (call $check (call $fd_fdstat_get (local.get $fd) (i32.const 64)))
+            (if (i32.ne (i32.load8_u (i32.const 64)) (i32.const 3))
+                (then (call $proc_exit (local.get $code)))
+            )
+            ;; PATH_OPEN
+            (if (i64.eqz (i64.and (i64.load (i32.const 72)) (i64.const 0x2000)))
+                (then (call $proc_exit (i32.add (local.get $code) (i32.const 1))))
+            )
+            (call $check (call $fd_filestat_get (local.get $fd) (i32.const 128)))
+            (if (i32.ne (i32.load8_u (i32.const 144)) (i32.const 3))
+                (then (call $proc_exit (i32.add (local.get $code) (i32.const 2))))
+            )
+        )
+
+        (func $main (export "_start")
+            ;; The virtual root and the preopen of '/'
+            (call $expect_dir (i32.const 3) (i32.const 10))
+            (call $expect_dir (i32.const 4) (i32.const 20))
+
+            ;; The root still is a directory under another number
+            (call $check (call $fd_renumber (i32.const 3) (i32.const 9)))
+            (call $expect_dir (i32.const 9) (i32.const 30))
+        )
+    )
+    "#,
+    )
+    .unwrap();
+
+    let builder = WasiEnv::builder("command-name")
+        .fs(Box::<mem_fs::FileSystem>::default())
+        .preopen_dir("/")
+        .unwrap();
+
+    #[cfg(feature = "js")]
+    let result = builder.run_with_store(module, &mut store);
+    #[cfg(not(feature = "js"))]
+    let result = std::thread::spawn(move || builder.run_with_store(module, &mut store))
+        .join()
+        .unwrap();
+
+    result.unwrap();
+}
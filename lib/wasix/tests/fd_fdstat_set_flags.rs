@@ -0,0 +1,109 @@
+use virtual_fs::mem_fs;
+use wasmer::{Module, Store};
+use wasmer_wasix::WasiEnv;
+
+mod sys {
+    #[tokio::test]
+    async fn test_toggle_append() {
+        super::test_toggle_append().await;
+    }
+}
+
+async fn test_toggle_append() {
+    let mut store = Store::default();
+    let module = Module::new(&store, br#"
+    (module
+        (import "wasi_snapshot_preview1" "path_open" (func $path_open (param i32 i32 i32 i32 i32 i64 i64 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "fd_fdstat_set_flags" (func $fd_fdstat_set_flags (param i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "fd_read" (func $fd_read (param i32 i32 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "fd_seek" (func $fd_seek (param i32 i64 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "proc_exit" (func $proc_exit (param i32)))
+
+        (memory 1)
+        (export "memory" (memory 0))
+
+        (data (i32.const 32) "f")
+        (data (i32.const 48) "hello")
+        (data (i32.const 56) "!!")
+        (data (i32.const 60) "J")
+
+        (func $write (param $fd i32) (param $buf i32) (param $len i32)
+            (i32.store (i32.const 8) (local.get $buf))
+            (i32.store (i32.const 12) (local.get $len))
+            (call $fd_write (local.get $fd) (i32.const 8) (i32.const 1) (i32.const 16))
+            drop
+        )
+
+        (func $main (export "_start")
+            (local $fd i32)
+
+            (call $path_open
+                (i32.const 4)   ;; dirfd
+                (i32.const 0)   ;; dirflags
+                (i32.const 32)  ;; path
+                (i32.const 1)   ;; path_len
+                (i32.const 1)   ;; oflags (CREATE)
+                (i64.const 78)  ;; rights_base (FD_READ | FD_SEEK | FD_FDSTAT_SET_FLAGS | FD_WRITE)
+                (i64.const 0)   ;; rights_inheriting
+                (i32.const 0)   ;; fdflags
+                (i32.const 0)   ;; fd_out
+            )
+            drop
+            (local.set $fd (i32.load (i32.const 0)))
+            (call $write (local.get $fd) (i32.const 48) (i32.const 5))
+
+            ;; With APPEND the write lands at the end wherever the cursor is
+            (call $fd_seek (local.get $fd) (i64.const 0) (i32.const 0) (i32.const 64))
+            drop
+            (call $fd_fdstat_set_flags (local.get $fd) (i32.const 1))
+            drop
+            (call $write (local.get $fd) (i32.const 56) (i32.const 2))
+
+            ;; Without it the write lands at the cursor again
+            (call $fd_fdstat_set_flags (local.get $fd) (i32.const 0))
+            drop
+            (call $fd_seek (local.get $fd) (i64.const 0) (i32.const 0) (i32.const 64))
+            drop
+            (call $write (local.get $fd) (i32.const 60) (i32.const 1))
+
+            ;; Report the size of the file and its first and last byte in
+            ;; the exit code
+            (call $fd_seek (local.get $fd) (i64.const 0) (i32.const 0) (i32.const 64))
+            drop
+            (i32.store (i32.const 8) (i32.const 128))
+            (i32.store (i32.const 12) (i32.const 16))
+            (call $fd_read (local.get $fd) (i32.const 8) (i32.const 1) (i32.const 16))
+            drop
+            (call $proc_exit
+                (i32.or
+                    (i32.or
+                        (i32.shl (i32.load (i32.const 16)) (i32.const 16))
+                        (i32.shl (i32.load8_u (i32.const 128)) (i32.const 8))
+                    )
+                    (i32.load8_u (i32.add (i32.const 127) (i32.load (i32.const 16))))
+                )
+            )
+        )
+    )
+    "#).unwrap();
+
+    let builder = WasiEnv::builder("command-name")
+        .fs(Box::<mem_fs::FileSystem>::default())
+        .preopen_dir("/")
+        .unwrap();
+
+    #[cfg(feature = "js")]
+    let result = builder.run_with_store(module, &mut store);
+    #[cfg(not(feature = "js"))]
+    let result = std::thread::spawn(move || builder.run_with_store(module, &mut store))
+        .join()
+        .unwrap();
+
+    // The file holds "Jello!!"
+    let exit_code = result.unwrap_err().as_exit_code().unwrap();
+    assert_eq!(
+        exit_code.raw(),
+        (7 << 16) | ((b'J' as i32) << 8) | b'!' as i32
+    );
+}
@@ -0,0 +1,140 @@
+use virtual_fs::mem_fs;
+use wasmer::{Module, Store};
+use wasmer_wasix::{WasiEnv, WasiEnvBuilder};
+
+mod sys {
+    #[tokio::test]
+    async fn test_fd_filestat_set_size_mem_fs() {
+        super::test_fd_filestat_set_size_mem_fs().await;
+    }
+
+    #[cfg(all(feature = "host-fs", not(feature = "js")))]
+    #[tokio::test]
+    async fn test_fd_filestat_set_size_host_fs() {
+        super::test_fd_filestat_set_size_host_fs().await;
+    }
+}
+
+async fn test_fd_filestat_set_size_mem_fs() {
+    let builder = WasiEnv::builder("command-name")
+        .fs(Box::<mem_fs::FileSystem>::default())
+        .preopen_dir("/")
+        .unwrap();
+
+    run_set_size(builder);
+}
+
+#[cfg(all(feature = "host-fs", not(feature = "js")))]
+async fn test_fd_filestat_set_size_host_fs() {
+    let host = tempfile::TempDir::new().unwrap();
+    let builder = WasiEnv::builder("command-name")
+        .fs(Box::new(virtual_fs::host_fs::FileSystem::new(
+            tokio::runtime::Handle::current(),
+        )))
+        .preopen_dir(host.path())
+        .unwrap();
+
+    run_set_size(builder);
+}
+
+fn run_set_size(builder: WasiEnvBuilder) {
+    let mut store = Store::default();
+    let module = Module::new(
+        &store,
+        br#"
+    (module
+        (import "wasi_snapshot_preview1" "path_open" (func $path_open (param i32 i32 i32 i32 i32 i64 i64 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "fd_read" (func $fd_read (param i32 i32 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "fd_seek" (func $fd_seek (param i32 i64 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "fd_filestat_set_size" (func $fd_filestat_set_size (param i32 i64) (result i32)))
+        (import "wasi_snapshot_preview1" "proc_exit" (func $proc_exit (param i32)))
+
+        (memory 1)
+        (export "memory" (memory 0))
+
+        ;; iovecs for the contents and for a 64 byte buffer
+        (data (i32.const 8) "\40\00\00\00\0b\00\00\00")
+        (data (i32.const 32) "\00\01\00\00\40\00\00\00")
+        (data (i32.const 64) "hello world")
+        (data (i32.const 96) "hello\00\00\00")
+        (data (i32.const 128) "f")
+
+        (func $check (param $errno i32)
+            (if (local.get $errno)
+                (then (call $proc_exit (i32.add (i32.const 100) (local.get $errno))))
+            )
+        )
+
+        ;; Reads from the cursor of the file and returns the number of bytes read
+        (func $read (param $fd i32) (result i32)
+            (call $check (call $fd_read (local.get $fd) (i32.const 32) (i32.const 1) (i32.const 16)))
+            (i32.load (i32.const 16))
+        )
+
+        (func $rewind (param $fd i32)
+            (call $check (call $fd_seek (local.get $fd) (i64.const 0) (i32.const 0) (i32.const 24)))
+        )
+
+        (func $main (export "_start")
+            (local $fd i32)
+            (local $past_eof i32)
+            (local $shrunk i32)
+            (local $grown i32)
+
+            (call $check
+                (call $path_open
+                    (i32.const 4)       ;; dirfd
+                    (i32.const 0)       ;; dirflags
+                    (i32.const 128)     ;; path
+                    (i32.const 1)       ;; path_len
+                    (i32.const 1)       ;; oflags (CREATE)
+                    (i64.const 4194374) ;; rights_base (FD_READ | FD_SEEK | FD_WRITE | FD_FILESTAT_SET_SIZE)
+                    (i64.const 0)       ;; rights_inheriting
+                    (i32.const 0)       ;; fdflags
+                    (i32.const 0)       ;; fd_out
+                )
+            )
+            (local.set $fd (i32.load (i32.const 0)))
+            (call $check (call $fd_write (local.get $fd) (i32.const 8) (i32.const 1) (i32.const 16)))
+
+            ;; Shrink below the cursor, which is then at EOF
+            (call $check (call $fd_filestat_set_size (local.get $fd) (i64.const 5)))
+            (local.set $past_eof (call $read (local.get $fd)))
+            (call $rewind (local.get $fd))
+            (local.set $shrunk (call $read (local.get $fd)))
+
+            ;; Grow the file, the new bytes are zeroes
+            (call $check (call $fd_filestat_set_size (local.get $fd) (i64.const 8)))
+            (call $rewind (local.get $fd))
+            (local.set $grown (call $read (local.get $fd)))
+            (if (i64.ne (i64.load (i32.const 256)) (i64.load (i32.const 96)))
+                (then (call $proc_exit (i32.const 1)))
+            )
+
+            (call $proc_exit
+                (i32.or
+                    (i32.or
+                        (i32.shl (local.get $past_eof) (i32.const 16))
+                        (i32.shl (local.get $shrunk) (i32.const 8))
+                    )
+                    (local.get $grown)
+                )
+            )
+        )
+    )
+    "#,
+    )
+    .unwrap();
+
+    #[cfg(feature = "js")]
+    let result = builder.run_with_store(module, &mut store);
+    #[cfg(not(feature = "js"))]
+    let result = std::thread::spawn(move || builder.run_with_store(module, &mut store))
+        .join()
+        .unwrap();
+
+    // Nothing read past EOF, then `hello` and `hello\0\0\0`
+    let exit_code = result.unwrap_err().as_exit_code().unwrap();
+    assert_eq!(exit_code.raw(), (5 << 8) | 8);
+}
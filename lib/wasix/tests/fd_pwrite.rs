@@ -0,0 +1,403 @@
+use std::{
+    io,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use virtual_fs::{mem_fs, AsyncRead, AsyncSeek, AsyncWrite, VirtualFile};
+use wasmer::{Module, Store};
+use wasmer_wasix::WasiEnv;
+
+mod sys {
+    #[tokio::test]
+    async fn test_pwrite_short_device() {
+        super::test_pwrite_short_device().await;
+    }
+    #[tokio::test]
+    async fn test_pwrite_short_device_file() {
+        super::test_pwrite_short_device_file().await;
+    }
+    #[tokio::test]
+    async fn test_pwrite_beyond_eof() {
+        super::test_pwrite_beyond_eof().await;
+    }
+    #[tokio::test]
+    async fn test_pwrite_append() {
+        super::test_pwrite_append().await;
+    }
+}
+
+/// Device that will only ever accept a fixed number of bytes
+#[derive(Debug, Default)]
+struct ShortDevice {
+    remaining: usize,
+    position: u64,
+}
+
+impl AsyncSeek for ShortDevice {
+    fn start_seek(mut self: Pin<&mut Self>, position: io::SeekFrom) -> io::Result<()> {
+        if let io::SeekFrom::Start(position) = position {
+            self.position = position;
+        }
+        Ok(())
+    }
+    fn poll_complete(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<u64>> {
+        Poll::Ready(Ok(self.position))
+    }
+}
+
+impl AsyncWrite for ShortDevice {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let amt = buf.len().min(self.remaining);
+        self.remaining -= amt;
+        Poll::Ready(Ok(amt))
+    }
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+    fn poll_shutdown(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl AsyncRead for ShortDevice {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        _buf: &mut virtual_fs::ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl VirtualFile for ShortDevice {
+    fn last_accessed(&self) -> u64 {
+        0
+    }
+    fn last_modified(&self) -> u64 {
+        0
+    }
+    fn created_time(&self) -> u64 {
+        0
+    }
+    fn size(&self) -> u64 {
+        0
+    }
+    fn set_len(&mut self, _new_size: u64) -> virtual_fs::Result<()> {
+        Ok(())
+    }
+    fn unlink(&mut self) -> virtual_fs::Result<()> {
+        Ok(())
+    }
+    fn poll_read_ready(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<usize>> {
+        Poll::Ready(Ok(0))
+    }
+    fn poll_write_ready(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<usize>> {
+        Poll::Ready(Ok(self.remaining))
+    }
+}
+
+async fn test_pwrite_short_device() {
+    let mut store = Store::default();
+    let module = Module::new(&store, br#"
+    (module
+        (import "wasi_snapshot_preview1" "fd_pwrite" (func $fd_pwrite (param i32 i32 i32 i64 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "proc_exit" (func $proc_exit (param i32)))
+
+        (memory 1)
+        (export "memory" (memory 0))
+
+        (data (i32.const 32) "hello world")
+
+        (func $main (export "_start")
+            ;; Two io vectors, 'hello' and ' world'
+            (i32.store (i32.const 0) (i32.const 32))
+            (i32.store (i32.const 4) (i32.const 5))
+            (i32.store (i32.const 8) (i32.const 37))
+            (i32.store (i32.const 12) (i32.const 6))
+
+            (call $fd_pwrite
+                (i32.const 1)  ;; file_descriptor
+                (i32.const 0)  ;; *iovs
+                (i32.const 2)  ;; iovs_len
+                (i64.const 0)  ;; offset
+                (i32.const 20) ;; nwritten
+            )
+            drop
+
+            ;; Report the number of bytes written as the exit code
+            (call $proc_exit (i32.load (i32.const 20)))
+        )
+    )
+    "#).unwrap();
+
+    let builder = WasiEnv::builder("command-name").stdout(Box::new(ShortDevice {
+        remaining: 7,
+        ..Default::default()
+    }));
+
+    #[cfg(feature = "js")]
+    let result = builder.run_with_store(module, &mut store);
+    #[cfg(not(feature = "js"))]
+    let result = std::thread::spawn(move || builder.run_with_store(module, &mut store))
+        .join()
+        .unwrap();
+
+    let exit_code = result.unwrap_err().as_exit_code().unwrap();
+    assert_eq!(exit_code.raw(), 7);
+}
+
+async fn test_pwrite_short_device_file() {
+    let mut store = Store::default();
+    let module = Module::new(&store, br#"
+(module
+    (import "wasi_snapshot_preview1" "path_open" (func $path_open (param i32 i32 i32 i32 i32 i64 i64 i32 i32) (result i32)))
+    (import "wasi_snapshot_preview1" "fd_pwrite" (func $fd_pwrite (param i32 i32 i32 i64 i32) (result i32)))
+    (import "wasi_snapshot_preview1" "fd_filestat_get" (func $fd_filestat_get (param i32 i32) (result i32)))
+    (import "wasi_snapshot_preview1" "proc_exit" (func $proc_exit (param i32)))
+
+    (memory 1)
+    (export "memory" (memory 0))
+
+    (data (i32.const 32) "hello world")
+    (data (i32.const 48) "short")
+
+    (func $main (export "_start")
+        (call $path_open
+            (i32.const 4)   ;; dirfd
+            (i32.const 0)   ;; dirflags
+            (i32.const 48)  ;; path
+            (i32.const 5)   ;; path_len
+            (i32.const 0)   ;; oflags
+            (i64.const -1)  ;; rights_base
+            (i64.const -1)  ;; rights_inheriting
+            (i32.const 0)   ;; fdflags
+            (i32.const 100) ;; fd_out
+        )
+        drop
+
+        ;; Write 'hello world' at offset 100 of a device that only
+        ;; takes 7 bytes
+        (i32.store (i32.const 0) (i32.const 32))
+        (i32.store (i32.const 4) (i32.const 11))
+        (call $fd_pwrite (i32.load (i32.const 100)) (i32.const 0) (i32.const 1) (i64.const 100) (i32.const 20))
+        drop
+
+        ;; Report the size of the file and the number of bytes written
+        ;; as the exit code
+        (call $fd_filestat_get (i32.load (i32.const 100)) (i32.const 128))
+        drop
+        (call $proc_exit
+            (i32.or
+                (i32.shl (i32.wrap_i64 (i64.load (i32.const 160))) (i32.const 8))
+                (i32.load (i32.const 20))
+            )
+        )
+    )
+)
+"#).unwrap();
+
+    let fs = mem_fs::FileSystem::default();
+    fs.insert_device_file(
+        "/short".into(),
+        Box::new(ShortDevice {
+            remaining: 7,
+            ..Default::default()
+        }),
+    )
+    .unwrap();
+    let builder = WasiEnv::builder("command-name")
+        .fs(Box::new(fs))
+        .preopen_dir("/")
+        .unwrap();
+
+    #[cfg(feature = "js")]
+    let result = builder.run_with_store(module, &mut store);
+    #[cfg(not(feature = "js"))]
+    let result = std::thread::spawn(move || builder.run_with_store(module, &mut store))
+        .join()
+        .unwrap();
+
+    // 7 bytes were written at offset 100 so the file ends at 107
+    let exit_code = result.unwrap_err().as_exit_code().unwrap();
+    assert_eq!(exit_code.raw(), (107 << 8) | 7);
+}
+
+async fn test_pwrite_beyond_eof() {
+    let mut store = Store::default();
+    let module = Module::new(&store, br#"
+    (module
+        (import "wasi_snapshot_preview1" "path_open" (func $path_open (param i32 i32 i32 i32 i32 i64 i64 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "fd_pwrite" (func $fd_pwrite (param i32 i32 i32 i64 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "fd_pread" (func $fd_pread (param i32 i32 i32 i64 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "fd_tell" (func $fd_tell (param i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "fd_filestat_get" (func $fd_filestat_get (param i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "proc_exit" (func $proc_exit (param i32)))
+
+        (memory 1)
+        (export "memory" (memory 0))
+
+        (data (i32.const 32) "x")
+        (data (i32.const 48) "file")
+
+        (func $main (export "_start")
+            (local $i i32)
+
+            ;; Create an empty file
+            (call $path_open
+                (i32.const 4)   ;; dirfd
+                (i32.const 0)   ;; dirflags
+                (i32.const 48)  ;; path
+                (i32.const 4)   ;; path_len
+                (i32.const 1)   ;; oflags (CREATE)
+                (i64.const -1)  ;; rights_base
+                (i64.const -1)  ;; rights_inheriting
+                (i32.const 0)   ;; fdflags
+                (i32.const 100) ;; fd_out
+            )
+            drop
+
+            ;; Write a single byte at offset 1000
+            (i32.store (i32.const 0) (i32.const 32))
+            (i32.store (i32.const 4) (i32.const 1))
+            (call $fd_pwrite (i32.load (i32.const 100)) (i32.const 0) (i32.const 1) (i64.const 1000) (i32.const 20))
+            drop
+
+            ;; The cursor must not have moved
+            (call $fd_tell (i32.load (i32.const 100)) (i32.const 112))
+            drop
+            (if (i64.ne (i64.load (i32.const 112)) (i64.const 0))
+                (then (call $proc_exit (i32.const 1))))
+
+            ;; Read the whole file back over a buffer full of garbage
+            (memory.fill (i32.const 2000) (i32.const 255) (i32.const 1001))
+            (i32.store (i32.const 8) (i32.const 2000))
+            (i32.store (i32.const 12) (i32.const 1001))
+            (call $fd_pread (i32.load (i32.const 100)) (i32.const 8) (i32.const 1) (i64.const 0) (i32.const 24))
+            drop
+
+            ;; The gap must read back as zeros followed by the byte
+            (loop $gap
+                (if (i32.load8_u (i32.add (i32.const 2000) (local.get $i)))
+                    (then (call $proc_exit (i32.const 2))))
+                (local.set $i (i32.add (local.get $i) (i32.const 1)))
+                (br_if $gap (i32.lt_u (local.get $i) (i32.const 1000)))
+            )
+            (if (i32.ne (i32.load8_u (i32.const 3000)) (i32.const 120))
+                (then (call $proc_exit (i32.const 3))))
+
+            ;; Report the size of the file as the exit code
+            (call $fd_filestat_get (i32.load (i32.const 100)) (i32.const 128))
+            drop
+            (call $proc_exit (i32.wrap_i64 (i64.load (i32.const 160))))
+        )
+    )
+    "#).unwrap();
+
+    let builder = WasiEnv::builder("command-name")
+        .fs(Box::<mem_fs::FileSystem>::default())
+        .preopen_dir("/")
+        .unwrap();
+
+    #[cfg(feature = "js")]
+    let result = builder.run_with_store(module, &mut store);
+    #[cfg(not(feature = "js"))]
+    let result = std::thread::spawn(move || builder.run_with_store(module, &mut store))
+        .join()
+        .unwrap();
+
+    let exit_code = result.unwrap_err().as_exit_code().unwrap();
+    assert_eq!(exit_code.raw(), 1001);
+}
+
+async fn test_pwrite_append() {
+    let mut store = Store::default();
+    let module = Module::new(&store, br#"
+    (module
+        (import "wasi_snapshot_preview1" "path_open" (func $path_open (param i32 i32 i32 i32 i32 i64 i64 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "fd_pwrite" (func $fd_pwrite (param i32 i32 i32 i64 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "fd_pread" (func $fd_pread (param i32 i32 i32 i64 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "fd_tell" (func $fd_tell (param i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "proc_exit" (func $proc_exit (param i32)))
+
+        (memory 1)
+        (export "memory" (memory 0))
+
+        (data (i32.const 32) "log")
+        (data (i32.const 48) "hello")
+        (data (i32.const 64) "ab")
+
+        (func $main (export "_start")
+            ;; Create the file 'log' containing 'hello'
+            (call $path_open
+                (i32.const 4)   ;; dirfd
+                (i32.const 0)   ;; dirflags
+                (i32.const 32)  ;; path
+                (i32.const 3)   ;; path_len
+                (i32.const 1)   ;; oflags (CREATE)
+                (i64.const -1)  ;; rights_base
+                (i64.const -1)  ;; rights_inheriting
+                (i32.const 0)   ;; fdflags
+                (i32.const 100) ;; fd_out
+            )
+            drop
+            (i32.store (i32.const 0) (i32.const 48))
+            (i32.store (i32.const 4) (i32.const 5))
+            (call $fd_write (i32.load (i32.const 100)) (i32.const 0) (i32.const 1) (i32.const 8))
+            drop
+
+            ;; Reopen it for appending and write at offset 0
+            (call $path_open
+                (i32.const 4) (i32.const 0) (i32.const 32) (i32.const 3)
+                (i32.const 0) (i64.const -1) (i64.const -1)
+                (i32.const 1)   ;; fdflags (APPEND)
+                (i32.const 104)
+            )
+            drop
+            (i32.store (i32.const 0) (i32.const 64))
+            (i32.store (i32.const 4) (i32.const 2))
+            (call $fd_pwrite (i32.load (i32.const 104)) (i32.const 0) (i32.const 1) (i64.const 0) (i32.const 20))
+            drop
+
+            ;; The cursor must not have moved
+            (call $fd_tell (i32.load (i32.const 104)) (i32.const 112))
+            drop
+            (if (i64.ne (i64.load (i32.const 112)) (i64.const 0))
+                (then (call $proc_exit (i32.const 1))))
+
+            ;; The positional read is not affected by APPEND, 'ab' was
+            ;; appended after 'hello' which is still there
+            (i32.store (i32.const 8) (i32.const 2000))
+            (i32.store (i32.const 12) (i32.const 16))
+            (call $fd_pread (i32.load (i32.const 104)) (i32.const 8) (i32.const 1) (i64.const 4) (i32.const 24))
+            drop
+            (if (i32.ne (i32.load (i32.const 2000)) (i32.const 0x0062616f)) ;; "oab"
+                (then (call $proc_exit (i32.const 2))))
+
+            ;; Report the number of bytes read as the exit code
+            (call $proc_exit (i32.load (i32.const 24)))
+        )
+    )
+    "#).unwrap();
+
+    let builder = WasiEnv::builder("command-name")
+        .fs(Box::<mem_fs::FileSystem>::default())
+        .preopen_dir("/")
+        .unwrap();
+
+    #[cfg(feature = "js")]
+    let result = builder.run_with_store(module, &mut store);
+    #[cfg(not(feature = "js"))]
+    let result = std::thread::spawn(move || builder.run_with_store(module, &mut store))
+        .join()
+        .unwrap();
+
+    // "hello" and "ab" from offset 4
+    let exit_code = result.unwrap_err().as_exit_code().unwrap();
+    assert_eq!(exit_code.raw(), 3);
+}
@@ -0,0 +1,522 @@
+use virtual_fs::{mem_fs, AsyncWriteExt, Pipe};
+use wasmer::{Module, Store};
+use wasmer_wasix::{types::wasi::Rights, ClosedStdin, WasiEnv};
+
+mod sys {
+    #[tokio::test]
+    async fn test_fd_read_on_directory() {
+        super::test_fd_read_on_directory().await;
+    }
+    #[tokio::test]
+    async fn test_fd_read_closed_stdin_is_eof() {
+        super::test_fd_read_closed_stdin_is_eof().await;
+    }
+    #[tokio::test]
+    async fn test_fd_read_closed_stdin_is_again() {
+        super::test_fd_read_closed_stdin_is_again().await;
+    }
+    #[tokio::test]
+    async fn test_fd_read_file_renumbered_onto_stdin_is_eof() {
+        super::test_fd_read_file_renumbered_onto_stdin_is_eof().await;
+    }
+    #[cfg(not(feature = "js"))]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_fd_read_closed_stdin_blocks_until_reopened() {
+        super::test_fd_read_closed_stdin_blocks_until_reopened().await;
+    }
+    #[tokio::test]
+    async fn test_fd_read_at_eof() {
+        super::test_fd_read_at_eof().await;
+    }
+    #[tokio::test]
+    async fn test_fd_read_partial_iovecs_at_eof() {
+        super::test_fd_read_partial_iovecs_at_eof().await;
+    }
+}
+
+async fn test_fd_read_on_directory() {
+    let mut store = Store::default();
+    let module = Module::new(&store, br#"
+    (module
+        (import "wasi_snapshot_preview1" "path_create_directory" (func $path_create_directory (param i32 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "path_open" (func $path_open (param i32 i32 i32 i32 i32 i64 i64 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "fd_read" (func $fd_read (param i32 i32 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "proc_exit" (func $proc_exit (param i32)))
+
+        (memory 1)
+        (export "memory" (memory 0))
+
+        (data (i32.const 32) "dir")
+
+        (func $main (export "_start")
+            (local $errno1 i32)
+            (local $errno2 i32)
+
+            ;; A single io vector for the reads
+            (i32.store (i32.const 0) (i32.const 128))
+            (i32.store (i32.const 4) (i32.const 64))
+
+            ;; Open the directory 'dir' with the rights to read from it
+            (call $path_create_directory (i32.const 4) (i32.const 32) (i32.const 3))
+            drop
+            (call $path_open
+                (i32.const 4)   ;; dirfd
+                (i32.const 0)   ;; dirflags
+                (i32.const 32)  ;; path
+                (i32.const 3)   ;; path_len
+                (i32.const 2)   ;; oflags (DIRECTORY)
+                (i64.const 2)   ;; rights_base (FD_READ)
+                (i64.const 0)   ;; rights_inheriting
+                (i32.const 0)   ;; fdflags
+                (i32.const 100) ;; fd_out
+            )
+            drop
+            (local.set $errno1
+                (call $fd_read (i32.load (i32.const 100)) (i32.const 0) (i32.const 1) (i32.const 8)))
+
+            ;; The preopened directory can not be read either
+            (local.set $errno2
+                (call $fd_read (i32.const 4) (i32.const 0) (i32.const 1) (i32.const 8)))
+
+            (call $proc_exit
+                (i32.or (i32.shl (local.get $errno1) (i32.const 8)) (local.get $errno2)))
+        )
+    )
+    "#).unwrap();
+
+    let builder = WasiEnv::builder("command-name")
+        .fs(Box::<mem_fs::FileSystem>::default())
+        .preopen_dir("/")
+        .unwrap();
+
+    #[cfg(feature = "js")]
+    let result = builder.run_with_store(module, &mut store);
+    #[cfg(not(feature = "js"))]
+    let result = std::thread::spawn(move || builder.run_with_store(module, &mut store))
+        .join()
+        .unwrap();
+
+    // Both reads fail with Isdir (31)
+    let exit_code = result.unwrap_err().as_exit_code().unwrap();
+    assert_eq!(exit_code.raw(), (31 << 8) | 31);
+}
+
+/// Reads twice from a non-blocking `stdin` that holds two bytes and whose
+/// writer was closed, returns the exit code that reports the results of both
+/// reads
+async fn read_closed_stdin(behavior: ClosedStdin) -> i32 {
+    let mut store = Store::default();
+    let module = Module::new(
+        &store,
+        br#"
+    (module
+        (import "wasi_snapshot_preview1" "fd_read" (func $fd_read (param i32 i32 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "fd_fdstat_set_flags" (func $fd_fdstat_set_flags (param i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "proc_exit" (func $proc_exit (param i32)))
+
+        (memory 1)
+        (export "memory" (memory 0))
+
+        (func $main (export "_start")
+            (local $nread1 i32)
+            (local $errno2 i32)
+
+            ;; Fdflags::NONBLOCK
+            (if (call $fd_fdstat_set_flags (i32.const 0) (i32.const 4))
+                (then unreachable)
+            )
+
+            ;; A single io vector for the reads
+            (i32.store (i32.const 0) (i32.const 128))
+            (i32.store (i32.const 4) (i32.const 16))
+
+            (call $fd_read (i32.const 0) (i32.const 0) (i32.const 1) (i32.const 24))
+            drop
+            (local.set $nread1 (i32.load (i32.const 24)))
+
+            (local.set $errno2
+                (call $fd_read (i32.const 0) (i32.const 0) (i32.const 1) (i32.const 24))
+            )
+
+            ;; Report the errno of the second read and the bytes read by
+            ;; both reads in the exit code
+            (call $proc_exit
+                (i32.or
+                    (i32.or
+                        (i32.shl (local.get $errno2) (i32.const 16))
+                        (i32.shl (local.get $nread1) (i32.const 8))
+                    )
+                    (i32.load (i32.const 24))
+                )
+            )
+        )
+    )
+    "#,
+    )
+    .unwrap();
+
+    let (mut stdin_tx, stdin_rx) = Pipe::channel();
+    stdin_tx.write_all(b"hi").await.unwrap();
+    drop(stdin_tx);
+
+    let builder = WasiEnv::builder("command-name")
+        .stdin(Box::new(stdin_rx))
+        .stdin_rights(Rights::FD_READ | Rights::FD_FDSTAT_SET_FLAGS)
+        .closed_stdin(behavior);
+
+    #[cfg(feature = "js")]
+    let result = builder.run_with_store(module, &mut store);
+    #[cfg(not(feature = "js"))]
+    let result = std::thread::spawn(move || builder.run_with_store(module, &mut store))
+        .join()
+        .unwrap();
+
+    result.unwrap_err().as_exit_code().unwrap().raw()
+}
+
+async fn test_fd_read_closed_stdin_is_eof() {
+    // The second read returns zero bytes
+    assert_eq!(read_closed_stdin(ClosedStdin::Eof).await, 2 << 8);
+}
+
+async fn test_fd_read_closed_stdin_is_again() {
+    // Errno::Again for the second read
+    assert_eq!(
+        read_closed_stdin(ClosedStdin::Again).await,
+        (6 << 16) | (2 << 8)
+    );
+}
+
+/// Only `stdin` itself reads Again once closed, a file that took its number
+/// still ends with EOF
+async fn test_fd_read_file_renumbered_onto_stdin_is_eof() {
+    let mut store = Store::default();
+    let module = Module::new(
+        &store,
+        br#"
+    (module
+        (import "wasi_snapshot_preview1" "path_open" (func $path_open (param i32 i32 i32 i32 i32 i64 i64 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "fd_renumber" (func $fd_renumber (param i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "fd_read" (func $fd_read (param i32 i32 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "proc_exit" (func $proc_exit (param i32)))
+
+        (memory 1)
+        (export "memory" (memory 0))
+
+        (data (i32.const 32) "f")
+
+        (func $main (export "_start")
+            ;; Creates the empty file 'f' and moves it onto fd 0
+            (if (call $path_open (i32.const 4) (i32.const 0) (i32.const 32) (i32.const 1)
+                    (i32.const 1) (i64.const 66) (i64.const 0) (i32.const 0) (i32.const 0))
+                (then unreachable)
+            )
+            (if (call $fd_renumber (i32.load (i32.const 0)) (i32.const 0))
+                (then unreachable)
+            )
+
+            ;; A single io vector for the read
+            (i32.store (i32.const 0) (i32.const 128))
+            (i32.store (i32.const 4) (i32.const 16))
+
+            ;; Reports the errno and what was read
+            (call $proc_exit
+                (i32.or
+                    (i32.shl
+                        (call $fd_read (i32.const 0) (i32.const 0) (i32.const 1) (i32.const 24))
+                        (i32.const 8)
+                    )
+                    (i32.load (i32.const 24))
+                )
+            )
+        )
+    )
+    "#,
+    )
+    .unwrap();
+
+    let builder = WasiEnv::builder("command-name")
+        .fs(Box::<mem_fs::FileSystem>::default())
+        .preopen_dir("/")
+        .unwrap()
+        .closed_stdin(ClosedStdin::Again);
+
+    #[cfg(feature = "js")]
+    let result = builder.run_with_store(module, &mut store);
+    #[cfg(not(feature = "js"))]
+    let result = std::thread::spawn(move || builder.run_with_store(module, &mut store))
+        .join()
+        .unwrap();
+
+    // Errno::Success with nothing read exits with 0
+    result.unwrap();
+}
+
+/// A blocking read from a closed `stdin` waits until there is something to
+/// read again rather than failing with Again
+#[cfg(not(feature = "js"))]
+async fn test_fd_read_closed_stdin_blocks_until_reopened() {
+    use std::{io::SeekFrom, time::Duration};
+
+    use virtual_fs::{ArcFile, AsyncSeekExt, BufferFile};
+
+    let mut store = Store::default();
+    let module = Module::new(
+        &store,
+        br#"
+    (module
+        (import "wasi_snapshot_preview1" "fd_read" (func $fd_read (param i32 i32 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "proc_exit" (func $proc_exit (param i32)))
+
+        (memory 1)
+        (export "memory" (memory 0))
+
+        (func $main (export "_start")
+            ;; A single io vector for the reads
+            (i32.store (i32.const 0) (i32.const 128))
+            (i32.store (i32.const 4) (i32.const 16))
+
+            ;; Drains stdin
+            (if (call $fd_read (i32.const 0) (i32.const 0) (i32.const 1) (i32.const 24))
+                (then unreachable)
+            )
+
+            ;; Waits for more and reports the errno and what was read
+            (call $proc_exit
+                (i32.or
+                    (i32.shl
+                        (call $fd_read (i32.const 0) (i32.const 0) (i32.const 1) (i32.const 24))
+                        (i32.const 8)
+                    )
+                    (i32.load (i32.const 24))
+                )
+            )
+        )
+    )
+    "#,
+    )
+    .unwrap();
+
+    let mut stdin = ArcFile::new(Box::<BufferFile>::default());
+    stdin.write_all(b"hi").await.unwrap();
+    stdin.seek(SeekFrom::Start(0)).await.unwrap();
+    let builder = WasiEnv::builder("command-name")
+        .stdin(Box::new(stdin.clone()))
+        .closed_stdin(ClosedStdin::Again);
+
+    let handle = tokio::runtime::Handle::current();
+    let guest = std::thread::spawn(move || {
+        let _guard = handle.enter();
+        builder.run_with_store(module, &mut store)
+    });
+
+    // More data shows up once the guest is waiting
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    assert!(!guest.is_finished());
+    stdin.write_all(b"yo").await.unwrap();
+    stdin.seek(SeekFrom::Current(-2)).await.unwrap();
+
+    let result = guest.join().unwrap();
+    let exit_code = result.unwrap_err().as_exit_code().unwrap();
+    assert_eq!(exit_code.raw(), 2);
+}
+
+async fn test_fd_read_at_eof() {
+    let mut store = Store::default();
+    let module = Module::new(
+        &store,
+        br#"
+    (module
+        (import "wasi_snapshot_preview1" "path_open" (func $path_open (param i32 i32 i32 i32 i32 i64 i64 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "fd_read" (func $fd_read (param i32 i32 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "fd_seek" (func $fd_seek (param i32 i64 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "proc_exit" (func $proc_exit (param i32)))
+
+        (memory 1)
+        (export "memory" (memory 0))
+
+        ;; io vectors for the reads and for both writes
+        (data (i32.const 0) "\80\00\00\00\10\00\00\00")
+        (data (i32.const 8) "\40\00\00\00\05\00\00\00")
+        (data (i32.const 16) "\48\00\00\00\05\00\00\00")
+        (data (i32.const 32) "data.txt")
+        (data (i32.const 64) "hello")
+        (data (i32.const 72) " more")
+
+        (func $check (param $errno i32)
+            (if (local.get $errno)
+                (then (call $proc_exit (i32.add (i32.const 100) (local.get $errno))))
+            )
+        )
+
+        (func $read (param $fd i32) (result i32)
+            (call $check (call $fd_read (local.get $fd) (i32.const 0) (i32.const 1) (i32.const 24)))
+            (i32.load (i32.const 24))
+        )
+
+        (func $main (export "_start")
+            (local $fd i32)
+            (local $nread1 i32)
+            (local $nread2 i32)
+            (local $nread3 i32)
+            (local $nread4 i32)
+
+            (call $check
+                (call $path_open
+                    (i32.const 4)   ;; dirfd
+                    (i32.const 0)   ;; dirflags
+                    (i32.const 32)  ;; path
+                    (i32.const 8)   ;; path_len
+                    (i32.const 1)   ;; oflags (CREAT)
+                    (i64.const 70)  ;; rights_base (FD_READ | FD_SEEK | FD_WRITE)
+                    (i64.const 0)   ;; rights_inheriting
+                    (i32.const 0)   ;; fdflags
+                    (i32.const 28)  ;; fd_out
+                )
+            )
+            (local.set $fd (i32.load (i32.const 28)))
+
+            (call $check (call $fd_write (local.get $fd) (i32.const 8) (i32.const 1) (i32.const 24)))
+            (call $check (call $fd_seek (local.get $fd) (i64.const 0) (i32.const 0) (i32.const 40)))
+
+            ;; Read the whole file, after which every read is at EOF
+            (local.set $nread1 (call $read (local.get $fd)))
+            (local.set $nread2 (call $read (local.get $fd)))
+            (local.set $nread3 (call $read (local.get $fd)))
+
+            ;; Appending to the file makes the new data readable
+            (call $check (call $fd_write (local.get $fd) (i32.const 16) (i32.const 1) (i32.const 24)))
+            (call $check (call $fd_seek (local.get $fd) (i64.const 5) (i32.const 0) (i32.const 40)))
+            (local.set $nread4 (call $read (local.get $fd)))
+            (if (i64.ne (i64.load (i32.const 128)) (i64.const 0x65726f6d20))
+                (then (call $proc_exit (i32.const 1)))
+            )
+
+            (call $proc_exit
+                (i32.or
+                    (i32.or
+                        (i32.shl (local.get $nread1) (i32.const 24))
+                        (i32.shl (local.get $nread2) (i32.const 16))
+                    )
+                    (i32.or
+                        (i32.shl (local.get $nread3) (i32.const 8))
+                        (local.get $nread4)
+                    )
+                )
+            )
+        )
+    )
+    "#,
+    )
+    .unwrap();
+
+    let builder = WasiEnv::builder("command-name")
+        .fs(Box::<mem_fs::FileSystem>::default())
+        .preopen_dir("/")
+        .unwrap();
+
+    #[cfg(feature = "js")]
+    let result = builder.run_with_store(module, &mut store);
+    #[cfg(not(feature = "js"))]
+    let result = std::thread::spawn(move || builder.run_with_store(module, &mut store))
+        .join()
+        .unwrap();
+
+    // Five bytes, then zero bytes twice and finally the five appended bytes
+    let exit_code = result.unwrap_err().as_exit_code().unwrap();
+    assert_eq!(exit_code.raw(), (5 << 24) | 5);
+}
+
+async fn test_fd_read_partial_iovecs_at_eof() {
+    let mut store = Store::default();
+    let module = Module::new(
+        &store,
+        br#"
+    (module
+        (import "wasi_snapshot_preview1" "path_open" (func $path_open (param i32 i32 i32 i32 i32 i64 i64 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "fd_read" (func $fd_read (param i32 i32 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "fd_seek" (func $fd_seek (param i32 i64 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "proc_exit" (func $proc_exit (param i32)))
+
+        (memory 1)
+        (export "memory" (memory 0))
+
+        ;; three io vectors of four bytes each for the read
+        (data (i32.const 0) "\80\00\00\00\04\00\00\00")
+        (data (i32.const 8) "\88\00\00\00\04\00\00\00")
+        (data (i32.const 16) "\90\00\00\00\04\00\00\00")
+        ;; io vector for the write
+        (data (i32.const 24) "\40\00\00\00\06\00\00\00")
+        (data (i32.const 64) "abcdef")
+        (data (i32.const 96) "short.txt")
+        ;; the read buffers start out filled with a marker
+        (data (i32.const 128) "\ff\ff\ff\ff\ff\ff\ff\ff\ff\ff\ff\ff\ff\ff\ff\ff\ff\ff\ff\ff\ff\ff\ff\ff")
+
+        (func $check (param $errno i32)
+            (if (local.get $errno)
+                (then (call $proc_exit (i32.add (i32.const 100) (local.get $errno))))
+            )
+        )
+
+        (func $main (export "_start")
+            (local $fd i32)
+
+            (call $check
+                (call $path_open
+                    (i32.const 4)   ;; dirfd
+                    (i32.const 0)   ;; dirflags
+                    (i32.const 96)  ;; path
+                    (i32.const 9)   ;; path_len
+                    (i32.const 1)   ;; oflags (CREAT)
+                    (i64.const 70)  ;; rights_base (FD_READ | FD_SEEK | FD_WRITE)
+                    (i64.const 0)   ;; rights_inheriting
+                    (i32.const 0)   ;; fdflags
+                    (i32.const 44)  ;; fd_out
+                )
+            )
+            (local.set $fd (i32.load (i32.const 44)))
+
+            (call $check (call $fd_write (local.get $fd) (i32.const 24) (i32.const 1) (i32.const 40)))
+            (call $check (call $fd_seek (local.get $fd) (i64.const 0) (i32.const 0) (i32.const 48)))
+
+            ;; The file runs out halfway through the second io vector
+            (call $check (call $fd_read (local.get $fd) (i32.const 0) (i32.const 3) (i32.const 40)))
+
+            ;; The first io vector is filled completely
+            (if (i32.ne (i32.load (i32.const 128)) (i32.const 0x64636261))
+                (then (call $proc_exit (i32.const 1)))
+            )
+            ;; The second io vector only gets the last two bytes
+            (if (i32.ne (i32.load (i32.const 136)) (i32.const 0xffff6665))
+                (then (call $proc_exit (i32.const 2)))
+            )
+            ;; The third io vector is left untouched
+            (if (i32.ne (i32.load (i32.const 144)) (i32.const 0xffffffff))
+                (then (call $proc_exit (i32.const 3)))
+            )
+
+            (call $proc_exit (i32.load (i32.const 40)))
+        )
+    )
+    "#,
+    )
+    .unwrap();
+
+    let builder = WasiEnv::builder("command-name")
+        .fs(Box::<mem_fs::FileSystem>::default())
+        .preopen_dir("/")
+        .unwrap();
+
+    #[cfg(feature = "js")]
+    let result = builder.run_with_store(module, &mut store);
+    #[cfg(not(feature = "js"))]
+    let result = std::thread::spawn(move || builder.run_with_store(module, &mut store))
+        .join()
+        .unwrap();
+
+    // All six bytes of the file are read without an error
+    let exit_code = result.unwrap_err().as_exit_code().unwrap();
+    assert_eq!(exit_code.raw(), 6);
+}
@@ -0,0 +1,104 @@
+use std::time::{Duration, Instant};
+
+use virtual_fs::{mem_fs, FileSystem};
+use wasmer::{Module, Store};
+use wasmer_wasix::WasiEnv;
+
+mod sys {
+    #[tokio::test]
+    async fn test_fd_readdir_large_directory() {
+        super::test_fd_readdir_large_directory().await;
+    }
+}
+
+const FILES: usize = 10_000;
+
+async fn test_fd_readdir_large_directory() {
+    let mut store = Store::default();
+    let module = Module::new(&store, br#"
+    (module
+        (import "wasi_snapshot_preview1" "fd_readdir" (func $fd_readdir (param i32 i32 i32 i64 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "proc_exit" (func $proc_exit (param i32)))
+
+        (memory 1)
+        (export "memory" (memory 0))
+
+        (func $main (export "_start")
+            (local $cookie i64)
+            (local $used i32)
+            (local $pos i32)
+            (local $namlen i32)
+            (local $count i32)
+
+            ;; Read the directory in chunks of 1024 bytes and count the entries
+            (loop $next_chunk
+                (call $fd_readdir
+                    (i32.const 4)    ;; fd
+                    (i32.const 1024) ;; buf
+                    (i32.const 1024) ;; buf_len
+                    (local.get $cookie)
+                    (i32.const 16)   ;; bufused
+                )
+                (if (then unreachable))
+                (local.set $used (i32.load (i32.const 16)))
+                (local.set $pos (i32.const 0))
+
+                (block $chunk_done
+                    (loop $next_entry
+                        ;; Entries that were cut off are read again in the next chunk
+                        (br_if $chunk_done
+                            (i32.gt_u (i32.add (local.get $pos) (i32.const 24)) (local.get $used)))
+                        (local.set $namlen (i32.load offset=1040 (local.get $pos)))
+                        (br_if $chunk_done
+                            (i32.gt_u
+                                (i32.add (i32.add (local.get $pos) (i32.const 24)) (local.get $namlen))
+                                (local.get $used)))
+
+                        (local.set $cookie (i64.load offset=1024 (local.get $pos)))
+                        (local.set $count (i32.add (local.get $count) (i32.const 1)))
+                        (local.set $pos
+                            (i32.add (local.get $pos) (i32.add (i32.const 24) (local.get $namlen))))
+                        (br $next_entry)
+                    )
+                )
+
+                ;; A full buffer means there may be more entries
+                (br_if $next_chunk (i32.eq (local.get $used) (i32.const 1024)))
+            )
+
+            (call $proc_exit (local.get $count))
+        )
+    )
+    "#).unwrap();
+
+    let fs = mem_fs::FileSystem::default();
+    for i in 0..FILES {
+        fs.new_open_options()
+            .write(true)
+            .create(true)
+            .open(format!("/file-{i:05}"))
+            .unwrap();
+    }
+
+    let builder = WasiEnv::builder("command-name")
+        .fs(Box::new(fs))
+        .preopen_dir("/")
+        .unwrap();
+
+    let started = Instant::now();
+    #[cfg(feature = "js")]
+    let result = builder.run_with_store(module, &mut store);
+    #[cfg(not(feature = "js"))]
+    let result = std::thread::spawn(move || builder.run_with_store(module, &mut store))
+        .join()
+        .unwrap();
+    let elapsed = started.elapsed();
+
+    // Every file plus `.` and `..`
+    let exit_code = result.unwrap_err().as_exit_code().unwrap();
+    assert_eq!(exit_code.raw(), FILES as i32 + 2);
+    assert!(
+        elapsed < Duration::from_secs(10),
+        "reading the directory took {elapsed:?}"
+    );
+}
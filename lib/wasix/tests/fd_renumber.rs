@@ -0,0 +1,62 @@
+use virtual_fs::mem_fs;
+use wasmer::{Module, Store};
+use wasmer_wasix::WasiEnv;
+
+mod sys {
+    #[tokio::test]
+    async fn test_fd_renumber_preopen() {
+        super::test_fd_renumber_preopen().await;
+    }
+}
+
+async fn test_fd_renumber_preopen() {
+    let mut store = Store::default();
+    let module = Module::new(&store, br#"
+    (module
+        (import "wasi_snapshot_preview1" "fd_renumber" (func $fd_renumber (param i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "fd_prestat_get" (func $fd_prestat_get (param i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "fd_prestat_dir_name" (func $fd_prestat_dir_name (param i32 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "proc_exit" (func $proc_exit (param i32)))
+
+        (memory 1)
+        (export "memory" (memory 0))
+
+        (func $main (export "_start")
+            ;; Move the preopen to another descriptor
+            (call $fd_renumber (i32.const 4) (i32.const 10))
+            (if (then unreachable))
+
+            ;; The new descriptor is reported as a preopened directory
+            (call $fd_prestat_get (i32.const 10) (i32.const 0))
+            (if (then unreachable))
+            (i32.load8_u (i32.const 0)) ;; pr_type
+            (if (then unreachable))     ;; Preopentype::Dir
+            (call $fd_prestat_dir_name (i32.const 10) (i32.const 16) (i32.load (i32.const 4)))
+            (if (then unreachable))
+            (i32.load8_u (i32.const 16))
+            (i32.const 47) ;; '/'
+            (i32.ne)
+            (if (then unreachable))
+
+            ;; The old descriptor is gone
+            (call $proc_exit (call $fd_prestat_get (i32.const 4) (i32.const 0)))
+        )
+    )
+    "#).unwrap();
+
+    let builder = WasiEnv::builder("command-name")
+        .fs(Box::<mem_fs::FileSystem>::default())
+        .preopen_dir("/")
+        .unwrap();
+
+    #[cfg(feature = "js")]
+    let result = builder.run_with_store(module, &mut store);
+    #[cfg(not(feature = "js"))]
+    let result = std::thread::spawn(move || builder.run_with_store(module, &mut store))
+        .join()
+        .unwrap();
+
+    // Errno::Badf
+    let exit_code = result.unwrap_err().as_exit_code().unwrap();
+    assert_eq!(exit_code.raw(), 8);
+}
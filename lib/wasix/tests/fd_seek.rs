@@ -0,0 +1,56 @@
+use wasmer::{Module, Store};
+use wasmer_wasix::WasiEnv;
+
+mod sys {
+    #[tokio::test]
+    async fn test_fd_seek_on_pipe() {
+        super::test_fd_seek_on_pipe().await;
+    }
+}
+
+async fn test_fd_seek_on_pipe() {
+    let mut store = Store::default();
+    let module = Module::new(
+        &store,
+        br#"
+    (module
+        (import "wasix_32v1" "fd_pipe" (func $fd_pipe (param i32 i32) (result i32)))
+        (import "wasix_32v1" "fd_seek" (func $fd_seek (param i32 i64 i32 i32) (result i32)))
+        (import "wasix_32v1" "proc_exit" (func $proc_exit (param i32)))
+
+        (memory 1)
+        (export "memory" (memory 0))
+
+        (func $main (export "_start")
+            (call $fd_pipe (i32.const 0) (i32.const 4))
+            drop
+
+            ;; Seek both ends of the pipe and report the errnos in the exit code
+            (call $proc_exit
+                (i32.or
+                    (i32.shl
+                        (call $fd_seek (i32.load (i32.const 0)) (i64.const 0) (i32.const 0) (i32.const 16))
+                        (i32.const 8)
+                    )
+                    (call $fd_seek (i32.load (i32.const 4)) (i64.const 0) (i32.const 1) (i32.const 16))
+                )
+            )
+        )
+    )
+    "#,
+    )
+    .unwrap();
+
+    let builder = WasiEnv::builder("command-name");
+
+    #[cfg(feature = "js")]
+    let result = builder.run_with_store(module, &mut store);
+    #[cfg(not(feature = "js"))]
+    let result = std::thread::spawn(move || builder.run_with_store(module, &mut store))
+        .join()
+        .unwrap();
+
+    // Errno::Spipe for both ends
+    let exit_code = result.unwrap_err().as_exit_code().unwrap();
+    assert_eq!(exit_code.raw(), (70 << 8) | 70);
+}
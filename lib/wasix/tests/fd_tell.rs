@@ -0,0 +1,83 @@
+use virtual_fs::mem_fs;
+use wasmer::{Module, Store};
+use wasmer_wasix::WasiEnv;
+
+mod sys {
+    #[tokio::test]
+    async fn test_fd_tell_after_append() {
+        super::test_fd_tell_after_append().await;
+    }
+}
+
+async fn test_fd_tell_after_append() {
+    let mut store = Store::default();
+    let module = Module::new(&store, br#"
+    (module
+        (import "wasi_snapshot_preview1" "path_open" (func $path_open (param i32 i32 i32 i32 i32 i64 i64 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "fd_tell" (func $fd_tell (param i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "proc_exit" (func $proc_exit (param i32)))
+
+        (memory 1)
+        (export "memory" (memory 0))
+
+        (data (i32.const 32) "log")
+        (data (i32.const 48) "hello")
+        (data (i32.const 64) "ab")
+
+        (func $main (export "_start")
+            ;; Create the file 'log' containing 'hello'
+            (call $path_open
+                (i32.const 4)   ;; dirfd
+                (i32.const 0)   ;; dirflags
+                (i32.const 32)  ;; path
+                (i32.const 3)   ;; path_len
+                (i32.const 1)   ;; oflags (CREATE)
+                (i64.const 98)  ;; rights_base (FD_READ | FD_TELL | FD_WRITE)
+                (i64.const 0)   ;; rights_inheriting
+                (i32.const 0)   ;; fdflags
+                (i32.const 100) ;; fd_out
+            )
+            drop
+            (i32.store (i32.const 0) (i32.const 48))
+            (i32.store (i32.const 4) (i32.const 5))
+            (call $fd_write (i32.load (i32.const 100)) (i32.const 0) (i32.const 1) (i32.const 8))
+            drop
+
+            ;; Reopen it for appending, the cursor starts at zero
+            (call $path_open
+                (i32.const 4) (i32.const 0) (i32.const 32) (i32.const 3)
+                (i32.const 0) (i64.const 98) (i64.const 0)
+                (i32.const 1)   ;; fdflags (APPEND)
+                (i32.const 104)
+            )
+            drop
+            (i32.store (i32.const 0) (i32.const 64))
+            (i32.store (i32.const 4) (i32.const 2))
+            (call $fd_write (i32.load (i32.const 104)) (i32.const 0) (i32.const 1) (i32.const 8))
+            drop
+
+            ;; Report the position of the appending descriptor as the exit code
+            (call $fd_tell (i32.load (i32.const 104)) (i32.const 112))
+            drop
+            (call $proc_exit (i32.wrap_i64 (i64.load (i32.const 112))))
+        )
+    )
+    "#).unwrap();
+
+    let builder = WasiEnv::builder("command-name")
+        .fs(Box::<mem_fs::FileSystem>::default())
+        .preopen_dir("/")
+        .unwrap();
+
+    #[cfg(feature = "js")]
+    let result = builder.run_with_store(module, &mut store);
+    #[cfg(not(feature = "js"))]
+    let result = std::thread::spawn(move || builder.run_with_store(module, &mut store))
+        .join()
+        .unwrap();
+
+    // The size of the file after appending
+    let exit_code = result.unwrap_err().as_exit_code().unwrap();
+    assert_eq!(exit_code.raw(), 7);
+}
@@ -0,0 +1,285 @@
+use std::{
+    path::Path,
+    sync::{Arc, Barrier},
+};
+
+use virtual_fs::{mem_fs, AsyncReadExt, FileSystem};
+use wasmer::{Engine, Instance, Module, Store, Value};
+use wasmer_wasix::{
+    types::{wasi::Errno, Signal},
+    Pipe, WasiEnv, WasiFunctionEnv,
+};
+
+mod sys {
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_concurrent_writes_do_not_interleave() {
+        super::test_concurrent_writes_do_not_interleave().await;
+    }
+
+    #[tokio::test]
+    async fn test_write_blocks_on_full_pipe() {
+        super::test_write_blocks_on_full_pipe().await;
+    }
+
+    #[tokio::test]
+    async fn test_signal_interrupts_write_on_full_pipe() {
+        super::test_signal_interrupts_write_on_full_pipe().await;
+    }
+}
+
+const WRITES: usize = 1000;
+
+/// Module that opens `out` and writes `marker` to it a number of times,
+/// each write is split over several io vectors
+fn marker_module(marker: char) -> String {
+    let marker = marker.to_string().repeat(4);
+    format!(
+        r#"
+    (module
+        (import "wasi_snapshot_preview1" "path_open" (func $path_open (param i32 i32 i32 i32 i32 i64 i64 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+
+        (memory 1)
+        (export "memory" (memory 0))
+
+        (data (i32.const 32) "{marker}{marker}\n")
+        (data (i32.const 48) "out")
+
+        (func (export "open") (result i32)
+            (call $path_open
+                (i32.const 4)   ;; dirfd
+                (i32.const 0)   ;; dirflags
+                (i32.const 48)  ;; path
+                (i32.const 3)   ;; path_len
+                (i32.const 1)   ;; oflags (creat)
+                (i64.const -1)  ;; rights_base
+                (i64.const -1)  ;; rights_inheriting
+                (i32.const 0)   ;; fdflags
+                (i32.const 100) ;; fd_out
+            )
+            drop
+            (i32.load (i32.const 100))
+        )
+
+        (func (export "write") (param $fd i32)
+            (local $i i32)
+            (i32.store (i32.const 0) (i32.const 32))
+            (i32.store (i32.const 4) (i32.const 4))
+            (i32.store (i32.const 8) (i32.const 36))
+            (i32.store (i32.const 12) (i32.const 4))
+            (i32.store (i32.const 16) (i32.const 40))
+            (i32.store (i32.const 20) (i32.const 1))
+            (loop $again
+                (call $fd_write (local.get $fd) (i32.const 0) (i32.const 3) (i32.const 64))
+                drop
+                (local.set $i (i32.add (local.get $i) (i32.const 1)))
+                (br_if $again (i32.lt_u (local.get $i) (i32.const {WRITES})))
+            )
+        )
+    )
+    "#
+    )
+}
+
+/// Instantiates the marker module on a store of its own against the shared
+/// environment
+fn instantiate_marker_module(engine: Engine, env: WasiEnv, marker: char) -> (Store, Instance) {
+    let mut store = Store::new(engine);
+    let module = Module::new(&store, marker_module(marker)).unwrap();
+
+    let mut func_env = WasiFunctionEnv::new(&mut store, env);
+    let imports = func_env.import_object(&mut store, &module).unwrap();
+    let instance = Instance::new(&mut store, &module, &imports).unwrap();
+    func_env.initialize(&mut store, instance.clone()).unwrap();
+    (store, instance)
+}
+
+async fn test_concurrent_writes_do_not_interleave() {
+    let fs = mem_fs::FileSystem::default();
+
+    let mut store = Store::default();
+    let engine = store.engine().clone();
+    let func_env = WasiEnv::builder("command-name")
+        .fs(Box::new(fs.clone()))
+        .preopen_dir("/")
+        .unwrap()
+        .finalize(&mut store)
+        .unwrap();
+    let env = func_env.data(&store).clone();
+
+    let handle = tokio::runtime::Handle::current();
+    let fd = {
+        let engine = engine.clone();
+        let env = env.clone();
+        let handle = handle.clone();
+        std::thread::spawn(move || {
+            let _guard = handle.enter();
+            let (mut store, instance) = instantiate_marker_module(engine, env, 'A');
+            let open = instance.exports.get_function("open").unwrap();
+            open.call(&mut store, &[]).unwrap()[0].unwrap_i32()
+        })
+        .join()
+        .unwrap()
+    };
+
+    // Both threads write through the same file descriptor at the same time
+    let barrier = Arc::new(Barrier::new(2));
+    let threads = ['A', 'B']
+        .into_iter()
+        .map(|marker| {
+            let engine = engine.clone();
+            let env = env.clone();
+            let handle = handle.clone();
+            let barrier = barrier.clone();
+            std::thread::spawn(move || {
+                let _guard = handle.enter();
+                let (mut store, instance) = instantiate_marker_module(engine, env, marker);
+                let write = instance.exports.get_function("write").unwrap();
+                barrier.wait();
+                write.call(&mut store, &[Value::I32(fd)]).unwrap();
+            })
+        })
+        .collect::<Vec<_>>();
+    for thread in threads {
+        thread.join().unwrap();
+    }
+
+    let mut file = fs
+        .new_open_options()
+        .read(true)
+        .open(Path::new("/out"))
+        .unwrap();
+    let mut out = String::new();
+    file.read_to_string(&mut out).await.unwrap();
+
+    // Every write must show up as a whole line and none of them may be
+    // overwritten by the other thread
+    for line in out.lines() {
+        assert!(
+            line == "AAAAAAAA" || line == "BBBBBBBB",
+            "interleaved write: {line:?}"
+        );
+    }
+    assert_eq!(out.lines().filter(|l| l.starts_with('A')).count(), WRITES);
+    assert_eq!(out.lines().filter(|l| l.starts_with('B')).count(), WRITES);
+}
+
+async fn test_write_blocks_on_full_pipe() {
+    let mut store = Store::default();
+    let module = Module::new(&store, br#"
+    (module
+        (import "wasi_snapshot_preview1" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "proc_exit" (func $proc_exit (param i32)))
+
+        (memory 1)
+        (export "memory" (memory 0))
+
+        (data (i32.const 32) "abcdefgh")
+
+        (func $main (export "_start")
+            (local $written i32)
+
+            ;; The first write fills the pipe
+            (i32.store (i32.const 0) (i32.const 32))
+            (i32.store (i32.const 4) (i32.const 4))
+            (call $fd_write (i32.const 1) (i32.const 0) (i32.const 1) (i32.const 16))
+            drop
+            (local.set $written (i32.load (i32.const 16)))
+
+            ;; The second write waits until the reader drained the pipe
+            (i32.store (i32.const 0) (i32.const 36))
+            (call $fd_write (i32.const 1) (i32.const 0) (i32.const 1) (i32.const 16))
+            drop
+
+            (call $proc_exit (i32.add (local.get $written) (i32.load (i32.const 16))))
+        )
+    )
+    "#).unwrap();
+
+    let (stdout_tx, mut stdout_rx) = Pipe::bounded_channel(4);
+
+    let builder = WasiEnv::builder("command-name").stdout(Box::new(stdout_tx));
+
+    let handle = tokio::runtime::Handle::current();
+    let guest = std::thread::spawn(move || {
+        let _guard = handle.enter();
+        builder.run_with_store(module, &mut store)
+    });
+
+    // The writer stays blocked as long as nobody reads from the pipe
+    std::thread::sleep(std::time::Duration::from_millis(200));
+    assert!(!guest.is_finished());
+
+    let mut stdout = [0u8; 8];
+    stdout_rx.read_exact(&mut stdout).await.unwrap();
+    assert_eq!(&stdout, b"abcdefgh");
+
+    let exit_code = guest.join().unwrap().unwrap_err().as_exit_code().unwrap();
+    assert_eq!(exit_code.raw(), 8);
+}
+
+async fn test_signal_interrupts_write_on_full_pipe() {
+    let mut store = Store::default();
+    let module = Module::new(&store, br#"
+    (module
+        (import "wasi_snapshot_preview1" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+
+        (memory 1)
+        (export "memory" (memory 0))
+
+        (data (i32.const 32) "abcdefgh")
+
+        (func $main (export "main") (result i32)
+            ;; The first write fills the pipe
+            (i32.store (i32.const 0) (i32.const 32))
+            (i32.store (i32.const 4) (i32.const 4))
+            (call $fd_write (i32.const 1) (i32.const 0) (i32.const 1) (i32.const 16))
+            drop
+
+            ;; The second write waits for room until a signal arrives
+            (i32.store (i32.const 0) (i32.const 36))
+            (call $fd_write (i32.const 1) (i32.const 0) (i32.const 1) (i32.const 16))
+        )
+    )
+    "#).unwrap();
+
+    let (stdout_tx, mut stdout_rx) = Pipe::bounded_channel(4);
+    let mut func_env = WasiEnv::builder("command-name")
+        .stdout(Box::new(stdout_tx))
+        .finalize(&mut store)
+        .unwrap();
+    let thread = func_env.data(&store).thread.clone();
+
+    let handle = tokio::runtime::Handle::current();
+    let guest = std::thread::spawn(move || {
+        let _guard = handle.enter();
+        let imports = func_env.import_object(&mut store, &module).unwrap();
+        let instance = Instance::new(&mut store, &module, &imports).unwrap();
+        func_env.initialize(&mut store, instance.clone()).unwrap();
+
+        let main = instance.exports.get_function("main").unwrap();
+        main.call(&mut store, &[]).unwrap()[0].unwrap_i32()
+    });
+
+    // The writer stays blocked as long as nobody reads from the pipe
+    std::thread::sleep(std::time::Duration::from_millis(200));
+    assert!(!guest.is_finished());
+
+    // The signal wakes it up again
+    thread.signal(Signal::Sigusr1);
+    let deadline = std::time::Instant::now() + std::time::Duration::from_secs(5);
+    while !guest.is_finished() {
+        assert!(
+            std::time::Instant::now() < deadline,
+            "the write was not interrupted"
+        );
+        std::thread::sleep(std::time::Duration::from_millis(10));
+    }
+    let errno = guest.join().unwrap();
+    assert_eq!(errno, Errno::Intr as i32);
+
+    // Only the first write made it into the pipe
+    let mut stdout = [0u8; 4];
+    stdout_rx.read_exact(&mut stdout).await.unwrap();
+    assert_eq!(&stdout, b"abcd");
+}
@@ -0,0 +1,123 @@
+#![cfg(not(feature = "js"))]
+
+use std::time::{Duration, Instant};
+
+use wasmer::{Instance, Module, Store};
+use wasmer_wasix::WasiEnv;
+
+mod sys {
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_futex_stats() {
+        super::test_futex_stats().await;
+    }
+}
+
+const FUTEX: u64 = 256;
+const WAITERS: u64 = 3;
+const HOLD: Duration = Duration::from_millis(50);
+
+/// Calls the exported function `name` until it returns `expected`
+fn wait_for(instance: &Instance, store: &mut Store, name: &str, expected: i32) {
+    let func = instance.exports.get_function(name).unwrap();
+    let deadline = Instant::now() + Duration::from_secs(10);
+    loop {
+        let ret = func.call(store, &[]).unwrap()[0].unwrap_i32();
+        if ret == expected {
+            return;
+        }
+        assert!(Instant::now() < deadline, "timed out waiting for `{name}`");
+        std::thread::sleep(Duration::from_millis(10));
+    }
+}
+
+async fn test_futex_stats() {
+    let mut store = Store::default();
+    let module = Module::new(
+        &store,
+        br#"
+    (module
+        (import "env" "memory" (memory 2 2 shared))
+        (import "wasix_32v1" "thread_spawn_v2" (func $thread_spawn (param i32 i32) (result i32)))
+        (import "wasix_32v1" "futex_wait" (func $futex_wait (param i32 i32 i32 i32) (result i32)))
+        (import "wasix_32v1" "futex_wake" (func $futex_wake (param i32 i32) (result i32)))
+
+        ;; Every thread sleeps on the futex at 256 (the timeout at 512 is
+        ;; none) and counts itself as done once it is woken
+        (func (export "wasi_thread_start") (param $tid i32) (param $start i32)
+            (if (call $futex_wait (i32.const 256) (i32.const 0) (i32.const 512) (i32.const 640))
+                (then unreachable)
+            )
+            (drop (i32.atomic.rmw.add (i32.const 128) (i32.const 1)))
+        )
+
+        (func $spawn (param $start i32) (param $stack_upper i32)
+            (i32.store (local.get $start) (local.get $stack_upper))              ;; stack_upper
+            (i32.store (i32.add (local.get $start) (i32.const 56)) (i32.const 4096)) ;; stack_size
+            (if (call $thread_spawn (local.get $start) (i32.const 64))
+                (then unreachable)
+            )
+        )
+
+        (func (export "_start")
+            ;; Stacks of 4KiB each right below 64KiB
+            (call $spawn (i32.const 1024) (i32.const 65536))
+            (call $spawn (i32.const 2048) (i32.const 61440))
+            (call $spawn (i32.const 3072) (i32.const 57344))
+        )
+
+        ;; Wakes a single waiter
+        (func (export "wake") (result i32)
+            (if (call $futex_wake (i32.const 256) (i32.const 768))
+                (then unreachable)
+            )
+            (i32.const 0)
+        )
+        (func (export "done") (result i32)
+            (i32.atomic.load (i32.const 128))
+        )
+    )
+    "#,
+    )
+    .unwrap();
+
+    let builder = WasiEnv::builder("command-name");
+
+    let handle = tokio::runtime::Handle::current();
+    std::thread::spawn(move || {
+        let _guard = handle.enter();
+        let (instance, env) = builder.instantiate(module, &mut store).unwrap();
+        let process = env.data(&store).process.clone();
+        assert!(process.futex_stats().is_empty());
+
+        let start = instance.exports.get_function("_start").unwrap();
+        start.call(&mut store, &[]).unwrap();
+
+        // Wait for all the threads to go to sleep on the futex
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while process.futex_stats().get(&FUTEX).map(|stats| stats.waits) != Some(WAITERS) {
+            assert!(Instant::now() < deadline, "the threads did not wait");
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        std::thread::sleep(HOLD);
+
+        // Wake them one at a time
+        let wake = instance.exports.get_function("wake").unwrap();
+        for _ in 0..WAITERS {
+            wake.call(&mut store, &[]).unwrap();
+        }
+        wait_for(&instance, &mut store, "done", WAITERS as i32);
+
+        let stats = process.futex_stats();
+        assert_eq!(stats.len(), 1);
+        let stats = stats[&FUTEX];
+        assert_eq!(stats.waits, WAITERS);
+        assert_eq!(stats.wakes, WAITERS);
+        assert!(
+            stats.total_wait_time >= HOLD * WAITERS as u32,
+            "{:?}",
+            stats.total_wait_time
+        );
+    })
+    .join()
+    .unwrap();
+}
@@ -0,0 +1,177 @@
+use virtual_fs::{AsyncReadExt, AsyncWriteExt};
+use wasmer::{Function, FunctionEnvMut, Instance, Memory32, Module, Store, WasmPtr};
+use wasmer_wasix::{Pipe, WasiEnv};
+
+mod sys {
+    #[tokio::test]
+    async fn test_host_function_writes_to_stdout() {
+        super::test_host_function_writes_to_stdout().await;
+    }
+    #[tokio::test]
+    async fn test_host_function_reads_from_stdin() {
+        super::test_host_function_reads_from_stdin().await;
+    }
+}
+
+/// Host function that reads from the guest stdin into its memory
+fn host_read(mut ctx: FunctionEnvMut<'_, WasiEnv>, ptr: u32, len: u32) -> i32 {
+    match WasiEnv::fd_read_to_guest::<Memory32>(&mut ctx, 0, WasmPtr::new(ptr), len) {
+        Ok(Ok(read)) => read as i32,
+        Ok(Err(err)) => -(err as i32),
+        Err(_) => -1,
+    }
+}
+
+/// Host function that writes a line from the guest memory to its stdout
+fn host_log(ctx: FunctionEnvMut<'_, WasiEnv>, ptr: u32, len: u32) -> i32 {
+    let written = match WasiEnv::fd_write_from_guest::<Memory32>(&ctx, 1, WasmPtr::new(ptr), len) {
+        Ok(Ok(written)) => written,
+        Ok(Err(err)) => return -(err as i32),
+        Err(_) => return -1,
+    };
+    match WasiEnv::fd_write_from_host(&ctx, 1, b"\n") {
+        Ok(Ok(_)) => written as i32,
+        Ok(Err(err)) => -(err as i32),
+        Err(_) => -1,
+    }
+}
+
+async fn test_host_function_writes_to_stdout() {
+    let mut store = Store::default();
+    let module = Module::new(&store, br#"
+    (module
+        (import "wasi_snapshot_preview1" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+        (import "env" "log" (func $log (param i32 i32) (result i32)))
+
+        (memory 1)
+        (export "memory" (memory 0))
+
+        (data (i32.const 8) "hello world")
+        (data (i32.const 32) "from the guest")
+
+        (func $main (export "_start")
+            ;; The host writes the message to stdout on our behalf
+            (call $log (i32.const 8) (i32.const 11))
+            drop
+
+            ;; Regions outside of the memory are rejected
+            (call $log (i32.const 65530) (i32.const 11))
+            (i32.const -21) ;; -Errno::Fault
+            (i32.ne)
+            (if (then unreachable))
+
+            ;; Writes of the guest and the host share the same descriptor
+            (i32.store (i32.const 0) (i32.const 32))
+            (i32.store (i32.const 4) (i32.const 14))
+            (call $fd_write (i32.const 1) (i32.const 0) (i32.const 1) (i32.const 48))
+            drop
+        )
+    )
+    "#).unwrap();
+
+    let (stdout_tx, mut stdout_rx) = Pipe::channel();
+
+    let builder = WasiEnv::builder("command-name").stdout(Box::new(stdout_tx));
+
+    let handle = tokio::runtime::Handle::current();
+    let run = move || {
+        let _guard = handle.enter();
+        let mut wasi_env = builder.finalize(&mut store).unwrap();
+
+        let mut imports = wasi_env.import_object(&mut store, &module).unwrap();
+        let log = Function::new_typed_with_env(&mut store, &wasi_env.env, host_log);
+        imports.define("env", "log", log);
+
+        let instance = Instance::new(&mut store, &module, &imports).unwrap();
+        wasi_env.initialize(&mut store, instance.clone()).unwrap();
+
+        let start = instance.exports.get_function("_start").unwrap();
+        let result = start.call(&mut store, &[]);
+        wasi_env.on_exit(&mut store, None);
+        result
+    };
+
+    #[cfg(feature = "js")]
+    run().unwrap();
+    #[cfg(not(feature = "js"))]
+    std::thread::spawn(run).join().unwrap().unwrap();
+
+    let mut stdout_str = String::new();
+    stdout_rx.read_to_string(&mut stdout_str).await.unwrap();
+    assert_eq!(stdout_str, "hello world\nfrom the guest");
+}
+
+async fn test_host_function_reads_from_stdin() {
+    let mut store = Store::default();
+    let module = Module::new(&store, br#"
+    (module
+        (import "wasi_snapshot_preview1" "fd_read" (func $fd_read (param i32 i32 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+        (import "env" "read" (func $read (param i32 i32) (result i32)))
+
+        (memory 1)
+        (export "memory" (memory 0))
+
+        (func $main (export "_start")
+            ;; Regions outside of the memory are rejected before reading
+            (call $read (i32.const 65530) (i32.const 11))
+            (i32.const -21) ;; -Errno::Fault
+            (i32.ne)
+            (if (then unreachable))
+
+            ;; The host reads the first 6 bytes on our behalf
+            (call $read (i32.const 64) (i32.const 6))
+            (i32.const 6)
+            (i32.ne)
+            (if (then unreachable))
+
+            ;; Reads of the guest and the host share the same descriptor
+            (i32.store (i32.const 0) (i32.const 70))
+            (i32.store (i32.const 4) (i32.const 5))
+            (call $fd_read (i32.const 0) (i32.const 0) (i32.const 1) (i32.const 48))
+            drop
+
+            (i32.store (i32.const 0) (i32.const 64))
+            (i32.store (i32.const 4) (i32.add (i32.load (i32.const 48)) (i32.const 6)))
+            (call $fd_write (i32.const 1) (i32.const 0) (i32.const 1) (i32.const 48))
+            drop
+        )
+    )
+    "#).unwrap();
+
+    let (mut stdin_tx, stdin_rx) = Pipe::channel();
+    let (stdout_tx, mut stdout_rx) = Pipe::channel();
+    stdin_tx.write_all(b"hello world").await.unwrap();
+    drop(stdin_tx);
+
+    let builder = WasiEnv::builder("command-name")
+        .stdin(Box::new(stdin_rx))
+        .stdout(Box::new(stdout_tx));
+
+    let handle = tokio::runtime::Handle::current();
+    let run = move || {
+        let _guard = handle.enter();
+        let mut wasi_env = builder.finalize(&mut store).unwrap();
+
+        let mut imports = wasi_env.import_object(&mut store, &module).unwrap();
+        let read = Function::new_typed_with_env(&mut store, &wasi_env.env, host_read);
+        imports.define("env", "read", read);
+
+        let instance = Instance::new(&mut store, &module, &imports).unwrap();
+        wasi_env.initialize(&mut store, instance.clone()).unwrap();
+
+        let start = instance.exports.get_function("_start").unwrap();
+        let result = start.call(&mut store, &[]);
+        wasi_env.on_exit(&mut store, None);
+        result
+    };
+
+    #[cfg(feature = "js")]
+    run().unwrap();
+    #[cfg(not(feature = "js"))]
+    std::thread::spawn(run).join().unwrap().unwrap();
+
+    let mut stdout_str = String::new();
+    stdout_rx.read_to_string(&mut stdout_str).await.unwrap();
+    assert_eq!(stdout_str, "hello world");
+}
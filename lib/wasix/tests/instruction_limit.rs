@@ -0,0 +1,136 @@
+// Metering needs a compiler middleware, which is not available on wasm targets
+#![cfg(all(feature = "metering", not(target_family = "wasm")))]
+
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use wasmer::{CompilerConfig, Cranelift, Module, Store};
+use wasmer_middlewares::Metering;
+use wasmer_wasix::{WasiEnv, WasiError, WasiRuntimeError};
+
+mod sys {
+    #[tokio::test]
+    async fn test_instruction_limit() {
+        super::test_instruction_limit().await;
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_instruction_limit_is_shared_with_threads() {
+        super::test_instruction_limit_is_shared_with_threads().await;
+    }
+}
+
+async fn test_instruction_limit() {
+    let mut compiler = Cranelift::default();
+    compiler.push_middleware(Arc::new(Metering::new(u64::MAX, |_| 1)));
+    let mut store = Store::new(compiler);
+    let module = Module::new(
+        &store,
+        br#"
+    (module
+        (import "wasi_snapshot_preview1" "proc_exit" (func $proc_exit (param i32)))
+
+        (memory 1)
+        (export "memory" (memory 0))
+
+        (func $main (export "_start")
+            (local $i i32)
+
+            ;; Count to a million, which takes way more than the budget
+            (loop $again
+                (local.set $i (i32.add (local.get $i) (i32.const 1)))
+                (br_if $again (i32.lt_u (local.get $i) (i32.const 1000000)))
+            )
+            (call $proc_exit (i32.const 0))
+        )
+    )
+    "#,
+    )
+    .unwrap();
+
+    let builder = WasiEnv::builder("command-name").instruction_limit(1000);
+
+    let result = std::thread::spawn(move || builder.run_with_store(module, &mut store))
+        .join()
+        .unwrap();
+
+    assert!(
+        matches!(
+            result,
+            Err(WasiRuntimeError::Wasi(WasiError::InstructionLimitExceeded))
+        ),
+        "{result:?}"
+    );
+}
+
+async fn test_instruction_limit_is_shared_with_threads() {
+    let mut compiler = Cranelift::default();
+    compiler.push_middleware(Arc::new(Metering::new(u64::MAX, |_| 1)));
+    let mut store = Store::new(compiler);
+    let module = Module::new(
+        &store,
+        br#"
+    (module
+        (import "env" "memory" (memory 2 2 shared))
+        (import "wasi" "thread-spawn" (func $thread_spawn (param i32) (result i32)))
+
+        ;; The thread stores 1 at 128 when it starts and 2 once it counted
+        ;; to 100000, which takes most of the budget of the whole process
+        (func (export "wasi_thread_start") (param $tid i32) (param $start i32)
+            (local $i i32)
+            (i32.atomic.store (i32.const 128) (i32.const 1))
+            (loop $again
+                (local.set $i (i32.add (local.get $i) (i32.const 1)))
+                (br_if $again (i32.lt_u (local.get $i) (i32.const 100000)))
+            )
+            (i32.atomic.store (i32.const 128) (i32.const 2))
+        )
+
+        ;; Spawns a thread with a 4KiB stack right below 64KiB
+        (func (export "spawn") (result i32)
+            (i32.store (i32.const 1024) (i32.const 65536))           ;; stack_upper
+            (i32.store (i32.add (i32.const 1024) (i32.const 56)) (i32.const 4096)) ;; stack_size
+            (call $thread_spawn (i32.const 1024))
+        )
+        (func (export "progress") (result i32)
+            (i32.atomic.load (i32.const 128))
+        )
+    )
+    "#,
+    )
+    .unwrap();
+
+    let builder = WasiEnv::builder("command-name").instruction_limit(1_000_000);
+
+    let handle = tokio::runtime::Handle::current();
+    std::thread::spawn(move || {
+        let _guard = handle.enter();
+        let (instance, env) = builder.instantiate(module, &mut store).unwrap();
+        let process = env.data(&store).process.clone();
+
+        let spawn = instance.exports.get_function("spawn").unwrap();
+        let tid = spawn.call(&mut store, &[]).unwrap()[0].unwrap_i32();
+        assert!(tid > 0);
+
+        // The thread only gets a share of the budget so it is stopped before
+        // it finishes counting
+        let progress = instance.exports.get_function("progress").unwrap();
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while process.active_threads() > 1 {
+            assert_ne!(
+                progress.call(&mut store, &[]).unwrap()[0].unwrap_i32(),
+                2,
+                "the thread used more than its share of the budget"
+            );
+            assert!(Instant::now() < deadline, "the thread was not stopped");
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(progress.call(&mut store, &[]).unwrap()[0].unwrap_i32(), 1);
+        // Running out of budget is not a crash of the thread
+        assert!(process.crashed_threads().is_empty());
+    })
+    .join()
+    .unwrap();
+}
@@ -0,0 +1,104 @@
+#![cfg(all(feature = "host-fs", not(feature = "js")))]
+
+use std::path::PathBuf;
+
+use wasmer::{Module, Store};
+use wasmer_wasix::WasiEnv;
+
+mod sys {
+    #[tokio::test]
+    async fn test_mount_translated_tenant_dirs() {
+        super::test_mount_translated_tenant_dirs().await;
+    }
+}
+
+async fn test_mount_translated_tenant_dirs() {
+    let host = tempfile::TempDir::new().unwrap();
+    std::fs::create_dir(host.path().join("tenant-42")).unwrap();
+    std::fs::create_dir(host.path().join("tenant-7")).unwrap();
+    std::fs::write(host.path().join("tenant-42").join("hello.txt"), "hello").unwrap();
+    std::fs::write(host.path().join("secret.txt"), "secret").unwrap();
+
+    let mut store = Store::default();
+    let module = Module::new(&store, br#"
+    (module
+        (import "wasi_snapshot_preview1" "path_open" (func $path_open (param i32 i32 i32 i32 i32 i64 i64 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "fd_read" (func $fd_read (param i32 i32 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "proc_exit" (func $proc_exit (param i32)))
+
+        (memory 1)
+        (export "memory" (memory 0))
+
+        (data (i32.const 32) "u/42/hello.txt")
+        (data (i32.const 64) "u/7/out.txt")
+        (data (i32.const 96) "u/escape")
+
+        (func $main (export "_start")
+            ;; Copy the file of tenant 42 to tenant 7
+            (call $path_open
+                (i32.const 4)   ;; dirfd
+                (i32.const 0)   ;; dirflags
+                (i32.const 32)  ;; path
+                (i32.const 14)  ;; path_len
+                (i32.const 0)   ;; oflags
+                (i64.const 2)   ;; rights_base (FD_READ)
+                (i64.const 0)   ;; rights_inheriting
+                (i32.const 0)   ;; fdflags
+                (i32.const 0)   ;; fd_out
+            )
+            drop
+            (i32.store (i32.const 8) (i32.const 128))
+            (i32.store (i32.const 12) (i32.const 16))
+            (call $fd_read (i32.load (i32.const 0)) (i32.const 8) (i32.const 1) (i32.const 16))
+            drop
+
+            (call $path_open
+                (i32.const 4) (i32.const 0) (i32.const 64) (i32.const 11)
+                (i32.const 1) (i64.const 66) (i64.const 0) (i32.const 0) (i32.const 0)
+            )
+            drop
+            (i32.store (i32.const 12) (i32.load (i32.const 16)))
+            (call $fd_write (i32.load (i32.const 0)) (i32.const 8) (i32.const 1) (i32.const 16))
+            drop
+
+            ;; A translation that leaves the root finds nothing
+            (call $proc_exit
+                (call $path_open
+                    (i32.const 4) (i32.const 0) (i32.const 96) (i32.const 8)
+                    (i32.const 0) (i64.const 2) (i64.const 0) (i32.const 0) (i32.const 0)
+                )
+            )
+        )
+    )
+    "#).unwrap();
+
+    let builder = WasiEnv::builder("command-name")
+        .mount_translated("/u", host.path(), |path: &str| {
+            // `/u/{id}/...` lives in the `tenant-{id}` directory
+            let path = path.trim_start_matches('/');
+            match path.split_once('/') {
+                Some((id, rest)) => PathBuf::from(format!("tenant-{id}")).join(rest),
+                None if path.is_empty() => PathBuf::new(),
+                None if path == "escape" => PathBuf::from("../secret.txt"),
+                None => PathBuf::from(format!("tenant-{path}")),
+            }
+        })
+        .preopen_dir("/")
+        .unwrap();
+
+    let handle = tokio::runtime::Handle::current();
+    let result = std::thread::spawn(move || {
+        let _guard = handle.enter();
+        builder.run_with_store(module, &mut store)
+    })
+    .join()
+    .unwrap();
+
+    // The file outside of the root is not visible (Errno::Noent)
+    let exit_code = result.unwrap_err().as_exit_code().unwrap();
+    assert_eq!(exit_code.raw(), 44);
+
+    let copied = std::fs::read_to_string(host.path().join("tenant-7").join("out.txt")).unwrap();
+    assert_eq!(copied, "hello");
+}
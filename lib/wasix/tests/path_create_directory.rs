@@ -0,0 +1,95 @@
+use virtual_fs::mem_fs;
+use wasmer::{Module, Store};
+use wasmer_wasix::WasiEnv;
+
+mod sys {
+    #[tokio::test]
+    async fn test_path_create_directory_errors() {
+        super::test_path_create_directory_errors().await;
+    }
+}
+
+async fn test_path_create_directory_errors() {
+    let mut store = Store::default();
+    let module = Module::new(
+        &store,
+        br#"
+    (module
+        (import "wasi_snapshot_preview1" "path_create_directory" (func $path_create_directory (param i32 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "path_open" (func $path_open (param i32 i32 i32 i32 i32 i64 i64 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "proc_exit" (func $proc_exit (param i32)))
+
+        (memory 1)
+        (export "memory" (memory 0))
+
+        (data (i32.const 32) "d")
+        (data (i32.const 48) "missing/d")
+        (data (i32.const 64) "f/d")
+
+        (func $main (export "_start")
+            (local $created i32)
+            (local $exist i32)
+            (local $noent i32)
+
+            ;; Create the directory 'd' and then create it again
+            (local.set $created
+                (call $path_create_directory (i32.const 4) (i32.const 32) (i32.const 1))
+            )
+            (local.set $exist
+                (call $path_create_directory (i32.const 4) (i32.const 32) (i32.const 1))
+            )
+
+            ;; Create a directory under a parent that does not exist
+            (local.set $noent
+                (call $path_create_directory (i32.const 4) (i32.const 48) (i32.const 9))
+            )
+
+            ;; Create the regular file 'f' and then a directory under it
+            (call $path_open
+                (i32.const 4)   ;; dirfd
+                (i32.const 0)   ;; dirflags
+                (i32.const 64)  ;; path
+                (i32.const 1)   ;; path_len
+                (i32.const 1)   ;; oflags (CREATE)
+                (i64.const 66)  ;; rights_base (FD_READ | FD_WRITE)
+                (i64.const 0)   ;; rights_inheriting
+                (i32.const 0)   ;; fdflags
+                (i32.const 0)   ;; fd_out
+            )
+            drop
+
+            ;; All the errnos are reported in the exit code
+            (call $proc_exit
+                (i32.or
+                    (i32.or
+                        (i32.shl (local.get $created) (i32.const 24))
+                        (i32.shl (local.get $exist) (i32.const 16))
+                    )
+                    (i32.or
+                        (i32.shl (local.get $noent) (i32.const 8))
+                        (call $path_create_directory (i32.const 4) (i32.const 64) (i32.const 3))
+                    )
+                )
+            )
+        )
+    )
+    "#,
+    )
+    .unwrap();
+
+    let builder = WasiEnv::builder("command-name")
+        .fs(Box::<mem_fs::FileSystem>::default())
+        .preopen_dir("/")
+        .unwrap();
+
+    #[cfg(feature = "js")]
+    let result = builder.run_with_store(module, &mut store);
+    #[cfg(not(feature = "js"))]
+    let result = std::thread::spawn(move || builder.run_with_store(module, &mut store))
+        .join()
+        .unwrap();
+
+    // Errno::Success, Errno::Exist, Errno::Noent and Errno::Notdir
+    let exit_code = result.unwrap_err().as_exit_code().unwrap();
+    assert_eq!(exit_code.raw(), (20 << 16) | (44 << 8) | 54);
+}
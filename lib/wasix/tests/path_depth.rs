@@ -0,0 +1,104 @@
+use std::path::Path;
+
+use virtual_fs::{mem_fs, FileSystem};
+use wasmer::{Module, Store};
+use wasmer_wasix::WasiEnv;
+
+mod sys {
+    #[tokio::test]
+    async fn test_max_path_depth() {
+        super::test_max_path_depth().await;
+    }
+}
+
+async fn test_max_path_depth() {
+    let mut store = Store::default();
+    let module = Module::new(
+        &store,
+        br#"
+    (module
+        (import "wasi_snapshot_preview1" "path_open" (func $path_open (param i32 i32 i32 i32 i32 i64 i64 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "proc_exit" (func $proc_exit (param i32)))
+
+        (memory 1)
+        (export "memory" (memory 0))
+
+        (data (i32.const 32) "a/b/c/d/e")
+
+        (func $open (param $path i32) (param $path_len i32) (result i32)
+            (call $path_open
+                (i32.const 4)            ;; dirfd
+                (i32.const 0)            ;; dirflags
+                (local.get $path)        ;; path
+                (local.get $path_len)    ;; path_len
+                (i32.const 0)            ;; oflags
+                (i64.const 2)            ;; rights_base (FD_READ)
+                (i64.const 0)            ;; rights_inheriting
+                (i32.const 0)            ;; fdflags
+                (i32.const 0)            ;; fd_out
+            )
+        )
+
+        (func $main (export "_start")
+            (local $i i32)
+            (local $missing_errno i32)
+            (local $deep_errno i32)
+            (local $deeper_errno i32)
+
+            ;; "a/b/c" is within the limit
+            (if (call $open (i32.const 32) (i32.const 5))
+                (then (call $proc_exit (i32.const 1)))
+            )
+            ;; so is "a/b/c/d", which does not exist
+            (local.set $missing_errno (call $open (i32.const 32) (i32.const 7)))
+            ;; but "a/b/c/d/e" is not
+            (local.set $deep_errno (call $open (i32.const 32) (i32.const 9)))
+
+            ;; Neither is a path with a thousand components
+            (block $done
+                (loop $fill
+                    (br_if $done (i32.eq (local.get $i) (i32.const 10000)))
+                    (i32.store16 (i32.add (i32.const 4096) (i32.mul (local.get $i) (i32.const 2))) (i32.const 0x2f61))
+                    (local.set $i (i32.add (local.get $i) (i32.const 1)))
+                    (br $fill)
+                )
+            )
+            (local.set $deeper_errno (call $open (i32.const 4096) (i32.const 1999)))
+
+            (call $proc_exit
+                (i32.or
+                    (i32.or
+                        (i32.shl (local.get $missing_errno) (i32.const 16))
+                        (i32.shl (local.get $deep_errno) (i32.const 8))
+                    )
+                    (local.get $deeper_errno)
+                )
+            )
+        )
+    )
+    "#,
+    )
+    .unwrap();
+
+    let fs = mem_fs::FileSystem::default();
+    fs.create_dir(Path::new("/a")).unwrap();
+    fs.create_dir(Path::new("/a/b")).unwrap();
+    fs.create_dir(Path::new("/a/b/c")).unwrap();
+
+    let builder = WasiEnv::builder("command-name")
+        .fs(Box::new(fs))
+        .preopen_dir("/")
+        .unwrap()
+        .max_path_depth(4);
+
+    #[cfg(feature = "js")]
+    let result = builder.run_with_store(module, &mut store);
+    #[cfg(not(feature = "js"))]
+    let result = std::thread::spawn(move || builder.run_with_store(module, &mut store))
+        .join()
+        .unwrap();
+
+    // `Errno::Noent` and then `Errno::Nametoolong` for both of the deep paths
+    let exit_code = result.unwrap_err().as_exit_code().unwrap();
+    assert_eq!(exit_code.raw(), (44 << 16) | (37 << 8) | 37);
+}
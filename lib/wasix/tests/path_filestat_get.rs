@@ -0,0 +1,84 @@
+use virtual_fs::mem_fs;
+use wasmer::{Module, Store};
+use wasmer_wasix::WasiEnv;
+
+mod sys {
+    #[tokio::test]
+    async fn test_path_filestat_get_symlink_follow() {
+        super::test_path_filestat_get_symlink_follow().await;
+    }
+}
+
+async fn test_path_filestat_get_symlink_follow() {
+    let mut store = Store::default();
+    let module = Module::new(&store, br#"
+    (module
+        (import "wasi_snapshot_preview1" "path_open" (func $path_open (param i32 i32 i32 i32 i32 i64 i64 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "path_symlink" (func $path_symlink (param i32 i32 i32 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "path_filestat_get" (func $path_filestat_get (param i32 i32 i32 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "proc_exit" (func $proc_exit (param i32)))
+
+        (memory 1)
+        (export "memory" (memory 0))
+
+        (data (i32.const 32) "target")
+        (data (i32.const 48) "link")
+
+        (func $main (export "_start")
+            ;; Create the file 'target' and a symlink 'link' pointing to it
+            (call $path_open
+                (i32.const 4)   ;; dirfd
+                (i32.const 0)   ;; dirflags
+                (i32.const 32)  ;; path
+                (i32.const 6)   ;; path_len
+                (i32.const 1)   ;; oflags (CREATE)
+                (i64.const 66)  ;; rights_base (FD_READ | FD_WRITE)
+                (i64.const 0)   ;; rights_inheriting
+                (i32.const 0)   ;; fdflags
+                (i32.const 100) ;; fd_out
+            )
+            drop
+            (call $path_symlink (i32.const 32) (i32.const 6) (i32.const 4) (i32.const 48) (i32.const 4))
+            drop
+
+            ;; Stat the link itself
+            (call $path_filestat_get
+                (i32.const 4)   ;; dirfd
+                (i32.const 0)   ;; flags
+                (i32.const 48)  ;; path
+                (i32.const 4)   ;; path_len
+                (i32.const 128) ;; buf
+            )
+            drop
+
+            ;; Stat the target of the link (SYMLINK_FOLLOW)
+            (call $path_filestat_get (i32.const 4) (i32.const 1) (i32.const 48) (i32.const 4) (i32.const 256))
+            drop
+
+            ;; Report both filetypes as the exit code
+            (call $proc_exit
+                (i32.or
+                    (i32.shl (i32.load8_u (i32.const 144)) (i32.const 8))
+                    (i32.load8_u (i32.const 272))
+                )
+            )
+        )
+    )
+    "#).unwrap();
+
+    let builder = WasiEnv::builder("command-name")
+        .fs(Box::<mem_fs::FileSystem>::default())
+        .preopen_dir("/")
+        .unwrap();
+
+    #[cfg(feature = "js")]
+    let result = builder.run_with_store(module, &mut store);
+    #[cfg(not(feature = "js"))]
+    let result = std::thread::spawn(move || builder.run_with_store(module, &mut store))
+        .join()
+        .unwrap();
+
+    // SymbolicLink (7) without following, RegularFile (4) when following
+    let exit_code = result.unwrap_err().as_exit_code().unwrap();
+    assert_eq!(exit_code.raw(), (7 << 8) | 4);
+}
@@ -0,0 +1,98 @@
+use std::sync::Arc;
+
+use virtual_fs::{mem_fs, FileSystem, TmpFileSystem};
+use wasmer::{Module, Store};
+use wasmer_wasix::WasiEnv;
+
+mod sys {
+    #[tokio::test]
+    async fn test_link_and_rename_across_mounts_is_xdev() {
+        super::test_link_and_rename_across_mounts_is_xdev().await;
+    }
+}
+
+async fn test_link_and_rename_across_mounts_is_xdev() {
+    let mut store = Store::default();
+    let module = Module::new(&store, br#"
+    (module
+        (import "wasi_snapshot_preview1" "path_open" (func $path_open (param i32 i32 i32 i32 i32 i64 i64 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "path_link" (func $path_link (param i32 i32 i32 i32 i32 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "path_rename" (func $path_rename (param i32 i32 i32 i32 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "proc_exit" (func $proc_exit (param i32)))
+
+        (memory 1)
+        (export "memory" (memory 0))
+
+        (data (i32.const 32) "a/f")
+        (data (i32.const 40) "b/g")
+        (data (i32.const 48) "a/h")
+
+        (func $main (export "_start")
+            ;; Create the file 'f' on the mount at 'a'
+            (call $path_open
+                (i32.const 4)   ;; dirfd
+                (i32.const 0)   ;; dirflags
+                (i32.const 32)  ;; path
+                (i32.const 3)   ;; path_len
+                (i32.const 1)   ;; oflags (CREATE)
+                (i64.const 66)  ;; rights_base (FD_READ | FD_WRITE)
+                (i64.const 0)   ;; rights_inheriting
+                (i32.const 0)   ;; fdflags
+                (i32.const 0)   ;; fd_out
+            )
+            drop
+
+            ;; Report the errnos of the link and the rename to the mount at
+            ;; 'b' and of the link within the same mount in the exit code
+            (call $proc_exit
+                (i32.or
+                    (i32.or
+                        (i32.shl
+                            (call $path_link
+                                (i32.const 4) (i32.const 0) (i32.const 32) (i32.const 3)
+                                (i32.const 4) (i32.const 40) (i32.const 3)
+                            )
+                            (i32.const 16)
+                        )
+                        (i32.shl
+                            (call $path_rename
+                                (i32.const 4) (i32.const 32) (i32.const 3)
+                                (i32.const 4) (i32.const 40) (i32.const 3)
+                            )
+                            (i32.const 8)
+                        )
+                    )
+                    (call $path_link
+                        (i32.const 4) (i32.const 0) (i32.const 32) (i32.const 3)
+                        (i32.const 4) (i32.const 48) (i32.const 3)
+                    )
+                )
+            )
+        )
+    )
+    "#).unwrap();
+
+    let root = TmpFileSystem::new();
+    let a: Arc<dyn FileSystem + Send + Sync> = Arc::new(mem_fs::FileSystem::default());
+    let b: Arc<dyn FileSystem + Send + Sync> = Arc::new(mem_fs::FileSystem::default());
+    root.mount("/a".into(), &a, "/".into()).unwrap();
+    root.mount("/b".into(), &b, "/".into()).unwrap();
+
+    let builder = WasiEnv::builder("command-name")
+        .sandbox_fs(root)
+        .preopen_dir("/")
+        .unwrap();
+
+    #[cfg(feature = "js")]
+    let result = builder.run_with_store(module, &mut store);
+    #[cfg(not(feature = "js"))]
+    let result = std::thread::spawn(move || builder.run_with_store(module, &mut store))
+        .join()
+        .unwrap();
+
+    // Errno::Xdev across the mounts, the link within the mount succeeds
+    let exit_code = result.unwrap_err().as_exit_code().unwrap();
+    assert_eq!(exit_code.raw(), (75 << 16) | (75 << 8));
+    assert!(a.metadata("/f".as_ref()).is_ok());
+    assert!(b.metadata("/g".as_ref()).is_err());
+}
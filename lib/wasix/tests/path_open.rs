@@ -0,0 +1,515 @@
+use virtual_fs::mem_fs;
+use wasmer::{Module, Store};
+use wasmer_wasix::WasiEnv;
+
+mod sys {
+    #[tokio::test]
+    async fn test_path_open_through_file_is_notdir() {
+        super::test_path_open_through_file_is_notdir().await;
+    }
+    #[tokio::test]
+    async fn test_path_open_trailing_slash() {
+        super::test_path_open_trailing_slash().await;
+    }
+    #[tokio::test]
+    async fn test_path_open_name_too_long() {
+        super::test_path_open_name_too_long().await;
+    }
+    #[tokio::test]
+    async fn test_path_open_twice_has_independent_cursors() {
+        super::test_path_open_twice_has_independent_cursors().await;
+    }
+    #[tokio::test]
+    async fn test_path_open_rights_bounded_by_inheriting() {
+        super::test_path_open_rights_bounded_by_inheriting().await;
+    }
+    #[tokio::test]
+    async fn test_empty_path_is_noent() {
+        super::test_empty_path_is_noent().await;
+    }
+}
+
+async fn test_path_open_through_file_is_notdir() {
+    let mut store = Store::default();
+    let module = Module::new(&store, br#"
+    (module
+        (import "wasi_snapshot_preview1" "path_open" (func $path_open (param i32 i32 i32 i32 i32 i64 i64 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "proc_exit" (func $proc_exit (param i32)))
+
+        (memory 1)
+        (export "memory" (memory 0))
+
+        (data (i32.const 32) "a/b")
+
+        (func $main (export "_start")
+            (local $errno i32)
+
+            ;; Create the regular file 'a' in the preopened directory
+            (call $path_open
+                (i32.const 4)   ;; dirfd
+                (i32.const 0)   ;; dirflags
+                (i32.const 32)  ;; path
+                (i32.const 1)   ;; path_len
+                (i32.const 1)   ;; oflags (CREATE)
+                (i64.const 66)  ;; rights_base (FD_READ | FD_WRITE)
+                (i64.const 0)   ;; rights_inheriting
+                (i32.const 0)   ;; fdflags
+                (i32.const 0)   ;; fd_out
+            )
+            drop
+
+            ;; Open 'a/b' without CREATE
+            (local.set $errno
+                (call $path_open
+                    (i32.const 4) (i32.const 0) (i32.const 32) (i32.const 3)
+                    (i32.const 0) (i64.const 66) (i64.const 0) (i32.const 0) (i32.const 0)
+                )
+            )
+
+            ;; Open 'a/b' with CREATE, both errnos are reported in the exit code
+            (call $proc_exit
+                (i32.or
+                    (i32.shl (local.get $errno) (i32.const 8))
+                    (call $path_open
+                        (i32.const 4) (i32.const 0) (i32.const 32) (i32.const 3)
+                        (i32.const 1) (i64.const 66) (i64.const 0) (i32.const 0) (i32.const 0)
+                    )
+                )
+            )
+        )
+    )
+    "#).unwrap();
+
+    let builder = WasiEnv::builder("command-name")
+        .fs(Box::<mem_fs::FileSystem>::default())
+        .preopen_dir("/")
+        .unwrap();
+
+    #[cfg(feature = "js")]
+    let result = builder.run_with_store(module, &mut store);
+    #[cfg(not(feature = "js"))]
+    let result = std::thread::spawn(move || builder.run_with_store(module, &mut store))
+        .join()
+        .unwrap();
+
+    // Errno::Notdir for both lookups
+    let exit_code = result.unwrap_err().as_exit_code().unwrap();
+    assert_eq!(exit_code.raw(), (54 << 8) | 54);
+}
+
+async fn test_path_open_trailing_slash() {
+    let mut store = Store::default();
+    let module = Module::new(&store, br#"
+    (module
+        (import "wasi_snapshot_preview1" "path_open" (func $path_open (param i32 i32 i32 i32 i32 i64 i64 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "path_create_directory" (func $path_create_directory (param i32 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "path_filestat_get" (func $path_filestat_get (param i32 i32 i32 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "proc_exit" (func $proc_exit (param i32)))
+
+        (memory 1)
+        (export "memory" (memory 0))
+
+        (data (i32.const 32) "a/")
+        (data (i32.const 40) "d/")
+        (data (i32.const 48) "x/")
+
+        (func $main (export "_start")
+            ;; Create the regular file 'a' and the directory 'd'
+            (call $path_open
+                (i32.const 4)   ;; dirfd
+                (i32.const 0)   ;; dirflags
+                (i32.const 32)  ;; path
+                (i32.const 1)   ;; path_len
+                (i32.const 1)   ;; oflags (CREATE)
+                (i64.const 66)  ;; rights_base (FD_READ | FD_WRITE)
+                (i64.const 0)   ;; rights_inheriting
+                (i32.const 0)   ;; fdflags
+                (i32.const 0)   ;; fd_out
+            )
+            drop
+            (call $path_create_directory (i32.const 4) (i32.const 40) (i32.const 1))
+            drop
+
+            ;; Report the errnos of all the lookups in the exit code
+            (call $proc_exit
+                (i32.or
+                    (i32.or
+                        ;; 'd/' is a directory
+                        (i32.shl
+                            (call $path_open
+                                (i32.const 4) (i32.const 0) (i32.const 40) (i32.const 2)
+                                (i32.const 2) (i64.const 66) (i64.const 0) (i32.const 0) (i32.const 0)
+                            )
+                            (i32.const 24)
+                        )
+                        ;; 'a/' is a file
+                        (i32.shl
+                            (call $path_open
+                                (i32.const 4) (i32.const 0) (i32.const 32) (i32.const 2)
+                                (i32.const 0) (i64.const 66) (i64.const 0) (i32.const 0) (i32.const 0)
+                            )
+                            (i32.const 16)
+                        )
+                    )
+                    (i32.or
+                        ;; 'x/' does not exist
+                        (i32.shl
+                            (call $path_open
+                                (i32.const 4) (i32.const 0) (i32.const 48) (i32.const 2)
+                                (i32.const 0) (i64.const 66) (i64.const 0) (i32.const 0) (i32.const 0)
+                            )
+                            (i32.const 8)
+                        )
+                        ;; the file can not be stat-ed as a directory either
+                        (call $path_filestat_get (i32.const 4) (i32.const 0) (i32.const 32) (i32.const 2) (i32.const 64))
+                    )
+                )
+            )
+        )
+    )
+    "#).unwrap();
+
+    let builder = WasiEnv::builder("command-name")
+        .fs(Box::<mem_fs::FileSystem>::default())
+        .preopen_dir("/")
+        .unwrap();
+
+    #[cfg(feature = "js")]
+    let result = builder.run_with_store(module, &mut store);
+    #[cfg(not(feature = "js"))]
+    let result = std::thread::spawn(move || builder.run_with_store(module, &mut store))
+        .join()
+        .unwrap();
+
+    // Success, Errno::Notdir, Errno::Noent and Errno::Notdir
+    let exit_code = result.unwrap_err().as_exit_code().unwrap();
+    assert_eq!(exit_code.raw(), (54 << 16) | (44 << 8) | 54);
+}
+
+async fn test_path_open_name_too_long() {
+    let long_name = "a".repeat(300);
+    let long_path = "d/".repeat(50);
+
+    let mut store = Store::default();
+    let module = Module::new(
+        &store,
+        format!(
+            r#"
+    (module
+        (import "wasi_snapshot_preview1" "path_open" (func $path_open (param i32 i32 i32 i32 i32 i64 i64 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "proc_exit" (func $proc_exit (param i32)))
+
+        (memory 1)
+        (export "memory" (memory 0))
+
+        (data (i32.const 256) "{long_name}")
+        (data (i32.const 1024) "{long_path}")
+
+        (func $main (export "_start")
+            ;; Report the errnos of both opens in the exit code
+            (call $proc_exit
+                (i32.or
+                    ;; A single component that is too long
+                    (i32.shl
+                        (call $path_open
+                            (i32.const 4) (i32.const 0) (i32.const 256) (i32.const 300)
+                            (i32.const 1) (i64.const 66) (i64.const 0) (i32.const 0) (i32.const 0)
+                        )
+                        (i32.const 8)
+                    )
+                    ;; Short components that add up to a path that is too long
+                    (call $path_open
+                        (i32.const 4) (i32.const 0) (i32.const 1024) (i32.const 100)
+                        (i32.const 1) (i64.const 66) (i64.const 0) (i32.const 0) (i32.const 0)
+                    )
+                )
+            )
+        )
+    )
+    "#
+        ),
+    )
+    .unwrap();
+
+    let builder = WasiEnv::builder("command-name")
+        .fs(Box::<mem_fs::FileSystem>::default())
+        .preopen_dir("/")
+        .unwrap()
+        .max_path_len(64);
+
+    #[cfg(feature = "js")]
+    let result = builder.run_with_store(module, &mut store);
+    #[cfg(not(feature = "js"))]
+    let result = std::thread::spawn(move || builder.run_with_store(module, &mut store))
+        .join()
+        .unwrap();
+
+    // Errno::Nametoolong for both
+    let exit_code = result.unwrap_err().as_exit_code().unwrap();
+    assert_eq!(exit_code.raw(), (37 << 8) | 37);
+}
+
+async fn test_path_open_twice_has_independent_cursors() {
+    let mut store = Store::default();
+    let module = Module::new(&store, br#"
+    (module
+        (import "wasi_snapshot_preview1" "path_open" (func $path_open (param i32 i32 i32 i32 i32 i64 i64 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "fd_read" (func $fd_read (param i32 i32 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "fd_seek" (func $fd_seek (param i32 i64 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "fd_tell" (func $fd_tell (param i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "proc_exit" (func $proc_exit (param i32)))
+
+        (memory 1)
+        (export "memory" (memory 0))
+
+        (data (i32.const 32) "f")
+        (data (i32.const 48) "hello")
+        (data (i32.const 56) "J")
+
+        (func $main (export "_start")
+            (local $a i32)
+            (local $b i32)
+            (local $tell_after_seek i32)
+
+            ;; Create 'f' and write to it through the first fd
+            (call $path_open
+                (i32.const 4)   ;; dirfd
+                (i32.const 0)   ;; dirflags
+                (i32.const 32)  ;; path
+                (i32.const 1)   ;; path_len
+                (i32.const 1)   ;; oflags (CREATE)
+                (i64.const 70)  ;; rights_base (FD_READ | FD_SEEK | FD_WRITE)
+                (i64.const 0)   ;; rights_inheriting
+                (i32.const 0)   ;; fdflags
+                (i32.const 0)   ;; fd_out
+            )
+            drop
+            (local.set $a (i32.load (i32.const 0)))
+            (i32.store (i32.const 8) (i32.const 48))
+            (i32.store (i32.const 12) (i32.const 5))
+            (call $fd_write (local.get $a) (i32.const 8) (i32.const 1) (i32.const 16))
+            drop
+
+            ;; Open 'f' a second time, only for writing (FD_TELL | FD_WRITE)
+            (call $path_open
+                (i32.const 4) (i32.const 0) (i32.const 32) (i32.const 1)
+                (i32.const 0) (i64.const 96) (i64.const 0) (i32.const 0) (i32.const 0)
+            )
+            drop
+            (local.set $b (i32.load (i32.const 0)))
+
+            ;; Seeking the first fd leaves the cursor of the second one alone
+            (call $fd_seek (local.get $a) (i64.const 3) (i32.const 0) (i32.const 64))
+            drop
+            (call $fd_tell (local.get $b) (i32.const 72))
+            drop
+            (local.set $tell_after_seek (i32.load (i32.const 72)))
+
+            ;; Overwrite the first byte through the second fd
+            (i32.store (i32.const 8) (i32.const 56))
+            (i32.store (i32.const 12) (i32.const 1))
+            (call $fd_write (local.get $b) (i32.const 8) (i32.const 1) (i32.const 16))
+            drop
+            (call $fd_tell (local.get $b) (i32.const 72))
+            drop
+
+            ;; The first fd sees the write
+            (call $fd_seek (local.get $a) (i64.const 0) (i32.const 0) (i32.const 64))
+            drop
+            (i32.store (i32.const 8) (i32.const 128))
+            (i32.store (i32.const 12) (i32.const 5))
+            (call $fd_read (local.get $a) (i32.const 8) (i32.const 1) (i32.const 16))
+            drop
+
+            ;; Report both cursors of the second fd and the first byte read
+            ;; through the first fd in the exit code
+            (call $proc_exit
+                (i32.or
+                    (i32.or
+                        (i32.shl (local.get $tell_after_seek) (i32.const 16))
+                        (i32.shl (i32.load (i32.const 72)) (i32.const 8))
+                    )
+                    (i32.load8_u (i32.const 128))
+                )
+            )
+        )
+    )
+    "#).unwrap();
+
+    let builder = WasiEnv::builder("command-name")
+        .fs(Box::<mem_fs::FileSystem>::default())
+        .preopen_dir("/")
+        .unwrap();
+
+    #[cfg(feature = "js")]
+    let result = builder.run_with_store(module, &mut store);
+    #[cfg(not(feature = "js"))]
+    let result = std::thread::spawn(move || builder.run_with_store(module, &mut store))
+        .join()
+        .unwrap();
+
+    // The second fd starts at 0 and moves to 1, the first fd reads "Jello"
+    let exit_code = result.unwrap_err().as_exit_code().unwrap();
+    assert_eq!(exit_code.raw(), (1 << 8) | b'J' as i32);
+}
+
+async fn test_path_open_rights_bounded_by_inheriting() {
+    let mut store = Store::default();
+    let module = Module::new(&store, br#"
+    (module
+        (import "wasi_snapshot_preview1" "path_create_directory" (func $path_create_directory (param i32 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "path_open" (func $path_open (param i32 i32 i32 i32 i32 i64 i64 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "fd_fdstat_get" (func $fd_fdstat_get (param i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "proc_exit" (func $proc_exit (param i32)))
+
+        (memory 1)
+        (export "memory" (memory 0))
+
+        (data (i32.const 32) "d/f")
+
+        (func $main (export "_start")
+            (local $file i32)
+
+            ;; Create the file 'd/f'
+            (call $path_create_directory (i32.const 4) (i32.const 32) (i32.const 1))
+            drop
+            (call $path_open
+                (i32.const 4) (i32.const 0) (i32.const 32) (i32.const 3)
+                (i32.const 1) (i64.const 66) (i64.const 0) (i32.const 0) (i32.const 0)
+            )
+            drop
+
+            ;; Open 'd', the fds derived from it may only read and seek
+            (call $path_open
+                (i32.const 4)   ;; dirfd
+                (i32.const 0)   ;; dirflags
+                (i32.const 32)  ;; path
+                (i32.const 1)   ;; path_len
+                (i32.const 2)   ;; oflags (DIRECTORY)
+                (i64.const 8192) ;; rights_base (PATH_OPEN)
+                (i64.const 6)   ;; rights_inheriting (FD_READ | FD_SEEK)
+                (i32.const 0)   ;; fdflags
+                (i32.const 0)   ;; fd_out
+            )
+            drop
+
+            ;; Open 'f' through 'd' asking for more than that
+            (call $path_open
+                (i32.load (i32.const 0))
+                (i32.const 0)
+                (i32.const 34)  ;; path
+                (i32.const 1)   ;; path_len
+                (i32.const 0)   ;; oflags
+                (i64.const 70)  ;; rights_base (FD_READ | FD_SEEK | FD_WRITE)
+                (i64.const 66)  ;; rights_inheriting (FD_READ | FD_WRITE)
+                (i32.const 0)   ;; fdflags
+                (i32.const 0)   ;; fd_out
+            )
+            drop
+            (local.set $file (i32.load (i32.const 0)))
+            (call $fd_fdstat_get (local.get $file) (i32.const 64))
+            drop
+
+            ;; Report the errno of a write and both rights of the file in
+            ;; the exit code
+            (i32.store (i32.const 8) (i32.const 32))
+            (i32.store (i32.const 12) (i32.const 1))
+            (call $proc_exit
+                (i32.or
+                    (i32.or
+                        (i32.shl
+                            (call $fd_write (local.get $file) (i32.const 8) (i32.const 1) (i32.const 16))
+                            (i32.const 16)
+                        )
+                        (i32.shl (i32.wrap_i64 (i64.load (i32.const 72))) (i32.const 8))
+                    )
+                    (i32.wrap_i64 (i64.load (i32.const 80)))
+                )
+            )
+        )
+    )
+    "#).unwrap();
+
+    let builder = WasiEnv::builder("command-name")
+        .fs(Box::<mem_fs::FileSystem>::default())
+        .preopen_dir("/")
+        .unwrap();
+
+    #[cfg(feature = "js")]
+    let result = builder.run_with_store(module, &mut store);
+    #[cfg(not(feature = "js"))]
+    let result = std::thread::spawn(move || builder.run_with_store(module, &mut store))
+        .join()
+        .unwrap();
+
+    // The write fails with Errno::Access, the rights are FD_READ | FD_SEEK
+    // and FD_READ
+    let exit_code = result.unwrap_err().as_exit_code().unwrap();
+    assert_eq!(exit_code.raw(), (2 << 16) | (6 << 8) | 2);
+}
+
+async fn test_empty_path_is_noent() {
+    let mut store = Store::default();
+    let module = Module::new(
+        &store,
+        br#"
+    (module
+        (import "wasi_snapshot_preview1" "path_open" (func $path_open (param i32 i32 i32 i32 i32 i64 i64 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "path_filestat_get" (func $path_filestat_get (param i32 i32 i32 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "proc_exit" (func $proc_exit (param i32)))
+
+        (memory 1)
+        (export "memory" (memory 0))
+
+        (func $main (export "_start")
+            ;; Report the errnos of all the calls in the exit code
+            (call $proc_exit
+                (i32.or
+                    (i32.or
+                        ;; Opening an empty path
+                        (i32.shl
+                            (call $path_open
+                                (i32.const 4) (i32.const 0) (i32.const 256) (i32.const 0)
+                                (i32.const 0) (i64.const 2) (i64.const 0) (i32.const 0) (i32.const 0)
+                            )
+                            (i32.const 16)
+                        )
+                        ;; Creating an empty path
+                        (i32.shl
+                            (call $path_open
+                                (i32.const 4) (i32.const 0) (i32.const 256) (i32.const 0)
+                                (i32.const 1) (i64.const 66) (i64.const 0) (i32.const 0) (i32.const 0)
+                            )
+                            (i32.const 8)
+                        )
+                    )
+                    ;; Querying an empty path
+                    (call $path_filestat_get
+                        (i32.const 4) (i32.const 0) (i32.const 256) (i32.const 0) (i32.const 512)
+                    )
+                )
+            )
+        )
+    )
+    "#,
+    )
+    .unwrap();
+
+    let builder = WasiEnv::builder("command-name")
+        .fs(Box::<mem_fs::FileSystem>::default())
+        .preopen_dir("/")
+        .unwrap();
+
+    #[cfg(feature = "js")]
+    let result = builder.run_with_store(module, &mut store);
+    #[cfg(not(feature = "js"))]
+    let result = std::thread::spawn(move || builder.run_with_store(module, &mut store))
+        .join()
+        .unwrap();
+
+    // Errno::Noent for all of them
+    let exit_code = result.unwrap_err().as_exit_code().unwrap();
+    assert_eq!(exit_code.raw(), (44 << 16) | (44 << 8) | 44);
+}
@@ -0,0 +1,191 @@
+use virtual_fs::mem_fs;
+use wasmer::{Module, Store};
+use wasmer_wasix::WasiEnv;
+
+mod sys {
+    #[tokio::test]
+    async fn test_path_rename_directory_onto_existing() {
+        super::test_path_rename_directory_onto_existing().await;
+    }
+    #[tokio::test]
+    async fn test_path_rename_file_onto_directory() {
+        super::test_path_rename_file_onto_directory().await;
+    }
+}
+
+async fn test_path_rename_directory_onto_existing() {
+    let mut store = Store::default();
+    let module = Module::new(
+        &store,
+        br#"
+    (module
+        (import "wasi_snapshot_preview1" "path_create_directory" (func $path_create_directory (param i32 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "path_open" (func $path_open (param i32 i32 i32 i32 i32 i64 i64 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "path_rename" (func $path_rename (param i32 i32 i32 i32 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "proc_exit" (func $proc_exit (param i32)))
+
+        (memory 1)
+        (export "memory" (memory 0))
+
+        (data (i32.const 32) "src")
+        (data (i32.const 40) "empty")
+        (data (i32.const 48) "full")
+        (data (i32.const 56) "full/x")
+        (data (i32.const 64) "f")
+
+        (func $main (export "_start")
+            (local $empty i32)
+            (local $full i32)
+
+            (drop (call $path_create_directory (i32.const 4) (i32.const 32) (i32.const 3)))
+            (drop (call $path_create_directory (i32.const 4) (i32.const 40) (i32.const 5)))
+            (drop (call $path_create_directory (i32.const 4) (i32.const 48) (i32.const 4)))
+            (drop (call $path_create_directory (i32.const 4) (i32.const 56) (i32.const 6)))
+            (call $path_open
+                (i32.const 4)   ;; dirfd
+                (i32.const 0)   ;; dirflags
+                (i32.const 64)  ;; path
+                (i32.const 1)   ;; path_len
+                (i32.const 1)   ;; oflags (CREATE)
+                (i64.const 66)  ;; rights_base (FD_READ | FD_WRITE)
+                (i64.const 0)   ;; rights_inheriting
+                (i32.const 0)   ;; fdflags
+                (i32.const 0)   ;; fd_out
+            )
+            drop
+
+            ;; Rename 'src' onto the empty directory 'empty'
+            (local.set $empty
+                (call $path_rename
+                    (i32.const 4) (i32.const 32) (i32.const 3)
+                    (i32.const 4) (i32.const 40) (i32.const 5)
+                )
+            )
+
+            ;; Rename the directory now at 'empty' onto the non-empty 'full'
+            (local.set $full
+                (call $path_rename
+                    (i32.const 4) (i32.const 40) (i32.const 5)
+                    (i32.const 4) (i32.const 48) (i32.const 4)
+                )
+            )
+
+            ;; All the errnos are reported in the exit code, the last one
+            ;; renames the directory at 'empty' onto the file 'f'
+            (call $proc_exit
+                (i32.or
+                    (i32.or
+                        (i32.shl (local.get $empty) (i32.const 16))
+                        (i32.shl (local.get $full) (i32.const 8))
+                    )
+                    (call $path_rename
+                        (i32.const 4) (i32.const 40) (i32.const 5)
+                        (i32.const 4) (i32.const 64) (i32.const 1)
+                    )
+                )
+            )
+        )
+    )
+    "#,
+    )
+    .unwrap();
+
+    let builder = WasiEnv::builder("command-name")
+        .fs(Box::<mem_fs::FileSystem>::default())
+        .preopen_dir("/")
+        .unwrap();
+
+    #[cfg(feature = "js")]
+    let result = builder.run_with_store(module, &mut store);
+    #[cfg(not(feature = "js"))]
+    let result = std::thread::spawn(move || builder.run_with_store(module, &mut store))
+        .join()
+        .unwrap();
+
+    // Errno::Success, Errno::Notempty and Errno::Notdir
+    let exit_code = result.unwrap_err().as_exit_code().unwrap();
+    assert_eq!(exit_code.raw(), (55 << 8) | 54);
+}
+
+async fn test_path_rename_file_onto_directory() {
+    let mut store = Store::default();
+    let module = Module::new(
+        &store,
+        br#"
+    (module
+        (import "wasi_snapshot_preview1" "path_create_directory" (func $path_create_directory (param i32 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "path_open" (func $path_open (param i32 i32 i32 i32 i32 i64 i64 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "path_rename" (func $path_rename (param i32 i32 i32 i32 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "proc_exit" (func $proc_exit (param i32)))
+
+        (memory 1)
+        (export "memory" (memory 0))
+
+        (data (i32.const 40) "empty")
+        (data (i32.const 64) "f")
+
+        (func $main (export "_start")
+            (local $rename i32)
+
+            (drop (call $path_create_directory (i32.const 4) (i32.const 40) (i32.const 5)))
+            (call $path_open
+                (i32.const 4)   ;; dirfd
+                (i32.const 0)   ;; dirflags
+                (i32.const 64)  ;; path
+                (i32.const 1)   ;; path_len
+                (i32.const 1)   ;; oflags (CREATE)
+                (i64.const 66)  ;; rights_base (FD_READ | FD_WRITE)
+                (i64.const 0)   ;; rights_inheriting
+                (i32.const 0)   ;; fdflags
+                (i32.const 0)   ;; fd_out
+            )
+            drop
+
+            ;; Rename the file 'f' onto the empty directory 'empty'
+            (local.set $rename
+                (call $path_rename
+                    (i32.const 4) (i32.const 64) (i32.const 1)
+                    (i32.const 4) (i32.const 40) (i32.const 5)
+                )
+            )
+
+            ;; The errno of the rename and of opening 'empty' as a directory
+            ;; afterwards are reported in the exit code
+            (call $proc_exit
+                (i32.or
+                    (i32.shl (local.get $rename) (i32.const 8))
+                    (call $path_open
+                        (i32.const 4)   ;; dirfd
+                        (i32.const 0)   ;; dirflags
+                        (i32.const 40)  ;; path
+                        (i32.const 5)   ;; path_len
+                        (i32.const 2)   ;; oflags (DIRECTORY)
+                        (i64.const 0)   ;; rights_base
+                        (i64.const 0)   ;; rights_inheriting
+                        (i32.const 0)   ;; fdflags
+                        (i32.const 0)   ;; fd_out
+                    )
+                )
+            )
+        )
+    )
+    "#,
+    )
+    .unwrap();
+
+    let builder = WasiEnv::builder("command-name")
+        .fs(Box::<mem_fs::FileSystem>::default())
+        .preopen_dir("/")
+        .unwrap();
+
+    #[cfg(feature = "js")]
+    let result = builder.run_with_store(module, &mut store);
+    #[cfg(not(feature = "js"))]
+    let result = std::thread::spawn(move || builder.run_with_store(module, &mut store))
+        .join()
+        .unwrap();
+
+    // Errno::Isdir and the directory is still there
+    let exit_code = result.unwrap_err().as_exit_code().unwrap();
+    assert_eq!(exit_code.raw(), 31 << 8);
+}
@@ -0,0 +1,233 @@
+use std::path::Path;
+
+use virtual_fs::{mem_fs, AsyncWriteExt, FileSystem};
+use wasmer::{Module, Store};
+use wasmer_wasix::{wasmer_wasix_types::wasi::Rights, WasiEnv};
+
+mod sys {
+    #[tokio::test]
+    async fn test_path_rights_limit_subpath() {
+        super::test_path_rights_limit_subpath().await;
+    }
+
+    #[tokio::test]
+    async fn test_path_rights_limit_path_syscalls() {
+        super::test_path_rights_limit_path_syscalls().await;
+    }
+}
+
+async fn test_path_rights_limit_subpath() {
+    let mut store = Store::default();
+    let module = Module::new(
+        &store,
+        br#"
+    (module
+        (import "wasi_snapshot_preview1" "path_open" (func $path_open (param i32 i32 i32 i32 i32 i64 i64 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "fd_pread" (func $fd_pread (param i32 i32 i32 i64 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "proc_exit" (func $proc_exit (param i32)))
+
+        (memory 1)
+        (export "memory" (memory 0))
+
+        (data (i32.const 32) "app/f")
+        (data (i32.const 48) "app/config/f")
+        (data (i32.const 64) "app/config/g")
+        (data (i32.const 256) "x")
+
+        ;; iovecs of "x" and of the read buffer
+        (data (i32.const 100) "\00\01\00\00\01\00\00\00")
+        (data (i32.const 108) "\00\02\00\00\03\00\00\00")
+
+        (func $check (param $errno i32)
+            (if (local.get $errno)
+                (then (call $proc_exit (i32.add (i32.const 100) (local.get $errno))))
+            )
+        )
+
+        ;; Opens the path at `path` for reading and writing
+        (func $open (param $path i32) (param $path_len i32) (param $oflags i32) (result i32)
+            (call $path_open
+                (i32.const 4)            ;; dirfd
+                (i32.const 0)            ;; dirflags
+                (local.get $path)        ;; path
+                (local.get $path_len)    ;; path_len
+                (local.get $oflags)      ;; oflags
+                (i64.const 70)           ;; rights_base (FD_READ | FD_SEEK | FD_WRITE)
+                (i64.const 0)            ;; rights_inheriting
+                (i32.const 0)            ;; fdflags
+                (i32.const 0)            ;; fd_out
+            )
+        )
+
+        (func $main (export "_start")
+            (local $create_errno i32)
+            (local $write_errno i32)
+
+            ;; Files can be created and written outside of the config
+            (call $check (call $open (i32.const 32) (i32.const 5) (i32.const 1)))
+            (call $check (call $fd_write (i32.load (i32.const 0)) (i32.const 100) (i32.const 1) (i32.const 8)))
+
+            ;; but not in it
+            (local.set $create_errno (call $open (i32.const 48) (i32.const 12) (i32.const 1)))
+
+            ;; where the files that exist can be read but not written
+            (call $check (call $open (i32.const 64) (i32.const 12) (i32.const 0)))
+            (call $check (call $fd_pread (i32.load (i32.const 0)) (i32.const 108) (i32.const 1) (i64.const 0) (i32.const 8)))
+            (if (i32.ne (i32.load8_u (i32.const 512)) (i32.const 0x63))
+                (then (call $proc_exit (i32.const 1)))
+            )
+            (local.set $write_errno (call $fd_write (i32.load (i32.const 0)) (i32.const 100) (i32.const 1) (i32.const 8)))
+
+            (call $proc_exit
+                (i32.or
+                    (i32.shl (local.get $create_errno) (i32.const 8))
+                    (local.get $write_errno)
+                )
+            )
+        )
+    )
+    "#,
+    )
+    .unwrap();
+
+    let fs = mem_fs::FileSystem::default();
+    fs.create_dir(Path::new("/app")).unwrap();
+    fs.create_dir(Path::new("/app/config")).unwrap();
+    let mut file = fs
+        .new_open_options()
+        .create(true)
+        .write(true)
+        .open("/app/config/g")
+        .unwrap();
+    file.write_all(b"cfg").await.unwrap();
+
+    let read_only = Rights::all()
+        - Rights::FD_WRITE
+        - Rights::FD_ALLOCATE
+        - Rights::FD_FILESTAT_SET_SIZE
+        - Rights::PATH_CREATE_FILE
+        - Rights::PATH_CREATE_DIRECTORY;
+    let builder = WasiEnv::builder("command-name")
+        .fs(Box::new(fs))
+        .preopen_dir("/")
+        .unwrap()
+        .path_rights("/app/config", read_only);
+
+    #[cfg(feature = "js")]
+    let result = builder.run_with_store(module, &mut store);
+    #[cfg(not(feature = "js"))]
+    let result = std::thread::spawn(move || builder.run_with_store(module, &mut store))
+        .join()
+        .unwrap();
+
+    // Both failed with `Errno::Access`
+    let exit_code = result.unwrap_err().as_exit_code().unwrap();
+    assert_eq!(exit_code.raw(), (2 << 8) | 2);
+}
+
+async fn test_path_rights_limit_path_syscalls() {
+    let mut store = Store::default();
+    let module = Module::new(
+        &store,
+        br#"
+    (module
+        (import "wasi_snapshot_preview1" "path_unlink_file" (func $path_unlink_file (param i32 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "path_rename" (func $path_rename (param i32 i32 i32 i32 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "path_create_directory" (func $path_create_directory (param i32 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "path_remove_directory" (func $path_remove_directory (param i32 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "path_symlink" (func $path_symlink (param i32 i32 i32 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "path_link" (func $path_link (param i32 i32 i32 i32 i32 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "path_filestat_set_times" (func $path_filestat_set_times (param i32 i32 i32 i32 i64 i64 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "proc_exit" (func $proc_exit (param i32)))
+
+        (memory 1)
+        (export "memory" (memory 0))
+
+        (data (i32.const 32) "app/config/g")
+        (data (i32.const 48) "app/g2")
+        (data (i32.const 64) "app/x")
+        (data (i32.const 80) "app/config/x")
+        (data (i32.const 96) "app/config/n")
+        (data (i32.const 112) "app/config/d")
+        (data (i32.const 128) "app/config/s")
+        (data (i32.const 144) "app/config/l")
+        (data (i32.const 160) "app/l")
+        (data (i32.const 176) "g")
+
+        ;; Exits with the step and the errno unless it is `Errno::Access`
+        (func $expect_access (param $step i32) (param $errno i32)
+            (if (i32.ne (local.get $errno) (i32.const 2))
+                (then (call $proc_exit (i32.or (i32.shl (local.get $step) (i32.const 8)) (local.get $errno))))
+            )
+        )
+
+        (func $main (export "_start")
+            (call $expect_access (i32.const 1)
+                (call $path_unlink_file (i32.const 4) (i32.const 32) (i32.const 12)))
+            (call $expect_access (i32.const 2)
+                (call $path_rename (i32.const 4) (i32.const 32) (i32.const 12) (i32.const 4) (i32.const 48) (i32.const 6)))
+            (call $expect_access (i32.const 3)
+                (call $path_rename (i32.const 4) (i32.const 64) (i32.const 5) (i32.const 4) (i32.const 80) (i32.const 12)))
+            (call $expect_access (i32.const 4)
+                (call $path_create_directory (i32.const 4) (i32.const 96) (i32.const 12)))
+            (call $expect_access (i32.const 5)
+                (call $path_remove_directory (i32.const 4) (i32.const 112) (i32.const 12)))
+            (call $expect_access (i32.const 6)
+                (call $path_symlink (i32.const 176) (i32.const 1) (i32.const 4) (i32.const 128) (i32.const 12)))
+            (call $expect_access (i32.const 7)
+                (call $path_link (i32.const 4) (i32.const 0) (i32.const 64) (i32.const 5) (i32.const 4) (i32.const 144) (i32.const 12)))
+            (call $expect_access (i32.const 8)
+                (call $path_link (i32.const 4) (i32.const 0) (i32.const 32) (i32.const 12) (i32.const 4) (i32.const 160) (i32.const 5)))
+            (call $expect_access (i32.const 9)
+                ;; fst_flags (MTIM_NOW)
+                (call $path_filestat_set_times (i32.const 4) (i32.const 0) (i32.const 32) (i32.const 12) (i64.const 0) (i64.const 0) (i32.const 8)))
+            (call $proc_exit (i32.const 0))
+        )
+    )
+    "#,
+    )
+    .unwrap();
+
+    let fs = mem_fs::FileSystem::default();
+    fs.create_dir(Path::new("/app")).unwrap();
+    fs.create_dir(Path::new("/app/config")).unwrap();
+    fs.create_dir(Path::new("/app/config/d")).unwrap();
+    for path in ["/app/x", "/app/config/g"] {
+        fs.new_open_options()
+            .create(true)
+            .write(true)
+            .open(path)
+            .unwrap();
+    }
+
+    let frozen = Rights::all()
+        - Rights::PATH_UNLINK_FILE
+        - Rights::PATH_RENAME_SOURCE
+        - Rights::PATH_RENAME_TARGET
+        - Rights::PATH_CREATE_DIRECTORY
+        - Rights::PATH_REMOVE_DIRECTORY
+        - Rights::PATH_SYMLINK
+        - Rights::PATH_LINK_SOURCE
+        - Rights::PATH_LINK_TARGET
+        - Rights::PATH_FILESTAT_SET_TIMES;
+    let builder = WasiEnv::builder("command-name")
+        .fs(Box::new(fs.clone()))
+        .preopen_dir("/")
+        .unwrap()
+        .path_rights("/app/config", frozen);
+
+    #[cfg(feature = "js")]
+    let result = builder.run_with_store(module, &mut store);
+    #[cfg(not(feature = "js"))]
+    let result = std::thread::spawn(move || builder.run_with_store(module, &mut store))
+        .join()
+        .unwrap();
+
+    // Every syscall failed with `Errno::Access`
+    result.unwrap();
+    assert!(fs.metadata(Path::new("/app/config/g")).is_ok());
+    assert!(fs.metadata(Path::new("/app/config/d")).is_ok());
+    assert!(fs.metadata(Path::new("/app/x")).is_ok());
+    assert!(fs.metadata(Path::new("/app/config/n")).is_err());
+}
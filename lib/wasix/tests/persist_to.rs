@@ -0,0 +1,94 @@
+#![cfg(all(feature = "host-fs", not(feature = "js")))]
+
+use std::{
+    path::{Path, PathBuf},
+    time::UNIX_EPOCH,
+};
+
+use virtual_fs::{AsyncWriteExt, FileSystem, TmpFileSystem, ZeroFile};
+use wasmer_wasix::WasiEnv;
+
+mod sys {
+    #[tokio::test]
+    async fn test_persist_to_host_dir() {
+        super::test_persist_to_host_dir().await;
+    }
+    #[tokio::test]
+    async fn test_persist_to_stops_at_first_error() {
+        super::test_persist_to_stops_at_first_error().await;
+    }
+}
+
+/// 2001-09-09 and a quarter second in nanoseconds
+const MODIFIED: u64 = 1_000_000_000 * 1_000_000_000 + 250_000_000;
+
+/// Builds a file system with a few files and a device
+async fn sandbox_fs() -> TmpFileSystem {
+    let tmp = TmpFileSystem::new();
+    tmp.create_dir(Path::new("/data")).unwrap();
+    tmp.create_dir(Path::new("/data/nested")).unwrap();
+    for (path, contents) in [("/data/a.txt", "hello"), ("/data/nested/b.txt", "world")] {
+        let mut file = tmp
+            .new_open_options()
+            .create(true)
+            .write(true)
+            .open(path)
+            .unwrap();
+        file.write_all(contents.as_bytes()).await.unwrap();
+        file.set_times(None, Some(MODIFIED)).unwrap();
+    }
+    tmp.create_dir(Path::new("/dev")).unwrap();
+    tmp.new_open_options_ext()
+        .insert_char_device(PathBuf::from("/dev/zero"), Box::<ZeroFile>::default())
+        .unwrap();
+    tmp
+}
+
+async fn test_persist_to_host_dir() {
+    let env = WasiEnv::builder("command-name")
+        .sandbox_fs(sandbox_fs().await)
+        .build()
+        .unwrap();
+
+    let host = tempfile::TempDir::new().unwrap();
+    env.fs().persist_to(host.path()).await.unwrap();
+
+    let a = host.path().join("data").join("a.txt");
+    let b = host.path().join("data").join("nested").join("b.txt");
+    assert_eq!(std::fs::read_to_string(&a).unwrap(), "hello");
+    assert_eq!(std::fs::read_to_string(b).unwrap(), "world");
+
+    // Device files and /proc are not written out
+    assert!(host.path().join("dev").is_dir());
+    assert!(!host.path().join("dev").join("zero").exists());
+    assert!(!host.path().join("proc").exists());
+
+    // The modification time is carried over in whole seconds
+    let host_modified = std::fs::metadata(&a)
+        .unwrap()
+        .modified()
+        .unwrap()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_nanos() as u64;
+    assert_eq!(host_modified, MODIFIED / 1_000_000_000 * 1_000_000_000);
+}
+
+async fn test_persist_to_stops_at_first_error() {
+    let env = WasiEnv::builder("command-name")
+        .sandbox_fs(sandbox_fs().await)
+        .build()
+        .unwrap();
+
+    // A directory is in the way of the nested file
+    let host = tempfile::TempDir::new().unwrap();
+    let b = host.path().join("data").join("nested").join("b.txt");
+    std::fs::create_dir_all(&b).unwrap();
+
+    assert!(env.fs().persist_to(host.path()).await.is_err());
+
+    // The files before the failing one were written out
+    let a = host.path().join("data").join("a.txt");
+    assert_eq!(std::fs::read_to_string(a).unwrap(), "hello");
+    assert!(b.is_dir());
+}
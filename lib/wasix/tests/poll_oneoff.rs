@@ -0,0 +1,157 @@
+use virtual_fs::{AsyncWriteExt, Pipe};
+use wasmer::{Module, Store};
+use wasmer_wasix::WasiEnv;
+
+mod sys {
+    #[tokio::test]
+    async fn test_poll_oneoff_zero_timeout_does_not_block() {
+        super::test_poll_oneoff_zero_timeout_does_not_block().await;
+    }
+    #[cfg(not(feature = "js"))]
+    #[tokio::test]
+    async fn test_poll_oneoff_huge_timeout_does_not_fire() {
+        super::test_poll_oneoff_huge_timeout_does_not_fire().await;
+    }
+}
+
+async fn test_poll_oneoff_zero_timeout_does_not_block() {
+    let mut store = Store::default();
+    let module = Module::new(
+        &store,
+        br#"
+    (module
+        (import "wasix_32v1" "fd_pipe" (func $fd_pipe (param i32 i32) (result i32)))
+        (import "wasix_32v1" "poll_oneoff" (func $poll_oneoff (param i32 i32 i32 i32) (result i32)))
+        (import "wasix_32v1" "proc_exit" (func $proc_exit (param i32)))
+
+        (memory 1)
+        (export "memory" (memory 0))
+
+        (func $main (export "_start")
+            ;; Create a pipe that nothing is ever written to
+            (call $fd_pipe (i32.const 0) (i32.const 4))
+            drop
+
+            ;; Subscription 1 reads from the pipe (userdata 7)
+            (i64.store (i32.const 64) (i64.const 7))
+            (i32.store8 (i32.const 72) (i32.const 1))             ;; FdRead
+            (i32.store (i32.const 80) (i32.load (i32.const 0)))   ;; fd
+
+            ;; Subscription 2 is a clock with a zero timeout (userdata 42)
+            (i64.store (i32.const 112) (i64.const 42))
+            (i32.store8 (i32.const 120) (i32.const 0))            ;; Clock
+            (i32.store (i32.const 128) (i32.const 1))             ;; Monotonic
+            (i64.store (i32.const 136) (i64.const 0))             ;; timeout
+
+            ;; Report the errno, the number of events and the userdata of
+            ;; the first event in the exit code
+            (call $proc_exit
+                (i32.or
+                    (i32.or
+                        (i32.shl
+                            (call $poll_oneoff
+                                (i32.const 64)   ;; in
+                                (i32.const 256)  ;; out
+                                (i32.const 2)    ;; nsubscriptions
+                                (i32.const 8)    ;; nevents
+                            )
+                            (i32.const 16)
+                        )
+                        (i32.shl (i32.load (i32.const 8)) (i32.const 8))
+                    )
+                    (i32.wrap_i64 (i64.load (i32.const 256)))
+                )
+            )
+        )
+    )
+    "#,
+    )
+    .unwrap();
+
+    let builder = WasiEnv::builder("command-name");
+
+    #[cfg(feature = "js")]
+    let result = builder.run_with_store(module, &mut store);
+    #[cfg(not(feature = "js"))]
+    let result = {
+        let (tx, rx) = std::sync::mpsc::channel();
+        std::thread::spawn(move || tx.send(builder.run_with_store(module, &mut store)));
+        rx.recv_timeout(std::time::Duration::from_secs(10))
+            .expect("poll_oneoff with a zero timeout blocked")
+    };
+
+    // Only the clock event is reported
+    let exit_code = result.unwrap_err().as_exit_code().unwrap();
+    assert_eq!(exit_code.raw(), (1 << 8) | 42);
+}
+
+#[cfg(not(feature = "js"))]
+async fn test_poll_oneoff_huge_timeout_does_not_fire() {
+    let mut store = Store::default();
+    let module = Module::new(
+        &store,
+        br#"
+    (module
+        (import "wasix_32v1" "poll_oneoff" (func $poll_oneoff (param i32 i32 i32 i32) (result i32)))
+        (import "wasix_32v1" "proc_exit" (func $proc_exit (param i32)))
+
+        (memory 1)
+        (export "memory" (memory 0))
+
+        (func $main (export "_start")
+            ;; Subscription 1 reads from stdin (userdata 7)
+            (i64.store (i32.const 64) (i64.const 7))
+            (i32.store8 (i32.const 72) (i32.const 1))             ;; FdRead
+            (i32.store (i32.const 80) (i32.const 0))              ;; fd
+
+            ;; Subscription 2 is a clock with a timeout close to the largest
+            ;; one possible (userdata 42)
+            (i64.store (i32.const 112) (i64.const 42))
+            (i32.store8 (i32.const 120) (i32.const 0))            ;; Clock
+            (i32.store (i32.const 128) (i32.const 1))             ;; Monotonic
+            (i64.store (i32.const 136) (i64.const -256))          ;; timeout
+
+            ;; Report the errno, the number of events and the userdata of
+            ;; the first event in the exit code
+            (call $proc_exit
+                (i32.or
+                    (i32.or
+                        (i32.shl
+                            (call $poll_oneoff
+                                (i32.const 64)   ;; in
+                                (i32.const 256)  ;; out
+                                (i32.const 2)    ;; nsubscriptions
+                                (i32.const 8)    ;; nevents
+                            )
+                            (i32.const 16)
+                        )
+                        (i32.shl (i32.load (i32.const 8)) (i32.const 8))
+                    )
+                    (i32.wrap_i64 (i64.load (i32.const 256)))
+                )
+            )
+        )
+    )
+    "#,
+    )
+    .unwrap();
+
+    let (mut stdin_tx, stdin_rx) = Pipe::channel();
+    let builder = WasiEnv::builder("command-name").stdin(Box::new(stdin_rx));
+
+    let (tx, rx) = std::sync::mpsc::channel();
+    std::thread::spawn(move || tx.send(builder.run_with_store(module, &mut store)));
+
+    // The clock must not fire while the guest waits for stdin
+    tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+    assert!(rx.try_recv().is_err(), "the huge timeout fired");
+
+    stdin_tx.write_all(b"hello").await.unwrap();
+    let result = rx
+        .recv_timeout(std::time::Duration::from_secs(10))
+        .expect("poll_oneoff did not see stdin become readable");
+
+    // Only the stdin event is reported
+    let exit_code = result.unwrap_err().as_exit_code().unwrap();
+    assert_eq!(exit_code.raw(), (1 << 8) | 7);
+}
@@ -0,0 +1,117 @@
+#![cfg(all(feature = "host-fs", not(feature = "js")))]
+
+use wasmer::{Module, Store};
+use wasmer_wasix::WasiEnv;
+
+mod sys {
+    #[tokio::test]
+    async fn test_preopen_host_root_ro() {
+        super::test_preopen_host_root_ro().await;
+    }
+}
+
+async fn test_preopen_host_root_ro() {
+    let host = tempfile::TempDir::new().unwrap();
+    std::fs::write(host.path().join("ro.txt"), "host").unwrap();
+
+    // The guest sees the host root, so the paths are the host paths
+    // relative to `/`
+    let dir = host.path().to_str().unwrap().trim_start_matches('/');
+    let file = format!("{dir}/ro.txt");
+    let new_file = format!("{dir}/new.txt");
+
+    let mut store = Store::default();
+    let wat = format!(
+        r#"
+    (module
+        (import "wasi_snapshot_preview1" "path_open" (func $path_open (param i32 i32 i32 i32 i32 i64 i64 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "fd_read" (func $fd_read (param i32 i32 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "proc_exit" (func $proc_exit (param i32)))
+
+        (memory 1)
+        (export "memory" (memory 0))
+
+        ;; io vectors for the read and the write
+        (data (i32.const 0) "\80\00\00\00\04\00\00\00")
+        (data (i32.const 8) "\40\00\00\00\04\00\00\00")
+        (data (i32.const 64) "evil")
+        (data (i32.const 256) "{file}")
+        (data (i32.const 1024) "{new_file}")
+
+        (func $main (export "_start")
+            (local $fd i32)
+            (local $errno1 i32)
+            (local $errno2 i32)
+
+            ;; Asking for write rights still opens the file, but read-only
+            (if (call $path_open
+                    (i32.const 4)   ;; dirfd
+                    (i32.const 0)   ;; dirflags
+                    (i32.const 256) ;; path
+                    (i32.const {file_len}) ;; path_len
+                    (i32.const 0)   ;; oflags
+                    (i64.const 66)  ;; rights_base (FD_READ | FD_WRITE)
+                    (i64.const 0)   ;; rights_inheriting
+                    (i32.const 0)   ;; fdflags
+                    (i32.const 16)  ;; fd_out
+                )
+                (then (call $proc_exit (i32.const 1)))
+            )
+            (local.set $fd (i32.load (i32.const 16)))
+
+            (if (call $fd_read (local.get $fd) (i32.const 0) (i32.const 1) (i32.const 20))
+                (then (call $proc_exit (i32.const 2)))
+            )
+            (if (i32.ne (i32.load (i32.const 128)) (i32.const 0x74736f68))
+                (then (call $proc_exit (i32.const 3)))
+            )
+
+            (local.set $errno1
+                (call $fd_write (local.get $fd) (i32.const 8) (i32.const 1) (i32.const 20))
+            )
+
+            ;; Creating a file on the host fails
+            (local.set $errno2
+                (call $path_open
+                    (i32.const 4) (i32.const 0) (i32.const 1024) (i32.const {new_file_len})
+                    (i32.const 1) (i64.const 66) (i64.const 0) (i32.const 0) (i32.const 16)
+                )
+            )
+
+            (call $proc_exit
+                (i32.or
+                    (i32.shl (local.get $errno1) (i32.const 8))
+                    (i32.ne (local.get $errno2) (i32.const 0))
+                )
+            )
+        )
+    )
+    "#,
+        file_len = file.len(),
+        new_file_len = new_file.len(),
+    );
+    let module = Module::new(&store, wat).unwrap();
+
+    let builder = WasiEnv::builder("command-name")
+        .preopen_host_root_ro()
+        .unwrap();
+
+    let handle = tokio::runtime::Handle::current();
+    let result = std::thread::spawn(move || {
+        let _guard = handle.enter();
+        builder.run_with_store(module, &mut store)
+    })
+    .join()
+    .unwrap();
+
+    // The write is refused (Errno::Access) and so is the creation
+    let exit_code = result.unwrap_err().as_exit_code().unwrap();
+    assert_eq!(exit_code.raw(), (2 << 8) | 1);
+
+    assert_eq!(
+        std::fs::read_to_string(host.path().join("ro.txt")).unwrap(),
+        "host"
+    );
+    assert!(!host.path().join("new.txt").exists());
+}
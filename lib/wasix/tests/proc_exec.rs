@@ -0,0 +1,80 @@
+#![cfg(not(feature = "js"))]
+
+use std::path::Path;
+
+use virtual_fs::{mem_fs, AsyncReadExt, FileSystem};
+use wasmer::{Module, Store};
+use wasmer_wasix::{Pipe, WasiEnv};
+
+mod sys {
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_exec_bare_name_from_search_path() {
+        super::test_exec_bare_name_from_search_path().await;
+    }
+}
+
+async fn test_exec_bare_name_from_search_path() {
+    let mut store = Store::default();
+    let module = Module::new(
+        &store,
+        br#"
+    (module
+        (import "wasix_32v1" "proc_exec" (func $proc_exec (param i32 i32 i32 i32)))
+        (import "wasix_32v1" "proc_exit" (func $proc_exit (param i32)))
+
+        (memory 1)
+        (export "memory" (memory 0))
+
+        (data (i32.const 32) "wasmer")
+        (data (i32.const 48) "wasmer\n--help")
+
+        (func $main (export "_start")
+            ;; The program is named without a directory
+            (call $proc_exec
+                (i32.const 32) ;; name
+                (i32.const 6)  ;; name_len
+                (i32.const 48) ;; args
+                (i32.const 13) ;; args_len
+            )
+            (call $proc_exit (i32.const 100))
+        )
+    )
+    "#,
+    )
+    .unwrap();
+
+    // The built-in `wasmer` command lives at `/bin/wasmer`
+    let fs = mem_fs::FileSystem::default();
+    fs.create_dir(Path::new("/bin")).unwrap();
+    fs.new_open_options()
+        .write(true)
+        .create(true)
+        .open(Path::new("/bin/wasmer"))
+        .unwrap();
+
+    let (stderr_tx, mut stderr_rx) = Pipe::channel();
+    let builder = WasiEnv::builder("command-name")
+        .fs(Box::new(fs))
+        .preopen_dir("/")
+        .unwrap()
+        .exec_search_path(["/usr/bin", "/bin"])
+        .stderr(Box::new(stderr_tx));
+
+    let handle = tokio::runtime::Handle::current();
+    let result = std::thread::spawn(move || {
+        let _guard = handle.enter();
+        builder.run_with_store(module, &mut store)
+    })
+    .join()
+    .unwrap();
+
+    // The process was replaced by the program found in the search path
+    result.unwrap();
+
+    let mut stderr = String::new();
+    stderr_rx.read_to_string(&mut stderr).await.unwrap();
+    assert!(
+        stderr.contains("wasmer <SUBCOMMAND>"),
+        "unexpected output: {stderr:?}"
+    );
+}
@@ -0,0 +1,117 @@
+#![cfg(not(feature = "js"))]
+
+use std::path::Path;
+
+use virtual_fs::{mem_fs, FileSystem};
+use wasmer::{Instance, Module, Store};
+use wasmer_wasix::{types::Signal, SignalDisposition, WasiEnv, WasiFunctionEnv};
+
+mod sys {
+    #[tokio::test]
+    async fn test_fork_inherits_cwd_and_signals() {
+        super::test_fork_inherits_cwd_and_signals().await;
+    }
+}
+
+/// Has the guest look up its working directory
+fn read_cwd(instance: &Instance, store: &mut Store) -> String {
+    let cwd = instance.exports.get_function("cwd").unwrap();
+    let len = cwd.call(store, &[]).unwrap()[0].unwrap_i32() as usize;
+
+    let memory = instance.exports.get_memory("memory").unwrap();
+    let mut buf = vec![0u8; len];
+    memory.view(store).read(16, &mut buf).unwrap();
+    String::from_utf8(buf).unwrap()
+}
+
+async fn test_fork_inherits_cwd_and_signals() {
+    let mut store = Store::default();
+    let module = Module::new(
+        &store,
+        br#"
+    (module
+        (import "wasix_32v1" "chdir" (func $chdir (param i32 i32) (result i32)))
+        (import "wasix_32v1" "getcwd" (func $getcwd (param i32 i32) (result i32)))
+        (import "wasix_32v1" "callback_signal" (func $callback_signal (param i32 i32)))
+
+        (memory 1)
+        (export "memory" (memory 0))
+
+        (data (i32.const 64) "handler")
+        (data (i32.const 80) "/app")
+
+        (func $handler (export "handler") (param i32))
+
+        ;; Changes directory and registers the signal handler
+        (func $main (export "_start")
+            (if (call $chdir (i32.const 80) (i32.const 4))
+                (then unreachable)
+            )
+            (call $callback_signal (i32.const 64) (i32.const 7))
+        )
+
+        ;; Reads the working directory into 16 and returns its length
+        (func (export "cwd") (result i32)
+            (i32.store (i32.const 8) (i32.const 256))
+            (if (call $getcwd (i32.const 16) (i32.const 8))
+                (then unreachable)
+            )
+            (i32.load (i32.const 8))
+        )
+    )
+    "#,
+    )
+    .unwrap();
+
+    let fs = mem_fs::FileSystem::default();
+    fs.create_dir(Path::new("/app")).unwrap();
+    let builder = WasiEnv::builder("command-name")
+        .fs(Box::new(fs))
+        .preopen_dir("/")
+        .unwrap();
+
+    let handle = tokio::runtime::Handle::current();
+    std::thread::spawn(move || {
+        let _guard = handle.enter();
+        let (instance, env) = builder.instantiate(module.clone(), &mut store).unwrap();
+        let start = instance.exports.get_function("_start").unwrap();
+        start.call(&mut store, &[]).unwrap();
+
+        // A blocked signal is pending on the parent when it forks
+        let parent = env.data(&store).process.clone();
+        parent.set_signal_disposition(Signal::Sigint, SignalDisposition::Ignore);
+        parent.block_signal(Signal::Sigusr1);
+        parent.signal_process(Signal::Sigusr1);
+        assert_eq!(parent.pending_signals(), [Signal::Sigusr1]);
+
+        let (child_env, _child_handle) = env.data(&store).fork().unwrap();
+
+        // The child inherits the signal mask and dispositions...
+        let child = child_env.process.clone();
+        assert_eq!(child.signal_mask(), parent.signal_mask());
+        assert_eq!(child.signal_dispositions(), parent.signal_dispositions());
+        assert_eq!(
+            child.signal_dispositions()[&Signal::Sigint],
+            SignalDisposition::Ignore
+        );
+        assert_eq!(
+            child.signal_dispositions()[&Signal::Sigterm],
+            SignalDisposition::Handler
+        );
+        // ...but not the signals pending on the parent
+        assert!(child.pending_signals().is_empty());
+        assert_eq!(parent.pending_signals(), [Signal::Sigusr1]);
+
+        // The child starts out in the working directory of the parent
+        let mut child_env = WasiFunctionEnv::new(&mut store, child_env);
+        let imports = child_env.import_object(&mut store, &module).unwrap();
+        let child_instance = Instance::new(&mut store, &module, &imports).unwrap();
+        child_env
+            .initialize(&mut store, child_instance.clone())
+            .unwrap();
+        assert_eq!(read_cwd(&child_instance, &mut store), "/app");
+        assert_eq!(read_cwd(&instance, &mut store), "/app");
+    })
+    .join()
+    .unwrap();
+}
@@ -0,0 +1,336 @@
+#[cfg(not(feature = "js"))]
+use wasmer::Instance;
+use wasmer::{Module, Store};
+#[cfg(not(feature = "js"))]
+use wasmer_wasix::WasiFunctionEnv;
+use wasmer_wasix::{types::wasi::Errno, WasiEnv};
+
+mod sys {
+    #[tokio::test]
+    async fn test_proc_self_cmdline() {
+        super::test_proc_self_cmdline().await;
+    }
+
+    #[cfg(not(feature = "js"))]
+    #[tokio::test]
+    async fn test_proc_self_fd_of_forked_child() {
+        super::test_proc_self_fd_of_forked_child().await;
+    }
+
+    #[cfg(not(feature = "js"))]
+    #[tokio::test]
+    async fn test_proc_dirs_follow_the_processes() {
+        super::test_proc_dirs_follow_the_processes().await;
+    }
+}
+
+async fn test_proc_self_cmdline() {
+    let mut store = Store::default();
+    let module = Module::new(
+        &store,
+        br#"
+    (module
+        (import "wasi_snapshot_preview1" "path_open" (func $path_open (param i32 i32 i32 i32 i32 i64 i64 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "fd_read" (func $fd_read (param i32 i32 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "proc_exit" (func $proc_exit (param i32)))
+
+        (memory 1)
+        (export "memory" (memory 0))
+
+        ;; iovec pointing at a 64 byte buffer
+        (data (i32.const 8) "\00\01\00\00\40\00\00\00")
+        (data (i32.const 32) "proc/self/cmdline")
+        (data (i32.const 128) "command-name\00a\00bc\00")
+
+        (func $main (export "_start")
+            (local $errno i32)
+            (local $nread i32)
+            (local $i i32)
+
+            (local.set $errno
+                (call $path_open
+                    (i32.const 4)   ;; dirfd
+                    (i32.const 0)   ;; dirflags
+                    (i32.const 32)  ;; path
+                    (i32.const 17)  ;; path_len
+                    (i32.const 0)   ;; oflags
+                    (i64.const 2)   ;; rights_base (FD_READ)
+                    (i64.const 0)   ;; rights_inheriting
+                    (i32.const 0)   ;; fdflags
+                    (i32.const 0)   ;; fd_out
+                )
+            )
+            (if (local.get $errno)
+                (then (call $proc_exit (i32.add (i32.const 100) (local.get $errno))))
+            )
+
+            (local.set $errno
+                (call $fd_read (i32.load (i32.const 0)) (i32.const 8) (i32.const 1) (i32.const 16))
+            )
+            (if (local.get $errno)
+                (then (call $proc_exit (i32.add (i32.const 200) (local.get $errno))))
+            )
+
+            ;; The contents must be the NUL terminated arguments
+            (local.set $nread (i32.load (i32.const 16)))
+            (if (i32.ne (local.get $nread) (i32.const 18))
+                (then (call $proc_exit (i32.const 1)))
+            )
+            (block $done
+                (loop $cmp
+                    (br_if $done (i32.eq (local.get $i) (local.get $nread)))
+                    (if (i32.ne
+                            (i32.load8_u (i32.add (i32.const 256) (local.get $i)))
+                            (i32.load8_u (i32.add (i32.const 128) (local.get $i))))
+                        (then (call $proc_exit (i32.const 2)))
+                    )
+                    (local.set $i (i32.add (local.get $i) (i32.const 1)))
+                    (br $cmp)
+                )
+            )
+
+            (call $proc_exit (local.get $nread))
+        )
+    )
+    "#,
+    )
+    .unwrap();
+
+    let builder = WasiEnv::builder("command-name")
+        .args(["a", "bc"])
+        .proc_fs(true)
+        .preopen_dir("/")
+        .unwrap();
+
+    #[cfg(feature = "js")]
+    let result = builder.run_with_store(module, &mut store);
+    #[cfg(not(feature = "js"))]
+    let result = std::thread::spawn(move || builder.run_with_store(module, &mut store))
+        .join()
+        .unwrap();
+
+    // The guest read all of `command-name\0a\0bc\0`
+    let exit_code = result.unwrap_err().as_exit_code().unwrap();
+    assert_eq!(exit_code.raw(), 18);
+}
+
+/// Has the guest list `/proc/self/fd` and returns the fds in there, except
+/// for the one it lists them through
+#[cfg(not(feature = "js"))]
+fn list_fds(instance: &Instance, store: &mut Store) -> Vec<u32> {
+    let list = instance.exports.get_function("list").unwrap();
+    let len = list.call(store, &[]).unwrap()[0].unwrap_i32() as usize;
+
+    let memory = instance.exports.get_memory("memory").unwrap();
+    let mut buf = vec![0u8; len];
+    memory.view(store).read(1024, &mut buf).unwrap();
+    let mut dir_fd = [0u8; 4];
+    memory.view(store).read(0, &mut dir_fd).unwrap();
+    let dir_fd = u32::from_le_bytes(dir_fd);
+
+    // Every dirent is a 24 byte header followed by the name
+    let mut fds = Vec::new();
+    let mut buf = buf.as_slice();
+    while buf.len() >= 24 {
+        let namlen = u32::from_le_bytes(buf[16..20].try_into().unwrap()) as usize;
+        let name = std::str::from_utf8(&buf[24..24 + namlen]).unwrap();
+        match name.parse() {
+            Ok(fd) if fd != dir_fd => fds.push(fd),
+            _ => {}
+        }
+        buf = &buf[24 + namlen..];
+    }
+    fds
+}
+
+#[cfg(not(feature = "js"))]
+async fn test_proc_self_fd_of_forked_child() {
+    let mut store = Store::default();
+    let module = Module::new(
+        &store,
+        br#"
+    (module
+        (import "wasi_snapshot_preview1" "path_open" (func $path_open (param i32 i32 i32 i32 i32 i64 i64 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "fd_readdir" (func $fd_readdir (param i32 i32 i32 i64 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "fd_close" (func $fd_close (param i32) (result i32)))
+
+        (memory 1)
+        (export "memory" (memory 0))
+
+        (data (i32.const 32) "proc/self/cmdline")
+        (data (i32.const 64) "proc/self/fd")
+
+        (func (export "_start"))
+
+        ;; Opens `/proc/self/cmdline` and returns the new fd
+        (func (export "open") (result i32)
+            (if (call $path_open (i32.const 4) (i32.const 0) (i32.const 32) (i32.const 17)
+                    (i32.const 0) (i64.const 2) (i64.const 0) (i32.const 0) (i32.const 0))
+                (then unreachable)
+            )
+            (i32.load (i32.const 0))
+        )
+
+        ;; Reads the entries of `/proc/self/fd` into 1024 and returns their size,
+        ;; the fd they were read through is left at 0
+        (func (export "list") (result i32)
+            (local $fd i32)
+            (if (call $path_open (i32.const 4) (i32.const 0) (i32.const 64) (i32.const 12)
+                    (i32.const 2) (i64.const 16384) (i64.const 0) (i32.const 0) (i32.const 0))
+                (then unreachable)
+            )
+            (local.set $fd (i32.load (i32.const 0)))
+            (if (call $fd_readdir (local.get $fd) (i32.const 1024) (i32.const 4096) (i64.const 0) (i32.const 16))
+                (then unreachable)
+            )
+            (if (call $fd_close (local.get $fd))
+                (then unreachable)
+            )
+            (i32.store (i32.const 0) (local.get $fd))
+            (i32.load (i32.const 16))
+        )
+    )
+    "#,
+    )
+    .unwrap();
+
+    let builder = WasiEnv::builder("command-name")
+        .proc_fs(true)
+        .preopen_dir("/")
+        .unwrap();
+
+    let handle = tokio::runtime::Handle::current();
+    std::thread::spawn(move || {
+        let _guard = handle.enter();
+        let (instance, env) = builder.instantiate(module.clone(), &mut store).unwrap();
+        let start = instance.exports.get_function("_start").unwrap();
+        start.call(&mut store, &[]).unwrap();
+
+        let (child_env, _child_handle) = env.data(&store).fork().unwrap();
+        let mut child_env = WasiFunctionEnv::new(&mut store, child_env);
+        let imports = child_env.import_object(&mut store, &module).unwrap();
+        let child_instance = Instance::new(&mut store, &module, &imports).unwrap();
+        child_env
+            .initialize(&mut store, child_instance.clone())
+            .unwrap();
+
+        // Both start out with the same fds...
+        let fds = list_fds(&instance, &mut store);
+        assert!(fds.contains(&4));
+        assert_eq!(list_fds(&child_instance, &mut store), fds);
+
+        // ...but an fd that only the child opens is only listed for the child
+        let open = child_instance.exports.get_function("open").unwrap();
+        let fd = open.call(&mut store, &[]).unwrap()[0].unwrap_i32() as u32;
+        assert!(!fds.contains(&fd));
+        assert!(list_fds(&child_instance, &mut store).contains(&fd));
+        assert_eq!(list_fds(&instance, &mut store), fds);
+    })
+    .join()
+    .unwrap();
+}
+
+/// Has the guest resolve `/proc/self` and returns where it links to
+#[cfg(not(feature = "js"))]
+fn proc_self(instance: &Instance, store: &mut Store) -> String {
+    let self_link = instance.exports.get_function("self_link").unwrap();
+    let len = self_link.call(store, &[]).unwrap()[0].unwrap_i32() as usize;
+
+    let memory = instance.exports.get_memory("memory").unwrap();
+    let mut buf = vec![0u8; len];
+    memory.view(store).read(2048, &mut buf).unwrap();
+    String::from_utf8(buf).unwrap()
+}
+
+/// Has the guest stat `path` and returns the errno
+#[cfg(not(feature = "js"))]
+fn stat(instance: &Instance, store: &mut Store, path: &str) -> Errno {
+    let memory = instance.exports.get_memory("memory").unwrap();
+    memory.view(store).write(512, path.as_bytes()).unwrap();
+
+    let stat = instance.exports.get_function("stat").unwrap();
+    let errno = stat
+        .call(store, &[512.into(), (path.len() as i32).into()])
+        .unwrap()[0]
+        .unwrap_i32();
+    Errno::try_from(errno as u16).unwrap()
+}
+
+#[cfg(not(feature = "js"))]
+async fn test_proc_dirs_follow_the_processes() {
+    let mut store = Store::default();
+    let module = Module::new(
+        &store,
+        br#"
+    (module
+        (import "wasi_snapshot_preview1" "path_readlink" (func $path_readlink (param i32 i32 i32 i32 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "path_filestat_get" (func $path_filestat_get (param i32 i32 i32 i32 i32) (result i32)))
+
+        (memory 1)
+        (export "memory" (memory 0))
+
+        (data (i32.const 32) "proc/self")
+
+        (func (export "_start"))
+
+        ;; Reads where `/proc/self` links to into 2048 and returns its length
+        (func (export "self_link") (result i32)
+            (if (call $path_readlink (i32.const 4) (i32.const 32) (i32.const 9)
+                    (i32.const 2048) (i32.const 32) (i32.const 16))
+                (then unreachable)
+            )
+            (i32.load (i32.const 16))
+        )
+
+        (func (export "stat") (param $path i32) (param $len i32) (result i32)
+            (call $path_filestat_get (i32.const 4) (i32.const 0)
+                (local.get $path) (local.get $len) (i32.const 3072))
+        )
+    )
+    "#,
+    )
+    .unwrap();
+
+    let builder = WasiEnv::builder("command-name")
+        .proc_fs(true)
+        .preopen_dir("/")
+        .unwrap();
+
+    let handle = tokio::runtime::Handle::current();
+    std::thread::spawn(move || {
+        let _guard = handle.enter();
+        let (instance, env) = builder.instantiate(module.clone(), &mut store).unwrap();
+        let start = instance.exports.get_function("_start").unwrap();
+        start.call(&mut store, &[]).unwrap();
+
+        let (child_env, _child_handle) = env.data(&store).fork().unwrap();
+        let child_process = child_env.process.clone();
+        let mut child_env = WasiFunctionEnv::new(&mut store, child_env);
+        let imports = child_env.import_object(&mut store, &module).unwrap();
+        let child_instance = Instance::new(&mut store, &module, &imports).unwrap();
+        child_env
+            .initialize(&mut store, child_instance.clone())
+            .unwrap();
+
+        // The directories are named after the processes
+        let pid = env.data(&store).process.pid().raw();
+        let child_pid = child_process.pid().raw();
+        assert_eq!(proc_self(&instance, &mut store), pid.to_string());
+        assert_eq!(
+            proc_self(&child_instance, &mut store),
+            child_pid.to_string()
+        );
+        let child_dir = format!("proc/{child_pid}");
+        assert_eq!(stat(&instance, &mut store, &child_dir), Errno::Success);
+
+        // ...and the directory of the child goes away once it exits
+        child_process.terminate(Errno::Success.into());
+        assert_eq!(stat(&instance, &mut store, &child_dir), Errno::Noent);
+        assert_eq!(
+            stat(&instance, &mut store, &format!("proc/{pid}")),
+            Errno::Success
+        );
+    })
+    .join()
+    .unwrap();
+}
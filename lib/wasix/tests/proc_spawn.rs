@@ -0,0 +1,208 @@
+use std::{sync::Arc, time::Duration};
+
+use futures::future::BoxFuture;
+use virtual_fs::AsyncWriteExt;
+use wasmer::{Module, Store};
+use wasmer_wasix::{
+    types::wasi::{Errno, ExitCode},
+    SpawnHandler, SpawnStdio, WasiEnv,
+};
+
+mod sys {
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_proc_spawn_native_handler() {
+        super::test_proc_spawn_native_handler().await;
+    }
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_proc_spawn_native_handler_panics() {
+        super::test_proc_spawn_misbehaving_handler("panic", super::Errno::Noexec).await;
+    }
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_proc_spawn_native_handler_hangs() {
+        super::test_proc_spawn_misbehaving_handler("hang", super::Errno::Timedout).await;
+    }
+}
+
+/// Runs `echo` natively
+#[derive(Debug)]
+struct NativeEcho;
+
+impl SpawnHandler for NativeEcho {
+    fn claims(&self, name: &str) -> bool {
+        name == "echo"
+    }
+
+    fn spawn(
+        &self,
+        _name: &str,
+        args: Vec<String>,
+        stdio: SpawnStdio,
+    ) -> BoxFuture<'static, ExitCode> {
+        Box::pin(async move {
+            if let Some(mut stdout) = stdio.stdout {
+                let line = format!("{}\n", args[1..].join(" "));
+                stdout.write_all(line.as_bytes()).await.unwrap();
+            }
+            Errno::Success.into()
+        })
+    }
+}
+
+/// Runs programs that either panic or never finish
+#[derive(Debug)]
+struct Misbehaving;
+
+impl SpawnHandler for Misbehaving {
+    fn claims(&self, name: &str) -> bool {
+        name == "panic" || name == "hang"
+    }
+
+    fn spawn(
+        &self,
+        name: &str,
+        _args: Vec<String>,
+        _stdio: SpawnStdio,
+    ) -> BoxFuture<'static, ExitCode> {
+        let panics = name == "panic";
+        Box::pin(async move {
+            if panics {
+                panic!("the native program crashed");
+            }
+            futures::future::pending().await
+        })
+    }
+
+    fn timeout(&self) -> Option<Duration> {
+        Some(Duration::from_millis(100))
+    }
+}
+
+async fn test_proc_spawn_native_handler() {
+    let mut store = Store::default();
+    let module = Module::new(&store, br#"
+    (module
+        (import "wasix_32v1" "proc_spawn" (func $proc_spawn (param i32 i32 i32 i32 i32 i32 i32 i32 i32 i32 i32 i32 i32) (result i32)))
+        (import "wasix_32v1" "fd_read" (func $fd_read (param i32 i32 i32 i32) (result i32)))
+        (import "wasix_32v1" "proc_exit" (func $proc_exit (param i32)))
+
+        (memory 1)
+        (export "memory" (memory 0))
+
+        (data (i32.const 32) "echo")
+        (data (i32.const 48) "echo\nhello\nworld")
+        (data (i32.const 80) ".")
+
+        (func $main (export "_start")
+            (call $proc_spawn
+                (i32.const 32)  ;; name
+                (i32.const 4)   ;; name_len
+                (i32.const 0)   ;; chroot
+                (i32.const 48)  ;; args
+                (i32.const 16)  ;; args_len
+                (i32.const 0)   ;; preopen
+                (i32.const 0)   ;; preopen_len
+                (i32.const 2)   ;; stdin (Null)
+                (i32.const 0)   ;; stdout (Piped)
+                (i32.const 2)   ;; stderr (Null)
+                (i32.const 80)  ;; working_dir
+                (i32.const 1)   ;; working_dir_len
+                (i32.const 200) ;; ret_handles
+            )
+            drop
+
+            ;; Read what the process wrote to its stdout
+            (i32.store (i32.const 8) (i32.const 128))
+            (i32.store (i32.const 12) (i32.const 64))
+            (call $fd_read (i32.load (i32.const 216)) (i32.const 8) (i32.const 1) (i32.const 16))
+            drop
+
+            ;; Report the number of bytes read and the first one in the exit code
+            (call $proc_exit
+                (i32.or
+                    (i32.shl (i32.load (i32.const 16)) (i32.const 8))
+                    (i32.load8_u (i32.const 128))
+                )
+            )
+        )
+    )
+    "#).unwrap();
+
+    let builder = WasiEnv::builder("command-name").spawn_handler(Arc::new(NativeEcho));
+
+    let handle = tokio::runtime::Handle::current();
+    let result = std::thread::spawn(move || {
+        let _guard = handle.enter();
+        builder.run_with_store(module, &mut store)
+    })
+    .join()
+    .unwrap();
+
+    // "hello world\n" was read from the pipe
+    let exit_code = result.unwrap_err().as_exit_code().unwrap();
+    assert_eq!(exit_code.raw(), (12 << 8) | b'h' as i32);
+}
+
+async fn test_proc_spawn_misbehaving_handler(name: &'static str, expected: Errno) {
+    let mut store = Store::default();
+    let module = Module::new(
+        &store,
+        format!(
+            r#"
+    (module
+        (import "wasix_32v1" "proc_spawn" (func $proc_spawn (param i32 i32 i32 i32 i32 i32 i32 i32 i32 i32 i32 i32 i32) (result i32)))
+
+        (memory 1)
+        (export "memory" (memory 0))
+
+        (data (i32.const 32) "{name}")
+        (data (i32.const 80) ".")
+
+        (func $main (export "_start")
+            (call $proc_spawn
+                (i32.const 32)  ;; name
+                (i32.const {len})   ;; name_len
+                (i32.const 0)   ;; chroot
+                (i32.const 32)  ;; args
+                (i32.const {len})   ;; args_len
+                (i32.const 0)   ;; preopen
+                (i32.const 0)   ;; preopen_len
+                (i32.const 2)   ;; stdin (Null)
+                (i32.const 2)   ;; stdout (Null)
+                (i32.const 2)   ;; stderr (Null)
+                (i32.const 80)  ;; working_dir
+                (i32.const 1)   ;; working_dir_len
+                (i32.const 200) ;; ret_handles
+            )
+            drop
+        )
+    )
+    "#,
+            len = name.len(),
+        ),
+    )
+    .unwrap();
+
+    let builder = WasiEnv::builder("command-name").spawn_handler(Arc::new(Misbehaving));
+
+    let handle = tokio::runtime::Handle::current();
+    let exit_code = std::thread::spawn(move || {
+        let _guard = handle.enter();
+        let (instance, env) = builder.instantiate(module, &mut store).unwrap();
+        let process = env.data(&store).process.clone();
+
+        let start = instance.exports.get_function("_start").unwrap();
+        start.call(&mut store, &[]).unwrap();
+
+        // The process of the program exits with an error
+        let child = process.lock().children[0].clone();
+        handle.block_on(async move {
+            tokio::time::timeout(Duration::from_secs(10), child.join())
+                .await
+                .expect("the process of the program did not exit")
+        })
+    })
+    .join()
+    .unwrap();
+
+    assert_eq!(exit_code.unwrap(), ExitCode::Errno(expected));
+}
@@ -0,0 +1,84 @@
+#![cfg(feature = "sys-thread")]
+
+use std::sync::{Arc, Mutex};
+
+use wasmer::{Module, Store};
+use wasmer_wasix::{
+    runtime::{task_manager::tokio::TokioTaskManager, ProcessExitStatus},
+    PluggableRuntime, WasiEnv,
+};
+
+mod sys {
+    #[tokio::test]
+    async fn test_process_exit_hook_on_exit() {
+        super::test_process_exit_hook_on_exit().await;
+    }
+    #[tokio::test]
+    async fn test_process_exit_hook_on_trap() {
+        super::test_process_exit_hook_on_trap().await;
+    }
+}
+
+/// Runs the module and returns the statuses reported to the exit hook
+fn run(wat: &[u8]) -> Vec<ProcessExitStatus> {
+    let mut store = Store::default();
+    let module = Module::new(&store, wat).unwrap();
+
+    let exits = Arc::new(Mutex::new(Vec::new()));
+    let mut runtime = PluggableRuntime::new(Arc::new(TokioTaskManager::default()));
+    runtime.set_process_exit_hook({
+        let exits = exits.clone();
+        move |exit| exits.lock().unwrap().push(exit.status.clone())
+    });
+
+    let builder = WasiEnv::builder("command-name").runtime(Arc::new(runtime));
+
+    let handle = tokio::runtime::Handle::current();
+    std::thread::spawn(move || {
+        let _guard = handle.enter();
+        builder.run_with_store(module, &mut store)
+    })
+    .join()
+    .unwrap()
+    .ok();
+
+    let exits = exits.lock().unwrap();
+    exits.clone()
+}
+
+async fn test_process_exit_hook_on_exit() {
+    let exits = run(br#"
+    (module
+        (import "wasi_snapshot_preview1" "proc_exit" (func $proc_exit (param i32)))
+
+        (memory 1)
+        (export "memory" (memory 0))
+
+        (func $main (export "_start")
+            (call $proc_exit (i32.const 3))
+        )
+    )
+    "#);
+
+    assert_eq!(exits, [ProcessExitStatus::Exited(3.into())]);
+}
+
+async fn test_process_exit_hook_on_trap() {
+    let exits = run(br#"
+    (module
+        (memory 1)
+        (export "memory" (memory 0))
+
+        (func $main (export "_start")
+            unreachable
+        )
+    )
+    "#);
+
+    assert_eq!(exits.len(), 1);
+    assert!(
+        matches!(&exits[0], ProcessExitStatus::Trapped(msg) if msg.contains("unreachable")),
+        "{:?}",
+        exits
+    );
+}
@@ -0,0 +1,123 @@
+use std::path::Path;
+
+use virtual_fs::{mem_fs, AsyncWriteExt, FileSystem, ReadOnlyFileSystem};
+use wasmer::{Module, Store};
+use wasmer_wasix::WasiEnv;
+
+mod sys {
+    #[tokio::test]
+    async fn test_read_only_fs() {
+        super::test_read_only_fs().await;
+    }
+}
+
+async fn test_read_only_fs() {
+    let fs = mem_fs::FileSystem::default();
+    let mut file = fs
+        .new_open_options()
+        .create(true)
+        .write(true)
+        .open("/data.txt")
+        .unwrap();
+    file.write_all(b"data").await.unwrap();
+
+    let mut store = Store::default();
+    let module = Module::new(
+        &store,
+        br#"
+    (module
+        (import "wasi_snapshot_preview1" "path_open" (func $path_open (param i32 i32 i32 i32 i32 i64 i64 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "path_unlink_file" (func $path_unlink_file (param i32 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "path_create_directory" (func $path_create_directory (param i32 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "fd_filestat_set_size" (func $fd_filestat_set_size (param i32 i64) (result i32)))
+        (import "wasi_snapshot_preview1" "fd_read" (func $fd_read (param i32 i32 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "proc_exit" (func $proc_exit (param i32)))
+
+        (memory 1)
+        (export "memory" (memory 0))
+
+        ;; io vector for the read
+        (data (i32.const 0) "\80\00\00\00\04\00\00\00")
+        (data (i32.const 32) "data.txt")
+        (data (i32.const 48) "new.txt")
+        (data (i32.const 64) "dir")
+
+        ;; Every step must fail with Errno::Rofs
+        (func $check_rofs (param $step i32) (param $errno i32)
+            (if (i32.ne (local.get $errno) (i32.const 69))
+                (then
+                    (call $proc_exit
+                        (i32.or (i32.shl (local.get $step) (i32.const 8)) (local.get $errno))
+                    )
+                )
+            )
+        )
+
+        (func $open (param $path i32) (param $path_len i32) (param $oflags i32) (param $rights i64) (result i32)
+            (call $path_open
+                (i32.const 4)           ;; dirfd
+                (i32.const 0)           ;; dirflags
+                (local.get $path)
+                (local.get $path_len)
+                (local.get $oflags)
+                (local.get $rights)     ;; rights_base
+                (i64.const 0)           ;; rights_inheriting
+                (i32.const 0)           ;; fdflags
+                (i32.const 16)          ;; fd_out
+            )
+        )
+
+        (func $main (export "_start")
+            (local $fd i32)
+
+            ;; Opening the file for writing (FD_READ | FD_WRITE)
+            (call $check_rofs (i32.const 1) (call $open (i32.const 32) (i32.const 8) (i32.const 0) (i64.const 66)))
+            ;; Creating a new file
+            (call $check_rofs (i32.const 2) (call $open (i32.const 48) (i32.const 7) (i32.const 1) (i64.const 2)))
+            ;; Removing the file
+            (call $check_rofs (i32.const 3) (call $path_unlink_file (i32.const 4) (i32.const 32) (i32.const 8)))
+            ;; Creating a directory
+            (call $check_rofs (i32.const 4) (call $path_create_directory (i32.const 4) (i32.const 64) (i32.const 3)))
+
+            ;; Truncating a file that was opened for reading
+            ;; (FD_READ | FD_FILESTAT_SET_SIZE)
+            (if (call $open (i32.const 32) (i32.const 8) (i32.const 0) (i64.const 4194306))
+                (then (call $proc_exit (i32.const 1)))
+            )
+            (local.set $fd (i32.load (i32.const 16)))
+            (call $check_rofs (i32.const 5) (call $fd_filestat_set_size (local.get $fd) (i64.const 0)))
+
+            ;; The file is still there and unchanged
+            (if (call $fd_read (local.get $fd) (i32.const 0) (i32.const 1) (i32.const 20))
+                (then (call $proc_exit (i32.const 2)))
+            )
+            (if (i32.ne (i32.load (i32.const 20)) (i32.const 4))
+                (then (call $proc_exit (i32.const 3)))
+            )
+            (call $proc_exit (i32.load8_u (i32.const 128)))
+        )
+    )
+    "#,
+    )
+    .unwrap();
+
+    let builder = WasiEnv::builder("command-name")
+        .fs(Box::new(ReadOnlyFileSystem::new(fs.clone())))
+        .preopen_dir("/")
+        .unwrap();
+
+    #[cfg(feature = "js")]
+    let result = builder.run_with_store(module, &mut store);
+    #[cfg(not(feature = "js"))]
+    let result = std::thread::spawn(move || builder.run_with_store(module, &mut store))
+        .join()
+        .unwrap();
+
+    // Every change was refused and the file could still be read
+    let exit_code = result.unwrap_err().as_exit_code().unwrap();
+    assert_eq!(exit_code.raw(), b'd' as i32);
+
+    assert_eq!(fs.metadata(Path::new("/data.txt")).unwrap().len(), 4);
+    assert!(fs.metadata(Path::new("/new.txt")).is_err());
+    assert!(fs.metadata(Path::new("/dir")).is_err());
+}
@@ -0,0 +1,76 @@
+use std::{
+    collections::HashMap,
+    net::{IpAddr, Ipv4Addr},
+};
+
+use wasmer::{Module, Store};
+use wasmer_wasix::WasiEnv;
+
+mod sys {
+    #[tokio::test]
+    async fn test_resolve_static_hosts() {
+        super::test_resolve_static_hosts().await;
+    }
+}
+
+async fn test_resolve_static_hosts() {
+    let mut store = Store::default();
+    let module = Module::new(
+        &store,
+        br#"
+    (module
+        (import "wasix_32v1" "resolve" (func $resolve (param i32 i32 i32 i32 i32 i32) (result i32)))
+        (import "wasix_32v1" "proc_exit" (func $proc_exit (param i32)))
+
+        (memory 1)
+        (export "memory" (memory 0))
+
+        (data (i32.const 32) "example.test")
+
+        (func $main (export "_start")
+            ;; Report the errno, the number of addresses and the first and
+            ;; last octet of the first address in the exit code
+            (call $proc_exit
+                (i32.or
+                    (i32.or
+                        (i32.shl
+                            (call $resolve
+                                (i32.const 32)  ;; host
+                                (i32.const 12)  ;; host_len
+                                (i32.const 0)   ;; port
+                                (i32.const 64)  ;; addrs
+                                (i32.const 4)   ;; naddrs
+                                (i32.const 0)   ;; ret_naddrs
+                            )
+                            (i32.const 24)
+                        )
+                        (i32.shl (i32.load (i32.const 0)) (i32.const 16))
+                    )
+                    (i32.or
+                        (i32.shl (i32.load8_u (i32.const 66)) (i32.const 8))
+                        (i32.load8_u (i32.const 69))
+                    )
+                )
+            )
+        )
+    )
+    "#,
+    )
+    .unwrap();
+
+    let builder = WasiEnv::builder("command-name").hosts(HashMap::from([(
+        "example.test".to_string(),
+        vec![IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1))],
+    )]));
+
+    #[cfg(feature = "js")]
+    let result = builder.run_with_store(module, &mut store);
+    #[cfg(not(feature = "js"))]
+    let result = std::thread::spawn(move || builder.run_with_store(module, &mut store))
+        .join()
+        .unwrap();
+
+    // One address (10.0.0.1) without asking the network
+    let exit_code = result.unwrap_err().as_exit_code().unwrap();
+    assert_eq!(exit_code.raw(), (1 << 16) | (10 << 8) | 1);
+}
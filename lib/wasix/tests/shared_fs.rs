@@ -0,0 +1,139 @@
+use wasmer::{Module, Store};
+use wasmer_wasix::{SharedFileSystem, WasiEnv};
+
+mod sys {
+    #[tokio::test]
+    async fn test_shared_fs_across_instances() {
+        super::test_shared_fs_across_instances().await;
+    }
+}
+
+async fn test_shared_fs_across_instances() {
+    let mut store = Store::default();
+    let writer = Module::new(
+        &store,
+        br#"
+    (module
+        (import "wasi_snapshot_preview1" "path_open" (func $path_open (param i32 i32 i32 i32 i32 i64 i64 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "proc_exit" (func $proc_exit (param i32)))
+
+        (memory 1)
+        (export "memory" (memory 0))
+
+        ;; iovec pointing at the contents
+        (data (i32.const 8) "\40\00\00\00\05\00\00\00")
+        (data (i32.const 32) "data.txt")
+        (data (i32.const 64) "hello")
+
+        (func $main (export "_start")
+            (local $errno i32)
+
+            (local.set $errno
+                (call $path_open
+                    (i32.const 4)   ;; dirfd
+                    (i32.const 0)   ;; dirflags
+                    (i32.const 32)  ;; path
+                    (i32.const 8)   ;; path_len
+                    (i32.const 1)   ;; oflags (CREATE)
+                    (i64.const 66)  ;; rights_base (FD_READ | FD_WRITE)
+                    (i64.const 0)   ;; rights_inheriting
+                    (i32.const 0)   ;; fdflags
+                    (i32.const 0)   ;; fd_out
+                )
+            )
+            (if (local.get $errno)
+                (then (call $proc_exit (i32.add (i32.const 100) (local.get $errno))))
+            )
+            (call $proc_exit
+                (call $fd_write (i32.load (i32.const 0)) (i32.const 8) (i32.const 1) (i32.const 16))
+            )
+        )
+    )
+    "#,
+    )
+    .unwrap();
+    let reader = Module::new(
+        &store,
+        br#"
+    (module
+        (import "wasi_snapshot_preview1" "path_open" (func $path_open (param i32 i32 i32 i32 i32 i64 i64 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "fd_read" (func $fd_read (param i32 i32 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "proc_exit" (func $proc_exit (param i32)))
+
+        (memory 1)
+        (export "memory" (memory 0))
+
+        ;; iovec pointing at a 64 byte buffer
+        (data (i32.const 8) "\00\01\00\00\40\00\00\00")
+        (data (i32.const 32) "data.txt")
+        (data (i32.const 64) "hello")
+
+        (func $main (export "_start")
+            (local $errno i32)
+
+            (local.set $errno
+                (call $path_open
+                    (i32.const 4)   ;; dirfd
+                    (i32.const 0)   ;; dirflags
+                    (i32.const 32)  ;; path
+                    (i32.const 8)   ;; path_len
+                    (i32.const 0)   ;; oflags
+                    (i64.const 2)   ;; rights_base (FD_READ)
+                    (i64.const 0)   ;; rights_inheriting
+                    (i32.const 0)   ;; fdflags
+                    (i32.const 0)   ;; fd_out
+                )
+            )
+            (if (local.get $errno)
+                (then (call $proc_exit (i32.add (i32.const 100) (local.get $errno))))
+            )
+            (local.set $errno
+                (call $fd_read (i32.load (i32.const 0)) (i32.const 8) (i32.const 1) (i32.const 16))
+            )
+            (if (local.get $errno)
+                (then (call $proc_exit (i32.add (i32.const 200) (local.get $errno))))
+            )
+
+            ;; Report the number of bytes read when they are what was written
+            (if (i64.ne (i64.load (i32.const 256)) (i64.load (i32.const 64)))
+                (then (call $proc_exit (i32.const 1)))
+            )
+            (call $proc_exit (i32.load (i32.const 16)))
+        )
+    )
+    "#,
+    )
+    .unwrap();
+
+    // Both instances are built before either of them runs
+    let shared = SharedFileSystem::new();
+    let writer_builder = WasiEnv::builder("writer")
+        .shared_fs(&shared)
+        .preopen_dir("/")
+        .unwrap();
+    let reader_builder = WasiEnv::builder("reader")
+        .shared_fs(&shared)
+        .preopen_dir("/")
+        .unwrap();
+
+    #[cfg(feature = "js")]
+    let (written, read) = {
+        let written = writer_builder.run_with_store(writer, &mut store);
+        let read = reader_builder.run_with_store(reader, &mut store);
+        (written, read)
+    };
+    #[cfg(not(feature = "js"))]
+    let (written, read) = std::thread::spawn(move || {
+        let written = writer_builder.run_with_store(writer, &mut store);
+        let read = reader_builder.run_with_store(reader, &mut store);
+        (written, read)
+    })
+    .join()
+    .unwrap();
+
+    // The writer succeeded and the reader read all of `hello`
+    written.unwrap();
+    let exit_code = read.unwrap_err().as_exit_code().unwrap();
+    assert_eq!(exit_code.raw(), 5);
+}
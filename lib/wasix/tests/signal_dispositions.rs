@@ -0,0 +1,252 @@
+#![cfg(not(feature = "js"))]
+
+use std::time::{Duration, Instant};
+
+use wasmer::{Module, Store};
+use wasmer_wasix::{
+    types::{wasi::Errno, Signal},
+    SignalDisposition, WasiEnv, WasiError,
+};
+
+const HANDLER_MODULE: &[u8] = br#"
+    (module
+        (import "wasix_32v1" "callback_signal" (func $callback_signal (param i32 i32)))
+
+        (memory 1)
+        (export "memory" (memory 0))
+
+        (global $signals (export "signals") (mut i32) (i32.const 0))
+
+        (data (i32.const 64) "handler")
+
+        (func $handler (export "handler") (param i32)
+            (global.set $signals (i32.add (global.get $signals) (i32.const 1)))
+        )
+
+        ;; Registers the signal handler
+        (func $main (export "_start")
+            (call $callback_signal (i32.const 64) (i32.const 7))
+        )
+
+        ;; Registering the handler again processes the pending signals
+        (func $poke (export "poke")
+            (call $callback_signal (i32.const 64) (i32.const 7))
+        )
+    )
+    "#;
+
+mod sys {
+    #[tokio::test]
+    async fn test_signal_dispositions() {
+        super::test_signal_dispositions().await;
+    }
+
+    #[tokio::test]
+    async fn test_forced_default_sigquit_terminates() {
+        super::test_forced_default_sigquit_terminates().await;
+    }
+
+    #[tokio::test]
+    async fn test_sigkill_bypasses_handler() {
+        super::test_sigkill_bypasses_handler().await;
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_forced_ignore_reaches_blocked_thread() {
+        super::test_forced_ignore_reaches_blocked_thread().await;
+    }
+}
+
+async fn test_signal_dispositions() {
+    let mut store = Store::default();
+    let module = Module::new(&store, HANDLER_MODULE).unwrap();
+
+    let builder = WasiEnv::builder("command-name");
+
+    let handle = tokio::runtime::Handle::current();
+    std::thread::spawn(move || {
+        let _guard = handle.enter();
+        let (instance, env) = builder.instantiate(module, &mut store).unwrap();
+        let process = env.data(&store).process.clone();
+
+        // Nothing was configured by the guest yet
+        let dispositions = process.signal_dispositions();
+        assert_eq!(dispositions[&Signal::Sigterm], SignalDisposition::Default);
+        assert!(!dispositions.contains_key(&Signal::Signone));
+
+        // The guest registers a handler which receives all the signals
+        let start = instance.exports.get_function("_start").unwrap();
+        start.call(&mut store, &[]).unwrap();
+        let dispositions = process.signal_dispositions();
+        assert_eq!(dispositions[&Signal::Sigterm], SignalDisposition::Handler);
+        assert_eq!(dispositions[&Signal::Sigint], SignalDisposition::Handler);
+
+        // The host forces the default action for SIGINT
+        process.set_signal_disposition(Signal::Sigint, SignalDisposition::Default);
+        process.set_signal_disposition(Signal::Sigkill, SignalDisposition::Ignore);
+        let dispositions = process.signal_dispositions();
+        assert_eq!(dispositions[&Signal::Sigint], SignalDisposition::Default);
+        assert_eq!(dispositions[&Signal::Sigterm], SignalDisposition::Handler);
+        assert_eq!(dispositions[&Signal::Sigkill], SignalDisposition::Default);
+        assert_eq!(dispositions[&Signal::Sigstop], SignalDisposition::Default);
+
+        // ...so SIGINT terminates the guest rather than reaching its handler
+        process.signal_process(Signal::Sigint);
+        let poke = instance.exports.get_function("poke").unwrap();
+        let err = poke.call(&mut store, &[]).unwrap_err();
+        match err.downcast::<WasiError>() {
+            Ok(WasiError::Exit(code)) => assert_eq!(code.raw(), Errno::Intr as i32),
+            other => panic!("unexpected result: {other:?}"),
+        }
+        let signals = instance.exports.get_global("signals").unwrap();
+        assert_eq!(signals.get(&mut store).unwrap_i32(), 0);
+        assert_eq!(process.exit_signal(), Some(Signal::Sigint));
+    })
+    .join()
+    .unwrap();
+}
+
+async fn test_forced_default_sigquit_terminates() {
+    let mut store = Store::default();
+    let module = Module::new(&store, HANDLER_MODULE).unwrap();
+
+    let builder = WasiEnv::builder("command-name");
+
+    let handle = tokio::runtime::Handle::current();
+    std::thread::spawn(move || {
+        let _guard = handle.enter();
+        let (instance, env) = builder.instantiate(module, &mut store).unwrap();
+        let process = env.data(&store).process.clone();
+
+        let start = instance.exports.get_function("_start").unwrap();
+        start.call(&mut store, &[]).unwrap();
+
+        // The default action of SIGQUIT terminates the process even though
+        // the guest registered a handler for it
+        process.set_signal_disposition(Signal::Sigquit, SignalDisposition::Default);
+        process.signal_process(Signal::Sigquit);
+        let poke = instance.exports.get_function("poke").unwrap();
+        let err = poke.call(&mut store, &[]).unwrap_err();
+        match err.downcast::<WasiError>() {
+            Ok(WasiError::Exit(code)) => assert_eq!(code.raw(), Errno::Success as i32),
+            other => panic!("unexpected result: {other:?}"),
+        }
+        let signals = instance.exports.get_global("signals").unwrap();
+        assert_eq!(signals.get(&mut store).unwrap_i32(), 0);
+        assert_eq!(process.exit_signal(), Some(Signal::Sigquit));
+    })
+    .join()
+    .unwrap();
+}
+
+async fn test_sigkill_bypasses_handler() {
+    let mut store = Store::default();
+    let module = Module::new(&store, HANDLER_MODULE).unwrap();
+
+    let builder = WasiEnv::builder("command-name");
+
+    let handle = tokio::runtime::Handle::current();
+    std::thread::spawn(move || {
+        let _guard = handle.enter();
+        let (instance, env) = builder.instantiate(module, &mut store).unwrap();
+        let process = env.data(&store).process.clone();
+
+        let start = instance.exports.get_function("_start").unwrap();
+        start.call(&mut store, &[]).unwrap();
+
+        // SIGKILL terminates the process without reaching the handler the
+        // guest registered
+        process.signal_process(Signal::Sigkill);
+        let poke = instance.exports.get_function("poke").unwrap();
+        let err = poke.call(&mut store, &[]).unwrap_err();
+        match err.downcast::<WasiError>() {
+            Ok(WasiError::Exit(code)) => assert_eq!(code.raw(), Errno::Intr as i32),
+            other => panic!("unexpected result: {other:?}"),
+        }
+        let signals = instance.exports.get_global("signals").unwrap();
+        assert_eq!(signals.get(&mut store).unwrap_i32(), 0);
+        assert_eq!(process.exit_signal(), Some(Signal::Sigkill));
+    })
+    .join()
+    .unwrap();
+}
+
+async fn test_forced_ignore_reaches_blocked_thread() {
+    let mut store = Store::default();
+    let module = Module::new(
+        &store,
+        br#"
+    (module
+        (import "env" "memory" (memory 2 2 shared))
+        (import "wasix_32v1" "thread_spawn_v2" (func $thread_spawn (param i32 i32) (result i32)))
+        (import "wasix_32v1" "futex_wait" (func $futex_wait (param i32 i32 i32 i32) (result i32)))
+        (import "wasix_32v1" "futex_wake" (func $futex_wake (param i32 i32) (result i32)))
+
+        ;; The thread sleeps on the futex at 256 (the timeout at 512 is
+        ;; none) and marks itself as done once it is woken
+        (func (export "wasi_thread_start") (param $tid i32) (param $start i32)
+            (if (call $futex_wait (i32.const 256) (i32.const 0) (i32.const 512) (i32.const 640))
+                (then unreachable)
+            )
+            (i32.atomic.store (i32.const 128) (i32.const 1))
+        )
+
+        (func (export "_start")
+            (i32.store (i32.const 1024) (i32.const 65536))            ;; stack_upper
+            (i32.store (i32.const 1080) (i32.const 4096))             ;; stack_size
+            (if (call $thread_spawn (i32.const 1024) (i32.const 64))
+                (then unreachable)
+            )
+        )
+
+        (func (export "wake")
+            (if (call $futex_wake (i32.const 256) (i32.const 768))
+                (then unreachable)
+            )
+        )
+        (func (export "done") (result i32)
+            (i32.atomic.load (i32.const 128))
+        )
+    )
+    "#,
+    )
+    .unwrap();
+
+    let builder = WasiEnv::builder("command-name");
+
+    let handle = tokio::runtime::Handle::current();
+    std::thread::spawn(move || {
+        let _guard = handle.enter();
+        let (instance, env) = builder.instantiate(module, &mut store).unwrap();
+        let process = env.data(&store).process.clone();
+
+        let start = instance.exports.get_function("_start").unwrap();
+        start.call(&mut store, &[]).unwrap();
+
+        // Wait for the thread to go to sleep on the futex
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while process.futex_stats().get(&256).map(|stats| stats.waits) != Some(1) {
+            assert!(Instant::now() < deadline, "the thread did not wait");
+            std::thread::sleep(Duration::from_millis(10));
+        }
+
+        // The thread has no handler but the host told it to ignore SIGINT
+        process.set_signal_disposition(Signal::Sigint, SignalDisposition::Ignore);
+        process.signal_process(Signal::Sigint);
+        std::thread::sleep(Duration::from_millis(100));
+        assert_eq!(process.exit_signal(), None);
+
+        // ...so it is still waiting and finishes once it is woken
+        let wake = instance.exports.get_function("wake").unwrap();
+        wake.call(&mut store, &[]).unwrap();
+        let done = instance.exports.get_function("done").unwrap();
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while done.call(&mut store, &[]).unwrap()[0].unwrap_i32() != 1 {
+            assert!(Instant::now() < deadline, "the thread did not finish");
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(process.exit_signal(), None);
+    })
+    .join()
+    .unwrap();
+}
@@ -0,0 +1,103 @@
+#![cfg(all(feature = "host-vnet", not(feature = "js")))]
+
+use std::time::Duration;
+
+use wasmer::{Module, Store};
+use wasmer_wasix::{types::Signal, WasiEnv, WasiError};
+
+mod sys {
+    #[tokio::test]
+    async fn test_sock_accept_interrupted_by_signal() {
+        super::test_sock_accept_interrupted_by_signal().await;
+    }
+}
+
+async fn test_sock_accept_interrupted_by_signal() {
+    let mut store = Store::default();
+    let module = Module::new(
+        &store,
+        br#"
+    (module
+        (import "wasix_32v1" "callback_signal" (func $callback_signal (param i32 i32)))
+        (import "wasix_32v1" "sock_open" (func $sock_open (param i32 i32 i32 i32) (result i32)))
+        (import "wasix_32v1" "sock_bind" (func $sock_bind (param i32 i32) (result i32)))
+        (import "wasix_32v1" "sock_listen" (func $sock_listen (param i32 i32) (result i32)))
+        (import "wasix_32v1" "sock_accept" (func $sock_accept (param i32 i32 i32 i32) (result i32)))
+        (import "wasix_32v1" "proc_exit" (func $proc_exit (param i32)))
+
+        (memory 1)
+        (export "memory" (memory 0))
+
+        (global $signals (mut i32) (i32.const 0))
+
+        (data (i32.const 64) "handler")
+
+        (func $handler (export "handler") (param i32)
+            (global.set $signals (i32.add (global.get $signals) (i32.const 1)))
+        )
+
+        (func $main (export "_start")
+            (local $fd i32)
+            (call $callback_signal (i32.const 64) (i32.const 7))
+
+            (call $sock_open
+                (i32.const 1)  ;; af (INET4)
+                (i32.const 1)  ;; ty (STREAM)
+                (i32.const 0)  ;; pt
+                (i32.const 16) ;; ro_sock
+            )
+            drop
+            (local.set $fd (i32.load (i32.const 16)))
+
+            ;; Listen on 127.0.0.1 on any port
+            (i32.store8 (i32.const 256) (i32.const 1))
+            (i32.store (i32.const 260) (i32.const 16777343))
+            (call $sock_bind (local.get $fd) (i32.const 256))
+            drop
+            (call $sock_listen (local.get $fd) (i32.const 1))
+            drop
+
+            ;; Report the errno of the accept (which nobody connects to) and
+            ;; the number of signals the handler saw in the exit code
+            (call $proc_exit
+                (i32.or
+                    (i32.shl
+                        (call $sock_accept (local.get $fd) (i32.const 0) (i32.const 20) (i32.const 32))
+                        (i32.const 8)
+                    )
+                    (global.get $signals)
+                )
+            )
+        )
+    )
+    "#,
+    )
+    .unwrap();
+
+    let builder = WasiEnv::builder("command-name");
+
+    let handle = tokio::runtime::Handle::current();
+    let (process_tx, process_rx) = std::sync::mpsc::channel();
+    let guest = std::thread::spawn(move || {
+        let _guard = handle.enter();
+        let (instance, env) = builder.instantiate(module, &mut store).unwrap();
+        process_tx.send(env.data(&store).process.clone()).unwrap();
+
+        let start = instance.exports.get_function("_start").unwrap();
+        start.call(&mut store, &[]).unwrap_err()
+    });
+
+    // Give the guest some time to block in the accept
+    let process = process_rx.recv().unwrap();
+    std::thread::sleep(Duration::from_millis(200));
+    assert!(!guest.is_finished());
+    process.signal_process(Signal::Sigusr1);
+
+    // Errno::Intr after the handler ran once
+    let err = guest.join().unwrap();
+    let exit_code = match err.downcast::<WasiError>() {
+        Ok(WasiError::Exit(code)) => code,
+        other => panic!("unexpected result: {other:?}"),
+    };
+    assert_eq!(exit_code.raw(), (27 << 8) | 1);
+}
@@ -0,0 +1,112 @@
+#![cfg(all(feature = "host-vnet", not(feature = "js")))]
+
+use std::net::TcpListener;
+
+use wasmer::{Module, Store};
+use wasmer_wasix::WasiEnv;
+
+mod sys {
+    #[tokio::test]
+    async fn test_sock_connect_nonblocking() {
+        super::test_sock_connect_nonblocking().await;
+    }
+}
+
+async fn test_sock_connect_nonblocking() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+
+    let mut store = Store::default();
+    let module = Module::new(
+        &store,
+        format!(
+            r#"
+    (module
+        (import "wasix_32v1" "sock_open" (func $sock_open (param i32 i32 i32 i32) (result i32)))
+        (import "wasix_32v1" "fd_fdstat_set_flags" (func $fd_fdstat_set_flags (param i32 i32) (result i32)))
+        (import "wasix_32v1" "sock_connect" (func $sock_connect (param i32 i32) (result i32)))
+        (import "wasix_32v1" "poll_oneoff" (func $poll_oneoff (param i32 i32 i32 i32) (result i32)))
+        (import "wasix_32v1" "sock_get_opt_size" (func $sock_get_opt_size (param i32 i32 i32) (result i32)))
+        (import "wasix_32v1" "proc_exit" (func $proc_exit (param i32)))
+
+        (memory 1)
+        (export "memory" (memory 0))
+
+        (func $main (export "_start")
+            (local $fd i32)
+            (local $connect i32)
+            (call $sock_open
+                (i32.const 1)  ;; af (INET4)
+                (i32.const 1)  ;; ty (STREAM)
+                (i32.const 6)  ;; pt (TCP)
+                (i32.const 16) ;; ro_sock
+            )
+            drop
+            (local.set $fd (i32.load (i32.const 16)))
+            (call $fd_fdstat_set_flags (local.get $fd) (i32.const 4)) ;; NONBLOCK
+            drop
+
+            ;; Start connecting to the listener on 127.0.0.1
+            (i32.store8 (i32.const 288) (i32.const 1))
+            (i32.store16 (i32.const 290) (i32.const {port}))
+            (i32.store (i32.const 292) (i32.const 16777343))
+            (local.set $connect (call $sock_connect (local.get $fd) (i32.const 288)))
+
+            ;; Wait for the socket to become writable (userdata 7)
+            (i64.store (i32.const 64) (i64.const 7))
+            (i32.store8 (i32.const 72) (i32.const 2))             ;; FdWrite
+            (i32.store (i32.const 80) (local.get $fd))            ;; fd
+            (if (call $poll_oneoff (i32.const 64) (i32.const 256) (i32.const 1) (i32.const 8))
+                (then (call $proc_exit (i32.const 255)))
+            )
+            (if (i32.ne (i32.load (i32.const 8)) (i32.const 1))
+                (then (call $proc_exit (i32.const 254)))
+            )
+
+            ;; Read SO_ERROR
+            (call $sock_get_opt_size (local.get $fd) (i32.const 11) (i32.const 32))
+            drop
+
+            ;; Report the errno of the connect, the userdata, error and type
+            ;; of the event and SO_ERROR in the exit code
+            (call $proc_exit
+                (i32.or
+                    (i32.or
+                        (local.get $connect)
+                        (i32.shl (i32.load8_u (i32.const 256)) (i32.const 8))
+                    )
+                    (i32.or
+                        (i32.shl
+                            (i32.or
+                                (i32.load16_u (i32.const 264))
+                                (i32.shl (i32.load8_u (i32.const 266)) (i32.const 4))
+                            )
+                            (i32.const 16)
+                        )
+                        (i32.shl (i32.load8_u (i32.const 32)) (i32.const 24))
+                    )
+                )
+            )
+        )
+    )
+    "#
+        ),
+    )
+    .unwrap();
+
+    let builder = WasiEnv::builder("command-name");
+
+    let handle = tokio::runtime::Handle::current();
+    let result = std::thread::spawn(move || {
+        let _guard = handle.enter();
+        builder.run_with_store(module, &mut store)
+    })
+    .join()
+    .unwrap();
+
+    // Errno::Inprogress, then a FdWrite event (type 2) for userdata 7
+    // without an error and no error in SO_ERROR
+    let exit_code = result.unwrap_err().as_exit_code().unwrap();
+    assert_eq!(exit_code.raw(), 26 | (7 << 8) | ((2 << 4) << 16));
+    assert!(listener.accept().is_ok());
+}
@@ -0,0 +1,77 @@
+use std::time::Duration;
+
+use wasmer::{Module, Store};
+use wasmer_wasix::WasiEnv;
+
+mod sys {
+    #[tokio::test]
+    async fn test_sock_default_timeout() {
+        super::test_sock_default_timeout().await;
+    }
+}
+
+async fn test_sock_default_timeout() {
+    let mut store = Store::default();
+    let module = Module::new(
+        &store,
+        br#"
+    (module
+        (import "wasix_32v1" "sock_open" (func $sock_open (param i32 i32 i32 i32) (result i32)))
+        (import "wasix_32v1" "sock_get_opt_time" (func $sock_get_opt_time (param i32 i32 i32) (result i32)))
+        (import "wasix_32v1" "proc_exit" (func $proc_exit (param i32)))
+
+        (memory 1)
+        (export "memory" (memory 0))
+
+        (func $check (param $errno i32)
+            (if (local.get $errno)
+                (then (call $proc_exit (i32.add (i32.const 100) (local.get $errno))))
+            )
+        )
+
+        ;; Exits with `code` unless the option is set to `time` nanoseconds
+        (func $expect_time (param $fd i32) (param $opt i32) (param $time i64) (param $code i32)
+            (call $check (call $sock_get_opt_time (local.get $fd) (local.get $opt) (i32.const 64)))
+            (if (i32.ne (i32.load8_u (i32.const 64)) (i32.const 1))
+                (then (call $proc_exit (local.get $code)))
+            )
+            (if (i64.ne (i64.load (i32.const 72)) (local.get $time))
+                (then (call $proc_exit (local.get $code)))
+            )
+        )
+
+        (func $main (export "_start")
+            (local $fd i32)
+
+            (call $check
+                (call $sock_open
+                    (i32.const 1)  ;; af (INET4)
+                    (i32.const 1)  ;; ty (STREAM)
+                    (i32.const 0)  ;; pt
+                    (i32.const 16) ;; ro_sock
+                )
+            )
+            (local.set $fd (i32.load (i32.const 16)))
+
+            ;; RecvTimeout and SendTimeout
+            (call $expect_time (local.get $fd) (i32.const 19) (i64.const 2000000000) (i32.const 1))
+            (call $expect_time (local.get $fd) (i32.const 20) (i64.const 3000000000) (i32.const 2))
+        )
+    )
+    "#,
+    )
+    .unwrap();
+
+    let builder = WasiEnv::builder("command-name")
+        .default_socket_read_timeout(Duration::from_secs(2))
+        .default_socket_write_timeout(Duration::from_secs(3));
+
+    #[cfg(feature = "js")]
+    let result = builder.run_with_store(module, &mut store);
+    #[cfg(not(feature = "js"))]
+    let result = std::thread::spawn(move || builder.run_with_store(module, &mut store))
+        .join()
+        .unwrap();
+
+    result.unwrap();
+}
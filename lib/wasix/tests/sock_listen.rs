@@ -0,0 +1,69 @@
+#![cfg(all(feature = "host-vnet", not(feature = "js")))]
+
+use wasmer::{Module, Store};
+use wasmer_wasix::WasiEnv;
+
+mod sys {
+    #[tokio::test]
+    async fn test_sock_listen_without_bind() {
+        super::test_sock_listen_without_bind().await;
+    }
+}
+
+async fn test_sock_listen_without_bind() {
+    let mut store = Store::default();
+    let module = Module::new(
+        &store,
+        br#"
+    (module
+        (import "wasix_32v1" "sock_open" (func $sock_open (param i32 i32 i32 i32) (result i32)))
+        (import "wasix_32v1" "sock_listen" (func $sock_listen (param i32 i32) (result i32)))
+        (import "wasix_32v1" "sock_addr_local" (func $sock_addr_local (param i32 i32) (result i32)))
+        (import "wasix_32v1" "proc_exit" (func $proc_exit (param i32)))
+
+        (memory 1)
+        (export "memory" (memory 0))
+
+        (func $check (param $errno i32)
+            (if (local.get $errno)
+                (then (call $proc_exit (i32.add (i32.const 100) (local.get $errno))))
+            )
+        )
+
+        (func $main (export "_start")
+            (local $fd i32)
+
+            (call $check
+                (call $sock_open
+                    (i32.const 1)  ;; af (INET4)
+                    (i32.const 1)  ;; ty (STREAM)
+                    (i32.const 0)  ;; pt
+                    (i32.const 16) ;; ro_sock
+                )
+            )
+            (local.set $fd (i32.load (i32.const 16)))
+
+            ;; Listening without binding first picks an ephemeral port
+            (call $check (call $sock_listen (local.get $fd) (i32.const 1)))
+            (call $check (call $sock_addr_local (local.get $fd) (i32.const 256)))
+            (if (i32.eqz (i32.load16_u (i32.const 258)))
+                (then (call $proc_exit (i32.const 1)))
+            )
+        )
+    )
+    "#,
+    )
+    .unwrap();
+
+    let builder = WasiEnv::builder("command-name");
+
+    let handle = tokio::runtime::Handle::current();
+    let result = std::thread::spawn(move || {
+        let _guard = handle.enter();
+        builder.run_with_store(module, &mut store)
+    })
+    .join()
+    .unwrap();
+
+    result.unwrap();
+}
@@ -0,0 +1,151 @@
+#![cfg(all(feature = "host-vnet", not(feature = "js")))]
+
+use wasmer::{Instance, Module, Store};
+use wasmer_wasix::{types::wasi::Errno, WasiEnv, WasiFunctionEnv};
+
+mod sys {
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_sock_peer_pid_of_child() {
+        super::test_sock_peer_pid_of_child().await;
+    }
+}
+
+/// Has the guest look up the peer of a socket, returns the errno and the pid
+fn peer_pid(instance: &Instance, store: &mut Store, fd: i32) -> (i32, u32) {
+    let peer_pid = instance.exports.get_function("peer_pid").unwrap();
+    let errno = peer_pid.call(store, &[fd.into()]).unwrap()[0].unwrap_i32();
+
+    let memory = instance.exports.get_memory("memory").unwrap();
+    let mut buf = [0u8; 4];
+    memory.view(store).read(40, &mut buf).unwrap();
+    (errno, u32::from_le_bytes(buf))
+}
+
+async fn test_sock_peer_pid_of_child() {
+    let mut store = Store::default();
+    let module = Module::new(
+        &store,
+        br#"
+    (module
+        (import "wasix_32v1" "sock_open" (func $sock_open (param i32 i32 i32 i32) (result i32)))
+        (import "wasix_32v1" "sock_bind" (func $sock_bind (param i32 i32) (result i32)))
+        (import "wasix_32v1" "sock_listen" (func $sock_listen (param i32 i32) (result i32)))
+        (import "wasix_32v1" "sock_addr_local" (func $sock_addr_local (param i32 i32) (result i32)))
+        (import "wasix_32v1" "sock_connect" (func $sock_connect (param i32 i32) (result i32)))
+        (import "wasix_32v1" "sock_accept" (func $sock_accept (param i32 i32 i32 i32) (result i32)))
+        (import "wasix_32v1" "sock_peer_pid" (func $sock_peer_pid (param i32 i32) (result i32)))
+
+        (memory 1)
+        (export "memory" (memory 0))
+
+        ;; Opens a stream socket and returns its descriptor
+        (func $open (result i32)
+            (if (call $sock_open
+                    (i32.const 1)  ;; af (INET4)
+                    (i32.const 1)  ;; ty (STREAM)
+                    (i32.const 0)  ;; pt
+                    (i32.const 16) ;; ro_sock
+                )
+                (then unreachable)
+            )
+            (i32.load (i32.const 16))
+        )
+
+        ;; Listens on 127.0.0.1 on any port and writes the address to 512
+        (func (export "listen") (result i32)
+            (local $fd i32)
+            (local.set $fd (call $open))
+            (i32.store8 (i32.const 256) (i32.const 1))
+            (i32.store (i32.const 260) (i32.const 16777343))
+            (if (call $sock_bind (local.get $fd) (i32.const 256))
+                (then unreachable)
+            )
+            (if (call $sock_listen (local.get $fd) (i32.const 1))
+                (then unreachable)
+            )
+            (if (call $sock_addr_local (local.get $fd) (i32.const 512))
+                (then unreachable)
+            )
+            (local.get $fd)
+        )
+
+        ;; Connects to a port on 127.0.0.1
+        (func (export "connect") (param $port i32) (result i32)
+            (local $fd i32)
+            (local.set $fd (call $open))
+            (i32.store8 (i32.const 288) (i32.const 1))
+            (i32.store16 (i32.const 290) (local.get $port))
+            (i32.store (i32.const 292) (i32.const 16777343))
+            (if (call $sock_connect (local.get $fd) (i32.const 288))
+                (then unreachable)
+            )
+            (local.get $fd)
+        )
+
+        (func (export "accept") (param $fd i32) (result i32)
+            (if (call $sock_accept (local.get $fd) (i32.const 0) (i32.const 20) (i32.const 64))
+                (then unreachable)
+            )
+            (i32.load (i32.const 20))
+        )
+
+        ;; Writes the pid of the peer to 40 and returns the errno
+        (func (export "peer_pid") (param $fd i32) (result i32)
+            (call $sock_peer_pid (local.get $fd) (i32.const 40))
+        )
+    )
+    "#,
+    )
+    .unwrap();
+
+    let builder = WasiEnv::builder("command-name");
+
+    let handle = tokio::runtime::Handle::current();
+    std::thread::spawn(move || {
+        let _guard = handle.enter();
+        let (instance, env) = builder.instantiate(module.clone(), &mut store).unwrap();
+        let parent_pid = env.data(&store).process.pid();
+
+        // The parent listens for connections
+        let listen = instance.exports.get_function("listen").unwrap();
+        let listener = listen.call(&mut store, &[]).unwrap()[0].unwrap_i32();
+        // The port of the local address is in network byte order
+        let mut port = [0u8; 2];
+        let memory = instance.exports.get_memory("memory").unwrap();
+        memory.view(&store).read(514, &mut port).unwrap();
+        let port = u16::from_be_bytes(port);
+
+        // Listeners have no peer
+        assert_eq!(
+            peer_pid(&instance, &mut store, listener).0,
+            Errno::Noprotoopt as i32
+        );
+
+        // The child connects to the parent
+        let (child_env, _child_handle) = env.data(&store).fork().unwrap();
+        let child_pid = child_env.process.pid();
+        assert_ne!(child_pid, parent_pid);
+        let mut child_env = WasiFunctionEnv::new(&mut store, child_env);
+        let imports = child_env.import_object(&mut store, &module).unwrap();
+        let child_instance = Instance::new(&mut store, &module, &imports).unwrap();
+        child_env
+            .initialize(&mut store, child_instance.clone())
+            .unwrap();
+        let connect = child_instance.exports.get_function("connect").unwrap();
+        let client = connect.call(&mut store, &[(port as i32).into()]).unwrap()[0].unwrap_i32();
+
+        // Both ends see the process on the other side
+        let accept = instance.exports.get_function("accept").unwrap();
+        let accepted = accept.call(&mut store, &[listener.into()]).unwrap()[0].unwrap_i32();
+        assert_eq!(
+            peer_pid(&instance, &mut store, accepted),
+            (Errno::Success as i32, child_pid.raw())
+        );
+        assert_eq!(
+            peer_pid(&child_instance, &mut store, client),
+            (Errno::Success as i32, parent_pid.raw())
+        );
+    })
+    .join()
+    .unwrap();
+}
@@ -0,0 +1,127 @@
+#![cfg(all(feature = "host-vnet", not(feature = "js")))]
+
+use std::{net::UdpSocket, time::Duration};
+
+use wasmer::{Module, Store};
+use wasmer_wasix::WasiEnv;
+
+mod sys {
+    #[tokio::test]
+    async fn test_sock_recv_from_connected_peer() {
+        super::test_sock_recv_from_connected_peer().await;
+    }
+}
+
+async fn test_sock_recv_from_connected_peer() {
+    // The peer the guest connects to and another peer that also sends to it
+    let peer = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let other = UdpSocket::bind("127.0.0.1:0").unwrap();
+    peer.set_read_timeout(Some(Duration::from_secs(10)))
+        .unwrap();
+    let port = peer.local_addr().unwrap().port();
+
+    // `sock_connect` reads the port in native order, `sock_recv_from`
+    // writes it in network order
+    let reported_port = u16::from_ne_bytes(port.to_be_bytes());
+
+    let mut store = Store::default();
+    let module = Module::new(
+        &store,
+        format!(
+            r#"
+    (module
+        (import "wasix_32v1" "sock_open" (func $sock_open (param i32 i32 i32 i32) (result i32)))
+        (import "wasix_32v1" "sock_bind" (func $sock_bind (param i32 i32) (result i32)))
+        (import "wasix_32v1" "sock_connect" (func $sock_connect (param i32 i32) (result i32)))
+        (import "wasix_32v1" "sock_send" (func $sock_send (param i32 i32 i32 i32 i32) (result i32)))
+        (import "wasix_32v1" "sock_recv_from" (func $sock_recv_from (param i32 i32 i32 i32 i32 i32 i32) (result i32)))
+        (import "wasix_32v1" "proc_exit" (func $proc_exit (param i32)))
+
+        (memory 1)
+        (export "memory" (memory 0))
+
+        (data (i32.const 64) "hi")
+
+        (func $main (export "_start")
+            (local $fd i32)
+            (call $sock_open
+                (i32.const 1)  ;; af (INET4)
+                (i32.const 2)  ;; ty (DGRAM)
+                (i32.const 17) ;; pt (UDP)
+                (i32.const 16) ;; ro_sock
+            )
+            drop
+            (local.set $fd (i32.load (i32.const 16)))
+
+            ;; Bind to 127.0.0.1 on any port
+            (i32.store8 (i32.const 256) (i32.const 1))
+            (i32.store (i32.const 260) (i32.const 16777343))
+            (call $sock_bind (local.get $fd) (i32.const 256))
+            drop
+
+            ;; Connect to the peer
+            (i32.store8 (i32.const 288) (i32.const 1))
+            (i32.store16 (i32.const 290) (i32.const {port}))
+            (i32.store (i32.const 292) (i32.const 16777343))
+            (call $sock_connect (local.get $fd) (i32.const 288))
+            drop
+
+            ;; Let the peer know our address
+            (i32.store (i32.const 0) (i32.const 64))
+            (i32.store (i32.const 4) (i32.const 2))
+            (call $sock_send (local.get $fd) (i32.const 0) (i32.const 1) (i32.const 0) (i32.const 20))
+            drop
+
+            ;; Only the datagram of the peer must be received
+            (i32.store (i32.const 8) (i32.const 128))
+            (i32.store (i32.const 12) (i32.const 16))
+            (call $sock_recv_from
+                (local.get $fd)
+                (i32.const 8)   ;; ri_data
+                (i32.const 1)   ;; ri_data_len
+                (i32.const 0)   ;; ri_flags
+                (i32.const 20)  ;; ro_data_len
+                (i32.const 24)  ;; ro_flags
+                (i32.const 320) ;; ro_addr
+            )
+            drop
+
+            ;; The reported address must be the one of the peer
+            (if (i32.ne (i32.load16_u (i32.const 322)) (i32.const {reported_port}))
+                (then (call $proc_exit (i32.const 1)))
+            )
+
+            ;; Report the length and first byte of the datagram as the exit code
+            (call $proc_exit
+                (i32.or
+                    (i32.shl (i32.load (i32.const 20)) (i32.const 8))
+                    (i32.load8_u (i32.const 128))
+                )
+            )
+        )
+    )
+    "#
+        ),
+    )
+    .unwrap();
+
+    let builder = WasiEnv::builder("command-name");
+
+    let handle = tokio::runtime::Handle::current();
+    let guest = std::thread::spawn(move || {
+        let _guard = handle.enter();
+        builder.run_with_store(module, &mut store)
+    });
+
+    let mut buf = [0u8; 16];
+    let (_, guest_addr) = peer.recv_from(&mut buf).unwrap();
+    other.send_to(b"bad", guest_addr).unwrap();
+    std::thread::sleep(Duration::from_millis(100));
+    peer.send_to(b"good", guest_addr).unwrap();
+
+    let result = guest.join().unwrap();
+
+    // Four bytes starting with 'g'
+    let exit_code = result.unwrap_err().as_exit_code().unwrap();
+    assert_eq!(exit_code.raw(), (4 << 8) | b'g' as i32);
+}
@@ -0,0 +1,190 @@
+use wasmer::{Module, Store};
+use wasmer_wasix::WasiEnv;
+
+mod sys {
+    #[tokio::test]
+    async fn test_sock_send_unconnected() {
+        super::test_sock_send_unconnected().await;
+    }
+
+    #[cfg(all(feature = "host-vnet", not(feature = "js")))]
+    #[tokio::test]
+    async fn test_sock_send_listening() {
+        super::test_sock_send_listening().await;
+    }
+
+    #[tokio::test]
+    async fn test_sock_send_bad_fd() {
+        super::test_sock_send_bad_fd().await;
+    }
+}
+
+async fn test_sock_send_unconnected() {
+    let mut store = Store::default();
+    let module = Module::new(
+        &store,
+        br#"
+    (module
+        (import "wasix_32v1" "sock_open" (func $sock_open (param i32 i32 i32 i32) (result i32)))
+        (import "wasix_32v1" "sock_send" (func $sock_send (param i32 i32 i32 i32 i32) (result i32)))
+        (import "wasix_32v1" "proc_exit" (func $proc_exit (param i32)))
+
+        (memory 1)
+        (export "memory" (memory 0))
+
+        (data (i32.const 32) "hello")
+
+        (func $main (export "_start")
+            (call $sock_open
+                (i32.const 1)  ;; af (INET4)
+                (i32.const 1)  ;; ty (STREAM)
+                (i32.const 0)  ;; pt
+                (i32.const 16) ;; ro_sock
+            )
+            drop
+
+            ;; Send without connecting the socket first
+            (i32.store (i32.const 0) (i32.const 32))
+            (i32.store (i32.const 4) (i32.const 5))
+            (call $proc_exit
+                (call $sock_send
+                    (i32.load (i32.const 16)) ;; fd
+                    (i32.const 0)             ;; si_data
+                    (i32.const 1)             ;; si_data_len
+                    (i32.const 0)             ;; si_flags
+                    (i32.const 20)            ;; ret_data_len
+                )
+            )
+        )
+    )
+    "#,
+    )
+    .unwrap();
+
+    let builder = WasiEnv::builder("command-name");
+
+    #[cfg(feature = "js")]
+    let result = builder.run_with_store(module, &mut store);
+    #[cfg(not(feature = "js"))]
+    let result = std::thread::spawn(move || builder.run_with_store(module, &mut store))
+        .join()
+        .unwrap();
+
+    // Errno::Notconn
+    let exit_code = result.unwrap_err().as_exit_code().unwrap();
+    assert_eq!(exit_code.raw(), 53);
+}
+
+#[cfg(all(feature = "host-vnet", not(feature = "js")))]
+async fn test_sock_send_listening() {
+    let mut store = Store::default();
+    let module = Module::new(
+        &store,
+        br#"
+    (module
+        (import "wasix_32v1" "sock_open" (func $sock_open (param i32 i32 i32 i32) (result i32)))
+        (import "wasix_32v1" "sock_listen" (func $sock_listen (param i32 i32) (result i32)))
+        (import "wasix_32v1" "sock_send" (func $sock_send (param i32 i32 i32 i32 i32) (result i32)))
+        (import "wasix_32v1" "proc_exit" (func $proc_exit (param i32)))
+
+        (memory 1)
+        (export "memory" (memory 0))
+
+        (data (i32.const 32) "hello")
+
+        (func $check (param $errno i32)
+            (if (local.get $errno)
+                (then (call $proc_exit (i32.add (i32.const 100) (local.get $errno))))
+            )
+        )
+
+        (func $main (export "_start")
+            (call $check
+                (call $sock_open
+                    (i32.const 1)  ;; af (INET4)
+                    (i32.const 1)  ;; ty (STREAM)
+                    (i32.const 0)  ;; pt
+                    (i32.const 16) ;; ro_sock
+                )
+            )
+            (call $check (call $sock_listen (i32.load (i32.const 16)) (i32.const 1)))
+
+            ;; A listening socket has no peer to send the data to
+            (i32.store (i32.const 0) (i32.const 32))
+            (i32.store (i32.const 4) (i32.const 5))
+            (call $proc_exit
+                (call $sock_send
+                    (i32.load (i32.const 16)) ;; fd
+                    (i32.const 0)             ;; si_data
+                    (i32.const 1)             ;; si_data_len
+                    (i32.const 0)             ;; si_flags
+                    (i32.const 20)            ;; ret_data_len
+                )
+            )
+        )
+    )
+    "#,
+    )
+    .unwrap();
+
+    let builder = WasiEnv::builder("command-name");
+
+    let handle = tokio::runtime::Handle::current();
+    let result = std::thread::spawn(move || {
+        let _guard = handle.enter();
+        builder.run_with_store(module, &mut store)
+    })
+    .join()
+    .unwrap();
+
+    // Errno::Notconn
+    let exit_code = result.unwrap_err().as_exit_code().unwrap();
+    assert_eq!(exit_code.raw(), 53);
+}
+
+async fn test_sock_send_bad_fd() {
+    let mut store = Store::default();
+    let module = Module::new(
+        &store,
+        br#"
+    (module
+        (import "wasix_32v1" "sock_send" (func $sock_send (param i32 i32 i32 i32 i32) (result i32)))
+        (import "wasix_32v1" "proc_exit" (func $proc_exit (param i32)))
+
+        (memory 1)
+        (export "memory" (memory 0))
+
+        (data (i32.const 32) "hello")
+
+        (func $main (export "_start")
+            ;; Nothing was ever opened with this descriptor
+            (i32.store (i32.const 0) (i32.const 32))
+            (i32.store (i32.const 4) (i32.const 5))
+            (call $proc_exit
+                (call $sock_send
+                    (i32.const 1234) ;; fd
+                    (i32.const 0)    ;; si_data
+                    (i32.const 1)    ;; si_data_len
+                    (i32.const 0)    ;; si_flags
+                    (i32.const 20)   ;; ret_data_len
+                )
+            )
+        )
+    )
+    "#,
+    )
+    .unwrap();
+
+    let builder = WasiEnv::builder("command-name");
+
+    #[cfg(feature = "js")]
+    let result = builder.run_with_store(module, &mut store);
+    #[cfg(not(feature = "js"))]
+    let result = std::thread::spawn(move || builder.run_with_store(module, &mut store))
+        .join()
+        .unwrap();
+
+    // Errno::Badf
+    let exit_code = result.unwrap_err().as_exit_code().unwrap();
+    assert_eq!(exit_code.raw(), 8);
+}
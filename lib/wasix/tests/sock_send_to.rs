@@ -0,0 +1,109 @@
+#![cfg(all(feature = "host-vnet", not(feature = "js")))]
+
+use std::{net::UdpSocket, time::Duration};
+
+use wasmer::{Module, Store};
+use wasmer_wasix::WasiEnv;
+
+mod sys {
+    #[tokio::test]
+    async fn test_sock_send_to_oversized_datagram() {
+        super::test_sock_send_to_oversized_datagram().await;
+    }
+}
+
+async fn test_sock_send_to_oversized_datagram() {
+    let peer = UdpSocket::bind("127.0.0.1:0").unwrap();
+    peer.set_read_timeout(Some(Duration::from_secs(10)))
+        .unwrap();
+    let port = peer.local_addr().unwrap().port();
+
+    let mut store = Store::default();
+    let module = Module::new(
+        &store,
+        format!(
+            r#"
+    (module
+        (import "wasix_32v1" "sock_open" (func $sock_open (param i32 i32 i32 i32) (result i32)))
+        (import "wasix_32v1" "sock_bind" (func $sock_bind (param i32 i32) (result i32)))
+        (import "wasix_32v1" "sock_send_to" (func $sock_send_to (param i32 i32 i32 i32 i32 i32) (result i32)))
+        (import "wasix_32v1" "proc_exit" (func $proc_exit (param i32)))
+
+        (memory 1)
+        (export "memory" (memory 0))
+
+        (data (i32.const 64) "0123456789abcdef")
+
+        (func $main (export "_start")
+            (local $fd i32)
+            (call $sock_open
+                (i32.const 1)  ;; af (INET4)
+                (i32.const 2)  ;; ty (DGRAM)
+                (i32.const 17) ;; pt (UDP)
+                (i32.const 16) ;; ro_sock
+            )
+            drop
+            (local.set $fd (i32.load (i32.const 16)))
+
+            ;; Bind to 127.0.0.1 on any port
+            (i32.store8 (i32.const 256) (i32.const 1))
+            (i32.store (i32.const 260) (i32.const 16777343))
+            (call $sock_bind (local.get $fd) (i32.const 256))
+            drop
+
+            ;; Address of the peer
+            (i32.store8 (i32.const 288) (i32.const 1))
+            (i32.store16 (i32.const 290) (i32.const {port}))
+            (i32.store (i32.const 292) (i32.const 16777343))
+
+            ;; Report the errno of sending a datagram that is too large (split
+            ;; over two buffers) and of one that fits in the exit code
+            (i32.store (i32.const 0) (i32.const 64))
+            (i32.store (i32.const 4) (i32.const 6))
+            (i32.store (i32.const 8) (i32.const 70))
+            (i32.store (i32.const 12) (i32.const 6))
+            (call $proc_exit
+                (i32.or
+                    (i32.shl
+                        (call $sock_send_to
+                            (local.get $fd)
+                            (i32.const 0)   ;; si_data
+                            (i32.const 2)   ;; si_data_len
+                            (i32.const 0)   ;; si_flags
+                            (i32.const 288) ;; addr
+                            (i32.const 20)  ;; ret_data_len
+                        )
+                        (i32.const 8)
+                    )
+                    (call $sock_send_to
+                        (local.get $fd) (i32.const 0) (i32.const 1) (i32.const 0)
+                        (i32.const 288) (i32.const 20)
+                    )
+                )
+            )
+        )
+    )
+    "#
+        )
+        .as_bytes(),
+    )
+    .unwrap();
+
+    let builder = WasiEnv::builder("command-name").max_datagram_size(8);
+
+    let handle = tokio::runtime::Handle::current();
+    let result = std::thread::spawn(move || {
+        let _guard = handle.enter();
+        builder.run_with_store(module, &mut store)
+    })
+    .join()
+    .unwrap();
+
+    // Errno::Msgsize for the oversized datagram, the other one is sent
+    let exit_code = result.unwrap_err().as_exit_code().unwrap();
+    assert_eq!(exit_code.raw(), 35 << 8);
+
+    let mut buf = [0u8; 64];
+    let (read, _) = peer.recv_from(&mut buf).unwrap();
+    assert_eq!(&buf[..read], b"012345");
+}
@@ -0,0 +1,192 @@
+#![cfg(all(feature = "host-vnet", not(feature = "js")))]
+
+use std::net::UdpSocket;
+
+use wasmer::{Module, Store};
+use wasmer_wasix::WasiEnv;
+
+mod sys {
+    #[tokio::test]
+    async fn test_socket_byte_limit() {
+        super::test_socket_byte_limit().await;
+    }
+
+    #[tokio::test]
+    async fn test_socket_byte_limit_fd_write() {
+        super::test_socket_byte_limit_fd_write().await;
+    }
+}
+
+async fn test_socket_byte_limit() {
+    let peer = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let port = peer.local_addr().unwrap().port();
+
+    let mut store = Store::default();
+    let module = Module::new(
+        &store,
+        format!(
+            r#"
+    (module
+        (import "wasix_32v1" "sock_open" (func $sock_open (param i32 i32 i32 i32) (result i32)))
+        (import "wasix_32v1" "sock_bind" (func $sock_bind (param i32 i32) (result i32)))
+        (import "wasix_32v1" "sock_connect" (func $sock_connect (param i32 i32) (result i32)))
+        (import "wasix_32v1" "sock_send" (func $sock_send (param i32 i32 i32 i32 i32) (result i32)))
+        (import "wasix_32v1" "proc_exit" (func $proc_exit (param i32)))
+
+        (memory 1)
+        (export "memory" (memory 0))
+
+        (data (i32.const 64) "hello")
+
+        (func $main (export "_start")
+            (local $fd i32)
+            (local $first i32)
+            (call $sock_open
+                (i32.const 1)  ;; af (INET4)
+                (i32.const 2)  ;; ty (DGRAM)
+                (i32.const 17) ;; pt (UDP)
+                (i32.const 16) ;; ro_sock
+            )
+            drop
+            (local.set $fd (i32.load (i32.const 16)))
+
+            ;; Bind to an ephemeral port on 127.0.0.1
+            (i32.store8 (i32.const 256) (i32.const 1))
+            (i32.store16 (i32.const 258) (i32.const 0))
+            (i32.store (i32.const 260) (i32.const 16777343))
+            (call $sock_bind (local.get $fd) (i32.const 256))
+            drop
+
+            ;; Connect to the peer on 127.0.0.1
+            (i32.store8 (i32.const 288) (i32.const 1))
+            (i32.store16 (i32.const 290) (i32.const {port}))
+            (i32.store (i32.const 292) (i32.const 16777343))
+            (call $sock_connect (local.get $fd) (i32.const 288))
+            drop
+
+            ;; The first send reaches the cap, the second one exceeds it
+            (i32.store (i32.const 0) (i32.const 64))
+            (i32.store (i32.const 4) (i32.const 5))
+            (local.set $first
+                (call $sock_send (local.get $fd) (i32.const 0) (i32.const 1) (i32.const 0) (i32.const 20))
+            )
+
+            ;; Report both errnos as the exit code
+            (call $proc_exit
+                (i32.or
+                    (i32.shl (local.get $first) (i32.const 8))
+                    (call $sock_send (local.get $fd) (i32.const 0) (i32.const 1) (i32.const 0) (i32.const 20))
+                )
+            )
+        )
+    )
+    "#
+        ),
+    )
+    .unwrap();
+
+    let builder = WasiEnv::builder("command-name").socket_byte_limit(5);
+
+    let handle = tokio::runtime::Handle::current();
+    let result = std::thread::spawn(move || {
+        let _guard = handle.enter();
+        builder.run_with_store(module, &mut store)
+    })
+    .join()
+    .unwrap();
+
+    // Errno::Success, then Errno::Notcapable
+    let exit_code = result.unwrap_err().as_exit_code().unwrap();
+    assert_eq!(exit_code.raw(), 76);
+
+    let mut buf = [0u8; 16];
+    let (amt, _) = peer.recv_from(&mut buf).unwrap();
+    assert_eq!(&buf[..amt], b"hello");
+}
+
+async fn test_socket_byte_limit_fd_write() {
+    let peer = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let port = peer.local_addr().unwrap().port();
+
+    let mut store = Store::default();
+    let module = Module::new(
+        &store,
+        format!(
+            r#"
+    (module
+        (import "wasix_32v1" "sock_open" (func $sock_open (param i32 i32 i32 i32) (result i32)))
+        (import "wasix_32v1" "sock_bind" (func $sock_bind (param i32 i32) (result i32)))
+        (import "wasix_32v1" "sock_connect" (func $sock_connect (param i32 i32) (result i32)))
+        (import "wasix_32v1" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+        (import "wasix_32v1" "proc_exit" (func $proc_exit (param i32)))
+
+        (memory 1)
+        (export "memory" (memory 0))
+
+        (data (i32.const 64) "hello")
+
+        (func $main (export "_start")
+            (local $fd i32)
+            (local $first i32)
+            (call $sock_open
+                (i32.const 1)  ;; af (INET4)
+                (i32.const 2)  ;; ty (DGRAM)
+                (i32.const 17) ;; pt (UDP)
+                (i32.const 16) ;; ro_sock
+            )
+            drop
+            (local.set $fd (i32.load (i32.const 16)))
+
+            ;; Bind to an ephemeral port on 127.0.0.1
+            (i32.store8 (i32.const 256) (i32.const 1))
+            (i32.store16 (i32.const 258) (i32.const 0))
+            (i32.store (i32.const 260) (i32.const 16777343))
+            (call $sock_bind (local.get $fd) (i32.const 256))
+            drop
+
+            ;; Connect to the peer on 127.0.0.1
+            (i32.store8 (i32.const 288) (i32.const 1))
+            (i32.store16 (i32.const 290) (i32.const {port}))
+            (i32.store (i32.const 292) (i32.const 16777343))
+            (call $sock_connect (local.get $fd) (i32.const 288))
+            drop
+
+            ;; The first write is cut short at the cap, the second one fails
+            (i32.store (i32.const 0) (i32.const 64))
+            (i32.store (i32.const 4) (i32.const 5))
+            (call $fd_write (local.get $fd) (i32.const 0) (i32.const 1) (i32.const 20))
+            drop
+            (local.set $first (i32.load (i32.const 20)))
+
+            ;; Report the bytes written and the errno as the exit code
+            (call $proc_exit
+                (i32.or
+                    (i32.shl (local.get $first) (i32.const 8))
+                    (call $fd_write (local.get $fd) (i32.const 0) (i32.const 1) (i32.const 20))
+                )
+            )
+        )
+    )
+    "#
+        ),
+    )
+    .unwrap();
+
+    let builder = WasiEnv::builder("command-name").socket_byte_limit(3);
+
+    let handle = tokio::runtime::Handle::current();
+    let result = std::thread::spawn(move || {
+        let _guard = handle.enter();
+        builder.run_with_store(module, &mut store)
+    })
+    .join()
+    .unwrap();
+
+    // 3 bytes written, then Errno::Notcapable
+    let exit_code = result.unwrap_err().as_exit_code().unwrap();
+    assert_eq!(exit_code.raw(), (3 << 8) | 76);
+
+    let mut buf = [0u8; 16];
+    let (amt, _) = peer.recv_from(&mut buf).unwrap();
+    assert_eq!(&buf[..amt], b"hel");
+}
@@ -17,6 +17,11 @@ mod sys {
     async fn test_env() {
         super::test_env().await;
     }
+
+    #[tokio::test]
+    async fn test_stderr_to_stdout() {
+        super::test_stderr_to_stdout().await;
+    }
 }
 
 // #[cfg(feature = "js")]
@@ -96,6 +101,60 @@ async fn test_stdout() {
     assert_eq!(stdout_as_str, "hello world");
 }
 
+async fn test_stderr_to_stdout() {
+    let mut store = Store::default();
+    let module = Module::new(
+        &store,
+        br#"
+    (module
+        (import "wasi_unstable" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+
+        (memory 1)
+        (export "memory" (memory 0))
+
+        (data (i32.const 32) "out1 err1 out2 err2 ")
+
+        (func $write (param $fd i32) (param $offset i32)
+            (i32.store (i32.const 0) (i32.add (i32.const 32) (local.get $offset)))
+            (i32.store (i32.const 4) (i32.const 5))
+            (call $fd_write (local.get $fd) (i32.const 0) (i32.const 1) (i32.const 20))
+            drop
+        )
+
+        (func $main (export "_start")
+            (call $write (i32.const 1) (i32.const 0))
+            (call $write (i32.const 2) (i32.const 5))
+            (call $write (i32.const 1) (i32.const 10))
+            (call $write (i32.const 2) (i32.const 15))
+        )
+    )
+    "#,
+    )
+    .unwrap();
+
+    let (stdout_tx, mut stdout_rx) = Pipe::channel();
+
+    let builder = WasiEnv::builder("command-name")
+        .stdout(Box::new(stdout_tx))
+        .stderr_to_stdout();
+
+    #[cfg(feature = "js")]
+    {
+        builder.run_with_store(module, &mut store).unwrap();
+    }
+    #[cfg(not(feature = "js"))]
+    {
+        std::thread::spawn(move || builder.run_with_store(module, &mut store))
+            .join()
+            .unwrap()
+            .unwrap();
+    }
+
+    let mut stdout_str = String::new();
+    stdout_rx.read_to_string(&mut stdout_str).await.unwrap();
+    assert_eq!(stdout_str, "out1 err1 out2 err2 ");
+}
+
 async fn test_env() {
     let mut store = Store::default();
     let module = Module::new(&store, include_bytes!("envvar.wasm")).unwrap();
@@ -0,0 +1,105 @@
+use wasmer::{Module, Store};
+use wasmer_wasix::{wasmer_wasix_types::wasi::Rights, WasiEnv};
+
+mod sys {
+    #[tokio::test]
+    async fn test_stdout_rights() {
+        super::test_stdout_rights().await;
+    }
+    #[tokio::test]
+    async fn test_stdout_rights_seek_and_tell() {
+        super::test_stdout_rights_seek_and_tell().await;
+    }
+}
+
+async fn test_stdout_rights() {
+    let mut store = Store::default();
+    let module = Module::new(
+        &store,
+        br#"
+    (module
+        (import "wasi_snapshot_preview1" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "fd_filestat_get" (func $fd_filestat_get (param i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "proc_exit" (func $proc_exit (param i32)))
+
+        (memory 1)
+        (export "memory" (memory 0))
+
+        ;; iovec pointing at the contents
+        (data (i32.const 8) "\40\00\00\00\03\00\00\00")
+        (data (i32.const 64) "hi\n")
+
+        (func $main (export "_start")
+            ;; Writing is allowed while the stat of stdout is not
+            (call $proc_exit
+                (i32.or
+                    (i32.shl
+                        (call $fd_write (i32.const 1) (i32.const 8) (i32.const 1) (i32.const 16))
+                        (i32.const 8)
+                    )
+                    (call $fd_filestat_get (i32.const 1) (i32.const 128))
+                )
+            )
+        )
+    )
+    "#,
+    )
+    .unwrap();
+
+    let builder = WasiEnv::builder("command-name").stdout_rights(Rights::FD_WRITE);
+
+    #[cfg(feature = "js")]
+    let result = builder.run_with_store(module, &mut store);
+    #[cfg(not(feature = "js"))]
+    let result = std::thread::spawn(move || builder.run_with_store(module, &mut store))
+        .join()
+        .unwrap();
+
+    // Errno::Success for the write and Errno::Notcapable for the stat
+    let exit_code = result.unwrap_err().as_exit_code().unwrap();
+    assert_eq!(exit_code.raw(), 76);
+}
+
+async fn test_stdout_rights_seek_and_tell() {
+    let mut store = Store::default();
+    let module = Module::new(
+        &store,
+        br#"
+    (module
+        (import "wasi_snapshot_preview1" "fd_seek" (func $fd_seek (param i32 i64 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "fd_tell" (func $fd_tell (param i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "proc_exit" (func $proc_exit (param i32)))
+
+        (memory 1)
+        (export "memory" (memory 0))
+
+        (func $main (export "_start")
+            ;; Neither seeking nor telling is allowed on stdout
+            (call $proc_exit
+                (i32.or
+                    (i32.shl
+                        (call $fd_seek (i32.const 1) (i64.const 0) (i32.const 1) (i32.const 16))
+                        (i32.const 8)
+                    )
+                    (call $fd_tell (i32.const 1) (i32.const 16))
+                )
+            )
+        )
+    )
+    "#,
+    )
+    .unwrap();
+
+    let builder = WasiEnv::builder("command-name").stdout_rights(Rights::FD_WRITE);
+
+    #[cfg(feature = "js")]
+    let result = builder.run_with_store(module, &mut store);
+    #[cfg(not(feature = "js"))]
+    let result = std::thread::spawn(move || builder.run_with_store(module, &mut store))
+        .join()
+        .unwrap();
+
+    // Errno::Notcapable for both the seek and the tell
+    let exit_code = result.unwrap_err().as_exit_code().unwrap();
+    assert_eq!(exit_code.raw(), (76 << 8) | 76);
+}
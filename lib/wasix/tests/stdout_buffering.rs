@@ -0,0 +1,90 @@
+use wasmer::{Function, FunctionEnv, FunctionEnvMut, Instance, Module, Store};
+use wasmer_wasix::{Pipe, StdoutBuffering, WasiEnv, WasiError};
+
+mod sys {
+    #[tokio::test]
+    async fn test_stdout_line_buffering() {
+        super::test_stdout_line_buffering().await;
+    }
+}
+
+/// Host function that returns how many bytes the reader of stdout can see
+fn available(mut ctx: FunctionEnvMut<'_, Pipe>) -> i32 {
+    let mut buf = [0u8; 16];
+    ctx.data_mut().try_read(&mut buf).unwrap_or(0) as i32
+}
+
+async fn test_stdout_line_buffering() {
+    let mut store = Store::default();
+    let module = Module::new(&store, br#"
+    (module
+        (import "wasi_snapshot_preview1" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "proc_exit" (func $proc_exit (param i32)))
+        (import "env" "available" (func $available (result i32)))
+
+        (memory 1)
+        (export "memory" (memory 0))
+
+        (data (i32.const 32) "abc\n")
+
+        (func $main (export "_start")
+            (local $partial i32)
+
+            ;; Write a partial line
+            (i32.store (i32.const 0) (i32.const 32))
+            (i32.store (i32.const 4) (i32.const 3))
+            (call $fd_write (i32.const 1) (i32.const 0) (i32.const 1) (i32.const 16))
+            drop
+            (local.set $partial (call $available))
+
+            ;; Complete the line
+            (i32.store (i32.const 0) (i32.const 35))
+            (i32.store (i32.const 4) (i32.const 1))
+            (call $fd_write (i32.const 1) (i32.const 0) (i32.const 1) (i32.const 16))
+            drop
+
+            ;; Report what the reader saw after each write as the exit code
+            (call $proc_exit
+                (i32.or
+                    (i32.shl (local.get $partial) (i32.const 8))
+                    (call $available)
+                )
+            )
+        )
+    )
+    "#).unwrap();
+
+    let (stdout_tx, stdout_rx) = Pipe::channel();
+
+    let builder = WasiEnv::builder("command-name")
+        .stdout(Box::new(stdout_tx))
+        .stdout_buffering(StdoutBuffering::Line);
+
+    let handle = tokio::runtime::Handle::current();
+    let run = move || {
+        let _guard = handle.enter();
+        let mut wasi_env = builder.finalize(&mut store).unwrap();
+
+        let mut imports = wasi_env.import_object(&mut store, &module).unwrap();
+        let reader = FunctionEnv::new(&mut store, stdout_rx);
+        let available = Function::new_typed_with_env(&mut store, &reader, available);
+        imports.define("env", "available", available);
+
+        let instance = Instance::new(&mut store, &module, &imports).unwrap();
+        wasi_env.initialize(&mut store, instance.clone()).unwrap();
+
+        let start = instance.exports.get_function("_start").unwrap();
+        let result = start.call(&mut store, &[]);
+        wasi_env.on_exit(&mut store, None);
+        result
+    };
+
+    #[cfg(feature = "js")]
+    let result = run();
+    #[cfg(not(feature = "js"))]
+    let result = std::thread::spawn(run).join().unwrap();
+
+    // Nothing is visible before the newline, then the whole line is
+    let err = result.unwrap_err().downcast::<WasiError>().unwrap();
+    assert!(matches!(err, WasiError::Exit(code) if code.raw() == 4));
+}
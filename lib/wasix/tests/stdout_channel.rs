@@ -0,0 +1,211 @@
+#![cfg(not(feature = "js"))]
+
+use std::time::Duration;
+
+use bytes::Bytes;
+use virtual_fs::{AsyncWriteExt, ChannelBackpressure, Pipe};
+use wasmer::{Module, Store};
+use wasmer_wasix::WasiEnv;
+
+mod sys {
+    #[tokio::test]
+    async fn test_stdout_channel() {
+        super::test_stdout_channel().await;
+    }
+    #[tokio::test]
+    async fn test_stdout_channel_drops_writes_when_full() {
+        super::test_stdout_channel_drops_writes_when_full().await;
+    }
+    #[tokio::test]
+    async fn test_stdout_channel_fails_writes_when_full() {
+        super::test_stdout_channel_fails_writes_when_full().await;
+    }
+}
+
+async fn test_stdout_channel() {
+    let mut store = Store::default();
+    let module = Module::new(
+        &store,
+        br#"
+    (module
+        (import "wasi_snapshot_preview1" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "fd_read" (func $fd_read (param i32 i32 i32 i32) (result i32)))
+
+        (memory 1)
+        (export "memory" (memory 0))
+
+        (data (i32.const 64) "one")
+        (data (i32.const 80) "two")
+
+        (func $main (export "_start")
+            (i32.store (i32.const 0) (i32.const 64))
+            (i32.store (i32.const 4) (i32.const 3))
+            (call $fd_write (i32.const 1) (i32.const 0) (i32.const 1) (i32.const 24))
+            drop
+
+            ;; Wait for the host to see the first write
+            (i32.store (i32.const 0) (i32.const 128))
+            (i32.store (i32.const 4) (i32.const 16))
+            (call $fd_read (i32.const 0) (i32.const 0) (i32.const 1) (i32.const 24))
+            drop
+
+            (i32.store (i32.const 0) (i32.const 80))
+            (i32.store (i32.const 4) (i32.const 3))
+            (call $fd_write (i32.const 1) (i32.const 0) (i32.const 1) (i32.const 24))
+            drop
+        )
+    )
+    "#,
+    )
+    .unwrap();
+
+    let (stdout_tx, mut stdout_rx) = tokio::sync::mpsc::channel(4);
+    let (mut stdin_tx, stdin_rx) = Pipe::channel();
+
+    let builder = WasiEnv::builder("command-name")
+        .stdin(Box::new(stdin_rx))
+        .stdout_channel(stdout_tx, ChannelBackpressure::Wait);
+    let guest = std::thread::spawn(move || builder.run_with_store(module, &mut store));
+
+    // The first write arrives while the guest is still running
+    let first = tokio::time::timeout(Duration::from_secs(10), stdout_rx.recv())
+        .await
+        .unwrap();
+    assert_eq!(first, Some(Bytes::from_static(b"one")));
+    assert!(!guest.is_finished());
+
+    stdin_tx.write_all(b"go\n").await.unwrap();
+    let second = tokio::time::timeout(Duration::from_secs(10), stdout_rx.recv())
+        .await
+        .unwrap();
+    assert_eq!(second, Some(Bytes::from_static(b"two")));
+
+    guest.join().unwrap().unwrap();
+}
+
+/// Module that writes "one", "two" and "three" to stdout without waiting
+/// for the host and exits with the errno of the last write in the second
+/// byte and the number of bytes it wrote in the first one
+const WRITE_THREE_MODULE: &[u8] = br#"
+    (module
+        (import "wasi_snapshot_preview1" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "proc_exit" (func $proc_exit (param i32)))
+
+        (memory 1)
+        (export "memory" (memory 0))
+
+        (data (i32.const 64) "one")
+        (data (i32.const 80) "two")
+        (data (i32.const 96) "three")
+
+        (func $write (param $ptr i32) (param $len i32) (result i32)
+            (i32.store (i32.const 0) (local.get $ptr))
+            (i32.store (i32.const 4) (local.get $len))
+            (call $fd_write (i32.const 1) (i32.const 0) (i32.const 1) (i32.const 24))
+        )
+
+        (func $main (export "_start")
+            (call $write (i32.const 64) (i32.const 3))
+            drop
+            (call $write (i32.const 80) (i32.const 3))
+            drop
+            (call $proc_exit
+                (i32.or
+                    (i32.shl (call $write (i32.const 96) (i32.const 5)) (i32.const 8))
+                    (i32.load (i32.const 24))
+                )
+            )
+        )
+    )
+"#;
+
+async fn test_stdout_channel_drops_writes_when_full() {
+    let mut store = Store::default();
+    let module = Module::new(&store, WRITE_THREE_MODULE).unwrap();
+
+    let (stdout_tx, mut stdout_rx) = tokio::sync::mpsc::channel(1);
+    let builder =
+        WasiEnv::builder("command-name").stdout_channel(stdout_tx, ChannelBackpressure::Drop);
+    let result = std::thread::spawn(move || builder.run_with_store(module, &mut store))
+        .join()
+        .unwrap();
+
+    // The writes that did not fit succeeded but were discarded
+    let exit_code = result.unwrap_err().as_exit_code().unwrap();
+    assert_eq!(exit_code.raw(), 5);
+    assert_eq!(stdout_rx.recv().await, Some(Bytes::from_static(b"one")));
+    assert!(stdout_rx.try_recv().is_err());
+}
+
+async fn test_stdout_channel_fails_writes_when_full() {
+    let mut store = Store::default();
+    let module = Module::new(
+        &store,
+        br#"
+    (module
+        (import "wasi_snapshot_preview1" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "poll_oneoff" (func $poll_oneoff (param i32 i32 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "proc_exit" (func $proc_exit (param i32)))
+
+        (memory 1)
+        (export "memory" (memory 0))
+
+        (data (i32.const 32) "one")
+        (data (i32.const 48) "two")
+
+        (func $write (param $ptr i32) (result i32)
+            (i32.store (i32.const 0) (local.get $ptr))
+            (i32.store (i32.const 4) (i32.const 3))
+            (call $fd_write (i32.const 1) (i32.const 0) (i32.const 1) (i32.const 24))
+        )
+
+        (func $main (export "_start")
+            (local $errno i32)
+            (call $write (i32.const 32))
+            drop
+            (local.set $errno (call $write (i32.const 48)))
+
+            ;; Subscription 1 waits for stdout to be writable (userdata 7)
+            (i64.store (i32.const 64) (i64.const 7))
+            (i32.store8 (i32.const 72) (i32.const 2))             ;; FdWrite
+            (i32.store (i32.const 80) (i32.const 1))              ;; fd
+
+            ;; Subscription 2 is a clock with a 10ms timeout (userdata 42)
+            (i64.store (i32.const 112) (i64.const 42))
+            (i32.store8 (i32.const 120) (i32.const 0))            ;; Clock
+            (i32.store (i32.const 128) (i32.const 1))             ;; Monotonic
+            (i64.store (i32.const 136) (i64.const 10000000))      ;; timeout
+
+            ;; Report the errno of the second write, the number of events
+            ;; and the userdata of the first event in the exit code
+            (call $poll_oneoff (i32.const 64) (i32.const 256) (i32.const 2) (i32.const 8))
+            drop
+            (call $proc_exit
+                (i32.or
+                    (i32.or
+                        (i32.shl (local.get $errno) (i32.const 16))
+                        (i32.shl (i32.load (i32.const 8)) (i32.const 8))
+                    )
+                    (i32.wrap_i64 (i64.load (i32.const 256)))
+                )
+            )
+        )
+    )
+    "#,
+    )
+    .unwrap();
+
+    let (stdout_tx, mut stdout_rx) = tokio::sync::mpsc::channel(1);
+    let builder =
+        WasiEnv::builder("command-name").stdout_channel(stdout_tx, ChannelBackpressure::Fail);
+    let result = std::thread::spawn(move || builder.run_with_store(module, &mut store))
+        .join()
+        .unwrap();
+
+    // The second write failed and stdout is not writable while the channel
+    // is full, so only the clock fired
+    let exit_code = result.unwrap_err().as_exit_code().unwrap();
+    assert_eq!(exit_code.raw(), (6 << 16) | (1 << 8) | 42); // Errno::Again
+    assert_eq!(stdout_rx.recv().await, Some(Bytes::from_static(b"one")));
+    assert!(stdout_rx.try_recv().is_err());
+}
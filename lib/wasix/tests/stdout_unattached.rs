@@ -0,0 +1,74 @@
+use wasmer::{Module, Store};
+use wasmer_wasix::{types::wasi::Errno, UnattachedStdout, WasiEnv};
+
+mod sys {
+    #[tokio::test]
+    async fn test_unattached_stdout_discard() {
+        super::test_unattached_stdout_discard().await;
+    }
+
+    #[tokio::test]
+    async fn test_unattached_stdout_errno() {
+        super::test_unattached_stdout_errno().await;
+    }
+}
+
+/// Writes to a `stdout` that no sink was attached to, returns the exit
+/// code that reports the errno and the number of bytes written
+async fn write_unattached_stdout(behavior: UnattachedStdout) -> i32 {
+    let mut store = Store::default();
+    let module = Module::new(
+        &store,
+        br#"
+    (module
+        (import "wasi_snapshot_preview1" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "proc_exit" (func $proc_exit (param i32)))
+
+        (memory 1)
+        (export "memory" (memory 0))
+
+        ;; iovec pointing at the contents
+        (data (i32.const 0) "\10\00\00\00\06\00\00\00")
+        (data (i32.const 16) "hello\n")
+
+        (func $main (export "_start")
+            (local $errno i32)
+            (local.set $errno
+                (call $fd_write (i32.const 1) (i32.const 0) (i32.const 1) (i32.const 8))
+            )
+            (call $proc_exit
+                (i32.or
+                    (i32.shl (local.get $errno) (i32.const 8))
+                    (i32.load (i32.const 8))
+                )
+            )
+        )
+    )
+    "#,
+    )
+    .unwrap();
+
+    let builder = WasiEnv::builder("command-name").unattached_stdout(behavior);
+
+    #[cfg(feature = "js")]
+    let result = builder.run_with_store(module, &mut store);
+    #[cfg(not(feature = "js"))]
+    let result = std::thread::spawn(move || builder.run_with_store(module, &mut store))
+        .join()
+        .unwrap();
+
+    result.unwrap_err().as_exit_code().unwrap().raw()
+}
+
+async fn test_unattached_stdout_discard() {
+    // The write succeeds and all the bytes are gone
+    assert_eq!(write_unattached_stdout(UnattachedStdout::Discard).await, 6);
+}
+
+async fn test_unattached_stdout_errno() {
+    // The write fails with exactly the configured errno
+    assert_eq!(
+        write_unattached_stdout(UnattachedStdout::Errno(Errno::Badf)).await,
+        (Errno::Badf as i32) << 8
+    );
+}
@@ -0,0 +1,69 @@
+#![cfg(not(feature = "js"))]
+
+use wasmer::{Module, Store, Value};
+use wasmer_wasix::WasiEnv;
+
+mod sys {
+    #[tokio::test]
+    async fn test_step_mode() {
+        super::test_step_mode().await;
+    }
+}
+
+async fn test_step_mode() {
+    let mut store = Store::default();
+    let module = Module::new(
+        &store,
+        br#"
+    (module
+        (import "wasi_snapshot_preview1" "args_sizes_get" (func $args_sizes_get (param i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "clock_time_get" (func $clock_time_get (param i32 i64 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "proc_exit" (func $proc_exit (param i32)))
+
+        (memory 1)
+        (export "memory" (memory 0))
+
+        (func $main (export "_start")
+            (call $args_sizes_get (i32.const 0) (i32.const 4))
+            drop
+            (call $clock_time_get (i32.const 1) (i64.const 1000) (i32.const 8))
+            drop
+            (call $proc_exit (i32.const 3))
+        )
+    )
+    "#,
+    )
+    .unwrap();
+
+    let (tx, rx) = std::sync::mpsc::channel();
+    let builder = WasiEnv::builder("command-name").step_mode(tx);
+
+    let guest = std::thread::spawn(move || builder.run_with_store(module, &mut store));
+
+    // The guest is paused before each syscall until it is resumed
+    let mut steps = Vec::new();
+    for _ in 0..3 {
+        let step = rx.recv().unwrap();
+        assert!(!guest.is_finished());
+        steps.push((step.namespace.clone(), step.name.clone(), step.args.clone()));
+        step.resume();
+    }
+
+    let result = guest.join().unwrap();
+    let exit_code = result.unwrap_err().as_exit_code().unwrap();
+    assert_eq!(exit_code.raw(), 3);
+
+    let names = steps
+        .iter()
+        .map(|(namespace, name, _)| format!("{namespace}::{name}"))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        names,
+        [
+            "wasi_snapshot_preview1::args_sizes_get",
+            "wasi_snapshot_preview1::clock_time_get",
+            "wasi_snapshot_preview1::proc_exit",
+        ]
+    );
+    assert_eq!(steps[1].2, [Value::I32(1), Value::I64(1000), Value::I32(8)]);
+}
@@ -0,0 +1,135 @@
+use std::time::{Duration, Instant};
+
+use wasmer::{Module, Store};
+use wasmer_wasix::{
+    types::wasi::{Errno, ExitCode},
+    WasiEnv, WasiError, WasiRuntimeError, WasiThreadId,
+};
+
+mod sys {
+    #[tokio::test]
+    async fn test_denied_syscall_terminates_the_guest() {
+        super::test_denied_syscall_terminates_the_guest().await;
+    }
+    #[cfg(not(feature = "js"))]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_denied_syscall_in_thread_exits_with_perm() {
+        super::test_denied_syscall_in_thread_exits_with_perm().await;
+    }
+}
+
+async fn test_denied_syscall_terminates_the_guest() {
+    let mut store = Store::default();
+    let module = Module::new(
+        &store,
+        br#"
+    (module
+        (import "wasix_32v1" "sock_open" (func $sock_open (param i32 i32 i32 i32) (result i32)))
+        (import "wasix_32v1" "proc_exit" (func $proc_exit (param i32)))
+
+        (memory 1)
+        (export "memory" (memory 0))
+
+        (func $main (export "_start")
+            (call $sock_open
+                (i32.const 1)  ;; af (INET4)
+                (i32.const 1)  ;; ty (STREAM)
+                (i32.const 0)  ;; pt
+                (i32.const 16) ;; ro_sock
+            )
+            drop
+
+            ;; Never reached
+            (call $proc_exit (i32.const 1))
+        )
+    )
+    "#,
+    )
+    .unwrap();
+
+    let builder = WasiEnv::builder("command-name").syscall_allowlist(["proc_exit"]);
+
+    #[cfg(feature = "js")]
+    let result = builder.run_with_store(module, &mut store);
+    #[cfg(not(feature = "js"))]
+    let result = std::thread::spawn(move || builder.run_with_store(module, &mut store))
+        .join()
+        .unwrap();
+
+    match result {
+        Err(WasiRuntimeError::Wasi(WasiError::SyscallDenied(syscall))) => {
+            assert_eq!(syscall, "sock_open");
+        }
+        other => panic!("the guest was not terminated: {other:?}"),
+    }
+}
+
+#[cfg(not(feature = "js"))]
+async fn test_denied_syscall_in_thread_exits_with_perm() {
+    let mut store = Store::default();
+    let module = Module::new(
+        &store,
+        br#"
+    (module
+        (import "env" "memory" (memory 2 2 shared))
+        (import "wasix_32v1" "thread_spawn_v2" (func $thread_spawn (param i32 i32) (result i32)))
+        (import "wasix_32v1" "sock_open" (func $sock_open (param i32 i32 i32 i32) (result i32)))
+
+        ;; The thread waits for the host before it calls the denied syscall
+        (func (export "wasi_thread_start") (param $tid i32) (param $start i32)
+            (drop (memory.atomic.wait32 (i32.const 128) (i32.const 0) (i64.const -1)))
+            (drop (call $sock_open (i32.const 1) (i32.const 1) (i32.const 0) (i32.const 16)))
+        )
+
+        (func (export "_start")
+            ;; A stack of 4KiB right below 64KiB
+            (i32.store (i32.const 1024) (i32.const 65536)) ;; stack_upper
+            (i32.store (i32.const 1080) (i32.const 4096))  ;; stack_size
+            (if (call $thread_spawn (i32.const 1024) (i32.const 64))
+                (then unreachable)
+            )
+        )
+
+        (func (export "spawned") (result i32)
+            (i32.load (i32.const 64))
+        )
+        (func (export "go")
+            (i32.atomic.store (i32.const 128) (i32.const 1))
+            (drop (memory.atomic.notify (i32.const 128) (i32.const 1)))
+        )
+    )
+    "#,
+    )
+    .unwrap();
+
+    let builder = WasiEnv::builder("command-name").syscall_allowlist(["thread_spawn_v2"]);
+
+    let handle = tokio::runtime::Handle::current();
+    std::thread::spawn(move || {
+        let _guard = handle.enter();
+        let (instance, env) = builder.instantiate(module, &mut store).unwrap();
+        let process = env.data(&store).process.clone();
+
+        let start = instance.exports.get_function("_start").unwrap();
+        start.call(&mut store, &[]).unwrap();
+        let spawned = instance.exports.get_function("spawned").unwrap();
+        let tid = spawned.call(&mut store, &[]).unwrap()[0].unwrap_i32();
+        let thread = process.get_thread(&WasiThreadId::from(tid)).unwrap();
+
+        let go = instance.exports.get_function("go").unwrap();
+        go.call(&mut store, &[]).unwrap();
+
+        // The thread exits with the errno of the denied syscall
+        let deadline = Instant::now() + Duration::from_secs(10);
+        let exit_code = loop {
+            if let Some(exit_code) = thread.try_join() {
+                break exit_code.unwrap();
+            }
+            assert!(Instant::now() < deadline, "the thread did not exit");
+            std::thread::sleep(Duration::from_millis(10));
+        };
+        assert_eq!(exit_code, ExitCode::Errno(Errno::Perm));
+    })
+    .join()
+    .unwrap();
+}
@@ -0,0 +1,188 @@
+use virtual_fs::{mem_fs, AsyncReadExt, Pipe};
+use wasmer::{Module, Store};
+use wasmer_wasix::WasiEnv;
+
+mod sys {
+    #[tokio::test]
+    async fn test_syscall_batch() {
+        super::test_syscall_batch().await;
+    }
+    #[tokio::test]
+    async fn test_syscall_batch_results_out_of_bounds() {
+        super::test_syscall_batch_results_out_of_bounds().await;
+    }
+}
+
+async fn test_syscall_batch() {
+    let mut store = Store::default();
+    let module = Module::new(
+        &store,
+        br#"
+    (module
+        (import "wasi_snapshot_preview1" "path_open" (func $path_open (param i32 i32 i32 i32 i32 i64 i64 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "fd_pread" (func $fd_pread (param i32 i32 i32 i64 i32) (result i32)))
+        (import "wasix_32v1" "syscall_batch" (func $syscall_batch (param i32 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "proc_exit" (func $proc_exit (param i32)))
+
+        (memory 1)
+        (export "memory" (memory 0))
+
+        (data (i32.const 32) "f")
+        (data (i32.const 256) "hello world")
+        (data (i32.const 272) "HELLO")
+
+        ;; iovecs of "hello world", of the read buffer and of "HELLO"
+        (data (i32.const 100) "\00\01\00\00\0b\00\00\00")
+        (data (i32.const 108) "\20\01\00\00\05\00\00\00")
+        (data (i32.const 116) "\10\01\00\00\05\00\00\00")
+
+        (func $check (param $errno i32)
+            (if (local.get $errno)
+                (then (call $proc_exit (i32.add (i32.const 100) (local.get $errno))))
+            )
+        )
+
+        ;; Fills in the operation at `index` of the batch at 512
+        (func $op (param $index i32) (param $opcode i32) (param $flags i32) (param $fd i32) (param $iovs i32) (param $offset i64)
+            (local $at i32)
+            (local.set $at (i32.add (i32.const 512) (i32.mul (local.get $index) (i32.const 24))))
+            (i32.store8 (local.get $at) (local.get $opcode))
+            (i32.store8 offset=1 (local.get $at) (local.get $flags))
+            (i32.store offset=4 (local.get $at) (local.get $fd))
+            (i32.store offset=8 (local.get $at) (local.get $iovs))
+            (i32.store offset=12 (local.get $at) (i32.const 1))
+            (i64.store offset=16 (local.get $at) (local.get $offset))
+        )
+
+        ;; Exits with 10 + `index` unless the result at `index` of the batch
+        ;; at 1024 is as expected
+        (func $expect (param $index i32) (param $errno i32) (param $nbytes i32)
+            (local $at i32)
+            (local.set $at (i32.add (i32.const 1024) (i32.mul (local.get $index) (i32.const 8))))
+            (if (i32.ne (i32.load16_u (local.get $at)) (local.get $errno))
+                (then (call $proc_exit (i32.add (i32.const 10) (local.get $index))))
+            )
+            (if (i32.ne (i32.load offset=4 (local.get $at)) (local.get $nbytes))
+                (then (call $proc_exit (i32.add (i32.const 10) (local.get $index))))
+            )
+        )
+
+        (func $main (export "_start")
+            (local $fd i32)
+
+            (call $check
+                (call $path_open
+                    (i32.const 4)   ;; dirfd
+                    (i32.const 0)   ;; dirflags
+                    (i32.const 32)  ;; path
+                    (i32.const 1)   ;; path_len
+                    (i32.const 1)   ;; oflags (CREATE)
+                    (i64.const 70)  ;; rights_base (FD_READ | FD_SEEK | FD_WRITE)
+                    (i64.const 0)   ;; rights_inheriting
+                    (i32.const 0)   ;; fdflags
+                    (i32.const 0)   ;; fd_out
+                )
+            )
+            (local.set $fd (i32.load (i32.const 0)))
+
+            ;; Write, read back part of it and poll the cursor at the end
+            (call $op (i32.const 0) (i32.const 1) (i32.const 0) (local.get $fd) (i32.const 100) (i64.const 0))
+            (call $op (i32.const 1) (i32.const 2) (i32.const 0) (local.get $fd) (i32.const 108) (i64.const 6))
+            (call $op (i32.const 2) (i32.const 4) (i32.const 0) (local.get $fd) (i32.const 0) (i64.const 0))
+            ;; A failed read that the write linked to it is canceled with
+            (call $op (i32.const 3) (i32.const 0) (i32.const 1) (i32.const 99) (i32.const 108) (i64.const 0))
+            (call $op (i32.const 4) (i32.const 1) (i32.const 0) (local.get $fd) (i32.const 100) (i64.const 0))
+            ;; The batch carries on after the canceled write
+            (call $op (i32.const 5) (i32.const 3) (i32.const 0) (local.get $fd) (i32.const 116) (i64.const 0))
+
+            (memory.fill (i32.const 1024) (i32.const 0xff) (i32.const 48))
+            (call $check (call $syscall_batch (i32.const 512) (i32.const 6) (i32.const 1024)))
+
+            (call $expect (i32.const 0) (i32.const 0) (i32.const 11))
+            (call $expect (i32.const 1) (i32.const 0) (i32.const 5))
+            (call $expect (i32.const 2) (i32.const 0) (i32.const 0))
+            (call $expect (i32.const 3) (i32.const 8) (i32.const 0))  ;; Errno::Badf
+            (call $expect (i32.const 4) (i32.const 11) (i32.const 0)) ;; Errno::Canceled
+            (call $expect (i32.const 5) (i32.const 0) (i32.const 5))
+
+            ;; "world" was read
+            (if (i32.ne (i32.load (i32.const 288)) (i32.const 0x6c726f77))
+                (then (call $proc_exit (i32.const 1)))
+            )
+
+            ;; "HELLO world" was written
+            (call $check (call $fd_pread (local.get $fd) (i32.const 108) (i32.const 1) (i64.const 0) (i32.const 8)))
+            (if (i32.ne (i32.load (i32.const 288)) (i32.const 0x4c4c4548))
+                (then (call $proc_exit (i32.const 2)))
+            )
+        )
+    )
+    "#,
+    )
+    .unwrap();
+
+    let builder = WasiEnv::builder("command-name")
+        .fs(Box::<mem_fs::FileSystem>::default())
+        .preopen_dir("/")
+        .unwrap();
+
+    #[cfg(feature = "js")]
+    let result = builder.run_with_store(module, &mut store);
+    #[cfg(not(feature = "js"))]
+    let result = std::thread::spawn(move || builder.run_with_store(module, &mut store))
+        .join()
+        .unwrap();
+
+    result.unwrap();
+}
+
+async fn test_syscall_batch_results_out_of_bounds() {
+    let mut store = Store::default();
+    let module = Module::new(
+        &store,
+        br#"
+    (module
+        (import "wasix_32v1" "syscall_batch" (func $syscall_batch (param i32 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "proc_exit" (func $proc_exit (param i32)))
+
+        (memory 1)
+        (export "memory" (memory 0))
+
+        (data (i32.const 256) "hello")
+
+        ;; iovec of "hello"
+        (data (i32.const 100) "\00\01\00\00\05\00\00\00")
+
+        (func $main (export "_start")
+            ;; A write of "hello" to stdout
+            (i32.store8 (i32.const 512) (i32.const 1))
+            (i32.store (i32.const 516) (i32.const 1))
+            (i32.store (i32.const 520) (i32.const 100))
+            (i32.store (i32.const 524) (i32.const 1))
+
+            ;; The result does not fit at the end of the memory
+            (call $proc_exit (call $syscall_batch (i32.const 512) (i32.const 1) (i32.const 65532)))
+        )
+    )
+    "#,
+    )
+    .unwrap();
+
+    let (stdout_tx, mut stdout_rx) = Pipe::channel();
+    let builder = WasiEnv::builder("command-name").stdout(Box::new(stdout_tx));
+
+    #[cfg(feature = "js")]
+    let result = builder.run_with_store(module, &mut store);
+    #[cfg(not(feature = "js"))]
+    let result = std::thread::spawn(move || builder.run_with_store(module, &mut store))
+        .join()
+        .unwrap();
+
+    // Errno::Memviolation without writing anything
+    let exit_code = result.unwrap_err().as_exit_code().unwrap();
+    assert_eq!(exit_code.raw(), 78);
+
+    let mut stdout = String::new();
+    stdout_rx.read_to_string(&mut stdout).await.unwrap();
+    assert_eq!(stdout, "");
+}
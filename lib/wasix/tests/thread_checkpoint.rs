@@ -0,0 +1,145 @@
+#![cfg(not(feature = "js"))]
+
+use wasmer::{Instance, Memory32, Module, Store, WasmPtr};
+use wasmer_wasix::{
+    wasmer_wasix_types::wasi::{Errno, StackSnapshot},
+    WasiEnv, WasiFunctionEnv,
+};
+
+mod sys {
+    #[tokio::test]
+    async fn test_checkpoint_resumes_in_another_instance() {
+        super::test_checkpoint_resumes_in_another_instance().await;
+    }
+}
+
+/// Module that checkpoints itself with a value in a local variable and
+/// another one on the memory stack. Once the checkpoint returns, the sum of
+/// both values and of the value returned by `stack_checkpoint` is stored
+/// at 400.
+///
+/// The asyncify instrumentation (normally added by `wasm-opt`) is written
+/// by hand, there is a single call site so only the local is saved.
+const MODULE: &[u8] = br#"
+    (module
+        (import "wasix_32v1" "stack_checkpoint" (func $stack_checkpoint (param i32 i32) (result i32)))
+
+        (memory 1)
+        (export "memory" (memory 0))
+
+        (global $__stack_pointer (export "__stack_pointer") (mut i32) (i32.const 8192))
+        (global $__data_end (export "__data_end") i32 (i32.const 4096))
+
+        ;; 0 = normal, 1 = unwinding, 2 = rewinding
+        (global $state (mut i32) (i32.const 0))
+        (global $data (mut i32) (i32.const 0))
+
+        (func (export "asyncify_start_unwind") (param i32)
+            (global.set $state (i32.const 1))
+            (global.set $data (local.get 0))
+        )
+        (func (export "asyncify_stop_unwind")
+            (global.set $state (i32.const 0))
+        )
+        (func (export "asyncify_start_rewind") (param i32)
+            (global.set $state (i32.const 2))
+            (global.set $data (local.get 0))
+        )
+        (func (export "asyncify_stop_rewind")
+            (global.set $state (i32.const 0))
+        )
+        (func (export "asyncify_get_state") (result i32)
+            (global.get $state)
+        )
+
+        (func $main (export "_start")
+            (local $x i32)
+            (if (i32.eq (global.get $state) (i32.const 2))
+                (then
+                    ;; Pop the local from the rewind stack
+                    (i32.store (global.get $data) (i32.sub (i32.load (global.get $data)) (i32.const 4)))
+                    (local.set $x (i32.load (i32.load (global.get $data))))
+                )
+                (else
+                    (local.set $x (i32.const 30))
+                    (global.set $__stack_pointer (i32.sub (global.get $__stack_pointer) (i32.const 16)))
+                    (i32.store (global.get $__stack_pointer) (i32.const 12))
+                )
+            )
+
+            (call $stack_checkpoint (i32.const 256) (i32.const 320))
+            drop
+
+            (if (i32.eq (global.get $state) (i32.const 1))
+                (then
+                    ;; Push the local onto the unwind stack
+                    (i32.store (i32.load (global.get $data)) (local.get $x))
+                    (i32.store (global.get $data) (i32.add (i32.load (global.get $data)) (i32.const 4)))
+                    (return)
+                )
+            )
+
+            (i32.store (i32.const 400)
+                (i32.add
+                    (i32.wrap_i64 (i64.load (i32.const 320)))
+                    (i32.add (local.get $x) (i32.load (global.get $__stack_pointer)))
+                )
+            )
+        )
+    )
+"#;
+
+/// Instantiates the module in a store and environment of its own
+fn instantiate(store: &mut Store) -> (WasiFunctionEnv, Instance) {
+    let module = Module::new(&*store, MODULE).unwrap();
+    let mut func_env = WasiEnv::builder("command-name").finalize(store).unwrap();
+    let imports = func_env.import_object(store, &module).unwrap();
+    let instance = Instance::new(store, &module, &imports).unwrap();
+    func_env.initialize(store, instance.clone()).unwrap();
+    (func_env, instance)
+}
+
+fn run(store: &mut Store, instance: &Instance) -> u32 {
+    let start = instance.exports.get_function("_start").unwrap();
+    start.call(store, &[]).unwrap();
+
+    let memory = instance.exports.get_memory("memory").unwrap();
+    let mut result = [0u8; 4];
+    memory.view(store).read(400, &mut result).unwrap();
+    u32::from_le_bytes(result)
+}
+
+async fn test_checkpoint_resumes_in_another_instance() {
+    let handle = tokio::runtime::Handle::current();
+    std::thread::spawn(move || {
+        let _guard = handle.enter();
+
+        // The guest checkpoints itself and carries on
+        let mut store = Store::default();
+        let (func_env, instance) = instantiate(&mut store);
+        assert_eq!(run(&mut store, &instance), 42);
+
+        let memory = instance.exports.get_memory("memory").unwrap();
+        let snapshot = WasmPtr::<StackSnapshot, Memory32>::new(256)
+            .read(&memory.view(&store))
+            .unwrap();
+        let checkpoint = func_env
+            .data(&store)
+            .thread
+            .export_checkpoint(snapshot.hash)
+            .unwrap();
+
+        // The checkpoint is resumed in a fresh instance of the same module
+        // which returns from `stack_checkpoint` with the value it was given
+        let mut store = Store::default();
+        let (func_env, instance) = instantiate(&mut store);
+        let mut ctx = func_env.env.clone().into_mut(&mut store);
+        assert_eq!(
+            WasiEnv::resume_checkpoint::<Memory32>(&mut ctx, &checkpoint, 100),
+            Errno::Success
+        );
+        assert_eq!(run(&mut store, &instance), 142);
+    })
+    .join()
+    .unwrap();
+}
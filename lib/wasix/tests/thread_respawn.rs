@@ -0,0 +1,188 @@
+#![cfg(not(feature = "js"))]
+
+use std::time::{Duration, Instant};
+
+use wasmer::{Instance, Module, Store};
+use wasmer_wasix::{types::wasi::Errno, WasiEnv, WasiThreadId};
+
+mod sys {
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_thread_respawn() {
+        super::test_thread_respawn().await;
+    }
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_thread_respawn_after_start_released() {
+        super::test_thread_respawn_after_start_released().await;
+    }
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_crashed_threads_are_not_retained_by_default() {
+        super::test_crashed_threads_are_not_retained_by_default().await;
+    }
+}
+
+/// Spawns a thread from `_start` with its start arguments at 1024
+const WAT: &[u8] = br#"
+    (module
+        (import "env" "memory" (memory 2 2 shared))
+        (import "wasix_32v1" "thread_spawn_v2" (func $thread_spawn (param i32 i32) (result i32)))
+
+        ;; The first run of the thread traps while the restarted one
+        ;; records its thread ID
+        (func (export "wasi_thread_start") (param $tid i32) (param $start i32)
+            (if (i32.eqz (i32.atomic.rmw.add (i32.const 128) (i32.const 1)))
+                (then unreachable)
+            )
+            (i32.atomic.store (i32.const 132) (local.get $tid))
+        )
+
+        (func (export "_start")
+            ;; A stack of 4KiB right below 64KiB
+            (i32.store (i32.const 1024) (i32.const 65536)) ;; stack_upper
+            (i32.store (i32.const 1080) (i32.const 4096))  ;; stack_size
+            (if (call $thread_spawn (i32.const 1024) (i32.const 64))
+                (then unreachable)
+            )
+        )
+
+        (func (export "spawned") (result i32)
+            (i32.load (i32.const 64))
+        )
+        (func (export "attempts") (result i32)
+            (i32.atomic.load (i32.const 128))
+        )
+        (func (export "restarted") (result i32)
+            (i32.atomic.load (i32.const 132))
+        )
+        (func (export "release_start")
+            (i32.store (i32.const 1024) (i32.const 0))
+        )
+        (func (export "restore_start")
+            (i32.store (i32.const 1024) (i32.const 65536))
+        )
+    )
+"#;
+
+/// Calls the exported function `name` until it returns something other
+/// than zero
+fn wait_for(instance: &Instance, store: &mut Store, name: &str) -> i32 {
+    let func = instance.exports.get_function(name).unwrap();
+    let deadline = Instant::now() + Duration::from_secs(10);
+    loop {
+        let ret = func.call(store, &[]).unwrap()[0].unwrap_i32();
+        if ret != 0 {
+            return ret;
+        }
+        assert!(Instant::now() < deadline, "timed out waiting for `{name}`");
+        std::thread::sleep(Duration::from_millis(10));
+    }
+}
+
+async fn test_thread_respawn() {
+    let mut store = Store::default();
+    let module = Module::new(&store, WAT).unwrap();
+
+    let builder = WasiEnv::builder("command-name").retain_crashed_threads(true);
+
+    let handle = tokio::runtime::Handle::current();
+    std::thread::spawn(move || {
+        let _guard = handle.enter();
+        let (instance, env) = builder.instantiate(module, &mut store).unwrap();
+        let process = env.data(&store).process.clone();
+
+        let start = instance.exports.get_function("_start").unwrap();
+        start.call(&mut store, &[]).unwrap();
+        let tid = WasiThreadId::from(wait_for(&instance, &mut store, "spawned"));
+
+        // Wait for the thread to trap
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while process.crashed_threads().is_empty() {
+            assert!(Instant::now() < deadline, "the thread did not trap");
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(process.crashed_threads(), [tid]);
+
+        // The thread is restarted with the same thread ID
+        let restarted = process.respawn_thread(tid).unwrap();
+        assert_eq!(restarted, tid);
+        assert!(process.crashed_threads().is_empty());
+        assert_eq!(
+            WasiThreadId::from(wait_for(&instance, &mut store, "restarted")),
+            tid
+        );
+
+        // It can not be restarted again as it did not crash this time
+        assert!(process.respawn_thread(tid).is_err());
+    })
+    .join()
+    .unwrap();
+}
+
+async fn test_thread_respawn_after_start_released() {
+    let mut store = Store::default();
+    let module = Module::new(&store, WAT).unwrap();
+
+    let builder = WasiEnv::builder("command-name").retain_crashed_threads(true);
+
+    let handle = tokio::runtime::Handle::current();
+    std::thread::spawn(move || {
+        let _guard = handle.enter();
+        let (instance, env) = builder.instantiate(module, &mut store).unwrap();
+        let process = env.data(&store).process.clone();
+
+        let start = instance.exports.get_function("_start").unwrap();
+        start.call(&mut store, &[]).unwrap();
+        let tid = WasiThreadId::from(wait_for(&instance, &mut store, "spawned"));
+
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while process.crashed_threads().is_empty() {
+            assert!(Instant::now() < deadline, "the thread did not trap");
+            std::thread::sleep(Duration::from_millis(10));
+        }
+
+        // The guest reused the memory of the start arguments so the
+        // thread can not be restarted...
+        let release = instance.exports.get_function("release_start").unwrap();
+        release.call(&mut store, &[]).unwrap();
+        assert_eq!(process.respawn_thread(tid), Err(Errno::Inval));
+        assert_eq!(process.crashed_threads(), [tid]);
+
+        // ...until the start arguments are back
+        let restore = instance.exports.get_function("restore_start").unwrap();
+        restore.call(&mut store, &[]).unwrap();
+        assert_eq!(process.respawn_thread(tid), Ok(tid));
+        assert!(process.crashed_threads().is_empty());
+        assert_eq!(
+            WasiThreadId::from(wait_for(&instance, &mut store, "restarted")),
+            tid
+        );
+    })
+    .join()
+    .unwrap();
+}
+
+async fn test_crashed_threads_are_not_retained_by_default() {
+    let mut store = Store::default();
+    let module = Module::new(&store, WAT).unwrap();
+
+    let builder = WasiEnv::builder("command-name");
+
+    let handle = tokio::runtime::Handle::current();
+    std::thread::spawn(move || {
+        let _guard = handle.enter();
+        let (instance, env) = builder.instantiate(module, &mut store).unwrap();
+        let process = env.data(&store).process.clone();
+        assert!(!process.retains_crashed_threads());
+
+        let start = instance.exports.get_function("_start").unwrap();
+        start.call(&mut store, &[]).unwrap();
+        let tid = WasiThreadId::from(wait_for(&instance, &mut store, "spawned"));
+
+        // The thread traps and exits without being kept for a restart
+        wait_for(&instance, &mut store, "attempts");
+        std::thread::sleep(Duration::from_millis(100));
+        assert!(process.crashed_threads().is_empty());
+        assert_eq!(process.respawn_thread(tid), Err(Errno::Srch));
+    })
+    .join()
+    .unwrap();
+}
@@ -0,0 +1,81 @@
+use std::sync::{Arc, Mutex};
+
+use wasmer::{Module, Store};
+use wasmer_wasix::{types::wasi::Errno, UnimplementedSyscall, WasiEnv};
+
+mod sys {
+    #[tokio::test]
+    async fn test_unimplemented_syscall_returns_errno() {
+        super::test_unimplemented_syscall_returns_errno().await;
+    }
+    #[tokio::test]
+    async fn test_unimplemented_syscall_handler() {
+        super::test_unimplemented_syscall_handler().await;
+    }
+}
+
+/// Calls `does_not_exist` and exits with the errno it returned
+const MODULE: &[u8] = br#"
+    (module
+        (import "wasi_snapshot_preview1" "does_not_exist" (func $does_not_exist (param i32 i64) (result i32)))
+        (import "wasi_snapshot_preview1" "proc_exit" (func $proc_exit (param i32)))
+
+        (memory 1)
+        (export "memory" (memory 0))
+
+        (func $main (export "_start")
+            (call $proc_exit
+                (call $does_not_exist (i32.const 1) (i64.const 2))
+            )
+        )
+    )
+    "#;
+
+async fn test_unimplemented_syscall_returns_errno() {
+    let mut store = Store::default();
+    let module = Module::new(&store, MODULE).unwrap();
+
+    let builder = WasiEnv::builder("command-name")
+        .unimplemented_syscall(UnimplementedSyscall::Errno(Errno::Nosys));
+
+    #[cfg(feature = "js")]
+    let result = builder.run_with_store(module, &mut store);
+    #[cfg(not(feature = "js"))]
+    let result = std::thread::spawn(move || builder.run_with_store(module, &mut store))
+        .join()
+        .unwrap();
+
+    let exit_code = result.unwrap_err().as_exit_code().unwrap();
+    assert_eq!(exit_code.raw(), Errno::Nosys as i32);
+}
+
+async fn test_unimplemented_syscall_handler() {
+    let mut store = Store::default();
+    let module = Module::new(&store, MODULE).unwrap();
+
+    let calls = Arc::new(Mutex::new(Vec::new()));
+    let handler = {
+        let calls = calls.clone();
+        move |namespace: &str, name: &str| {
+            calls.lock().unwrap().push(format!("{namespace}::{name}"));
+            Errno::Notsup
+        }
+    };
+
+    let builder = WasiEnv::builder("command-name")
+        .unimplemented_syscall(UnimplementedSyscall::Handler(Arc::new(handler)));
+
+    #[cfg(feature = "js")]
+    let result = builder.run_with_store(module, &mut store);
+    #[cfg(not(feature = "js"))]
+    let result = std::thread::spawn(move || builder.run_with_store(module, &mut store))
+        .join()
+        .unwrap();
+
+    let exit_code = result.unwrap_err().as_exit_code().unwrap();
+    assert_eq!(exit_code.raw(), Errno::Notsup as i32);
+    assert_eq!(
+        calls.lock().unwrap().as_slice(),
+        ["wasi_snapshot_preview1::does_not_exist"]
+    );
+}